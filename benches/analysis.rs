@@ -0,0 +1,166 @@
+//! Measures the throughput of ddshow's ingestion and aggregation by analyzing
+//! the logs of `timely_test`'s generated workloads over varying numbers of epochs
+//!
+//! ```sh
+//! cargo bench --bench analysis
+//! ```
+
+#[path = "../src/bin/timely_test/workload.rs"]
+mod workload;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use ddshow::{
+    ddshow_types::{
+        differential_logging::DifferentialEvent, timely_logging::TimelyEvent, WorkerId,
+    },
+    DDShow,
+};
+use differential_dataflow::logging::DifferentialEvent as RawDifferentialEvent;
+use std::{
+    mem,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use timely::{dataflow::ProbeHandle, logging::TimelyEvent as RawTimelyEvent, Config};
+use workload::Workload;
+
+type TimelyEvents = Vec<(Duration, WorkerId, TimelyEvent)>;
+type DifferentialEvents = Vec<(Duration, WorkerId, DifferentialEvent)>;
+
+/// Runs the workload on `workers` timely workers for `epochs` epochs, capturing
+/// the events they log so that they can be injected into ddshow
+fn capture(
+    workload: Workload,
+    workers: usize,
+    epochs: usize,
+) -> (TimelyEvents, DifferentialEvents) {
+    let timely = Arc::new(Mutex::new(Vec::new()));
+    let differential = Arc::new(Mutex::new(Vec::new()));
+
+    {
+        let (timely, differential) = (timely.clone(), differential.clone());
+
+        timely::execute(Config::process(workers), move |worker| {
+            let timely = timely.clone();
+            worker
+                .log_register()
+                .insert::<RawTimelyEvent, _>("timely", move |_time, data| {
+                    timely
+                        .lock()
+                        .unwrap()
+                        .extend(data.drain(..).map(|(time, worker, event)| {
+                            (time, WorkerId::new(worker), TimelyEvent::from(event))
+                        }));
+                });
+
+            let differential = differential.clone();
+            worker.log_register().insert::<RawDifferentialEvent, _>(
+                "differential/arrange",
+                move |_time, data| {
+                    differential.lock().unwrap().extend(data.drain(..).map(
+                        |(time, worker, event)| {
+                            (time, WorkerId::new(worker), DifferentialEvent::from(event))
+                        },
+                    ));
+                },
+            );
+
+            let mut probe = ProbeHandle::new();
+            let mut inputs = workload.build(worker, &mut probe);
+
+            for epoch in 1..=epochs {
+                if worker.index() == 0 {
+                    workload.ingest(&mut inputs, epoch);
+                }
+
+                for input in inputs.iter_mut() {
+                    input.advance_to(epoch);
+                    input.flush();
+                }
+            }
+
+            worker.step_while(|| probe.less_than(&epochs));
+        })
+        .unwrap()
+        .join();
+    }
+
+    let timely = mem::take(&mut *timely.lock().unwrap());
+    let differential = mem::take(&mut *differential.lock().unwrap());
+
+    (timely, differential)
+}
+
+fn ingestion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ingestion");
+    group.sample_size(10);
+
+    let workload = Workload {
+        dataflows: 4,
+        operators: 12,
+        records: 1_000,
+        churn: 100,
+    };
+
+    for &epochs in &[10, 100, 1_000] {
+        let (timely, _) = capture(workload, 2, epochs);
+
+        group.throughput(Throughput::Elements(timely.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(epochs),
+            &timely,
+            |bench, timely| {
+                bench.iter_batched(
+                    || timely.clone(),
+                    |timely| DDShow::builder().inject_timely(timely).run().unwrap(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn aggregation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aggregation");
+    group.sample_size(10);
+
+    let workload = Workload {
+        dataflows: 16,
+        operators: 24,
+        records: 1_000,
+        churn: 100,
+    };
+    let (timely, differential) = capture(workload, 4, 100);
+    group.throughput(Throughput::Elements(
+        (timely.len() + differential.len()) as u64,
+    ));
+
+    for &workers in &[1, 2, 4] {
+        group.bench_with_input(
+            BenchmarkId::new("workers", workers),
+            &workers,
+            |bench, &workers| {
+                bench.iter_batched(
+                    || (timely.clone(), differential.clone()),
+                    |(timely, differential)| {
+                        DDShow::builder()
+                            .workers(NonZeroUsize::new(workers).unwrap())
+                            .inject_timely(timely)
+                            .inject_differential(differential)
+                            .run()
+                            .unwrap()
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, ingestion, aggregation);
+criterion_main!(benches);
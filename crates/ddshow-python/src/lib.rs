@@ -0,0 +1,150 @@
+//! Python bindings for querying the stats computed by ddshow
+//!
+//! ```python
+//! import ddshow
+//!
+//! stats = ddshow.load("dump.json")
+//! for operator in stats.operators():
+//!     print(operator["name"], stats.aggregated_stats(operator["addr"]))
+//! ```
+
+use ddshow_lib::{
+    ddshow_types::{OperatorAddr, WorkerId},
+    DDShowStats, EventKind, Summation,
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyDict, wrap_pyfunction};
+use std::path::PathBuf;
+
+/// The stats computed by an analysis
+#[pyclass(name = "Stats")]
+struct Stats {
+    stats: DDShowStats,
+}
+
+#[pymethods]
+impl Stats {
+    /// Every worker of the target computation
+    fn workers(&self) -> Vec<usize> {
+        self.stats
+            .workers()
+            .iter()
+            .map(|worker| worker.into_inner())
+            .collect()
+    }
+
+    /// Every operator and subgraph as dicts of their `id`, `addr` and `name`
+    fn operators(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let data = self.stats.data();
+
+        data.nodes
+            .iter()
+            .chain(data.subgraphs.iter())
+            .map(|(addr, operator)| {
+                let dict = PyDict::new(py);
+                dict.set_item("id", operator.id.into_inner())?;
+                dict.set_item("addr", addr_segments(addr))?;
+                dict.set_item("name", &operator.name)?;
+
+                Ok(dict.into())
+            })
+            .collect()
+    }
+
+    /// The activation stats of the operator at `addr` on each worker that it ran on
+    fn operator_stats(&self, py: Python, addr: Vec<usize>) -> PyResult<Vec<PyObject>> {
+        self.stats
+            .operator_stats(&OperatorAddr::from(addr))
+            .map(|(worker, stats)| {
+                let dict = summation_dict(py, stats)?;
+                dict.set_item("worker", worker.into_inner())?;
+
+                Ok(dict.into())
+            })
+            .collect()
+    }
+
+    /// The activation stats of the operator at `addr` merged across every worker,
+    /// or `None` if it was never activated
+    fn aggregated_stats(&self, py: Python, addr: Vec<usize>) -> PyResult<Option<PyObject>> {
+        self.stats
+            .aggregated_stats()
+            .get(&OperatorAddr::from(addr))
+            .map(|stats| summation_dict(py, stats).map(Into::into))
+            .transpose()
+    }
+
+    /// The events within the worker timelines, optionally only those of a single worker
+    #[args(worker = "None")]
+    fn timeline(&self, py: Python, worker: Option<usize>) -> PyResult<Vec<PyObject>> {
+        let worker = worker.map(WorkerId::new);
+
+        self.stats
+            .data()
+            .timeline_events
+            .iter()
+            .filter(|event| worker.map_or(true, |worker| event.worker == worker))
+            .map(|event| {
+                let (kind, operator) = event_kind(event.event);
+
+                let dict = PyDict::new(py);
+                dict.set_item("worker", event.worker.into_inner())?;
+                dict.set_item("kind", kind)?;
+                dict.set_item("operator", operator)?;
+                dict.set_item("start_ns", event.start_time)?;
+                dict.set_item("duration_ns", event.duration)?;
+                dict.set_item("collapsed_events", event.collapsed_events)?;
+
+                Ok(dict.into())
+            })
+            .collect()
+    }
+}
+
+/// Loads a dump that was written with either `--dump-json` or `--dump-binary`
+#[pyfunction]
+fn load(path: PathBuf) -> PyResult<Stats> {
+    DDShowStats::load(path)
+        .map(|stats| Stats { stats })
+        .map_err(|err| PyRuntimeError::new_err(format!("{:#}", err)))
+}
+
+#[pymodule]
+fn ddshow(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Stats>()?;
+    module.add_function(wrap_pyfunction!(load, module)?)?;
+
+    Ok(())
+}
+
+fn addr_segments(addr: &OperatorAddr) -> Vec<usize> {
+    addr.iter().map(|segment| segment.into_inner()).collect()
+}
+
+/// Durations are given in nanoseconds to match the timeline
+fn summation_dict<'py>(py: Python<'py>, stats: &Summation) -> PyResult<&'py PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("activations", stats.count)?;
+    dict.set_item("total_ns", stats.total.as_nanos() as u64)?;
+    dict.set_item("average_ns", stats.average.as_nanos() as u64)?;
+    dict.set_item("max_ns", stats.max.as_nanos() as u64)?;
+    dict.set_item("min_ns", stats.min.as_nanos() as u64)?;
+    dict.set_item("stddev_ns", stats.stddev.as_nanos() as u64)?;
+
+    Ok(dict)
+}
+
+/// The name of the event's kind along with the operator it belongs to, if any
+fn event_kind(kind: EventKind) -> (&'static str, Option<usize>) {
+    match kind {
+        EventKind::OperatorActivation { operator_id } => {
+            ("activation", Some(operator_id.into_inner()))
+        }
+        EventKind::Merge { operator_id } => ("merge", Some(operator_id.into_inner())),
+        EventKind::Application { .. } => ("application", None),
+        EventKind::Parked => ("parked", None),
+        EventKind::Input => ("input", None),
+        EventKind::Message => ("message", None),
+        EventKind::Progress => ("progress", None),
+        EventKind::MinorActivity => ("minor_activity", None),
+    }
+}
@@ -0,0 +1,622 @@
+//! Embeds ddshow's analysis within other programs
+//!
+//! [`DDShow::builder()`] configures where the logs come from, running it
+//! analyzes them and hands back the computed stats without rendering the
+//! graph or writing a report. Results can also be streamed out while the
+//! analysis runs by registering a [`StatsSink`] and a [`DataflowBuilder`]
+//! narrows the analysis down to the parts that are actually needed
+
+use crate::{
+    args::{Analyses, Analysis, Args},
+    dataflow::{utils::XXHasher, AnalysisSink, DataflowData, StatsSink, StatsSinks, Summation},
+    diff, dump,
+    in_process::InProcess,
+    operator_groups::OperatorGroups,
+    output_sink::{OutputSink, OutputSinks},
+    replay_loading::InjectedEvents,
+    run,
+    ui::{RunStats, WorkerUtilization},
+};
+use anyhow::{Context, Result};
+use ddshow_types::{
+    differential_logging::DifferentialEvent,
+    progress_logging::TimelyProgressEvent,
+    timely_logging::{OperatesEvent, TimelyEvent},
+    OperatorAddr, WorkerId,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Debug},
+    fs,
+    net::SocketAddr,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// The entry point for running ddshow as a library
+#[derive(Debug, Clone, Copy)]
+pub struct DDShow;
+
+impl DDShow {
+    /// Starts configuring an analysis
+    pub fn builder() -> DDShowBuilder {
+        DDShowBuilder::new()
+    }
+}
+
+/// Configures and runs an analysis, created with [`DDShow::builder()`]
+pub struct DDShowBuilder {
+    args: Args,
+    stats_sinks: StatsSinks,
+    output_sinks: OutputSinks,
+    injected: Option<InjectedEvents>,
+}
+
+impl DDShowBuilder {
+    fn new() -> Self {
+        Self::from_args(Args::default())
+    }
+
+    /// Starts from a full set of [`Args`] for the options that the builder
+    /// doesn't cover
+    pub fn from_args(args: Args) -> Self {
+        Self {
+            args: Args {
+                // Embedders own the terminal
+                quiet: true,
+                ..args
+            },
+            stats_sinks: Vec::new(),
+            output_sinks: Vec::new(),
+            injected: None,
+        }
+    }
+
+    /// Replays logs that were saved by `ddshow record` or `--save-logs`,
+    /// can be given multiple times to replay multiple directories
+    pub fn replay<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.args
+            .replay_logs
+            .get_or_insert_with(Vec::new)
+            .push(path.into());
+
+        self
+    }
+
+    /// Listens for a running target's timely logs on the given address
+    pub fn capture(mut self, address: SocketAddr) -> Self {
+        self.args.timely_address = address;
+        self.args.replay_logs = None;
+        self
+    }
+
+    /// The number of timely workers running in the target computation
+    pub fn connections(mut self, connections: NonZeroUsize) -> Self {
+        self.args.timely_connections = connections;
+        self
+    }
+
+    /// Also listens for the target's differential logs on the given address
+    pub fn differential(mut self, address: SocketAddr) -> Self {
+        self.args.differential_enabled = true;
+        self.args.differential_address = address;
+        self
+    }
+
+    /// Also listens for the target's progress logs on the given address
+    pub fn progress(mut self, address: SocketAddr) -> Self {
+        self.args.progress_enabled = true;
+        self.args.progress_address = address;
+        self
+    }
+
+    /// Analyzes the given timely events instead of reading them from the target,
+    /// each event is the time it was logged at, the worker that logged it and the event
+    ///
+    /// Once any events have been injected the target's logs are never read, which
+    /// allows driving the analysis with synthetic events in tests without going
+    /// through sockets or files. Can be given multiple times to inject more events
+    pub fn inject_timely<I>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = (Duration, WorkerId, TimelyEvent)>,
+    {
+        self.injected
+            .get_or_insert_with(InjectedEvents::default)
+            .timely
+            .extend(events);
+
+        self
+    }
+
+    /// Analyzes the given differential events alongside the injected timely events,
+    /// see [`DDShowBuilder::inject_timely()`]
+    pub fn inject_differential<I>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = (Duration, WorkerId, DifferentialEvent)>,
+    {
+        self.args.differential_enabled = true;
+        self.injected
+            .get_or_insert_with(InjectedEvents::default)
+            .differential
+            .extend(events);
+
+        self
+    }
+
+    /// Analyzes the given progress events alongside the injected timely events,
+    /// see [`DDShowBuilder::inject_timely()`]
+    pub fn inject_progress<I>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = (Duration, WorkerId, TimelyProgressEvent)>,
+    {
+        self.args.progress_enabled = true;
+        self.injected
+            .get_or_insert_with(InjectedEvents::default)
+            .progress
+            .extend(events);
+
+        self
+    }
+
+    /// Replays the contents of a single timely log file that was saved by `ddshow record`
+    /// or `--save-logs`, for when the logs can't be read from disk such as within a browser.
+    /// Can be given multiple times to replay each of the target's workers, and like
+    /// injected events the target's logs are never read once any have been given
+    pub fn replay_timely_log(mut self, log: Vec<u8>) -> Self {
+        self.injected
+            .get_or_insert_with(InjectedEvents::default)
+            .timely_logs
+            .push(log);
+
+        self
+    }
+
+    /// Replays the contents of a saved differential log file alongside the timely logs,
+    /// see [`DDShowBuilder::replay_timely_log()`]
+    pub fn replay_differential_log(mut self, log: Vec<u8>) -> Self {
+        self.args.differential_enabled = true;
+        self.injected
+            .get_or_insert_with(InjectedEvents::default)
+            .differential_logs
+            .push(log);
+
+        self
+    }
+
+    /// Replays the contents of a saved progress log file alongside the timely logs,
+    /// see [`DDShowBuilder::replay_timely_log()`]
+    pub fn replay_progress_log(mut self, log: Vec<u8>) -> Self {
+        self.args.progress_enabled = true;
+        self.injected
+            .get_or_insert_with(InjectedEvents::default)
+            .progress_logs
+            .push(log);
+
+        self
+    }
+
+    /// The number of workers to run the analysis dataflow with
+    pub fn workers(mut self, workers: NonZeroUsize) -> Self {
+        self.args.workers = workers;
+        self
+    }
+
+    /// Streams the analysis' results into the given sink as they're computed,
+    /// can be given multiple times to feed multiple sinks
+    pub fn stats_sink<T>(mut self, sink: T) -> Self
+    where
+        T: StatsSink + 'static,
+    {
+        self.stats_sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Writes the analysis' results with the given sink once it has finished,
+    /// alongside the dumps, report and graph that are written by [`DDShowBuilder::render()`].
+    /// Can be given multiple times to write multiple outputs
+    pub fn output_sink<T>(mut self, sink: T) -> Self
+    where
+        T: OutputSink + 'static,
+    {
+        self.output_sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Selects which analyses are run and the sinks fed by each of them
+    pub fn dataflow(mut self, dataflow: DataflowBuilder) -> Self {
+        self.args.analyses = dataflow.analyses;
+        self.stats_sinks
+            .extend(dataflow.sinks.into_iter().map(|(analysis, sink)| {
+                Box::new(AnalysisSink::new(analysis, sink)) as Box<dyn StatsSink>
+            }));
+
+        self
+    }
+
+    /// Runs the analysis within the current process, reading the logs of
+    /// `target_workers` timely workers that are attached to the returned
+    /// [`InProcess`] instead of connecting to them over tcp
+    pub fn in_process(self, target_workers: NonZeroUsize) -> Result<InProcess> {
+        InProcess::start(
+            self.args.with_memory_limit_applied(),
+            self.stats_sinks,
+            self.output_sinks,
+            target_workers,
+        )
+    }
+
+    /// Collects and analyzes the target's logs, blocking until the logs
+    /// have been fully processed
+    pub fn run(self) -> Result<DDShowStats> {
+        let args = Arc::new(self.args.with_memory_limit_applied());
+        let sources = self
+            .injected
+            .map(|injected| injected.into_sources(&args))
+            .transpose()?;
+
+        let collected = run::collect(&args, false, self.stats_sinks, sources)?
+            .ok_or_else(|| anyhow::anyhow!("the target exited before connecting to ddshow"))?;
+
+        Ok(DDShowStats {
+            data: collected.data,
+            target_runs: collected.target_runs,
+            dropped_events: collected.dropped_events,
+        })
+    }
+
+    /// Collects and analyzes the target's logs and then writes every output the
+    /// same way the cli does, including the sinks registered with
+    /// [`DDShowBuilder::output_sink()`]
+    pub fn render(self) -> Result<()> {
+        let start_time = Instant::now();
+        let args = Arc::new(self.args.with_memory_limit_applied());
+
+        let operator_groups = args
+            .operator_groups
+            .as_deref()
+            .map(OperatorGroups::load)
+            .transpose()?
+            .unwrap_or_default();
+        let baseline = args
+            .baseline
+            .as_deref()
+            .map(dump::load_baseline)
+            .transpose()?;
+
+        let sources = self
+            .injected
+            .map(|injected| injected.into_sources(&args))
+            .transpose()?;
+
+        let collected =
+            if let Some(collected) = run::collect(&args, false, self.stats_sinks, sources)? {
+                collected
+            } else {
+                return Ok(());
+            };
+
+        fs::create_dir_all(&args.output_dir).with_context(|| {
+            anyhow::anyhow!(
+                "failed to create output directory '{}'",
+                args.output_dir.display(),
+            )
+        })?;
+
+        run::render(
+            &args,
+            collected,
+            &operator_groups,
+            baseline.as_ref(),
+            self.output_sinks,
+            start_time,
+        )
+    }
+}
+
+impl Debug for DDShowBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DDShowBuilder")
+            .field("args", &self.args)
+            .field("stats_sinks", &self.stats_sinks.len())
+            .field("injected", &self.injected.is_some())
+            .field(
+                "output_sinks",
+                &self
+                    .output_sinks
+                    .iter()
+                    .map(|sink| sink.name())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Selects the analyses run by the analysis dataflow, everything is run by default
+///
+/// Leaving out analyses skips building their parts of the dataflow entirely, so
+/// embedders only pay for the stats they use
+///
+/// ```no_run
+/// use ddshow::{Analysis, DDShow, DataflowBuilder};
+///
+/// let dataflow = DataflowBuilder::new()
+///     .disable(Analysis::Timeline)
+///     .disable(Analysis::Arrangements);
+///
+/// let stats = DDShow::builder().replay("ddshow-logs").dataflow(dataflow).run()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct DataflowBuilder {
+    analyses: Analyses,
+    sinks: Vec<(Analysis, Box<dyn StatsSink>)>,
+}
+
+impl DataflowBuilder {
+    pub fn new() -> Self {
+        Self {
+            analyses: Analyses::all(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Runs the given analysis
+    pub fn enable(mut self, analysis: Analysis) -> Self {
+        self.analyses.set(analysis, true);
+        self
+    }
+
+    /// Skips the given analysis
+    pub fn disable(mut self, analysis: Analysis) -> Self {
+        self.analyses.set(analysis, false);
+        self
+    }
+
+    /// Streams the results of the given analysis into the sink, enabling the analysis
+    /// if it was disabled. Only the callbacks belonging to that analysis are called,
+    /// see [`Analysis`] for which those are
+    pub fn sink<T>(mut self, analysis: Analysis, sink: T) -> Self
+    where
+        T: StatsSink + 'static,
+    {
+        self.analyses.set(analysis, true);
+        self.sinks.push((analysis, Box::new(sink)));
+        self
+    }
+}
+
+impl Default for DataflowBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for DataflowBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataflowBuilder")
+            .field("analyses", &self.analyses)
+            .field(
+                "sinks",
+                &self
+                    .sinks
+                    .iter()
+                    .map(|&(analysis, _)| analysis)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// The stats computed by an analysis
+#[derive(Debug, Clone)]
+pub struct DDShowStats {
+    data: DataflowData,
+    target_runs: Vec<RunStats>,
+    dropped_events: Option<usize>,
+}
+
+impl DDShowStats {
+    /// Loads a dump that was written with either `--dump-json` or `--dump-binary`,
+    /// detecting which one it is from its contents
+    pub fn load<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        dump::load(path.as_ref()).map(Self::from_dump)
+    }
+
+    /// Loads a dump that was written with `--dump-json`
+    pub fn from_json<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        dump::load_json(path.as_ref()).map(Self::from_dump)
+    }
+
+    /// Loads a dump that was written with `--dump-binary`
+    pub fn from_binary<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        dump::load_binary(path.as_ref()).map(Self::from_dump)
+    }
+
+    /// Dumps don't record the target's runs or dropped events
+    fn from_dump(data: DataflowData) -> Self {
+        Self {
+            data,
+            target_runs: Vec::new(),
+            dropped_events: None,
+        }
+    }
+
+    /// Everything the analysis dataflow computed
+    pub const fn data(&self) -> &DataflowData {
+        &self.data
+    }
+
+    pub fn into_data(self) -> DataflowData {
+        self.data
+    }
+
+    /// Each separate run of the target, there's only more than one when the
+    /// target reconnected
+    pub fn target_runs(&self) -> &[RunStats] {
+        &self.target_runs
+    }
+
+    /// The number of events dropped due to full ingestion buffers, if
+    /// dropping events was enabled
+    pub const fn dropped_events(&self) -> Option<usize> {
+        self.dropped_events
+    }
+
+    /// Every worker of the target computation
+    pub fn workers(&self) -> &[WorkerId] {
+        &self.data.workers
+    }
+
+    /// The operator or subgraph at the given address
+    pub fn operator(&self, addr: &OperatorAddr) -> Option<&OperatesEvent> {
+        self.data
+            .nodes
+            .iter()
+            .chain(self.data.subgraphs.iter())
+            .find(|(operator_addr, _)| operator_addr == addr)
+            .map(|(_, operator)| operator)
+    }
+
+    /// The activation stats of the operator at the given address on each
+    /// worker that it ran on
+    pub fn operator_stats<'a>(
+        &'a self,
+        addr: &OperatorAddr,
+    ) -> impl Iterator<Item = (WorkerId, &'a Summation)> + 'a {
+        let keys: HashSet<_, XXHasher> = self
+            .data
+            .addr_lookup
+            .iter()
+            .filter(|(_, operator_addr)| operator_addr == addr)
+            .map(|&(key, _)| key)
+            .collect();
+
+        self.data
+            .summarized
+            .iter()
+            .filter(move |(key, _)| keys.contains(key))
+            .map(|((worker, _), stats)| (*worker, stats))
+    }
+
+    /// The activation stats of every operator that ran on the given worker,
+    /// along with their addresses
+    pub fn worker_stats(
+        &self,
+        worker: WorkerId,
+    ) -> impl Iterator<Item = (&OperatorAddr, &Summation)> + '_ {
+        let addrs: HashMap<_, _, XXHasher> = self
+            .data
+            .addr_lookup
+            .iter()
+            .filter(|((operator_worker, _), _)| *operator_worker == worker)
+            .map(|(key, addr)| (*key, addr))
+            .collect();
+
+        self.data
+            .summarized
+            .iter()
+            .filter_map(move |(key, stats)| addrs.get(key).map(|&addr| (addr, stats)))
+    }
+
+    /// How the given worker's time was split between work, idling and parking
+    pub fn worker_utilization(&self, worker: WorkerId) -> Option<&WorkerUtilization> {
+        self.data
+            .worker_utilization
+            .iter()
+            .find(|(utilized_worker, _)| *utilized_worker == worker)
+            .map(|(_, utilization)| utilization)
+    }
+
+    /// Each operator's activation stats merged across every worker, keyed by address
+    pub fn aggregated_stats(&self) -> BTreeMap<&OperatorAddr, Summation> {
+        diff::operator_totals(&self.data)
+            .into_iter()
+            .map(|(addr, (_, stats))| (addr, stats))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DDShow, DDShowStats};
+    use crate::{dataflow::DataflowData, Summation};
+    use ddshow_types::{
+        timely_logging::{OperatesEvent, TimelyEvent},
+        OperatorAddr, OperatorId, WorkerId,
+    };
+    use std::{num::NonZeroUsize, time::Duration};
+
+    fn stats(total_ms: u64) -> Summation {
+        let total = Duration::from_millis(total_ms);
+        Summation::new(total, total, total, total, 1)
+    }
+
+    #[test]
+    fn queries_by_address_and_worker() {
+        let mut data = DataflowData::default();
+
+        let addr = OperatorAddr::from(vec![0, 1]);
+        for worker in 0..2 {
+            let key = (WorkerId::new(worker), OperatorId::new(1));
+            data.addr_lookup.push((key, addr.clone()));
+            data.summarized.push((key, stats(10 * (worker as u64 + 1))));
+        }
+
+        let stats = DDShowStats::from_dump(data);
+        assert_eq!(stats.operator_stats(&addr).count(), 2);
+        assert_eq!(stats.worker_stats(WorkerId::new(1)).count(), 1);
+        assert_eq!(
+            stats.aggregated_stats()[&addr].total,
+            Duration::from_millis(30),
+        );
+    }
+
+    #[test]
+    fn analyzes_injected_events() {
+        let (dataflow, map) = (OperatorAddr::from(vec![0]), OperatorAddr::from(vec![0, 1]));
+        let events = vec![
+            (
+                Duration::from_millis(1),
+                WorkerId::new(0),
+                TimelyEvent::Operates(OperatesEvent::new(
+                    OperatorId::new(0),
+                    dataflow,
+                    "Dataflow".to_owned(),
+                )),
+            ),
+            (
+                Duration::from_millis(2),
+                WorkerId::new(0),
+                TimelyEvent::Operates(OperatesEvent::new(
+                    OperatorId::new(1),
+                    map.clone(),
+                    "Map".to_owned(),
+                )),
+            ),
+        ];
+
+        let stats = DDShow::builder()
+            .workers(NonZeroUsize::new(1).unwrap())
+            .inject_timely(events)
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            stats.operator(&map).map(|operator| operator.name.as_str()),
+            Some("Map"),
+        );
+    }
+}
@@ -1,12 +1,16 @@
 pub use colorous::Gradient;
 
+use crate::dataflow::constants::{DEFAULT_INGEST_BUFFER, MEMORY_LIMIT_TIMELINE_MICROS};
+use ddshow_types::WorkerId;
 use differential_dataflow::Config as DifferentialConfig;
+use regex::Regex;
 use std::{
     fmt::{self, Display},
     net::SocketAddr,
     num::NonZeroUsize,
     path::PathBuf,
     str::FromStr,
+    time::Duration,
 };
 use structopt::{clap::Shell, StructOpt};
 use timely::{CommunicationConfig, WorkerConfig};
@@ -18,6 +22,12 @@ use timely::{CommunicationConfig, WorkerConfig};
 /// and the `DIFFERENTIAL_LOG_ADDR` variable to gather data on Differential Dataflow computations.
 /// Set `--connections` to the number of timely workers that the target computation is using.
 ///
+/// Alternatively, pass the target computation's command after `--` and ddshow will
+/// spawn it with the correct environmental variables already set.
+///
+/// Running ddshow without a subcommand captures and analyzes the target in one go,
+/// the `record`, `analyze`, `serve` and `diff` subcommands each do just one part of that.
+///
 // TODO: Better docs
 // TODO: Number of workers
 // TODO: Save logs to file
@@ -25,7 +35,6 @@ use timely::{CommunicationConfig, WorkerConfig};
 // TODO: Progress logging
 // TODO: Build info in help message
 // TODO: Reachability logging
-// TODO: Disable timeline events
 // TODO: This is complex enough to where it may need an
 //       actual config file
 #[derive(Debug, Clone, StructOpt)]
@@ -35,6 +44,15 @@ pub struct Args {
     #[structopt(short = "w", long, default_value = "1")]
     pub workers: NonZeroUsize,
 
+    /// Splits each key of the heaviest reductions (activation stats and the
+    /// timeline tiers) across this many ddshow workers, merging their
+    /// results afterwards
+    ///
+    /// Every event of an extremely hot operator otherwise ends up on the same
+    /// ddshow worker, so this only helps with more than one `--workers`
+    #[structopt(long, value_name = "SALTS")]
+    pub exchange_salt: Option<NonZeroUsize>,
+
     /// The number of timely workers running in the target computation
     #[structopt(short = "c", long = "connections", default_value = "1")]
     pub timely_connections: NonZeroUsize,
@@ -70,16 +88,70 @@ pub struct Args {
     )]
     pub palette: Gradient,
 
+    /// The metric that decides the fill color of each operator within the graph
+    ///
+    /// `idle-time` is the time the operator's worker spent running
+    /// without the operator being scheduled
+    #[structopt(
+        long,
+        default_value = "activation-time",
+        possible_values = &["activation-time", "invocations", "arrangement-size", "idle-time"],
+    )]
+    pub color_by: ColorBy,
+
+    /// The color theme of the generated graph, `auto` follows the
+    /// viewer's system preference
+    #[structopt(
+        long,
+        default_value = "auto",
+        possible_values = &["light", "dark", "auto"],
+    )]
+    pub theme: Theme,
+
+    /// Links the source locations of operators to a repository, given as a url
+    /// with `{file}`, `{line}` and `{column}` placeholders
+    ///
+    /// Operators named with a `Name @ file:line:column` suffix (as produced by
+    /// `std::panic::Location`) have their locations shown within the graph,
+    /// e.g. `https://github.com/user/repo/blob/main/{file}#L{line}`
+    #[structopt(long, value_name = "URL")]
+    pub source_url: Option<String>,
+
     /// The directory to generate artifacts in
     #[structopt(long, default_value = "dataflow-graph")]
     pub output_dir: PathBuf,
 
-    /// The path to dump the json data to
+    /// The path to dump the analyzed data to as json
     ///
-    /// The format is currently unstable, so don't depend on it too hard
-    #[structopt(long, hidden(true))]
+    /// Dumps can be compared with `ddshow diff`. The format is currently
+    /// unstable, so don't depend on it too hard. Paths ending in `.gz` are
+    /// gzip compressed
+    #[structopt(long)]
     pub dump_json: Option<PathBuf>,
 
+    /// The path to dump the analyzed data to in a compact binary format
+    ///
+    /// Binary dumps can only be read by the same version of ddshow
+    #[structopt(long)]
+    pub dump_binary: Option<PathBuf>,
+
+    /// A dump of a previous run to compare against, every operator within
+    /// the report is annotated with how much it changed since then
+    #[structopt(long, value_name = "DUMP")]
+    pub baseline: Option<PathBuf>,
+
+    /// Operators whose runtime, activations or arrangement size grew by more
+    /// than this percentage over the `--baseline` are flagged as regressions
+    #[structopt(long, value_name = "PERCENT", default_value = "10")]
+    pub regression_threshold: f64,
+
+    /// Gzips the graph's data files, they're decompressed by the browser
+    /// once the graph is opened
+    ///
+    /// Requires a browser that supports `DecompressionStream`
+    #[structopt(long)]
+    pub compress_data: bool,
+
     /// The folder to save the target process's logs to
     #[structopt(long)]
     pub save_logs: Option<PathBuf>,
@@ -99,12 +171,74 @@ pub struct Args {
     )]
     pub replay_logs: Option<Vec<PathBuf>>,
 
-    /// The file to output a text report to
+    /// Keep listening for the target to reconnect for this many seconds after
+    /// its log streams close
+    ///
+    /// Each reconnection starts a new run of the target, runs are shifted in
+    /// time to follow one another and the workers of the `n`th run are
+    /// numbered starting at `n * connections`. Requires the `rkyv`, `bincode`
+    /// or `auto` stream encodings
+    #[structopt(long, value_name = "SECONDS", conflicts_with("replay-logs"))]
+    pub reconnect_timeout: Option<u64>,
+
+    /// Finish collecting once the log streams have gone this many seconds
+    /// without receiving any events, even if their connections are still open
+    ///
+    /// Long-lived services never close their log connections, this lets ddshow
+    /// analyze them once they've settled down
+    #[structopt(long, value_name = "SECONDS", conflicts_with("replay-logs"))]
+    pub completion_idle: Option<u64>,
+
+    /// Hosts the graph on a local http server at the given address, pushing
+    /// live stats to it while the target is still running
+    ///
+    /// Once the final graph has been rendered the server keeps hosting it
+    /// until ddshow is interrupted with ctrl+c. Requires the `live-server`
+    /// feature
+    #[structopt(long, value_name = "ADDR")]
+    pub serve: Option<SocketAddr>,
+
+    /// The PEM-encoded certificate chain to terminate TLS on the log listeners with
+    ///
+    /// When given along with `--tls-key` every log stream must connect over
+    /// TLS, which keeps streams from remote hosts from being sent in plaintext
+    #[structopt(long, requires("tls-key"), conflicts_with("replay-logs"))]
+    pub tls_cert: Option<PathBuf>,
+
+    /// The PEM-encoded private key (PKCS#8 or RSA) for `--tls-cert`
+    #[structopt(long, requires("tls-cert"), conflicts_with("replay-logs"))]
+    pub tls_key: Option<PathBuf>,
+
+    /// A TOML file declaring logical groups of operators
+    ///
+    /// Each `[[group]]` has a `name` and a list of `operators` addresses,
+    /// every operator whose address starts with one of them is considered
+    /// part of the group. Stats are aggregated per group and each group
+    /// is outlined within the generated graph
+    #[structopt(long)]
+    pub operator_groups: Option<PathBuf>,
+
+    /// The file to output the report to, when left as `report.txt` the
+    /// extension follows the `--report-format` that's used
     #[structopt(long, default_value = "report.txt")]
     pub report_file: PathBuf,
 
-    /// Disables text report generation
-    #[structopt(long, conflicts_with("report-file"))]
+    /// The format to write the report in
+    #[structopt(
+        long,
+        default_value = "text",
+        possible_values = &["text", "markdown", "json", "html"],
+    )]
+    pub report_format: ReportFormat,
+
+    /// Also writes the report as json to the given file, regardless of
+    /// `--report-format`, so that scripts can consume the same sections,
+    /// warnings and findings that the report holds
+    #[structopt(long, value_name = "FILE")]
+    pub report_json: Option<PathBuf>,
+
+    /// Disables report generation
+    #[structopt(long, conflicts_with_all(&["report-file", "report-json"]))]
     pub no_report_file: bool,
 
     /// The coloring to use for terminal output
@@ -115,10 +249,164 @@ pub struct Args {
     #[structopt(long, hidden(true))]
     pub dataflow_profiling: bool,
 
-    /// Disables dataflow timeline analysis, can vastly improve performance
-    /// and memory usage on very large target dataflows
+    /// Skips collecting the worker timeline entirely, can vastly improve
+    /// performance, memory usage and output size on long captures or very
+    /// large target dataflows
+    #[structopt(long, alias = "disable-timeline")]
+    pub no_timeline: bool,
+
+    /// Spills the worker timeline to temporary files once it takes up more
+    /// than this many mebibytes of memory, merging it back together when rendering
+    ///
+    /// Keeps multi-hour captures from running out of memory at the cost of
+    /// some disk io, without it the whole timeline is kept in memory
+    #[structopt(long, value_name = "MiB", conflicts_with("no-timeline"))]
+    pub timeline_memory_budget: Option<NonZeroUsize>,
+
+    /// Merges each worker's timeline events of the same kind that are less
+    /// than this many microseconds apart into a single event
+    ///
+    /// Shrinks the timelines of chatty operators that are activated over
+    /// and over again, at the cost of the gaps between their activations
+    #[structopt(long, value_name = "MICROS", conflicts_with("no-timeline"))]
+    pub timeline_merge_gap: Option<u64>,
+
+    /// Coalesces timeline events shorter than this many microseconds into
+    /// "minor activity" spans, no matter what kind of event they were
+    #[structopt(long, value_name = "MICROS", conflicts_with("no-timeline"))]
+    pub timeline_minor_threshold: Option<u64>,
+
+    /// The number of exact activation durations kept for each operator
+    ///
+    /// Operators that are activated more often than this are still fully
+    /// summarized, but their percentiles come from a quantile sketch and
+    /// only their first activations are shown within the graph
+    #[structopt(long, value_name = "COUNT", default_value = "10000")]
+    pub exact_activations: usize,
+
+    /// The most data points kept within each arrangement's size, batch and
+    /// merge stats and within each scope's iteration times
+    ///
+    /// The stats themselves are always computed from every data point, past
+    /// this many points only an evenly spaced sample of them is kept
+    #[structopt(long, value_name = "COUNT", default_value = "10000")]
+    pub max_data_points: usize,
+
+    /// The number of operators kept within the leaderboard of the slowest
+    /// operators, ranked by their total activation time across workers, and
+    /// within each of the report's top offenders tables
+    #[structopt(long, value_name = "COUNT", default_value = "20")]
+    pub top_k: usize,
+
+    /// The upper bounds of the buckets that each operator's activation
+    /// durations are sorted into, in microseconds
+    ///
+    /// Activations longer than the last bound land in an extra overflow bucket
+    #[structopt(
+        long,
+        value_name = "MICROS",
+        use_delimiter = true,
+        default_value = "10,100,1000,10000,100000"
+    )]
+    pub histogram_buckets: Vec<u64>,
+
+    /// The width of the windows that each operator's activation rate is
+    /// measured over, in milliseconds
+    #[structopt(long, value_name = "MILLIS", default_value = "100")]
+    pub activation_rate_interval: u64,
+
+    /// Activations that take longer than this multiple of their operator's
+    /// median activation time are flagged as anomalies
+    #[structopt(long, value_name = "MULTIPLE", default_value = "10")]
+    pub anomaly_threshold: f64,
+
+    /// Workers whose busy time, logged events or sent records deviate from
+    /// the mean across all workers by more than this percentage are flagged
+    /// as imbalanced within the report
+    #[structopt(long, value_name = "PERCENT", default_value = "25")]
+    pub imbalance_threshold: f64,
+
+    /// Operators with any single activation that took longer than this
+    /// are flagged within the report, e.g. `500us` or `1s 200ms`
+    #[structopt(
+        long,
+        value_name = "DURATION",
+        parse(try_from_str = humantime::parse_duration),
+    )]
+    pub warn_activation_over: Option<Duration>,
+
+    /// Arrangements that held more than this many updates at once are
+    /// flagged within the report
+    #[structopt(long, value_name = "UPDATES")]
+    pub warn_arrangement_over: Option<usize>,
+
+    /// Exits with an error after writing every output if any operator went
+    /// over `--warn-activation-over` or `--warn-arrangement-over`, for use
+    /// as a performance gate within ci
     #[structopt(long)]
-    pub disable_timeline: bool,
+    pub fail_on_warnings: bool,
+
+    /// Event kinds to drop as soon as they're replayed, before they
+    /// enter the analysis dataflow
+    ///
+    /// Dropping high-volume events like `messages` or `park` can vastly
+    /// reduce memory usage on long captures at the cost of the stats that
+    /// are derived from them. `operates` and `channels` events describe
+    /// the dataflow graph itself and can't be ignored
+    #[structopt(
+        long,
+        value_name = "KINDS",
+        use_delimiter = true,
+        possible_values = IgnoredEvent::ACCEPTED,
+    )]
+    pub ignore_events: Vec<IgnoredEvent>,
+
+    /// Only ingest events logged by the given target workers, dropping
+    /// all others as soon as they're replayed
+    #[structopt(long, value_name = "IDS", use_delimiter = true)]
+    pub only_workers: Option<Vec<usize>>,
+
+    /// Only render the stats of the given target workers within the graph
+    ///
+    /// Unlike `--only-workers` every worker is still analyzed, the others
+    /// are just left out of the graph's nodes, edges and worker toggles
+    #[structopt(long, value_name = "IDS", use_delimiter = true)]
+    pub workers_filter: Option<Vec<usize>>,
+
+    /// Merges the stats of each operator across every worker, so each node
+    /// within the graph shows the summed invocations and the combined
+    /// min, max and average activation times of all its workers
+    #[structopt(long, conflicts_with = "workers-filter")]
+    pub aggregate_workers: bool,
+
+    /// Only analyze the operators whose names or addresses match the given regex
+    ///
+    /// Addresses are matched in the form of `[0, 3, 1]`. The scopes that contain
+    /// a matching operator are kept so that the graph stays connected, every
+    /// other operator is left out of the stats, graph, timeline and report
+    #[structopt(long, value_name = "REGEX")]
+    pub filter: Option<Regex>,
+
+    /// Leaves the operators whose names or addresses match the given regex out of the
+    /// analysis, applied after `--filter`
+    #[structopt(long, value_name = "REGEX")]
+    pub exclude: Option<Regex>,
+
+    /// Listens for an extra stream of application events, given as `NAME=ADDR`
+    ///
+    /// Each stream receives newline-delimited json records like
+    /// `{"time_ns": 1500, "worker": 0, "label": "flush", "duration_ns": 20}`
+    /// and is shown as its own lane within the timeline. `worker` defaults
+    /// to the index of the connection and `duration_ns` to zero. When ddshow
+    /// spawns the target, each stream's address is given to it within the
+    /// `DDSHOW_CUSTOM_LOG_ADDR_<NAME>` environmental variable
+    #[structopt(
+        long = "custom-stream",
+        value_name = "NAME=ADDR",
+        number_of_values = 1,
+        conflicts_with("replay-logs")
+    )]
+    pub custom_streams: Vec<CustomStreamArg>,
 
     /// Sets the stream encoding for ddshow's tcp streams
     ///
@@ -128,14 +416,72 @@ pub struct Args {
     /// infrastructure. Note that when using the `abomonation`
     /// stream encoding the versions of timely that the target
     /// program uses must be the same as the version that ddshow
-    /// uses due to how `abomonation` works.
+    /// uses due to how `abomonation` works. `bincode` accepts
+    /// ddshow's event types encoded with serde & bincode, each
+    /// prefixed by its length as a little-endian `u64`, which
+    /// is useful for cross-version or cross-architecture captures.
+    /// `auto` detects the encoding of each stream from the first
+    /// bytes it sends
     #[structopt(
         long,
         default_value = "abomonation",
-        possible_values = &["abomonation", "rkyv"],
+        possible_values = &["abomonation", "rkyv", "bincode", "auto"],
     )]
     pub stream_encoding: StreamEncoding,
 
+    /// The number of event batches buffered for each log stream while the
+    /// analysis dataflow catches up [default: 4096]
+    ///
+    /// Every stream is read and decoded on its own thread so that slow
+    /// analysis operators don't stop ddshow from reading the target's
+    /// sockets. Once a buffer fills up `--buffer-policy` decides whether to
+    /// push backpressure onto the target or drop events
+    #[structopt(
+        long,
+        value_name = "BATCHES",
+        conflicts_with_all(&["replay-logs", "inline-ingestion"]),
+    )]
+    pub ingest_buffer: Option<NonZeroUsize>,
+
+    /// Reads log streams from directly within the analysis dataflow instead
+    /// of on dedicated ingestion threads
+    ///
+    /// Anything the dataflow falls behind on is left within the target's
+    /// socket buffers, which stalls the target once they fill up
+    #[structopt(long)]
+    pub inline_ingestion: bool,
+
+    /// Only keeps the message events of every nth message sent over each channel
+    ///
+    /// Both the send and the receive of a sampled message are kept, so the
+    /// channel stats only count the sampled messages
+    #[structopt(long, value_name = "N")]
+    pub sample_messages: Option<NonZeroUsize>,
+
+    /// Keeps ddshow within roughly this many bytes of memory so that it can
+    /// be attached to production jobs on shared hosts
+    ///
+    /// Degrades every subsystem that would otherwise grow with the length of
+    /// the capture: fewer exact activations and data points are kept in favor
+    /// of sketches, the timeline is compacted and spilled to disk, message
+    /// events are sampled and ingestion buffers are bounded. Options that are
+    /// given explicitly are only ever tightened, never loosened
+    #[structopt(long, value_name = "BYTES")]
+    pub memory_limit: Option<NonZeroUsize>,
+
+    /// What to do when an ingestion buffer fills up
+    ///
+    /// `block` stops reading from the stream until there's room, which
+    /// slows the target down once its socket buffers fill. `drop` throws
+    /// away the incoming events and counts them instead, progress updates
+    /// are never dropped
+    #[structopt(
+        long,
+        default_value = "block",
+        possible_values = &["block", "drop"],
+    )]
+    pub buffer_policy: BufferPolicy,
+
     /// The time between updating the report file in seconds
     #[structopt(long, conflicts_with("no-report-file"), hidden(true))]
     pub report_update_duration: Option<u8>,
@@ -144,6 +490,25 @@ pub struct Args {
     #[structopt(long, short = "q")]
     pub quiet: bool,
 
+    /// Raises the level of ddshow's own logs, `-v` shows info, `-vv` debug
+    /// and `-vvv` trace logs
+    ///
+    /// The `DDSHOW_LOG` environmental variable takes precedence when it's set
+    #[structopt(long, short = "v", parse(from_occurrences))]
+    pub verbose: u8,
+
+    /// Where ddshow's own logs are written to
+    #[structopt(
+        long,
+        default_value = "stderr",
+        possible_values = &["stdout", "stderr", "quiet"],
+    )]
+    pub log_output: Output,
+
+    /// Also writes ddshow's own logs to `ddshow.log` within the output directory
+    #[structopt(long)]
+    pub log_file: bool,
+
     /// Prints out a text representation of the given replay files
     #[structopt(long, hidden(true), requires("replay-logs"))]
     pub debug_replay_files: bool,
@@ -151,9 +516,175 @@ pub struct Args {
     /// Generates shell completions for the given shell
     #[structopt(long, possible_values = &["bash", "fish", "zsh", "powershell", "elvish"])]
     pub completions: Option<Shell>,
+
+    /// The target computation to spawn and profile
+    ///
+    /// When given (e.g. `ddshow --workers 4 -- cargo run --release`), ddshow
+    /// will start the command with `TIMELY_WORKER_LOG_ADDR` (along with
+    /// `DIFFERENTIAL_LOG_ADDR` and `TIMELY_PROGRESS_LOG_ADDR` when enabled)
+    /// pointed at its listeners and will finish up once the command exits
+    #[structopt(last = true, conflicts_with("replay-logs"))]
+    pub target_command: Vec<String>,
+
+    /// The optional analyses that the dataflow runs, only selectable
+    /// through the library's `DataflowBuilder`
+    #[structopt(skip)]
+    pub analyses: Analyses,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Command {
+    /// Captures the target's logs to disk without analyzing them
+    ///
+    /// The saved logs can be analyzed later on with `ddshow analyze`
+    Record(RecordArgs),
+
+    /// Replays and analyzes a set of recorded logs, rendering the graph and report
+    Analyze(AnalyzeArgs),
+
+    /// Captures and analyzes the target while hosting the graph on a local http server
+    ///
+    /// Requires the `live-server` feature
+    Serve(ServeArgs),
+
+    /// Compares two runs that were saved with `--dump-json` or `--dump-binary`
+    ///
+    /// Writes a comparison report to `--report-file` and a graph of the new run
+    /// to `--output-dir` whose operators are colored by their relative change
+    /// in runtime, red operators got slower and green ones got faster
+    Diff(DiffArgs),
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct RecordArgs {
+    /// The folder to save the target process's logs to
+    pub logs: PathBuf,
+
+    /// The target computation to spawn and record
+    #[structopt(last = true)]
+    pub target_command: Vec<String>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct AnalyzeArgs {
+    /// The directories of recorded logs to replay
+    #[structopt(required = true, min_values = 1)]
+    pub logs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct ServeArgs {
+    /// The address to host the graph at
+    #[structopt(default_value = "127.0.0.1:8080")]
+    pub address: SocketAddr,
+
+    /// The target computation to spawn and profile
+    #[structopt(last = true)]
+    pub target_command: Vec<String>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct DiffArgs {
+    /// The dump of the baseline run
+    pub old: PathBuf,
+
+    /// The dump of the run to compare against the baseline
+    pub new: PathBuf,
 }
 
 impl Args {
+    /// Parses the cli args, folding the options of the `record`, `analyze` and
+    /// `serve` subcommands into their flat counterparts
+    pub fn from_cli() -> Self {
+        Self::from_args()
+            .with_command_applied()
+            .with_memory_limit_applied()
+    }
+
+    fn with_command_applied(mut self) -> Self {
+        match self.command.clone() {
+            Some(Command::Record(record)) => {
+                self.save_logs = Some(record.logs);
+                if !record.target_command.is_empty() {
+                    self.target_command = record.target_command;
+                }
+            }
+
+            Some(Command::Analyze(analyze)) => {
+                self.replay_logs = Some(analyze.logs);
+            }
+
+            Some(Command::Serve(serve)) => {
+                self.serve = Some(serve.address);
+                if !serve.target_command.is_empty() {
+                    self.target_command = serve.target_command;
+                }
+            }
+
+            Some(Command::Diff(_)) | None => {}
+        }
+
+        if self.report_file == PathBuf::from("report.txt") {
+            self.report_file
+                .set_extension(self.report_format.extension());
+        }
+
+        self
+    }
+
+    /// Applies the degradation policy of `--memory-limit`, scaled to the limit
+    pub(crate) fn with_memory_limit_applied(mut self) -> Self {
+        let limit = match self.memory_limit {
+            Some(limit) => limit.get(),
+            None => return self,
+        };
+        let mib = (limit / (1024 * 1024)).max(1);
+
+        // A gibibyte keeps the defaults, smaller limits scale them down
+        let data_points = (mib * 10).clamp(100, 10_000);
+        self.exact_activations = self.exact_activations.min(data_points);
+        self.max_data_points = self.max_data_points.min(data_points);
+
+        // A quarter of the limit goes towards the timeline, everything
+        // past that is spilled to disk
+        if !self.no_timeline {
+            let budget = NonZeroUsize::new((mib / 4).max(1)).unwrap();
+            self.timeline_memory_budget = Some(
+                self.timeline_memory_budget
+                    .map_or(budget, |current| current.min(budget)),
+            );
+            self.timeline_merge_gap
+                .get_or_insert(MEMORY_LIMIT_TIMELINE_MICROS);
+            self.timeline_minor_threshold
+                .get_or_insert(MEMORY_LIMIT_TIMELINE_MICROS);
+        }
+
+        if mib < 1024 {
+            self.sample_messages
+                .get_or_insert(NonZeroUsize::new((1024 / mib).min(64)).unwrap());
+        }
+
+        if let Some(capacity) = self.ingest_capacity() {
+            let batches = NonZeroUsize::new(mib.clamp(16, 1024)).unwrap();
+            self.ingest_buffer = Some(capacity.min(batches));
+        }
+
+        self
+    }
+
+    /// Returns `true` if the logs should only be saved to disk, not analyzed
+    pub fn records_only(&self) -> bool {
+        matches!(self.command, Some(Command::Record(_)))
+    }
+
     pub fn timely_config(&self) -> (CommunicationConfig, WorkerConfig) {
         let communication = if self.workers.get() == 1 {
             CommunicationConfig::Thread
@@ -176,6 +707,47 @@ impl Args {
         self.replay_logs.is_some()
     }
 
+    /// Returns `true` if ddshow should spawn the target computation itself
+    pub fn spawns_target(&self) -> bool {
+        !self.target_command.is_empty()
+    }
+
+    /// Returns `true` if the given target worker should be rendered within the graph
+    pub fn renders_worker(&self, worker: WorkerId) -> bool {
+        self.workers_filter
+            .as_ref()
+            .map_or(true, |workers| workers.contains(&worker.into_inner()))
+    }
+
+    /// The capacity of each stream's ingestion buffer, or `None` if streams are
+    /// read from within the dataflow. Ingestion threads are never used while
+    /// replaying logs from disk
+    pub fn ingest_capacity(&self) -> Option<NonZeroUsize> {
+        if self.replay_logs.is_some() || self.inline_ingestion {
+            None
+        } else {
+            Some(self.ingest_buffer.unwrap_or(DEFAULT_INGEST_BUFFER))
+        }
+    }
+
+    /// Returns `true` if the timeline should be compacted
+    pub fn compacts_timeline(&self) -> bool {
+        self.timeline_merge_gap.is_some() || self.timeline_minor_threshold.is_some()
+    }
+
+    /// The sorted and deduplicated bounds of `--histogram-buckets`
+    pub fn histogram_bounds(&self) -> Vec<Duration> {
+        let mut bounds: Vec<_> = self
+            .histogram_buckets
+            .iter()
+            .map(|&micros| Duration::from_micros(micros))
+            .collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        bounds
+    }
+
     pub fn is_quiet(&self) -> bool {
         self.quiet || atty::isnt(atty::Stream::Stdout)
     }
@@ -183,6 +755,21 @@ impl Args {
     pub fn isnt_quiet(&self) -> bool {
         !self.is_quiet()
     }
+
+    /// Returns `true` if the worker timeline is collected
+    pub const fn runs_timeline(&self) -> bool {
+        !self.no_timeline && self.analyses.runs(Analysis::Timeline)
+    }
+
+    /// The level of ddshow's own logs selected by `-v`
+    pub const fn log_level(&self) -> &'static str {
+        match self.verbose {
+            0 => "warn",
+            1 => "info",
+            2 => "debug",
+            _ => "trace",
+        }
+    }
 }
 
 impl Default for Args {
@@ -192,6 +779,7 @@ impl Default for Args {
 
         Self {
             workers: ONE,
+            exchange_salt: None,
             timely_connections: ONE,
             timely_address: "127.0.0.1:51317".parse().unwrap(),
             differential_enabled: false,
@@ -199,28 +787,150 @@ impl Default for Args {
             progress_enabled: false,
             progress_address: "127.0.0.1:51319".parse().unwrap(),
             palette: colorous::INFERNO,
+            color_by: ColorBy::ActivationTime,
+            theme: Theme::Auto,
+            source_url: None,
             output_dir: PathBuf::from("dataflow-graph"),
             dump_json: None,
+            dump_binary: None,
+            baseline: None,
+            regression_threshold: 10.0,
+            compress_data: false,
             save_logs: None,
             replay_logs: None,
+            reconnect_timeout: None,
+            completion_idle: None,
+            serve: None,
+            tls_cert: None,
+            tls_key: None,
+            operator_groups: None,
             report_file: PathBuf::from("report.txt"),
+            report_format: ReportFormat::Text,
+            report_json: None,
             no_report_file: false,
             color: TerminalColor::Auto,
             dataflow_profiling: false,
-            disable_timeline: false,
+            no_timeline: false,
+            timeline_memory_budget: None,
+            timeline_merge_gap: None,
+            timeline_minor_threshold: None,
+            exact_activations: 10_000,
+            max_data_points: 10_000,
+            top_k: 20,
+            histogram_buckets: vec![10, 100, 1_000, 10_000, 100_000],
+            activation_rate_interval: 100,
+            anomaly_threshold: 10.0,
+            imbalance_threshold: 25.0,
+            warn_activation_over: None,
+            warn_arrangement_over: None,
+            fail_on_warnings: false,
+            ignore_events: Vec::new(),
+            only_workers: None,
+            workers_filter: None,
+            aggregate_workers: false,
+            filter: None,
+            exclude: None,
+            custom_streams: Vec::new(),
             stream_encoding: StreamEncoding::Abomonation,
+            ingest_buffer: None,
+            inline_ingestion: false,
+            sample_messages: None,
+            memory_limit: None,
+            buffer_policy: BufferPolicy::Block,
             report_update_duration: None,
             quiet: false,
+            verbose: 0,
+            log_output: Output::Stderr,
+            log_file: false,
             debug_replay_files: false,
             completions: None,
+            target_command: Vec::new(),
+            analyses: Analyses::all(),
+            command: None,
         }
     }
 }
 
+/// An optional part of the analysis dataflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Analysis {
+    /// The worker timeline, feeds [`StatsSink::on_timeline_event()`]
+    ///
+    /// [`StatsSink::on_timeline_event()`]: crate::StatsSink::on_timeline_event
+    Timeline,
+    /// Arrangement sizes and batches from the differential logs, feeds
+    /// [`StatsSink::on_arrangement_stats()`]
+    ///
+    /// [`StatsSink::on_arrangement_stats()`]: crate::StatsSink::on_arrangement_stats
+    Arrangements,
+    /// Program-wide stats like worker utilization, parking and per-dataflow
+    /// totals, feeds [`StatsSink::on_worker_stats()`]
+    ///
+    /// [`StatsSink::on_worker_stats()`]: crate::StatsSink::on_worker_stats
+    ProgramStats,
+    /// The target's operators, edges and subgraphs, feeds [`StatsSink::on_operator()`]
+    /// and [`StatsSink::on_channel_stats()`]
+    ///
+    /// [`StatsSink::on_operator()`]: crate::StatsSink::on_operator
+    /// [`StatsSink::on_channel_stats()`]: crate::StatsSink::on_channel_stats
+    Graph,
+}
+
+/// The set of optional analyses that are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Analyses {
+    timeline: bool,
+    arrangements: bool,
+    program_stats: bool,
+    graph: bool,
+}
+
+impl Analyses {
+    /// Runs every analysis
+    pub const fn all() -> Self {
+        Self {
+            timeline: true,
+            arrangements: true,
+            program_stats: true,
+            graph: true,
+        }
+    }
+
+    /// Returns `true` if the given analysis is run
+    pub const fn runs(&self, analysis: Analysis) -> bool {
+        match analysis {
+            Analysis::Timeline => self.timeline,
+            Analysis::Arrangements => self.arrangements,
+            Analysis::ProgramStats => self.program_stats,
+            Analysis::Graph => self.graph,
+        }
+    }
+
+    pub fn set(&mut self, analysis: Analysis, enabled: bool) -> &mut Self {
+        match analysis {
+            Analysis::Timeline => self.timeline = enabled,
+            Analysis::Arrangements => self.arrangements = enabled,
+            Analysis::ProgramStats => self.program_stats = enabled,
+            Analysis::Graph => self.graph = enabled,
+        }
+
+        self
+    }
+}
+
+impl Default for Analyses {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum StreamEncoding {
     Abomonation,
     Rkyv,
+    Bincode,
+    /// Detect the encoding of each stream individually
+    Auto,
 }
 
 impl FromStr for StreamEncoding {
@@ -231,8 +941,10 @@ impl FromStr for StreamEncoding {
         match lowercase.as_str() {
             "abomonation" => Ok(Self::Abomonation),
             "rkyv" => Ok(Self::Rkyv),
+            "bincode" => Ok(Self::Bincode),
+            "auto" => Ok(Self::Auto),
             _ => Err(format!(
-                "invalid terminal color {:?}, only `rkyv` and `abomonation` are supported",
+                "invalid stream encoding {:?}, only `rkyv`, `abomonation`, `bincode` and `auto` are supported",
                 string,
             )),
         }
@@ -244,6 +956,8 @@ impl Display for StreamEncoding {
         match self {
             Self::Abomonation => f.write_str("abomonation"),
             Self::Rkyv => f.write_str("rkyv"),
+            Self::Bincode => f.write_str("bincode"),
+            Self::Auto => f.write_str("auto"),
         }
     }
 }
@@ -254,6 +968,322 @@ impl Default for StreamEncoding {
     }
 }
 
+/// A custom stream given on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomStreamArg {
+    pub name: String,
+    pub address: SocketAddr,
+}
+
+impl CustomStreamArg {
+    /// The environmental variable the stream's address is given to the target within
+    pub fn env_var(&self) -> String {
+        format!(
+            "DDSHOW_CUSTOM_LOG_ADDR_{}",
+            self.name.to_uppercase().replace('-', "_"),
+        )
+    }
+}
+
+impl FromStr for CustomStreamArg {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (name, address) = string
+            .split_once('=')
+            .ok_or_else(|| format!("invalid custom stream {:?}, expected `NAME=ADDR`", string))?;
+
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_');
+        if !valid_name {
+            return Err(format!(
+                "invalid custom stream name {:?}, names can only contain letters, numbers, `-` and `_`",
+                name,
+            ));
+        }
+
+        let address = address
+            .parse()
+            .map_err(|err| format!("invalid custom stream address {:?}: {}", address, err))?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            address,
+        })
+    }
+}
+
+/// What to do with incoming events when an ingestion buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BufferPolicy {
+    /// Wait for room within the buffer
+    Block,
+    /// Drop the events and count them
+    Drop,
+}
+
+impl FromStr for BufferPolicy {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "block" => Ok(Self::Block),
+            "drop" => Ok(Self::Drop),
+
+            _ => Err(format!(
+                "invalid buffer policy {:?}, only `block` and `drop` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+impl Default for BufferPolicy {
+    fn default() -> Self {
+        Self::Block
+    }
+}
+
+/// The metric used to color operators within the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorBy {
+    /// The operator's total activation time
+    ActivationTime,
+    /// The number of times the operator was activated
+    Invocations,
+    /// The largest size the operator's arrangement reached
+    ArrangementSize,
+    /// The worker's runtime minus the operator's activation time
+    IdleTime,
+}
+
+impl FromStr for ColorBy {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "activation-time" => Ok(Self::ActivationTime),
+            "invocations" => Ok(Self::Invocations),
+            "arrangement-size" => Ok(Self::ArrangementSize),
+            "idle-time" => Ok(Self::IdleTime),
+
+            _ => Err(format!(
+                "invalid coloring metric {:?}, only `activation-time`, `invocations`, \
+                `arrangement-size` and `idle-time` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+impl Display for ColorBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ActivationTime => f.write_str("activation-time"),
+            Self::Invocations => f.write_str("invocations"),
+            Self::ArrangementSize => f.write_str("arrangement-size"),
+            Self::IdleTime => f.write_str("idle-time"),
+        }
+    }
+}
+
+impl Default for ColorBy {
+    fn default() -> Self {
+        Self::ActivationTime
+    }
+}
+
+/// The format that the report is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReportFormat {
+    /// Plain text tables
+    Text,
+    /// Markdown tables, suitable for pasting into issues and pull requests
+    Markdown,
+    /// A json document for consumption by scripts
+    Json,
+    /// A standalone html page
+    Html,
+}
+
+impl ReportFormat {
+    /// The file extension used for reports of this format
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "text" | "txt" => Ok(Self::Text),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "html" => Ok(Self::Html),
+
+            _ => Err(format!(
+                "invalid report format {:?}, only `text`, `markdown`, `json` and `html` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => f.write_str("text"),
+            Self::Markdown => f.write_str("markdown"),
+            Self::Json => f.write_str("json"),
+            Self::Html => f.write_str("html"),
+        }
+    }
+}
+
+impl Default for ReportFormat {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+/// The color theme of the generated graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follow the viewer's `prefers-color-scheme`
+    Auto,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            "auto" => Ok(Self::Auto),
+
+            _ => Err(format!(
+                "invalid theme {:?}, only `light`, `dark` and `auto` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// The kinds of events that can be dropped during ingestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IgnoredEvent {
+    PushProgress,
+    Messages,
+    Schedule,
+    Shutdown,
+    Application,
+    GuardedMessage,
+    GuardedProgress,
+    CommChannels,
+    Input,
+    Park,
+    Text,
+    Batch,
+    Merge,
+    Drop,
+    MergeShortfall,
+}
+
+impl IgnoredEvent {
+    pub const ACCEPTED: &'static [&'static str] = &[
+        "push-progress",
+        "messages",
+        "schedule",
+        "shutdown",
+        "application",
+        "guarded-message",
+        "guarded-progress",
+        "comm-channels",
+        "input",
+        "park",
+        "text",
+        "batch",
+        "merge",
+        "drop",
+        "merge-shortfall",
+    ];
+}
+
+impl FromStr for IgnoredEvent {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "push-progress" => Ok(Self::PushProgress),
+            "messages" => Ok(Self::Messages),
+            "schedule" => Ok(Self::Schedule),
+            "shutdown" => Ok(Self::Shutdown),
+            "application" => Ok(Self::Application),
+            "guarded-message" => Ok(Self::GuardedMessage),
+            "guarded-progress" => Ok(Self::GuardedProgress),
+            "comm-channels" => Ok(Self::CommChannels),
+            "input" => Ok(Self::Input),
+            "park" => Ok(Self::Park),
+            "text" => Ok(Self::Text),
+            "batch" => Ok(Self::Batch),
+            "merge" => Ok(Self::Merge),
+            "drop" => Ok(Self::Drop),
+            "merge-shortfall" => Ok(Self::MergeShortfall),
+
+            "operates" | "channels" => Err(format!(
+                "{:?} events are required to build the dataflow graph and can't be ignored",
+                string,
+            )),
+            _ => Err(format!("invalid event kind {:?}", string)),
+        }
+    }
+}
+
+impl Display for IgnoredEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PushProgress => "push-progress",
+            Self::Messages => "messages",
+            Self::Schedule => "schedule",
+            Self::Shutdown => "shutdown",
+            Self::Application => "application",
+            Self::GuardedMessage => "guarded-message",
+            Self::GuardedProgress => "guarded-progress",
+            Self::CommChannels => "comm-channels",
+            Self::Input => "input",
+            Self::Park => "park",
+            Self::Text => "text",
+            Self::Batch => "batch",
+            Self::Merge => "merge",
+            Self::Drop => "drop",
+            Self::MergeShortfall => "merge-shortfall",
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TerminalColor {
     Auto,
@@ -350,6 +1380,7 @@ parse_gradient! {
 // unsafe impl Send for ThreadedGradient {}
 // unsafe impl Sync for ThreadedGradient {}
 
+/// Where ddshow's own logs are written to
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Output {
     Stdout,
@@ -357,6 +1388,12 @@ pub enum Output {
     Quiet,
 }
 
+impl Default for Output {
+    fn default() -> Self {
+        Self::Stderr
+    }
+}
+
 impl FromStr for Output {
     type Err = String;
 
@@ -372,3 +1409,63 @@ impl FromStr for Output {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use std::{num::NonZeroUsize, path::PathBuf};
+
+    fn limited(mib: usize) -> Args {
+        Args {
+            memory_limit: NonZeroUsize::new(mib * 1024 * 1024),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn memory_limit_scales_down() {
+        let args = limited(64).with_memory_limit_applied();
+        assert_eq!(args.exact_activations, 640);
+        assert_eq!(args.max_data_points, 640);
+        assert_eq!(args.timeline_memory_budget, NonZeroUsize::new(16));
+        assert!(args.compacts_timeline());
+        assert_eq!(args.sample_messages, NonZeroUsize::new(16));
+        assert_eq!(args.ingest_buffer, NonZeroUsize::new(64));
+
+        // Large limits keep the defaults and only bound the ingestion buffers
+        let args = limited(4096).with_memory_limit_applied();
+        assert_eq!(args.exact_activations, 10_000);
+        assert_eq!(args.max_data_points, 10_000);
+        assert_eq!(args.timeline_memory_budget, NonZeroUsize::new(1024));
+        assert_eq!(args.sample_messages, None);
+        assert_eq!(args.ingest_buffer, NonZeroUsize::new(1024));
+    }
+
+    #[test]
+    fn memory_limit_only_tightens() {
+        let args = Args {
+            exact_activations: 100,
+            ingest_buffer: NonZeroUsize::new(8),
+            sample_messages: NonZeroUsize::new(2),
+            ..limited(64)
+        }
+        .with_memory_limit_applied();
+        assert_eq!(args.exact_activations, 100);
+        assert_eq!(args.max_data_points, 640);
+        assert_eq!(args.ingest_buffer, NonZeroUsize::new(8));
+        assert_eq!(args.sample_messages, NonZeroUsize::new(2));
+
+        // Replays aren't buffered, so their buffers are left alone
+        let args = Args {
+            replay_logs: Some(vec![PathBuf::from("logs")]),
+            ..limited(64)
+        }
+        .with_memory_limit_applied();
+        assert_eq!(args.ingest_buffer, None);
+
+        let args = Args::default().with_memory_limit_applied();
+        assert_eq!(args.exact_activations, 10_000);
+        assert_eq!(args.timeline_memory_budget, None);
+        assert!(!args.compacts_timeline());
+    }
+}
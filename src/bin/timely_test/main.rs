@@ -1,3 +1,16 @@
+//! A configurable workload for exercising ddshow
+//!
+//! Alongside a fixed set of hand-written dataflows it can generate any number
+//! of extra dataflows, each a chain of operators whose arrangements have a
+//! portion of their records replaced every epoch. The rate that epochs are
+//! ingested at can be limited to simulate long-running targets
+//!
+//! ```sh
+//! cargo run --bin timely_test -- --workers 4 --dataflows 16 --operators 50 --churn 1000
+//! ```
+
+mod workload;
+
 use differential_dataflow::{
     input::Input,
     operators::{arrange::ArrangeBySelf, Consolidate, Iterate, Threshold},
@@ -10,6 +23,8 @@ use std::{
     num::NonZeroUsize,
     path::PathBuf,
     str::FromStr,
+    thread,
+    time::{Duration, Instant},
 };
 use structopt::StructOpt;
 use timely::{
@@ -22,9 +37,7 @@ use tracing_subscriber::{
     fmt::time::Uptime, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt,
     EnvFilter,
 };
-
-type Time = usize;
-type Diff = isize;
+use workload::{Diff, Time, Workload};
 
 fn main() {
     let args = TestArgs::from_args();
@@ -111,19 +124,36 @@ fn main() {
                 .probe_with(&mut probe);
         });
 
+        let workload = args.workload();
+        let mut generated = workload.build(worker, &mut probe);
+        let started = Instant::now();
+
         for i in 1..=args.iterations.get() {
             if worker.index() == 0 {
                 for elem in 0..args.records.get() {
                     input.insert(elem);
                 }
+
+                workload.ingest(&mut generated, i);
             }
 
             input.advance_to(i);
             input.flush();
+            for input in generated.iter_mut() {
+                input.advance_to(i);
+                input.flush();
+            }
 
             if worker.index() == 0 {
                 println!("ingested epoch {}/{}", i, args.iterations.get());
             }
+
+            if let Some(rate) = args.epochs_per_second {
+                let target = Duration::from_secs_f64(i as f64 / rate.get() as f64);
+                if let Some(remaining) = target.checked_sub(started.elapsed()) {
+                    thread::sleep(remaining);
+                }
+            }
         }
 
         println!(
@@ -163,9 +193,37 @@ struct TestArgs {
 
     #[structopt(long, default_value = "10000")]
     records: NonZeroUsize,
+
+    /// The number of extra dataflows to generate
+    #[structopt(long, default_value = "0")]
+    dataflows: usize,
+
+    /// The number of operators within each generated dataflow, every third
+    /// one is an arrangement
+    #[structopt(long, default_value = "12")]
+    operators: NonZeroUsize,
+
+    /// The number of records replaced within each generated dataflow every
+    /// epoch, which churns their arrangements
+    #[structopt(long, default_value = "0")]
+    churn: usize,
+
+    /// Limits the number of epochs ingested each second, which also limits
+    /// the rate that events are logged at
+    #[structopt(long)]
+    epochs_per_second: Option<NonZeroUsize>,
 }
 
 impl TestArgs {
+    pub fn workload(&self) -> Workload {
+        Workload {
+            dataflows: self.dataflows,
+            operators: self.operators.get(),
+            records: self.records.get(),
+            churn: self.churn,
+        }
+    }
+
     pub fn timely_config(&self) -> (Vec<GenericBuilder>, Box<dyn Any + Send>, WorkerConfig) {
         let (builders, others) = match self.workers.get() {
             0 | 1 => CommunicationConfig::Thread,
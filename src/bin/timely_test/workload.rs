@@ -0,0 +1,91 @@
+//! The generated dataflows that both the `timely_test` binary and the analysis
+//! benchmarks run, each a chain of operators whose arrangements have a portion
+//! of their records replaced every epoch
+
+use differential_dataflow::{
+    input::{Input, InputSession},
+    operators::arrange::ArrangeBySelf,
+    Collection,
+};
+use timely::{communication::Allocate, dataflow::ProbeHandle, worker::Worker};
+
+pub type Time = usize;
+pub type Diff = isize;
+
+/// The shape of the generated dataflows
+#[derive(Debug, Clone, Copy)]
+pub struct Workload {
+    /// The number of dataflows to generate
+    pub dataflows: usize,
+    /// The number of operators within each dataflow, every third one is an arrangement
+    pub operators: usize,
+    /// The number of records that each dataflow starts out with
+    pub records: usize,
+    /// The number of records replaced within each dataflow every epoch
+    pub churn: usize,
+}
+
+impl Workload {
+    /// Builds every generated dataflow on the worker, returning their inputs
+    pub fn build<A>(
+        &self,
+        worker: &mut Worker<A>,
+        probe: &mut ProbeHandle<Time>,
+    ) -> Vec<InputSession<Time, usize, Diff>>
+    where
+        A: Allocate,
+    {
+        (0..self.dataflows)
+            .map(|dataflow| self.build_dataflow(worker, dataflow, probe))
+            .collect()
+    }
+
+    /// Builds a dataflow that's a chain of maps, filters and arrangements
+    fn build_dataflow<A>(
+        &self,
+        worker: &mut Worker<A>,
+        dataflow: usize,
+        probe: &mut ProbeHandle<Time>,
+    ) -> InputSession<Time, usize, Diff>
+    where
+        A: Allocate,
+    {
+        let operators = self.operators;
+
+        worker.dataflow_named(&format!("Generated Dataflow {}", dataflow), |scope| {
+            let (input, mut collection): (_, Collection<_, usize, Diff>) = scope.new_collection();
+
+            for operator in 0..operators {
+                collection = match operator % 3 {
+                    0 => collection.map(move |x| x.wrapping_add(operator)),
+                    1 => collection.filter(|&x| x != usize::MAX),
+                    _ => collection.arrange_by_self().as_collection(|&x, &()| x),
+                };
+            }
+
+            collection.probe_with(probe);
+            input
+        })
+    }
+
+    /// Feeds the records of the given epoch, starting from one, into the inputs
+    /// of the generated dataflows. They start out with every record and then
+    /// replace the oldest `churn` records with new ones each epoch
+    pub fn ingest(&self, inputs: &mut [InputSession<Time, usize, Diff>], epoch: Time) {
+        let churn = self.churn.min(self.records);
+
+        for input in inputs.iter_mut() {
+            if epoch == 1 {
+                for elem in 0..self.records {
+                    input.insert(elem);
+                }
+            } else {
+                let oldest = (epoch - 2) * churn;
+                for elem in oldest..oldest + churn {
+                    input.remove(elem);
+                    input.insert(elem + self.records);
+                }
+            }
+        }
+    }
+}
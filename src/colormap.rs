@@ -1,3 +1,7 @@
+use crate::{
+    args::ColorBy,
+    dataflow::{ArrangementStats, Summation},
+};
 use colorous::{Color as InnerColor, Gradient};
 use std::{
     fmt::{self, Debug, Display},
@@ -68,3 +72,43 @@ pub fn select_color(
         ((duration.as_secs_f64().log2() - lower_bound) / (upper_bound - lower_bound)) * 1.0,
     ))
 }
+
+/// Selects the color of a value within the given bounds on a logarithmic scale
+pub fn select_scaled_color(
+    colormap: &Gradient,
+    value: f64,
+    (upper_bound, lower_bound): (f64, f64),
+) -> Color {
+    let scale = |value: f64| value.max(0.0).ln_1p();
+    let (upper_bound, lower_bound) = (scale(upper_bound), scale(lower_bound));
+
+    let position = if upper_bound > lower_bound {
+        (scale(value) - lower_bound) / (upper_bound - lower_bound)
+    } else {
+        0.0
+    };
+
+    Color::new(colormap.eval_continuous(position.max(0.0).min(1.0)))
+}
+
+/// Gets the value of the metric an operator is colored by, `None` if the
+/// operator doesn't have the metric (e.g. arrangement sizes of operators
+/// without arrangements)
+pub fn color_metric(
+    color_by: ColorBy,
+    stats: &Summation,
+    arrangement: Option<&ArrangementStats>,
+    worker_runtime: Option<Duration>,
+) -> Option<f64> {
+    match color_by {
+        ColorBy::ActivationTime => Some(stats.total.as_nanos() as f64),
+        ColorBy::Invocations => Some(stats.count as f64),
+        ColorBy::ArrangementSize => arrangement.map(|arrangement| arrangement.max_size as f64),
+        ColorBy::IdleTime => worker_runtime.map(|runtime| {
+            runtime
+                .checked_sub(stats.total)
+                .unwrap_or_default()
+                .as_nanos() as f64
+        }),
+    }
+}
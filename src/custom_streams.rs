@@ -0,0 +1,455 @@
+//! Extra, user-defined logging streams whose events are shown as their own
+//! lanes within the timeline
+//!
+//! Each stream listens on its own address and turns the bytes it receives
+//! into [`CustomRecord`]s through a [`CustomDecoder`]. Streams given with
+//! `--custom-stream NAME=ADDR` are decoded as json lines that look like
+//!
+//! ```json
+//! {"time_ns": 1500000, "worker": 0, "label": "flushed batch", "duration_ns": 20000}
+//! ```
+//!
+//! Times should come from the same clock as the target's timely loggers
+//! so that custom events line up with operator activations
+
+use crate::args::Args;
+use anyhow::{Context, Result};
+use ddshow_types::WorkerId;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+#[cfg(feature = "network")]
+use std::{
+    io::{ErrorKind, Read},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+/// The interval that idle listeners and sockets check for shutdown at
+#[cfg(feature = "network")]
+const CUSTOM_STREAM_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A single decoded event from a custom stream
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CustomRecord {
+    /// The time the event occurred at, in nanoseconds
+    pub time_ns: u64,
+    /// The target worker that logged the event, defaults to the index
+    /// of the connection the event arrived on
+    #[serde(default)]
+    pub worker: Option<usize>,
+    pub label: String,
+    /// How long the event lasted for in nanoseconds, instantaneous
+    /// events have no duration
+    #[serde(default)]
+    pub duration_ns: Option<u64>,
+}
+
+/// A custom event that's been attributed to its stream and worker
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct CustomEvent {
+    pub stream: String,
+    pub worker: WorkerId,
+    pub start: Duration,
+    pub duration: Duration,
+    pub label: String,
+}
+
+/// Decodes [`CustomRecord`]s out of the raw bytes of a custom stream
+pub trait CustomDecoder: Send {
+    /// Attempts to decode a single record from the start of `bytes`, returning
+    /// the number of bytes consumed along with the record if there was one
+    ///
+    /// Returning zero consumed bytes means that more input is needed
+    fn decode(&mut self, bytes: &[u8]) -> Result<(usize, Option<CustomRecord>)>;
+}
+
+impl<F> CustomDecoder for F
+where
+    F: FnMut(&[u8]) -> Result<(usize, Option<CustomRecord>)> + Send,
+{
+    fn decode(&mut self, bytes: &[u8]) -> Result<(usize, Option<CustomRecord>)> {
+        (self)(bytes)
+    }
+}
+
+/// Decodes newline-delimited json records, skipping blank lines
+pub fn json_lines(bytes: &[u8]) -> Result<(usize, Option<CustomRecord>)> {
+    let end = match bytes.iter().position(|&byte| byte == b'\n') {
+        Some(end) => end,
+        None => return Ok((0, None)),
+    };
+
+    let line = &bytes[..end];
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok((end + 1, None));
+    }
+
+    let record = serde_json::from_slice(line).with_context(|| {
+        anyhow::anyhow!(
+            "failed to decode custom event {:?}",
+            String::from_utf8_lossy(line),
+        )
+    })?;
+
+    Ok((end + 1, Some(record)))
+}
+
+type DecoderFactory = Arc<dyn Fn() -> Box<dyn CustomDecoder> + Send + Sync>;
+
+/// A custom stream that hasn't started listening yet
+pub struct CustomStream {
+    name: String,
+    address: SocketAddr,
+    /// Creates the decoder for each connection to the stream
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    decoder: DecoderFactory,
+}
+
+impl CustomStream {
+    pub fn new<F, D>(name: impl Into<String>, address: SocketAddr, decoder: F) -> Self
+    where
+        F: Fn() -> D + Send + Sync + 'static,
+        D: CustomDecoder + 'static,
+    {
+        Self {
+            name: name.into(),
+            address,
+            decoder: Arc::new(move || Box::new(decoder()) as Box<dyn CustomDecoder>),
+        }
+    }
+}
+
+impl Debug for CustomStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomStream")
+            .field("name", &self.name)
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+/// All registered custom streams
+#[derive(Debug, Default)]
+pub struct CustomStreams {
+    streams: Vec<CustomStream>,
+}
+
+impl CustomStreams {
+    /// Registers every stream given with `--custom-stream`, all of which
+    /// are decoded as json lines
+    pub fn from_args(args: &Args) -> Self {
+        let mut streams = Self::default();
+        for stream in args.custom_streams.iter() {
+            streams.register(CustomStream::new(
+                stream.name.clone(),
+                stream.address,
+                || json_lines,
+            ));
+        }
+
+        streams
+    }
+
+    pub fn register(&mut self, stream: CustomStream) -> &mut Self {
+        self.streams.push(stream);
+        self
+    }
+
+    /// Binds every stream's listener and starts accepting connections on
+    /// background threads
+    ///
+    /// This must be called before the target is spawned so that it has
+    /// something to connect to
+    #[cfg(feature = "network")]
+    pub fn listen(self) -> Result<CustomListeners> {
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let mut listeners = Vec::with_capacity(self.streams.len());
+        for stream in self.streams {
+            let listener = TcpListener::bind(stream.address).with_context(|| {
+                anyhow::anyhow!(
+                    "failed to bind custom stream `{}` to {}",
+                    stream.name,
+                    stream.address,
+                )
+            })?;
+            listener
+                .set_nonblocking(true)
+                .context("failed to set listener to non-blocking mode")?;
+
+            tracing::info!(
+                name = %stream.name,
+                address = %stream.address,
+                "listening for custom stream",
+            );
+
+            let moved_shutdown = shutdown.clone();
+            let handle = thread::Builder::new()
+                .name(format!("custom-stream-{}", stream.name))
+                .spawn(move || accept_connections(stream, listener, &moved_shutdown))
+                .context("failed to spawn custom stream thread")?;
+
+            listeners.push(handle);
+        }
+
+        Ok(CustomListeners {
+            listeners,
+            shutdown,
+        })
+    }
+
+    /// Without network support there's nothing to listen on, so custom streams can't be used
+    #[cfg(not(feature = "network"))]
+    pub fn listen(self) -> Result<CustomListeners> {
+        if !self.streams.is_empty() {
+            anyhow::bail!(
+                "ddshow was built without network support, rebuild it with the `network` \
+                feature to use custom streams",
+            );
+        }
+
+        Ok(CustomListeners {
+            listeners: Vec::new(),
+            shutdown: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
+/// The listeners of every custom stream
+#[derive(Debug)]
+pub struct CustomListeners {
+    listeners: Vec<JoinHandle<Vec<CustomEvent>>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl CustomListeners {
+    /// Stops listening, reads whatever's left within each connection and
+    /// returns every event that was received sorted by stream and start time
+    pub fn finish(self) -> Vec<CustomEvent> {
+        self.shutdown.store(true, Ordering::Release);
+
+        let mut events = Vec::new();
+        for listener in self.listeners {
+            match listener.join() {
+                Ok(stream_events) => events.extend(stream_events),
+                Err(_) => tracing::error!("a custom stream thread panicked"),
+            }
+        }
+
+        events.sort_unstable_by(|left, right| {
+            (&left.stream, left.start, left.worker).cmp(&(&right.stream, right.start, right.worker))
+        });
+        events
+    }
+}
+
+#[cfg(feature = "network")]
+fn accept_connections(
+    stream: CustomStream,
+    listener: TcpListener,
+    shutdown: &Arc<AtomicBool>,
+) -> Vec<CustomEvent> {
+    let mut connections = Vec::new();
+
+    while !shutdown.load(Ordering::Acquire) {
+        match listener.accept() {
+            Ok((socket, peer)) => {
+                let connection = connections.len();
+                tracing::debug!(
+                    name = %stream.name,
+                    peer = %peer,
+                    "accepted custom stream connection {}",
+                    connection,
+                );
+
+                let (name, decoder, shutdown) =
+                    (stream.name.clone(), (stream.decoder)(), shutdown.clone());
+                let handle = thread::Builder::new()
+                    .name(format!("custom-stream-{}-{}", stream.name, connection))
+                    .spawn(move || read_connection(name, connection, socket, decoder, &shutdown));
+
+                match handle {
+                    Ok(handle) => connections.push(handle),
+                    Err(err) => {
+                        tracing::error!(
+                            name = %stream.name,
+                            "failed to spawn custom stream reader: {}",
+                            err,
+                        );
+                    }
+                }
+            }
+
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(CUSTOM_STREAM_POLL_INTERVAL);
+            }
+
+            Err(err) => {
+                tracing::error!(
+                    name = %stream.name,
+                    "failed to accept custom stream connection: {}",
+                    err,
+                );
+
+                break;
+            }
+        }
+    }
+
+    let mut events = Vec::new();
+    for connection in connections {
+        match connection.join() {
+            Ok(connection_events) => events.extend(connection_events),
+            Err(_) => tracing::error!(name = %stream.name, "a custom stream reader panicked"),
+        }
+    }
+
+    events
+}
+
+#[cfg(feature = "network")]
+fn read_connection(
+    name: String,
+    connection: usize,
+    mut socket: TcpStream,
+    mut decoder: Box<dyn CustomDecoder>,
+    shutdown: &AtomicBool,
+) -> Vec<CustomEvent> {
+    let (mut events, mut buffer, mut chunk) = (Vec::new(), Vec::new(), [0; 4096]);
+
+    if let Err(err) = socket
+        .set_nonblocking(false)
+        .and_then(|()| socket.set_read_timeout(Some(CUSTOM_STREAM_POLL_INTERVAL)))
+    {
+        tracing::error!(name = %name, "failed to configure custom stream socket: {}", err);
+        return events;
+    }
+
+    loop {
+        match socket.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+
+            // Once we've been told to shut down an idle socket means that
+            // everything the target sent has been read
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if shutdown.load(Ordering::Acquire) {
+                    break;
+                }
+
+                continue;
+            }
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+
+            Err(err) => {
+                tracing::error!(name = %name, "failed to read from custom stream: {}", err);
+                break;
+            }
+        }
+
+        if let Err(err) =
+            decode_buffered(&name, connection, &mut buffer, &mut *decoder, &mut events)
+        {
+            tracing::error!(name = %name, "stopped reading custom stream: {:?}", err);
+            break;
+        }
+    }
+
+    if !buffer.is_empty() {
+        tracing::warn!(
+            name = %name,
+            "custom stream closed with {} undecoded bytes",
+            buffer.len(),
+        );
+    }
+
+    events
+}
+
+/// Decodes every complete record within `buffer`, leaving any trailing
+/// partial record within it
+#[cfg(feature = "network")]
+fn decode_buffered(
+    name: &str,
+    connection: usize,
+    buffer: &mut Vec<u8>,
+    decoder: &mut dyn CustomDecoder,
+    events: &mut Vec<CustomEvent>,
+) -> Result<()> {
+    let mut consumed = 0;
+
+    loop {
+        let (read, record) = decoder.decode(&buffer[consumed..])?;
+        if read == 0 {
+            break;
+        }
+        consumed += read;
+
+        if let Some(record) = record {
+            events.push(CustomEvent {
+                stream: name.to_owned(),
+                worker: WorkerId::new(record.worker.unwrap_or(connection)),
+                start: Duration::from_nanos(record.time_ns),
+                duration: Duration::from_nanos(record.duration_ns.unwrap_or(0)),
+                label: record.label,
+            });
+        }
+
+        if consumed == buffer.len() {
+            break;
+        }
+    }
+
+    buffer.drain(..consumed);
+    Ok(())
+}
+
+#[cfg(all(test, feature = "network"))]
+mod tests {
+    use super::{decode_buffered, json_lines, CustomEvent};
+    use ddshow_types::WorkerId;
+    use std::time::Duration;
+
+    #[test]
+    fn json_lines_keep_partial_records() {
+        let mut buffer = b"{\"time_ns\": 10, \"label\": \"flush\"}\n\n\
+            {\"time_ns\": 20, \"worker\": 3, \"label\": \"spill\", \"duration_ns\": 5}\n\
+            {\"time_ns\": 30"
+            .to_vec();
+        let mut events = Vec::new();
+
+        decode_buffered("app", 1, &mut buffer, &mut json_lines, &mut events).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                CustomEvent {
+                    stream: "app".to_owned(),
+                    worker: WorkerId::new(1),
+                    start: Duration::from_nanos(10),
+                    duration: Duration::from_nanos(0),
+                    label: "flush".to_owned(),
+                },
+                CustomEvent {
+                    stream: "app".to_owned(),
+                    worker: WorkerId::new(3),
+                    start: Duration::from_nanos(20),
+                    duration: Duration::from_nanos(5),
+                    label: "spill".to_owned(),
+                },
+            ],
+        );
+        assert_eq!(buffer, b"{\"time_ns\": 30");
+
+        let mut garbage = b"not json\n".to_vec();
+        assert!(decode_buffered("app", 0, &mut garbage, &mut json_lines, &mut events).is_err());
+    }
+}
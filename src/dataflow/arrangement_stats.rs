@@ -0,0 +1,286 @@
+//! Detailed statistics about each arrangement
+//!
+//! Built from differential's batch, merge, drop and trace share events. The size
+//! of an arrangement is tracked by adding the length of every batch it receives
+//! and replacing the inputs of each merge with its completed size, which gives the
+//! number of updates held within the arrangement after each event
+//!
+//! How well a trace compacts comes from comparing the sizes that go into its merges
+//! with the sizes that come out of them, along with the number of times each batched
+//! update ends up being merged
+//!
+//! Merge shortfalls are logged when a merge couldn't be finished with the fuel it
+//! was given and had to be forced to completion, the time between a shortfall and
+//! the following completed merge of the same scale is how long the trace was behind
+
+use crate::{
+    dataflow::{
+        operators::FilterMapTimed,
+        utils::{Diff, DifferentialLogBundle, OpKey, Time, XXHasher},
+        ArrangedVal, OperatorAddr,
+    },
+    ui::{AggregatedStats, ArrangementStats, CompactionStats, Lifespan, ShortfallStats},
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::differential_logging::DifferentialEvent;
+use differential_dataflow::{
+    operators::{arrange::ArrangeByKey, JoinCore, Reduce},
+    AsCollection, Collection,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, iter, time::Duration};
+use timely::dataflow::{Scope, Stream};
+
+/// An event that changed an arrangement
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+enum ArrangementEvent {
+    Batch { length: usize },
+    MergeStarted { scale: usize, inputs: usize },
+    MergeCompleted { scale: usize, length: usize },
+    Dropped,
+    Shared { diff: isize },
+    Shortfall { scale: usize, shortfall: usize },
+}
+
+pub fn arrangement_details<S>(
+    differential_stream: &Stream<S, DifferentialLogBundle>,
+    operator_addrs: &ArrangedVal<S, OpKey, OperatorAddr>,
+    max_data_points: usize,
+) -> Collection<S, (OpKey, ArrangementStats), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    differential_stream
+        .filter_map_ref_timed_named(
+            "Arrangement Events",
+            |&timestamp, &(event_time, worker, ref event)| {
+                let (operator, event) = match *event {
+                    DifferentialEvent::Batch(ref batch) => (
+                        batch.operator,
+                        ArrangementEvent::Batch {
+                            length: batch.length,
+                        },
+                    ),
+                    DifferentialEvent::Merge(ref merge) => (
+                        merge.operator,
+                        match merge.complete {
+                            None => ArrangementEvent::MergeStarted {
+                                scale: merge.scale,
+                                inputs: merge.length1 + merge.length2,
+                            },
+                            Some(length) => ArrangementEvent::MergeCompleted {
+                                scale: merge.scale,
+                                length,
+                            },
+                        },
+                    ),
+                    DifferentialEvent::Drop(ref drop) => (drop.operator, ArrangementEvent::Dropped),
+                    DifferentialEvent::TraceShare(ref share) => (
+                        share.operator,
+                        ArrangementEvent::Shared { diff: share.diff },
+                    ),
+                    DifferentialEvent::MergeShortfall(ref shortfall) => (
+                        shortfall.operator,
+                        ArrangementEvent::Shortfall {
+                            scale: shortfall.scale,
+                            shortfall: shortfall.shortfall,
+                        },
+                    ),
+                };
+
+                Some((((worker, operator), (event_time, event)), timestamp, 1))
+            },
+        )
+        .as_collection()
+        .reduce_named(
+            "Reduce: Arrangement Details",
+            move |_operator, events, output| {
+                output.push((tally_events(events, max_data_points), 1));
+            },
+        )
+        .arrange_by_key_named("ArrangeByKey: Arrangement Details")
+        .join_core(operator_addrs, |&operator, stats, addr| {
+            let stats = ArrangementStats {
+                operator_addr: addr.clone(),
+                ..stats.clone()
+            };
+
+            iter::once((operator, stats))
+        })
+}
+
+/// Replays the time-ordered events of an arrangement, keeping at most
+/// `max_data_points` of each stat's data points
+fn tally_events(
+    events: &[(&(Duration, ArrangementEvent), Diff)],
+    max_data_points: usize,
+) -> ArrangementStats {
+    let (mut size, mut sizes, mut batches, mut merges) = (0, Vec::new(), Vec::new(), Vec::new());
+    let mut size_series = Vec::new();
+    let (mut shares, mut peak_shares) = (0, 0);
+    let mut started_merges: HashMap<usize, (Duration, usize), XXHasher> = HashMap::default();
+    let mut compaction = CompactionStats::default();
+    let mut shortfalls = ShortfallStats::default();
+    let mut behind_since: HashMap<usize, Duration, XXHasher> = HashMap::default();
+    let mut lifespan: Option<Lifespan> = None;
+    let mut dropped = None;
+
+    for &(&(time, event), diff) in events {
+        for _ in 0..diff.max(0) {
+            lifespan
+                .get_or_insert_with(|| Lifespan::new(time, time))
+                .death = time;
+
+            match event {
+                ArrangementEvent::Batch { length } => {
+                    size += length;
+                    sizes.push(size);
+                    size_series.push((time, size));
+                    batches.push(length);
+                    compaction.batched_updates += length;
+                }
+
+                ArrangementEvent::MergeStarted { scale, inputs } => {
+                    started_merges.insert(scale, (time, inputs));
+                }
+
+                // Merges that started before logging began are left out
+                ArrangementEvent::MergeCompleted { scale, length } => {
+                    if let Some(since) = behind_since.remove(&scale) {
+                        shortfalls.behind += time.checked_sub(since).unwrap_or_default();
+                    }
+
+                    if let Some((started, inputs)) = started_merges.remove(&scale) {
+                        size = (size + length).saturating_sub(inputs);
+                        sizes.push(size);
+                        size_series.push((time, size));
+                        merges.push(time.checked_sub(started).unwrap_or_default());
+                        compaction.merge_inputs += inputs;
+                        compaction.merge_outputs += length;
+                    }
+                }
+
+                ArrangementEvent::Dropped => {
+                    dropped.get_or_insert(time);
+                }
+
+                ArrangementEvent::Shared { diff } => {
+                    shares += diff;
+                    peak_shares = peak_shares.max(shares);
+                }
+
+                ArrangementEvent::Shortfall { scale, shortfall } => {
+                    shortfalls.shortfalls += 1;
+                    shortfalls.total_shortfall += shortfall;
+                    shortfalls.max_shortfall = shortfalls.max_shortfall.max(shortfall);
+                    behind_since.entry(scale).or_insert(time);
+                }
+            }
+        }
+    }
+
+    compaction.unfinished_merges = started_merges.len();
+
+    // Traces that never caught up were behind until their last event
+    if let Some(death) = lifespan.map(|lifespan| lifespan.death) {
+        for since in behind_since.values() {
+            shortfalls.behind += death.checked_sub(*since).unwrap_or_default();
+        }
+    }
+
+    ArrangementStats {
+        operator_addr: OperatorAddr::default(),
+        size_stats: AggregatedStats::from_sizes(sizes, max_data_points),
+        size_series,
+        merge_stats: AggregatedStats::from_durations(merges, max_data_points),
+        batch_stats: AggregatedStats::from_sizes(batches, max_data_points),
+        trace_shares: peak_shares as usize,
+        lifespan: lifespan.unwrap_or_default(),
+        compaction,
+        shortfalls,
+        dropped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tally_events, ArrangementEvent};
+    use crate::ui::Lifespan;
+    use std::time::Duration;
+
+    #[test]
+    fn events_are_tallied() {
+        let ms = Duration::from_millis;
+        let events = vec![
+            (ms(1), ArrangementEvent::Shared { diff: 1 }),
+            (ms(2), ArrangementEvent::Batch { length: 10 }),
+            (ms(3), ArrangementEvent::Batch { length: 30 }),
+            (ms(4), ArrangementEvent::Shared { diff: 1 }),
+            (
+                ms(4),
+                ArrangementEvent::MergeStarted {
+                    scale: 5,
+                    inputs: 40,
+                },
+            ),
+            (
+                ms(7),
+                ArrangementEvent::MergeCompleted {
+                    scale: 5,
+                    length: 25,
+                },
+            ),
+            (
+                ms(8),
+                ArrangementEvent::MergeStarted {
+                    scale: 6,
+                    inputs: 25,
+                },
+            ),
+            (ms(8), ArrangementEvent::Shared { diff: -2 }),
+            (
+                ms(8),
+                ArrangementEvent::Shortfall {
+                    scale: 6,
+                    shortfall: 7,
+                },
+            ),
+            (ms(9), ArrangementEvent::Dropped),
+        ];
+        let events: Vec<_> = events.iter().map(|event| (event, 1)).collect();
+
+        let stats = tally_events(&events, usize::MAX);
+        assert_eq!(stats.size_stats.data_points, vec![10, 40, 25]);
+        assert_eq!((stats.size_stats.max, stats.size_stats.min), (40, 10));
+        assert_eq!(
+            stats.size_series,
+            vec![(ms(2), 10), (ms(3), 40), (ms(7), 25)],
+        );
+        assert_eq!(stats.batch_stats.total, 2);
+        assert_eq!(stats.batch_stats.average, 20);
+        assert_eq!(stats.merge_stats.data_points, vec![ms(3)]);
+        assert!((stats.merge_stats.sum().as_secs_f64() - ms(3).as_secs_f64()).abs() < 1e-9);
+        assert_eq!(stats.trace_shares, 2);
+        assert_eq!(stats.lifespan, Lifespan::new(ms(1), ms(9)));
+        assert_eq!(stats.dropped, Some(ms(9)));
+
+        assert_eq!(stats.compaction.batched_updates, 40);
+        assert_eq!(
+            (
+                stats.compaction.merge_inputs,
+                stats.compaction.merge_outputs
+            ),
+            (40, 25),
+        );
+        assert_eq!(stats.compaction.unfinished_merges, 1);
+        assert!((stats.compaction.compaction_ratio() - 0.625).abs() < f64::EPSILON);
+        assert!((stats.compaction.merge_amplification() - 1.0).abs() < f64::EPSILON);
+
+        assert_eq!(stats.shortfalls.shortfalls, 1);
+        assert_eq!(stats.shortfalls.max_shortfall, 7);
+        assert_eq!(stats.shortfalls.behind, ms(1));
+        assert!(!stats.shortfalls.falls_behind_repeatedly());
+    }
+}
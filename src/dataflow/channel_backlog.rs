@@ -0,0 +1,97 @@
+//! Estimates how many records are in flight over each channel
+//!
+//! Every batch of records that's sent over a channel adds to its backlog until
+//! the matching receive takes it back off, so the running sum of sent minus
+//! received records is the number of records that are waiting to be received.
+//! Sends and receives are logged by different workers and so are only as
+//! accurate as the workers' clocks are in sync
+
+use crate::{
+    dataflow::{
+        operators::FilterMapTimed,
+        utils::{Diff, Time, TimelyLogBundle},
+    },
+    ui::ChannelBacklog,
+};
+use ddshow_types::{timely_logging::TimelyEvent, ChannelId};
+use differential_dataflow::{operators::Reduce, AsCollection, Collection};
+use std::time::Duration;
+use timely::dataflow::{Scope, Stream};
+
+pub fn channel_backlogs<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+) -> Collection<S, (ChannelId, ChannelBacklog), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    timely_stream
+        .filter_map_ref_timed_named(
+            "Channel Sends & Receives",
+            |&timestamp, &(time, _, ref event)| match event {
+                TimelyEvent::Messages(messages) if messages.length != 0 => {
+                    let length = messages.length as isize;
+                    let change = if messages.is_send { length } else { -length };
+
+                    Some(((messages.channel, (time, change)), timestamp, 1))
+                }
+                _ => None,
+            },
+        )
+        .as_collection()
+        .reduce_named("Reduce: Channel Backlog", |_channel, changes, output| {
+            output.push((tally_backlog(changes), 1));
+        })
+}
+
+/// Walks through the time-ordered changes to a channel's backlog
+fn tally_backlog(changes: &[(&(Duration, isize), Diff)]) -> ChannelBacklog {
+    let mut backlog = ChannelBacklog::default();
+    let (mut in_flight, mut backlogged_since) = (0isize, None);
+
+    for &(&(time, change), count) in changes {
+        in_flight += change * count;
+        backlog.peak_records = backlog.peak_records.max(in_flight.max(0) as usize);
+
+        if in_flight > 0 {
+            backlogged_since.get_or_insert(time);
+        } else if let Some(since) = backlogged_since.take() {
+            backlog.backlogged += time.checked_sub(since).unwrap_or_default();
+        }
+    }
+
+    if let (Some(&(&(first, _), _)), Some(&(&(last, _), _))) = (changes.first(), changes.last()) {
+        backlog.span = last.checked_sub(first).unwrap_or_default();
+
+        // Records that were never received are backlogged until the end of the channel's events
+        if let Some(since) = backlogged_since {
+            backlog.backlogged += last.checked_sub(since).unwrap_or_default();
+        }
+    }
+
+    backlog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tally_backlog;
+    use std::time::Duration;
+
+    #[test]
+    fn backlog_follows_sends_and_receives() {
+        let ms = Duration::from_millis;
+        let changes = vec![
+            (ms(0), 10),
+            (ms(1), 5),
+            (ms(4), -15),
+            (ms(10), 3),
+            (ms(11), -3),
+        ];
+        let changes: Vec<_> = changes.iter().map(|change| (change, 1)).collect();
+
+        let backlog = tally_backlog(&changes);
+        assert_eq!(backlog.peak_records, 15);
+        assert_eq!(backlog.backlogged, ms(5));
+        assert_eq!(backlog.span, ms(11));
+        assert!(!backlog.is_sustained());
+    }
+}
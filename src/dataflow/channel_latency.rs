@@ -0,0 +1,153 @@
+//! Measures how long messages spend within each channel
+//!
+//! Every message is logged once when it's sent and again when it's received,
+//! both times tagged with the channel, the source and target workers and the
+//! message's sequence number, so the two events can be paired up to find the
+//! time between the message being enqueued and dequeued. Messages sent between
+//! workers are only as accurate as the workers' clocks are in sync
+
+use crate::{
+    dataflow::{
+        operators::FilterMapTimed,
+        utils::{Diff, Time, TimelyLogBundle},
+    },
+    ui::ChannelLatency,
+};
+use ddshow_types::{timely_logging::TimelyEvent, ChannelId};
+use differential_dataflow::{operators::Reduce, AsCollection, Collection};
+use std::time::Duration;
+use timely::dataflow::{Scope, Stream};
+
+pub fn channel_latencies<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+) -> Collection<S, (ChannelId, ChannelLatency), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    timely_stream
+        .filter_map_ref_timed_named(
+            "Channel Message Events",
+            |&timestamp, &(time, _, ref event)| match event {
+                TimelyEvent::Messages(messages) => Some((
+                    (
+                        (
+                            messages.channel,
+                            (messages.source, messages.target, messages.seq_no),
+                        ),
+                        (messages.is_send, time),
+                    ),
+                    timestamp,
+                    1,
+                )),
+                _ => None,
+            },
+        )
+        .as_collection()
+        .reduce_named("Reduce: Pair Messages", |_message, events, output| {
+            if let Some(latency) = pair_message(events) {
+                output.push((latency, 1));
+            }
+        })
+        .map(|((channel, _message), latency)| (channel, latency))
+        .reduce_named(
+            "Reduce: Channel Latencies",
+            |_channel, latencies, output| {
+                output.push((tally_latencies(latencies), 1));
+            },
+        )
+}
+
+/// Finds the time between a message's send and receive, messages that weren't
+/// both sent and received within the logs are skipped
+fn pair_message(events: &[(&(bool, Duration), Diff)]) -> Option<Duration> {
+    let (mut sent, mut received) = (None::<Duration>, None::<Duration>);
+    for &(&(is_send, time), diff) in events {
+        if diff <= 0 {
+            continue;
+        }
+
+        if is_send {
+            sent = Some(sent.map_or(time, |sent| sent.min(time)));
+        } else {
+            received = Some(received.map_or(time, |received| received.max(time)));
+        }
+    }
+
+    Some(received?.checked_sub(sent?).unwrap_or_default())
+}
+
+/// Summarizes the ordered latencies of a channel's messages
+fn tally_latencies(latencies: &[(&Duration, Diff)]) -> ChannelLatency {
+    let messages = latencies
+        .iter()
+        .map(|&(_, diff)| diff.max(0) as usize)
+        .sum::<usize>();
+
+    // Latencies arrive in ascending order so the percentiles can be read off
+    // of the running total of messages
+    let percentile = |fraction: f64| {
+        let rank = ((messages as f64 * fraction).ceil() as usize).max(1);
+
+        let mut seen = 0;
+        for &(&latency, diff) in latencies {
+            seen += diff.max(0) as usize;
+            if seen >= rank {
+                return latency;
+            }
+        }
+
+        Duration::default()
+    };
+
+    ChannelLatency {
+        messages,
+        min: latencies
+            .first()
+            .map(|&(&latency, _)| latency)
+            .unwrap_or_default(),
+        max: latencies
+            .last()
+            .map(|&(&latency, _)| latency)
+            .unwrap_or_default(),
+        p50: percentile(0.5),
+        p90: percentile(0.9),
+        p99: percentile(0.99),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pair_message, tally_latencies};
+    use std::time::Duration;
+
+    #[test]
+    fn messages_are_paired_by_sequence() {
+        let ms = Duration::from_millis;
+
+        let events = vec![(true, ms(2)), (false, ms(7))];
+        let events: Vec<_> = events.iter().map(|event| (event, 1)).collect();
+        assert_eq!(pair_message(&events), Some(ms(5)));
+
+        // Messages that were never received have no latency
+        let events = vec![(true, ms(2))];
+        let events: Vec<_> = events.iter().map(|event| (event, 1)).collect();
+        assert_eq!(pair_message(&events), None);
+    }
+
+    #[test]
+    fn latencies_are_summarized() {
+        let ms = Duration::from_millis;
+        let latencies = vec![(ms(1), 5), (ms(2), 4), (ms(40), 1)];
+        let latencies: Vec<_> = latencies
+            .iter()
+            .map(|(latency, count)| (latency, *count))
+            .collect();
+
+        let latency = tally_latencies(&latencies);
+        assert_eq!(latency.messages, 10);
+        assert_eq!((latency.min, latency.max), (ms(1), ms(40)));
+        assert_eq!(latency.p50, ms(1));
+        assert_eq!(latency.p90, ms(2));
+        assert_eq!(latency.p99, ms(40));
+    }
+}
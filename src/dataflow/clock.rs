@@ -0,0 +1,253 @@
+//! Sources of time for ingested events
+//!
+//! Lifespans, runtimes and utilizations are all derived from the timestamps
+//! attached to events, so ingestion stamps every event through a [`TimeSource`]
+//! instead of reading those timestamps directly. Real captures use [`EventTime`]
+//! while tests can drive a synthetic clock to get deterministic results
+
+use crate::dataflow::operators::{EventBatch, MapInPlace};
+#[cfg(test)]
+use crate::dataflow::utils::XXHasher;
+use ddshow_types::WorkerId;
+#[cfg(test)]
+use std::collections::HashMap;
+use std::time::Duration;
+use timely::{
+    dataflow::{Scope, Stream},
+    Data,
+};
+
+/// A source of timestamps for ingested events
+pub trait TimeSource: 'static {
+    /// Returns the time to record for an event that `worker` logged at `recorded`
+    fn stamp(&mut self, worker: WorkerId, recorded: Duration) -> Duration;
+}
+
+/// Trusts the timestamps that were recorded alongside each event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventTime;
+
+impl TimeSource for EventTime {
+    #[inline]
+    fn stamp(&mut self, _worker: WorkerId, recorded: Duration) -> Duration {
+        recorded
+    }
+}
+
+pub trait StampEvents<C> {
+    /// Re-stamps every event within the stream with the time given by `clock`
+    fn stamp_events(&self, clock: C) -> Self;
+}
+
+impl<S, E, C> StampEvents<C> for Stream<S, (Duration, WorkerId, E)>
+where
+    S: Scope,
+    E: Data,
+    C: TimeSource,
+{
+    fn stamp_events(&self, mut clock: C) -> Self {
+        self.map_in_place_named("Stamp Event Times", move |(time, worker, _)| {
+            *time = clock.stamp(*worker, *time);
+        })
+    }
+}
+
+impl<S, E, C> StampEvents<C> for Stream<S, EventBatch<E>>
+where
+    S: Scope,
+    E: Data,
+    C: TimeSource,
+{
+    fn stamp_events(&self, mut clock: C) -> Self {
+        self.map_in_place_named("Stamp Event Times", move |batch| {
+            for (time, &worker) in batch.times.iter_mut().zip(batch.workers.iter()) {
+                *time = clock.stamp(worker, *time);
+            }
+        })
+    }
+}
+
+/// A clock that ignores recorded timestamps, instead advancing each worker's
+/// time by a fixed tick for every event it logs
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct SyntheticClock {
+    start: Duration,
+    tick: Duration,
+    ticks: HashMap<WorkerId, u32, XXHasher>,
+}
+
+#[cfg(test)]
+impl SyntheticClock {
+    pub(crate) fn new(start: Duration, tick: Duration) -> Self {
+        Self {
+            start,
+            tick,
+            ticks: HashMap::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl TimeSource for SyntheticClock {
+    fn stamp(&mut self, worker: WorkerId, _recorded: Duration) -> Duration {
+        let ticks = self.ticks.entry(worker).or_insert(0);
+        let time = self.start + self.tick * *ticks;
+        *ticks += 1;
+
+        time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StampEvents, SyntheticClock};
+    use crate::{
+        dataflow::{
+            program_stats, timely_source,
+            utils::{Diff, TimelyLogBundle},
+            Channel,
+        },
+        ui::Lifespan,
+    };
+    use ddshow_types::{
+        timely_logging::{OperatesEvent, ScheduleEvent, ShutdownEvent, StartStop, TimelyEvent},
+        OperatorAddr, OperatorId, WorkerId,
+    };
+    use differential_dataflow::{operators::arrange::ArrangeBySelf, AsCollection};
+    use std::{
+        sync::{mpsc, Arc, Mutex},
+        time::Duration,
+    };
+    use timely::dataflow::operators::{capture::Extract, generic::operator, Capture, Input, Probe};
+
+    type Captured<D> = Vec<(Duration, Vec<(D, Duration, Diff)>)>;
+
+    fn extract<D>(captured: Captured<D>) -> Vec<(D, Diff)> {
+        captured
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .map(|(data, _, diff)| (data, diff))
+            .collect()
+    }
+
+    #[test]
+    fn synthetic_lifespans_and_activations() {
+        let (lifespans_send, lifespans_recv) = mpsc::channel();
+        let (activations_send, activations_recv) = mpsc::channel();
+        let senders = Arc::new(Mutex::new(Some((lifespans_send, activations_send))));
+
+        timely::execute_directly(move |worker| {
+            let (mut input, probe) = worker.dataflow(|scope| {
+                let (input, stream) = scope.new_input::<TimelyLogBundle>();
+                let stream = stream.stamp_events(SyntheticClock::new(
+                    Duration::from_secs(10),
+                    Duration::from_secs(1),
+                ));
+
+                let collections = timely_source::extract_timely_info(scope, &stream, true);
+                let (lifespans_send, activations_send) = senders.lock().unwrap().take().unwrap();
+                collections.lifespans.inner.capture_into(lifespans_send);
+                collections.activations.inner.capture_into(activations_send);
+
+                (input, stream.probe())
+            });
+
+            let (worker_id, operator) = (WorkerId::new(0), OperatorId::new(1));
+            let events = vec![
+                TimelyEvent::Operates(OperatesEvent::new(
+                    operator,
+                    OperatorAddr::from(vec![0, 1]),
+                    "Map".to_owned(),
+                )),
+                TimelyEvent::Schedule(ScheduleEvent {
+                    id: operator,
+                    start_stop: StartStop::start(),
+                }),
+                TimelyEvent::Schedule(ScheduleEvent {
+                    id: operator,
+                    start_stop: StartStop::stop(),
+                }),
+                TimelyEvent::Shutdown(ShutdownEvent { id: operator }),
+            ];
+
+            // The recorded times are all garbage, only the synthetic clock matters
+            for event in events {
+                input.send((Duration::from_secs(0), worker_id, event));
+            }
+
+            input.advance_to(Duration::from_secs(1));
+            worker.step_or_park_while(None, || probe.less_than(input.time()));
+        });
+
+        let key = (WorkerId::new(0), OperatorId::new(1));
+        assert_eq!(
+            extract(lifespans_recv.extract()),
+            vec![(
+                (
+                    key,
+                    Lifespan::new(Duration::from_secs(10), Duration::from_secs(13)),
+                ),
+                1,
+            )],
+        );
+        assert_eq!(
+            extract(activations_recv.extract()),
+            vec![((key, (Duration::from_secs(11), Duration::from_secs(1))), 1)],
+        );
+    }
+
+    #[test]
+    fn synthetic_total_runtime() {
+        let (send, recv) = mpsc::channel();
+        let send = Arc::new(Mutex::new(Some(send)));
+
+        timely::execute_directly(move |worker| {
+            let (mut input, probe) = worker.dataflow(|scope| {
+                let (input, stream) = scope.new_input::<TimelyLogBundle>();
+                let stream = stream.stamp_events(SyntheticClock::new(
+                    Duration::from_secs(5),
+                    Duration::from_secs(2),
+                ));
+
+                let channels =
+                    operator::empty::<_, (Channel, Duration, Diff)>(scope).as_collection();
+                let addrs = operator::empty::<_, (OperatorAddr, Duration, Diff)>(scope)
+                    .as_collection()
+                    .arrange_by_self();
+
+                let stats = program_stats::aggregate_program_stats(
+                    &stream, None, &channels, &addrs, &addrs,
+                );
+                stats
+                    .total_runtime
+                    .inner
+                    .capture_into(send.lock().unwrap().take().unwrap());
+
+                (input, stream.probe())
+            });
+
+            for _ in 0..3 {
+                input.send((
+                    Duration::from_secs(0),
+                    WorkerId::new(0),
+                    TimelyEvent::Text(String::new()),
+                ));
+            }
+
+            input.advance_to(Duration::from_secs(1));
+            worker.step_or_park_while(None, || probe.less_than(input.time()));
+        });
+
+        assert_eq!(
+            extract(recv.extract()),
+            vec![(
+                (
+                    WorkerId::new(0),
+                    (Duration::from_secs(5), Duration::from_secs(9)),
+                ),
+                1,
+            )],
+        );
+    }
+}
@@ -19,7 +19,21 @@ pub(crate) const DEFAULT_EXTRACTOR_CAPACITY: usize = 1024;
 /// The margin to decide whether or not to fuse adjacent events by
 pub(crate) const EVENT_NS_MARGIN: u64 = 500_000;
 
+/// The timeline merge gap and minor event threshold used with `--memory-limit`,
+/// in microseconds
+pub(crate) const MEMORY_LIMIT_TIMELINE_MICROS: u64 = 10;
+
+/// The size of the buffers that large outputs like dumps and the graph's data
+/// files are written through, which saves a lot of tiny writes
+pub(crate) const OUTPUT_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// The default number of event batches buffered for each log stream
+/// by its ingestion thread
+// Safety: 4096 isn't zero
+pub(crate) const DEFAULT_INGEST_BUFFER: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(4096) };
+
 /// The read timeout to impose on tcp connections
+#[cfg(feature = "network")]
 pub(crate) const TCP_READ_TIMEOUT: Option<Duration> = Some(Duration::from_millis(200));
 
 /// The fuel used to extract data from the dataflow within the
@@ -46,6 +60,25 @@ pub(crate) const FILE_SOURCED_FUEL: NonZeroUsize = unsafe { NonZeroUsize::new_un
 /// The delay to reactivate replay operators after
 pub(crate) const DEFAULT_REACTIVATION_DELAY: Duration = Duration::from_millis(500);
 
+/// The largest length-prefixed event frame (in bytes) that replay readers will
+/// accept, anything larger is assumed to be a corrupted length header
+pub(crate) const MAX_EVENT_FRAME_LEN: usize = 256 * 1024 * 1024;
+
+/// The maximum length (in bytes) of operator names within ddshow's outputs
+///
+/// Operator names containing fully expanded generic types can easily be
+/// thousands of characters long, so anything longer than this is truncated
+/// and given a hash suffix to keep it distinct from similarly-named operators
+pub(crate) const MAX_OPERATOR_NAME_LEN: usize = 128;
+
+/// The widths (in nanoseconds) of the pre-aggregated timeline tiers, going from
+/// the coarsest to the finest
+///
+/// Each tier buckets the timeline events that started within each window of its
+/// width so that the timeline can be zoomed out on captures with far too many
+/// events to render individually, the raw events act as the final tier
+pub const TIMELINE_TIER_WIDTHS: [u64; 4] = [1_000_000_000, 100_000_000, 10_000_000, 1_000_000];
+
 /// The current version of DDShow
 pub const DDSHOW_VERSION: &str = concat!(
     env!("CARGO_PKG_NAME"),
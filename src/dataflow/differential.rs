@@ -8,10 +8,12 @@ use ddshow_types::differential_logging::DifferentialEvent;
 use differential_dataflow::difference::DiffPair;
 use differential_dataflow::{operators::CountTotal, AsCollection, Collection};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{convert::TryFrom, time::Duration};
 use timely::dataflow::{operators::Enter, Scope, Stream};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Serialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
 pub struct SplineLevel {
     pub event_time: Duration,
     pub scale: usize,
@@ -43,16 +45,24 @@ where
     scope.region_named("Collect arrangement statistics", |region| {
         let differential_events = differential_events.enter(region);
 
+        // Each event contributes `(size, batches, merges)`, the sizes and counts are taken
+        // from the events themselves so they don't depend on the target's difference type
         let merge_diffs = differential_events
             .filter_map_timed(|&time, (_event_time, worker, event)| match event {
                 DifferentialEvent::Batch(batch) => Some((
-                    ((worker, batch.operator), (batch.length as isize, 1)),
+                    (
+                        (worker, batch.operator),
+                        (saturating_diff(batch.length), 1, 0),
+                    ),
                     time,
                     1,
                 )),
                 DifferentialEvent::Merge(merge) => merge.complete.map(|complete_size| {
                     (
-                        ((worker, merge.operator), (complete_size as isize, 0)),
+                        (
+                            (worker, merge.operator),
+                            (saturating_diff(complete_size), 0, 1),
+                        ),
                         time,
                         1,
                     )
@@ -86,17 +96,18 @@ where
 
         #[cfg(feature = "timely-next")]
         let merge_stats = merge_diffs
-            .explode(|(key, (size, batches))| {
+            .explode(|(key, (size, batches, merges))| {
                 let (min, max) = (Min::new(size), Max::new(size));
 
-                Some((key, (1, (size, batches, min, max))))
+                Some((key, (1, ((batches, merges), min, max))))
             })
             .count_total()
-            .map(|(key, (_count, (_total, batches, min, max)))| {
+            .map(|(key, (_count, ((batches, merges), min, max)))| {
                 let stats = ArrangementStats {
                     max_size: max.value as usize,
                     min_size: min.value as usize,
                     batches: batches as usize,
+                    merges: merges as usize,
                 };
 
                 (key, stats)
@@ -104,14 +115,14 @@ where
 
         #[cfg(not(feature = "timely-next"))]
         let merge_stats = merge_diffs
-            .explode(|(key, (size, batches))| {
+            .explode(|(key, (size, batches, merges))| {
                 let (min, max) = (Min::new(size), Max::new(size));
 
                 Some((
                     key,
                     DiffPair::new(
                         1,
-                        DiffPair::new(size, DiffPair::new(batches, DiffPair::new(min, max))),
+                        DiffPair::new(DiffPair::new(batches, merges), DiffPair::new(min, max)),
                     ),
                 ))
             })
@@ -123,15 +134,15 @@ where
                         element1: _count,
                         element2:
                             DiffPair {
-                                element1: _total,
-                                element2:
+                                element1:
                                     DiffPair {
                                         element1: batches,
-                                        element2:
-                                            DiffPair {
-                                                element1: min,
-                                                element2: max,
-                                            },
+                                        element2: merges,
+                                    },
+                                element2:
+                                    DiffPair {
+                                        element1: min,
+                                        element2: max,
                                     },
                             },
                     },
@@ -140,6 +151,7 @@ where
                         max_size: max.value as usize,
                         min_size: min.value as usize,
                         batches: batches as usize,
+                        merges: merges as usize,
                     };
 
                     (key, stats)
@@ -150,13 +162,22 @@ where
     })
 }
 
+/// Converts an update count into our diff type, saturating instead of
+/// wrapping on absurdly large batches
+fn saturating_diff(count: usize) -> Diff {
+    Diff::try_from(count).unwrap_or(Diff::MAX)
+}
+
 #[derive(
     Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
 )]
 pub struct ArrangementStats {
     pub max_size: usize,
     pub min_size: usize,
+    /// The number of batches the arrangement received
     pub batches: usize,
+    /// The number of merges the arrangement completed
+    pub merges: usize,
     // /// Merge time, merge scale and the completed size of the merge
     // pub spline_levels: Vec<(Duration, usize, usize)>,
     // TODO: Max/min/average batch size
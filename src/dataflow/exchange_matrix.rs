@@ -0,0 +1,55 @@
+//! Tallies the records that each worker sent to every other worker
+//!
+//! Pipeline channels only ever send records to the worker they're on, so only
+//! the channels that carried at least one message between two different workers
+//! are counted as exchange channels. Records that an exchange channel routed back
+//! to the worker that sent them are kept on the matrix's diagonal
+
+use crate::dataflow::{
+    operators::FilterMapTimed,
+    utils::{Diff, Time, TimelyLogBundle},
+};
+use ddshow_types::{timely_logging::TimelyEvent, WorkerId};
+use differential_dataflow::{
+    operators::{CountTotal, Join, ThresholdTotal},
+    AsCollection, Collection,
+};
+use std::iter;
+use timely::dataflow::{Scope, Stream};
+
+pub fn exchange_matrix<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+) -> Collection<S, ((WorkerId, WorkerId), usize), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    // Only sends are counted so that every message is only counted once
+    let sends = timely_stream
+        .filter_map_ref_timed_named("Exchanged Messages", |&timestamp, &(_, _, ref event)| {
+            match event {
+                TimelyEvent::Messages(messages) if messages.is_send && messages.length != 0 => {
+                    let source = WorkerId::new(messages.source.into_inner());
+                    let target = WorkerId::new(messages.target.into_inner());
+
+                    Some((
+                        (messages.channel, (source, target, messages.length)),
+                        timestamp,
+                        1,
+                    ))
+                }
+                _ => None,
+            }
+        })
+        .as_collection();
+
+    let exchange_channels = sends
+        .filter(|&(_, (source, target, _))| source != target)
+        .map(|(channel, _)| channel)
+        .distinct_total();
+
+    sends
+        .semijoin(&exchange_channels)
+        .explode(|(_, (source, target, length))| iter::once(((source, target), length as Diff)))
+        .count_total()
+        .map(|(workers, records)| (workers, records as usize))
+}
@@ -0,0 +1,232 @@
+//! Drops unwanted events as soon as they're replayed, before they ever
+//! make it into the analysis dataflow
+//!
+//! Configured with `--ignore-events`, `--only-workers` and `--sample-messages`
+
+use crate::{
+    args::{Args, IgnoredEvent},
+    dataflow::operators::{EventBatch, MapInPlace},
+};
+use ddshow_types::{
+    differential_logging::DifferentialEvent, progress_logging::TimelyProgressEvent,
+    timely_logging::TimelyEvent, WorkerId,
+};
+use std::num::NonZeroUsize;
+use timely::{
+    dataflow::{Scope, Stream},
+    Data,
+};
+
+/// Decides which replayed events are kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngestFilter {
+    /// A bitset of the ignored event kinds, indexed by [`IgnoredEvent`]
+    ignored: u32,
+    /// The target workers to keep events from, `None` keeps all of them
+    workers: Option<Vec<WorkerId>>,
+    /// Only the messages whose sequence number is a multiple of this are kept
+    message_sampling: Option<NonZeroUsize>,
+}
+
+impl IngestFilter {
+    pub fn new(args: &Args) -> Self {
+        let ignored = args
+            .ignore_events
+            .iter()
+            .fold(0, |ignored, &kind| ignored | kind_bit(kind));
+
+        let workers = args.only_workers.as_ref().map(|workers| {
+            let mut workers: Vec<_> = workers.iter().copied().map(WorkerId::new).collect();
+            workers.sort_unstable();
+            workers.dedup();
+            workers
+        });
+
+        Self {
+            ignored,
+            workers,
+            message_sampling: args.sample_messages,
+        }
+    }
+
+    /// Returns `true` if the filter would never drop an event
+    pub fn is_passthrough(&self) -> bool {
+        self.ignored == 0 && self.workers.is_none() && self.message_sampling.is_none()
+    }
+
+    fn keeps_worker(&self, worker: WorkerId) -> bool {
+        self.workers
+            .as_ref()
+            .map_or(true, |workers| workers.binary_search(&worker).is_ok())
+    }
+
+    fn keeps_kind(&self, kind: Option<IgnoredEvent>) -> bool {
+        kind.map_or(true, |kind| self.ignored & kind_bit(kind) == 0)
+    }
+
+    fn keeps_message(&self, seq_no: Option<usize>) -> bool {
+        seq_no
+            .zip(self.message_sampling)
+            .map_or(true, |(seq_no, sampling)| seq_no % sampling.get() == 0)
+    }
+}
+
+/// The kind of an ingested event, used to decide whether it should be dropped
+pub trait IngestKind {
+    /// Returns the event's kind, `None` for events that can never be ignored
+    fn ingest_kind(&self) -> Option<IgnoredEvent>;
+
+    /// Returns the sequence number of message events, both the send and the
+    /// receive of a message share the same one so they're sampled together
+    fn message_seq_no(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl IngestKind for TimelyEvent {
+    fn ingest_kind(&self) -> Option<IgnoredEvent> {
+        match self {
+            Self::Operates(_) | Self::Channels(_) => None,
+            Self::PushProgress(_) => Some(IgnoredEvent::PushProgress),
+            Self::Messages(_) => Some(IgnoredEvent::Messages),
+            Self::Schedule(_) => Some(IgnoredEvent::Schedule),
+            Self::Shutdown(_) => Some(IgnoredEvent::Shutdown),
+            Self::Application(_) => Some(IgnoredEvent::Application),
+            Self::GuardedMessage(_) => Some(IgnoredEvent::GuardedMessage),
+            Self::GuardedProgress(_) => Some(IgnoredEvent::GuardedProgress),
+            Self::CommChannels(_) => Some(IgnoredEvent::CommChannels),
+            Self::Input(_) => Some(IgnoredEvent::Input),
+            Self::Park(_) => Some(IgnoredEvent::Park),
+            Self::Text(_) => Some(IgnoredEvent::Text),
+        }
+    }
+
+    fn message_seq_no(&self) -> Option<usize> {
+        match self {
+            Self::Messages(messages) => Some(messages.seq_no),
+            _ => None,
+        }
+    }
+}
+
+impl IngestKind for DifferentialEvent {
+    fn ingest_kind(&self) -> Option<IgnoredEvent> {
+        match self {
+            Self::Batch(_) => Some(IgnoredEvent::Batch),
+            Self::Merge(_) => Some(IgnoredEvent::Merge),
+            Self::Drop(_) => Some(IgnoredEvent::Drop),
+            Self::MergeShortfall(_) => Some(IgnoredEvent::MergeShortfall),
+            Self::TraceShare(_) => None,
+        }
+    }
+}
+
+/// Progress events are only ever filtered by worker
+impl IngestKind for TimelyProgressEvent {
+    fn ingest_kind(&self) -> Option<IgnoredEvent> {
+        None
+    }
+}
+
+pub trait FilterIngested {
+    /// Drops every event rejected by `filter`
+    fn filter_ingested(&self, filter: &IngestFilter) -> Self;
+}
+
+impl<S, E> FilterIngested for Stream<S, EventBatch<E>>
+where
+    S: Scope,
+    E: Data + IngestKind,
+{
+    fn filter_ingested(&self, filter: &IngestFilter) -> Self {
+        if filter.is_passthrough() {
+            return self.clone();
+        }
+
+        let filter = filter.clone();
+        self.map_in_place_named("Filter Ingested Events", move |batch| {
+            batch.retain(|_, worker, event| {
+                filter.keeps_worker(worker)
+                    && filter.keeps_kind(event.ingest_kind())
+                    && filter.keeps_message(event.message_seq_no())
+            })
+        })
+    }
+}
+
+const fn kind_bit(kind: IgnoredEvent) -> u32 {
+    1 << kind as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IngestFilter, IngestKind};
+    use crate::args::{Args, IgnoredEvent};
+    use ddshow_types::{
+        timely_logging::{MessagesEvent, OperatesEvent, ParkEvent, TimelyEvent},
+        ChannelId, OperatorAddr, OperatorId, WorkerId,
+    };
+    use std::{num::NonZeroUsize, time::Duration};
+
+    #[test]
+    fn drops_ignored_kinds_and_workers() {
+        let args = Args {
+            ignore_events: vec![IgnoredEvent::Park, IgnoredEvent::Messages],
+            only_workers: Some(vec![2, 0]),
+            ..Default::default()
+        };
+        let filter = IngestFilter::new(&args);
+        assert!(!filter.is_passthrough());
+
+        let park = TimelyEvent::Park(ParkEvent::Park(Some(Duration::from_secs(1))));
+        let operates = TimelyEvent::Operates(OperatesEvent::new(
+            OperatorId::new(0),
+            OperatorAddr::from(vec![0]),
+            "Map".to_owned(),
+        ));
+
+        assert!(!filter.keeps_kind(park.ingest_kind()));
+        assert!(filter.keeps_kind(operates.ingest_kind()));
+        assert!(filter.keeps_worker(WorkerId::new(0)));
+        assert!(filter.keeps_worker(WorkerId::new(2)));
+        assert!(!filter.keeps_worker(WorkerId::new(1)));
+
+        assert!(IngestFilter::new(&Args::default()).is_passthrough());
+    }
+
+    #[test]
+    fn samples_messages_by_sequence_number() {
+        let args = Args {
+            sample_messages: NonZeroUsize::new(4),
+            ..Default::default()
+        };
+        let filter = IngestFilter::new(&args);
+        assert!(!filter.is_passthrough());
+
+        let message = |seq_no| {
+            TimelyEvent::Messages(MessagesEvent {
+                is_send: true,
+                channel: ChannelId::new(0),
+                source: OperatorId::new(0),
+                target: OperatorId::new(1),
+                seq_no,
+                length: 10,
+            })
+        };
+
+        let kept: Vec<_> = (0..10)
+            .filter(|&seq_no| filter.keeps_message(message(seq_no).message_seq_no()))
+            .collect();
+        assert_eq!(kept, vec![0, 4, 8]);
+
+        let park = TimelyEvent::Park(ParkEvent::Park(None));
+        assert!(filter.keeps_message(park.message_seq_no()));
+    }
+
+    #[test]
+    fn structural_events_cant_be_ignored() {
+        assert!("operates".parse::<IgnoredEvent>().is_err());
+        assert!("channels".parse::<IgnoredEvent>().is_err());
+        assert_eq!("Park".parse::<IgnoredEvent>(), Ok(IgnoredEvent::Park));
+    }
+}
@@ -1,60 +1,91 @@
 // mod channel_stats;
 #[macro_use]
 pub mod operators;
+mod arrangement_stats;
+mod channel_backlog;
+mod channel_latency;
+pub(crate) mod clock;
 pub(crate) mod constants;
 mod differential;
+mod exchange_matrix;
+pub(crate) mod ingest_filter;
+mod operator_filter;
 mod operator_stats;
+mod park_stats;
 mod program_stats;
 mod progress_stats;
+mod quantile_sketch;
 #[cfg(feature = "timely-next")]
 mod reachability;
+mod scheduling_latency;
 mod send_recv;
 mod shape;
+mod shared_arrangements;
+mod stats_sink;
 mod subgraphs;
 mod summation;
 pub(crate) mod utils;
 // FIXME: Fix the tests
 // mod tests;
+mod timeline_spill;
 mod timely_source;
 mod worker;
 mod worker_timeline;
+mod worker_utilization;
 
-pub use constants::PROGRAM_NS_GRANULARITY;
+pub use constants::{PROGRAM_NS_GRANULARITY, TIMELINE_TIER_WIDTHS};
 pub use differential::{ArrangementStats, SplineLevel};
-pub use operator_stats::OperatorStats;
-pub use progress_stats::{Channel, OperatorProgress, ProgressInfo};
+pub use operator_stats::{ActivationPercentiles, OperatorStats};
+pub use progress_stats::{
+    progress_channel_stats, Channel, OperatorProgress, ProgressChannelStats, ProgressInfo,
+};
 pub use send_recv::{DataflowData, DataflowExtractor, DataflowReceivers, DataflowSenders};
 pub use shape::OperatorShape;
+pub use stats_sink::StatsSink;
+pub(crate) use stats_sink::{AnalysisSink, StatsSinks};
 pub use summation::Summation;
+pub use timeline_spill::TimelineSpill;
 pub use worker::worker_runtime;
-pub use worker_timeline::{EventKind, TimelineEvent};
+pub use worker_timeline::{
+    compact_timeline, EventCategory, EventKind, TimelineBucket, TimelineEvent,
+};
 
 use crate::{
-    args::Args,
+    args::{Analysis, Args},
     dataflow::{
+        operator_filter::OperatorFilter,
         operator_stats::OperatorStatsRelations,
-        operators::{FilterMap, JoinArranged},
+        operators::{FilterMap, JoinArranged, MapInPlace},
         program_stats::GraphStats,
+        progress_stats::ProgressCollections,
+        scheduling_latency::SchedulingLatency,
         send_recv::ChannelAddrs,
+        shared_arrangements::SharedArrangements,
         subgraphs::rewire_channels,
         timely_source::TimelyCollections,
         utils::{
-            ArrangedKey, ArrangedVal, Diff, DifferentialLogBundle, OpKey, ProgressLogBundle, Time,
-            TimelyLogBundle,
+            truncate_name, ArrangedKey, ArrangedVal, Diff, DifferentialLogBundle, OpKey,
+            ProgressLogBundle, Time, TimelyLogBundle,
         },
     },
-    ui::{DataflowStats, Lifespan},
+    ui::{
+        ArrangementStats as ArrangementDetails, ChannelBacklog, ChannelLatency,
+        ChannelMessageStats, DataflowStats, Lifespan, LoggedEventKind, ParkStats,
+        WorkerUtilization,
+    },
 };
+use abomonation_derive::Abomonation;
 use anyhow::Result;
 use ddshow_types::{timely_logging::OperatesEvent, ChannelId, OperatorAddr, OperatorId, WorkerId};
 use differential_dataflow::{
     operators::{
         arrange::{Arrange, ArrangeByKey, ArrangeBySelf},
-        CountTotal, Join, JoinCore, ThresholdTotal,
+        CountTotal, Join, JoinCore, Reduce, ThresholdTotal,
     },
     AsCollection, Collection,
 };
-use std::{iter, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, iter, time::Duration};
 use timely::dataflow::{
     operators::{generic::operator, probe::Handle as ProbeHandle},
     Scope, Stream,
@@ -79,12 +110,20 @@ pub fn dataflow<S>(
     master_probe: &mut ProbeHandle<Time>,
     timely_stream: &Stream<S, TimelyLogBundle>,
     differential_stream: Option<&Stream<S, DifferentialLogBundle>>,
-    _progress_stream: Option<&Stream<S, ProgressLogBundle>>,
+    progress_stream: Option<&Stream<S, ProgressLogBundle>>,
     senders: DataflowSenders,
 ) -> Result<Vec<(ProbeHandle<Time>, &'static str)>>
 where
     S: Scope<Timestamp = Time>,
 {
+    // `ddshow record` only saves the logs, none of them are analyzed
+    if args.records_only() {
+        let mut probes = Vec::new();
+        save_logs(scope, args, timely_stream, differential_stream, &mut probes)?;
+
+        return Ok(probes);
+    }
+
     let TimelyCollections {
         lifespans,
         activations,
@@ -95,18 +134,112 @@ where
         operator_addrs_to_ids,
         operator_addrs,
         channel_scope_addrs,
+        channel_messages,
         dataflow_ids,
         timeline_events,
         ..
-    } = timely_source::extract_timely_info(scope, timely_stream, args.disable_timeline);
+    } = timely_source::extract_timely_info(scope, timely_stream, !args.runs_timeline());
+
+    // Arrangement stats are only gathered when their analysis is run, the
+    // timeline still shows merges from the full differential stream
+    let arrangement_stream =
+        differential_stream.filter(|_| args.analyses.runs(Analysis::Arrangements));
+    let (runs_program_stats, runs_graph) = (
+        args.analyses.runs(Analysis::ProgramStats),
+        args.analyses.runs(Analysis::Graph),
+    );
+
+    // Operators left out by `--filter` and `--exclude` are dropped from
+    // everything that's keyed by operator before it's analyzed
+    let excluded_operators = OperatorFilter::new(args).map(|filter| {
+        operator_filter::excluded_operators(timely_stream, &operator_addrs_to_ids, &filter)
+    });
+    // Arranged once and shared between every collection that's filtered
+    let excluded_keys = excluded_operators.as_ref().map(|excluded| {
+        excluded
+            .map(|(operator, _)| operator)
+            .arrange_by_self_named("ArrangeBySelf: Excluded Operators")
+    });
+    // Graph-wide collections only hold the first worker's operators
+    let excluded_ids = excluded_keys.as_ref().map(|excluded| {
+        excluded.flat_map_ref(|&(worker, id), &()| (worker == WorkerId::new(0)).then(|| id))
+    });
+
+    let (lifespans, activations, raw_operator_events, operator_addrs) =
+        match (&excluded_operators, &excluded_keys) {
+            (Some(excluded_operators), Some(excluded_keys)) => {
+                let excluded_addrs = excluded_operators
+                    .filter(|&((worker, _), _)| worker == WorkerId::new(0))
+                    .map(|(_, addr)| addr);
+
+                (
+                    lifespans.antijoin_arranged(excluded_keys),
+                    activations.antijoin_arranged(excluded_keys),
+                    raw_operator_events
+                        .map(|operator| ((WorkerId::new(0), operator.id), operator))
+                        .antijoin_arranged(excluded_keys)
+                        .map(|(_, operator)| operator),
+                    operator_addrs
+                        .as_collection(|addr, &()| (addr.clone(), ()))
+                        .antijoin(&excluded_addrs)
+                        .arrange_named("Arrange: Filtered Operator Addrs"),
+                )
+            }
+
+            _ => (lifespans, activations, raw_operator_events, operator_addrs),
+        };
 
     let OperatorStatsRelations {
         summarized,
+        activation_percentiles,
         aggregated_summaries,
         arrangements,
         aggregated_arrangements,
         spline_levels,
-    } = operator_stats::operator_stats(scope, &activations, differential_stream);
+    } = operator_stats::operator_stats(scope, &activations, arrangement_stream, args.exchange_salt);
+
+    let (arrangements, aggregated_arrangements) = match (&excluded_keys, &excluded_ids) {
+        (Some(excluded_keys), Some(excluded_ids)) => (
+            arrangements.map(|arrangements| arrangements.antijoin_arranged(excluded_keys)),
+            aggregated_arrangements.map(|arrangements| arrangements.antijoin(excluded_ids)),
+        ),
+        _ => (arrangements, aggregated_arrangements),
+    };
+
+    // Merge each operator's stats across workers for `--aggregate-workers`
+    let addr_summaries = args
+        .aggregate_workers
+        .then(|| operator_stats::addr_summaries(&summarized, &operator_ids_to_addrs));
+
+    let slowest_operators = operator_stats::slowest_operators(&aggregated_summaries, args.top_k);
+    let activation_histograms =
+        operator_stats::activation_histograms(&activations, args.histogram_bounds());
+    let last_activations = operator_stats::last_activations(&activations);
+    let activation_anomalies = operator_stats::activation_anomalies(
+        &activations,
+        &activation_percentiles,
+        args.anomaly_threshold,
+    );
+    let activation_rates = operator_stats::activation_rates(
+        &activations,
+        Duration::from_millis(args.activation_rate_interval),
+    );
+
+    let arrangement_details = arrangement_stream.map(|stream| {
+        let details = arrangement_stats::arrangement_details(
+            stream,
+            &operator_ids_to_addrs,
+            args.max_data_points,
+        );
+
+        match &excluded_keys {
+            Some(excluded) => details.antijoin_arranged(excluded),
+            None => details,
+        }
+    });
+
+    // Only the first `--exact-activations` activations of each operator are extracted
+    let exact_activations = operator_stats::cap_activations(&activations, args.exact_activations);
 
     // FIXME: This is pretty much a guess since there's no way to actually associate
     //        operators/arrangements/channels across workers
@@ -115,7 +248,9 @@ where
     // let aggregated_operator_stats = operator_stats::aggregate_operator_stats(&operator_stats);
 
     // TODO: Turn these into collections of `OpKey` and arrange them
-    let (leaves, subgraphs) = sift_leaves_and_scopes(scope, &operator_addrs);
+    let shared = SharedArrangements::new(operator_addrs, &raw_operator_events);
+
+    let (leaves, subgraphs) = sift_leaves_and_scopes(scope, &shared.operator_addrs);
     let (leaves_arranged, subgraphs_arranged) = (
         leaves.arrange_by_self_named("ArrangeBySelf: Dataflow Graph Leaves"),
         subgraphs.arrange_by_self_named("ArrangeBySelf: Dataflow Graph Subgraphs"),
@@ -130,9 +265,28 @@ where
         .arrange_named("Arrange: Dataflow Graph Subgraph Ids");
 
     let channels = rewire_channels(scope, &raw_channel_events, &subgraphs_arranged);
-    let edges = attach_operators(scope, &raw_operator_events, &channels, &leaves_arranged);
+    let edges = attach_operators(
+        scope,
+        &shared.operators_by_addr,
+        &channels,
+        &leaves_arranged,
+    );
+
+    let scheduling_latency = scheduling_latency::scheduling_latency(timely_stream, &edges);
+    let channel_backlogs = channel_backlog::channel_backlogs(timely_stream);
+    let channel_latencies = channel_latency::channel_latencies(timely_stream);
+    let exchange_matrix = exchange_matrix::exchange_matrix(timely_stream);
 
-    let operator_shapes = shape::operator_shapes(&raw_operator_events, &raw_channel_events);
+    let operator_shapes = runs_graph.then(|| {
+        shape::operator_shapes(
+            &shared.operators_by_addr,
+            &shared.operator_ids,
+            &raw_channel_events,
+        )
+    });
+    let progress = progress_stream.map(|progress_stream| {
+        progress_stats::progress_stats(scope, progress_stream, args.max_data_points)
+    });
     // let operator_progress = progress_stream.map(|progress_stream| {
     //     progress_stats::aggregate_channel_messages(progress_stream, &operator_shapes)
     // });
@@ -141,12 +295,23 @@ where
     // TODO: Grabbing events absolutely shits the bed when it comes to large dataflows,
     //       it needs a serious, intrinsic rework and/or disk backed arrangements
     let timeline_events = timeline_events.as_ref().map(|timeline_events| {
-        worker_timeline::worker_timeline(scope, timeline_events, differential_stream)
+        let timeline =
+            worker_timeline::worker_timeline(scope, timeline_events, differential_stream);
+
+        // Activations and merges are filtered together once they're both on the timeline
+        match &excluded_keys {
+            Some(excluded) => timeline
+                .map(|event| (event.operator(), event))
+                .antijoin(&excluded.as_collection(|&operator, &()| Some(operator)))
+                .map(|(_, event)| event),
+            None => timeline,
+        }
+    });
+    let timeline_buckets = timeline_events.as_ref().map(|timeline_events| {
+        worker_timeline::timeline_tiers(timeline_events, args.exchange_salt)
     });
 
-    let addressed_operators = raw_operator_events
-        .map(|operator| (operator.addr.clone(), operator))
-        .arrange_by_key_named("ArrangeByKey: Addressed Operators");
+    let addressed_operators = runs_graph.then(|| shared.operators_by_addr.clone());
 
     let GraphStats {
         workers,
@@ -155,21 +320,41 @@ where
         channels,
         arrangements: arrangement_ids,
         total_runtime,
-    } = program_stats::aggregate_program_stats(
-        timely_stream,
-        differential_stream,
-        &channels,
-        &subgraphs_arranged,
-        &operator_addrs,
-    );
+        event_kinds,
+    } = if runs_program_stats {
+        program_stats::aggregate_program_stats(
+            timely_stream,
+            arrangement_stream,
+            &channels,
+            &subgraphs_arranged,
+            &shared.operator_addrs,
+        )
+    } else {
+        GraphStats::empty(scope)
+    };
 
-    let dataflow_stats = dataflow_stats(
-        &lifespans,
-        &dataflow_ids,
-        &operator_ids_to_addrs,
-        &subgraph_ids,
-        &channel_scope_addrs,
-    );
+    let (worker_utilization, park_stats, dataflow_stats) = if runs_program_stats {
+        (
+            worker_utilization::worker_utilization(timely_stream, &total_runtime),
+            park_stats::park_stats(timely_stream),
+            dataflow_stats(
+                &lifespans,
+                &dataflow_ids,
+                &operator_ids_to_addrs,
+                &subgraph_ids,
+                &channel_scope_addrs,
+                &summarized,
+                &channel_messages,
+                arrangements.as_ref(),
+            ),
+        )
+    } else {
+        (
+            operator::empty(scope).as_collection(),
+            operator::empty(scope).as_collection(),
+            operator::empty(scope).as_collection(),
+        )
+    };
 
     let mut probes = install_data_extraction(
         scope,
@@ -181,22 +366,41 @@ where
         channels,
         arrangement_ids,
         total_runtime,
+        worker_utilization,
+        event_kinds,
+        park_stats,
         leaves_arranged,
-        edges,
+        runs_graph.then(|| edges),
+        channel_messages,
+        channel_backlogs,
+        channel_latencies,
+        exchange_matrix,
         subgraphs_arranged,
         addressed_operators,
         dataflow_stats,
         timeline_events,
+        timeline_buckets,
         operator_names,
         operator_ids_to_addrs,
-        &operator_shapes,
+        operator_shapes.as_ref(),
         None,
-        activations,
+        exact_activations,
+        lifespans,
         summarized,
+        activation_percentiles,
+        activation_histograms,
+        activation_rates,
+        last_activations,
+        activation_anomalies,
         aggregated_summaries,
+        addr_summaries,
+        slowest_operators,
+        scheduling_latency,
         arrangements,
         aggregated_arrangements,
+        arrangement_details,
         spline_levels,
+        progress,
     );
 
     // TODO: Save ddflow logs
@@ -205,7 +409,22 @@ where
     //       log files in order of timestamp
     // TODO: For pause/resume profiling/debugging we'll probably need a custom log
     //       hook within timely, we can make it serve us rkyv events while we're at it
-    // If saving logs is enabled, write all log messages to the `save_logs` directory
+    save_logs(scope, args, timely_stream, differential_stream, &mut probes)?;
+
+    Ok(probes)
+}
+
+/// If saving logs is enabled, write all log messages to the `save_logs` directory
+fn save_logs<S>(
+    scope: &mut S,
+    args: &Args,
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    differential_stream: Option<&Stream<S, DifferentialLogBundle>>,
+    probes: &mut Vec<(ProbeHandle<Time>, &'static str)>,
+) -> Result<()>
+where
+    S: Scope<Timestamp = Time>,
+{
     if let Some(save_logs) = args.save_logs.as_ref() {
         tracing::info!(
             "installing timely{} log sinks",
@@ -228,7 +447,7 @@ where
         probes.push((probe, "log_event_sink"));
     }
 
-    Ok(probes)
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
@@ -242,22 +461,41 @@ fn install_data_extraction<S>(
     channels: Collection<S, ChannelId, Diff>,
     arrangement_ids: Option<Collection<S, (WorkerId, OperatorId), Diff>>,
     total_runtime: Collection<S, (WorkerId, (Duration, Duration)), Diff>,
+    worker_utilization: Collection<S, (WorkerId, WorkerUtilization), Diff>,
+    event_kinds: Collection<S, ((WorkerId, LoggedEventKind), usize), Diff>,
+    park_stats: Collection<S, (WorkerId, ParkStats), Diff>,
     nodes: ArrangedKey<S, OperatorAddr, Diff>,
-    edges: Collection<S, (OperatesEvent, Channel, OperatesEvent), Diff>,
+    edges: Option<Collection<S, (OperatesEvent, Channel, OperatesEvent), Diff>>,
+    channel_messages: Collection<S, ((WorkerId, ChannelId), ChannelMessageStats), Diff>,
+    channel_backlogs: Collection<S, (ChannelId, ChannelBacklog), Diff>,
+    channel_latencies: Collection<S, (ChannelId, ChannelLatency), Diff>,
+    exchange_matrix: Collection<S, ((WorkerId, WorkerId), usize), Diff>,
     subgraphs: ArrangedKey<S, OperatorAddr, Diff>,
-    addressed_operators: ArrangedVal<S, OperatorAddr, OperatesEvent, Diff>,
+    addressed_operators: Option<ArrangedVal<S, OperatorAddr, OperatesEvent, Diff>>,
     dataflow_stats: Collection<S, DataflowStats, Diff>,
     timeline_events: Option<Collection<S, TimelineEvent, Diff>>,
+    timeline_buckets: Option<Collection<S, TimelineBucket, Diff>>,
     operator_names: ArrangedVal<S, OpKey, String, Diff>,
     operator_ids: ArrangedVal<S, OpKey, OperatorAddr, Diff>,
-    operator_shapes: &Collection<S, OperatorShape, Diff>,
+    operator_shapes: Option<&Collection<S, OperatorShape, Diff>>,
     operator_progress: Option<&Collection<S, OperatorProgress, Diff>>,
     operator_activations: Collection<S, (OpKey, (Duration, Duration)), Diff>,
+    operator_lifespans: Collection<S, (OpKey, Lifespan), Diff>,
     summarized: Collection<S, (OpKey, Summation), Diff>,
+    activation_percentiles: Collection<S, (OpKey, ActivationPercentiles), Diff>,
+    activation_histograms: Collection<S, (OpKey, (usize, usize)), Diff>,
+    activation_rates: Collection<S, (OpKey, (Duration, (usize, Duration))), Diff>,
+    last_activations: Collection<S, (OpKey, Duration), Diff>,
+    activation_anomalies: Collection<S, (OpKey, (Duration, (Duration, Duration))), Diff>,
     aggregated_summaries: Collection<S, (OperatorId, Summation), Diff>,
+    addr_summaries: Option<Collection<S, (OperatorAddr, Summation), Diff>>,
+    slowest_operators: Collection<S, (OperatorId, Summation), Diff>,
+    scheduling_latency: SchedulingLatency<S>,
     arrangements: Option<Collection<S, (OpKey, ArrangementStats), Diff>>,
     aggregated_arrangements: Option<Collection<S, (OperatorId, ArrangementStats), Diff>>,
+    arrangement_details: Option<Collection<S, (OpKey, ArrangementDetails), Diff>>,
     spline_levels: Option<Collection<S, (OpKey, SplineLevel), Diff>>,
+    progress: Option<ProgressCollections<S>>,
 ) -> Vec<(ProbeHandle<Time>, &'static str)>
 where
     S: Scope<Timestamp = Time>,
@@ -271,39 +509,127 @@ where
             .map(|ids| ids.enter_region(region))
             .unwrap_or_else(|| operator::empty(region).as_collection());
         let total_runtime = total_runtime.enter_region(region);
-        let nodes = nodes.enter_region(region);
-        let edges = edges.enter_region(region);
-        let subgraphs = subgraphs.enter_region(region);
-        let addressed_operators = addressed_operators.enter_region(region);
+        let worker_utilization = worker_utilization.enter_region(region);
+        let event_kinds = event_kinds.enter_region(region);
+        let park_stats = park_stats.enter_region(region);
+        let channel_messages = channel_messages.enter_region(region);
+        let channel_backlogs = channel_backlogs.enter_region(region);
+        let channel_latencies = channel_latencies.enter_region(region);
+        let exchange_matrix = exchange_matrix.enter_region(region);
         let dataflow_stats = dataflow_stats.enter_region(region);
         let timeline_events = timeline_events
             .map(|events| events.enter_region(region))
             .unwrap_or_else(|| operator::empty(region).as_collection());
-        let operator_names = operator_names
-            .enter_region(region)
-            .as_collection(|&(worker, operator), name| ((worker, operator), name.to_owned()));
+        let timeline_buckets = timeline_buckets
+            .map(|buckets| buckets.enter_region(region))
+            .unwrap_or_else(|| operator::empty(region).as_collection());
+        let operator_names = operator_names.enter_region(region);
+        // Full names are only kept for operators whose names were truncated
+        let full_operator_names = operator_names.flat_map_ref(|&key, name| {
+            if let Cow::Owned(_) = truncate_name(name) {
+                Some((key, name.to_owned()))
+            } else {
+                None
+            }
+        });
+        let operator_names = operator_names.as_collection(|&(worker, operator), name| {
+            ((worker, operator), truncate_name(name).into_owned())
+        });
         let operator_ids = operator_ids
             .enter_region(region)
             .as_collection(|&key, addr| (key, addr.clone()));
-        let operator_shapes = operator_shapes.enter_region(region);
+        let operator_shapes = operator_shapes
+            .map(|shapes| shapes.enter_region(region))
+            .unwrap_or_else(|| operator::empty(region).as_collection());
         let operator_progress = operator_progress
             .map(|progress| progress.enter_region(region))
             .unwrap_or_else(|| operator::empty(region).as_collection());
         let operator_activations = operator_activations.enter_region(region);
+        let operator_lifespans = operator_lifespans.enter_region(region);
         let summarized = summarized.enter_region(region);
+        let activation_percentiles = activation_percentiles.enter_region(region);
+        let activation_histograms = activation_histograms.enter_region(region);
+        let activation_rates = activation_rates.enter_region(region);
+        let last_activations = last_activations.enter_region(region);
+        let activation_anomalies = activation_anomalies.enter_region(region);
         let aggregated_summaries = aggregated_summaries.enter_region(region);
+        let addr_summaries = addr_summaries
+            .map(|summaries| summaries.enter_region(region))
+            .unwrap_or_else(|| operator::empty(region).as_collection());
+        let slowest_operators = slowest_operators.enter_region(region);
+        let scheduling_latencies = scheduling_latency.latencies.enter_region(region);
+        let scheduling_percentiles = scheduling_latency.percentiles.enter_region(region);
         let arrangements = arrangements
             .map(|arrangements| arrangements.enter_region(region))
             .unwrap_or_else(|| operator::empty(region).as_collection());
         let aggregated_arrangements = aggregated_arrangements
             .map(|aggregated| aggregated.enter_region(region))
             .unwrap_or_else(|| operator::empty(region).as_collection());
+        let arrangement_details = arrangement_details
+            .map(|details| details.enter_region(region))
+            .unwrap_or_else(|| operator::empty(region).as_collection());
         let spline_levels = spline_levels
             .map(|splines| splines.enter_region(region))
             .unwrap_or_else(|| operator::empty(region).as_collection());
+        let (
+            progress_traffic,
+            frontier_progression,
+            dataflow_frontiers,
+            scope_iterations,
+            epoch_latencies,
+        ) = progress
+            .map(|progress| {
+                (
+                    progress.traffic.enter_region(region),
+                    progress.frontier_progression.enter_region(region),
+                    progress.dataflow_frontiers.enter_region(region),
+                    progress.scope_iterations.enter_region(region),
+                    progress.epoch_latencies.enter_region(region),
+                )
+            })
+            .unwrap_or_else(|| {
+                (
+                    operator::empty(region).as_collection(),
+                    operator::empty(region).as_collection(),
+                    operator::empty(region).as_collection(),
+                    operator::empty(region).as_collection(),
+                    operator::empty(region).as_collection(),
+                )
+            });
+
+        // The graph's nodes, subgraphs and edges are only extracted when the graph analysis is run
+        let (nodes, subgraphs) = addressed_operators
+            .map(|addressed_operators| {
+                let addressed_operators = addressed_operators.enter_region(region);
 
-        let nodes = addressed_operators.semijoin_arranged(&nodes);
-        let subgraphs = addressed_operators.semijoin_arranged(&subgraphs);
+                let nodes = addressed_operators
+                    .semijoin_arranged(&nodes.enter_region(region))
+                    .map_in_place_named("Truncate Node Names", |(_, node)| truncate_operates(node));
+                let subgraphs = addressed_operators
+                    .semijoin_arranged(&subgraphs.enter_region(region))
+                    .map_in_place_named("Truncate Subgraph Names", |(_, subgraph)| {
+                        truncate_operates(subgraph)
+                    });
+
+                (nodes, subgraphs)
+            })
+            .unwrap_or_else(|| {
+                (
+                    operator::empty(region).as_collection(),
+                    operator::empty(region).as_collection(),
+                )
+            });
+        let edges = edges
+            .map(|edges| {
+                edges.enter_region(region).map_in_place_named(
+                    "Truncate Edge Names",
+                    |(source, _, target)| {
+                        truncate_operates(source);
+                        truncate_operates(target);
+                    },
+                )
+            })
+            .unwrap_or_else(|| operator::empty(region).as_collection());
 
         // TODO: Since we pseudo-consolidate on the receiver side we may
         //       not actually need to maintain arrangements here,
@@ -318,31 +644,144 @@ where
             (&channels, false),
             (&arrangement_ids, false),
             (&total_runtime, false),
+            (&worker_utilization, false),
+            (&event_kinds, false),
+            (&park_stats, false),
             (&nodes, false),
             (&edges, false),
+            (&channel_messages, false),
+            (&channel_backlogs, false),
+            (&channel_latencies, false),
+            (&exchange_matrix, false),
             (&subgraphs, false),
             (&dataflow_stats, false),
             (&timeline_events, false),
+            (&timeline_buckets, false),
             (&operator_names, false),
+            (&full_operator_names, false),
             (&operator_ids, false),
             (&operator_shapes, false),
             (&operator_progress, false),
             (&operator_activations, false),
+            (&operator_lifespans, false),
             (&summarized, false),
+            (&activation_percentiles, false),
+            (&activation_histograms, false),
+            (&activation_rates, false),
+            (&last_activations, false),
+            (&activation_anomalies, false),
             (&aggregated_summaries, false),
+            (&addr_summaries, false),
+            (&slowest_operators, false),
+            (&scheduling_latencies, false),
+            (&scheduling_percentiles, false),
             (&arrangements, false),
             (&aggregated_arrangements, false),
+            (&arrangement_details, false),
             (&spline_levels, false),
+            (&progress_traffic, false),
+            (&frontier_progression, false),
+            (&dataflow_frontiers, false),
+            (&scope_iterations, false),
+            (&epoch_latencies, false),
         )
     })
 }
 
+/// Truncates the name of an operator if it's too long
+fn truncate_operates(operator: &mut OperatesEvent) {
+    if let Cow::Owned(name) = truncate_name(&operator.name) {
+        operator.name = name;
+    }
+}
+
+/// Something within a dataflow that's rolled up into its [`DataflowStats`]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+enum RollupPart {
+    /// Makes sure that every dataflow has a rollup, even if nothing within it ran
+    Dataflow,
+    /// An operator's activations and, for the dataflow's root scope, the time it
+    /// was scheduled for. The root's schedules contain every operator within it,
+    /// so its time is the dataflow's total activation time
+    Operator {
+        total: Duration,
+        activations: usize,
+    },
+    Channel {
+        records: usize,
+        batches: usize,
+    },
+    Arrangement {
+        max_size: usize,
+    },
+}
+
+/// The totals of everything within a dataflow
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Abomonation,
+    Deserialize,
+    Serialize,
+)]
+struct DataflowRollup {
+    total_activation_time: Duration,
+    events: usize,
+    records_exchanged: usize,
+    peak_arrangement_size: Option<usize>,
+}
+
+impl DataflowRollup {
+    fn tally(parts: &[(&RollupPart, Diff)]) -> Self {
+        let mut rollup = Self::default();
+
+        for &(&part, diff) in parts {
+            let count = diff.max(0) as usize;
+
+            match part {
+                RollupPart::Dataflow => {}
+
+                RollupPart::Operator { total, activations } => {
+                    rollup.total_activation_time += total * count as u32;
+                    rollup.events += activations * count;
+                }
+
+                // Every sent or received batch is its own message event
+                RollupPart::Channel { records, batches } => {
+                    rollup.records_exchanged += records * count;
+                    rollup.events += batches * count;
+                }
+
+                RollupPart::Arrangement { max_size } => {
+                    let peak = rollup.peak_arrangement_size.get_or_insert(0);
+                    *peak = (*peak).max(max_size);
+                }
+            }
+        }
+
+        rollup
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn dataflow_stats<S>(
     operator_lifespans: &Collection<S, (OpKey, Lifespan), Diff>,
     dataflow_ids: &ArrangedKey<S, OpKey>,
     addr_lookup: &ArrangedVal<S, OpKey, OperatorAddr>,
     subgraph_ids: &ArrangedKey<S, OpKey>,
     channel_scopes: &ArrangedVal<S, (WorkerId, ChannelId), OperatorAddr>,
+    summarized: &Collection<S, (OpKey, Summation), Diff>,
+    channel_messages: &Collection<S, ((WorkerId, ChannelId), ChannelMessageStats), Diff>,
+    arrangements: Option<&Collection<S, (OpKey, ArrangementStats), Diff>>,
 ) -> Collection<S, DataflowStats, Diff>
 where
     S: Scope<Timestamp = Time>,
@@ -401,25 +840,136 @@ where
     // Find the addresses of all dataflows
     let dataflows = addr_lookup.semijoin_arranged(dataflow_ids);
 
-    // TODO: Delta join this :(
+    // The first element of every address is the id of the dataflow it's within
+    let dataflow_of = |addr: &OperatorAddr| OperatorAddr::from(&addr[..addr.len().min(1)]);
+
+    let operator_parts = summarized.join_core(addr_lookup, |&(worker, _), stats, addr| {
+        let total = if addr.len() == 1 {
+            stats.total
+        } else {
+            Duration::from_secs(0)
+        };
+        let part = RollupPart::Operator {
+            total,
+            activations: stats.count,
+        };
+
+        iter::once(((worker, dataflow_of(addr)), part))
+    });
+
+    let channel_parts = channel_messages.join_core(channel_scopes, |&(worker, _), stats, addr| {
+        let part = RollupPart::Channel {
+            records: stats.records_sent,
+            batches: stats.batches_sent + stats.batches_received,
+        };
+
+        iter::once(((worker, dataflow_of(addr)), part))
+    });
+
+    let mut parts = dataflows
+        .map(|((worker, _), addr)| ((worker, addr), RollupPart::Dataflow))
+        .concat(&operator_parts)
+        .concat(&channel_parts);
+
+    if let Some(arrangements) = arrangements {
+        let arrangement_parts = arrangements.join_core(addr_lookup, |&(worker, _), stats, addr| {
+            let part = RollupPart::Arrangement {
+                max_size: stats.max_size,
+            };
+
+            iter::once(((worker, dataflow_of(addr)), part))
+        });
+
+        parts = parts.concat(&arrangement_parts);
+    }
+
+    let rollups = parts
+        .reduce_named("Reduce: Dataflow Rollups", |_dataflow, parts, output| {
+            output.push((DataflowRollup::tally(parts), 1));
+        })
+        .join_map(
+            &dataflows.map(|((worker, id), addr)| ((worker, addr), id)),
+            |&(worker, _), &rollup, &id| ((worker, id), rollup),
+        );
+
+    // Rather than joining each of the parts against each other, which arranges
+    // every intermediate result, they're all merged into a single reduce that
+    // emits each dataflow's stats as soon as its address and lifespan are known
     dataflows
-        .join(operator_lifespans)
-        .join(&subgraph_operators)
-        .join(&subgraph_subgraphs)
-        .join(&subgraph_channels)
-        .map(
-            |((worker, id), ((((addr, lifespan), operators), subgraphs), channels))| {
-                DataflowStats {
-                    id,
-                    addr,
-                    worker,
-                    operators,
-                    subgraphs,
-                    channels,
-                    lifespan,
-                }
-            },
+        .map(|(dataflow, addr)| (dataflow, DataflowPart::Addr(addr)))
+        .concat(
+            &operator_lifespans
+                .semijoin_arranged(dataflow_ids)
+                .map(|(dataflow, lifespan)| (dataflow, DataflowPart::Lifespan(lifespan))),
         )
+        .concat(
+            &subgraph_operators
+                .map(|(dataflow, operators)| (dataflow, DataflowPart::Operators(operators))),
+        )
+        .concat(
+            &subgraph_subgraphs
+                .map(|(dataflow, subgraphs)| (dataflow, DataflowPart::Subgraphs(subgraphs))),
+        )
+        .concat(
+            &subgraph_channels
+                .map(|(dataflow, channels)| (dataflow, DataflowPart::Channels(channels))),
+        )
+        .concat(&rollups.map(|(dataflow, rollup)| (dataflow, DataflowPart::Rollup(rollup))))
+        .reduce_named("Reduce: Dataflow Stats", |_dataflow, parts, output| {
+            if let Some(stats) = DataflowPart::assemble(parts) {
+                output.push((stats, 1));
+            }
+        })
+        .map(|((worker, id), mut stats)| {
+            stats.worker = worker;
+            stats.id = id;
+            stats
+        })
+}
+
+/// A piece of a dataflow's [`DataflowStats`], all of which are merged together
+/// by [`dataflow_stats()`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+enum DataflowPart {
+    Addr(OperatorAddr),
+    Lifespan(Lifespan),
+    Operators(usize),
+    Subgraphs(usize),
+    Channels(usize),
+    Rollup(DataflowRollup),
+}
+
+impl DataflowPart {
+    /// Builds the dataflow's stats once both its address and lifespan have
+    /// arrived, the counts of anything within it that hasn't arrived yet are zero
+    fn assemble(parts: &[(&Self, Diff)]) -> Option<DataflowStats> {
+        let (mut addr, mut lifespan) = (None, None);
+        let mut stats = DataflowStats::default();
+
+        for &(part, diff) in parts {
+            if diff <= 0 {
+                continue;
+            }
+
+            match part {
+                Self::Addr(dataflow_addr) => addr = Some(dataflow_addr.clone()),
+                Self::Lifespan(dataflow_lifespan) => lifespan = Some(*dataflow_lifespan),
+                &Self::Operators(operators) => stats.operators = operators,
+                &Self::Subgraphs(subgraphs) => stats.subgraphs = subgraphs,
+                &Self::Channels(channels) => stats.channels = channels,
+                Self::Rollup(rollup) => {
+                    stats.total_activation_time = rollup.total_activation_time;
+                    stats.events = rollup.events;
+                    stats.records_exchanged = rollup.records_exchanged;
+                    stats.peak_arrangement_size = rollup.peak_arrangement_size;
+                }
+            }
+        }
+
+        stats.addr = addr?;
+        stats.lifespan = lifespan?;
+        Some(stats)
+    }
 }
 
 type LeavesAndScopes<S, R> = (
@@ -466,7 +1016,7 @@ where
 
 fn attach_operators<S>(
     scope: &mut S,
-    operators: &Collection<S, OperatesEvent, Diff>,
+    operators_by_address: &ArrangedVal<S, OperatorAddr, OperatesEvent>,
     channels: &Collection<S, Channel, Diff>,
     leaves: &ChannelAddrs<S, Diff>,
 ) -> Collection<S, (OperatesEvent, Channel, OperatesEvent), Diff>
@@ -475,16 +1025,12 @@ where
 {
     // TODO: Make `Graph` nested so that subgraphs contain a `Vec<Graph>` of all children
     scope.region_named("Attach Operators to Channels", |region| {
-        let (operators, channels, leaves) = (
-            operators.enter_region(region),
+        let (operators_by_address, channels, leaves) = (
+            operators_by_address.enter_region(region),
             channels.enter_region(region),
             leaves.enter_region(region),
         );
 
-        let operators_by_address = operators
-            .map(|operator| ((operator.addr.clone()), operator))
-            .arrange_by_key_named("ArrangeByKey: Operators by Address");
-
         operators_by_address
             .semijoin_arranged(&leaves)
             .join_map(
@@ -0,0 +1,124 @@
+//! Narrows the analysis down to the operators matched by `--filter` and `--exclude`
+//!
+//! Operators are matched by their names and their addresses, the scopes that
+//! contain a kept operator are always kept as well so that the graph stays
+//! connected. Every collection that's keyed by operator is then filtered
+//! against the excluded operators before it's analyzed
+
+use crate::{
+    args::Args,
+    dataflow::{
+        operators::FilterMapTimed,
+        utils::{ArrangedVal, Diff, OpKey, Time, TimelyLogBundle},
+    },
+};
+use ddshow_types::{timely_logging::TimelyEvent, OperatorAddr, OperatorId, WorkerId};
+use differential_dataflow::{
+    operators::{arrange::ArrangeByKey, Join, JoinCore, ThresholdTotal},
+    AsCollection, Collection,
+};
+use regex::Regex;
+use timely::dataflow::{Scope, Stream};
+
+/// Decides which operators are analyzed
+#[derive(Debug, Clone)]
+pub struct OperatorFilter {
+    filter: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl OperatorFilter {
+    /// Creates the filter if the user gave `--filter` or `--exclude`
+    pub fn new(args: &Args) -> Option<Self> {
+        if args.filter.is_none() && args.exclude.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            filter: args.filter.clone(),
+            exclude: args.exclude.clone(),
+        })
+    }
+
+    /// Returns `true` if the operator with the given name and address is analyzed
+    pub fn keeps(&self, name: &str, addr: &OperatorAddr) -> bool {
+        let addr = addr.to_string();
+        let matches = |regex: &Regex| regex.is_match(name) || regex.is_match(&addr);
+
+        self.filter.as_ref().map_or(true, matches) && !self.exclude.as_ref().map_or(false, matches)
+    }
+}
+
+/// Finds the operators that the filter leaves out, along with their addresses
+pub fn excluded_operators<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    operator_addrs_to_ids: &ArrangedVal<S, (WorkerId, OperatorAddr), OperatorId>,
+    filter: &OperatorFilter,
+) -> Collection<S, (OpKey, OperatorAddr), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    let filter = filter.clone();
+    let matched = timely_stream
+        .filter_map_ref_timed_named(
+            "Filter Operators",
+            move |&timestamp, &(_, worker, ref event)| match event {
+                TimelyEvent::Operates(operates) => Some((
+                    (
+                        (worker, operates.id),
+                        (
+                            operates.addr.clone(),
+                            filter.keeps(&operates.name, &operates.addr),
+                        ),
+                    ),
+                    timestamp,
+                    1,
+                )),
+                _ => None,
+            },
+        )
+        .as_collection();
+
+    // Every scope that a kept operator lives within is kept too
+    let kept_scopes = matched
+        .flat_map(|((worker, _), (addr, kept))| {
+            let scopes = if kept {
+                addr.len().saturating_sub(1)
+            } else {
+                0
+            };
+
+            (1..=scopes).map(move |len| ((worker, OperatorAddr::from_slice(&addr[..len])), ()))
+        })
+        .distinct_total()
+        .arrange_by_key_named("ArrangeByKey: Kept Scopes")
+        .join_core(operator_addrs_to_ids, |&(worker, _), &(), &id| {
+            Some((worker, id))
+        });
+
+    matched
+        .filter(|(_, (_, kept))| !kept)
+        .map(|(operator, (addr, _))| (operator, addr))
+        .antijoin(&kept_scopes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OperatorFilter;
+    use ddshow_types::OperatorAddr;
+    use regex::Regex;
+
+    #[test]
+    fn operators_are_matched_by_name_or_address() {
+        let filter = OperatorFilter {
+            filter: Some(Regex::new("Join|^\\[0, 4").unwrap()),
+            exclude: Some(Regex::new("Internal").unwrap()),
+        };
+        let addr = OperatorAddr::from(vec![0, 1]);
+
+        assert!(filter.keeps("JoinCore", &addr));
+        assert!(!filter.keeps("Map", &addr));
+        assert!(filter.keeps("Map", &OperatorAddr::from(vec![0, 4, 2])));
+        assert!(!filter.keeps("Internal Join", &addr));
+    }
+}
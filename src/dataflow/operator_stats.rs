@@ -1,17 +1,21 @@
 use crate::dataflow::{
     differential::{self, ArrangementStats, SplineLevel},
     operators::{DiffDuration, Max, Min},
-    summation::{summation, Summation},
-    utils::{Diff, DifferentialLogBundle, OpKey, Time},
-    OperatorId, WorkerId,
+    quantile_sketch::QuantileSketch,
+    summation::{merge_salted, merge_summations, summation, Summation},
+    utils::{Diff, DifferentialLogBundle, OpKey, Time, XXHasher},
+    ArrangedVal, OperatorAddr, OperatorId, WorkerId,
 };
 use abomonation_derive::Abomonation;
 #[cfg(not(feature = "timely-next"))]
 use differential_dataflow::difference::DiffPair;
-use differential_dataflow::{operators::CountTotal, Collection};
+use differential_dataflow::{
+    operators::{CountTotal, Join, JoinCore, Reduce},
+    AsCollection, Collection, ExchangeData, Hashable,
+};
 use serde::{Deserialize, Serialize};
-use std::{iter, time::Duration};
-use timely::dataflow::{Scope, Stream};
+use std::{cmp::Reverse, collections::HashMap, iter, num::NonZeroUsize, time::Duration};
+use timely::dataflow::{channels::pact::Pipeline, operators::Operator, Scope, Stream};
 
 type ActivationTimes<S> = Collection<S, ((WorkerId, OperatorId), (Duration, Duration)), Diff>;
 
@@ -45,11 +49,46 @@ pub struct AggregatedOperatorStats {
     // pub messages_received: usize,
 }
 
+/// Percentiles of an operator's activation durations
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Abomonation,
+    Deserialize,
+    Serialize,
+)]
+pub struct ActivationPercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+}
+
+impl ActivationPercentiles {
+    /// Estimates the percentiles from a sketch of the activation durations
+    fn from_sketch(sketch: &QuantileSketch) -> Self {
+        Self {
+            p50: sketch.quantile(0.5),
+            p90: sketch.quantile(0.9),
+            p99: sketch.quantile(0.99),
+            p999: sketch.quantile(0.999),
+        }
+    }
+}
+
 pub struct OperatorStatsRelations<S>
 where
     S: Scope<Timestamp = Time>,
 {
     pub summarized: Collection<S, (OpKey, Summation), Diff>,
+    pub activation_percentiles: Collection<S, (OpKey, ActivationPercentiles), Diff>,
     pub aggregated_summaries: Collection<S, (OperatorId, Summation), Diff>,
     pub arrangements: Option<Collection<S, (OpKey, ArrangementStats), Diff>>,
     pub aggregated_arrangements: Option<Collection<S, (OperatorId, ArrangementStats), Diff>>,
@@ -60,12 +99,31 @@ pub fn operator_stats<S>(
     scope: &mut S,
     activation_times: &ActivationTimes<S>,
     differential_stream: Option<&Stream<S, DifferentialLogBundle>>,
+    salts: Option<NonZeroUsize>,
 ) -> OperatorStatsRelations<S>
 where
     S: Scope<Timestamp = Time>,
 {
-    let summarized =
-        summation(&activation_times.map(|(operator, (_start, duration))| (operator, duration)));
+    let durations = activation_times.map(|(operator, (_start, duration))| (operator, duration));
+
+    // Salting each operator by its activations' start times spreads the
+    // activations of hot operators across workers before they're merged
+    let (summarized, activation_percentiles) =
+        match salts.map(NonZeroUsize::get).filter(|&salts| salts > 1) {
+            Some(salts) => {
+                let salts = salts as u64;
+                let salted = activation_times.map(move |(operator, (start, duration))| {
+                    ((operator, start.hashed() % salts), duration)
+                });
+
+                (
+                    merge_salted(&summation(&salted)),
+                    salted_percentiles(&salted),
+                )
+            }
+            None => (summation(&durations), sketched_percentiles(&durations)),
+        };
+
     let (arrangements, spline_levels) = if let Some(stream) = differential_stream {
         let (arranged, splines) = differential::arrangement_stats(scope, stream);
 
@@ -84,6 +142,7 @@ where
                     average,
                     total,
                     count,
+                    ..
                 },
             )| {
                 #[cfg(not(feature = "timely-next"))]
@@ -164,16 +223,22 @@ where
                 .checked_div(total_workers as u32)
                 .unwrap_or_else(|| Duration::from_secs(0));
 
-            let stats = Summation {
-                max: max.to_duration(),
-                min: min.to_duration(),
-                average,
+            let stats = Summation::new(
+                max.to_duration(),
+                min.to_duration(),
                 total,
-                count: activations as usize,
-            };
+                average,
+                activations as usize,
+            );
 
             (operator, stats)
-        });
+        })
+        // The deviation is taken over every worker's activations of the operator, pooled
+        // from each worker's summation so that no single reduction sees all of them
+        .join_map(
+            &merge_summations(&summarized.map(|((_worker, operator), stats)| (operator, stats))),
+            |&operator, stats, merged| (operator, stats.with_stddev(merged.stddev)),
+        );
 
     let aggregated_arrangements = arrangements.as_ref().map(|arrangements| {
         arrangements
@@ -183,7 +248,10 @@ where
                     1,
                     DiffPair::new(
                         Max::new(stats.max_size as isize),
-                        DiffPair::new(Min::new(stats.min_size as isize), stats.batches as isize),
+                        DiffPair::new(
+                            Min::new(stats.min_size as isize),
+                            DiffPair::new(stats.batches as isize, stats.merges as isize),
+                        ),
                     ),
                 );
                 #[cfg(feature = "timely-next")]
@@ -191,7 +259,10 @@ where
                     1,
                     (
                         Max::new(stats.max_size as isize),
-                        (Min::new(stats.min_size as isize), stats.batches as isize),
+                        (
+                            Min::new(stats.min_size as isize),
+                            (stats.batches as isize, stats.merges as isize),
+                        ),
                     ),
                 );
 
@@ -210,7 +281,11 @@ where
                                 element2:
                                     DiffPair {
                                         element1: Min { value: min_size },
-                                        element2: batches,
+                                        element2:
+                                            DiffPair {
+                                                element1: batches,
+                                                element2: merges,
+                                            },
                                     },
                             },
                     },
@@ -218,11 +293,17 @@ where
                 #[cfg(feature = "timely-next")]
                 let (
                     operator,
-                    (total_workers, (Max { value: max_size }, (Min { value: min_size }, batches))),
+                    (
+                        total_workers,
+                        (Max { value: max_size }, (Min { value: min_size }, (batches, merges))),
+                    ),
                 ) = x;
                 let average_batches = (batches as usize)
                     .checked_div(total_workers as usize)
                     .unwrap_or(0);
+                let average_merges = (merges as usize)
+                    .checked_div(total_workers as usize)
+                    .unwrap_or(0);
 
                 (
                     operator,
@@ -230,6 +311,7 @@ where
                         max_size: max_size as usize,
                         min_size: min_size as usize,
                         batches: average_batches,
+                        merges: average_merges,
                     },
                 )
             })
@@ -237,6 +319,7 @@ where
 
     OperatorStatsRelations {
         summarized,
+        activation_percentiles,
         aggregated_summaries,
         arrangements,
         aggregated_arrangements,
@@ -244,6 +327,253 @@ where
     }
 }
 
+/// Estimates the percentiles of each key's durations from a sketch that's
+/// updated incrementally instead of holding onto every duration
+pub(crate) fn sketched_percentiles<S, K>(
+    durations: &Collection<S, (K, Duration), Diff>,
+) -> Collection<S, (K, ActivationPercentiles), Diff>
+where
+    S: Scope<Timestamp = Time>,
+    K: ExchangeData + Hashable,
+{
+    durations
+        .explode(|(key, duration)| iter::once((key, QuantileSketch::new(duration))))
+        .count_total()
+        .map(|(key, sketch)| (key, ActivationPercentiles::from_sketch(&sketch)))
+}
+
+/// Estimates the percentiles of each key's durations after they've been split
+/// across several salts, each salt is sketched on its own before the sketches
+/// of every salt are merged
+fn salted_percentiles<S, K>(
+    salted: &Collection<S, ((K, u64), Duration), Diff>,
+) -> Collection<S, (K, ActivationPercentiles), Diff>
+where
+    S: Scope<Timestamp = Time>,
+    K: ExchangeData + Hashable,
+{
+    salted
+        .explode(|(key, duration)| iter::once((key, QuantileSketch::new(duration))))
+        .count_total()
+        .explode(|((key, _salt), sketch)| iter::once((key, sketch)))
+        .count_total()
+        .map(|(key, sketch)| (key, ActivationPercentiles::from_sketch(&sketch)))
+}
+
+/// Counts each operator's activations within each of the buckets delimited by
+/// the sorted `bounds`, bucket `i` holds the activations shorter than `bounds[i]`
+/// and the final bucket holds every activation that's longer than all of them
+pub fn activation_histograms<S>(
+    activation_times: &ActivationTimes<S>,
+    bounds: Vec<Duration>,
+) -> Collection<S, (OpKey, (usize, usize)), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    activation_times
+        .map(move |(operator, (_start, duration))| {
+            let bucket = bounds
+                .iter()
+                .take_while(|&&bound| bound <= duration)
+                .count();
+            (operator, bucket)
+        })
+        .count_total()
+        .map(|((operator, bucket), count)| (operator, (bucket, count as usize)))
+}
+
+/// Flags the activations that took longer than `threshold` times their operator's
+/// median activation time as `(start, (duration, median))`
+///
+/// The medians are estimated incrementally, so activations that were flagged
+/// early on can be retracted again as more of an operator's activations arrive
+pub fn activation_anomalies<S>(
+    activation_times: &ActivationTimes<S>,
+    percentiles: &Collection<S, (OpKey, ActivationPercentiles), Diff>,
+    threshold: f64,
+) -> Collection<S, (OpKey, (Duration, (Duration, Duration))), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    activation_times
+        .join_map(percentiles, |&operator, &(start, duration), percentiles| {
+            (operator, (start, (duration, percentiles.p50)))
+        })
+        .filter(move |&(_, (_, (duration, median)))| {
+            duration.as_secs_f64() > median.as_secs_f64() * threshold
+        })
+}
+
+/// The time that each operator's last activation finished at
+pub fn last_activations<S>(
+    activation_times: &ActivationTimes<S>,
+) -> Collection<S, (OpKey, Duration), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    activation_times
+        .explode(|(operator, (start, duration))| {
+            iter::once((operator, Max::new(DiffDuration::new(start + duration))))
+        })
+        .count_total()
+        .map(|(operator, Max { value: finished })| (operator, finished.to_duration()))
+}
+
+/// Buckets each operator's activations into windows of `interval` by their
+/// start times, counting the activations and summing the time spent within
+/// each window as `(window start, (activations, time spent))`
+pub fn activation_rates<S>(
+    activation_times: &ActivationTimes<S>,
+    interval: Duration,
+) -> Collection<S, (OpKey, (Duration, (usize, Duration))), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    let interval = interval.as_nanos().max(1);
+
+    activation_times
+        .explode(move |(operator, (start, duration))| {
+            let window = (start.as_nanos() / interval * interval) as u64;
+
+            #[cfg(not(feature = "timely-next"))]
+            let diff = DiffPair::new(1, DiffDuration::new(duration));
+            #[cfg(feature = "timely-next")]
+            let diff = (1, DiffDuration::new(duration));
+
+            iter::once(((operator, window), diff))
+        })
+        .count_total()
+        .map(|x| {
+            #[cfg(not(feature = "timely-next"))]
+            let (
+                (operator, window),
+                DiffPair {
+                    element1: activations,
+                    element2: busy,
+                },
+            ) = x;
+            #[cfg(feature = "timely-next")]
+            let ((operator, window), (activations, busy)) = x;
+
+            (
+                operator,
+                (
+                    Duration::from_nanos(window),
+                    (activations as usize, busy.to_duration()),
+                ),
+            )
+        })
+}
+
+/// Keeps the first `limit` activations of each operator, the rest are only
+/// reflected within the operator's summaries and percentiles
+///
+/// Every activation of an operator is produced on the same worker, so the
+/// activations don't need to be exchanged before they're counted
+pub fn cap_activations<S>(activations: &ActivationTimes<S>, limit: usize) -> ActivationTimes<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    activations
+        .inner
+        .unary(
+            Pipeline,
+            "Cap Exact Activations",
+            move |_capability, _info| {
+                let mut seen: HashMap<OpKey, usize, XXHasher> = HashMap::default();
+                let mut buffer = Vec::new();
+
+                move |input, output| {
+                    input.for_each(|capability, data| {
+                        data.swap(&mut buffer);
+
+                        output
+                            .session(&capability)
+                            .give_iterator(buffer.drain(..).filter(|&((operator, _), _, _)| {
+                                let seen = seen.entry(operator).or_insert(0);
+                                *seen += 1;
+
+                                *seen <= limit
+                            }));
+                    });
+                }
+            },
+        )
+        .as_collection()
+}
+
+/// Merges the stats of each operator across every worker, matching operators
+/// up by their address
+pub fn addr_summaries<S>(
+    summarized: &Collection<S, (OpKey, Summation), Diff>,
+    operator_addrs: &ArrangedVal<S, OpKey, OperatorAddr>,
+) -> Collection<S, (OperatorAddr, Summation), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    summarized
+        .join_core(operator_addrs, |_, stats, addr| {
+            iter::once((addr.clone(), *stats))
+        })
+        .reduce_named(
+            "Reduce: Summaries by Address",
+            |_addr, summaries, output| {
+                let merged = summaries
+                    .iter()
+                    .skip(1)
+                    .fold(*summaries[0].0, |merged, &(stats, _)| merged.merge(stats));
+
+                output.push((merged, 1));
+            },
+        )
+}
+
+/// The number of bits that each level of the top-k reduction removes from
+/// the operators' buckets
+const TOP_K_BUCKET_BITS: u32 = 4;
+/// The number of levels within the top-k reduction, operators start out
+/// hashed across `2^(bits * levels)` buckets
+const TOP_K_LEVELS: u32 = 4;
+
+/// Keeps the `k` operators with the largest total activation time
+///
+/// Operators are hashed into buckets that each keep their own `k` slowest, and
+/// the buckets are repeatedly merged until a single bucket is left. This keeps
+/// each reduction small so that the leaderboard is cheap to update incrementally
+/// as the operators' stats change instead of only after everything is aggregated
+pub fn slowest_operators<S>(
+    aggregated_summaries: &Collection<S, (OperatorId, Summation), Diff>,
+    k: usize,
+) -> Collection<S, (OperatorId, Summation), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    let mut leaders = aggregated_summaries.map(|(operator, stats)| {
+        let bucket = operator.hashed() & ((1 << (TOP_K_BUCKET_BITS * TOP_K_LEVELS)) - 1);
+        (bucket, (operator, stats))
+    });
+
+    for _ in 0..TOP_K_LEVELS {
+        leaders = leaders
+            .map(|(bucket, leader)| (bucket >> TOP_K_BUCKET_BITS, leader))
+            .reduce_named(
+                "Reduce: Top-K Slowest Operators",
+                move |_bucket, candidates, output| {
+                    let mut candidates: Vec<_> = candidates
+                        .iter()
+                        .filter(|&&(_, diff)| diff > 0)
+                        .map(|&(leader, _)| leader)
+                        .collect();
+                    candidates.sort_by_key(|&&(operator, stats)| (Reverse(stats.total), operator));
+
+                    output.extend(candidates.into_iter().take(k).map(|&leader| (leader, 1)));
+                },
+            );
+    }
+
+    leaders.map(|(_bucket, leader)| leader)
+}
+
 /*
 pub(crate) fn aggregate_operator_stats<S>(
     operator_stats: &Collection<S, ((WorkerId, OperatorId), OperatorStats), Diff>,
@@ -433,3 +763,156 @@ where
     })
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::{operator_stats, ActivationPercentiles};
+    use crate::dataflow::{
+        operators::{CrossbeamExtractor, CrossbeamPusher},
+        quantile_sketch::QuantileSketch,
+        summation::Summation,
+        utils::{OpKey, Time},
+    };
+    use ddshow_types::{OperatorId, WorkerId};
+    use differential_dataflow::input::Input;
+    use std::{fmt::Debug, num::NonZeroUsize, time::Duration};
+    use timely::dataflow::operators::Capture;
+
+    type Stats = (
+        Vec<(OpKey, Summation)>,
+        Vec<(OpKey, ActivationPercentiles)>,
+        Vec<(OperatorId, Summation)>,
+    );
+
+    fn run_operator_stats(salts: Option<NonZeroUsize>) -> Stats {
+        let (summarized_send, summarized_recv) = crossbeam_channel::unbounded();
+        let (percentiles_send, percentiles_recv) = crossbeam_channel::unbounded();
+        let (aggregated_send, aggregated_recv) = crossbeam_channel::unbounded();
+
+        timely::execute_directly(move |worker| {
+            let mut activations = worker.dataflow::<Time, _, _>(|scope| {
+                let (input, activation_times) = scope.new_collection();
+                let stats = operator_stats(scope, &activation_times, None, salts);
+
+                stats
+                    .summarized
+                    .inner
+                    .capture_into(CrossbeamPusher::new(summarized_send));
+                stats
+                    .activation_percentiles
+                    .inner
+                    .capture_into(CrossbeamPusher::new(percentiles_send));
+                stats
+                    .aggregated_summaries
+                    .inner
+                    .capture_into(CrossbeamPusher::new(aggregated_send));
+
+                input
+            });
+
+            for worker_id in 0..2 {
+                for activation in 0..300_u64 {
+                    let operator = (
+                        WorkerId::new(worker_id),
+                        OperatorId::new(activation as usize % 3),
+                    );
+                    let start = Duration::from_micros(activation * 10);
+                    let duration = Duration::from_micros((activation * 7919) % 1000 + 1);
+
+                    activations.insert((operator, (start, duration)));
+                }
+            }
+            activations.advance_to(Duration::from_secs(1));
+        });
+
+        let sorted = |mut data: Vec<_>| {
+            data.sort_unstable();
+            data
+        };
+        (
+            sorted(CrossbeamExtractor::new(summarized_recv).extract_all()),
+            sorted(CrossbeamExtractor::new(percentiles_recv).extract_all()),
+            sorted(CrossbeamExtractor::new(aggregated_recv).extract_all()),
+        )
+    }
+
+    /// Pooled deviations can be off from the directly computed ones by rounding
+    fn assert_same_summaries<K>(salted: &[(K, Summation)], unsalted: &[(K, Summation)])
+    where
+        K: Debug + PartialEq,
+    {
+        assert_eq!(salted.len(), unsalted.len());
+        for ((salted_key, salted), (unsalted_key, unsalted)) in salted.iter().zip(unsalted) {
+            assert_eq!(salted_key, unsalted_key);
+            assert_eq!(
+                Summation::new(
+                    salted.max,
+                    salted.min,
+                    salted.total,
+                    salted.average,
+                    salted.count,
+                ),
+                Summation::new(
+                    unsalted.max,
+                    unsalted.min,
+                    unsalted.total,
+                    unsalted.average,
+                    unsalted.count,
+                ),
+            );
+            assert!((salted.stddev.as_secs_f64() - unsalted.stddev.as_secs_f64()).abs() < 1e-6);
+            assert!((salted.stderr.as_secs_f64() - unsalted.stderr.as_secs_f64()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn salted_stats_match_unsalted() {
+        let (summarized, percentiles, aggregated) = run_operator_stats(None);
+        let (salted_summarized, salted_percentiles, salted_aggregated) =
+            run_operator_stats(NonZeroUsize::new(4));
+
+        assert_eq!(summarized.len(), 6);
+        assert_same_summaries(&salted_summarized, &summarized);
+        assert_eq!(salted_percentiles, percentiles);
+        assert_eq!(aggregated.len(), 3);
+        assert_same_summaries(&salted_aggregated, &aggregated);
+    }
+
+    #[test]
+    fn sketched_percentiles() {
+        let within_a_percent = |estimate: Duration, exact: Duration| {
+            (estimate.as_secs_f64() - exact.as_secs_f64()).abs() <= exact.as_secs_f64() * 0.01
+        };
+
+        let sketch = (1..=1000)
+            .map(|micros| QuantileSketch::new(Duration::from_micros(micros)))
+            .fold(QuantileSketch::default(), |sketch, single| sketch + single);
+
+        let percentiles = ActivationPercentiles::from_sketch(&sketch);
+        assert!(within_a_percent(
+            percentiles.p50,
+            Duration::from_micros(500)
+        ));
+        assert!(within_a_percent(
+            percentiles.p90,
+            Duration::from_micros(900)
+        ));
+        assert!(within_a_percent(
+            percentiles.p99,
+            Duration::from_micros(990)
+        ));
+        assert!(within_a_percent(
+            percentiles.p999,
+            Duration::from_micros(999)
+        ));
+
+        // Repeated durations are counted by their diffs
+        let (fast, slow) = (Duration::from_millis(1), Duration::from_secs(1));
+        let sketch = QuantileSketch::new(fast) * 95 + QuantileSketch::new(slow) * 5;
+
+        let percentiles = ActivationPercentiles::from_sketch(&sketch);
+        assert!(within_a_percent(percentiles.p50, fast));
+        assert!(within_a_percent(percentiles.p90, fast));
+        assert!(within_a_percent(percentiles.p99, slow));
+    }
+}
@@ -25,6 +25,9 @@ use differential_dataflow::difference::Multiply as MulAlias;
 
 //type MulAlias<R> = differential_dataflow::difference::Multiply<R>;
 
+/// Semijoins and antijoins against collections that have already been arranged
+/// by key, saving the arrangement that differential's own `semijoin()` and
+/// `antijoin()` build from their inputs
 pub trait JoinArranged<S, K, V, R>
 where
     S: Scope,
@@ -32,6 +35,7 @@ where
     V: Data,
     R: Semigroup,
 {
+    /// Keeps the updates whose keys are present within `other`
     #[track_caller]
     fn semijoin_arranged<R2, T>(
         &self,
@@ -47,6 +51,7 @@ where
         T::Batch: BatchReader<K, (), S::Timestamp, R2> + 'static,
         T::Cursor: Cursor<K, (), S::Timestamp, R2> + 'static;
 
+    /// Keeps the updates whose keys aren't present within `other`
     #[track_caller]
     fn antijoin_arranged<R2, T>(&self, other: &Arranged<S, T>) -> Collection<S, (K, V), R>
     where
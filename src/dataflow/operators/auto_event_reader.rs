@@ -0,0 +1,403 @@
+use crate::dataflow::operators::{
+    raw_layouts::{LayoutAdapter, RawLayout},
+    BincodeEventReader, EventIterator, RkyvEventReader,
+};
+use abomonation::Abomonation;
+use bincode::Options;
+use bytecheck::CheckBytes;
+use ddshow_types::{Event, WorkerId};
+use rkyv::{
+    check_archived_root, de::deserializers::SharedDeserializeMap,
+    validation::validators::DefaultValidator, AlignedVec, Archive, Deserialize,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    convert::TryInto,
+    fmt::{self, Debug},
+    io::{self, Cursor, Read},
+    marker::PhantomData,
+    mem,
+    time::Duration,
+};
+use timely::dataflow::operators::capture::event::Event as TimelyEvent;
+
+/// The largest frame we'll consider plausible while detecting a stream's encoding,
+/// anything claiming to be larger than this is assumed to not be length-prefixed
+const MAX_DETECTION_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Converts the raw event bundles emitted by the timely & differential
+/// releases that ddshow was built against into ddshow's own event bundles,
+/// the bundles of any other compiled-in [`RawLayout`] are read through
+/// [`LayoutAdapter`]
+pub trait RawEventAdapter<Raw>: LayoutAdapter<Raw> {
+    fn adapt(raw: Raw) -> Self;
+}
+
+impl<E, R> RawEventAdapter<(Duration, usize, R)> for (Duration, WorkerId, E)
+where
+    Self: LayoutAdapter<(Duration, usize, R)>,
+    E: From<R>,
+{
+    fn adapt((time, worker, event): (Duration, usize, R)) -> Self {
+        (time, WorkerId::new(worker), E::from(event))
+    }
+}
+
+/// The stream layouts that an [`AutoEventReader`] is able to detect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedEncoding {
+    /// ddshow's event types encoded with rkyv, as written by `ddshow-sink`
+    Rkyv,
+    /// ddshow's event types encoded with bincode
+    Bincode,
+    /// Timely's raw event types encoded with abomonation, the layout of these
+    /// is tied to the timely & differential releases that emitted them, see
+    /// [`RawLayout`]
+    Abomonation,
+}
+
+impl DetectedEncoding {
+    /// Detects the encoding of a stream from the bytes it started with, returning
+    /// `None` if more bytes are required to make a decision
+    ///
+    /// Both rkyv and bincode streams start with a length-prefixed frame, so we
+    /// check whether the first frame successfully validates as either of them.
+    /// Streams that don't look like either are assumed to be abomonation
+    pub fn detect<T, D>(prefix: &[u8], peer_finished: bool) -> Option<Self>
+    where
+        T: Archive + DeserializeOwned,
+        T::Archived: for<'a> CheckBytes<DefaultValidator<'a>>,
+        D: Archive + DeserializeOwned,
+        D::Archived: for<'a> CheckBytes<DefaultValidator<'a>>,
+    {
+        let mut pending = false;
+
+        // Rkyv frames are prefixed by a `u128` length
+        match prefix.get(..mem::size_of::<u128>()) {
+            Some(header) => {
+                let length = u128::from_le_bytes(header.try_into().unwrap());
+
+                if length <= MAX_DETECTION_FRAME_LEN as u128 {
+                    let start = mem::size_of::<u128>();
+
+                    match prefix.get(start..start + length as usize) {
+                        Some(archive) => {
+                            let mut aligned = AlignedVec::with_capacity(archive.len());
+                            aligned.extend_from_slice(archive);
+
+                            if check_archived_root::<Event<T, D>>(&aligned).is_ok() {
+                                return Some(Self::Rkyv);
+                            }
+                        }
+                        None => pending = true,
+                    }
+                }
+            }
+            None => pending = true,
+        }
+
+        // Bincode frames are prefixed by a `u64` length
+        if let Some(header) = prefix.get(..mem::size_of::<u64>()) {
+            let length = u64::from_le_bytes(header.try_into().unwrap());
+
+            if length <= MAX_DETECTION_FRAME_LEN as u64 {
+                let start = mem::size_of::<u64>();
+
+                match prefix.get(start..start + length as usize) {
+                    Some(encoded) => {
+                        // The frame must be consumed exactly, otherwise it's a
+                        // coincidence that the prefix happened to decode
+                        let is_bincode = bincode::DefaultOptions::new()
+                            .with_fixint_encoding()
+                            .reject_trailing_bytes()
+                            .deserialize::<Event<T, D>>(encoded)
+                            .is_ok();
+
+                        if is_bincode {
+                            return Some(Self::Bincode);
+                        }
+                    }
+                    None => pending = true,
+                }
+            }
+        }
+
+        if pending && !peer_finished {
+            None
+        } else {
+            Some(Self::Abomonation)
+        }
+    }
+}
+
+/// A reader that detects the encoding of the stream it wraps from the stream's
+/// first few bytes, producing ddshow's event types no matter which encoding
+/// the source used
+pub struct AutoEventReader<T, D, A, R> {
+    state: ReaderState<T, D, R>,
+    encoding: Option<DetectedEncoding>,
+    layout: Option<RawLayout>,
+    __type: PhantomData<fn(A)>,
+}
+
+enum ReaderState<T, D, R> {
+    Detecting {
+        reader: R,
+        bytes: Vec<u8>,
+        buffer: Vec<u8>,
+        peer_finished: bool,
+    },
+    Typed(Box<dyn EventIterator<T, D> + Send + 'static>),
+    Finished,
+}
+
+impl<T, D, A, R> AutoEventReader<T, D, A, R> {
+    /// Allocates a new `AutoEventReader` wrapping a supplied reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            state: ReaderState::Detecting {
+                reader,
+                bytes: vec![0u8; 1 << 16],
+                buffer: Vec::new(),
+                peer_finished: false,
+            },
+            encoding: None,
+            layout: None,
+            __type: PhantomData,
+        }
+    }
+
+    /// Returns the stream's encoding if it's been detected
+    pub const fn encoding(&self) -> Option<DetectedEncoding> {
+        self.encoding
+    }
+
+    /// Returns the layout of the stream's raw events if it's been
+    /// detected as abomonation
+    pub const fn layout(&self) -> Option<RawLayout> {
+        self.layout
+    }
+}
+
+impl<T, D, A, R> EventIterator<T, D> for AutoEventReader<T, D, A, R>
+where
+    R: Read + Send + 'static,
+    T: Archive + Abomonation + DeserializeOwned + Clone + Send + 'static,
+    T::Archived: Deserialize<T, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+    D: Archive + DeserializeOwned + RawEventAdapter<A> + Send + 'static,
+    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+    A: Abomonation + Clone + Send + 'static,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<TimelyEvent<T, D>>> {
+        let (encoding, layout) = match &mut self.state {
+            ReaderState::Typed(reader) => return reader.next(is_finished, bytes_read),
+
+            ReaderState::Finished => {
+                *is_finished = true;
+                return Ok(None);
+            }
+
+            ReaderState::Detecting {
+                reader,
+                bytes,
+                buffer,
+                peer_finished,
+            } => {
+                if let Ok(len) = reader.read(&mut bytes[..]) {
+                    *bytes_read += len;
+                    if len == 0 {
+                        *peer_finished = true;
+                    }
+
+                    buffer.extend_from_slice(&bytes[..len]);
+                }
+
+                // The stream ended without sending us anything
+                if *peer_finished && buffer.is_empty() {
+                    self.state = ReaderState::Finished;
+                    *is_finished = true;
+
+                    return Ok(None);
+                }
+
+                match DetectedEncoding::detect::<T, D>(buffer, *peer_finished) {
+                    Some(DetectedEncoding::Abomonation) => {
+                        match RawLayout::detect::<T, D, A>(buffer, *peer_finished) {
+                            Some(layout) => (DetectedEncoding::Abomonation, Some(layout)),
+                            None => return Ok(None),
+                        }
+                    }
+                    Some(encoding) => (encoding, None),
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let (reader, buffer) = match mem::replace(&mut self.state, ReaderState::Finished) {
+            ReaderState::Detecting { reader, buffer, .. } => (reader, buffer),
+            _ => unreachable!("only detecting readers can detect an encoding"),
+        };
+        tracing::debug!(
+            encoding = ?encoding,
+            layout = ?layout,
+            buffered = buffer.len(),
+            "detected the encoding of a stream",
+        );
+
+        // Replay the bytes consumed during detection before the rest of the stream
+        let stream = Box::new(Cursor::new(buffer).chain(reader)) as Box<dyn Read + Send + 'static>;
+        self.encoding = Some(encoding);
+        self.layout = layout;
+        self.state = match (encoding, layout) {
+            (DetectedEncoding::Rkyv, _) => {
+                ReaderState::Typed(Box::new(RkyvEventReader::new(stream)))
+            }
+            (DetectedEncoding::Bincode, _) => {
+                ReaderState::Typed(Box::new(BincodeEventReader::new(stream)))
+            }
+            (DetectedEncoding::Abomonation, layout) => {
+                let layout = layout.unwrap_or(RawLayout::Native);
+
+                match <D as LayoutAdapter<A>>::layout_reader::<T>(layout, stream) {
+                    Some(reader) => ReaderState::Typed(reader),
+                    None => {
+                        tracing::error!(
+                            layout = ?layout,
+                            "detected a raw layout that has no reader for the stream's events",
+                        );
+
+                        *is_finished = true;
+                        ReaderState::Finished
+                    }
+                }
+            }
+        };
+
+        Ok(None)
+    }
+}
+
+impl<T, D, A, R> Debug for AutoEventReader<T, D, A, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoEventReader")
+            .field("encoding", &self.encoding())
+            .field("layout", &self.layout())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DetectedEncoding;
+    use crate::dataflow::operators::{AutoEventReader, EventIterator, RawLayout};
+    use ddshow_sink::EventWriter;
+    use ddshow_types::{
+        timely_logging::{InputEvent, StartStop, TimelyEvent},
+        Event, WorkerId,
+    };
+    use std::{io::Cursor, time::Duration};
+    use timely::{
+        dataflow::operators::capture::{Event as RawEvent, EventPusher},
+        logging::{
+            InputEvent as RawInputEvent, StartStop as RawStartStop, TimelyEvent as RawTimelyEvent,
+        },
+    };
+
+    type Bundle = (Duration, WorkerId, TimelyEvent);
+    type RawBundle = (Duration, usize, RawTimelyEvent);
+
+    fn events() -> Vec<RawEvent<Duration, Bundle>> {
+        vec![
+            RawEvent::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    WorkerId::new(0),
+                    TimelyEvent::Input(InputEvent::new(StartStop::start())),
+                )],
+            ),
+            RawEvent::Progress(vec![(Duration::from_secs(1), 1)]),
+        ]
+    }
+
+    fn detect(bytes: &[u8], peer_finished: bool) -> Option<DetectedEncoding> {
+        DetectedEncoding::detect::<Duration, Bundle>(bytes, peer_finished)
+    }
+
+    #[test]
+    fn detect_rkyv() {
+        let mut bytes = Vec::new();
+        {
+            let mut writer = EventWriter::new(&mut bytes);
+            for event in events() {
+                writer.push(event);
+            }
+        }
+
+        assert_eq!(detect(&bytes[..8], false), None);
+        assert_eq!(detect(&bytes, false), Some(DetectedEncoding::Rkyv));
+
+        let mut reader = AutoEventReader::<Duration, Bundle, RawBundle, _>::new(Cursor::new(bytes));
+        assert_eq!(reader.take_events().unwrap(), events());
+        assert_eq!(reader.encoding(), Some(DetectedEncoding::Rkyv));
+    }
+
+    #[test]
+    fn detect_bincode() {
+        let mut bytes = Vec::new();
+        for event in events() {
+            let encoded = bincode::serialize(&Event::from(event)).unwrap();
+            bytes.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&encoded);
+        }
+
+        assert_eq!(detect(&bytes, false), Some(DetectedEncoding::Bincode));
+
+        let mut reader = AutoEventReader::<Duration, Bundle, RawBundle, _>::new(Cursor::new(bytes));
+        assert_eq!(reader.take_events().unwrap(), events());
+        assert_eq!(reader.encoding(), Some(DetectedEncoding::Bincode));
+    }
+
+    #[test]
+    fn implausible_lengths_are_abomonation() {
+        let bytes = [0xFF; 32];
+        assert_eq!(detect(&bytes, false), Some(DetectedEncoding::Abomonation));
+
+        // Short streams that end before we can decide fall back to abomonation
+        assert_eq!(detect(&bytes[..4], false), None);
+        assert_eq!(
+            detect(&bytes[..4], true),
+            Some(DetectedEncoding::Abomonation)
+        );
+    }
+
+    #[test]
+    fn detect_abomonation() {
+        let mut bytes = Vec::new();
+        {
+            let mut writer = timely::dataflow::operators::capture::EventWriter::new(&mut bytes);
+            writer.push(RawEvent::<Duration, RawBundle>::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    0,
+                    RawTimelyEvent::Input(RawInputEvent {
+                        start_stop: RawStartStop::Start,
+                    }),
+                )],
+            ));
+            writer.push(RawEvent::Progress(vec![(Duration::from_secs(1), 1)]));
+        }
+
+        assert_eq!(detect(&bytes, true), Some(DetectedEncoding::Abomonation));
+
+        let mut reader = AutoEventReader::<Duration, Bundle, RawBundle, _>::new(Cursor::new(bytes));
+        assert_eq!(reader.take_events().unwrap(), events());
+        assert_eq!(reader.encoding(), Some(DetectedEncoding::Abomonation));
+        assert_eq!(reader.layout(), Some(RawLayout::Native));
+    }
+}
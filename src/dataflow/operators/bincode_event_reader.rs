@@ -0,0 +1,224 @@
+use crate::dataflow::{constants::MAX_EVENT_FRAME_LEN, operators::EventIterator};
+use ddshow_types::Event;
+use serde::de::DeserializeOwned;
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt::{self, Debug},
+    io::{self, Read},
+    marker::PhantomData,
+    mem,
+};
+use timely::dataflow::operators::capture::event::Event as TimelyEvent;
+
+/// A Wrapper for `R: Read` implementing `EventIterator<T, D>` over bincode-encoded events
+///
+/// Each event is framed as a little-endian `u64` holding the length of the
+/// encoded event followed by the bincode-encoded [`Event<T, D>`] itself
+pub struct BincodeEventReader<T, D, R> {
+    reader: R,
+    bytes: Vec<u8>,
+    buffer: Vec<u8>,
+    consumed: usize,
+    peer_finished: bool,
+    __type: PhantomData<(T, D)>,
+}
+
+impl<T, D, R> BincodeEventReader<T, D, R> {
+    /// Allocates a new `BincodeEventReader` wrapping a supplied reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            bytes: vec![0u8; 1 << 20],
+            buffer: Vec::new(),
+            consumed: 0,
+            peer_finished: false,
+            __type: PhantomData,
+        }
+    }
+}
+
+impl<T, D, R> EventIterator<T, D> for BincodeEventReader<T, D, R>
+where
+    R: Read,
+    T: DeserializeOwned,
+    D: DeserializeOwned,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<TimelyEvent<T, D>>> {
+        let remaining = &self.buffer[self.consumed..];
+
+        if let Some(header_slice) = remaining.get(..mem::size_of::<u64>()) {
+            let event_length = header_slice
+                .try_into()
+                .expect("the slice is the length of a u64");
+            let event_length = u64::from_le_bytes(event_length);
+            let event_start = mem::size_of::<u64>();
+
+            // Corrupted headers can claim any length, so they're rejected
+            // rather than waiting forever on bytes that will never arrive
+            let event_end = usize::try_from(event_length)
+                .ok()
+                .filter(|&length| length <= MAX_EVENT_FRAME_LEN)
+                .and_then(|length| event_start.checked_add(length))
+                .ok_or_else(|| {
+                    tracing::error!(
+                        type_name = std::any::type_name::<Event<T, D>>(),
+                        consumed = self.consumed,
+                        event_length = event_length,
+                        "bincode event frame is larger than the maximum of {} bytes",
+                        MAX_EVENT_FRAME_LEN,
+                    );
+
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "bincode event frame of {} bytes is larger than the maximum of {} bytes",
+                            event_length, MAX_EVENT_FRAME_LEN,
+                        ),
+                    )
+                })?;
+
+            if let Some(slice) = remaining.get(event_start..event_end) {
+                let event: Event<T, D> = bincode::deserialize(slice).map_err(|err| {
+                    tracing::error!(
+                        type_name = std::any::type_name::<Event<T, D>>(),
+                        consumed = self.consumed,
+                        event_length = event_length,
+                        "failed to deserialize bincode event: {:?}",
+                        err,
+                    );
+
+                    io::Error::new(io::ErrorKind::InvalidData, err)
+                })?;
+
+                self.consumed += event_end;
+                return Ok(Some(TimelyEvent::from(event)));
+            }
+        }
+
+        if self.peer_finished {
+            if self.consumed != self.buffer.len() {
+                tracing::warn!(
+                    type_name = std::any::type_name::<Event<T, D>>(),
+                    trailing_bytes = self.buffer.len() - self.consumed,
+                    "bincode stream ended with a partial event",
+                );
+            }
+
+            // Perform some cleanup so that there's less work to be
+            // done when everything drops
+            if !*is_finished {
+                self.bytes = Vec::new();
+                self.buffer = Vec::new();
+                self.consumed = 0;
+            }
+
+            *is_finished = true;
+            return Ok(None);
+        }
+
+        // If we've exhausted our buffered data shift the unconsumed tail
+        // back to the start of the buffer
+        if self.consumed != 0 {
+            self.buffer.drain(..self.consumed);
+            self.consumed = 0;
+        }
+
+        if let Ok(len) = self.reader.read(&mut self.bytes[..]) {
+            *bytes_read += len;
+            if len == 0 {
+                self.peer_finished = true;
+            }
+
+            self.buffer.extend_from_slice(&self.bytes[..len]);
+        }
+
+        Ok(None)
+    }
+}
+
+impl<T, D, R> Debug for BincodeEventReader<T, D, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BincodeEventReader")
+            .field("buffer_len", &self.buffer.len())
+            .field("consumed", &self.consumed)
+            .field("peer_finished", &self.peer_finished)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataflow::operators::{BincodeEventReader, EventIterator};
+    use ddshow_types::{
+        timely_logging::{InputEvent, StartStop, TimelyEvent},
+        Event, WorkerId,
+    };
+    use std::{
+        io::{Cursor, ErrorKind},
+        time::Duration,
+    };
+    use timely::dataflow::operators::capture::event::Event as RawEvent;
+
+    type Bundle = (Duration, WorkerId, TimelyEvent);
+
+    #[test]
+    fn roundtrip() {
+        let events: Vec<Event<Duration, Bundle>> = vec![
+            Event::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    WorkerId::new(0),
+                    TimelyEvent::Input(InputEvent::new(StartStop::start())),
+                )],
+            ),
+            Event::Progress(vec![(Duration::from_secs(1), 1)]),
+        ];
+
+        let mut bytes = Vec::new();
+        for event in events.iter() {
+            let encoded = bincode::serialize(event).unwrap();
+            bytes.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&encoded);
+        }
+
+        let mut reader = BincodeEventReader::<Duration, Bundle, _>::new(Cursor::new(bytes));
+        let replayed = reader.take_events().unwrap();
+        assert_eq!(replayed.len(), 2);
+
+        let messages: Vec<_> = replayed
+            .into_iter()
+            .filter_map(|event| match event {
+                RawEvent::Messages(_, data) => Some(data),
+                RawEvent::Progress(_) => None,
+            })
+            .flatten()
+            .map(|(_, _, event)| event)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![TimelyEvent::Input(InputEvent::new(StartStop::start()))],
+        );
+    }
+
+    #[test]
+    fn oversized_frames_are_invalid() {
+        let mut bytes = u64::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 32]);
+
+        let mut reader = BincodeEventReader::<Duration, Bundle, _>::new(Cursor::new(bytes));
+        let (mut is_finished, mut bytes_read) = (false, 0);
+
+        let err = loop {
+            match reader.next(&mut is_finished, &mut bytes_read) {
+                Ok(_) => assert!(!is_finished, "corrupt frames should error"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    args::BufferPolicy,
+    dataflow::operators::{DroppedEvents, EventIterator},
+};
+use crossbeam_channel::{Receiver, Sender, TryRecvError, TrySendError};
+use std::{
+    fmt::{self, Debug},
+    io, mem,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use timely::dataflow::operators::capture::event::Event as TimelyEvent;
+
+/// How long the ingestion thread sleeps for when its reader has nothing for it
+const INGEST_IDLE_DELAY: Duration = Duration::from_millis(1);
+
+type BoxedReader<T, D> = Box<dyn EventIterator<T, D> + Send + 'static>;
+type Buffered<T, D> = io::Result<TimelyEvent<T, D>>;
+
+/// Reads events from a source on a dedicated thread, handing them off to the
+/// replay operator through a channel of bounded capacity
+///
+/// When the channel fills up the reader either blocks, pushing backpressure
+/// onto the target through its tcp connection, or drops the message batches
+/// it can't fit and counts them. Progress updates are never dropped since
+/// the replay operator's capabilities depend on them
+pub struct BufferedReader<T, D> {
+    receiver: Receiver<Buffered<T, D>>,
+    bytes_read: Arc<AtomicUsize>,
+    closed: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<T, D> BufferedReader<T, D>
+where
+    T: Send + 'static,
+    D: Send + 'static,
+{
+    /// Spawns the thread that `reader` will be read from
+    pub fn spawn(
+        name: String,
+        reader: BoxedReader<T, D>,
+        capacity: usize,
+        policy: BufferPolicy,
+        dropped: DroppedEvents,
+    ) -> io::Result<Self> {
+        let (sender, receiver) = crossbeam_channel::bounded(capacity);
+        let (bytes_read, closed) = (
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicBool::new(false)),
+        );
+
+        let handle = {
+            let (bytes_read, closed) = (bytes_read.clone(), closed.clone());
+
+            thread::Builder::new()
+                .name(name)
+                .spawn(move || ingest(reader, sender, policy, dropped, &bytes_read, &closed))?
+        };
+
+        Ok(Self {
+            receiver,
+            bytes_read,
+            closed,
+            handle: Some(handle),
+        })
+    }
+}
+
+fn ingest<T, D>(
+    mut reader: BoxedReader<T, D>,
+    sender: Sender<Buffered<T, D>>,
+    policy: BufferPolicy,
+    dropped: DroppedEvents,
+    bytes_read: &AtomicUsize,
+    closed: &AtomicBool,
+) {
+    let mut is_finished = false;
+
+    while !is_finished && !closed.load(Ordering::Acquire) {
+        let mut read = 0;
+        let event = reader.next(&mut is_finished, &mut read);
+        bytes_read.fetch_add(read, Ordering::AcqRel);
+
+        let event = match event {
+            Ok(Some(event)) => Ok(event),
+            Ok(None) => {
+                if read == 0 && !is_finished {
+                    thread::sleep(INGEST_IDLE_DELAY);
+                }
+
+                continue;
+            }
+            Err(err) => {
+                is_finished = true;
+                Err(err)
+            }
+        };
+
+        let sent = match policy {
+            BufferPolicy::Block => sender.send(event).is_ok(),
+
+            BufferPolicy::Drop => match sender.try_send(event) {
+                Ok(()) => true,
+
+                Err(TrySendError::Full(Ok(TimelyEvent::Messages(_, data)))) => {
+                    tracing::trace!("ingestion buffer is full, dropping {} events", data.len());
+                    dropped.add(data.len());
+
+                    true
+                }
+                Err(TrySendError::Full(event)) => sender.send(event).is_ok(),
+
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+        };
+
+        // The replay operator hung up on us
+        if !sent {
+            break;
+        }
+    }
+}
+
+impl<T, D> EventIterator<T, D> for BufferedReader<T, D> {
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<TimelyEvent<T, D>>> {
+        *bytes_read += self.bytes_read.swap(0, Ordering::AcqRel);
+
+        match self.receiver.try_recv() {
+            Ok(event) => event.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => {
+                *is_finished = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<T, D> Drop for BufferedReader<T, D> {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Release);
+
+        // Disconnect the channel to unblock the ingestion thread if
+        // it's waiting on a full buffer
+        drop(mem::replace(&mut self.receiver, crossbeam_channel::never()));
+
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                tracing::error!("an ingestion thread panicked");
+            }
+        }
+    }
+}
+
+impl<T, D> Debug for BufferedReader<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferedReader")
+            .field("buffered", &self.receiver.len())
+            .field("capacity", &self.receiver.capacity())
+            .field("closed", &self.closed.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferedReader;
+    use crate::{
+        args::BufferPolicy,
+        dataflow::{
+            operators::{DroppedEvents, EventIterator},
+            DataflowData,
+        },
+        ui::ProgramStats,
+    };
+    use std::{io, time::Duration};
+    use timely::dataflow::operators::capture::event::Event as TimelyEvent;
+
+    struct Events(Vec<TimelyEvent<Duration, usize>>);
+
+    impl EventIterator<Duration, usize> for Events {
+        fn next(
+            &mut self,
+            is_finished: &mut bool,
+            _bytes_read: &mut usize,
+        ) -> io::Result<Option<TimelyEvent<Duration, usize>>> {
+            if self.0.is_empty() {
+                *is_finished = true;
+                Ok(None)
+            } else {
+                Ok(Some(self.0.remove(0)))
+            }
+        }
+    }
+
+    fn events() -> Vec<TimelyEvent<Duration, usize>> {
+        let mut events = Vec::new();
+        for time in 0..10 {
+            let time = Duration::from_secs(time);
+
+            events.push(TimelyEvent::Messages(time, vec![1, 2, 3]));
+            events.push(TimelyEvent::Progress(vec![
+                (time + Duration::from_secs(1), 1),
+                (time, -1),
+            ]));
+        }
+
+        events
+    }
+
+    #[test]
+    fn blocking_keeps_everything() {
+        let dropped = DroppedEvents::new();
+        let mut reader = BufferedReader::spawn(
+            "blocking".to_owned(),
+            Box::new(Events(events())),
+            1,
+            BufferPolicy::Block,
+            dropped.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(reader.take_events().unwrap(), events());
+        assert_eq!(dropped.count(), 0);
+    }
+
+    #[test]
+    fn dropping_keeps_progress() {
+        let dropped = DroppedEvents::new();
+        let mut reader = BufferedReader::spawn(
+            "dropping".to_owned(),
+            Box::new(Events(events())),
+            1,
+            BufferPolicy::Drop,
+            dropped.clone(),
+        )
+        .unwrap();
+
+        // Let the ingestion thread fill up the buffer
+        std::thread::sleep(Duration::from_millis(100));
+
+        let replayed = reader.take_events().unwrap();
+        let messages: usize = replayed
+            .iter()
+            .map(|event| match event {
+                TimelyEvent::Messages(_, data) => data.len(),
+                TimelyEvent::Progress(_) => 0,
+            })
+            .sum();
+
+        let expected_progress: Vec<_> = events()
+            .into_iter()
+            .filter(|event| matches!(event, TimelyEvent::Progress(_)))
+            .collect();
+        let progress: Vec<_> = replayed
+            .into_iter()
+            .filter(|event| matches!(event, TimelyEvent::Progress(_)))
+            .collect();
+
+        assert_eq!(progress, expected_progress);
+        assert_eq!(messages + dropped.count(), 30);
+    }
+
+    #[test]
+    fn truncated_traces_report_drops() {
+        let time = Duration::from_secs(0);
+        let events = vec![
+            TimelyEvent::Messages(time, vec![1, 2, 3]),
+            TimelyEvent::Messages(time, vec![4, 5]),
+            TimelyEvent::Progress(vec![(time + Duration::from_secs(1), 1), (time, -1)]),
+        ];
+
+        let dropped = DroppedEvents::new();
+        let mut reader = BufferedReader::spawn(
+            "truncated".to_owned(),
+            Box::new(Events(events)),
+            1,
+            BufferPolicy::Drop,
+            dropped.clone(),
+        )
+        .unwrap();
+
+        // The first batch fills the buffer and nothing is replayed until the
+        // second one has been dropped, so the second batch can never fit
+        while dropped.count() == 0 {
+            std::thread::yield_now();
+        }
+        reader.take_events().unwrap();
+
+        let program = ProgramStats::partial(&DataflowData::default(), &[], dropped.count());
+        assert_eq!(program.dropped_events, 2);
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// The number of events that have been dropped because an ingestion
+/// buffer was full, shared between every buffered reader
+#[derive(Debug, Clone, Default)]
+pub struct DroppedEvents {
+    dropped: Arc<AtomicUsize>,
+}
+
+impl DroppedEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of events that have been dropped so far
+    pub fn count(&self) -> usize {
+        self.dropped.load(Ordering::Acquire)
+    }
+
+    pub(super) fn add(&self, dropped: usize) {
+        self.dropped.fetch_add(dropped, Ordering::AcqRel);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::dataflow::operators::EventIterator;
+use ddshow_types::WorkerId;
+use std::{io, iter::FromIterator, marker::PhantomData, time::Duration};
+use timely::{
+    dataflow::{
+        channels::pact::Pipeline,
+        operators::{capture::event::Event, Operator},
+        Scope, Stream,
+    },
+    Data,
+};
+
+/// A batch of replayed events held as parallel columns of timestamps,
+/// workers and events
+///
+/// Each message read from a log stream is replayed as a single batch, so the
+/// replay and ingestion operators handle one record per message instead of
+/// one tuple per event. Batches are split back up into `(time, worker, event)`
+/// records with [`Unbatch::unbatch()`] once ingestion is done with them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventBatch<E> {
+    pub times: Vec<Duration>,
+    pub workers: Vec<WorkerId>,
+    pub events: Vec<E>,
+}
+
+impl<E> EventBatch<E> {
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Keeps only the events that `keep` returns `true` for, preserving their order
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(Duration, WorkerId, &E) -> bool,
+    {
+        let mut kept = 0;
+        for idx in 0..self.len() {
+            if keep(self.times[idx], self.workers[idx], &self.events[idx]) {
+                self.times.swap(kept, idx);
+                self.workers.swap(kept, idx);
+                self.events.swap(kept, idx);
+                kept += 1;
+            }
+        }
+
+        self.times.truncate(kept);
+        self.workers.truncate(kept);
+        self.events.truncate(kept);
+    }
+
+    /// Splits the batch back up into individual records
+    pub fn into_records(self) -> impl Iterator<Item = (Duration, WorkerId, E)> {
+        self.times
+            .into_iter()
+            .zip(self.workers)
+            .zip(self.events)
+            .map(|((time, worker), event)| (time, worker, event))
+    }
+}
+
+impl<E, R> FromIterator<R> for EventBatch<E>
+where
+    R: BatchRecord<E>,
+{
+    fn from_iter<I>(records: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+    {
+        let records = records.into_iter();
+        let (capacity, _) = records.size_hint();
+
+        let mut batch = Self {
+            times: Vec::with_capacity(capacity),
+            workers: Vec::with_capacity(capacity),
+            events: Vec::with_capacity(capacity),
+        };
+        for record in records {
+            let (time, worker, event) = record.into_columns();
+            batch.times.push(time);
+            batch.workers.push(worker);
+            batch.events.push(event);
+        }
+
+        batch
+    }
+}
+
+/// Records read from a log stream that can be split into the columns of an [`EventBatch`]
+pub trait BatchRecord<E> {
+    fn into_columns(self) -> (Duration, WorkerId, E);
+}
+
+impl<E> BatchRecord<E> for (Duration, WorkerId, E) {
+    fn into_columns(self) -> (Duration, WorkerId, E) {
+        self
+    }
+}
+
+/// Timely's raw events are converted into ddshow's own as they're batched
+impl<R, E> BatchRecord<E> for (Duration, usize, R)
+where
+    E: From<R>,
+{
+    fn into_columns(self) -> (Duration, WorkerId, E) {
+        let (time, worker, event) = self;
+        (time, WorkerId::new(worker), E::from(event))
+    }
+}
+
+/// Turns every message read from the wrapped reader into a single [`EventBatch`]
+pub struct BatchedReader<R, D, E> {
+    reader: R,
+    __type: PhantomData<fn(D) -> E>,
+}
+
+impl<R, D, E> BatchedReader<R, D, E> {
+    pub const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            __type: PhantomData,
+        }
+    }
+}
+
+impl<R, D, E> EventIterator<Duration, EventBatch<E>> for BatchedReader<R, D, E>
+where
+    R: EventIterator<Duration, D>,
+    D: BatchRecord<E>,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<Event<Duration, EventBatch<E>>>> {
+        let event = self.reader.next(is_finished, bytes_read)?;
+
+        Ok(event.map(|event| match event {
+            Event::Progress(updates) => Event::Progress(updates),
+            Event::Messages(time, data) => Event::Messages(time, vec![data.into_iter().collect()]),
+        }))
+    }
+}
+
+pub trait Unbatch<S, E>
+where
+    S: Scope,
+{
+    /// Splits every batch within the stream into individual `(time, worker, event)` records
+    fn unbatch(&self) -> Stream<S, (Duration, WorkerId, E)>;
+}
+
+impl<S, E> Unbatch<S, E> for Stream<S, EventBatch<E>>
+where
+    S: Scope,
+    E: Data,
+{
+    fn unbatch(&self) -> Stream<S, (Duration, WorkerId, E)> {
+        let mut buffer = Vec::new();
+
+        self.unary(Pipeline, "Unbatch Events", move |_, _| {
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut buffer);
+
+                    output
+                        .session(&time)
+                        .give_iterator(buffer.drain(..).flat_map(EventBatch::into_records));
+                });
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatchedReader, EventBatch};
+    use crate::dataflow::operators::{EventIterator, InjectedEventReader};
+    use ddshow_types::WorkerId;
+    use std::time::Duration;
+    use timely::dataflow::operators::capture::event::Event;
+
+    #[test]
+    fn messages_become_batches() {
+        let secs = Duration::from_secs;
+        let mut reader: BatchedReader<_, (Duration, WorkerId, char), char> =
+            BatchedReader::new(InjectedEventReader::new(vec![
+                (secs(1), WorkerId::new(0), 'a'),
+                (secs(1), WorkerId::new(1), 'b'),
+                (secs(2), WorkerId::new(0), 'c'),
+            ]));
+
+        let batches: Vec<EventBatch<char>> = reader
+            .take_events()
+            .unwrap()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Messages(_, mut batches) => {
+                    assert_eq!(batches.len(), 1);
+                    batches.pop()
+                }
+                Event::Progress(_) => None,
+            })
+            .collect();
+
+        assert_eq!(
+            batches,
+            vec![
+                EventBatch {
+                    times: vec![secs(1), secs(1)],
+                    workers: vec![WorkerId::new(0), WorkerId::new(1)],
+                    events: vec!['a', 'b'],
+                },
+                EventBatch {
+                    times: vec![secs(2)],
+                    workers: vec![WorkerId::new(0)],
+                    events: vec!['c'],
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn retain_keeps_columns_aligned() {
+        let secs = Duration::from_secs;
+        let mut batch: EventBatch<char> = vec![
+            (secs(1), WorkerId::new(0), 'a'),
+            (secs(2), WorkerId::new(1), 'b'),
+            (secs(3), WorkerId::new(2), 'c'),
+            (secs(4), WorkerId::new(3), 'd'),
+        ]
+        .into_iter()
+        .collect();
+
+        batch.retain(|_, worker, &event| worker != WorkerId::new(1) && event != 'd');
+        assert_eq!(
+            batch.into_records().collect::<Vec<_>>(),
+            vec![
+                (secs(1), WorkerId::new(0), 'a'),
+                (secs(3), WorkerId::new(2), 'c'),
+            ],
+        );
+    }
+}
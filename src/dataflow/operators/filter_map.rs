@@ -7,6 +7,7 @@ use timely::{
     Data,
 };
 
+/// Filters and maps the data of a stream or collection within a single operator
 pub trait FilterMap<D, D2> {
     type Output;
 
@@ -127,6 +128,8 @@ where
     }
 }
 
+/// Filters and maps the data of a stream or collection within a single operator,
+/// giving the mapping function each update's timestamp
 pub trait FilterMapTimed<T, D, D2> {
     type Output;
 
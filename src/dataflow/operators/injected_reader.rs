@@ -0,0 +1,106 @@
+use crate::dataflow::operators::EventIterator;
+use ddshow_types::WorkerId;
+use std::{collections::VecDeque, io, time::Duration};
+use timely::dataflow::operators::capture::event::Event;
+
+/// Replays a fixed set of events as if they were a single worker's log stream
+///
+/// Events are batched by their timestamps and the stream's capability is
+/// advanced between batches the same way that timely's loggers do, so the
+/// replayed stream looks just like one captured from a real target
+pub struct InjectedEventReader<E> {
+    events: VecDeque<Event<Duration, (Duration, WorkerId, E)>>,
+}
+
+impl<E> InjectedEventReader<E> {
+    pub fn new(mut events: Vec<(Duration, WorkerId, E)>) -> Self {
+        events.sort_by_key(|&(time, _, _)| time);
+
+        let mut batches = VecDeque::new();
+        let mut capability = Duration::from_secs(0);
+
+        for event in events {
+            let time = event.0;
+
+            match batches.back_mut() {
+                Some(Event::Messages(batch_time, batch)) if *batch_time == time => {
+                    batch.push(event)
+                }
+
+                _ => {
+                    if time > capability {
+                        batches.push_back(Event::Progress(vec![(time, 1), (capability, -1)]));
+                        capability = time;
+                    }
+
+                    batches.push_back(Event::Messages(time, vec![event]));
+                }
+            }
+        }
+
+        // Release the final capability so the stream completes
+        batches.push_back(Event::Progress(vec![(capability, -1)]));
+
+        Self { events: batches }
+    }
+}
+
+impl<E> EventIterator<Duration, (Duration, WorkerId, E)> for InjectedEventReader<E> {
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        _bytes_read: &mut usize,
+    ) -> io::Result<Option<Event<Duration, (Duration, WorkerId, E)>>> {
+        let event = self.events.pop_front();
+        if self.events.is_empty() {
+            *is_finished = true;
+        }
+
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InjectedEventReader;
+    use crate::dataflow::operators::EventIterator;
+    use ddshow_types::WorkerId;
+    use std::time::Duration;
+    use timely::dataflow::operators::capture::event::Event;
+
+    #[test]
+    fn batches_by_timestamp() {
+        let (worker, secs) = (WorkerId::new(0), Duration::from_secs);
+        let mut reader = InjectedEventReader::new(vec![
+            (secs(2), worker, 'c'),
+            (secs(1), worker, 'a'),
+            (secs(1), worker, 'b'),
+        ]);
+
+        let (mut messages, mut progress) = (Vec::new(), Vec::new());
+        for event in reader.take_events().unwrap() {
+            match event {
+                Event::Messages(time, data) => messages.push((
+                    time,
+                    data.into_iter()
+                        .map(|(_, _, data)| data)
+                        .collect::<String>(),
+                )),
+                Event::Progress(updates) => progress.push(updates),
+            }
+        }
+
+        assert_eq!(
+            messages,
+            vec![(secs(1), "ab".to_owned()), (secs(2), "c".to_owned())],
+        );
+        assert_eq!(
+            progress,
+            vec![
+                vec![(secs(1), 1), (secs(0), -1)],
+                vec![(secs(2), 1), (secs(1), -1)],
+                vec![(secs(2), -1)],
+            ],
+        );
+    }
+}
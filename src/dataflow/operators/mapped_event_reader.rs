@@ -0,0 +1,267 @@
+use crate::dataflow::{constants::MAX_EVENT_FRAME_LEN, operators::EventIterator};
+use bytecheck::CheckBytes;
+use ddshow_types::Event;
+use memmap2::Mmap;
+use rkyv::{
+    check_archived_root, de::deserializers::SharedDeserializeMap,
+    validation::validators::DefaultValidator, Archive, Deserialize,
+};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt::{self, Debug},
+    io,
+    marker::PhantomData,
+    mem,
+};
+use timely::dataflow::operators::capture::event::Event as TimelyEvent;
+
+/// Reads rkyv encoded events from a memory-mapped replay file
+///
+/// Unlike [`RkyvEventReader`] every archive is checked and deserialized
+/// directly from the mapping, so nothing is copied into intermediate buffers
+/// and the os is free to page the file in and out as it's replayed.
+/// Any `B` that's aligned to 16 bytes can be read from, page-aligned mappings
+/// always are
+///
+/// [`RkyvEventReader`]: crate::dataflow::operators::RkyvEventReader
+pub struct MappedEventReader<T, D, B = Mmap> {
+    bytes: B,
+    consumed: usize,
+    shared: SharedDeserializeMap,
+    __type: PhantomData<(T, D)>,
+}
+
+impl<T, D, B> MappedEventReader<T, D, B>
+where
+    B: AsRef<[u8]>,
+{
+    /// Creates a reader over the given mapped bytes
+    pub fn new(bytes: B) -> Self {
+        debug_assert_eq!(
+            bytes.as_ref().as_ptr() as usize & 15,
+            0,
+            "mapped events must be aligned to 16 bytes",
+        );
+
+        Self {
+            bytes,
+            consumed: 0,
+            shared: SharedDeserializeMap::new(),
+            __type: PhantomData,
+        }
+    }
+
+    fn finish(&mut self, is_finished: &mut bool) -> io::Result<Option<TimelyEvent<T, D>>> {
+        if !*is_finished {
+            self.shared = SharedDeserializeMap::new();
+        }
+
+        *is_finished = true;
+        Ok(None)
+    }
+}
+
+impl<T, D, B> EventIterator<T, D> for MappedEventReader<T, D, B>
+where
+    B: AsRef<[u8]>,
+    T: Archive,
+    T::Archived: Deserialize<T, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+    D: Archive,
+    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<TimelyEvent<T, D>>> {
+        let bytes = self.bytes.as_ref();
+        if self.consumed >= bytes.len() {
+            return self.finish(is_finished);
+        }
+
+        // Archives are aligned to 16 bytes relative to the start of the file
+        let header_start = (self.consumed + 15) & !15;
+        let archive_start = header_start + mem::size_of::<u128>();
+
+        let archive_end = bytes
+            .get(header_start..archive_start)
+            .map(|header| {
+                let archive_length = u128::from_le_bytes(
+                    header
+                        .try_into()
+                        .expect("the slice is the length of a u128"),
+                );
+
+                // Corrupted headers can claim any length, so they're rejected
+                // rather than being trusted to index into the file
+                usize::try_from(archive_length)
+                    .ok()
+                    .filter(|&length| length <= MAX_EVENT_FRAME_LEN)
+                    .and_then(|length| archive_start.checked_add(length))
+                    .ok_or_else(|| {
+                        tracing::error!(
+                            type_name = std::any::type_name::<Event<T, D>>(),
+                            consumed = self.consumed,
+                            archive_length = %archive_length,
+                            "rkyv archive is larger than the maximum of {} bytes",
+                            MAX_EVENT_FRAME_LEN,
+                        );
+
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "rkyv archive of {} bytes is larger than the maximum of {} bytes",
+                                archive_length, MAX_EVENT_FRAME_LEN,
+                            ),
+                        )
+                    })
+            })
+            .transpose()?;
+        let archive = archive_end.and_then(|archive_end| bytes.get(archive_start..archive_end));
+
+        let archive = match archive {
+            Some(archive) => archive,
+
+            // Captures that were cut off partway through writing an event
+            // still replay everything before it
+            None => {
+                if header_start < bytes.len() {
+                    tracing::warn!(
+                        type_name = std::any::type_name::<Event<T, D>>(),
+                        consumed = self.consumed,
+                        length = bytes.len(),
+                        "skipped a truncated event at the end of a mapped replay file",
+                    );
+                }
+
+                return self.finish(is_finished);
+            }
+        };
+
+        let event: Event<T, D> = check_archived_root::<Event<T, D>>(archive)
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to check archived event: {:?}", err),
+                )
+            })?
+            .deserialize(&mut self.shared)
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to deserialize archived event: {:?}", err),
+                )
+            })?;
+
+        let end = archive_start + archive.len();
+        *bytes_read += end - self.consumed;
+        self.consumed = end;
+
+        Ok(Some(TimelyEvent::from(event)))
+    }
+}
+
+impl<T, D, B> Debug for MappedEventReader<T, D, B>
+where
+    B: AsRef<[u8]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedEventReader")
+            .field("length", &self.bytes.as_ref().len())
+            .field("consumed", &self.consumed)
+            .finish()
+    }
+}
+
+// FIXME: https://github.com/djkoloski/rkyv/issues/174
+unsafe impl<T, D, B> Send for MappedEventReader<T, D, B>
+where
+    T: Send,
+    D: Send,
+    B: Send,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataflow::{
+        constants::MAX_EVENT_FRAME_LEN,
+        operators::{EventIterator, MappedEventReader},
+    };
+    use ddshow_sink::EventWriter;
+    use ddshow_types::{
+        timely_logging::{OperatesEvent, TimelyEvent},
+        OperatorAddr, OperatorId,
+    };
+    use rkyv::AlignedVec;
+    use std::{io, time::Duration};
+    use timely::dataflow::operators::capture::{Event, EventPusher};
+
+    #[test]
+    fn reads_events_in_place() {
+        let events = vec![
+            Event::Progress(vec![
+                (Duration::from_secs(0), 1),
+                (Duration::from_secs(1), 2),
+            ]),
+            Event::Messages(
+                Duration::from_secs(0),
+                vec![TimelyEvent::Operates(OperatesEvent::new(
+                    OperatorId::new(0),
+                    OperatorAddr::from_elem(OperatorId::new(0)),
+                    "foobar".to_owned(),
+                ))],
+            ),
+            Event::Messages(
+                Duration::from_secs(40),
+                vec![TimelyEvent::Operates(OperatesEvent::new(
+                    OperatorId::new(4),
+                    OperatorAddr::from(vec![OperatorId::new(0); 100]),
+                    "foobarbaz".to_owned(),
+                ))],
+            ),
+        ];
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = EventWriter::new(&mut buffer);
+            for event in events.iter().cloned() {
+                writer.push(event);
+            }
+        }
+
+        // Cut the last event off partway through to make sure truncated
+        // captures still replay the events before it
+        let mut bytes = AlignedVec::new();
+        bytes.extend_from_slice(&buffer[..buffer.len() - 4]);
+
+        let mut reader = MappedEventReader::<Duration, TimelyEvent, _>::new(bytes);
+        let (mut is_finished, mut bytes_read) = (false, 0);
+
+        let mut replayed = Vec::new();
+        while !is_finished {
+            if let Some(event) = reader.next(&mut is_finished, &mut bytes_read).unwrap() {
+                replayed.push(event);
+            }
+        }
+
+        assert_eq!(replayed, events[..2]);
+        assert!(bytes_read < buffer.len() - 4);
+    }
+
+    #[test]
+    fn rejects_oversized_archives() {
+        for &length in &[(MAX_EVENT_FRAME_LEN + 1) as u128, u128::MAX] {
+            let mut bytes = AlignedVec::new();
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&[0; 64]);
+
+            let mut reader = MappedEventReader::<Duration, TimelyEvent, _>::new(bytes);
+            let (mut is_finished, mut bytes_read) = (false, 0);
+
+            let err = reader.next(&mut is_finished, &mut bytes_read).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(bytes_read, 0);
+        }
+    }
+}
@@ -11,7 +11,10 @@ use std::{
     time::Duration,
 };
 
-/// A type for getting the minimum value of a stream
+/// A difference type for getting the minimum value of a stream
+///
+/// Adding two `Min`s keeps the smaller of them, so counting a collection
+/// with `Min` differences yields the minimum of each key's values
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
 pub struct Min<T> {
     pub value: T,
@@ -137,7 +140,7 @@ where
     }
 }
 
-/// A type for getting the maximum value of a stream
+/// A difference type for getting the maximum value of a stream, see [`Min`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
 pub struct Max<T> {
     pub value: T,
@@ -3,44 +3,70 @@ mod util;
 
 mod activate_capability_set;
 mod antijoin;
+mod auto_event_reader;
+mod bincode_event_reader;
+#[cfg(feature = "network")]
+mod buffered_reader;
 mod delay;
 #[cfg(not(feature = "timely-next"))]
 mod diff_list;
+mod dropped_events;
 mod epoch;
+mod event_batch;
 mod filter_map;
 mod filter_split;
 mod flat_split;
+mod injected_reader;
 mod inspect;
 mod iterate_ext;
 mod keys;
 mod map;
+#[cfg(not(target_arch = "wasm32"))]
+mod mapped_event_reader;
 mod min_max;
 mod negate;
+mod raw_layouts;
+#[cfg(feature = "network")]
+mod reconnecting_reader;
 mod reduce;
 mod replay_with_shutdown;
 pub mod rkyv_event_reader;
 mod sort;
 mod split;
+mod target_runs;
 mod timely_version_hack;
 
 #[cfg(test)]
 pub use activate_capability_set::ActivateCapabilitySet;
 pub use antijoin::JoinArranged;
+pub use auto_event_reader::{AutoEventReader, RawEventAdapter};
+pub use bincode_event_reader::BincodeEventReader;
+#[cfg(feature = "network")]
+pub use buffered_reader::BufferedReader;
 pub use delay::DelayExt;
+pub use dropped_events::DroppedEvents;
 pub use epoch::Epoch;
+pub use event_batch::{BatchRecord, BatchedReader, EventBatch, Unbatch};
 pub use filter_map::{FilterMap, FilterMapTimed};
 pub use filter_split::FilterSplit;
 pub use flat_split::FlatSplit;
+pub use injected_reader::InjectedEventReader;
 pub use inspect::InspectExt;
 pub use iterate_ext::IterateExt;
 pub use keys::Keys;
 pub use map::{MapExt, MapInPlace, MapTimed};
+#[cfg(not(target_arch = "wasm32"))]
+pub use mapped_event_reader::MappedEventReader;
 pub use min_max::{DiffDuration, Max, Maybe, Min};
 pub use negate::NegateExt;
+pub use raw_layouts::{LayoutAdapter, LegacyEvent, RawLayout};
+#[cfg(feature = "network")]
+pub use reconnecting_reader::{ConnectReader, ReconnectingReader};
 pub use reduce::HierarchicalReduce;
 pub use replay_with_shutdown::{EventIterator, EventReader, ReplayWithShutdown};
 pub use rkyv_event_reader::RkyvEventReader;
 pub use sort::SortBy;
 pub use split::Split;
+pub use target_runs::{RunSegment, TargetRuns};
 pub use timely_version_hack::Multiply;
 pub use util::{CrossbeamExtractor, CrossbeamPusher, Fuel, OperatorExt};
@@ -0,0 +1,680 @@
+//! The layouts of the raw, abomonation-encoded events emitted by the timely &
+//! differential releases that ddshow is able to read
+
+use crate::dataflow::operators::{EventIterator, EventReader};
+use abomonation::Abomonation;
+use ddshow_types::{
+    differential_logging::DifferentialEvent, progress_logging::TimelyProgressEvent,
+    timely_logging::TimelyEvent, WorkerId,
+};
+use std::{
+    fmt::{self, Debug},
+    io::{self, Read},
+    marker::PhantomData,
+    mem,
+    time::Duration,
+};
+use timely::dataflow::operators::capture::event::Event as RawEvent;
+
+/// The largest worker id we'll believe while checking whether a stream's events
+/// plausibly decode under a given layout
+const MAX_PLAUSIBLE_WORKER: usize = 1 << 16;
+
+/// The layouts of raw event streams, each tied to the timely & differential
+/// releases that emitted them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawLayout {
+    /// The timely & differential releases that ddshow was built against
+    Native,
+    /// Timely 0.11 & differential 0.11, requires the `timely-0-11` feature
+    #[cfg(feature = "timely-0-11")]
+    Timely0_11,
+}
+
+impl RawLayout {
+    /// Every layout compiled into ddshow, in the order they're tried during detection
+    #[cfg(not(feature = "timely-0-11"))]
+    pub const ALL: &'static [Self] = &[Self::Native];
+
+    /// Every layout compiled into ddshow, in the order they're tried during detection
+    #[cfg(feature = "timely-0-11")]
+    pub const ALL: &'static [Self] = &[Self::Native, Self::Timely0_11];
+
+    /// Detects which layout an abomonation-encoded stream uses from the bytes it
+    /// started with, returning `None` if more bytes are required to make a decision
+    ///
+    /// Each layout is tried in order and the first one that decodes the stream's
+    /// complete frames into plausible events wins, once the peer has finished
+    /// those frames must also line up exactly with the end of the stream. Streams
+    /// that don't plausibly decode under any layout are assumed to use the native one
+    pub fn detect<T, D, A>(prefix: &[u8], peer_finished: bool) -> Option<Self>
+    where
+        T: Abomonation,
+        D: LayoutAdapter<A>,
+    {
+        if Self::ALL.len() == 1 {
+            return Some(Self::Native);
+        }
+
+        let mut pending = false;
+        for &layout in Self::ALL {
+            match D::is_plausible::<T>(layout, prefix, peer_finished) {
+                Some(true) => return Some(layout),
+                Some(false) => {}
+                None => pending = true,
+            }
+        }
+
+        if pending && !peer_finished {
+            None
+        } else {
+            Some(Self::Native)
+        }
+    }
+}
+
+/// Reads ddshow's event bundles out of the raw event streams of each [`RawLayout`]
+pub trait LayoutAdapter<Raw>: Sized {
+    /// Whether the start of an abomonation-encoded stream plausibly holds events
+    /// of the given layout, `None` if it doesn't hold a complete frame yet
+    fn is_plausible<T>(layout: RawLayout, prefix: &[u8], peer_finished: bool) -> Option<bool>
+    where
+        T: Abomonation;
+
+    /// Creates a reader for an abomonation-encoded stream of the given layout,
+    /// returning `None` if the layout has no counterpart to this bundle
+    fn layout_reader<T>(
+        layout: RawLayout,
+        stream: Box<dyn Read + Send + 'static>,
+    ) -> Option<Box<dyn EventIterator<T, Self> + Send + 'static>>
+    where
+        T: Abomonation + Clone + Send + 'static;
+}
+
+/// Implemented by ddshow's event types to decode them from the raw events of
+/// releases other than the ones ddshow was built against
+pub trait LegacyEvent: Sized {
+    /// Whether the start of an abomonation-encoded stream plausibly holds events
+    /// of the given legacy layout
+    fn is_plausible<T>(_layout: RawLayout, _prefix: &[u8], _peer_finished: bool) -> Option<bool>
+    where
+        T: Abomonation,
+    {
+        Some(false)
+    }
+
+    /// Creates a reader for an abomonation-encoded stream of the given legacy layout
+    #[allow(clippy::type_complexity)]
+    fn legacy_reader<T>(
+        _layout: RawLayout,
+        _stream: Box<dyn Read + Send + 'static>,
+    ) -> Option<Box<dyn EventIterator<T, (Duration, WorkerId, Self)> + Send + 'static>>
+    where
+        T: Abomonation + Clone + Send + 'static,
+    {
+        None
+    }
+}
+
+impl<E, R> LayoutAdapter<(Duration, usize, R)> for (Duration, WorkerId, E)
+where
+    E: From<R> + LegacyEvent + Send + 'static,
+    R: Abomonation + Clone + Send + 'static,
+{
+    fn is_plausible<T>(layout: RawLayout, prefix: &[u8], peer_finished: bool) -> Option<bool>
+    where
+        T: Abomonation,
+    {
+        match layout {
+            RawLayout::Native => decodes_plausibly::<T, R>(prefix, peer_finished),
+            #[cfg(feature = "timely-0-11")]
+            legacy => E::is_plausible::<T>(legacy, prefix, peer_finished),
+        }
+    }
+
+    fn layout_reader<T>(
+        layout: RawLayout,
+        stream: Box<dyn Read + Send + 'static>,
+    ) -> Option<Box<dyn EventIterator<T, Self> + Send + 'static>>
+    where
+        T: Abomonation + Clone + Send + 'static,
+    {
+        match layout {
+            RawLayout::Native => Some(Box::new(AdaptedReader::new(
+                EventReader::<T, (Duration, usize, R), _>::new(stream),
+                |(time, worker, event)| (time, WorkerId::new(worker), E::from(event)),
+            ))),
+            #[cfg(feature = "timely-0-11")]
+            legacy => E::legacy_reader::<T>(legacy, stream),
+        }
+    }
+}
+
+// Progress logging didn't exist before the native releases
+impl LegacyEvent for TimelyProgressEvent {}
+
+#[cfg(not(feature = "timely-0-11"))]
+impl LegacyEvent for TimelyEvent {}
+
+#[cfg(not(feature = "timely-0-11"))]
+impl LegacyEvent for DifferentialEvent {}
+
+/// Decodes the complete frames at the start of an abomonation-encoded stream as
+/// raw event bundles, checking that every bundle came from a plausible worker.
+/// Once the peer has finished the prefix is the entire stream, so its frames
+/// have to consume it exactly. Returns `None` if the prefix doesn't contain a
+/// single complete frame
+pub(crate) fn decodes_plausibly<T, R>(prefix: &[u8], peer_finished: bool) -> Option<bool>
+where
+    T: Abomonation,
+    R: Abomonation,
+{
+    // Decoding happens in place, so we work on a copy of the prefix
+    let mut bytes = prefix.to_vec();
+    let mut remaining = &mut bytes[..];
+    let (mut frames, mut leftover) = (0, remaining.len());
+
+    while let Some((event, rest)) = unsafe {
+        abomonation::decode::<RawEvent<T, (Duration, usize, R)>>(mem::take(&mut remaining))
+    } {
+        if let RawEvent::Messages(_, bundles) = event {
+            if bundles
+                .iter()
+                .any(|&(_, worker, _)| worker >= MAX_PLAUSIBLE_WORKER)
+            {
+                return Some(false);
+            }
+        }
+
+        frames += 1;
+        leftover = rest.len();
+        remaining = rest;
+    }
+
+    // Frames decoded under the wrong layout don't line up with the stream's end,
+    // leaving behind bytes that look like the start of a frame that never finished
+    if peer_finished && leftover != 0 {
+        Some(false)
+    } else if frames == 0 {
+        None
+    } else {
+        Some(true)
+    }
+}
+
+/// Adapts the events of a raw event reader into ddshow's event bundles
+pub(crate) struct AdaptedReader<A, R, F> {
+    reader: R,
+    adapt: F,
+    __type: PhantomData<fn(A)>,
+}
+
+impl<A, R, F> AdaptedReader<A, R, F> {
+    pub(crate) fn new(reader: R, adapt: F) -> Self {
+        Self {
+            reader,
+            adapt,
+            __type: PhantomData,
+        }
+    }
+}
+
+impl<T, A, D, R, F> EventIterator<T, D> for AdaptedReader<A, R, F>
+where
+    R: EventIterator<T, A>,
+    F: FnMut(A) -> D,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<RawEvent<T, D>>> {
+        let adapt = &mut self.adapt;
+        let event = self
+            .reader
+            .next(is_finished, bytes_read)?
+            .map(|event| match event {
+                RawEvent::Progress(progress) => RawEvent::Progress(progress),
+                RawEvent::Messages(time, data) => {
+                    RawEvent::Messages(time, data.into_iter().map(&mut *adapt).collect())
+                }
+            });
+
+        Ok(event)
+    }
+}
+
+impl<A, R, F> Debug for AdaptedReader<A, R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdaptedReader").finish()
+    }
+}
+
+/// Adapters for the events of timely 0.11 & differential 0.11
+#[cfg(feature = "timely-0-11")]
+mod timely_0_11 {
+    use super::{decodes_plausibly, AdaptedReader, LegacyEvent, RawLayout};
+    use crate::dataflow::operators::{EventIterator, EventReader};
+    use abomonation::Abomonation;
+    use ddshow_types::{
+        differential_logging::DifferentialEvent, timely_logging::TimelyEvent, WorkerId,
+    };
+    use differential_0_11::logging as legacy_differential;
+    use differential_dataflow::logging as differential;
+    use std::{io::Read, time::Duration};
+    use timely::logging as timely;
+    use timely_0_11::logging as legacy_timely;
+
+    impl LegacyEvent for TimelyEvent {
+        fn is_plausible<T>(layout: RawLayout, prefix: &[u8], peer_finished: bool) -> Option<bool>
+        where
+            T: Abomonation,
+        {
+            match layout {
+                RawLayout::Timely0_11 => {
+                    decodes_plausibly::<T, legacy_timely::TimelyEvent>(prefix, peer_finished)
+                }
+                RawLayout::Native => Some(false),
+            }
+        }
+
+        fn legacy_reader<T>(
+            layout: RawLayout,
+            stream: Box<dyn Read + Send + 'static>,
+        ) -> Option<Box<dyn EventIterator<T, (Duration, WorkerId, Self)> + Send + 'static>>
+        where
+            T: Abomonation + Clone + Send + 'static,
+        {
+            match layout {
+                RawLayout::Timely0_11 => Some(Box::new(AdaptedReader::new(
+                    EventReader::<T, (Duration, usize, legacy_timely::TimelyEvent), _>::new(stream),
+                    |(time, worker, event)| (time, WorkerId::new(worker), timely_event(event)),
+                ))),
+                RawLayout::Native => None,
+            }
+        }
+    }
+
+    impl LegacyEvent for DifferentialEvent {
+        fn is_plausible<T>(layout: RawLayout, prefix: &[u8], peer_finished: bool) -> Option<bool>
+        where
+            T: Abomonation,
+        {
+            match layout {
+                RawLayout::Timely0_11 => decodes_plausibly::<
+                    T,
+                    legacy_differential::DifferentialEvent,
+                >(prefix, peer_finished),
+                RawLayout::Native => Some(false),
+            }
+        }
+
+        fn legacy_reader<T>(
+            layout: RawLayout,
+            stream: Box<dyn Read + Send + 'static>,
+        ) -> Option<Box<dyn EventIterator<T, (Duration, WorkerId, Self)> + Send + 'static>>
+        where
+            T: Abomonation + Clone + Send + 'static,
+        {
+            match layout {
+                RawLayout::Timely0_11 => {
+                    Some(Box::new(AdaptedReader::new(
+                        EventReader::<
+                            T,
+                            (Duration, usize, legacy_differential::DifferentialEvent),
+                            _,
+                        >::new(stream),
+                        |(time, worker, event)| {
+                            (time, WorkerId::new(worker), differential_event(event))
+                        },
+                    )))
+                }
+                RawLayout::Native => None,
+            }
+        }
+    }
+
+    /// Converts a timely 0.11 event into the native release's event and then into ddshow's
+    pub(super) fn timely_event(event: legacy_timely::TimelyEvent) -> TimelyEvent {
+        let start_stop = |start_stop| match start_stop {
+            legacy_timely::StartStop::Start => timely::StartStop::Start,
+            legacy_timely::StartStop::Stop => timely::StartStop::Stop,
+        };
+
+        let event = match event {
+            legacy_timely::TimelyEvent::Operates(event) => {
+                timely::TimelyEvent::Operates(timely::OperatesEvent {
+                    id: event.id,
+                    addr: event.addr,
+                    name: event.name,
+                })
+            }
+            legacy_timely::TimelyEvent::Channels(event) => {
+                timely::TimelyEvent::Channels(timely::ChannelsEvent {
+                    id: event.id,
+                    scope_addr: event.scope_addr,
+                    source: event.source,
+                    target: event.target,
+                })
+            }
+            legacy_timely::TimelyEvent::PushProgress(event) => {
+                timely::TimelyEvent::PushProgress(timely::PushProgressEvent { op_id: event.op_id })
+            }
+            legacy_timely::TimelyEvent::Messages(event) => {
+                timely::TimelyEvent::Messages(timely::MessagesEvent {
+                    is_send: event.is_send,
+                    channel: event.channel,
+                    source: event.source,
+                    target: event.target,
+                    seq_no: event.seq_no,
+                    length: event.length,
+                })
+            }
+            legacy_timely::TimelyEvent::Schedule(event) => {
+                timely::TimelyEvent::Schedule(timely::ScheduleEvent {
+                    id: event.id,
+                    start_stop: start_stop(event.start_stop),
+                })
+            }
+            legacy_timely::TimelyEvent::Shutdown(event) => {
+                timely::TimelyEvent::Shutdown(timely::ShutdownEvent { id: event.id })
+            }
+            legacy_timely::TimelyEvent::Application(event) => {
+                timely::TimelyEvent::Application(timely::ApplicationEvent {
+                    id: event.id,
+                    is_start: event.is_start,
+                })
+            }
+            legacy_timely::TimelyEvent::GuardedMessage(event) => {
+                timely::TimelyEvent::GuardedMessage(timely::GuardedMessageEvent {
+                    is_start: event.is_start,
+                })
+            }
+            legacy_timely::TimelyEvent::GuardedProgress(event) => {
+                timely::TimelyEvent::GuardedProgress(timely::GuardedProgressEvent {
+                    is_start: event.is_start,
+                })
+            }
+            legacy_timely::TimelyEvent::CommChannels(event) => {
+                timely::TimelyEvent::CommChannels(timely::CommChannelsEvent {
+                    identifier: event.identifier,
+                    kind: match event.kind {
+                        legacy_timely::CommChannelKind::Progress => {
+                            timely::CommChannelKind::Progress
+                        }
+                        legacy_timely::CommChannelKind::Data => timely::CommChannelKind::Data,
+                    },
+                })
+            }
+            legacy_timely::TimelyEvent::Input(event) => {
+                timely::TimelyEvent::Input(timely::InputEvent {
+                    start_stop: start_stop(event.start_stop),
+                })
+            }
+            legacy_timely::TimelyEvent::Park(event) => timely::TimelyEvent::Park(match event {
+                legacy_timely::ParkEvent::Park(duration) => timely::ParkEvent::Park(duration),
+                legacy_timely::ParkEvent::Unpark => timely::ParkEvent::Unpark,
+            }),
+            legacy_timely::TimelyEvent::Text(text) => timely::TimelyEvent::Text(text),
+        };
+
+        TimelyEvent::from(event)
+    }
+
+    /// Converts a differential 0.11 event into the native release's event and then into ddshow's
+    pub(super) fn differential_event(
+        event: legacy_differential::DifferentialEvent,
+    ) -> DifferentialEvent {
+        let event = match event {
+            legacy_differential::DifferentialEvent::Batch(event) => {
+                differential::DifferentialEvent::Batch(differential::BatchEvent {
+                    operator: event.operator,
+                    length: event.length,
+                })
+            }
+            legacy_differential::DifferentialEvent::Merge(event) => {
+                differential::DifferentialEvent::Merge(differential::MergeEvent {
+                    operator: event.operator,
+                    scale: event.scale,
+                    length1: event.length1,
+                    length2: event.length2,
+                    complete: event.complete,
+                })
+            }
+            legacy_differential::DifferentialEvent::Drop(event) => {
+                differential::DifferentialEvent::Drop(differential::DropEvent {
+                    operator: event.operator,
+                    length: event.length,
+                })
+            }
+            legacy_differential::DifferentialEvent::MergeShortfall(event) => {
+                differential::DifferentialEvent::MergeShortfall(differential::MergeShortfall {
+                    operator: event.operator,
+                    scale: event.scale,
+                    shortfall: event.shortfall,
+                })
+            }
+            legacy_differential::DifferentialEvent::TraceShare(event) => {
+                differential::DifferentialEvent::TraceShare(differential::TraceShare {
+                    operator: event.operator,
+                    diff: event.diff,
+                })
+            }
+        };
+
+        DifferentialEvent::from(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decodes_plausibly, LayoutAdapter, RawLayout};
+    use crate::dataflow::operators::EventIterator;
+    use ddshow_types::{
+        timely_logging::{InputEvent, StartStop, TimelyEvent},
+        WorkerId,
+    };
+    use std::{
+        io::{Cursor, Read},
+        time::Duration,
+    };
+    use timely::{
+        dataflow::operators::capture::{Event as RawEvent, EventPusher, EventWriter},
+        logging as raw,
+    };
+
+    type Bundle = (Duration, WorkerId, TimelyEvent);
+    type NativeBundle = (Duration, usize, raw::TimelyEvent);
+
+    fn stream(bytes: Vec<u8>) -> Box<dyn Read + Send + 'static> {
+        Box::new(Cursor::new(bytes))
+    }
+
+    fn expected(worker: usize) -> Vec<RawEvent<Duration, Bundle>> {
+        vec![
+            RawEvent::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    WorkerId::new(worker),
+                    TimelyEvent::Input(InputEvent::new(StartStop::start())),
+                )],
+            ),
+            RawEvent::Progress(vec![(Duration::from_secs(1), 1)]),
+        ]
+    }
+
+    fn native_events(worker: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut writer = EventWriter::new(&mut bytes);
+            writer.push(RawEvent::<Duration, NativeBundle>::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    worker,
+                    raw::TimelyEvent::Input(raw::InputEvent {
+                        start_stop: raw::StartStop::Start,
+                    }),
+                )],
+            ));
+            writer.push(RawEvent::Progress(vec![(Duration::from_secs(1), 1)]));
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn native_layout() {
+        let bytes = native_events(3);
+        assert_eq!(
+            decodes_plausibly::<Duration, raw::TimelyEvent>(&bytes, true),
+            Some(true),
+        );
+        assert_eq!(
+            RawLayout::detect::<Duration, Bundle, NativeBundle>(&bytes, true),
+            Some(RawLayout::Native),
+        );
+
+        let mut reader = <Bundle as LayoutAdapter<NativeBundle>>::layout_reader::<Duration>(
+            RawLayout::Native,
+            stream(bytes),
+        )
+        .expect("every bundle can be read from the native layout");
+        assert_eq!(reader.take_events().unwrap(), expected(3));
+    }
+
+    #[test]
+    fn implausible_workers() {
+        let bytes = native_events(usize::MAX);
+        assert_eq!(
+            decodes_plausibly::<Duration, raw::TimelyEvent>(&bytes, true),
+            Some(false),
+        );
+
+        // Incomplete frames can't be judged either way
+        assert_eq!(
+            decodes_plausibly::<Duration, raw::TimelyEvent>(&bytes[..4], false),
+            None,
+        );
+    }
+
+    #[test]
+    fn finished_streams_end_on_a_frame() {
+        let bytes = native_events(3);
+        let truncated = &bytes[..bytes.len() - 1];
+
+        // Streams can still be waiting on the rest of their final frame
+        assert_eq!(
+            decodes_plausibly::<Duration, raw::TimelyEvent>(truncated, false),
+            Some(true),
+        );
+
+        // But finished ones that stop partway through a frame were misread
+        assert_eq!(
+            decodes_plausibly::<Duration, raw::TimelyEvent>(truncated, true),
+            Some(false),
+        );
+        assert_eq!(
+            decodes_plausibly::<Duration, raw::TimelyEvent>(&bytes[..4], true),
+            Some(false),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "timely-0-11")]
+    fn timely_0_11_layout() {
+        use timely_0_11::{
+            dataflow::operators::capture::{
+                Event as LegacyEvent, EventPusher as LegacyPusher, EventWriter as LegacyWriter,
+            },
+            logging as legacy,
+        };
+
+        let mut bytes = Vec::new();
+        {
+            let mut writer = LegacyWriter::new(&mut bytes);
+            writer.push(LegacyEvent::<
+                Duration,
+                (Duration, usize, legacy::TimelyEvent),
+            >::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    3,
+                    legacy::TimelyEvent::Input(legacy::InputEvent {
+                        start_stop: legacy::StartStop::Start,
+                    }),
+                )],
+            ));
+            writer.push(LegacyEvent::Progress(vec![(Duration::from_secs(1), 1)]));
+        }
+
+        assert_eq!(
+            decodes_plausibly::<Duration, legacy::TimelyEvent>(&bytes, true),
+            Some(true),
+        );
+        assert_eq!(
+            RawLayout::detect::<Duration, Bundle, NativeBundle>(&bytes, true),
+            Some(RawLayout::Timely0_11),
+        );
+
+        let mut reader = <Bundle as LayoutAdapter<NativeBundle>>::layout_reader::<Duration>(
+            RawLayout::Timely0_11,
+            stream(bytes),
+        )
+        .expect("timely events can be read from timely 0.11");
+        assert_eq!(reader.take_events().unwrap(), expected(3));
+    }
+
+    #[test]
+    #[cfg(feature = "timely-0-11")]
+    fn differential_0_11_layout() {
+        use ddshow_types::{
+            differential_logging::{BatchEvent, DifferentialEvent},
+            OperatorId,
+        };
+        use differential_0_11::logging as legacy;
+        use timely_0_11::dataflow::operators::capture::{
+            Event as LegacyEvent, EventPusher as LegacyPusher, EventWriter as LegacyWriter,
+        };
+
+        let mut bytes = Vec::new();
+        {
+            let mut writer = LegacyWriter::new(&mut bytes);
+            writer.push(LegacyEvent::<
+                Duration,
+                (Duration, usize, legacy::DifferentialEvent),
+            >::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    1,
+                    legacy::DifferentialEvent::Batch(legacy::BatchEvent {
+                        operator: 5,
+                        length: 10,
+                    }),
+                )],
+            ));
+        }
+
+        let mut reader =
+            <(Duration, WorkerId, DifferentialEvent) as LayoutAdapter<(
+                Duration,
+                usize,
+                differential_dataflow::logging::DifferentialEvent,
+            )>>::layout_reader::<Duration>(RawLayout::Timely0_11, stream(bytes))
+            .expect("differential events can be read from differential 0.11");
+        assert_eq!(
+            reader.take_events().unwrap(),
+            vec![RawEvent::Messages(
+                Duration::from_secs(0),
+                vec![(
+                    Duration::from_secs(0),
+                    WorkerId::new(1),
+                    DifferentialEvent::Batch(BatchEvent::new(OperatorId::new(5), 10)),
+                )],
+            )],
+        );
+    }
+}
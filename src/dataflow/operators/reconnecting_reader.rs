@@ -0,0 +1,386 @@
+use crate::dataflow::operators::{EventIterator, RunSegment, TargetRuns};
+use std::{
+    fmt::{self, Debug},
+    io::{self, ErrorKind},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use timely::{dataflow::operators::capture::event::Event as TimelyEvent, progress::ChangeBatch};
+
+type BoxedReader<D> = Box<dyn EventIterator<Duration, D> + Send + 'static>;
+
+/// Turns a freshly accepted socket into an event reader
+pub type ConnectReader<D> = Arc<dyn Fn(TcpStream) -> anyhow::Result<BoxedReader<D>> + Send + Sync>;
+
+/// A reader that keeps accepting connections from the target after its
+/// current one closes, splitting the stream into distinct runs of the
+/// target process
+///
+/// The events of each run are shifted in time so that they start after
+/// the previous run's events and the workers of run `n` are renumbered to
+/// `worker + n * workers_per_run`, keeping the operators of each run apart
+/// from one another within all downstream stats
+pub struct ReconnectingReader<D> {
+    current: Option<BoxedReader<D>>,
+    listener: Arc<TcpListener>,
+    connect: ConnectReader<D>,
+    reconnect_timeout: Duration,
+    disconnected_at: Option<Instant>,
+    workers_per_run: usize,
+    runs: Option<TargetRuns>,
+    run: usize,
+    /// The offset applied to all timestamps within the current run
+    offset: Duration,
+    /// The latest (shifted) timestamp we've seen
+    latest: Duration,
+    /// The (shifted) capabilities held by the current connection
+    capabilities: ChangeBatch<Duration>,
+}
+
+impl<D> ReconnectingReader<D>
+where
+    D: RunSegment,
+{
+    /// Creates a reader for the first run of the target, the listener
+    /// must be in non-blocking mode
+    pub fn new(
+        reader: BoxedReader<D>,
+        listener: Arc<TcpListener>,
+        connect: ConnectReader<D>,
+        reconnect_timeout: Duration,
+        workers_per_run: usize,
+        runs: Option<TargetRuns>,
+    ) -> Self {
+        if let Some(runs) = runs.as_ref() {
+            runs.connected(0, Duration::from_secs(0));
+        }
+
+        // Every stream starts out holding a capability for the minimum time
+        let mut capabilities = ChangeBatch::new();
+        capabilities.update(Duration::from_secs(0), 1);
+
+        Self {
+            current: Some(reader),
+            listener,
+            connect,
+            reconnect_timeout,
+            disconnected_at: None,
+            workers_per_run,
+            runs,
+            run: 0,
+            offset: Duration::from_secs(0),
+            latest: Duration::from_secs(0),
+            capabilities,
+        }
+    }
+
+    /// Returns the run that the reader is currently on
+    pub const fn run(&self) -> usize {
+        self.run
+    }
+
+    fn shift(&mut self, event: TimelyEvent<Duration, D>) -> TimelyEvent<Duration, D> {
+        match event {
+            TimelyEvent::Progress(mut updates) => {
+                let mut last_released = None;
+                for (time, diff) in updates.iter_mut() {
+                    *time += self.offset;
+                    self.latest = self.latest.max(*time);
+
+                    if *diff < 0 {
+                        last_released = last_released.max(Some(*time));
+                    }
+                }
+                self.capabilities.extend(updates.iter().copied());
+
+                // Hold onto a capability when the connection releases its last one so
+                // that we can downgrade it to the next run's times if the target reconnects
+                if self.capabilities.is_empty() {
+                    if let Some(time) = last_released {
+                        updates.push((time, 1));
+                        self.capabilities.update(time, 1);
+                    }
+                }
+
+                TimelyEvent::Progress(updates)
+            }
+
+            TimelyEvent::Messages(time, mut data) => {
+                let time = time + self.offset;
+                self.latest = self.latest.max(time);
+
+                let worker_offset = self.run * self.workers_per_run;
+                for event in data.iter_mut() {
+                    event.segment(self.offset, worker_offset);
+                    self.latest = self.latest.max(event.time());
+                }
+
+                TimelyEvent::Messages(time, data)
+            }
+        }
+    }
+
+    /// Releases every capability the previous connection held
+    fn release_capabilities(&mut self) -> Vec<(Duration, i64)> {
+        self.capabilities
+            .drain()
+            .map(|(time, diff)| (time, -diff))
+            .collect()
+    }
+
+    fn disconnect(&mut self) {
+        tracing::info!(
+            run = self.run,
+            latest = ?self.latest,
+            "connection for run {} of the target closed",
+            self.run,
+        );
+
+        if let Some(runs) = self.runs.as_ref() {
+            runs.disconnected(self.run, self.latest);
+        }
+
+        self.current = None;
+        self.disconnected_at = Some(Instant::now());
+    }
+
+    fn reconnect(&mut self, socket: TcpStream) -> anyhow::Result<Vec<(Duration, i64)>> {
+        let reader = (self.connect)(socket)?;
+
+        // The new run starts after everything the previous run did
+        let mut updates = self.release_capabilities();
+        let offset = updates
+            .iter()
+            .map(|&(time, _)| time)
+            .fold(self.latest, Duration::max);
+
+        self.run += 1;
+        self.offset = offset;
+        self.current = Some(reader);
+        self.disconnected_at = None;
+
+        updates.push((offset, 1));
+        self.capabilities.update(offset, 1);
+
+        if let Some(runs) = self.runs.as_ref() {
+            runs.connected(self.run, offset);
+        }
+        tracing::info!(
+            run = self.run,
+            offset = ?offset,
+            "the target reconnected, starting run {}",
+            self.run,
+        );
+
+        Ok(updates)
+    }
+}
+
+impl<D> EventIterator<Duration, D> for ReconnectingReader<D>
+where
+    D: RunSegment,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+        bytes_read: &mut usize,
+    ) -> io::Result<Option<TimelyEvent<Duration, D>>> {
+        if let Some(reader) = self.current.as_mut() {
+            let mut connection_finished = false;
+            let event = reader.next(&mut connection_finished, bytes_read)?;
+            let event = event.map(|event| self.shift(event));
+
+            if connection_finished {
+                self.disconnect();
+            }
+
+            return Ok(event);
+        }
+
+        match self.listener.accept() {
+            Ok((socket, _)) => {
+                let updates = self
+                    .reconnect(socket)
+                    .map_err(|err| io::Error::new(ErrorKind::Other, err))?;
+
+                Ok(Some(TimelyEvent::Progress(updates)))
+            }
+
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                let waited = self
+                    .disconnected_at
+                    .map_or(Duration::from_secs(0), |disconnected| {
+                        disconnected.elapsed()
+                    });
+
+                if waited < self.reconnect_timeout {
+                    return Ok(None);
+                }
+
+                tracing::info!(
+                    runs = self.run + 1,
+                    "the target didn't reconnect within {:?}, finishing the stream",
+                    self.reconnect_timeout,
+                );
+                *is_finished = true;
+
+                let updates = self.release_capabilities();
+                if updates.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(TimelyEvent::Progress(updates)))
+                }
+            }
+
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<D> Drop for ReconnectingReader<D> {
+    fn drop(&mut self) {
+        // Record the end of runs that were still going when we shut down
+        if self.current.is_some() {
+            if let Some(runs) = self.runs.as_ref() {
+                runs.disconnected(self.run, self.latest);
+            }
+        }
+    }
+}
+
+impl<D> Debug for ReconnectingReader<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingReader")
+            .field("connected", &self.current.is_some())
+            .field("run", &self.run)
+            .field("offset", &self.offset)
+            .field("latest", &self.latest)
+            .field("reconnect_timeout", &self.reconnect_timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReconnectingReader, TargetRuns};
+    use crate::dataflow::operators::EventIterator;
+    use ddshow_types::WorkerId;
+    use std::{
+        io,
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        time::Duration,
+    };
+    use timely::dataflow::operators::capture::event::Event;
+
+    type Bundle = (Duration, WorkerId, usize);
+
+    /// A reader over a fixed set of events
+    struct Events(Vec<Event<Duration, Bundle>>);
+
+    impl EventIterator<Duration, Bundle> for Events {
+        fn next(
+            &mut self,
+            is_finished: &mut bool,
+            _bytes_read: &mut usize,
+        ) -> io::Result<Option<Event<Duration, Bundle>>> {
+            if self.0.is_empty() {
+                *is_finished = true;
+                Ok(None)
+            } else {
+                Ok(Some(self.0.remove(0)))
+            }
+        }
+    }
+
+    fn run_events(id: usize) -> Vec<Event<Duration, Bundle>> {
+        vec![
+            Event::Messages(
+                Duration::from_secs(0),
+                vec![(Duration::from_secs(1), WorkerId::new(1), id)],
+            ),
+            Event::Progress(vec![
+                (Duration::from_secs(2), 1),
+                (Duration::from_secs(0), -1),
+            ]),
+            Event::Messages(
+                Duration::from_secs(2),
+                vec![(Duration::from_secs(3), WorkerId::new(1), id)],
+            ),
+            Event::Progress(vec![(Duration::from_secs(2), -1)]),
+        ]
+    }
+
+    #[test]
+    fn runs_are_segmented() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let runs = TargetRuns::new();
+        let mut reader = ReconnectingReader::new(
+            Box::new(Events(run_events(0))),
+            Arc::new(listener),
+            Arc::new(|_socket: TcpStream| {
+                Ok(Box::new(Events(run_events(1))) as Box<dyn EventIterator<_, _> + Send>)
+            }),
+            Duration::from_millis(100),
+            2,
+            Some(runs.clone()),
+        );
+
+        let mut events = Vec::new();
+        let mut is_finished = false;
+        while !is_finished {
+            if let Some(event) = reader.next(&mut is_finished, &mut 0).unwrap() {
+                events.push(event);
+            }
+
+            // Restart the target once its first run finishes
+            if reader.current.is_none() && reader.run() == 0 {
+                let _socket = TcpStream::connect(address).unwrap();
+                while reader.run() == 0 {
+                    if let Some(event) = reader.next(&mut is_finished, &mut 0).unwrap() {
+                        events.push(event);
+                    }
+                }
+            }
+        }
+
+        let messages: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Messages(_, data) => Some(data.clone()),
+                Event::Progress(_) => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (Duration::from_secs(1), WorkerId::new(1), 0),
+                (Duration::from_secs(3), WorkerId::new(1), 0),
+                (Duration::from_secs(4), WorkerId::new(3), 1),
+                (Duration::from_secs(6), WorkerId::new(3), 1),
+            ],
+        );
+
+        // Every capability that was acquired must have been released
+        let mut net = timely::progress::ChangeBatch::new();
+        net.update(Duration::from_secs(0), 1);
+        for event in events {
+            if let Event::Progress(updates) = event {
+                net.extend(updates.into_iter());
+            }
+        }
+        assert!(net.is_empty());
+
+        let runs = runs.snapshot();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(
+            (runs[1].started_at, runs[1].ended_at),
+            (Duration::from_secs(3), Duration::from_secs(6)),
+        );
+    }
+}
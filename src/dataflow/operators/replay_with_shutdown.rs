@@ -4,7 +4,11 @@ use crate::dataflow::{
     utils::Time,
 };
 use abomonation::Abomonation;
-use crossbeam_channel::TryRecvError;
+use crossbeam_channel::{Sender, TryRecvError};
+#[cfg(target_arch = "wasm32")]
+use std::convert::Infallible;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::Builder;
 use std::{
     convert::identity,
     fmt::Debug,
@@ -17,8 +21,7 @@ use std::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
-    thread::Builder,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use timely::{
     dataflow::{
@@ -206,6 +209,7 @@ where
             replays_finished,
             Fuel::unlimited(),
             DEFAULT_REACTIVATION_DELAY,
+            None,
         )
     }
 
@@ -220,6 +224,7 @@ where
         is_running: Arc<AtomicBool>,
         replays_finished: Arc<AtomicUsize>,
         fuel: Fuel,
+        completion_idle: Option<Duration>,
     ) -> Stream<S, D>
     where
         Self: Sized,
@@ -234,6 +239,7 @@ where
             replays_finished,
             fuel,
             DEFAULT_REACTIVATION_DELAY,
+            completion_idle,
         )
     }
 
@@ -247,6 +253,7 @@ where
         replays_finished: Arc<AtomicUsize>,
         fuel: Fuel,
         reactivation_delay: Duration,
+        completion_idle: Option<Duration>,
     ) -> Stream<S, D>
     where
         N: Into<String>,
@@ -270,6 +277,7 @@ where
         replays_finished: Arc<AtomicUsize>,
         mut fuel: Fuel,
         reactivation_delay: Duration,
+        completion_idle: Option<Duration>,
     ) -> Stream<S, D>
     where
         N: Into<String>,
@@ -277,7 +285,7 @@ where
     {
         let worker_index = scope.index();
         let caller = Location::caller();
-        let mut event_streams = self.into_iter().collect::<Vec<_>>();
+        let event_streams = self.into_iter().collect::<Vec<_>>();
         let total_streams = event_streams.len();
 
         tracing::debug!(
@@ -285,6 +293,7 @@ where
             worker_index = worker_index,
             fuel = ?fuel,
             reactivation_delay = ?reactivation_delay,
+            completion_idle = ?completion_idle,
             "started up ReplayWithShutdown instance with {} event streams",
             event_streams.len(),
         );
@@ -303,6 +312,7 @@ where
 
         let address = builder.operator_info().address;
         let activator = scope.activator_for(&address);
+        #[cfg(not(target_arch = "wasm32"))]
         let sync_activator = scope.sync_activator_for(&address);
 
         let (targets, stream) = builder.new_output();
@@ -316,97 +326,48 @@ where
         let logger: Option<TimelyLogger> = scope.log_register().get("timely");
         let mut buffer: Vec<Event<Time, _>> = Vec::new();
 
-        let running = is_running.clone();
         let (senders, receivers): (Vec<_>, Vec<_>) = (0..total_streams)
             .map(|_| crossbeam_channel::bounded(FILE_SOURCED_FUEL.get()))
             .unzip();
 
-        Builder::new()
-            .name(format!("ddshow-replay-worker-{}", worker_index))
-            .spawn(move || {
-                tracing::debug!(worker = worker_index, "spawned thread worker for replay");
-
-                let (mut streams_finished, mut bytes_read) =
-                    (vec![false; total_streams], vec![0; total_streams]);
-
-                'outer: while running.load(Ordering::Acquire)
-                    && !streams_finished.iter().copied().all(identity)
-                {
-                    for ((stream_idx, event_stream), channel) in
-                        event_streams.iter_mut().enumerate().zip(senders.iter())
-                    {
-                        'inner: while !streams_finished[stream_idx] {
-                            let next = event_stream.next(
-                                &mut streams_finished[stream_idx],
-                                &mut bytes_read[stream_idx],
-                            );
-
-                            match next {
-                                Ok(Some(event)) => {
-                                    if let Err(err) = channel.send(event) {
-                                        tracing::error!(
-                                            worker = worker_index,
-                                            "failed to send data to replay channel: {:?}",
-                                            err,
-                                        );
-                                        running.store(false, Ordering::Release);
-
-                                        break 'outer;
-                                    }
-
-                                    if let Err(err) = sync_activator.activate() {
-                                        tracing::error!(
-                                            worker = worker_index,
-                                            is_running = running.load(Ordering::Acquire),
-                                            "replay thread failed to activate replay operator, \
-                                                setting is_running to false: {:?}",
-                                            err,
-                                        );
-                                        running.store(false, Ordering::Release);
-
-                                        break 'outer;
-                                    }
-                                }
-
-                                Ok(None) => break 'inner,
+        let pump = ReplayPump {
+            event_streams,
+            senders,
+            streams_finished: vec![false; total_streams],
+            bytes_read: vec![0; total_streams],
+            last_event: Instant::now(),
+            running: is_running.clone(),
+            completion_idle,
+            worker_index,
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            Builder::new()
+                .name(format!("ddshow-replay-worker-{}", worker_index))
+                .spawn(move || {
+                    tracing::debug!(worker = worker_index, "spawned thread worker for replay");
 
-                                Err(err) => {
-                                    tracing::error!(
-                                        worker = worker_index,
-                                        "encountered an error from the event stream: {:?}",
-                                        err,
-                                    );
-                                    running.store(false, Ordering::Release);
+                    let mut pump = pump;
+                    while pump.pump(|| sync_activator.activate()) {}
 
-                                    break 'outer;
-                                }
-                            }
-                        }
+                    if let Err(err) = sync_activator.activate() {
+                        tracing::error!(
+                            worker = worker_index,
+                            is_running = pump.running.load(Ordering::Acquire),
+                            "replay thread failed to activate replay operator, \
+                                setting is_running to false: {:?}",
+                            err,
+                        );
+                        pump.running.store(false, Ordering::Release);
                     }
-                }
+                })
+                .expect("the thread's name is valid");
+        }
 
-                tracing::debug!(
-                    worker = worker_index,
-                    running = running.load(Ordering::Acquire),
-                    streams_finished = ?streams_finished,
-                    bytes_read = ?bytes_read,
-                    "thread worker for replay on worker {} finished",
-                    worker_index,
-                );
-                drop(senders);
-
-                if let Err(err) = sync_activator.activate() {
-                    tracing::error!(
-                        worker = worker_index,
-                        is_running = running.load(Ordering::Acquire),
-                        "replay thread failed to activate replay operator, \
-                                                setting is_running to false: {:?}",
-                        err,
-                    );
-                    running.store(false, Ordering::Release);
-                }
-            })
-            .expect("the thread's name is valid");
+        // Without threads the operator pumps its own streams every time it's activated
+        #[cfg(target_arch = "wasm32")]
+        let mut pump = Some(pump);
 
         let mut receivers = receivers.into_iter().enumerate().cycle();
 
@@ -433,6 +394,16 @@ where
             } else {
                 fuel.reset();
 
+                #[cfg(target_arch = "wasm32")]
+                {
+                    if pump
+                        .as_mut()
+                        .map_or(false, |pump| !pump.pump(|| Ok::<_, Infallible>(())))
+                    {
+                        pump = None;
+                    }
+                }
+
                 let mut idx = 0;
                 while idx < buffer.len() {
                     let event = &buffer[idx];
@@ -729,3 +700,137 @@ where
         stream
     }
 }
+
+/// Moves events out of the replayed event streams and into the replay operator's channels,
+/// either from a dedicated thread or from within the operator itself on targets without threads
+struct ReplayPump<E, D> {
+    event_streams: Vec<E>,
+    senders: Vec<Sender<Event<Time, D>>>,
+    streams_finished: Vec<bool>,
+    bytes_read: Vec<usize>,
+    last_event: Instant,
+    running: Arc<AtomicBool>,
+    completion_idle: Option<Duration>,
+    worker_index: usize,
+}
+
+impl<E, D> ReplayPump<E, D>
+where
+    E: EventIterator<Time, D>,
+{
+    /// Reads from every stream until they all run dry, calling `activate` after each
+    /// sent event. Returns `false` once the replay is over and the channels are closed
+    fn pump<F, Err>(&mut self, mut activate: F) -> bool
+    where
+        F: FnMut() -> Result<(), Err>,
+        Err: Debug,
+    {
+        let worker_index = self.worker_index;
+        if !self.running.load(Ordering::Acquire)
+            || self.streams_finished.iter().copied().all(identity)
+        {
+            self.finish();
+            return false;
+        }
+
+        for ((stream_idx, event_stream), channel) in self
+            .event_streams
+            .iter_mut()
+            .enumerate()
+            .zip(self.senders.iter())
+        {
+            'inner: while !self.streams_finished[stream_idx] {
+                // Nothing can drain a full channel while we're blocked on it without threads
+                #[cfg(target_arch = "wasm32")]
+                {
+                    if channel.is_full() {
+                        break 'inner;
+                    }
+                }
+
+                let next = event_stream.next(
+                    &mut self.streams_finished[stream_idx],
+                    &mut self.bytes_read[stream_idx],
+                );
+
+                match next {
+                    Ok(Some(event)) => {
+                        self.last_event = Instant::now();
+
+                        if let Err(err) = channel.send(event) {
+                            tracing::error!(
+                                worker = worker_index,
+                                "failed to send data to replay channel: {:?}",
+                                err,
+                            );
+                            self.running.store(false, Ordering::Release);
+                            self.finish();
+
+                            return false;
+                        }
+
+                        if let Err(err) = activate() {
+                            tracing::error!(
+                                worker = worker_index,
+                                is_running = self.running.load(Ordering::Acquire),
+                                "replay thread failed to activate replay operator, \
+                                    setting is_running to false: {:?}",
+                                err,
+                            );
+                            self.running.store(false, Ordering::Release);
+                            self.finish();
+
+                            return false;
+                        }
+                    }
+
+                    Ok(None) => break 'inner,
+
+                    Err(err) => {
+                        tracing::error!(
+                            worker = worker_index,
+                            "encountered an error from the event stream: {:?}",
+                            err,
+                        );
+                        self.running.store(false, Ordering::Release);
+                        self.finish();
+
+                        return false;
+                    }
+                }
+            }
+        }
+
+        // Targets that never close their log streams are considered
+        // finished once their streams have been quiet for long enough
+        if let Some(idle) = self.completion_idle {
+            if self.last_event.elapsed() >= idle {
+                tracing::info!(
+                    worker = worker_index,
+                    "event streams on worker {} have been idle for {:#?}, finishing replay",
+                    worker_index,
+                    idle,
+                );
+                self.finish();
+
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Closes the replay channels so that the operator sees every stream as disconnected
+    fn finish(&mut self) {
+        tracing::debug!(
+            worker = self.worker_index,
+            running = self.running.load(Ordering::Acquire),
+            streams_finished = ?self.streams_finished,
+            bytes_read = ?self.bytes_read,
+            "replay pump on worker {} finished",
+            self.worker_index,
+        );
+
+        self.senders.clear();
+    }
+}
@@ -215,7 +215,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{args::TerminalColor, dataflow::operators::RkyvEventReader};
+    use crate::{
+        args::{Args, TerminalColor},
+        dataflow::operators::RkyvEventReader,
+    };
     use ddshow_sink::EventWriter;
     use ddshow_types::{
         differential_logging::{DifferentialEvent, MergeEvent},
@@ -228,7 +231,11 @@ mod tests {
     // FIXME: Make this a proptest
     #[test]
     fn timely_roundtrip() {
-        crate::logging::init_logging(TerminalColor::Never);
+        crate::logging::init_logging(&Args {
+            color: TerminalColor::Never,
+            ..Default::default()
+        })
+        .unwrap();
 
         let events = vec![
             Event::Progress(vec![
@@ -281,7 +288,11 @@ mod tests {
     // FIXME: Make this a proptest
     #[test]
     fn differential_roundtrip() {
-        crate::logging::init_logging(TerminalColor::Never);
+        crate::logging::init_logging(&Args {
+            color: TerminalColor::Never,
+            ..Default::default()
+        })
+        .unwrap();
 
         let events = vec![
             Event::Progress(vec![
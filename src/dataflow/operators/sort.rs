@@ -1,19 +1,33 @@
-use std::panic::Location;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    io::{self, Write},
+    panic::Location,
+};
 
 use crate::dataflow::operators::Multiply;
+use abomonation::Abomonation;
 use differential_dataflow::{
     difference::Abelian, lattice::Lattice, operators::Reduce, AsCollection, Collection, Data,
     ExchangeData, Hashable,
 };
+use serde::{Deserialize, Serialize};
 use timely::dataflow::{operators::Map, Scope};
+use tinyvec::TinyVec;
 
 #[allow(dead_code)]
 const DEFAULT_HIERARCHICAL_BUCKETS: [u64; 16] =
     [60, 56, 52, 48, 44, 40, 36, 32, 28, 24, 20, 16, 12, 8, 4, 0];
 
+/// Sorts the values of each key into a single vector, ordered by the given key function
+///
+/// The hierarchical variants reduce the values in stages, bucketing them by
+/// the low bits of their hash first so that each update to a large group only
+/// resorts a fraction of its values
 pub trait SortBy<T> {
     type Output;
 
+    /// Sorts the values of each key within a single reduction
     #[track_caller]
     fn sort_by<F, K>(&self, key: F) -> Self::Output
     where
@@ -40,6 +54,7 @@ pub trait SortBy<T> {
         self.hierarchical_sort_core(name, [0].iter().copied(), key)
     }
 
+    /// Sorts the values of each key over multiple reductions, see [`SortBy`]
     #[track_caller]
     fn hierarchical_sort_by<F, K>(&self, key: F) -> Self::Output
     where
@@ -66,6 +81,8 @@ pub trait SortBy<T> {
         self.hierarchical_sort_core(name, DEFAULT_HIERARCHICAL_BUCKETS.iter().copied(), key)
     }
 
+    /// Sorts the values of each key with one reduction for each of the given
+    /// bucket bit-shifts, ending with a shift of `0`
     fn hierarchical_sort_core<B, F, K>(&self, name: &str, buckets: B, key: F) -> Self::Output
     where
         B: IntoIterator<Item = u64>,
@@ -84,7 +101,7 @@ where
     (K, Vec<D>): Hashable,
     ((u64, K), Vec<D>): ExchangeData,
     (u64, K): ExchangeData + Hashable,
-    R: Abelian + ExchangeData + Multiply<Output = R> + Into<isize> + From<i8>,
+    R: Abelian + ExchangeData + Multiply<Output = R> + Into<isize> + From<i8> + Default,
 {
     type Output = Collection<S, (K, Vec<D>), R>;
 
@@ -98,8 +115,13 @@ where
             let this = self.enter_region(region);
 
             // Utilizes hierarchical aggregation to minimize the number of recomputation that must happen
-            let mut hashed =
-                this.map(|(key, data)| ((data.hashed(), key), vec![(data, R::from(1))]));
+            let mut hashed = this.map(|(key, data)| {
+                let hash = data.hashed();
+                let mut values = TinyVec::new();
+                values.push((data, R::from(1)));
+
+                ((hash, key), SortBuffer(values))
+            });
             for bucket in buckets {
                 hashed = build_sort_bucket(hashed, key.clone(), 1u64 << bucket);
             }
@@ -108,6 +130,7 @@ where
                 .inner
                 .map(|(((_hash, key), data), time, diff)| {
                     let data = data
+                        .0
                         .into_iter()
                         .flat_map(|(data, inner_diff)| {
                             (0..inner_diff.into()).map(move |_| data.clone())
@@ -122,8 +145,60 @@ where
     }
 }
 
-// TODO: Switch to `TinyVec<[(D, R); 16]>`
-type Bucketed<S, K, D, R> = Collection<S, ((u64, K), Vec<(D, R)>), R>;
+type Bucketed<S, K, D, R> = Collection<S, ((u64, K), SortBuffer<D, R>), R>;
+
+/// The sorted values of a bucket along with their diffs, most buckets are small
+/// enough for their values to be kept inline without allocating
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize)]
+struct SortBuffer<D, R>(TinyVec<[(D, R); 16]>)
+where
+    D: Default,
+    R: Default;
+
+// `TinyVec` doesn't implement `Abomonation`, so values kept inline are encoded in
+// place and spilled values are encoded the same way as a `Vec`
+impl<D, R> Abomonation for SortBuffer<D, R>
+where
+    D: Abomonation + Default,
+    R: Abomonation + Default,
+{
+    #[inline]
+    unsafe fn entomb<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        match &self.0 {
+            TinyVec::Inline(values) => {
+                for value in values.iter() {
+                    value.entomb(write)?;
+                }
+
+                Ok(())
+            }
+            TinyVec::Heap(values) => values.entomb(write),
+        }
+    }
+
+    #[inline]
+    unsafe fn exhume<'a, 'b>(&'a mut self, mut bytes: &'b mut [u8]) -> Option<&'b mut [u8]> {
+        match &mut self.0 {
+            TinyVec::Inline(values) => {
+                for value in values.iter_mut() {
+                    let remaining = bytes;
+                    bytes = value.exhume(remaining)?;
+                }
+
+                Some(bytes)
+            }
+            TinyVec::Heap(values) => values.exhume(bytes),
+        }
+    }
+
+    #[inline]
+    fn extent(&self) -> usize {
+        match &self.0 {
+            TinyVec::Inline(values) => values.iter().map(Abomonation::extent).sum(),
+            TinyVec::Heap(values) => values.extent(),
+        }
+    }
+}
 
 fn build_sort_bucket<S, K, D, R, F, DK>(
     hashed: Bucketed<S, K, D, R>,
@@ -134,58 +209,73 @@ where
     S: Scope,
     S::Timestamp: Lattice,
     D: Data + Default,
-    Vec<(D, R)>: ExchangeData,
+    SortBuffer<D, R>: ExchangeData,
     ((u64, K), Vec<D>): ExchangeData,
     (u64, K): ExchangeData + Hashable,
-    R: Abelian + ExchangeData + Multiply<Output = R> + From<i8>,
+    R: Abelian + ExchangeData + Multiply<Output = R> + From<i8> + Default,
     F: Fn(&D) -> DK + 'static,
     DK: Ord,
 {
     let input = hashed.map(move |((hash, key), data)| ((hash % bucket, key), data));
 
-    // TODO: The buckets could take advantage of their inputs already being sorted
-    //       by using k-way merges https://en.wikipedia.org/wiki/K-way_merge_algorithm
-    //       See also https://docs.rs/itertools/0.10.0/src/itertools/kmerge_impl.rs.html
-    input.reduce_named::<_, Vec<(D, R)>, R>("SortByBucket", move |_key, input, output| {
-        let mut data = Vec::with_capacity(input.iter().map(|(data, _)| data.len()).sum());
-        data.extend(input.iter().flat_map(|(data, diff)| {
-            data.iter().cloned().map(move |(data, inner_diff)| {
-                (data, {
-                    #[cfg(not(feature = "timely-next"))]
-                    let result = diff.clone() * inner_diff;
-                    #[cfg(feature = "timely-next")]
-                    let result = diff.clone().multiply(&inner_diff);
-                    result
-                })
+    // Every input to a bucket was already sorted by the bucket before it, so rather
+    // than concatenating and resorting them they're k-way merged by their heads
+    // https://en.wikipedia.org/wiki/K-way_merge_algorithm
+    input.reduce_named::<_, SortBuffer<D, R>, R>("SortByBucket", move |_key, input, output| {
+        let mut data: TinyVec<[(D, R); 16]> =
+            TinyVec::with_capacity(input.iter().map(|(data, _)| data.0.len()).sum());
+
+        // Heads are ordered by their sort key and then by their input, which keeps
+        // the merge stable
+        let mut heads: BinaryHeap<_> = input
+            .iter()
+            .enumerate()
+            .filter_map(|(source, (values, _))| {
+                values
+                    .0
+                    .first()
+                    .map(|(value, _)| Reverse((key(value), source, 0)))
             })
-        }));
+            .collect();
+
+        while let Some(Reverse((_, source, idx))) = heads.pop() {
+            let (SortBuffer(values), diff) = &input[source];
+            if let Some((next, _)) = values.get(idx + 1) {
+                heads.push(Reverse((key(next), source, idx + 1)));
+            }
 
-        data.sort_unstable_by_key(|(data, _diff)| key(data));
+            let (value, inner_diff) = &values[idx];
+            #[cfg(not(feature = "timely-next"))]
+            let diff = (*diff).clone() * inner_diff.clone();
+            #[cfg(feature = "timely-next")]
+            let diff = (*diff).clone().multiply(inner_diff);
 
-        let mut idx = 0;
-        while idx + 1 < data.len() {
-            if data[idx].1.is_zero() {
-                data.remove(idx);
-            } else if data[idx].0 == data[idx + 1].0 {
-                let diff = data[idx + 1].1.clone();
+            // Equal values are consolidated as they're merged, values whose diffs
+            // cancel out are overwritten by the next value
+            if data.last().map_or(false, |(last, _)| last == value) {
+                let last_diff = &mut data.last_mut().unwrap().1;
                 #[cfg(not(feature = "timely-next"))]
                 {
-                    data[idx].1 += &diff;
+                    *last_diff += &diff;
                 };
                 #[cfg(feature = "timely-next")]
                 {
-                    differential_dataflow::difference::Semigroup::plus_equals(
-                        &mut data[idx].1,
-                        &diff,
-                    );
+                    differential_dataflow::difference::Semigroup::plus_equals(last_diff, &diff);
                 };
-                data.remove(idx + 1);
             } else {
-                idx += 1;
+                if data.last().map_or(false, |(_, diff)| diff.is_zero()) {
+                    data.pop();
+                }
+
+                data.push((value.clone(), diff));
             }
         }
 
-        output.push((data, R::from(1)));
+        if data.last().map_or(false, |(_, diff)| diff.is_zero()) {
+            data.pop();
+        }
+
+        output.push((SortBuffer(data), R::from(1)));
     })
 }
 
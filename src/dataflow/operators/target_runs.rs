@@ -0,0 +1,68 @@
+use crate::ui::RunStats;
+use ddshow_types::WorkerId;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Events that can be moved into a later run of the target
+pub trait RunSegment {
+    /// Shifts the event's time by `offset` and its worker by `worker_offset`
+    fn segment(&mut self, offset: Duration, worker_offset: usize);
+
+    /// Returns the (possibly shifted) time the event occurred at
+    fn time(&self) -> Duration;
+}
+
+impl<E> RunSegment for (Duration, WorkerId, E) {
+    fn segment(&mut self, offset: Duration, worker_offset: usize) {
+        self.0 += offset;
+        self.1 = WorkerId::new(self.1.into_inner() + worker_offset);
+    }
+
+    fn time(&self) -> Duration {
+        self.0
+    }
+}
+
+/// The runs of the target process that have been observed so far, shared
+/// between every reader of a single log stream
+#[derive(Debug, Clone, Default)]
+pub struct TargetRuns {
+    runs: Arc<Mutex<Vec<RunStats>>>,
+}
+
+impl TargetRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns every run that's been observed so far
+    pub fn snapshot(&self) -> Vec<RunStats> {
+        self.runs.lock().expect("poisoned run lock").clone()
+    }
+
+    #[cfg(feature = "network")]
+    pub(super) fn connected(&self, run: usize, started_at: Duration) {
+        let mut runs = self.runs.lock().expect("poisoned run lock");
+        while runs.len() <= run {
+            let run = runs.len();
+            runs.push(RunStats::new(run));
+        }
+
+        let stats = &mut runs[run];
+        if stats.connections == 0 || started_at < stats.started_at {
+            stats.started_at = started_at;
+        }
+        stats.ended_at = stats.ended_at.max(started_at);
+        stats.connections += 1;
+    }
+
+    #[cfg(feature = "network")]
+    pub(super) fn disconnected(&self, run: usize, ended_at: Duration) {
+        let mut runs = self.runs.lock().expect("poisoned run lock");
+        if let Some(stats) = runs.get_mut(run) {
+            stats.ended_at = stats.ended_at.max(ended_at);
+        }
+    }
+}
@@ -31,6 +31,8 @@ pub trait OperatorExt<G, D, R> {
 
 impl<G: Scope, D: Data, R: Semigroup> OperatorExt<G, D, R> for Collection<G, D, R> {}
 
+/// Captures a stream's events into a crossbeam channel, pass it to
+/// timely's `capture_into()`
 #[derive(Debug, Clone)]
 pub struct CrossbeamPusher<T>(Sender<T>);
 
@@ -47,6 +49,7 @@ impl<T, D> EventPusher<T, D> for CrossbeamPusher<Event<T, D>> {
     }
 }
 
+/// Pulls the events captured by a [`CrossbeamPusher`] back out of its channel
 #[derive(Debug, Clone)]
 pub struct CrossbeamExtractor<T>(Receiver<T>);
 
@@ -64,12 +67,18 @@ where
     /// Extracts in a non-blocking manner, exerting fuel for any data pulled from the channel
     /// and returning when the fuel is exhausted or the channel is empty. Returns `true` if
     /// channel's sending side disconnects and `false` otherwise
-    pub fn extract_with_fuel(
+    ///
+    /// Every update is shown to `observe` before it's added to the sink
+    pub fn extract_with_fuel<F>(
         &self,
         fuel: &mut Fuel,
         sink: &mut HashMap<D, Diff, XXHasher>,
         consumed: &mut ChangeBatch<T>,
-    ) -> bool {
+        mut observe: F,
+    ) -> bool
+    where
+        F: FnMut(&D, Diff),
+    {
         while !fuel.is_exhausted() {
             // Exert one fuel for the channel receive
             fuel.exert(1);
@@ -101,6 +110,7 @@ where
 
                     // Add all the data to the given sink
                     for (data, _time, diff) in data {
+                        observe(&data, diff);
                         sink.entry(data).and_modify(|d| *d += &diff).or_insert(diff);
                     }
                 }
@@ -140,7 +150,8 @@ where
         false
     }
 
-    #[allow(dead_code)]
+    /// Consolidates every update currently in the channel, returning the
+    /// data whose differences don't cancel out
     pub fn extract_all(self) -> Vec<D> {
         let mut data = HashMap::with_hasher(XXHasher::default());
         for (event, _time, diff) in self.extract().into_iter().flat_map(|(_, data)| data) {
@@ -185,6 +196,8 @@ impl<T: Ord, D: Ord> Extract<T, D> for CrossbeamExtractor<Event<T, D>> {
     }
 }
 
+/// Bounds the amount of work done in a single call, either limited to a
+/// number of units of effort that can be reset or unlimited
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Fuel {
     Unlimited,
@@ -207,6 +220,7 @@ impl Fuel {
         matches!(self, Self::Unlimited)
     }
 
+    /// Uses up the given amount of fuel, returns `true` if it's now exhausted
     pub fn exert(&mut self, effort: usize) -> bool {
         if let Self::Limited { fuel, .. } = self {
             *fuel = fuel.saturating_sub(effort);
@@ -223,6 +237,7 @@ impl Fuel {
         }
     }
 
+    /// Refills limited fuel to the amount it was created with
     pub fn reset(&mut self) {
         if let Self::Limited { fuel, default } = self {
             *fuel = default.get();
@@ -0,0 +1,147 @@
+//! Breaks down how long and why each worker parked
+//!
+//! Workers park with a timeout when they have activations scheduled for later
+//! and park indefinitely when they have nothing left to do, so timed parks
+//! that run out their timeouts point towards workers waiting on their own
+//! delayed activations rather than on other workers
+
+use crate::{
+    dataflow::{
+        operators::FilterMapTimed,
+        utils::{Diff, Time, TimelyLogBundle},
+    },
+    ui::ParkStats,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{
+    timely_logging::{ParkEvent, TimelyEvent},
+    WorkerId,
+};
+use differential_dataflow::{operators::Reduce, AsCollection, Collection};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use timely::dataflow::{Scope, Stream};
+
+/// A worker parking with an optional timeout or being unparked
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+enum ParkSignal {
+    Park { timeout: Option<Duration> },
+    Unpark,
+}
+
+pub fn park_stats<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+) -> Collection<S, (WorkerId, ParkStats), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    timely_stream
+        .filter_map_ref_timed_named("Park Events", |&timestamp, &(time, worker, ref event)| {
+            match event {
+                TimelyEvent::Park(ParkEvent::Park(timeout)) => Some((
+                    (worker, (time, ParkSignal::Park { timeout: *timeout })),
+                    timestamp,
+                    1,
+                )),
+                TimelyEvent::Park(ParkEvent::Unpark) => {
+                    Some(((worker, (time, ParkSignal::Unpark)), timestamp, 1))
+                }
+                _ => None,
+            }
+        })
+        .as_collection()
+        .reduce_named("Reduce: Park Stats", |_worker, signals, output| {
+            output.push((tally_parks(signals), 1));
+        })
+}
+
+/// Pairs up each of a worker's time-ordered parks with the unpark that ended it
+fn tally_parks(signals: &[(&(Duration, ParkSignal), Diff)]) -> ParkStats {
+    let mut stats = ParkStats::default();
+    let (mut parked, mut durations) = (None, Vec::new());
+
+    for &(&(time, signal), diff) in signals {
+        for _ in 0..diff.max(0) {
+            match signal {
+                ParkSignal::Park { timeout } => {
+                    parked.get_or_insert((time, timeout));
+                }
+
+                ParkSignal::Unpark => {
+                    if let Some((since, timeout)) = parked.take() {
+                        let duration = time.checked_sub(since).unwrap_or_default();
+                        durations.push(duration);
+
+                        stats.parks += 1;
+                        match timeout {
+                            Some(timeout) => {
+                                stats.timed += 1;
+                                if duration >= timeout {
+                                    stats.timed_out += 1;
+                                }
+                            }
+                            None => stats.indefinite += 1,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    durations.sort_unstable();
+    let percentile = |fraction: f64| {
+        durations
+            .get(((durations.len() as f64 * fraction).ceil() as usize).saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    };
+
+    stats.total = durations.iter().sum();
+    stats.max = durations.last().copied().unwrap_or_default();
+    stats.p50 = percentile(0.5);
+    stats.p90 = percentile(0.9);
+    stats.p99 = percentile(0.99);
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tally_parks, ParkSignal};
+    use std::time::Duration;
+
+    #[test]
+    fn parks_are_paired_with_unparks() {
+        let ms = Duration::from_millis;
+        let signals = vec![
+            (ms(0), ParkSignal::Park { timeout: None }),
+            (ms(10), ParkSignal::Unpark),
+            (
+                ms(12),
+                ParkSignal::Park {
+                    timeout: Some(ms(5)),
+                },
+            ),
+            (ms(17), ParkSignal::Unpark),
+            (
+                ms(20),
+                ParkSignal::Park {
+                    timeout: Some(ms(5)),
+                },
+            ),
+            (ms(21), ParkSignal::Unpark),
+            // Unmatched unparks aren't counted
+            (ms(30), ParkSignal::Unpark),
+        ];
+        let signals: Vec<_> = signals.iter().map(|signal| (signal, 1)).collect();
+
+        let stats = tally_parks(&signals);
+        assert_eq!(stats.parks, 3);
+        assert_eq!((stats.timed, stats.indefinite, stats.timed_out), (2, 1, 1));
+        assert_eq!(stats.total, ms(16));
+        assert_eq!(stats.max, ms(10));
+        assert_eq!(stats.p50, ms(5));
+    }
+}
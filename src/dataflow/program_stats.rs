@@ -1,10 +1,13 @@
-use crate::dataflow::{
-    operators::{
-        DiffDuration, FilterMap, FilterMapTimed, JoinArranged, MapExt, MapTimed, Max, Min,
+use crate::{
+    dataflow::{
+        operators::{
+            DiffDuration, FilterMap, FilterMapTimed, JoinArranged, MapExt, MapTimed, Max, Min,
+        },
+        send_recv::ChannelAddrs,
+        utils::{ArrangedKey, DifferentialLogBundle, Time, TimelyLogBundle},
+        Channel, Diff, OperatorAddr,
     },
-    send_recv::ChannelAddrs,
-    utils::{ArrangedKey, DifferentialLogBundle, Time, TimelyLogBundle},
-    Channel, Diff, OperatorAddr,
+    ui::LoggedEventKind,
 };
 use ddshow_types::{
     differential_logging::{
@@ -20,7 +23,10 @@ use differential_dataflow::{
     AsCollection, Collection, Data,
 };
 use std::time::Duration;
-use timely::dataflow::{operators::Concat, Scope, Stream};
+use timely::dataflow::{
+    operators::{generic::operator, Concat},
+    Scope, Stream,
+};
 
 pub struct GraphStats<S>
 where
@@ -34,6 +40,26 @@ where
     /// The worker which this timespan is for and the `(start, end)` interval
     /// of their events
     pub total_runtime: Collection<S, (WorkerId, (Duration, Duration)), Diff>,
+    /// The number of events of each kind that each worker logged
+    pub event_kinds: Collection<S, ((WorkerId, LoggedEventKind), usize), Diff>,
+}
+
+impl<S> GraphStats<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    /// Stats that are never populated, used when program stats aren't collected
+    pub fn empty(scope: &S) -> Self {
+        Self {
+            workers: operator::empty(scope).as_collection(),
+            operators: operator::empty(scope).as_collection(),
+            dataflows: operator::empty(scope).as_collection(),
+            channels: operator::empty(scope).as_collection(),
+            arrangements: None,
+            total_runtime: operator::empty(scope).as_collection(),
+            event_kinds: operator::empty(scope).as_collection(),
+        }
+    }
 }
 
 // TODO: Overhaul this, we shouldn't be using operator addresses everywhere since that's
@@ -125,6 +151,18 @@ where
         (worker, (start.to_duration(), end.to_duration()))
     });
 
+    let event_kinds = combine_events(
+        timely,
+        |&time, (_, worker, event)| ((worker, LoggedEventKind::from(&event)), time, 1isize),
+        differential,
+        |&time, (_, worker, event)| ((worker, LoggedEventKind::from(&event)), time, 1isize),
+    )
+    .as_collection()
+    .count_total()
+    .map_named("Map: Worker Event Kinds", |(kind, count)| {
+        (kind, count as usize)
+    });
+
     GraphStats {
         workers,
         operators,
@@ -132,6 +170,7 @@ where
         channels,
         arrangements,
         total_runtime,
+        event_kinds,
     }
 }
 
@@ -1,9 +1,12 @@
 #![allow(unused_imports)]
 
-use crate::dataflow::{
-    operators::{FlatSplit, Keys, MapTimed},
-    utils::{Diff, ProgressLogBundle, Time},
-    OperatorShape,
+use crate::{
+    dataflow::{
+        operators::{FlatSplit, Keys, MapTimed},
+        utils::{Diff, ProgressLogBundle, Time, XXHasher},
+        OperatorShape,
+    },
+    ui::{AggregatedStats, EpochLatency, IterationStats},
 };
 use abomonation_derive::Abomonation;
 use ddshow_types::{ChannelId, OperatorAddr, OperatorId, PortId, WorkerId};
@@ -12,8 +15,11 @@ use differential_dataflow::{
     AsCollection, Collection,
 };
 use serde::{Deserialize, Serialize};
-use std::iter;
-use timely::dataflow::{Scope, Stream};
+use std::{collections::HashMap, convert::TryFrom, iter, time::Duration};
+use timely::dataflow::{
+    operators::{Enter, Map},
+    Scope, Stream,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
 pub struct ChannelMessageStats {
@@ -146,6 +152,286 @@ pub struct ProgressStats {
     pub capability_updates: usize,
 }
 
+/// The kinds of progress-tracking traffic that are counted for each progress channel
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub enum ProgressMetric {
+    /// Progress messages sent by the worker
+    Sent,
+    /// Progress messages received by the worker
+    Received,
+    /// Pointstamp updates for messages in flight
+    MessageUpdates,
+    /// Pointstamp updates for operator capabilities
+    CapabilityUpdates,
+}
+
+/// A progress channel, identified by the worker that logged its events,
+/// the address of the scope it tracks progress for and its channel id
+pub type ProgressChannel = (WorkerId, OperatorAddr, ChannelId);
+
+pub struct ProgressCollections<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    /// The amount of each kind of progress traffic carried by each progress channel
+    pub traffic: Collection<S, ((ProgressChannel, ProgressMetric), usize), Diff>,
+    /// The first time that each scope released a capability at each of its timestamps,
+    /// tracing out how the scope's frontier progressed
+    pub frontier_progression: Collection<S, ((WorkerId, OperatorAddr), (Duration, String)), Diff>,
+    /// When each dataflow's frontier passed each of its timestamps on every worker
+    pub dataflow_frontiers: Collection<S, (OperatorAddr, (Duration, String)), Diff>,
+    /// The iterations each iterative scope ran for each of its outer timestamps
+    pub scope_iterations: Collection<S, ((WorkerId, OperatorAddr), IterationStats), Diff>,
+    /// How long each timestamp of each dataflow took to complete
+    pub epoch_latencies: Collection<S, (OperatorAddr, EpochLatency), Diff>,
+}
+
+pub fn progress_stats<S>(
+    scope: &mut S,
+    progress_stream: &Stream<S, ProgressLogBundle>,
+    max_data_points: usize,
+) -> ProgressCollections<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    scope.region_named("Collect progress statistics", |region| {
+        let progress_stream = progress_stream.enter(region);
+
+        // Each event's traffic is counted within its diff so that no
+        // events need to be retained
+        let traffic = progress_stream
+            .flat_map(|(_event_time, worker, event)| {
+                let channel = (worker, event.addr, event.channel);
+                let direction = if event.is_send {
+                    ProgressMetric::Sent
+                } else {
+                    ProgressMetric::Received
+                };
+
+                IntoIterator::into_iter([
+                    ((channel.clone(), direction), 1),
+                    (
+                        (channel.clone(), ProgressMetric::MessageUpdates),
+                        event.messages.len(),
+                    ),
+                    (
+                        (channel, ProgressMetric::CapabilityUpdates),
+                        event.internal.len(),
+                    ),
+                ])
+                .filter(|&(_, count)| count != 0)
+            })
+            .map_timed(|&time, (key, count)| {
+                (key, time, Diff::try_from(count).unwrap_or(Diff::MAX))
+            })
+            .as_collection()
+            .count_total()
+            .map(|(key, count)| (key, count as usize));
+
+        // Only sent events are used so that each capability release is seen once
+        let frontier_progression = progress_stream
+            .flat_map(|(event_time, worker, event)| {
+                let (is_send, addr) = (event.is_send, event.addr);
+
+                event
+                    .internal
+                    .into_iter()
+                    .filter(move |update| is_send && update.diff < 0)
+                    .map(move |update| (((worker, addr.clone()), update.timestamp), event_time))
+            })
+            .map_timed(|&time, data| (data, time, 1))
+            .as_collection()
+            .reduce_named("Reduce: First Timestamp Release", |_, released, output| {
+                // Values are sorted, so the first is the earliest release
+                output.push((*released[0].0, 1));
+            })
+            .map(|((scope, timestamp), released_at)| (scope, (released_at, timestamp)));
+
+        // Iterative scopes have `(outer, iteration)` timestamps, so each of their outer
+        // timestamps is split into the iterations that were released for it
+        let scope_iterations = frontier_progression
+            .flat_map(|(scope, (released_at, timestamp))| {
+                split_iteration(&timestamp)
+                    .map(|(epoch, iteration)| ((scope, epoch.to_owned()), (released_at, iteration)))
+            })
+            .reduce_named("Reduce: Scope Iterations", move |_, releases, output| {
+                let (iterations, total, iteration_times) = tally_iterations(releases);
+                let iteration_times =
+                    AggregatedStats::from_durations(iteration_times, max_data_points);
+
+                output.push(((iterations, total, iteration_times), 1));
+            })
+            .map(|((scope, epoch), (iterations, total, iteration_times))| {
+                let stats = IterationStats {
+                    epoch,
+                    iterations,
+                    total,
+                    iteration_times,
+                };
+
+                (scope, stats)
+            });
+
+        // A dataflow's frontier has only passed a timestamp once every worker released it
+        let dataflow_frontiers = frontier_progression
+            .filter(|((_, addr), _)| addr.is_top_level())
+            .map(|((_worker, addr), (released_at, timestamp))| ((addr, timestamp), released_at))
+            .reduce_named(
+                "Reduce: Dataflow Frontier Advances",
+                |_, released, output| {
+                    // Values are sorted, so the last is the latest release
+                    output.push((*released[released.len() - 1].0, 1));
+                },
+            )
+            .map(|((addr, timestamp), released_at)| (addr, (released_at, timestamp)));
+
+        // Every pointstamp update sent for a dataflow's timestamps, summed over every
+        // worker since each worker broadcasts its own updates exactly once
+        let epoch_latencies = progress_stream
+            .flat_map(|(event_time, _worker, event)| {
+                let addr = event.addr;
+                let is_dataflow_send = event.is_send && addr.is_top_level();
+                let updates = event
+                    .messages
+                    .into_iter()
+                    .map(|update| (update.timestamp, update.diff))
+                    .chain(
+                        event
+                            .internal
+                            .into_iter()
+                            .map(|update| (update.timestamp, update.diff)),
+                    );
+
+                updates
+                    .filter(move |_| is_dataflow_send)
+                    .map(move |(timestamp, diff)| {
+                        ((addr.clone(), timestamp), (event_time, diff as isize))
+                    })
+            })
+            .map_timed(|&time, data| (data, time, 1))
+            .as_collection()
+            .reduce_named("Reduce: Epoch Latency", |_, updates, output| {
+                if let Some((first_seen, completed)) = epoch_span(updates) {
+                    output.push(((first_seen, completed), 1));
+                }
+            })
+            .map(|((addr, timestamp), (first_seen, completed))| {
+                (addr, EpochLatency::new(timestamp, first_seen, completed))
+            });
+
+        ProgressCollections {
+            traffic: traffic.leave_region(),
+            frontier_progression: frontier_progression.leave_region(),
+            dataflow_frontiers: dataflow_frontiers.leave_region(),
+            scope_iterations: scope_iterations.leave_region(),
+            epoch_latencies: epoch_latencies.leave_region(),
+        }
+    })
+}
+
+/// Splits a timestamp printed from timely's `Product` as `(outer, inner)` into its
+/// outer timestamp and iteration counter
+fn split_iteration(timestamp: &str) -> Option<(&str, u64)> {
+    let product = timestamp.strip_prefix('(')?.strip_suffix(')')?;
+    let (outer, inner) = product.rsplit_once(", ")?;
+
+    inner.parse().ok().map(|iteration| (outer, iteration))
+}
+
+/// Counts the iterations that were released in time order, returning the number
+/// of iterations, the time they spanned and the time between each of them
+fn tally_iterations(releases: &[(&(Duration, u64), Diff)]) -> (u64, Duration, Vec<Duration>) {
+    let iterations = releases
+        .iter()
+        .map(|&(&(_, iteration), _)| iteration + 1)
+        .max()
+        .unwrap_or(0);
+
+    let iteration_times: Vec<_> = releases
+        .windows(2)
+        .map(|pair| {
+            let (&(previous, _), &(next, _)) = (pair[0].0, pair[1].0);
+            next.checked_sub(previous).unwrap_or_default()
+        })
+        .collect();
+    let total = iteration_times.iter().sum();
+
+    (iterations, total, iteration_times)
+}
+
+/// Finds when a timestamp was first seen and when the frontier passed it, which
+/// is the last time that its outstanding pointstamps were all retired. Returns
+/// `None` for timestamps that the frontier never passed
+fn epoch_span(updates: &[(&(Duration, isize), Diff)]) -> Option<(Duration, Duration)> {
+    let first_seen = updates.first().map(|&(&(time, _), _)| time)?;
+
+    let (mut outstanding, mut completed) = (0, None);
+    for &(&(time, update), count) in updates {
+        outstanding += update * count;
+        completed = if outstanding == 0 { Some(time) } else { None };
+    }
+
+    completed.map(|completed| (first_seen, completed))
+}
+
+/// The progress-tracking overhead of a single progress channel
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
+pub struct ProgressChannelStats {
+    pub worker: WorkerId,
+    /// The address of the scope the channel tracks progress for
+    pub scope_addr: OperatorAddr,
+    pub channel: ChannelId,
+    pub sent: usize,
+    pub received: usize,
+    pub message_updates: usize,
+    pub capability_updates: usize,
+}
+
+impl ProgressChannelStats {
+    /// The total number of pointstamp updates that went over the channel
+    pub const fn pointstamps(&self) -> usize {
+        self.message_updates + self.capability_updates
+    }
+}
+
+/// Collects the traffic counts of each progress channel into per-channel stats,
+/// sorted by the number of pointstamps they exchanged
+pub fn progress_channel_stats(
+    traffic: &[((ProgressChannel, ProgressMetric), usize)],
+) -> Vec<ProgressChannelStats> {
+    let mut channels: HashMap<ProgressChannel, ProgressChannelStats, XXHasher> = HashMap::default();
+
+    for ((channel, metric), count) in traffic {
+        let stats = channels
+            .entry(channel.clone())
+            .or_insert_with(|| ProgressChannelStats {
+                worker: channel.0,
+                scope_addr: channel.1.clone(),
+                channel: channel.2,
+                ..Default::default()
+            });
+
+        match metric {
+            ProgressMetric::Sent => stats.sent += count,
+            ProgressMetric::Received => stats.received += count,
+            ProgressMetric::MessageUpdates => stats.message_updates += count,
+            ProgressMetric::CapabilityUpdates => stats.capability_updates += count,
+        }
+    }
+
+    let mut channels: Vec<_> = channels.into_iter().map(|(_, stats)| stats).collect();
+    channels.sort_unstable_by(|left, right| {
+        right
+            .pointstamps()
+            .cmp(&left.pointstamps())
+            .then_with(|| left.cmp(right))
+    });
+
+    channels
+}
+
 /*
 pub fn aggregate_channel_messages<S>(
     progress_stream: &Stream<S, ProgressLogBundle>,
@@ -271,3 +557,70 @@ where
         })
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        epoch_span, progress_channel_stats, split_iteration, tally_iterations, ProgressMetric,
+    };
+    use ddshow_types::{ChannelId, OperatorAddr, WorkerId};
+    use std::time::Duration;
+
+    #[test]
+    fn channel_stats_are_sorted_by_pointstamps() {
+        let quiet = (
+            WorkerId::new(0),
+            OperatorAddr::from(vec![0]),
+            ChannelId::new(1),
+        );
+        let noisy = (
+            WorkerId::new(0),
+            OperatorAddr::from(vec![0, 1]),
+            ChannelId::new(2),
+        );
+
+        let traffic = vec![
+            ((quiet.clone(), ProgressMetric::Sent), 1),
+            ((quiet.clone(), ProgressMetric::MessageUpdates), 2),
+            ((noisy.clone(), ProgressMetric::Received), 3),
+            ((noisy.clone(), ProgressMetric::MessageUpdates), 10),
+            ((noisy.clone(), ProgressMetric::CapabilityUpdates), 5),
+        ];
+
+        let stats = progress_channel_stats(&traffic);
+        assert_eq!(stats.len(), 2);
+
+        assert_eq!(stats[0].channel, ChannelId::new(2));
+        assert_eq!(stats[0].scope_addr, OperatorAddr::from(vec![0, 1]));
+        assert_eq!((stats[0].sent, stats[0].received), (0, 3));
+        assert_eq!(stats[0].pointstamps(), 15);
+
+        assert_eq!(stats[1].channel, ChannelId::new(1));
+        assert_eq!((stats[1].sent, stats[1].received), (1, 0));
+        assert_eq!(stats[1].pointstamps(), 2);
+    }
+
+    #[test]
+    fn iterations_are_split_from_product_timestamps() {
+        assert_eq!(split_iteration("(3, 7)"), Some(("3", 7)));
+        assert_eq!(split_iteration("((0, 1), 2)"), Some(("(0, 1)", 2)));
+        assert_eq!(split_iteration("5"), None);
+        assert_eq!(split_iteration("(5, foo)"), None);
+
+        let ms = Duration::from_millis;
+        let releases = vec![(ms(10), 0), (ms(12), 1), (ms(17), 2)];
+        let releases: Vec<_> = releases.iter().map(|release| (release, 1)).collect();
+        assert_eq!(tally_iterations(&releases), (3, ms(7), vec![ms(2), ms(5)]),);
+    }
+
+    #[test]
+    fn epochs_complete_once_their_pointstamps_are_retired() {
+        let ms = Duration::from_millis;
+        let updates = vec![(ms(1), 2), (ms(3), -1), (ms(4), 1), (ms(6), -2)];
+        let updates: Vec<_> = updates.iter().map(|update| (update, 1)).collect();
+        assert_eq!(epoch_span(&updates), Some((ms(1), ms(6))));
+
+        // Timestamps with outstanding pointstamps haven't completed yet
+        assert_eq!(epoch_span(&updates[..3]), None);
+    }
+}
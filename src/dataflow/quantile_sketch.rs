@@ -0,0 +1,243 @@
+//! A mergeable sketch of a distribution's quantiles
+//!
+//! Based off of [DDSketch](https://arxiv.org/abs/1908.10693), each duration is
+//! counted within a logarithmically sized bin so that every quantile is reported
+//! within a fixed relative error of the exact one. Sketches are merged by summing
+//! their bins, so they're used as the difference type of a `count_total()` to keep
+//! them up to date incrementally without holding onto every duration
+
+use abomonation_derive::Abomonation;
+#[cfg(feature = "timely-next")]
+use differential_dataflow::difference::Multiply;
+use differential_dataflow::difference::{Monoid, Semigroup};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    ops::{Add, AddAssign, Mul},
+    time::Duration,
+};
+
+/// The relative accuracy of the quantiles reported by a sketch
+const RELATIVE_ACCURACY: f64 = 0.01;
+/// The ratio between the upper and lower bounds of each bin
+const GAMMA: f64 = (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY);
+/// The bin that zero-length durations are counted within
+const ZERO_BIN: i32 = i32::MIN;
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Abomonation, Deserialize, Serialize,
+)]
+pub struct QuantileSketch {
+    /// The number of durations within each bin, sorted by the bin's index
+    bins: Vec<(i32, isize)>,
+}
+
+impl QuantileSketch {
+    /// Creates a sketch containing a single duration
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            bins: vec![(bin_index(duration), 1)],
+        }
+    }
+
+    /// The number of durations within the sketch
+    pub fn count(&self) -> isize {
+        self.bins.iter().map(|&(_, count)| count.max(0)).sum()
+    }
+
+    /// Estimates the given quantile using the nearest-rank method, the result is
+    /// within [`RELATIVE_ACCURACY`] of the exact quantile
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        let rank = ((quantile * self.count() as f64).ceil() as isize).max(1);
+
+        let mut seen = 0;
+        for &(bin, count) in &self.bins {
+            seen += count.max(0);
+            if seen >= rank {
+                return bin_value(bin);
+            }
+        }
+
+        self.bins
+            .last()
+            .map_or_else(Duration::default, |&(bin, _)| bin_value(bin))
+    }
+
+    fn merge(&mut self, other: &Self) {
+        if other.bins.is_empty() {
+            return;
+        }
+
+        let mut merged = Vec::with_capacity(self.bins.len() + other.bins.len());
+        let (mut left, mut right) = (self.bins.iter().peekable(), other.bins.iter().peekable());
+
+        loop {
+            let next = match (left.peek(), right.peek()) {
+                (Some(&&(left_bin, left_count)), Some(&&(right_bin, right_count))) => {
+                    match left_bin.cmp(&right_bin) {
+                        Ordering::Less => {
+                            left.next();
+                            (left_bin, left_count)
+                        }
+                        Ordering::Greater => {
+                            right.next();
+                            (right_bin, right_count)
+                        }
+                        Ordering::Equal => {
+                            left.next();
+                            right.next();
+                            (left_bin, left_count + right_count)
+                        }
+                    }
+                }
+                (Some(&&bin), None) => {
+                    left.next();
+                    bin
+                }
+                (None, Some(&&bin)) => {
+                    right.next();
+                    bin
+                }
+                (None, None) => break,
+            };
+
+            if next.1 != 0 {
+                merged.push(next);
+            }
+        }
+
+        self.bins = merged;
+    }
+}
+
+fn bin_index(duration: Duration) -> i32 {
+    let nanos = duration.as_nanos() as f64;
+
+    if nanos < 1.0 {
+        ZERO_BIN
+    } else {
+        (nanos.ln() / GAMMA.ln()).ceil() as i32
+    }
+}
+
+/// The representative value of a bin, chosen so that every duration within the
+/// bin is within [`RELATIVE_ACCURACY`] of it
+fn bin_value(bin: i32) -> Duration {
+    if bin == ZERO_BIN {
+        Duration::from_secs(0)
+    } else {
+        Duration::from_nanos((2.0 * GAMMA.powi(bin) / (GAMMA + 1.0)).round() as u64)
+    }
+}
+
+impl Add<Self> for QuantileSketch {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self.merge(&rhs);
+        self
+    }
+}
+
+impl AddAssign<Self> for QuantileSketch {
+    fn add_assign(&mut self, rhs: Self) {
+        self.merge(&rhs);
+    }
+}
+
+impl AddAssign<&Self> for QuantileSketch {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.merge(rhs);
+    }
+}
+
+impl Mul<isize> for QuantileSketch {
+    type Output = Self;
+
+    fn mul(mut self, rhs: isize) -> Self {
+        if rhs == 0 {
+            self.bins.clear();
+        } else {
+            for (_, count) in self.bins.iter_mut() {
+                *count *= rhs;
+            }
+        }
+
+        self
+    }
+}
+
+impl Monoid for QuantileSketch {
+    fn zero() -> Self {
+        Self::default()
+    }
+}
+
+impl Semigroup for QuantileSketch {
+    fn is_zero(&self) -> bool {
+        self.bins.is_empty()
+    }
+
+    #[cfg(feature = "timely-next")]
+    fn plus_equals(&mut self, rhs: &Self) {
+        self.merge(rhs);
+    }
+}
+
+#[cfg(feature = "timely-next")]
+impl Multiply<isize> for QuantileSketch {
+    type Output = Self;
+
+    fn multiply(self, &rhs: &isize) -> Self {
+        self * rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuantileSketch, RELATIVE_ACCURACY};
+    use differential_dataflow::difference::Semigroup;
+    use std::time::Duration;
+
+    fn assert_close(estimate: Duration, exact: Duration) {
+        let error = (estimate.as_secs_f64() - exact.as_secs_f64()).abs() / exact.as_secs_f64();
+        assert!(
+            error <= RELATIVE_ACCURACY,
+            "{:?} isn't within {} of {:?}",
+            estimate,
+            RELATIVE_ACCURACY,
+            exact,
+        );
+    }
+
+    #[test]
+    fn quantiles_are_within_the_relative_accuracy() {
+        let sketch = (1..=1000)
+            .map(|micros| QuantileSketch::new(Duration::from_micros(micros)))
+            .fold(QuantileSketch::default(), |sketch, single| sketch + single);
+
+        assert_eq!(sketch.count(), 1000);
+        assert_close(sketch.quantile(0.5), Duration::from_micros(500));
+        assert_close(sketch.quantile(0.9), Duration::from_micros(900));
+        assert_close(sketch.quantile(0.99), Duration::from_micros(990));
+        assert_close(sketch.quantile(0.999), Duration::from_micros(999));
+        assert_eq!(
+            QuantileSketch::new(Duration::from_secs(0)).quantile(0.5),
+            Duration::from_secs(0),
+        );
+    }
+
+    #[test]
+    fn retractions_cancel_out() {
+        let (fast, slow) = (Duration::from_millis(1), Duration::from_secs(1));
+        let mut sketch = QuantileSketch::new(fast) * 95 + QuantileSketch::new(slow) * 5;
+        assert_close(sketch.quantile(0.9), fast);
+        assert_close(sketch.quantile(0.99), slow);
+
+        sketch += QuantileSketch::new(slow) * -5;
+        assert_close(sketch.quantile(0.99), fast);
+
+        sketch += &(QuantileSketch::new(fast) * -95);
+        assert!(sketch.is_zero());
+    }
+}
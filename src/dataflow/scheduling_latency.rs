@@ -0,0 +1,145 @@
+//! Measures how long operators wait to be scheduled once input arrives for them
+//!
+//! Each message that's sent over a channel is attributed to the operator at the
+//! end of it on the worker it was sent to, the operator's scheduling latency is
+//! the time between the first message that arrived for it and the next time the
+//! worker actually scheduled it. This includes any time the worker spent parked
+//! before it woke up to run the operator, so operators that are starved out by
+//! activation storms or sleepy workers show up as having high latencies
+
+use crate::dataflow::{
+    operator_stats::{sketched_percentiles, ActivationPercentiles},
+    operators::FilterMapTimed,
+    summation::{summation, Summation},
+    utils::{Diff, OpKey, Time, TimelyLogBundle},
+    Channel,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{
+    timely_logging::{OperatesEvent, TimelyEvent},
+    WorkerId,
+};
+use differential_dataflow::{
+    operators::{Join, Reduce},
+    AsCollection, Collection,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use timely::dataflow::{Scope, Stream};
+
+/// Something that happened to an operator, inputs sort before schedules
+/// that happen at the same time since they'll be handled by the schedule
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+enum OperatorWake {
+    Input,
+    Scheduled,
+}
+
+pub struct SchedulingLatency<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    pub latencies: Collection<S, (OpKey, Summation), Diff>,
+    pub percentiles: Collection<S, (OpKey, ActivationPercentiles), Diff>,
+}
+
+pub fn scheduling_latency<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    edges: &Collection<S, (OperatesEvent, Channel, OperatesEvent), Diff>,
+) -> SchedulingLatency<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    // Messages that were sent over each channel, keyed by the worker they were sent to
+    let sent_messages = timely_stream
+        .filter_map_ref_timed_named(
+            "Sent Messages",
+            |&timestamp, &(time, _, ref event)| match event {
+                TimelyEvent::Messages(messages) if messages.is_send && messages.length != 0 => {
+                    let target = WorkerId::new(messages.target.into_inner());
+
+                    Some(((messages.channel, (target, time)), timestamp, 1))
+                }
+                _ => None,
+            },
+        )
+        .as_collection();
+
+    let channel_targets = edges.map(|(_, channel, target)| (channel.channel_id(), target.id));
+    let inputs = sent_messages.join_map(&channel_targets, |_, &(worker, time), &operator| {
+        ((worker, operator), (time, OperatorWake::Input))
+    });
+
+    let schedules = timely_stream
+        .filter_map_ref_timed_named(
+            "Schedule Starts",
+            |&timestamp, &(time, worker, ref event)| match event {
+                TimelyEvent::Schedule(schedule) if schedule.start_stop.is_start() => Some((
+                    ((worker, schedule.id), (time, OperatorWake::Scheduled)),
+                    timestamp,
+                    1,
+                )),
+                _ => None,
+            },
+        )
+        .as_collection();
+
+    let latencies = inputs.concat(&schedules).reduce_named(
+        "Reduce: Scheduling Latency",
+        |_operator, wakes, output| {
+            let (mut latencies, mut pending_since) = (Vec::new(), None);
+            for &(&(time, wake), diff) in wakes {
+                if diff <= 0 {
+                    continue;
+                }
+
+                match wake {
+                    OperatorWake::Input => {
+                        pending_since.get_or_insert(time);
+                    }
+
+                    OperatorWake::Scheduled => {
+                        if let Some(arrived) = pending_since.take() {
+                            latencies.push(time.checked_sub(arrived).unwrap_or_default());
+                        }
+                    }
+                }
+            }
+
+            latencies.sort_unstable();
+            consolidate_latencies(&latencies, output);
+        },
+    );
+
+    SchedulingLatency {
+        latencies: summation(&latencies),
+        percentiles: sketched_percentiles(&latencies),
+    }
+}
+
+/// Counts up runs of identical latencies
+fn consolidate_latencies(latencies: &[Duration], output: &mut Vec<(Duration, Diff)>) {
+    for &latency in latencies {
+        match output.last_mut() {
+            Some((last, count)) if *last == latency => *count += 1,
+            _ => output.push((latency, 1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::consolidate_latencies;
+    use std::time::Duration;
+
+    #[test]
+    fn latencies_are_consolidated() {
+        let (fast, slow) = (Duration::from_micros(5), Duration::from_millis(2));
+
+        let mut output = Vec::new();
+        consolidate_latencies(&[fast, fast, fast, slow], &mut output);
+        assert_eq!(output, vec![(fast, 3), (slow, 1)]);
+    }
+}
@@ -2,14 +2,21 @@ use crate::{
     dataflow::{
         constants::DEFAULT_EXTRACTOR_CAPACITY,
         differential::{ArrangementStats, SplineLevel},
+        operator_stats::ActivationPercentiles,
         operators::{CrossbeamExtractor, Fuel},
-        progress_stats::{Channel, OperatorProgress},
+        progress_stats::{Channel, OperatorProgress, ProgressChannel, ProgressMetric},
+        stats_sink::{StatsSink, StatsSinks},
         summation::Summation,
+        timeline_spill::TimelineSpill,
         utils::{channel_sink, Diff, OpKey, Time, XXHasher},
-        worker_timeline::TimelineEvent,
+        worker_timeline::{TimelineBucket, TimelineEvent},
         OperatorShape,
     },
-    ui::DataflowStats,
+    ui::{
+        ArrangementStats as ArrangementDetails, ChannelBacklog, ChannelLatency,
+        ChannelMessageStats, DataflowStats, EpochLatency, IterationStats, Lifespan,
+        LoggedEventKind, ParkStats, WorkerUtilization,
+    },
 };
 use crossbeam_channel::{Receiver, Sender};
 use ddshow_types::{timely_logging::OperatesEvent, ChannelId, OperatorAddr, OperatorId, WorkerId};
@@ -19,7 +26,7 @@ use differential_dataflow::{
     trace::implementations::ord::OrdKeySpine,
     Collection,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, iter::Cycle, time::Duration};
 use strum::{EnumIter, IntoEnumIterator};
 use timely::{
@@ -38,7 +45,7 @@ type EReceiver<D, R = Diff> = Receiver<Event<Time, Bundled<D, R>>>;
 type Extractor<D, R = Diff> = CrossbeamExtractor<Event<Time, Bundled<D, R>>>;
 
 macro_rules! make_send_recv {
-    ($($name:ident : $ty:ty $(= $diff:ty)?),* $(,)?) => {
+    ($($name:ident : $ty:ty $(= $diff:ty)? $(=> $callback:ident)?),* $(,)?) => {
         #[derive(Clone, Debug)]
         pub struct DataflowSenders {
             $($name: (ESender<$ty, $($diff)?>, bool),)*
@@ -148,9 +155,10 @@ macro_rules! make_send_recv {
             1
         })*;
 
-        #[derive(Clone)]
         pub struct DataflowExtractor {
             $(pub $name: (Extractor<$ty, $($diff)?>, HashMap<$ty, make_send_recv!(@diff $($diff)?), XXHasher>),)*
+            stats_sinks: StatsSinks,
+            timeline_spill: Option<TimelineSpill>,
             step: Cycle<DataflowStepIter>,
             consumed: ChangeBatch<Time>,
             last_consumed: Time,
@@ -169,14 +177,30 @@ macro_rules! make_send_recv {
                             XXHasher::default(),
                         ),
                     ),)*
+                    stats_sinks: Vec::new(),
+                    timeline_spill: None,
                     step: DataflowStep::iter().cycle(),
                     consumed: ChangeBatch::new(),
                     last_consumed: Time::minimum(),
                 }
             }
 
+            /// Passes every result along to the given sinks as it's extracted
+            pub fn with_stats_sinks(mut self, stats_sinks: StatsSinks) -> Self {
+                self.stats_sinks = stats_sinks;
+                self
+            }
+
+            /// Spills the worker timeline to disk whenever it outgrows its budget
+            pub fn with_timeline_spill(mut self, timeline_spill: Option<TimelineSpill>) -> Self {
+                self.timeline_spill = timeline_spill;
+                self
+            }
+
             /// Extract data from the current dataflow in a non-blocking manner
             pub fn extract_with_fuel(&mut self, fuel: &mut Fuel) -> bool {
+                self.spill_timeline();
+
                 let mut is_finished = true;
                 for step in self.step.by_ref().take(NUM_VARIANTS) {
                     if fuel.is_exhausted() {
@@ -187,8 +211,17 @@ macro_rules! make_send_recv {
                         $(
                             DataflowStep::$name => {
                                 let (extractor, sink) = &mut self.$name;
-
-                                if !extractor.extract_with_fuel(fuel, sink, &mut self.consumed) {
+                                let _stats_sinks = &mut self.stats_sinks;
+
+                                let is_disconnected = extractor.extract_with_fuel(
+                                    fuel,
+                                    sink,
+                                    &mut self.consumed,
+                                    |_data, _diff| {
+                                        $(make_send_recv!(@notify _stats_sinks, $callback, _data, _diff);)?
+                                    },
+                                );
+                                if !is_disconnected {
                                     is_finished = false;
                                 }
                             },
@@ -219,12 +252,19 @@ macro_rules! make_send_recv {
 
             // TODO: Does this need to guard against never-disconnected channels?
             pub fn extract_all(mut self) -> DataflowData {
+                let spilled_timeline = self.merge_timeline();
+
                 $({
                     let (extractor, sink) = &mut self.$name;
+                    let _stats_sinks = &mut self.stats_sinks;
+
                     let is_disconnected = extractor.extract_with_fuel(
                         &mut Fuel::unlimited(),
                         sink,
                         &mut self.consumed,
+                        |_data, _diff| {
+                            $(make_send_recv!(@notify _stats_sinks, $callback, _data, _diff);)?
+                        },
                     );
 
                     if !is_disconnected {
@@ -247,10 +287,16 @@ macro_rules! make_send_recv {
                     );
                 )*
 
-                DataflowData::new($($name,)*)
+                let mut data = DataflowData::new($($name,)*);
+                if let Some(timeline_events) = spilled_timeline {
+                    data.timeline_events = timeline_events;
+                }
+
+                data
             }
 
-            #[allow(dead_code)]
+            /// The data that's been extracted so far, without any of the
+            /// timeline events that have been spilled to disk
             pub fn current_dataflow_data(&self) -> DataflowData {
                 $(
                     let $name: Vec<_> = self.$name.1
@@ -269,7 +315,7 @@ macro_rules! make_send_recv {
             }
         }
 
-        #[derive(Clone, Debug, Serialize)]
+        #[derive(Clone, Debug, Default, Deserialize, Serialize)]
         pub struct DataflowData {
             $(pub $name: Vec<$ty>,)*
         }
@@ -294,6 +340,12 @@ macro_rules! make_send_recv {
 
     (@diff) => { Diff };
     (@diff $diff:ty) => { $diff };
+
+    (@notify $sinks:ident, $callback:ident, $data:ident, $diff:ident) => {
+        for stats_sink in $sinks.iter_mut() {
+            StatsSink::$callback(&mut **stats_sink, $data, $diff);
+        }
+    };
 }
 
 type NodeData = (OperatorAddr, OperatesEvent);
@@ -316,19 +368,104 @@ make_send_recv! {
     arrangement_ids: (WorkerId, OperatorId),
     // `(start, end)` durations for each worker's runtime
     total_runtime: (WorkerId, (Duration, Duration)),
-    nodes: NodeData,
+    // How each worker's runtime was split between work, idling and parking
+    worker_utilization: (WorkerId, WorkerUtilization) => on_worker_stats,
+    // The number of events of each kind that each worker logged
+    event_kinds: ((WorkerId, LoggedEventKind), usize),
+    // How long and why each worker parked
+    park_stats: (WorkerId, ParkStats),
+    nodes: NodeData => on_operator,
     edges: EdgeData,
+    // The records and batches each worker sent and received over each channel
+    channel_messages: ((WorkerId, ChannelId), ChannelMessageStats) => on_channel_stats,
+    // The records that were in flight over each channel
+    channel_backlogs: (ChannelId, ChannelBacklog),
+    // The time messages spent between being sent and received over each channel
+    channel_latencies: (ChannelId, ChannelLatency),
+    // The records each worker sent to each worker over exchange channels
+    exchange_matrix: ((WorkerId, WorkerId), usize),
     subgraphs: SubgraphData,
     dataflow_stats: DataflowStats,
-    timeline_events: TimelineEventData,
+    timeline_events: TimelineEventData => on_timeline_event,
+    // Timeline events bucketed into each of the `TIMELINE_TIER_WIDTHS`
+    timeline_buckets: TimelineBucket,
     name_lookup: NameLookupData,
+    // The full names of operators whose names were truncated
+    full_name_lookup: NameLookupData,
     addr_lookup: AddrLookupData,
     operator_shapes: OperatorShape,
     operator_progress: OperatorProgress,
     operator_activations: (OpKey, (Duration, Duration)),
-    summarized: (OpKey, Summation),
+    // When each operator was created and dropped
+    operator_lifespans: (OpKey, Lifespan),
+    summarized: (OpKey, Summation) => on_node_stats,
+    activation_percentiles: (OpKey, ActivationPercentiles),
+    // The number of activations within each `--histogram-buckets` bucket
+    activation_histograms: (OpKey, (usize, usize)),
+    // Each operator's activations and time spent within each `--activation-rate-interval` window
+    activation_rates: (OpKey, (Duration, (usize, Duration))),
+    // When each operator's last activation finished
+    last_activations: (OpKey, Duration),
+    // Activations that exceeded `--anomaly-threshold` times their operator's median
+    // activation time as `(start, (duration, median))`
+    activation_anomalies: (OpKey, (Duration, (Duration, Duration))),
     aggregated_summaries: (OperatorId, Summation),
-    arrangements: (OpKey, ArrangementStats),
+    // Each operator's stats merged across every worker, only with `--aggregate-workers`
+    addr_summaries: (OperatorAddr, Summation),
+    // The `--top-k` operators with the largest total activation time
+    slowest_operators: (OperatorId, Summation),
+    // How long each operator waited to be scheduled after input arrived for it
+    scheduling_latencies: (OpKey, Summation),
+    scheduling_percentiles: (OpKey, ActivationPercentiles),
+    arrangements: (OpKey, ArrangementStats) => on_arrangement_stats,
     aggregated_arrangements: (OperatorId, ArrangementStats),
+    // The sizes, batches, merges and shares of each arrangement, only with differential logging
+    arrangement_details: (OpKey, ArrangementDetails),
     spline_levels: (OpKey, SplineLevel),
+    progress_traffic: ((ProgressChannel, ProgressMetric), usize),
+    // The first time each scope released a capability at each timestamp
+    frontier_progression: ((WorkerId, OperatorAddr), (Duration, String)),
+    // When each dataflow's frontier passed each of its timestamps
+    dataflow_frontiers: (OperatorAddr, (Duration, String)),
+    // The iterations each iterative scope ran for each of its outer timestamps
+    scope_iterations: ((WorkerId, OperatorAddr), IterationStats),
+    // How long each timestamp of each dataflow took to complete
+    epoch_latencies: (OperatorAddr, EpochLatency),
+}
+
+impl DataflowExtractor {
+    /// Moves the extracted timeline events to disk if they're over budget
+    fn spill_timeline(&mut self) {
+        let spill = match self.timeline_spill.as_mut() {
+            Some(spill) if spill.is_over_budget(self.timeline_events.1.len()) => spill,
+            _ => return,
+        };
+
+        if let Err(err) = spill.spill(&mut self.timeline_events.1) {
+            // Failed chunks are never kept so the events are still in memory, but
+            // there's no point in spilling again and the earlier chunks still need merging
+            tracing::error!(
+                "failed to spill timeline events to disk, keeping them in memory: {:?}",
+                err,
+            );
+            spill.stop_spilling();
+        }
+    }
+
+    /// Merges the spilled timeline events back together with the ones in memory,
+    /// returns `None` if nothing was spilled
+    fn merge_timeline(&mut self) -> Option<Vec<TimelineEvent>> {
+        let spill = self.timeline_spill.take()?;
+
+        match spill.merge(self.timeline_events.1.drain()) {
+            Ok(timeline_events) => Some(timeline_events),
+            Err(err) => {
+                tracing::error!(
+                    "failed to merge spilled timeline events, the timeline will be incomplete: {:?}",
+                    err,
+                );
+                None
+            }
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use crate::dataflow::{
     operators::Keys,
-    utils::{ArrangedKey, Diff, Time},
+    utils::{ArrangedKey, ArrangedVal, Diff, Time},
 };
 use abomonation_derive::Abomonation;
 use ddshow_types::{
@@ -8,10 +8,7 @@ use ddshow_types::{
     OperatorAddr, OperatorId, PortId,
 };
 use differential_dataflow::{
-    operators::{
-        arrange::{Arrange, ArrangeByKey},
-        Join, JoinCore, Reduce,
-    },
+    operators::{Join, JoinCore, Reduce},
     Collection,
 };
 use serde::{Deserialize, Serialize};
@@ -45,27 +42,21 @@ impl OperatorShape {
 }
 
 pub fn operator_shapes<S>(
-    operators: &Collection<S, OperatesEvent, Diff>,
+    operators_by_addr: &ArrangedVal<S, OperatorAddr, OperatesEvent>,
+    operator_ids: &ArrangedKey<S, OperatorId>,
     channels: &Collection<S, ChannelsEvent, Diff>,
 ) -> Collection<S, OperatorShape, Diff>
 where
     S: Scope<Timestamp = Time>,
 {
-    let operator_ids: ArrangedKey<_, OperatorId, Diff> = operators
-        .map(|operator| (operator.id, ()))
-        .arrange_named("Arrange: Operator Ids");
-    let operator_addrs = operators
-        .map(|operator| (operator.addr, operator.id))
-        .arrange_by_key_named("Arrange: Operator Addresses");
-
     let inputs = channels.map(|mut channel| {
         channel.scope_addr.push(channel.target[0]);
         (channel.scope_addr, channel.target[1])
     });
 
     let mut operator_inputs = inputs
-        .join_core(&operator_addrs, |_, &input_port, &operator_id| {
-            iter::once((operator_id, input_port))
+        .join_core(operators_by_addr, |_, &input_port, operator| {
+            iter::once((operator.id, input_port))
         })
         .reduce(|_, ports, output| {
             output.push((ports.iter().map(|(&port, _)| port).collect::<Vec<_>>(), 1));
@@ -81,8 +72,8 @@ where
     });
 
     let mut operator_outputs = outputs
-        .join_core(&operator_addrs, |_, &output_port, &operator_id| {
-            iter::once((operator_id, output_port))
+        .join_core(operators_by_addr, |_, &output_port, operator| {
+            iter::once((operator.id, output_port))
         })
         .reduce(|_, ports, output| {
             output.push((ports.iter().map(|(&port, _)| port).collect::<Vec<_>>(), 1));
@@ -92,8 +83,8 @@ where
         .map(|(operator_id, ())| (operator_id, Vec::new()))
         .concat(&operator_outputs);
 
-    operators
-        .map(|operator| (operator.id, operator.addr))
+    operators_by_addr
+        .as_collection(|addr, operator| (operator.id, addr.clone()))
         .join(&operator_inputs)
         .join(&operator_outputs)
         .map(|(operator_id, ((scope_addr, inputs), outputs))| {
@@ -0,0 +1,53 @@
+//! Arrangements that several analyses key their collections by
+//!
+//! Every arrangement holds its own copy of the collection it arranges, so the
+//! operator metadata that program stats, graph extraction and operator shapes
+//! all join against is arranged once per scope and each analysis imports the
+//! same trace instead of building another one
+
+use crate::dataflow::utils::{ArrangedKey, ArrangedVal, Diff, Time};
+use ddshow_types::{timely_logging::OperatesEvent, OperatorAddr, OperatorId};
+use differential_dataflow::{
+    operators::arrange::{Arrange, ArrangeByKey},
+    Collection,
+};
+use timely::dataflow::Scope;
+
+/// The operator arrangements shared between analyses, all of which only hold
+/// the first worker's operators
+pub(super) struct SharedArrangements<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    /// The address of every operator
+    pub operator_addrs: ArrangedKey<S, OperatorAddr>,
+    /// Every operator keyed by its address
+    pub operators_by_addr: ArrangedVal<S, OperatorAddr, OperatesEvent>,
+    /// The id of every operator
+    pub operator_ids: ArrangedKey<S, OperatorId>,
+}
+
+impl<S> SharedArrangements<S>
+where
+    S: Scope<Timestamp = Time>,
+{
+    /// Arranges the given operators, `operator_addrs` is already arranged
+    /// while extracting the timely logs so it's reused as-is
+    pub fn new(
+        operator_addrs: ArrangedKey<S, OperatorAddr>,
+        operators: &Collection<S, OperatesEvent, Diff>,
+    ) -> Self {
+        let operators_by_addr = operators
+            .map(|operator| (operator.addr.clone(), operator))
+            .arrange_by_key_named("ArrangeByKey: Shared Operators by Address");
+        let operator_ids = operators
+            .map(|operator| (operator.id, ()))
+            .arrange_named("Arrange: Shared Operator Ids");
+
+        Self {
+            operator_addrs,
+            operators_by_addr,
+            operator_ids,
+        }
+    }
+}
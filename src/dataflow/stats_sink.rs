@@ -0,0 +1,131 @@
+//! Callbacks for streaming the analysis' results out as they're computed
+//!
+//! Every result is an update to a collection, a positive `diff` adds the
+//! value and a negative one retracts a previously added value. Stats that
+//! change over time are retracted and re-added with their new values
+
+use crate::{
+    args::Analysis,
+    dataflow::{
+        utils::{Diff, OpKey},
+        ArrangementStats, Summation, TimelineEvent,
+    },
+    ui::{ChannelMessageStats, WorkerUtilization},
+};
+use ddshow_types::{timely_logging::OperatesEvent, ChannelId, OperatorAddr, WorkerId};
+
+/// Receives the analysis dataflow's results as they're extracted from it
+///
+/// Every callback does nothing by default, so only the interesting ones
+/// need to be implemented. Sinks are called from the thread that drives the
+/// analysis, which isn't always the thread they were created on
+pub trait StatsSink: Send {
+    /// Called for each operator within the target dataflow
+    fn on_operator(&mut self, _operator: &(OperatorAddr, OperatesEvent), _diff: Diff) {}
+
+    /// Called with each operator's activation stats on each worker
+    fn on_node_stats(&mut self, _stats: &(OpKey, Summation), _diff: Diff) {}
+
+    /// Called with how each worker's time was split between work, idling and parking
+    fn on_worker_stats(&mut self, _stats: &(WorkerId, WorkerUtilization), _diff: Diff) {}
+
+    /// Called with the messages each worker sent and received over each channel
+    fn on_channel_stats(
+        &mut self,
+        _stats: &((WorkerId, ChannelId), ChannelMessageStats),
+        _diff: Diff,
+    ) {
+    }
+
+    /// Called with each arrangement's size and batch stats, only with differential logging
+    fn on_arrangement_stats(&mut self, _stats: &(OpKey, ArrangementStats), _diff: Diff) {}
+
+    /// Called for each event of the worker timeline
+    fn on_timeline_event(&mut self, _event: &TimelineEvent, _diff: Diff) {}
+}
+
+/// The stats sinks that results are passed along to
+pub(crate) type StatsSinks = Vec<Box<dyn StatsSink>>;
+
+/// Passes along only the results produced by a single analysis
+pub(crate) struct AnalysisSink {
+    analysis: Analysis,
+    sink: Box<dyn StatsSink>,
+}
+
+impl AnalysisSink {
+    pub(crate) fn new(analysis: Analysis, sink: Box<dyn StatsSink>) -> Self {
+        Self { analysis, sink }
+    }
+}
+
+impl StatsSink for AnalysisSink {
+    fn on_operator(&mut self, operator: &(OperatorAddr, OperatesEvent), diff: Diff) {
+        if self.analysis == Analysis::Graph {
+            self.sink.on_operator(operator, diff);
+        }
+    }
+
+    fn on_worker_stats(&mut self, stats: &(WorkerId, WorkerUtilization), diff: Diff) {
+        if self.analysis == Analysis::ProgramStats {
+            self.sink.on_worker_stats(stats, diff);
+        }
+    }
+
+    fn on_channel_stats(
+        &mut self,
+        stats: &((WorkerId, ChannelId), ChannelMessageStats),
+        diff: Diff,
+    ) {
+        if self.analysis == Analysis::Graph {
+            self.sink.on_channel_stats(stats, diff);
+        }
+    }
+
+    fn on_arrangement_stats(&mut self, stats: &(OpKey, ArrangementStats), diff: Diff) {
+        if self.analysis == Analysis::Arrangements {
+            self.sink.on_arrangement_stats(stats, diff);
+        }
+    }
+
+    fn on_timeline_event(&mut self, event: &TimelineEvent, diff: Diff) {
+        if self.analysis == Analysis::Timeline {
+            self.sink.on_timeline_event(event, diff);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnalysisSink, StatsSink};
+    use crate::{args::Analysis, ui::WorkerUtilization};
+    use ddshow_types::WorkerId;
+    use std::{cell::Cell, rc::Rc};
+
+    struct CountingSink(Rc<Cell<usize>>);
+
+    impl StatsSink for CountingSink {
+        fn on_worker_stats(&mut self, _stats: &(WorkerId, WorkerUtilization), _diff: isize) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn analysis_sinks_only_see_their_analysis() {
+        let (program, timeline) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let mut sinks = vec![
+            AnalysisSink::new(
+                Analysis::ProgramStats,
+                Box::new(CountingSink(program.clone())),
+            ),
+            AnalysisSink::new(Analysis::Timeline, Box::new(CountingSink(timeline.clone()))),
+        ];
+
+        let stats = (WorkerId::new(0), WorkerUtilization::default());
+        for sink in sinks.iter_mut() {
+            sink.on_worker_stats(&stats, 1);
+        }
+
+        assert_eq!((program.get(), timeline.get()), (1, 0));
+    }
+}
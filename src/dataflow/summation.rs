@@ -6,10 +6,12 @@ use abomonation_derive::Abomonation;
 #[cfg(not(feature = "timely-next"))]
 use differential_dataflow::difference::DiffPair;
 use differential_dataflow::{
-    lattice::Lattice, operators::CountTotal, Collection, ExchangeData, Hashable,
+    lattice::Lattice,
+    operators::{CountTotal, Join, Reduce},
+    Collection, ExchangeData, Hashable,
 };
-use serde::Serialize;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::{iter, time::Duration};
 use timely::{dataflow::Scope, order::TotalOrder};
 
 pub fn summation<S, K>(
@@ -86,16 +88,97 @@ where
             )
         });
 
-    summation
+    summation.join_map(&deviations(collection), |id, stats, &stddev| {
+        (id.clone(), stats.with_stddev(stddev))
+    })
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Serialize)]
+/// Merges the summations of keys that were split across several salts,
+/// pooling the stats of each salt back into a single summation per key
+pub fn merge_salted<S, K>(
+    salted: &Collection<S, ((K, u64), Summation), Diff>,
+) -> Collection<S, (K, Summation), Diff>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+    K: ExchangeData + Hashable,
+{
+    merge_summations(&salted.map(|((key, _salt), stats)| (key, stats)))
+}
+
+/// Merges every summation of each key into a single one that covers the data
+/// points of all of them
+pub fn merge_summations<S, K>(
+    summations: &Collection<S, (K, Summation), Diff>,
+) -> Collection<S, (K, Summation), Diff>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+    K: ExchangeData + Hashable,
+{
+    summations.reduce_named("Reduce: Merge Summations", |_key, summations, output| {
+        let merged = summations
+            .iter()
+            .flat_map(|&(stats, diff)| iter::repeat(*stats).take(diff.max(0) as usize))
+            .reduce(|merged, stats| merged.merge(&stats));
+
+        if let Some(merged) = merged {
+            output.push((merged, 1));
+        }
+    })
+}
+
+/// Calculates the sample standard deviation of each key's durations
+pub fn deviations<S, K>(
+    collection: &Collection<S, (K, Duration), Diff>,
+) -> Collection<S, (K, Duration), Diff>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+    K: ExchangeData + Hashable,
+{
+    collection.reduce_named("Reduce: Standard Deviation", |_key, durations, output| {
+        output.push((standard_deviation(durations), 1));
+    })
+}
+
+/// Calculates the sample standard deviation of the given durations
+/// in a single pass using Welford's algorithm
+fn standard_deviation(durations: &[(&Duration, Diff)]) -> Duration {
+    let (mut count, mut mean, mut squared_distance) = (0.0, 0.0, 0.0);
+    for &(duration, diff) in durations {
+        if diff <= 0 {
+            continue;
+        }
+
+        let (value, weight) = (duration.as_secs_f64(), diff as f64);
+        count += weight;
+
+        let delta = value - mean;
+        mean += delta * weight / count;
+        squared_distance += delta * (value - mean) * weight;
+    }
+
+    if count > 1.0 {
+        Duration::from_secs_f64((squared_distance.max(0.0) / (count - 1.0)).sqrt())
+    } else {
+        Duration::from_secs(0)
+    }
+}
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
 pub struct Summation {
     pub max: Duration,
     pub min: Duration,
     pub total: Duration,
     pub average: Duration,
     pub count: usize,
+    /// The sample standard deviation of the activation durations
+    pub stddev: Duration,
+    /// The standard error of the mean activation duration
+    pub stderr: Duration,
 }
 
 impl Summation {
@@ -112,6 +195,77 @@ impl Summation {
             total,
             average,
             count,
+            stddev: Duration::from_secs(0),
+            stderr: Duration::from_secs(0),
         }
     }
+
+    /// Sets the standard deviation of the summation, deriving the standard error from it
+    pub fn with_stddev(mut self, stddev: Duration) -> Self {
+        self.stddev = stddev;
+        self.stderr = if self.count != 0 {
+            Duration::from_secs_f64(stddev.as_secs_f64() / (self.count as f64).sqrt())
+        } else {
+            Duration::from_secs(0)
+        };
+
+        self
+    }
+
+    /// Combines two summations into one that covers the data points of both
+    pub fn merge(&self, other: &Self) -> Self {
+        let (total, count) = (self.total + other.total, self.count + other.count);
+
+        // Pools the variances of both summations using Chan et al.'s method
+        let squared_distance = |stats: &Self| {
+            stats.stddev.as_secs_f64().powi(2) * stats.count.saturating_sub(1) as f64
+        };
+        let delta = other.average.as_secs_f64() - self.average.as_secs_f64();
+        let pooled = squared_distance(self)
+            + squared_distance(other)
+            + delta.powi(2) * (self.count as f64 * other.count as f64) / count.max(1) as f64;
+        let stddev = if count > 1 {
+            Duration::from_secs_f64((pooled / (count - 1) as f64).sqrt())
+        } else {
+            Duration::from_secs(0)
+        };
+
+        Self::new(
+            self.max.max(other.max),
+            self.min.min(other.min),
+            total,
+            total
+                .checked_div(count as u32)
+                .unwrap_or_else(|| Duration::from_secs(0)),
+            count,
+        )
+        .with_stddev(stddev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{standard_deviation, Summation};
+    use std::time::Duration;
+
+    #[test]
+    fn welford_standard_deviation() {
+        let ms = Duration::from_millis;
+        let (two, four, five, seven, nine) = (ms(2), ms(4), ms(5), ms(7), ms(9));
+        let durations = [(&two, 1), (&four, 3), (&five, 2), (&seven, 1), (&nine, 1)];
+
+        // The sample standard deviation of 2, 4, 4, 4, 5, 5, 7, 9 is sqrt(32 / 7)
+        let stddev = standard_deviation(&durations);
+        assert!((stddev.as_secs_f64() * 1000.0 - (32.0f64 / 7.0).sqrt()).abs() < 1e-6);
+
+        // Merging the summations of two halves pools their deviations
+        let half = |values: &[(&Duration, isize)], total: u64| {
+            Summation::new(ms(0), ms(0), ms(total), ms(total / 4), 4)
+                .with_stddev(standard_deviation(values))
+        };
+        let merged = half(&durations[..2], 14).merge(&half(&durations[2..], 26));
+        assert!((merged.stddev.as_secs_f64() - stddev.as_secs_f64()).abs() < 1e-6);
+        assert!((merged.stderr.as_secs_f64() - stddev.as_secs_f64() / 8.0f64.sqrt()).abs() < 1e-6,);
+        assert_eq!(standard_deviation(&durations[..1]), Duration::from_secs(0));
+    }
 }
@@ -0,0 +1,249 @@
+//! Spills the worker timeline to disk once it outgrows its memory budget,
+//! enabled with `--timeline-memory-budget`
+//!
+//! Whenever the extracted timeline events grow past the budget they're sorted
+//! and written out to a temporary file as a single chunk. Once the analysis
+//! finishes every chunk is merged back together along with the events still
+//! held in memory, consolidating the diffs of events that were split across
+//! chunks so that only the events that still exist are kept
+
+use crate::dataflow::{utils::Diff, TimelineEvent};
+use anyhow::{Context, Result};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    env,
+    fs::{self, File},
+    hash::BuildHasher,
+    io::{BufReader, BufWriter, Write},
+    mem,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Used to give every spill file within the process a unique name
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// The on-disk chunks of the worker timeline
+#[derive(Debug)]
+pub struct TimelineSpill {
+    /// The most events held in memory before they're spilled
+    max_events: usize,
+    chunks: Vec<SpillChunk>,
+}
+
+#[derive(Debug)]
+struct SpillChunk {
+    path: PathBuf,
+    len: usize,
+}
+
+impl TimelineSpill {
+    /// Creates a spill with a budget of `budget_mib` mebibytes
+    pub fn new(budget_mib: NonZeroUsize) -> Self {
+        let event_size = mem::size_of::<(TimelineEvent, Diff)>();
+
+        Self {
+            max_events: (budget_mib.get() * 1024 * 1024 / event_size).max(1),
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if `events` timeline events are over budget
+    pub fn is_over_budget(&self, events: usize) -> bool {
+        events > self.max_events
+    }
+
+    /// Keeps every later event in memory while still merging the chunks that
+    /// have already been spilled
+    pub fn stop_spilling(&mut self) {
+        self.max_events = usize::MAX;
+    }
+
+    /// Writes the given events out to a new chunk, they're only removed from
+    /// memory once the chunk has been fully written
+    pub fn spill<S>(&mut self, events: &mut HashMap<TimelineEvent, Diff, S>) -> Result<()>
+    where
+        S: BuildHasher,
+    {
+        let mut sorted: Vec<_> = events
+            .iter()
+            .filter(|&(_, &diff)| diff != 0)
+            .map(|(event, &diff)| (event, diff))
+            .collect();
+        sorted.sort_unstable_by(|(event1, _), (event2, _)| event1.cmp(event2));
+
+        let len = sorted.len();
+        let path = env::temp_dir().join(format!(
+            "ddshow-timeline-{}-{}.spill",
+            process::id(),
+            SPILL_FILES.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        if let Err(err) = write_chunk(&path, &sorted) {
+            // Partially written chunks can't be merged, the events are still in memory
+            let _ = fs::remove_file(&path);
+            return Err(err);
+        }
+
+        self.chunks.push(SpillChunk { path, len });
+        events.clear();
+
+        tracing::debug!(
+            chunks = self.chunks.len(),
+            "spilled {} timeline events to disk",
+            len,
+        );
+
+        Ok(())
+    }
+
+    /// Merges every spilled chunk with the events still in memory, returning
+    /// every event whose diffs don't cancel out
+    pub fn merge<I>(self, in_memory: I) -> Result<Vec<TimelineEvent>>
+    where
+        I: IntoIterator<Item = (TimelineEvent, Diff)>,
+    {
+        let mut in_memory: Vec<_> = in_memory.into_iter().collect();
+        in_memory.sort_unstable_by(|(event1, _), (event2, _)| event1.cmp(event2));
+        let mut in_memory = in_memory.into_iter();
+
+        let mut readers = Vec::with_capacity(self.chunks.len());
+        for chunk in self.chunks.iter() {
+            let file = File::open(&chunk.path).with_context(|| {
+                anyhow::anyhow!(
+                    "failed to open timeline spill file '{}'",
+                    chunk.path.display(),
+                )
+            })?;
+
+            readers.push((BufReader::new(file), chunk.len));
+        }
+
+        // The in-memory events are the source after all of the chunks
+        let mut next_event = |source: usize| -> Result<Option<(TimelineEvent, Diff)>> {
+            match readers.get_mut(source) {
+                Some((reader, remaining)) if *remaining != 0 => {
+                    *remaining -= 1;
+                    bincode::deserialize_from(reader)
+                        .map(Some)
+                        .context("failed to decode spilled timeline event")
+                }
+                Some(_) => Ok(None),
+                None => Ok(in_memory.next()),
+            }
+        };
+
+        let sources = self.chunks.len() + 1;
+        let mut heap = BinaryHeap::with_capacity(sources);
+        for source in 0..sources {
+            if let Some((event, diff)) = next_event(source)? {
+                heap.push(Reverse((event, source, diff)));
+            }
+        }
+
+        let mut merged: Vec<TimelineEvent> = Vec::new();
+        let mut current: Option<(TimelineEvent, Diff)> = None;
+        while let Some(Reverse((event, source, diff))) = heap.pop() {
+            if let Some((next, next_diff)) = next_event(source)? {
+                heap.push(Reverse((next, source, next_diff)));
+            }
+
+            match current.as_mut() {
+                Some((current_event, current_diff)) if *current_event == event => {
+                    *current_diff += diff;
+                }
+
+                _ => {
+                    if let Some((finished, total)) = current.replace((event, diff)) {
+                        if total != 0 {
+                            merged.push(finished);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((finished, total)) = current {
+            if total != 0 {
+                merged.push(finished);
+            }
+        }
+
+        tracing::debug!(
+            chunks = self.chunks.len(),
+            "merged {} timeline events from {} spilled chunks",
+            merged.len(),
+            self.chunks.len(),
+        );
+
+        Ok(merged)
+    }
+}
+
+fn write_chunk(path: &Path, events: &[(&TimelineEvent, Diff)]) -> Result<()> {
+    let file = File::create(path).with_context(|| {
+        anyhow::anyhow!("failed to create timeline spill file '{}'", path.display())
+    })?;
+
+    let mut writer = BufWriter::new(file);
+    for event in events {
+        bincode::serialize_into(&mut writer, event)
+            .context("failed to encode spilled timeline event")?;
+    }
+
+    writer.flush().with_context(|| {
+        anyhow::anyhow!("failed to write timeline spill file '{}'", path.display())
+    })
+}
+
+impl Drop for TimelineSpill {
+    fn drop(&mut self) {
+        for chunk in self.chunks.iter() {
+            if let Err(err) = fs::remove_file(&chunk.path) {
+                tracing::warn!(
+                    path = %chunk.path.display(),
+                    "failed to remove timeline spill file: {}",
+                    err,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimelineSpill;
+    use crate::dataflow::{EventKind, TimelineEvent};
+    use ddshow_types::WorkerId;
+    use std::{collections::HashMap, num::NonZeroUsize, time::Duration};
+
+    fn event(start: u64) -> TimelineEvent {
+        TimelineEvent::new(
+            WorkerId::new(0),
+            EventKind::Parked,
+            Duration::from_nanos(start),
+            Duration::from_nanos(10),
+        )
+    }
+
+    #[test]
+    fn merges_chunks_and_consolidates() {
+        let mut spill = TimelineSpill::new(NonZeroUsize::new(1).unwrap());
+
+        let mut events: HashMap<_, _> = vec![(event(3), 1), (event(1), 1), (event(2), 1)]
+            .into_iter()
+            .collect();
+        spill.spill(&mut events).unwrap();
+        assert!(events.is_empty());
+
+        // Retracts an event from the first chunk and adds one that's retracted in memory
+        events.extend(vec![(event(2), -1), (event(4), 1), (event(5), 0)]);
+        spill.spill(&mut events).unwrap();
+
+        let merged = spill.merge(vec![(event(0), 1), (event(4), -1)]).unwrap();
+        assert_eq!(merged, vec![event(0), event(1), event(3)]);
+    }
+}
@@ -6,15 +6,20 @@ use crate::{
         ArrangedKey, ArrangedVal, ChannelId, Diff, OperatorAddr, OperatorId, TimelineEvent,
         TimelyLogBundle, WorkerId,
     },
-    ui::Lifespan,
+    ui::{ChannelMessageStats, Lifespan},
 };
 use ddshow_types::timely_logging::{ChannelsEvent, OperatesEvent, StartStop, TimelyEvent};
+#[cfg(not(feature = "timely-next"))]
+use differential_dataflow::difference::DiffPair;
 use differential_dataflow::{
     collection::AsCollection,
-    operators::arrange::{Arrange, ArrangeByKey},
+    operators::{
+        arrange::{Arrange, ArrangeByKey},
+        CountTotal,
+    },
     Collection,
 };
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, iter, time::Duration};
 use timely::{
     communication::message::RefOrMut,
     dataflow::{
@@ -52,6 +57,8 @@ where
     pub(crate) operator_addrs: ArrangedKey<S, OperatorAddr>,
     /// Channel scope addresses
     pub(crate) channel_scope_addrs: ArrangedVal<S, (WorkerId, ChannelId), OperatorAddr>,
+    /// The records and batches each worker sent and received over each channel
+    pub(crate) channel_messages: Collection<S, ((WorkerId, ChannelId), ChannelMessageStats), Diff>,
     /// Dataflow operator ids
     pub(crate) dataflow_ids: ArrangedKey<S, OpKey>,
     /// Timely event data, will be `None` if timeline analysis is disabled
@@ -363,6 +370,72 @@ where
         .as_collection()
         .arrange_by_key_named("ArrangeByKey: Channel Scope Addrs");
 
+    // Each message is a single batch weighted by the number of records it carried,
+    // so counting them up gives the total volume sent and received over each channel
+    let channel_messages = timely_stream
+        .filter_map_ref_timed_named("Channel Messages", |&timestamp, &(_, worker, ref event)| {
+            match event {
+                TimelyEvent::Messages(messages) => Some((
+                    (
+                        (worker, messages.channel),
+                        (messages.is_send, messages.length),
+                    ),
+                    timestamp,
+                    1,
+                )),
+                _ => None,
+            }
+        })
+        .as_collection()
+        .explode(|(channel, (is_send, length))| {
+            let (records, batches) = (length as Diff, 1);
+
+            #[cfg(not(feature = "timely-next"))]
+            let diff = if is_send {
+                DiffPair::new(DiffPair::new(records, batches), DiffPair::new(0, 0))
+            } else {
+                DiffPair::new(DiffPair::new(0, 0), DiffPair::new(records, batches))
+            };
+            #[cfg(feature = "timely-next")]
+            let diff = if is_send {
+                ((records, batches), (0, 0))
+            } else {
+                ((0, 0), (records, batches))
+            };
+
+            iter::once((channel, diff))
+        })
+        .count_total()
+        .map(|x| {
+            #[cfg(not(feature = "timely-next"))]
+            let (
+                channel,
+                DiffPair {
+                    element1:
+                        DiffPair {
+                            element1: records_sent,
+                            element2: batches_sent,
+                        },
+                    element2:
+                        DiffPair {
+                            element1: records_received,
+                            element2: batches_received,
+                        },
+                },
+            ) = x;
+            #[cfg(feature = "timely-next")]
+            let (channel, ((records_sent, batches_sent), (records_received, batches_received))) = x;
+
+            let stats = ChannelMessageStats {
+                records_sent: records_sent as usize,
+                records_received: records_received as usize,
+                batches_sent: batches_sent as usize,
+                batches_received: batches_received as usize,
+            };
+
+            (channel, stats)
+        });
+
     let timeline_events = if disable_timeline {
         None
     } else {
@@ -412,6 +485,7 @@ where
         operator_addrs_to_ids: operator_addrs_to_ids.leave_region(),
         operator_addrs: operator_addrs.leave_region(),
         channel_scope_addrs: channel_scope_addrs.leave_region(),
+        channel_messages: channel_messages.leave_region(),
         dataflow_ids: dataflow_ids.leave_region(),
         timeline_events,
     }
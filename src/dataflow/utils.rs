@@ -1,6 +1,8 @@
 #[cfg(feature = "timely-next")]
 use crate::dataflow::reachability::TrackerEvent;
-use crate::dataflow::{operators::CrossbeamPusher, PROGRAM_NS_GRANULARITY};
+use crate::dataflow::{
+    constants::MAX_OPERATOR_NAME_LEN, operators::CrossbeamPusher, PROGRAM_NS_GRANULARITY,
+};
 use anyhow::{Context, Result};
 use crossbeam_channel::Sender;
 use ddshow_sink::{EventWriter, DIFFERENTIAL_ARRANGEMENT_LOG_FILE, TIMELY_LOG_FILE};
@@ -18,6 +20,7 @@ use differential_dataflow::{
 };
 use std::{
     any::Any,
+    borrow::Cow,
     convert::TryFrom,
     fmt::{self, Debug, Display},
     fs::{self, File},
@@ -36,7 +39,7 @@ use timely::{
     },
     PartialOrder,
 };
-use xxhash_rust::xxh3::Xxh3;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
 
 pub(crate) type Diff = isize;
 pub(crate) type Time = Duration; // Epoch<Duration, Duration>;
@@ -74,6 +77,32 @@ pub(crate) fn granulate(&time: &Time) -> Time {
     minted
 }
 
+/// The separator placed between a truncated name and its hash suffix
+const TRUNCATION_MARKER: &str = "…#";
+
+/// Truncates operator names longer than [`MAX_OPERATOR_NAME_LEN`]
+///
+/// The tail of over-long names is replaced with a hash of the full name so
+/// that operators sharing a long common prefix remain distinguishable
+pub(crate) fn truncate_name(name: &str) -> Cow<'_, str> {
+    if name.len() <= MAX_OPERATOR_NAME_LEN {
+        return Cow::Borrowed(name);
+    }
+
+    // The suffix is the marker followed by 16 hex digits
+    let mut end = MAX_OPERATOR_NAME_LEN - (TRUNCATION_MARKER.len() + 16);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    Cow::Owned(format!(
+        "{}{}{:016x}",
+        &name[..end],
+        TRUNCATION_MARKER,
+        xxh3_64(name.as_bytes()),
+    ))
+}
+
 #[allow(clippy::type_complexity)]
 pub(super) fn channel_sink<S, D>(
     collection: &Collection<S, D, Diff>,
@@ -429,3 +458,31 @@ impl Display for HumanDuration {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::truncate_name;
+    use crate::dataflow::constants::MAX_OPERATOR_NAME_LEN;
+    use std::borrow::Cow;
+
+    #[test]
+    fn short_names_are_untouched() {
+        assert!(matches!(truncate_name("Map"), Cow::Borrowed("Map")));
+    }
+
+    #[test]
+    fn long_names_are_truncated() {
+        let prefix = "Map<Vec<(".repeat(100);
+        let (first, second) = (format!("{}A", prefix), format!("{}B", prefix));
+
+        let (first_truncated, second_truncated) = (truncate_name(&first), truncate_name(&second));
+        assert!(first_truncated.len() <= MAX_OPERATOR_NAME_LEN);
+        assert!(second_truncated.len() <= MAX_OPERATOR_NAME_LEN);
+        assert_ne!(first_truncated, second_truncated);
+        assert_eq!(first_truncated, truncate_name(&first));
+
+        // Truncation must respect char boundaries
+        let wide = "é".repeat(MAX_OPERATOR_NAME_LEN);
+        assert!(truncate_name(&wide).len() <= MAX_OPERATOR_NAME_LEN);
+    }
+}
@@ -2,8 +2,13 @@ use crate::{
     args::Args,
     dataflow::{
         self,
+        clock::{EventTime, StampEvents},
         constants::FILE_SOURCED_FUEL,
-        operators::{EventIterator, Fuel, InspectExt, ReplayWithShutdown},
+        ingest_filter::{FilterIngested, IngestFilter},
+        operators::{
+            BatchedReader, EventBatch, EventIterator, Fuel, InspectExt, MapInPlace,
+            ReplayWithShutdown, Unbatch,
+        },
         utils::Time,
         DataflowSenders,
     },
@@ -11,6 +16,7 @@ use crate::{
     replay_loading::{
         DifferentialReplaySource, ProgressReplaySource, ReplaySource, TimelyReplaySource,
     },
+    status::{CaptureStatus, EventSource},
 };
 use anyhow::Result;
 use ddshow_types::{
@@ -28,10 +34,7 @@ use std::{
 };
 use timely::{
     communication::Allocate,
-    dataflow::{
-        operators::{Filter, Map},
-        ProbeHandle, Scope, Stream,
-    },
+    dataflow::{operators::Inspect, ProbeHandle, Scope, Stream},
     logging::TimelyEvent as RawTimelyEvent,
     worker::Worker,
 };
@@ -45,7 +48,7 @@ pub fn worker_runtime<A>(
     replay_shutdown: Arc<AtomicBool>,
     workers_finished: Arc<AtomicUsize>,
     replays_finished: Arc<AtomicUsize>,
-    // multi_progress: Arc<MultiProgress>,
+    capture_status: Arc<CaptureStatus>,
     timely_traces: TimelyReplaySource,
     differential_traces: Option<DifferentialReplaySource>,
     progress_traces: Option<ProgressReplaySource>,
@@ -128,6 +131,18 @@ where
             if fuel.is_unlimited() { "unlimited" } else { "limited" },
         );
 
+        // Events the user doesn't care about are dropped as soon as
+        // they're replayed so they never take up space in the dataflow
+        let ingest_filter = IngestFilter::new(&args);
+        if !ingest_filter.is_passthrough() {
+            tracing::debug!(
+                worker_id = scope.index(),
+                ignore_events = ?args.ignore_events,
+                only_workers = ?args.only_workers,
+                "filtering ingested events",
+            );
+        }
+
         // The timely log stream filtered down to worker 0's events
         let span = tracing::info_span!("replay timely logs", worker_id = scope.index());
         let timely_stream = span.in_scope(|| {
@@ -140,9 +155,13 @@ where
                 replays_finished.clone(),
                 fuel.clone(),
                 "Timely",
+                &capture_status,
+                EventSource::Timely,
                 &mut source_counter,
                 total_sources,
             )
+            .filter_ingested(&ingest_filter)
+            .unbatch()
         });
 
         let span = tracing::info_span!("replay differential logs", worker_id = scope.index());
@@ -157,10 +176,16 @@ where
                     replays_finished.clone(),
                     fuel.clone(),
                     "Differential",
+                    &capture_status,
+                    EventSource::Differential,
                     &mut source_counter,
                     total_sources,
                 )
-                .filter(|(_, _, event)| !event.is_trace_share());
+                .map_in_place_named("Filter Trace Shares", |batch| {
+                    batch.retain(|_, _, event| !event.is_trace_share())
+                })
+                .filter_ingested(&ingest_filter)
+                .unbatch();
 
                 Some(stream)
             } else {
@@ -185,9 +210,13 @@ where
                     replays_finished.clone(),
                     fuel.clone(),
                     "Progress",
+                    &capture_status,
+                    EventSource::Progress,
                     &mut source_counter,
                     total_sources,
-                );
+                )
+                .filter_ingested(&ingest_filter)
+                .unbatch();
 
                 Ok(Some(stream))
             } else {
@@ -265,16 +294,18 @@ where
 #[allow(clippy::too_many_arguments)]
 fn replay_traces<S, Event, RawEvent, R, A>(
     scope: &mut S,
-    _args: &Args,
+    args: &Args,
     probe: ProbeHandle<Time>,
     traces: ReplaySource<R, A>,
     replay_shutdown: Arc<AtomicBool>,
     replays_finished: Arc<AtomicUsize>,
     fuel: Fuel,
     source: &'static str,
+    capture_status: &Arc<CaptureStatus>,
+    event_source: EventSource,
     source_counter: &mut usize,
     _total_sources: usize,
-) -> Stream<S, (Duration, WorkerId, Event)>
+) -> Stream<S, EventBatch<Event>>
 where
     S: Scope<Timestamp = Time>,
     Event: Data + From<RawEvent> + Send,
@@ -300,17 +331,27 @@ where
         source.to_lowercase(),
     );
 
+    let completion_idle = args.completion_idle.map(Duration::from_secs);
+
+    // Each message is replayed as a single columnar batch of events, raw
+    // events are converted into ddshow's own types as they're batched
     match traces {
-        ReplaySource::Rkyv(rkyv) => rkyv.replay_with_shutdown_into_named(
-            &name,
-            scope,
-            probe,
-            replay_shutdown,
-            replays_finished,
-            fuel,
-        ),
+        ReplaySource::Rkyv(rkyv) => rkyv
+            .into_iter()
+            .map(BatchedReader::<R, (Duration, WorkerId, Event), Event>::new)
+            .replay_with_shutdown_into_named(
+                &name,
+                scope,
+                probe,
+                replay_shutdown,
+                replays_finished,
+                fuel,
+                completion_idle,
+            ),
 
         ReplaySource::Abomonation(abomonation) => abomonation
+            .into_iter()
+            .map(BatchedReader::<A, (Duration, usize, RawEvent), Event>::new)
             .replay_with_shutdown_into_named(
                 &name,
                 scope,
@@ -318,12 +359,17 @@ where
                 replay_shutdown,
                 replays_finished,
                 fuel,
-            )
-            .map(|(time, worker, event): (Duration, usize, RawEvent)| {
-                (time, WorkerId::new(worker), Event::from(event))
-            }),
+                completion_idle,
+            ),
     }
     .debug_inspect(
-        move |x| tracing::trace!(target: "raw_event_streams", "{} event: {:?}", source, x),
+        move |x| tracing::trace!(target: "raw_event_streams", "{} events: {:?}", source, x),
     )
+    .inspect_batch({
+        let capture_status = capture_status.clone();
+        move |_, batches| {
+            capture_status.record(event_source, batches.iter().map(EventBatch::len).sum())
+        }
+    })
+    .stamp_events(EventTime)
 }
@@ -1,7 +1,7 @@
 use crate::dataflow::{
-    constants::EVENT_NS_MARGIN,
+    constants::{EVENT_NS_MARGIN, TIMELINE_TIER_WIDTHS},
     operators::{Multiply, Split},
-    utils::{Diff, DifferentialLogBundle, Time, XXHasher},
+    utils::{Diff, DifferentialLogBundle, OpKey, Time, XXHasher},
 };
 use abomonation_derive::Abomonation;
 use ddshow_types::{
@@ -10,10 +10,11 @@ use ddshow_types::{
     OperatorId, WorkerId,
 };
 use differential_dataflow::{
-    difference::Abelian, lattice::Lattice, AsCollection, Collection, ExchangeData,
+    difference::Abelian, lattice::Lattice, operators::Reduce, AsCollection, Collection,
+    ExchangeData, Hashable,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, mem, time::Duration};
+use std::{collections::HashMap, mem, num::NonZeroUsize, time::Duration};
 use timely::dataflow::{
     channels::{pact::Pipeline, pushers::Tee},
     operators::{
@@ -52,6 +53,87 @@ where
     })
 }
 
+/// Buckets timeline events into each of the [`TIMELINE_TIER_WIDTHS`], events are
+/// attributed entirely to the bucket they started within
+///
+/// With `salts` each bucket is first summed across that many keys, salted by
+/// the start times of its events, so the coarsest tiers' buckets aren't each
+/// summed on a single worker
+pub(super) fn timeline_tiers<S>(
+    timeline_events: &Collection<S, TimelineEvent, Diff>,
+    salts: Option<NonZeroUsize>,
+) -> Collection<S, TimelineBucket, Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    let salts = salts.map_or(1, |salts| salts.get() as u64);
+
+    let buckets = timeline_events
+        .flat_map(move |event| {
+            let category = EventCategory::from(event.event);
+            let salt = if salts > 1 {
+                event.start_time.hashed() % salts
+            } else {
+                0
+            };
+
+            IntoIterator::into_iter(TIMELINE_TIER_WIDTHS)
+                .enumerate()
+                .map(move |(tier, width)| {
+                    (
+                        (
+                            (
+                                event.worker,
+                                tier,
+                                category,
+                                event.start_time / width * width,
+                            ),
+                            salt,
+                        ),
+                        (event.duration, event.collapsed_events),
+                    )
+                })
+        })
+        .reduce_named("Reduce: Timeline Tiers", |_bucket, events, output| {
+            output.push((sum_bucket(events), 1));
+        });
+
+    let buckets = if salts > 1 {
+        buckets
+            .map(|((bucket, _salt), sums)| (bucket, sums))
+            .reduce_named(
+                "Reduce: Merge Salted Timeline Tiers",
+                |_bucket, sums, output| {
+                    output.push((sum_bucket(sums), 1));
+                },
+            )
+    } else {
+        buckets.map(|((bucket, _salt), sums)| (bucket, sums))
+    };
+
+    buckets.map(
+        |((worker, tier, category, start_time), (busy_time, events))| TimelineBucket {
+            worker,
+            tier,
+            category,
+            start_time,
+            busy_time,
+            events,
+        },
+    )
+}
+
+/// Sums the busy time and collapsed events of a timeline bucket
+fn sum_bucket(events: &[(&(u64, usize), Diff)]) -> (u64, usize) {
+    let (mut busy_time, mut collapsed_events) = (0, 0);
+    for &(&(duration, collapsed), diff) in events {
+        busy_time += duration * diff as u64;
+        collapsed_events += collapsed * diff as usize;
+    }
+
+    (busy_time, collapsed_events)
+}
+
 pub(super) type TimelineStreamEvent = (TimelineEvent, Time, Diff);
 pub(super) type EventMap = HashMap<(WorkerId, EventKind), Vec<Duration>, XXHasher>;
 type EventOutput<'a> = OutputHandle<'a, Time, TimelineStreamEvent, Tee<Time, TimelineStreamEvent>>;
@@ -392,6 +474,7 @@ impl<'a, 'b> EventProcessor<'a, 'b> {
                 | EventKind::Progress
                 | EventKind::Input
                 | EventKind::Parked
+                | EventKind::MinorActivity
                 | EventKind::Application { .. } => {
                     self.event_map.insert((worker, event_kind), value_stack);
                 }
@@ -410,6 +493,71 @@ impl<'a, 'b> EventProcessor<'a, 'b> {
     }
 }
 
+/// Compacts the extracted timeline, merging each worker's events of the same
+/// kind that are less than `merge_gap` apart into a single event
+///
+/// Events shorter than `minor_threshold` are coalesced into
+/// [`EventKind::MinorActivity`] spans regardless of their kind, which are
+/// merged across gaps of up to `minor_threshold` as well. Merged events span
+/// from the start of their first event to the end of their last, while
+/// `collapsed_events` keeps counting every event that went into them
+pub fn compact_timeline(
+    mut events: Vec<TimelineEvent>,
+    merge_gap: Duration,
+    minor_threshold: Duration,
+) -> Vec<TimelineEvent> {
+    let (merge_gap, minor_threshold) = (
+        merge_gap.as_nanos() as u64,
+        minor_threshold.as_nanos() as u64,
+    );
+    let minor_gap = merge_gap.max(minor_threshold);
+
+    events.sort_unstable_by_key(|event| (event.worker, event.start_time));
+
+    // The index of the last compacted event of each kind on each worker
+    let mut open: HashMap<(WorkerId, EventKind), usize, XXHasher> = HashMap::default();
+    let mut compacted: Vec<TimelineEvent> = Vec::with_capacity(events.len());
+
+    for mut event in events {
+        if event.duration < minor_threshold {
+            event.event = EventKind::MinorActivity;
+        }
+
+        let gap = if event.event == EventKind::MinorActivity {
+            minor_gap
+        } else {
+            merge_gap
+        };
+
+        let key = (event.worker, event.event);
+        if let Some(&idx) = open.get(&key) {
+            let span = &mut compacted[idx];
+            let span_end = span.start_time + span.duration;
+
+            if event.start_time <= span_end + gap {
+                span.duration = span_end.max(event.start_time + event.duration) - span.start_time;
+                span.collapsed_events += event.collapsed_events;
+
+                continue;
+            }
+        }
+
+        open.insert(key, compacted.len());
+        compacted.push(event);
+    }
+
+    tracing::debug!(
+        "compacted {} timeline events into {}",
+        compacted
+            .iter()
+            .map(|event| event.collapsed_events)
+            .sum::<usize>(),
+        compacted.len(),
+    );
+
+    compacted
+}
+
 // TODO: This may be slightly unreliable
 #[allow(dead_code)]
 fn collapse_events<S, R>(
@@ -536,13 +684,21 @@ where
     Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Abomonation,
 )]
 pub enum EventKind {
-    OperatorActivation { operator_id: OperatorId },
-    Application { id: usize },
+    OperatorActivation {
+        operator_id: OperatorId,
+    },
+    Application {
+        id: usize,
+    },
     Parked,
     Input,
     Message,
     Progress,
-    Merge { operator_id: OperatorId },
+    Merge {
+        operator_id: OperatorId,
+    },
+    /// A span of events that were each shorter than `--timeline-minor-threshold`
+    MinorActivity,
 }
 
 impl EventKind {
@@ -586,4 +742,101 @@ impl TimelineEvent {
             collapsed_events: 1,
         }
     }
+
+    /// The operator that the event belongs to, if any
+    pub const fn operator(&self) -> Option<OpKey> {
+        match self.event {
+            EventKind::OperatorActivation { operator_id } | EventKind::Merge { operator_id } => {
+                Some((self.worker, operator_id))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The general kind of a timeline event, used to group events
+/// within each timeline bucket
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Abomonation,
+)]
+pub enum EventCategory {
+    Activation,
+    Application,
+    Parked,
+    Input,
+    Message,
+    Progress,
+    Merge,
+    MinorActivity,
+}
+
+impl From<EventKind> for EventCategory {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::OperatorActivation { .. } => Self::Activation,
+            EventKind::Application { .. } => Self::Application,
+            EventKind::Parked => Self::Parked,
+            EventKind::Input => Self::Input,
+            EventKind::Message => Self::Message,
+            EventKind::Progress => Self::Progress,
+            EventKind::Merge { .. } => Self::Merge,
+            EventKind::MinorActivity => Self::MinorActivity,
+        }
+    }
+}
+
+/// The timeline events of a single category that started within
+/// a window of one of the timeline tiers
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, Abomonation,
+)]
+pub struct TimelineBucket {
+    pub worker: WorkerId,
+    /// The index of the bucket's tier within [`TIMELINE_TIER_WIDTHS`]
+    pub tier: usize,
+    pub category: EventCategory,
+    pub start_time: u64,
+    /// The combined duration of every event within the bucket
+    pub busy_time: u64,
+    pub events: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compact_timeline, EventKind, TimelineEvent};
+    use ddshow_types::{OperatorId, WorkerId};
+    use std::time::Duration;
+
+    fn event(kind: EventKind, start: u64, duration: u64) -> TimelineEvent {
+        TimelineEvent::new(
+            WorkerId::new(0),
+            kind,
+            Duration::from_nanos(start),
+            Duration::from_nanos(duration),
+        )
+    }
+
+    #[test]
+    fn compaction_merges_nearby_events() {
+        let (activation, ns) = (
+            EventKind::activation(OperatorId::new(1)),
+            Duration::from_nanos,
+        );
+        let events = vec![
+            event(activation, 100, 50),
+            event(activation, 0, 50),
+            event(EventKind::Parked, 400, 2),
+            event(EventKind::Input, 406, 1),
+            event(activation, 500, 50),
+        ];
+
+        let mut minor = event(EventKind::MinorActivity, 400, 7);
+        minor.collapsed_events = 2;
+        let mut merged = event(activation, 0, 150);
+        merged.collapsed_events = 2;
+
+        let mut compacted = compact_timeline(events, ns(50), ns(5));
+        compacted.sort_by_key(|event| event.start_time);
+        assert_eq!(compacted, vec![merged, minor, event(activation, 500, 50)],);
+    }
 }
@@ -0,0 +1,187 @@
+//! Breaks down how each worker spent its time over the run
+//!
+//! A worker is busy whenever it's within the schedule of any operator, parked
+//! between each park and the following unpark and idle for the rest of its
+//! runtime, which is mostly spent spinning within `step()` and handling
+//! communication
+//!
+//! Progress tracking happens within the schedules of subgraphs, so the time
+//! workers spend within guarded progress handling is carved out of their busy
+//! time to tell how much of it went towards progress rather than operator work
+
+use crate::{
+    dataflow::{
+        operators::FilterMapTimed,
+        utils::{Diff, Time, TimelyLogBundle},
+    },
+    ui::WorkerUtilization,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{
+    timely_logging::{ParkEvent, TimelyEvent},
+    WorkerId,
+};
+use differential_dataflow::{
+    operators::{Join, Reduce},
+    AsCollection, Collection,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use timely::dataflow::{Scope, Stream};
+
+/// Something a worker did, stops sort before starts that happen at the same
+/// time so that back-to-back schedules don't overlap
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+enum WorkerEvent {
+    ScheduleStop,
+    ProgressStop,
+    ScheduleStart,
+    ProgressStart,
+    Park,
+    Unpark,
+}
+
+/// The time a worker spent on each kind of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct WorkerTime {
+    busy: Duration,
+    parked: Duration,
+    progress: Duration,
+}
+
+pub fn worker_utilization<S>(
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    total_runtime: &Collection<S, (WorkerId, (Duration, Duration)), Diff>,
+) -> Collection<S, (WorkerId, WorkerUtilization), Diff>
+where
+    S: Scope<Timestamp = Time>,
+{
+    let worker_events = timely_stream
+        .filter_map_ref_timed_named(
+            "Worker Utilization Events",
+            |&timestamp, &(time, worker, ref event)| {
+                let event = match event {
+                    TimelyEvent::Schedule(schedule) if schedule.start_stop.is_start() => {
+                        WorkerEvent::ScheduleStart
+                    }
+                    TimelyEvent::Schedule(_) => WorkerEvent::ScheduleStop,
+                    TimelyEvent::GuardedProgress(progress) if progress.is_start => {
+                        WorkerEvent::ProgressStart
+                    }
+                    TimelyEvent::GuardedProgress(_) => WorkerEvent::ProgressStop,
+                    TimelyEvent::Park(ParkEvent::Park(_)) => WorkerEvent::Park,
+                    TimelyEvent::Park(ParkEvent::Unpark) => WorkerEvent::Unpark,
+                    _ => return None,
+                };
+
+                Some(((worker, (time, event)), timestamp, 1))
+            },
+        )
+        .as_collection();
+
+    worker_events
+        .reduce_named("Reduce: Worker Utilization", |_worker, events, output| {
+            let time = tally_worker_time(events);
+            output.push(((time.busy, time.parked, time.progress), 1));
+        })
+        .join_map(
+            total_runtime,
+            |&worker, &(busy, parked, progress), &(start, end)| {
+                let runtime = end.checked_sub(start).unwrap_or_default();
+                (
+                    worker,
+                    WorkerUtilization::new(runtime, busy, parked, progress),
+                )
+            },
+        )
+}
+
+/// Sums up the time a worker spent on each kind of work from its time-ordered events
+fn tally_worker_time(events: &[(&(Duration, WorkerEvent), Diff)]) -> WorkerTime {
+    let mut time = WorkerTime::default();
+    let (mut depth, mut busy_since, mut parked_since, mut progress_since) =
+        (0usize, None, None, None);
+
+    for &(&(timestamp, event), diff) in events {
+        for _ in 0..diff.max(0) {
+            match event {
+                // Operators are nested within their scopes' schedules, so only the
+                // outermost schedule counts towards the worker's busy time
+                WorkerEvent::ScheduleStart => {
+                    if depth == 0 {
+                        busy_since = Some(timestamp);
+                    }
+                    depth += 1;
+                }
+
+                WorkerEvent::ScheduleStop => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        if let Some(started) = busy_since.take() {
+                            time.busy += timestamp.checked_sub(started).unwrap_or_default();
+                        }
+                    }
+                }
+
+                WorkerEvent::ProgressStart => {
+                    progress_since.get_or_insert(timestamp);
+                }
+
+                WorkerEvent::ProgressStop => {
+                    if let Some(since) = progress_since.take() {
+                        time.progress += timestamp.checked_sub(since).unwrap_or_default();
+                    }
+                }
+
+                WorkerEvent::Park => {
+                    parked_since.get_or_insert(timestamp);
+                }
+
+                WorkerEvent::Unpark => {
+                    if let Some(since) = parked_since.take() {
+                        time.parked += timestamp.checked_sub(since).unwrap_or_default();
+                    }
+                }
+            }
+        }
+    }
+
+    time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tally_worker_time, WorkerEvent, WorkerTime};
+    use std::time::Duration;
+
+    #[test]
+    fn nested_schedules_and_parks() {
+        let ms = Duration::from_millis;
+        let events = vec![
+            (ms(0), WorkerEvent::ScheduleStart),
+            (ms(1), WorkerEvent::ScheduleStart),
+            (ms(3), WorkerEvent::ScheduleStop),
+            (ms(3), WorkerEvent::ProgressStart),
+            (ms(4), WorkerEvent::ScheduleStop),
+            (ms(4), WorkerEvent::ProgressStop),
+            (ms(5), WorkerEvent::Park),
+            (ms(15), WorkerEvent::Unpark),
+            (ms(16), WorkerEvent::ScheduleStart),
+            (ms(18), WorkerEvent::ScheduleStop),
+            // Unmatched parks aren't counted
+            (ms(20), WorkerEvent::Park),
+        ];
+        let events: Vec<_> = events.iter().map(|event| (event, 1)).collect();
+
+        assert_eq!(
+            tally_worker_time(&events),
+            WorkerTime {
+                busy: ms(6),
+                parked: ms(10),
+                progress: ms(1),
+            },
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Comparing two runs saved with `--dump-json` or `--dump-binary`,
+//! invoked as `ddshow diff <old> <new>`
+//!
+//! Operators are matched up across runs by their address and their stats are
+//! summed over every worker before being compared
+
+use crate::{
+    args::{Args, DiffArgs},
+    colormap::Color,
+    dataflow::{
+        utils::{OpKey, XXHasher},
+        ArrangementStats, Channel, DataflowData, SplineLevel, Summation,
+    },
+    dump, report,
+    ui::{self, EdgeKind},
+};
+use anyhow::Result;
+use colorous::Gradient;
+use ddshow_types::{ChannelId, OperatorAddr, OperatorId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+/// Operators that got slower are red and ones that got faster are green
+const DIFF_GRADIENT: Gradient = colorous::RED_YELLOW_GREEN;
+
+/// The color of operators that only exist within one of the runs
+const UNMATCHED_COLOR: colorous::Color = colorous::Color {
+    r: 204,
+    g: 204,
+    b: 204,
+};
+
+/// How a single operator changed between two runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorChange<'a> {
+    pub addr: &'a OperatorAddr,
+    pub name: &'a str,
+    /// The operator's stats within the baseline run
+    pub old: Option<Summation>,
+    /// The operator's stats within the new run
+    pub new: Option<Summation>,
+    /// The operator's peak arrangement size within the baseline run, only
+    /// for operators that arrange their input
+    pub old_arrangement_size: Option<usize>,
+    /// The operator's peak arrangement size within the new run
+    pub new_arrangement_size: Option<usize>,
+}
+
+impl<'a> OperatorChange<'a> {
+    /// The relative change in total runtime, `None` if the operator
+    /// isn't within both runs
+    pub fn runtime_change(&self) -> Option<f64> {
+        let (old, new) = (self.old?, self.new?);
+        relative_change(old.total.as_secs_f64(), new.total.as_secs_f64())
+    }
+
+    /// The relative change in the number of activations, `None` if the
+    /// operator isn't within both runs
+    pub fn activation_change(&self) -> Option<f64> {
+        let (old, new) = (self.old?, self.new?);
+        relative_change(old.count as f64, new.count as f64)
+    }
+
+    /// The relative change in peak arrangement size, `None` if the operator
+    /// isn't an arrangement within both runs
+    pub fn arrangement_change(&self) -> Option<f64> {
+        let (old, new) = (self.old_arrangement_size?, self.new_arrangement_size?);
+        relative_change(old as f64, new as f64)
+    }
+
+    /// Whether the operator's runtime, activations or arrangement size grew
+    /// by more than `threshold`, given as a fraction
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        IntoIterator::into_iter([
+            self.runtime_change(),
+            self.activation_change(),
+            self.arrangement_change(),
+        ])
+        .flatten()
+        .any(|change| change > threshold)
+    }
+
+    /// The absolute difference in total runtime, used for ordering changes
+    fn runtime_delta(&self) -> Duration {
+        let total = |stats: Option<Summation>| stats.map_or(Duration::from_secs(0), |s| s.total);
+        let (old, new) = (total(self.old), total(self.new));
+
+        if old > new {
+            old - new
+        } else {
+            new - old
+        }
+    }
+
+    /// A short, human readable description of the change
+    pub fn describe(&self) -> String {
+        match (self.old, self.new) {
+            (Some(old), Some(new)) => format!(
+                "runtime {:#?} → {:#?} ({}), activations {} → {} ({})",
+                old.total,
+                new.total,
+                format_change(self.runtime_change()),
+                old.count,
+                new.count,
+                format_change(self.activation_change()),
+            ),
+            (None, Some(_)) => "only within the new run".to_owned(),
+            (Some(_), None) => "only within the baseline run".to_owned(),
+            (None, None) => "never activated".to_owned(),
+        }
+    }
+}
+
+fn relative_change(old: f64, new: f64) -> Option<f64> {
+    if old == 0.0 {
+        (new == 0.0).then(|| 0.0)
+    } else {
+        Some((new - old) / old)
+    }
+}
+
+/// Formats a relative change as a signed percentage
+pub fn format_change(change: Option<f64>) -> String {
+    change.map_or_else(
+        || "n/a".to_owned(),
+        |change| format!("{:+.1}%", change * 100.0),
+    )
+}
+
+/// Matches up the operators of both runs by address, ordered by how
+/// much their runtime changed
+pub fn compare<'a>(old: &'a DataflowData, new: &'a DataflowData) -> Vec<OperatorChange<'a>> {
+    let (old_stats, new_stats) = (operator_totals(old), operator_totals(new));
+    let (old_arrangements, new_arrangements) = (arrangement_totals(old), arrangement_totals(new));
+
+    let mut changes: BTreeMap<&OperatorAddr, OperatorChange<'a>> = BTreeMap::new();
+    for (addr, (name, stats)) in old_stats {
+        changes.insert(
+            addr,
+            OperatorChange {
+                addr,
+                name,
+                old: Some(stats),
+                new: None,
+                old_arrangement_size: old_arrangements.get(addr).copied(),
+                new_arrangement_size: None,
+            },
+        );
+    }
+
+    for (addr, (name, stats)) in new_stats {
+        let new_arrangement_size = new_arrangements.get(addr).copied();
+
+        changes
+            .entry(addr)
+            .and_modify(|change| {
+                change.new = Some(stats);
+                change.new_arrangement_size = new_arrangement_size;
+            })
+            .or_insert(OperatorChange {
+                addr,
+                name,
+                old: None,
+                new: Some(stats),
+                old_arrangement_size: None,
+                new_arrangement_size,
+            });
+    }
+
+    let mut changes: Vec<_> = changes.into_iter().map(|(_, change)| change).collect();
+    changes.sort_by(|left, right| right.runtime_delta().cmp(&left.runtime_delta()));
+
+    changes
+}
+
+/// Sums up the stats of each operator over every worker, keyed by address
+pub(crate) fn operator_totals(data: &DataflowData) -> BTreeMap<&OperatorAddr, (&str, Summation)> {
+    let addrs: HashMap<_, _, XXHasher> = data
+        .addr_lookup
+        .iter()
+        .map(|(key, addr)| (*key, addr))
+        .collect();
+    let names: HashMap<_, _, XXHasher> = data
+        .name_lookup
+        .iter()
+        .map(|(key, name)| (*key, name.as_str()))
+        .collect();
+
+    let mut totals = BTreeMap::new();
+    for (key, stats) in data.summarized.iter() {
+        let addr = match addrs.get(key) {
+            Some(addr) => *addr,
+            None => continue,
+        };
+        let name = names.get(key).copied().unwrap_or("Unknown");
+
+        totals
+            .entry(addr)
+            .and_modify(|(_, total): &mut (&str, Summation)| *total = total.merge(stats))
+            .or_insert((name, *stats));
+    }
+
+    totals
+}
+
+/// Sums up the peak size of each arrangement over every worker, keyed by address
+fn arrangement_totals(data: &DataflowData) -> HashMap<&OperatorAddr, usize, XXHasher> {
+    let addrs: HashMap<_, _, XXHasher> = data
+        .addr_lookup
+        .iter()
+        .map(|(key, addr)| (*key, addr))
+        .collect();
+
+    let mut totals = HashMap::default();
+    for (key, stats) in data.arrangements.iter() {
+        if let Some(&addr) = addrs.get(key) {
+            *totals.entry(addr).or_insert(0) += stats.max_size;
+        }
+    }
+
+    totals
+}
+
+fn change_color(change: Option<f64>) -> Color {
+    match change {
+        Some(change) => {
+            Color::new(DIFF_GRADIENT.eval_continuous(0.5 - change.max(-1.0).min(1.0) / 2.0))
+        }
+        None => Color::new(UNMATCHED_COLOR),
+    }
+}
+
+/// Runs `ddshow diff`
+pub fn run(args: &Args, diff: &DiffArgs) -> Result<()> {
+    let (old, new) = (dump::load(&diff.old)?, dump::load(&diff.new)?);
+    let changes = compare(&old, &new);
+    tracing::info!(
+        old = %diff.old.display(),
+        new = %diff.new.display(),
+        "compared {} operators",
+        changes.len(),
+    );
+
+    report::build_diff_report(args, diff, &changes)?;
+    render_graph(args, &new, &changes)?;
+
+    if args.isnt_quiet() {
+        if !args.no_report_file {
+            println!("Wrote comparison report to {}", args.report_file.display());
+        }
+
+        if ui::graph_enabled() {
+            println!(
+                "Wrote comparison graph to {}",
+                args.output_dir.join("graph.html").display(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders the new run's graph with each operator colored by its change
+fn render_graph(args: &Args, new: &DataflowData, changes: &[OperatorChange]) -> Result<()> {
+    let changes: HashMap<_, _, XXHasher> =
+        changes.iter().map(|change| (change.addr, change)).collect();
+
+    let node = |addr: &OperatorAddr| {
+        let change = changes.get(addr)?;
+        let stats = change.new?;
+        let fill_color = change_color(change.runtime_change());
+
+        Some((change, stats, fill_color))
+    };
+
+    let nodes: Vec<_> = new
+        .nodes
+        .iter()
+        .filter_map(|(addr, event)| {
+            let (change, stats, fill_color) = node(addr)?;
+
+            Some(ui::Node {
+                id: event.id,
+                addr,
+                name: &event.name,
+                full_name: None,
+                max_activation_time: format!("{:#?}", stats.max),
+                min_activation_time: format!("{:#?}", stats.min),
+                average_activation_time: format!("{:#?}", stats.average),
+                total_activation_time: format!("{:#?}", stats.total),
+                invocations: stats.count,
+                text_color: format!("{}", fill_color.text_color()),
+                fill_color: format!("{}", fill_color),
+                activation_durations: Vec::new(),
+                activation_percentiles: None,
+                activation_histogram: Vec::new(),
+                activation_rates: Vec::new(),
+                source_location: None,
+                source_url: None,
+                max_arrangement_size: None,
+                min_arrangement_size: None,
+                average_batch_size: None,
+                average_merge_time: None,
+                trace_shares: None,
+                workers: &[],
+                comparison: Some(change.describe()),
+            })
+        })
+        .collect();
+
+    let operator_stats: HashMap<_, _, XXHasher> = new
+        .summarized
+        .iter()
+        .map(|((_, operator), stats)| (*operator, stats))
+        .collect();
+    let mut subgraph_contents = ui::subgraph_contents(&new.nodes, &new.subgraphs, &operator_stats);
+
+    let subgraphs: Vec<_> = new
+        .subgraphs
+        .iter()
+        .filter_map(|(addr, event)| {
+            let (change, stats, fill_color) = node(addr)?;
+            let (children, rollup) = subgraph_contents.remove(addr).unwrap_or_default();
+
+            Some(ui::Subgraph {
+                id: event.id,
+                addr,
+                name: &event.name,
+                max_activation_time: format!("{:#?}", stats.max),
+                min_activation_time: format!("{:#?}", stats.min),
+                average_activation_time: format!("{:#?}", stats.average),
+                total_activation_time: format!("{:#?}", stats.total),
+                invocations: stats.count,
+                text_color: format!("{}", fill_color.text_color()),
+                fill_color: format!("{}", fill_color),
+                workers: &[],
+                comparison: Some(change.describe()),
+                parent: ui::parent_addr(addr),
+                children,
+                rollup,
+            })
+        })
+        .collect();
+
+    let mut channel_records: HashMap<ChannelId, usize, XXHasher> = HashMap::default();
+    for &((_, channel), messages) in new.channel_messages.iter() {
+        *channel_records.entry(channel).or_insert(0) += messages.records_sent;
+    }
+
+    let edges: Vec<_> = new
+        .edges
+        .iter()
+        .map(|(_, channel, _)| ui::Edge {
+            src: channel.source_addr(),
+            dest: channel.target_addr(),
+            channel_id: channel.channel_id(),
+            edge_kind: match channel {
+                Channel::Normal { .. } => EdgeKind::Normal,
+                Channel::ScopeCrossing { .. } => EdgeKind::Crossing,
+            },
+            workers: Vec::new(),
+            records_sent: channel_records
+                .get(&channel.channel_id())
+                .copied()
+                .unwrap_or(0),
+            peak_backlog: None,
+            sustained_backlog: false,
+            latency: None,
+        })
+        .collect();
+
+    // The legend goes from faster at the bottom to slower at the top
+    let palette_colors: Vec<_> = (0..10)
+        .map(|step| {
+            format!(
+                "{}",
+                Color::new(DIFF_GRADIENT.eval_continuous(1.0 - step as f64 / 10.0))
+            )
+        })
+        .collect();
+
+    let arrangements: HashMap<OpKey, &ArrangementStats, XXHasher> = HashMap::default();
+    let activations: HashMap<OpKey, Vec<(Duration, Duration)>, XXHasher> = HashMap::default();
+    let agg_operators: HashMap<OperatorId, &Summation, XXHasher> = HashMap::default();
+    let agg_arrangements: HashMap<OperatorId, &ArrangementStats, XXHasher> = HashMap::default();
+    let agg_activations: HashMap<OperatorId, Vec<&Vec<(Duration, Duration)>>, XXHasher> =
+        HashMap::default();
+    let spline_levels: HashMap<OpKey, Vec<SplineLevel>, XXHasher> = HashMap::default();
+
+    ui::render(
+        args,
+        new,
+        &nodes,
+        &subgraphs,
+        &edges,
+        &palette_colors,
+        &arrangements,
+        &activations,
+        &agg_operators,
+        &agg_arrangements,
+        &agg_activations,
+        &spline_levels,
+        &[],
+        &[],
+        &[],
+        &[],
+        &[],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_change, OperatorChange};
+    use crate::dataflow::Summation;
+    use ddshow_types::OperatorAddr;
+    use std::time::Duration;
+
+    fn stats(total_ms: u64, count: usize) -> Summation {
+        let total = Duration::from_millis(total_ms);
+        Summation::new(total, total, total, total / count as u32, count)
+    }
+
+    #[test]
+    fn relative_changes() {
+        let addr = OperatorAddr::from(vec![0, 1]);
+        let change = OperatorChange {
+            addr: &addr,
+            name: "Map",
+            old: Some(stats(100, 10)),
+            new: Some(stats(150, 8)),
+            old_arrangement_size: Some(1000),
+            new_arrangement_size: Some(1050),
+        };
+
+        assert_eq!(format_change(change.runtime_change()), "+50.0%");
+        assert_eq!(format_change(change.activation_change()), "-20.0%");
+        assert_eq!(format_change(change.arrangement_change()), "+5.0%");
+        assert!(change.is_regression(0.1));
+        assert!(!change.is_regression(0.5));
+
+        let added = OperatorChange {
+            old: None,
+            ..change
+        };
+        assert_eq!(added.runtime_change(), None);
+        assert_eq!(format_change(added.runtime_change()), "n/a");
+        assert_eq!(added.describe(), "only within the new run");
+    }
+}
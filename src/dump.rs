@@ -0,0 +1,200 @@
+//! Saving the analyzed data to disk with `--dump-json` or `--dump-binary`
+//! so that runs can be compared or post-processed later
+//!
+//! The format of dumps follows [`DataflowData`] and is currently unstable,
+//! dumps should only be loaded by the same version of ddshow that wrote them.
+//! Json dumps written to paths ending in `.gz` are gzip compressed, which
+//! is detected when they're loaded
+
+use crate::{
+    args::Args,
+    dataflow::{constants::OUTPUT_BUFFER_SIZE, DataflowData},
+};
+use anyhow::{Context, Result};
+use crossbeam_utils::thread;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io::{BufWriter, Read, Write},
+    panic,
+    path::Path,
+};
+
+/// The header that every binary dump starts with
+const BINARY_DUMP_MAGIC: &[u8] = b"ddshow-dump\0";
+
+/// The header that every gzip stream starts with
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Writes every dump the user asked for, the json and binary dumps are
+/// written concurrently when both were asked for
+pub fn write_dumps(args: &Args, data: &DataflowData) -> Result<()> {
+    let write_json = || {
+        args.dump_json.as_deref().map_or(Ok(()), |path| {
+            write_dump(path, |writer| {
+                if path.extension() != Some(OsStr::new("gz")) {
+                    return serde_json::to_writer(writer, data)
+                        .context("failed to encode json dump");
+                }
+
+                let mut encoder = GzEncoder::new(writer, Compression::default());
+                serde_json::to_writer(&mut encoder, data).context("failed to encode json dump")?;
+                encoder
+                    .finish()
+                    .map(drop)
+                    .context("failed to compress json dump")
+            })
+        })
+    };
+
+    let write_binary = || {
+        args.dump_binary.as_deref().map_or(Ok(()), |path| {
+            write_dump(path, |writer| {
+                writer
+                    .write_all(BINARY_DUMP_MAGIC)
+                    .context("failed to write binary dump header")?;
+                bincode::serialize_into(writer, data).context("failed to encode binary dump")
+            })
+        })
+    };
+
+    if args.dump_json.is_none() || args.dump_binary.is_none() {
+        return write_json().and_then(|()| write_binary());
+    }
+
+    let (json, binary) = thread::scope(|scope| {
+        let json = scope.spawn(|_| write_json());
+        let binary = write_binary();
+
+        (
+            json.join().unwrap_or_else(|err| panic::resume_unwind(err)),
+            binary,
+        )
+    })
+    .unwrap_or_else(|err| panic::resume_unwind(err));
+
+    json.and(binary)
+}
+
+fn write_dump<F>(path: &Path, encode: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    tracing::info!(path = %path.display(), "writing dump");
+
+    let file = File::create(path)
+        .with_context(|| anyhow::anyhow!("failed to create dump file '{}'", path.display()))?;
+
+    let mut writer = BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, file);
+    encode(&mut writer)?;
+    writer
+        .flush()
+        .with_context(|| anyhow::anyhow!("failed to write dump to '{}'", path.display()))
+}
+
+/// Loads a dump written by either `--dump-json` or `--dump-binary`
+pub fn load(path: &Path) -> Result<DataflowData> {
+    let bytes = read_dump(path)?;
+
+    if bytes.starts_with(BINARY_DUMP_MAGIC) {
+        decode_binary(path, &bytes)
+    } else {
+        decode_json(path, &bytes)
+    }
+}
+
+/// Loads the `--baseline` dump that the report is compared against
+pub fn load_baseline(path: &Path) -> Result<DataflowData> {
+    let baseline = load(path)
+        .with_context(|| anyhow::anyhow!("failed to load the baseline '{}'", path.display()))?;
+    tracing::debug!(path = %path.display(), "loaded the baseline");
+
+    Ok(baseline)
+}
+
+/// Loads a dump written by `--dump-json`
+pub fn load_json(path: &Path) -> Result<DataflowData> {
+    decode_json(path, &read_dump(path)?)
+}
+
+/// Loads a dump written by `--dump-binary`
+pub fn load_binary(path: &Path) -> Result<DataflowData> {
+    decode_binary(path, &read_dump(path)?)
+}
+
+fn read_dump(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)
+        .with_context(|| anyhow::anyhow!("failed to read dump '{}'", path.display()))?;
+
+    if !bytes.starts_with(GZIP_MAGIC) {
+        return Ok(bytes);
+    }
+
+    let mut decompressed = Vec::with_capacity(bytes.len() * 8);
+    GzDecoder::new(&*bytes)
+        .read_to_end(&mut decompressed)
+        .with_context(|| anyhow::anyhow!("failed to decompress dump '{}'", path.display()))?;
+    tracing::debug!(path = %path.display(), "decompressed gzipped dump");
+
+    Ok(decompressed)
+}
+
+fn decode_json(path: &Path, bytes: &[u8]) -> Result<DataflowData> {
+    let data = serde_json::from_slice(bytes)
+        .with_context(|| anyhow::anyhow!("failed to decode json dump '{}'", path.display()))?;
+    tracing::debug!(path = %path.display(), "loaded json dump");
+
+    Ok(data)
+}
+
+fn decode_binary(path: &Path, bytes: &[u8]) -> Result<DataflowData> {
+    let encoded = bytes.strip_prefix(BINARY_DUMP_MAGIC).ok_or_else(|| {
+        anyhow::anyhow!(
+            "'{}' isn't a binary dump, it's missing the binary dump header",
+            path.display(),
+        )
+    })?;
+
+    let data = bincode::deserialize(encoded)
+        .with_context(|| anyhow::anyhow!("failed to decode binary dump '{}'", path.display()))?;
+    tracing::debug!(path = %path.display(), "loaded binary dump");
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        args::Args,
+        dataflow::{DataflowData, Summation},
+        dump,
+    };
+    use ddshow_types::{OperatorId, WorkerId};
+    use std::{env, fs, process, time::Duration};
+
+    #[test]
+    fn gzipped_json_roundtrip() {
+        let path = env::temp_dir().join(format!("ddshow-dump-{}.json.gz", process::id()));
+
+        let mut data = DataflowData::default();
+        let total = Duration::from_millis(10);
+        data.summarized.push((
+            (WorkerId::new(0), OperatorId::new(1)),
+            Summation::new(total, total, total, total, 1),
+        ));
+
+        let args = Args {
+            dump_json: Some(path.clone()),
+            ..Default::default()
+        };
+        dump::write_dumps(&args, &data).unwrap();
+
+        let compressed = fs::read(&path).unwrap();
+        let loaded = dump::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(compressed.starts_with(super::GZIP_MAGIC));
+        assert_eq!(loaded.unwrap().summarized, data.summarized);
+    }
+}
@@ -0,0 +1,391 @@
+//! Runs ddshow's analysis within the target computation's own process
+//!
+//! Rather than streaming their logs to ddshow over tcp, each of the target's
+//! timely workers hands its events to an in-memory pipe that the analysis
+//! dataflow reads from. The analysis runs on its own threads alongside the
+//! target and once every attached worker has shut down the results are
+//! written to the output directory just like they are by the cli
+
+use crate::{
+    args::Args,
+    dataflow::{
+        operators::{DroppedEvents, RkyvEventReader, TargetRuns},
+        DataflowData, StatsSinks,
+    },
+    dump,
+    operator_groups::OperatorGroups,
+    output_sink::OutputSinks,
+    replay_loading::{
+        make_streams, ConnectedSources, DifferentialReplaySource, ProgressReplaySource,
+        TimelyReplaySource, TypedEventReader,
+    },
+    run::{self, Collected},
+};
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use std::{
+    fs,
+    io::{self, BufWriter, Read, Write},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+use timely::{communication::Allocate, worker::Worker};
+
+/// Analyzes the logs of the timely workers running within the current process,
+/// created with [`DDShowBuilder::in_process()`]
+///
+/// Every worker of the target computation has to be passed to [`InProcess::attach()`]
+/// before it starts running any dataflows, after the target computation finishes
+/// [`InProcess::finish()`] waits for the analysis and writes out its results
+///
+/// ```no_run
+/// let ddshow = ddshow::DDShow::builder().in_process(std::num::NonZeroUsize::new(2).unwrap())?;
+///
+/// let attached = ddshow.clone_handle();
+/// timely::execute(timely::Config::process(2), move |worker| {
+///     attached.attach(worker).unwrap();
+///     // ...build and run the target dataflow
+/// })
+/// .unwrap();
+///
+/// ddshow.finish()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// [`DDShowBuilder::in_process()`]: crate::DDShowBuilder::in_process
+pub struct InProcess {
+    args: Arc<Args>,
+    pipes: Arc<Mutex<Vec<WorkerPipes>>>,
+    operator_groups: OperatorGroups,
+    baseline: Option<DataflowData>,
+    output_sinks: OutputSinks,
+    start_time: Instant,
+    analysis: JoinHandle<Result<Option<Collected>>>,
+}
+
+impl InProcess {
+    pub(crate) fn start(
+        args: Args,
+        stats_sinks: StatsSinks,
+        output_sinks: OutputSinks,
+        target_workers: NonZeroUsize,
+    ) -> Result<Self> {
+        let start_time = Instant::now();
+        let args = Arc::new(Args {
+            timely_connections: target_workers,
+            replay_logs: None,
+            ..args
+        });
+
+        // Load the operator groups and the baseline up front so that malformed
+        // files are reported before the target starts running
+        let operator_groups = args
+            .operator_groups
+            .as_deref()
+            .map(OperatorGroups::load)
+            .transpose()?
+            .unwrap_or_default();
+        let baseline = args
+            .baseline
+            .as_deref()
+            .map(dump::load_baseline)
+            .transpose()?;
+
+        let (pipes, sources) = worker_pipes(&args, target_workers)?;
+
+        let moved_args = args.clone();
+        let analysis = thread::Builder::new()
+            .name("ddshow-in-process".to_owned())
+            .spawn(move || run::collect(&moved_args, false, stats_sinks, Some(sources)))
+            .context("failed to spawn the in-process analysis thread")?;
+
+        Ok(Self {
+            args,
+            pipes: Arc::new(Mutex::new(pipes)),
+            operator_groups,
+            baseline,
+            output_sinks,
+            start_time,
+            analysis,
+        })
+    }
+
+    /// Creates a handle for attaching workers that can be moved into the
+    /// target computation's worker closure
+    pub fn clone_handle(&self) -> InProcessHandle {
+        InProcessHandle {
+            args: self.args.clone(),
+            pipes: self.pipes.clone(),
+        }
+    }
+
+    /// Installs ddshow's loggers on the given worker, see [`InProcessHandle::attach()`]
+    pub fn attach<A>(&self, worker: &mut Worker<A>) -> Result<()>
+    where
+        A: Allocate,
+    {
+        self.clone_handle().attach(worker)
+    }
+
+    /// Waits for the analysis to process every attached worker's logs and
+    /// writes the results to the output directory
+    ///
+    /// This should only be called after the target computation has finished,
+    /// any workers that were never attached are treated as having logged nothing
+    pub fn finish(self) -> Result<()> {
+        // Close the pipes of workers that never attached so the analysis doesn't wait on them
+        self.pipes
+            .lock()
+            .map_err(|_| anyhow::anyhow!("the in-process worker pipes were poisoned"))?
+            .clear();
+
+        let collected = self
+            .analysis
+            .join()
+            .map_err(|_| anyhow::anyhow!("the in-process analysis thread panicked"))??;
+
+        let collected = if let Some(collected) = collected {
+            collected
+        } else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(&self.args.output_dir).with_context(|| {
+            anyhow::anyhow!(
+                "failed to create output directory '{}'",
+                self.args.output_dir.display(),
+            )
+        })?;
+
+        run::render(
+            &self.args,
+            collected,
+            &self.operator_groups,
+            self.baseline.as_ref(),
+            self.output_sinks,
+            self.start_time,
+        )
+    }
+}
+
+/// Attaches the target's workers to an [`InProcess`] analysis
+#[derive(Clone)]
+pub struct InProcessHandle {
+    args: Arc<Args>,
+    pipes: Arc<Mutex<Vec<WorkerPipes>>>,
+}
+
+impl InProcessHandle {
+    /// Installs ddshow's timely loggers on the given worker along with its differential
+    /// and progress loggers if they were enabled, each worker can only be attached once
+    pub fn attach<A>(&self, worker: &mut Worker<A>) -> Result<()>
+    where
+        A: Allocate,
+    {
+        let pipes = self
+            .pipes
+            .lock()
+            .map_err(|_| anyhow::anyhow!("the in-process worker pipes were poisoned"))?
+            .pop()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "attached more than the {} workers ddshow was started with",
+                    self.args.timely_connections,
+                )
+            })?;
+
+        tracing::debug!(
+            worker = worker.index(),
+            differential = pipes.differential.is_some(),
+            progress = pipes.progress.is_some(),
+            "attaching in-process loggers to worker {}",
+            worker.index(),
+        );
+
+        ddshow_sink::enable_timely_logging(worker, BufWriter::new(pipes.timely));
+        if let Some(differential) = pipes.differential {
+            ddshow_sink::enable_differential_logging(worker, BufWriter::new(differential));
+        }
+        if let Some(progress) = pipes.progress {
+            ddshow_sink::enable_timely_progress_logging(worker, BufWriter::new(progress));
+        }
+
+        Ok(())
+    }
+}
+
+/// The writing halves of a single worker's log pipes
+struct WorkerPipes {
+    timely: PipeWriter,
+    differential: Option<PipeWriter>,
+    progress: Option<PipeWriter>,
+}
+
+/// Creates the pipes for every target worker along with the sources that read from them
+fn worker_pipes(
+    args: &Args,
+    target_workers: NonZeroUsize,
+) -> Result<(Vec<WorkerPipes>, ConnectedSources)> {
+    let mut pipes = Vec::with_capacity(target_workers.get());
+    let (mut timely, mut differential, mut progress) = (Vec::new(), Vec::new(), Vec::new());
+
+    for _ in 0..target_workers.get() {
+        let (timely_writer, reader) = pipe();
+        timely.push(Box::new(RkyvEventReader::new(
+            Box::new(reader) as Box<dyn Read + Send + 'static>
+        )) as TypedEventReader<_, _>);
+
+        let differential_writer = if args.differential_enabled {
+            let (writer, reader) = pipe();
+            differential.push(Box::new(RkyvEventReader::new(
+                Box::new(reader) as Box<dyn Read + Send + 'static>
+            )) as TypedEventReader<_, _>);
+
+            Some(writer)
+        } else {
+            None
+        };
+
+        let progress_writer = if args.progress_enabled {
+            let (writer, reader) = pipe();
+            progress.push(Box::new(RkyvEventReader::new(
+                Box::new(reader) as Box<dyn Read + Send + 'static>
+            )) as TypedEventReader<_, _>);
+
+            Some(writer)
+        } else {
+            None
+        };
+
+        pipes.push(WorkerPipes {
+            timely: timely_writer,
+            differential: differential_writer,
+            progress: progress_writer,
+        });
+    }
+
+    let mut indices = (0..args.workers.get()).cycle();
+    let total_sources = timely.len() + differential.len() + progress.len();
+
+    let timely = make_streams(
+        args.workers.get(),
+        TimelyReplaySource::Rkyv(timely),
+        &mut indices,
+    )?;
+    let differential = if args.differential_enabled {
+        Some(make_streams(
+            args.workers.get(),
+            DifferentialReplaySource::Rkyv(differential),
+            &mut indices,
+        )?)
+    } else {
+        None
+    };
+    let progress = if args.progress_enabled {
+        Some(make_streams(
+            args.workers.get(),
+            ProgressReplaySource::Rkyv(progress),
+            &mut indices,
+        )?)
+    } else {
+        None
+    };
+
+    let sources = (
+        timely,
+        differential,
+        progress,
+        total_sources,
+        None,
+        TargetRuns::new(),
+        DroppedEvents::new(),
+    );
+
+    Ok((pipes, sources))
+}
+
+/// Creates an in-memory pipe, the reading half never blocks and reaches its
+/// end once the writing half has been dropped
+fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+
+    (
+        PipeWriter(sender),
+        PipeReader {
+            receiver,
+            buffer: Vec::new(),
+            position: 0,
+        },
+    )
+}
+
+struct PipeWriter(Sender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // If the analysis has gone away there's nobody left to hear about it
+        let _ = self.0.send(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct PipeReader {
+    receiver: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            match self.receiver.try_recv() {
+                Ok(bytes) => {
+                    self.buffer = bytes;
+                    self.position = 0;
+                }
+
+                // Mirror a non-blocking socket that has nothing to read yet
+                Err(TryRecvError::Empty) => return Err(io::ErrorKind::WouldBlock.into()),
+                Err(TryRecvError::Disconnected) => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pipe;
+    use std::io::{ErrorKind, Read, Write};
+
+    #[test]
+    fn pipes_pass_bytes_until_closed() {
+        let (mut writer, mut reader) = pipe();
+        let mut buf = [0; 4];
+
+        assert_eq!(
+            reader.read(&mut buf).unwrap_err().kind(),
+            ErrorKind::WouldBlock,
+        );
+
+        writer.write_all(b"timely").unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"time");
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ly");
+
+        drop(writer);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+}
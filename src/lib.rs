@@ -0,0 +1,43 @@
+//! Timely and Differential dataflow log analysis and visualization
+//!
+//! Alongside the `ddshow` binary the analysis can be embedded within other
+//! programs through [`DDShow`]
+//!
+//! ```no_run
+//! let stats = ddshow::DDShow::builder().replay("ddshow-logs").run()?;
+//! println!("analyzed {} operators", stats.data().operators.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod api;
+pub mod args;
+mod colormap;
+mod custom_streams;
+mod dataflow;
+mod diff;
+mod dump;
+mod in_process;
+mod live_server;
+mod logging;
+mod operator_groups;
+pub mod operators;
+mod output_sink;
+mod replay_loading;
+mod report;
+mod run;
+mod status;
+mod target_process;
+#[cfg(feature = "network")]
+mod tls;
+mod ui;
+
+pub use crate::{
+    api::{DDShow, DDShowBuilder, DDShowStats, DataflowBuilder},
+    args::{Analysis, Args},
+    dataflow::{ArrangementStats, DataflowData, EventKind, StatsSink, Summation, TimelineEvent},
+    in_process::{InProcess, InProcessHandle},
+    output_sink::{DumpOutput, GraphOutput, OutputContext, OutputSink, ReportOutput},
+    run::run_cli,
+    ui::{ChannelMessageStats, RunStats, WorkerUtilization},
+};
+pub use ddshow_types;
@@ -0,0 +1,514 @@
+//! A small http server for watching the target's stats as they're computed,
+//! enabled by passing `--serve`
+//!
+//! While the analysis dataflow is running the server hosts a live page that
+//! receives snapshots of the current operator stats and worker timelines over
+//! a websocket. Once the final graph has been rendered the page switches over
+//! to it and the server keeps hosting it until ddshow is interrupted
+//!
+//! `/graph.svg` serves a picture of the graph colored by the latest stats
+//! that's rendered on the server, for embedding within dashboards
+//!
+//! Requires the `live-server` feature
+
+use crate::{args::Args, dataflow::DataflowData, ui::RunStats};
+#[cfg(feature = "live-server")]
+use crate::{
+    dataflow::{utils::XXHasher, EventKind},
+    ui::{svg, ProgramStats, WorkerStats},
+};
+#[cfg(feature = "live-server")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "live-server")]
+use colorous::Gradient;
+#[cfg(feature = "live-server")]
+use ddshow_types::{OperatorAddr, OperatorId, WorkerId};
+#[cfg(feature = "live-server")]
+use serde::Serialize;
+#[cfg(feature = "live-server")]
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{atomic::Ordering, Arc, Mutex},
+    thread,
+};
+use std::{net::SocketAddr, path::Path, sync::atomic::AtomicBool, time::Duration};
+#[cfg(feature = "live-server")]
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+#[cfg(feature = "live-server")]
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+/// How often the live page gets sent a fresh snapshot of the stats
+pub const LIVE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often websocket connections check for new snapshots
+#[cfg(feature = "live-server")]
+const SOCKET_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The most timeline events sent within a single snapshot, only the
+/// latest events are kept so that the page stays responsive
+#[cfg(feature = "live-server")]
+const MAX_LIVE_TIMELINE_EVENTS: usize = 5000;
+
+#[cfg(feature = "live-server")]
+const LIVE_HTML: &str = include_str!("ui/live.html");
+
+/// The live stats server
+#[cfg(feature = "live-server")]
+#[derive(Debug, Clone)]
+pub struct LiveServer {
+    address: SocketAddr,
+    state: Arc<Mutex<LiveState>>,
+    /// The palette operators are colored with within `/graph.svg`
+    palette: Gradient,
+}
+
+/// Serving can never be enabled without the `live-server` feature
+#[cfg(not(feature = "live-server"))]
+#[derive(Debug, Clone)]
+pub enum LiveServer {}
+
+#[cfg(feature = "live-server")]
+#[derive(Debug, Default)]
+struct LiveState {
+    /// The latest stats snapshot, encoded as json
+    snapshot: Arc<str>,
+    /// The latest svg render of the graph
+    svg: Arc<str>,
+    /// Incremented for every published snapshot
+    version: u64,
+    /// The directory the final graph was rendered into, set once it's done
+    rendered: Option<PathBuf>,
+}
+
+impl LiveServer {
+    /// Starts the server if the user asked for it with `--serve`
+    pub fn start(args: &Args) -> Result<Option<Self>> {
+        let address = match args.serve {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+
+        #[cfg(not(feature = "live-server"))]
+        {
+            let _ = address;
+            anyhow::bail!(
+                "ddshow was built without the live server, rebuild it with the `live-server` feature",
+            );
+        }
+
+        #[cfg(feature = "live-server")]
+        {
+            let server = Server::http(address).map_err(|err| {
+                anyhow::anyhow!("failed to start live server on {}: {}", address, err)
+            })?;
+
+            let state = Arc::new(Mutex::new(LiveState::default()));
+            let moved_state = state.clone();
+            thread::Builder::new()
+                .name("ddshow-live-server".to_owned())
+                .spawn(move || {
+                    for request in server.incoming_requests() {
+                        handle_request(request, &moved_state);
+                    }
+                })
+                .context("failed to spawn live server thread")?;
+
+            tracing::info!(address = %address, "started live server");
+            if args.isnt_quiet() {
+                println!("Serving live stats on http://{}", address);
+            }
+
+            Ok(Some(Self {
+                address,
+                state,
+                palette: args.palette,
+            }))
+        }
+    }
+
+    /// Publishes a snapshot of the stats that have been computed so far, along
+    /// with the target's runs and the number of events dropped so far
+    pub fn publish(&self, data: &DataflowData, runs: &[RunStats], dropped_events: usize) {
+        #[cfg(not(feature = "live-server"))]
+        {
+            let _ = (data, runs, dropped_events);
+            match *self {}
+        }
+
+        #[cfg(feature = "live-server")]
+        {
+            let snapshot = LiveSnapshot::new(data, runs, dropped_events);
+            let snapshot = match serde_json::to_string(&snapshot) {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    tracing::error!("failed to encode live stats snapshot: {}", err);
+                    return;
+                }
+            };
+
+            let svg = svg::render_svg(data, &self.palette);
+
+            let mut state = self.state.lock().unwrap();
+            state.snapshot = Arc::from(snapshot);
+            state.svg = Arc::from(svg);
+            state.version += 1;
+        }
+    }
+
+    /// Switches the live page over to the graph rendered within `output_dir`
+    pub fn rendered(&self, output_dir: &Path) {
+        #[cfg(not(feature = "live-server"))]
+        {
+            let _ = output_dir;
+            match *self {}
+        }
+
+        #[cfg(feature = "live-server")]
+        {
+            let mut state = self.state.lock().unwrap();
+            state.rendered = Some(output_dir.to_owned());
+            state.version += 1;
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        #[cfg(not(feature = "live-server"))]
+        {
+            match *self {}
+        }
+
+        #[cfg(feature = "live-server")]
+        {
+            self.address
+        }
+    }
+
+    /// Keeps serving until the user interrupts ddshow
+    pub fn serve_until(&self, interrupted: &AtomicBool) {
+        #[cfg(not(feature = "live-server"))]
+        {
+            let _ = interrupted;
+            match *self {}
+        }
+
+        #[cfg(feature = "live-server")]
+        {
+            tracing::info!(address = %self.address, "serving the rendered graph");
+
+            while !interrupted.load(Ordering::Acquire) {
+                thread::sleep(SOCKET_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "live-server")]
+fn handle_request(request: Request, state: &Arc<Mutex<LiveState>>) {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+    tracing::trace!(method = %request.method(), path = %path, "live server request");
+
+    let rendered = state.lock().unwrap().rendered.clone();
+    let result = match (path.as_str(), rendered) {
+        ("/updates", _) => upgrade_socket(request, state.clone()),
+        ("/graph.svg", _) => serve_svg(request, state),
+
+        ("/", None) | ("/live.html", _) => {
+            request.respond(Response::from_string(LIVE_HTML).with_header(content_type("text/html")))
+        }
+
+        ("/", Some(output_dir)) => serve_file(request, &output_dir, "graph.html"),
+        (file @ "/graph.html", Some(output_dir))
+        | (file @ "/graph.js", Some(output_dir))
+        | (file @ "/d3.v5.js", Some(output_dir))
+        | (file @ "/dagre-d3.js", Some(output_dir)) => serve_file(request, &output_dir, &file[1..]),
+
+        // The graph's data files, only bare file names are served to keep requests
+        // within the data directory
+        (file, Some(output_dir))
+            if file.starts_with("/data/")
+                && !file["/data/".len()..].contains(|c| c == '/' || c == '\\')
+                && !file.contains("..") =>
+        {
+            serve_file(request, &output_dir, &file[1..])
+        }
+
+        _ => request.respond(Response::from_string("not found").with_status_code(StatusCode(404))),
+    };
+
+    if let Err(err) = result {
+        tracing::debug!(path = %path, "failed to respond to live server request: {}", err);
+    }
+}
+
+#[cfg(feature = "live-server")]
+fn serve_file(request: Request, output_dir: &Path, file: &str) -> std::io::Result<()> {
+    let mime = if file.ends_with(".html") {
+        "text/html"
+    } else {
+        "application/javascript"
+    };
+
+    match fs::read(output_dir.join(file)) {
+        Ok(contents) => {
+            request.respond(Response::from_data(contents).with_header(content_type(mime)))
+        }
+        Err(err) => {
+            tracing::error!(file = %file, "failed to read rendered graph file: {}", err);
+            request.respond(Response::from_string("not found").with_status_code(StatusCode(404)))
+        }
+    }
+}
+
+/// Serves the latest svg render of the graph
+#[cfg(feature = "live-server")]
+fn serve_svg(request: Request, state: &Arc<Mutex<LiveState>>) -> std::io::Result<()> {
+    let svg = state.lock().unwrap().svg.clone();
+
+    if svg.is_empty() {
+        request.respond(
+            Response::from_string("no stats have been computed yet")
+                .with_status_code(StatusCode(503)),
+        )
+    } else {
+        request.respond(
+            Response::from_string(svg.to_string())
+                .with_header(content_type("image/svg+xml"))
+                // Embedded pictures should always be refetched
+                .with_header(header("Cache-Control", "no-store")),
+        )
+    }
+}
+
+/// Upgrades the request into a websocket and starts pushing snapshots to it
+#[cfg(feature = "live-server")]
+fn upgrade_socket(request: Request, state: Arc<Mutex<LiveState>>) -> std::io::Result<()> {
+    let key = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| derive_accept_key(header.value.as_str().as_bytes()));
+
+    let key = match key {
+        Some(key) => key,
+        None => {
+            return request.respond(
+                Response::from_string("expected a websocket request")
+                    .with_status_code(StatusCode(400)),
+            );
+        }
+    };
+
+    let response = Response::empty(StatusCode(101))
+        .with_header(header("Upgrade", "websocket"))
+        .with_header(header("Connection", "Upgrade"))
+        .with_header(header("Sec-WebSocket-Accept", &key));
+    let stream = request.upgrade("websocket", response);
+
+    thread::Builder::new()
+        .name("ddshow-live-socket".to_owned())
+        .spawn(move || {
+            let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+            let mut last_version = 0;
+
+            loop {
+                let (snapshot, version, finished) = {
+                    let state = state.lock().unwrap();
+                    (
+                        state.snapshot.clone(),
+                        state.version,
+                        state.rendered.is_some(),
+                    )
+                };
+
+                if version != last_version {
+                    last_version = version;
+
+                    let message = if finished {
+                        String::from(r#"{"kind":"rendered"}"#)
+                    } else if !snapshot.is_empty() {
+                        snapshot.to_string()
+                    } else {
+                        continue;
+                    };
+
+                    if let Err(err) = socket.write_message(Message::Text(message)) {
+                        tracing::debug!("live socket disconnected: {}", err);
+                        break;
+                    }
+
+                    if finished {
+                        let _ = socket.close(None);
+                        break;
+                    }
+                }
+
+                thread::sleep(SOCKET_POLL_INTERVAL);
+            }
+        })?;
+
+    Ok(())
+}
+
+#[cfg(feature = "live-server")]
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field, value).expect("invalid http header")
+}
+
+#[cfg(feature = "live-server")]
+fn content_type(mime: &str) -> Header {
+    header("Content-Type", mime)
+}
+
+/// The stats sent to the live page
+#[cfg(feature = "live-server")]
+#[derive(Debug, Serialize)]
+struct LiveSnapshot<'a> {
+    kind: &'static str,
+    workers: usize,
+    /// The program's stats so far, anything that hasn't arrived yet is zeroed
+    program: ProgramStats,
+    /// The stats of each worker so far
+    worker_stats: Vec<WorkerStats>,
+    operators: Vec<LiveOperator<'a>>,
+    /// The `--top-k` slowest operators across every worker, slowest first
+    leaderboard: Vec<LiveLeader<'a>>,
+    /// The `(start, end)` of each worker's runtime
+    worker_runtimes: &'a [(WorkerId, (Duration, Duration))],
+    timeline_events: Vec<LiveTimelineEvent>,
+}
+
+#[cfg(feature = "live-server")]
+#[derive(Debug, Serialize)]
+struct LiveOperator<'a> {
+    worker: WorkerId,
+    id: OperatorId,
+    addr: Option<&'a OperatorAddr>,
+    name: Option<&'a str>,
+    activations: usize,
+    total: Duration,
+    average: Duration,
+    max: Duration,
+}
+
+#[cfg(feature = "live-server")]
+#[derive(Debug, Serialize)]
+struct LiveLeader<'a> {
+    id: OperatorId,
+    addr: Option<&'a OperatorAddr>,
+    name: Option<&'a str>,
+    activations: usize,
+    total: Duration,
+}
+
+#[cfg(feature = "live-server")]
+#[derive(Debug, Serialize)]
+struct LiveTimelineEvent {
+    worker: WorkerId,
+    event: String,
+    start_time: u64,
+    duration: u64,
+}
+
+#[cfg(feature = "live-server")]
+impl<'a> LiveSnapshot<'a> {
+    fn new(data: &'a DataflowData, runs: &[RunStats], dropped_events: usize) -> Self {
+        let names: HashMap<_, _, XXHasher> = data
+            .name_lookup
+            .iter()
+            .map(|(key, name)| (*key, name.as_str()))
+            .collect();
+        let addrs: HashMap<_, _, XXHasher> = data
+            .addr_lookup
+            .iter()
+            .map(|(key, addr)| (*key, addr))
+            .collect();
+
+        let mut operators: Vec<_> = data
+            .summarized
+            .iter()
+            .map(|&(key, summary)| LiveOperator {
+                worker: key.0,
+                id: key.1,
+                addr: addrs.get(&key).copied(),
+                name: names.get(&key).copied(),
+                activations: summary.count,
+                total: summary.total,
+                average: summary.average,
+                max: summary.max,
+            })
+            .collect();
+        operators.sort_unstable_by(|left, right| right.total.cmp(&left.total));
+
+        // Operators are named by the first worker that has a name or address for them
+        let mut leaderboard: Vec<_> = data
+            .slowest_operators
+            .iter()
+            .map(|&(operator, summary)| LiveLeader {
+                id: operator,
+                addr: data
+                    .workers
+                    .iter()
+                    .find_map(|&worker| addrs.get(&(worker, operator)).copied()),
+                name: data
+                    .workers
+                    .iter()
+                    .find_map(|&worker| names.get(&(worker, operator)).copied()),
+                activations: summary.count,
+                total: summary.total,
+            })
+            .collect();
+        leaderboard.sort_unstable_by(|left, right| {
+            right.total.cmp(&left.total).then(left.id.cmp(&right.id))
+        });
+
+        let mut events: Vec<_> = data.timeline_events.iter().collect();
+        events.sort_unstable_by_key(|event| event.start_time);
+        let skipped = events.len().saturating_sub(MAX_LIVE_TIMELINE_EVENTS);
+
+        let timeline_events = events[skipped..]
+            .iter()
+            .map(|event| LiveTimelineEvent {
+                worker: event.worker,
+                event: match event.event {
+                    EventKind::OperatorActivation { operator_id } => names
+                        .get(&(event.worker, operator_id))
+                        .map_or_else(|| format!("{}", operator_id), |name| (*name).to_owned()),
+                    EventKind::Merge { operator_id } => format!("Merge {}", operator_id),
+                    EventKind::Application { id } => format!("Application {}", id),
+                    EventKind::Parked => "Parked".to_owned(),
+                    EventKind::Input => "Input".to_owned(),
+                    EventKind::Message => "Message".to_owned(),
+                    EventKind::Progress => "Progress".to_owned(),
+                    EventKind::MinorActivity => "Minor Activity".to_owned(),
+                },
+                start_time: event.start_time,
+                duration: event.duration,
+            })
+            .collect();
+
+        let worker_stats = data
+            .workers
+            .iter()
+            .map(|&worker| WorkerStats::partial(data, worker))
+            .collect();
+
+        Self {
+            kind: "stats",
+            workers: data.workers.len(),
+            program: ProgramStats::partial(data, runs, dropped_events),
+            worker_stats,
+            operators,
+            leaderboard,
+            worker_runtimes: &data.total_runtime,
+            timeline_events,
+        }
+    }
+}
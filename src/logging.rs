@@ -1,42 +1,120 @@
-use crate::args::TerminalColor;
+use crate::args::{Args, Output, TerminalColor};
 use anyhow::{Context, Result};
 use ddshow_sink::{
     DIFFERENTIAL_ARRANGEMENT_LOGGER_NAME, TIMELY_LOGGER_NAME, TIMELY_PROGRESS_LOGGER_NAME,
 };
+#[cfg(feature = "network")]
 use differential_dataflow::logging::DifferentialEvent as RawDifferentialEvent;
-use std::{env, net::TcpStream};
+#[cfg(feature = "network")]
+use std::net::TcpStream;
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Write},
+    sync::Arc,
+};
+use timely::{communication::Allocate, worker::Worker};
+#[cfg(feature = "network")]
 use timely::{
-    communication::Allocate,
     dataflow::operators::capture::EventWriter as TimelyEventWriter,
     logging::{BatchLogger as TimelyBatchLogger, TimelyEvent as RawTimelyEvent},
-    worker::Worker,
 };
 use tracing_subscriber::{
-    fmt::time::Uptime, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt,
+    fmt::{
+        format::{DefaultFields, Format, Full},
+        time::Uptime,
+        Layer, MakeWriter, TestWriter,
+    },
+    prelude::__tracing_subscriber_SubscriberExt,
+    util::SubscriberInitExt,
     EnvFilter,
 };
 
-pub(crate) fn init_logging(color: TerminalColor) {
-    let ansi_enabled = match color {
-        TerminalColor::Auto => atty::is(atty::Stream::Stdout),
+/// The name of the file that `--log-file` writes ddshow's logs to
+const LOG_FILE_NAME: &str = "ddshow.log";
+
+pub(crate) fn init_logging(args: &Args) -> Result<()> {
+    let ansi_enabled = match args.color {
+        TerminalColor::Auto => match args.log_output {
+            Output::Stdout => atty::is(atty::Stream::Stdout),
+            Output::Stderr | Output::Quiet => atty::is(atty::Stream::Stderr),
+        },
         TerminalColor::Always => true,
         TerminalColor::Never => false,
     };
 
-    let filter_layer = EnvFilter::from_env("DDSHOW_LOG");
-    // TODO: Write an improved version of the pretty formatter
-    let fmt_layer = tracing_subscriber::fmt::layer()
-        .with_timer(Uptime::default())
-        .with_thread_names(true)
-        .with_ansi(ansi_enabled)
-        .with_level(true);
+    // `DDSHOW_LOG` overrides the verbosity given on the command line
+    let filter_layer = if env::var_os("DDSHOW_LOG").is_some() {
+        EnvFilter::from_env("DDSHOW_LOG")
+    } else {
+        EnvFilter::new(args.log_level())
+    };
+
+    let file_layer = if args.log_file {
+        fs::create_dir_all(&args.output_dir).with_context(|| {
+            format!(
+                "failed to create the output directory {}",
+                args.output_dir.display(),
+            )
+        })?;
+
+        let path = args.output_dir.join(LOG_FILE_NAME);
+        let file = File::create(&path)
+            .with_context(|| format!("failed to create log file {}", path.display()))?;
 
-    let registry = tracing_subscriber::registry().with(filter_layer);
+        let file = LogFile(Arc::new(file));
+        Some(fmt_layer(move || file.clone(), false))
+    } else {
+        None
+    };
+
+    let registry = tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(file_layer);
     let _ = if cfg!(test) {
-        registry.with(fmt_layer.with_test_writer()).try_init()
+        registry
+            .with(fmt_layer(TestWriter::new, ansi_enabled))
+            .try_init()
     } else {
-        registry.with(fmt_layer).try_init()
+        match args.log_output {
+            Output::Stdout => registry
+                .with(fmt_layer(io::stdout, ansi_enabled))
+                .try_init(),
+            Output::Stderr => registry
+                .with(fmt_layer(io::stderr, ansi_enabled))
+                .try_init(),
+            Output::Quiet => registry.try_init(),
+        }
     };
+
+    Ok(())
+}
+
+// TODO: Write an improved version of the pretty formatter
+fn fmt_layer<S, W>(writer: W, ansi: bool) -> Layer<S, DefaultFields, Format<Full, Uptime>, W>
+where
+    W: MakeWriter + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .with_timer(Uptime::default())
+        .with_thread_names(true)
+        .with_ansi(ansi)
+        .with_level(true)
+        .with_writer(writer)
+}
+
+/// A log file shared between all of the threads that write logs
+#[derive(Debug, Clone)]
+struct LogFile(Arc<File>);
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.0).flush()
+    }
 }
 
 // TODO: Progress logging & configure logging via the cli
@@ -54,73 +132,7 @@ where
     tracing::debug!(DDSHOW_OBEY_TCP = ?obey_tcp, obey_tcp);
 
     if obey_tcp {
-        tracing::info!("logging to a tcp-based consumer");
-
-        let (timely_log_addr, progress_log_addr, differential_log_addr) = (
-            env::var("TIMELY_LOG_ADDR"),
-            env::var("TIMELY_PROGRESS_LOG_ADDR"),
-            env::var("DIFFERENTIAL_LOG_ADDR"),
-        );
-        tracing::debug!(
-            TIMELY_LOG_ADDR = ?timely_log_addr,
-            TIMELY_PROGRESS_LOG_ADDR = ?progress_log_addr,
-            DIFFERENTIAL_LOG_ADDR = ?differential_log_addr,
-        );
-
-        if let Ok(addr) = timely_log_addr {
-            if !addr.is_empty() {
-                let socket = TcpStream::connect(&addr).with_context(|| {
-                    anyhow::anyhow!("failed to connect to timely log addr: {}", addr)
-                })?;
-                let mut logger = TimelyBatchLogger::new(TimelyEventWriter::new(socket));
-
-                let old_logger = worker
-                    .log_register()
-                    .insert::<RawTimelyEvent, _>(TIMELY_LOGGER_NAME, move |time, data| {
-                        logger.publish_batch(time, data)
-                    });
-
-                if let Some(old_logger) = old_logger {
-                    tracing::warn!(
-                        type_id = ?old_logger.type_id(),
-                        "overwrote previously registered timely logger at {:p}",
-                        old_logger,
-                    );
-                }
-
-                tracing::info!("installed timely tcp log to {}", addr);
-            }
-        }
-
-        // if let Ok(addr) = progress_log_addr {
-        //     if !addr.is_empty() {
-        //         todo!()
-        //     }
-        // }
-
-        if let Ok(addr) = differential_log_addr {
-            if !addr.is_empty() {
-                let socket = TcpStream::connect(&addr).with_context(|| {
-                    anyhow::anyhow!("failed to connect to differential log addr: {}", addr)
-                })?;
-                let mut logger = TimelyBatchLogger::new(TimelyEventWriter::new(socket));
-
-                let old_logger = worker.log_register().insert::<RawDifferentialEvent, _>(
-                    DIFFERENTIAL_ARRANGEMENT_LOGGER_NAME,
-                    move |time, data| logger.publish_batch(time, data),
-                );
-
-                if let Some(old_logger) = old_logger {
-                    tracing::warn!(
-                        type_id = ?old_logger.type_id(),
-                        "overwrote previously registered differential logger at {:p}",
-                        old_logger,
-                    );
-                }
-
-                tracing::info!("installed differential tcp log to {}", addr);
-            }
-        }
+        init_tcp_logging(worker)?;
     } else {
         tracing::info!("logging to a disk-based consumer");
 
@@ -166,6 +178,95 @@ where
     Ok(())
 }
 
+/// Sends ddshow's own logs to the tcp consumers given by `TIMELY_LOG_ADDR`
+/// and `DIFFERENTIAL_LOG_ADDR`
+#[cfg(feature = "network")]
+fn init_tcp_logging<A>(worker: &mut Worker<A>) -> Result<()>
+where
+    A: Allocate,
+{
+    tracing::info!("logging to a tcp-based consumer");
+
+    let (timely_log_addr, progress_log_addr, differential_log_addr) = (
+        env::var("TIMELY_LOG_ADDR"),
+        env::var("TIMELY_PROGRESS_LOG_ADDR"),
+        env::var("DIFFERENTIAL_LOG_ADDR"),
+    );
+    tracing::debug!(
+        TIMELY_LOG_ADDR = ?timely_log_addr,
+        TIMELY_PROGRESS_LOG_ADDR = ?progress_log_addr,
+        DIFFERENTIAL_LOG_ADDR = ?differential_log_addr,
+    );
+
+    if let Ok(addr) = timely_log_addr {
+        if !addr.is_empty() {
+            let socket = TcpStream::connect(&addr).with_context(|| {
+                anyhow::anyhow!("failed to connect to timely log addr: {}", addr)
+            })?;
+            let mut logger = TimelyBatchLogger::new(TimelyEventWriter::new(socket));
+
+            let old_logger = worker
+                .log_register()
+                .insert::<RawTimelyEvent, _>(TIMELY_LOGGER_NAME, move |time, data| {
+                    logger.publish_batch(time, data)
+                });
+
+            if let Some(old_logger) = old_logger {
+                tracing::warn!(
+                    type_id = ?old_logger.type_id(),
+                    "overwrote previously registered timely logger at {:p}",
+                    old_logger,
+                );
+            }
+
+            tracing::info!("installed timely tcp log to {}", addr);
+        }
+    }
+
+    // if let Ok(addr) = progress_log_addr {
+    //     if !addr.is_empty() {
+    //         todo!()
+    //     }
+    // }
+
+    if let Ok(addr) = differential_log_addr {
+        if !addr.is_empty() {
+            let socket = TcpStream::connect(&addr).with_context(|| {
+                anyhow::anyhow!("failed to connect to differential log addr: {}", addr)
+            })?;
+            let mut logger = TimelyBatchLogger::new(TimelyEventWriter::new(socket));
+
+            let old_logger = worker.log_register().insert::<RawDifferentialEvent, _>(
+                DIFFERENTIAL_ARRANGEMENT_LOGGER_NAME,
+                move |time, data| logger.publish_batch(time, data),
+            );
+
+            if let Some(old_logger) = old_logger {
+                tracing::warn!(
+                    type_id = ?old_logger.type_id(),
+                    "overwrote previously registered differential logger at {:p}",
+                    old_logger,
+                );
+            }
+
+            tracing::info!("installed differential tcp log to {}", addr);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "network"))]
+fn init_tcp_logging<A>(_worker: &mut Worker<A>) -> Result<()>
+where
+    A: Allocate,
+{
+    anyhow::bail!(
+        "ddshow was built without network support, rebuild it with the `network` \
+        feature to use `DDSHOW_OBEY_TCP`",
+    )
+}
+
 /// Timely may register some logging hooks automatically,
 /// this just attempts to remove all of them
 pub(crate) fn unset_logging_hooks<A>(worker: &mut Worker<A>)
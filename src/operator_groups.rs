@@ -0,0 +1,165 @@
+//! User-declared logical groups of operators
+//!
+//! Groups are read from a sidecar TOML file given with `--operator-groups`
+//! that looks something like this
+//!
+//! ```toml
+//! [[group]]
+//! name = "ingest pipeline"
+//! # Every operator whose address starts with one of these is within the group
+//! operators = [[0, 1], [0, 2, 3]]
+//!
+//! [[group]]
+//! name = "join core"
+//! operators = [[0, 4]]
+//! ```
+
+use crate::dataflow::{utils::XXHasher, ArrangementStats, DataflowData, Summation};
+use anyhow::{Context, Result};
+use ddshow_types::{timely_logging::OperatesEvent, OperatorAddr, OperatorId};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OperatorGroups {
+    #[serde(default, rename = "group")]
+    pub groups: Vec<OperatorGroup>,
+}
+
+impl OperatorGroups {
+    /// Loads the operator groups declared within the given file
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path).with_context(|| {
+            anyhow::anyhow!("failed to read operator groups from '{}'", path.display())
+        })?;
+
+        let groups: Self = toml::from_str(&source).with_context(|| {
+            anyhow::anyhow!("failed to parse operator groups from '{}'", path.display())
+        })?;
+        tracing::debug!(
+            path = %path.display(),
+            "loaded {} operator groups",
+            groups.groups.len(),
+        );
+
+        Ok(groups)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OperatorGroup {
+    pub name: String,
+    /// The address prefixes of the operators within the group
+    pub operators: Vec<OperatorAddr>,
+}
+
+impl OperatorGroup {
+    /// Returns `true` if the operator at `addr` is a member of the group
+    pub fn contains(&self, addr: &OperatorAddr) -> bool {
+        self.operators
+            .iter()
+            .any(|prefix| addr.as_slice().starts_with(prefix.as_slice()))
+    }
+}
+
+/// The aggregated stats of all operators within a group
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStats<'a> {
+    pub name: &'a str,
+    /// The addresses of every (non-subgraph) operator within the group
+    pub members: Vec<&'a OperatorAddr>,
+    pub activations: Option<Summation>,
+    pub arrangement_size: Option<usize>,
+    pub arrangement_batches: Option<usize>,
+}
+
+/// Aggregates the stats of all operators within each group
+///
+/// Subgraphs are skipped since their runtime already includes the
+/// runtime of all of their children
+pub fn group_stats<'a>(
+    groups: &'a OperatorGroups,
+    data: &'a DataflowData,
+    agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
+    agg_arrangement_stats: &HashMap<OperatorId, &ArrangementStats, XXHasher>,
+) -> Vec<GroupStats<'a>> {
+    groups
+        .groups
+        .iter()
+        .map(|group| {
+            let mut stats = GroupStats {
+                name: &group.name,
+                members: Vec::new(),
+                activations: None,
+                arrangement_size: None,
+                arrangement_batches: None,
+            };
+
+            for (addr, OperatesEvent { id, .. }) in data.nodes.iter() {
+                if !group.contains(addr) {
+                    continue;
+                }
+                stats.members.push(addr);
+
+                if let Some(&summation) = agg_operator_stats.get(id) {
+                    stats.activations = Some(
+                        stats
+                            .activations
+                            .map_or_else(|| *summation, |activations| activations.merge(summation)),
+                    );
+                }
+
+                if let Some(&arranged) = agg_arrangement_stats.get(id) {
+                    *stats.arrangement_size.get_or_insert(0) += arranged.max_size;
+                    *stats.arrangement_batches.get_or_insert(0) += arranged.batches;
+                }
+            }
+
+            if stats.members.is_empty() {
+                tracing::warn!(group = %group.name, "operator group didn't match any operators");
+            }
+
+            stats
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OperatorGroups;
+    use ddshow_types::{OperatorAddr, OperatorId};
+
+    fn addr(segments: &[usize]) -> OperatorAddr {
+        segments.iter().copied().map(OperatorId::new).collect()
+    }
+
+    #[test]
+    fn parse_groups() {
+        let groups: OperatorGroups = toml::from_str(
+            r#"
+            [[group]]
+            name = "ingest pipeline"
+            operators = [[0, 1], [0, 2, 3]]
+
+            [[group]]
+            name = "join core"
+            operators = [[0, 4]]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(groups.groups.len(), 2);
+        assert_eq!(groups.groups[0].name, "ingest pipeline");
+
+        let ingest = &groups.groups[0];
+        assert!(ingest.contains(&addr(&[0, 1])));
+        assert!(ingest.contains(&addr(&[0, 1, 5])));
+        assert!(ingest.contains(&addr(&[0, 2, 3])));
+        assert!(!ingest.contains(&addr(&[0, 2])));
+        assert!(!ingest.contains(&addr(&[0, 4])));
+    }
+}
@@ -0,0 +1,154 @@
+//! Timely and differential operators from ddshow's analysis dataflow
+//!
+//! These are general purpose and can be used to build other analyses over
+//! timely and differential dataflows, operators are extension traits that are
+//! implemented for timely's `Stream`s and/or differential's `Collection`s
+
+pub use crate::dataflow::operators::{
+    CrossbeamExtractor, CrossbeamPusher, DiffDuration, FilterMap, FilterMapTimed, Fuel,
+    JoinArranged, Max, Maybe, Min, SortBy,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CrossbeamExtractor, CrossbeamPusher, FilterMapTimed, Fuel, JoinArranged, Max, Min,
+    };
+    use differential_dataflow::{
+        input::Input,
+        operators::{arrange::ArrangeBySelf, CountTotal, Join},
+    };
+    use std::{iter, num::NonZeroUsize};
+    use timely::dataflow::operators::Capture;
+
+    #[test]
+    fn joins_against_arrangements() {
+        let (semi_send, semi_recv) = crossbeam_channel::unbounded();
+        let (anti_send, anti_recv) = crossbeam_channel::unbounded();
+
+        timely::execute_directly(|worker| {
+            let (mut values, mut keys, probe) = worker.dataflow(|scope| {
+                let (values_input, values) = scope.new_collection::<(usize, char), isize>();
+                let (keys_input, keys) = scope.new_collection::<usize, isize>();
+                let keys = keys.arrange_by_self();
+
+                let semijoined = values.semijoin_arranged(&keys);
+                let antijoined = values.antijoin_arranged(&keys);
+                semijoined
+                    .inner
+                    .capture_into(CrossbeamPusher::new(semi_send));
+                antijoined
+                    .inner
+                    .capture_into(CrossbeamPusher::new(anti_send));
+
+                (values_input, keys_input, antijoined.probe())
+            });
+
+            values.insert((1, 'a'));
+            values.insert((2, 'b'));
+            values.insert((3, 'c'));
+            keys.insert(2);
+            keys.insert(3);
+
+            values.advance_to(1);
+            keys.advance_to(1);
+            values.flush();
+            keys.flush();
+            worker.step_or_park_while(None, || probe.less_than(values.time()));
+        });
+
+        let mut semijoined = CrossbeamExtractor::new(semi_recv).extract_all();
+        semijoined.sort_unstable();
+
+        assert_eq!(semijoined, vec![(2, 'b'), (3, 'c')]);
+        assert_eq!(
+            CrossbeamExtractor::new(anti_recv).extract_all(),
+            vec![(1, 'a')],
+        );
+    }
+
+    #[test]
+    fn filter_maps_see_timestamps() {
+        let (send, recv) = crossbeam_channel::unbounded();
+
+        timely::execute_directly(|worker| {
+            let (mut input, probe) = worker.dataflow(|scope| {
+                let (input, collection) = scope.new_collection::<usize, isize>();
+
+                let odd_times = collection
+                    .filter_map_timed(|&time, value| (time % 2 == 1).then(|| (time, value)));
+                odd_times.inner.capture_into(CrossbeamPusher::new(send));
+
+                (input, odd_times.probe())
+            });
+
+            for time in 0..4 {
+                input.insert(time * 10);
+                input.advance_to(time + 1);
+            }
+
+            input.flush();
+            worker.step_or_park_while(None, || probe.less_than(input.time()));
+        });
+
+        let mut result = CrossbeamExtractor::new(recv).extract_all();
+        result.sort_unstable();
+
+        assert_eq!(result, vec![(1, 10), (3, 30)]);
+    }
+
+    #[test]
+    fn min_and_max_differences() {
+        let (send, recv) = crossbeam_channel::unbounded();
+
+        timely::execute_directly(|worker| {
+            let (mut input, probe) = worker.dataflow(|scope| {
+                let (input, collection) = scope.new_collection::<(char, usize), isize>();
+
+                let (min, max) = (
+                    collection
+                        .explode(|(key, value)| iter::once((key, Min::new(value))))
+                        .count_total()
+                        .map(|(key, min)| (key, min.value)),
+                    collection
+                        .explode(|(key, value)| iter::once((key, Max::new(value))))
+                        .count_total()
+                        .map(|(key, max)| (key, max.value)),
+                );
+                let bounds = min.join(&max);
+                bounds.inner.capture_into(CrossbeamPusher::new(send));
+
+                (input, bounds.probe())
+            });
+
+            for &(key, value) in &[('a', 5), ('a', 1), ('a', 9), ('b', 4)] {
+                input.insert((key, value));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_or_park_while(None, || probe.less_than(input.time()));
+        });
+
+        let mut result = CrossbeamExtractor::new(recv).extract_all();
+        result.sort_unstable();
+
+        assert_eq!(result, vec![('a', (1, 9)), ('b', (4, 4))]);
+    }
+
+    #[test]
+    fn fuel_is_exhausted_and_reset() {
+        let mut fuel = Fuel::limited(NonZeroUsize::new(10).unwrap());
+
+        assert!(!fuel.exert(4));
+        assert!(fuel.exert(6));
+        assert!(fuel.is_exhausted());
+
+        fuel.reset();
+        assert!(!fuel.is_exhausted());
+
+        let mut unlimited = Fuel::unlimited();
+        assert!(!unlimited.exert(usize::MAX));
+        assert!(!unlimited.is_exhausted());
+    }
+}
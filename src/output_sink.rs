@@ -0,0 +1,254 @@
+//! The outputs written once the analysis has finished
+//!
+//! Writing the dumps, the report and the html graph are each an [`OutputSink`]
+//! and embedders can register their own through [`DDShowBuilder::output_sink()`]
+//! to export the results in other formats. Every sink is handed the same
+//! [`OutputContext`] after the built-in outputs have been written, the
+//! built-in outputs themselves are written concurrently since rendering
+//! large captures can take a while
+//!
+//! [`DDShowBuilder::output_sink()`]: crate::DDShowBuilder::output_sink
+
+use crate::{
+    args::Args,
+    custom_streams::CustomEvent,
+    dataflow::{
+        utils::{HumanDuration, OpKey, XXHasher},
+        ArrangementStats, DataflowData, ProgressChannelStats, SplineLevel, Summation,
+    },
+    dump,
+    operator_groups::GroupStats,
+    report,
+    ui::{self, Edge, Node, RunStats, Subgraph},
+};
+use anyhow::{Context, Result};
+use crossbeam_utils::thread;
+use ddshow_types::{OperatorAddr, OperatorId, WorkerId};
+use std::{
+    collections::HashMap,
+    fs, panic,
+    time::{Duration, Instant},
+};
+
+/// Writes the analysis' results somewhere once it has finished
+pub trait OutputSink {
+    /// The name of the output, used within logs and errors
+    fn name(&self) -> &str;
+
+    /// Writes the output
+    fn write(&mut self, output: &OutputContext<'_>) -> Result<()>;
+}
+
+pub(crate) type OutputSinks = Vec<Box<dyn OutputSink>>;
+
+/// Everything an [`OutputSink`] has access to
+pub struct OutputContext<'a> {
+    pub(crate) args: &'a Args,
+    pub(crate) data: &'a DataflowData,
+    pub(crate) target_runs: &'a [RunStats],
+    pub(crate) dropped_events: Option<usize>,
+    pub(crate) custom_events: &'a [CustomEvent],
+    pub(crate) workers: &'a [WorkerId],
+    pub(crate) name_lookup: &'a HashMap<OpKey, &'a str, XXHasher>,
+    pub(crate) addr_lookup: &'a HashMap<OpKey, &'a OperatorAddr, XXHasher>,
+    pub(crate) nodes: &'a [Node<'a>],
+    pub(crate) subgraphs: &'a [Subgraph<'a>],
+    pub(crate) edges: &'a [Edge<'a>],
+    pub(crate) palette_colors: &'a [String],
+    pub(crate) arrangement_map: &'a HashMap<OpKey, &'a ArrangementStats, XXHasher>,
+    pub(crate) activations_map: &'a HashMap<OpKey, Vec<(Duration, Duration)>, XXHasher>,
+    pub(crate) agg_operator_stats: &'a HashMap<OperatorId, &'a Summation, XXHasher>,
+    pub(crate) agg_arrangement_stats: &'a HashMap<OperatorId, &'a ArrangementStats, XXHasher>,
+    pub(crate) agg_activations_map:
+        &'a HashMap<OperatorId, Vec<&'a Vec<(Duration, Duration)>>, XXHasher>,
+    pub(crate) spline_levels: &'a HashMap<OpKey, Vec<SplineLevel>, XXHasher>,
+    pub(crate) group_stats: &'a [GroupStats<'a>],
+    pub(crate) progress_channels: &'a [ProgressChannelStats],
+    /// The `--baseline` run that the report is compared against
+    pub(crate) baseline: Option<&'a DataflowData>,
+}
+
+impl<'a> OutputContext<'a> {
+    /// The options ddshow was run with, including the output directory
+    pub const fn args(&self) -> &'a Args {
+        self.args
+    }
+
+    /// Everything the analysis dataflow computed
+    pub const fn data(&self) -> &'a DataflowData {
+        self.data
+    }
+
+    /// Each separate run of the target
+    pub const fn target_runs(&self) -> &'a [RunStats] {
+        self.target_runs
+    }
+
+    /// The number of events dropped due to full ingestion buffers, if
+    /// dropping events was enabled
+    pub const fn dropped_events(&self) -> Option<usize> {
+        self.dropped_events
+    }
+
+    /// The workers that were selected with `--workers-filter`, or every
+    /// worker when no filter was given
+    pub const fn workers(&self) -> &'a [WorkerId] {
+        self.workers
+    }
+}
+
+/// Writes ddshow's own outputs, each of which is skipped when the user
+/// didn't ask for it
+///
+/// The outputs don't depend on each other so each one is written on its own
+/// thread, the first error is returned once all of them have finished
+pub(crate) fn write_builtin_outputs(output: &OutputContext<'_>) -> Result<()> {
+    let results = thread::scope(|scope| {
+        let handles = vec![
+            scope.spawn(|_| write_output(&mut DumpOutput, output)),
+            scope.spawn(|_| write_output(&mut ReportOutput, output)),
+            scope.spawn(|_| write_output(&mut GraphOutput, output)),
+        ];
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect::<Vec<_>>()
+    })
+    .unwrap_or_else(|err| panic::resume_unwind(err));
+
+    results.into_iter().collect()
+}
+
+/// Writes a single output, logging how long it took
+pub(crate) fn write_output(sink: &mut dyn OutputSink, output: &OutputContext<'_>) -> Result<()> {
+    let rendering_start_time = Instant::now();
+
+    sink.write(output)
+        .with_context(|| anyhow::anyhow!("failed to write the {} output", sink.name()))?;
+
+    let rendering_elapsed = rendering_start_time.elapsed();
+    tracing::info!(
+        elapsed = ?rendering_elapsed,
+        "spent {} writing the {} output",
+        HumanDuration(rendering_elapsed),
+        sink.name(),
+    );
+
+    Ok(())
+}
+
+/// Writes `--dump-json` and `--dump-binary`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DumpOutput;
+
+impl OutputSink for DumpOutput {
+    fn name(&self) -> &str {
+        "dump"
+    }
+
+    fn write(&mut self, output: &OutputContext<'_>) -> Result<()> {
+        dump::write_dumps(output.args, output.data)
+    }
+}
+
+/// Writes the report to `--report-file`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReportOutput;
+
+impl OutputSink for ReportOutput {
+    fn name(&self) -> &str {
+        "report"
+    }
+
+    fn write(&mut self, output: &OutputContext<'_>) -> Result<()> {
+        let args = output.args;
+
+        report::build_report(
+            args,
+            output.data,
+            output.name_lookup,
+            output.addr_lookup,
+            output.agg_operator_stats,
+            output.agg_arrangement_stats,
+            output.group_stats,
+            output.target_runs,
+            output.dropped_events,
+            output.progress_channels,
+            output.baseline,
+        )?;
+
+        if !args.no_report_file {
+            let mut report_file = args.report_file.display().to_string();
+            if cfg!(windows) && report_file.starts_with(r"\\?\") {
+                report_file.replace_range(..r"\\?\".len(), "");
+            }
+
+            if args.isnt_quiet() {
+                println!("Wrote report file to {}", report_file);
+
+                if let Some(report_json) = args.report_json.as_ref() {
+                    println!("Wrote report json to {}", report_json.display());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders the html graph into the output directory
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphOutput;
+
+impl OutputSink for GraphOutput {
+    fn name(&self) -> &str {
+        "graph"
+    }
+
+    fn write(&mut self, output: &OutputContext<'_>) -> Result<()> {
+        let args = output.args;
+
+        ui::render(
+            args,
+            output.data,
+            output.nodes,
+            output.subgraphs,
+            output.edges,
+            output.palette_colors,
+            output.arrangement_map,
+            output.activations_map,
+            output.agg_operator_stats,
+            output.agg_arrangement_stats,
+            output.agg_activations_map,
+            output.spline_levels,
+            output.group_stats,
+            output.target_runs,
+            output.progress_channels,
+            output.custom_events,
+            output.workers,
+        )?;
+
+        if ui::graph_enabled() {
+            let mut graph_file = fs::canonicalize(&args.output_dir)
+                .context("failed to get path of output dir")?
+                .join("graph.html")
+                .display()
+                .to_string();
+            if cfg!(windows) && graph_file.starts_with(r"\\?\") {
+                graph_file.replace_range(..r"\\?\".len(), "");
+                graph_file = graph_file.replace("\\", "/");
+            }
+
+            if args.isnt_quiet() {
+                println!("Wrote output graph to file:///{}", graph_file);
+            }
+        }
+
+        Ok(())
+    }
+}
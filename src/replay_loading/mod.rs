@@ -1,69 +1,174 @@
+#[cfg(feature = "network")]
+mod network;
+
+/// Without network support there's nothing to listen on, so only saved logs can be replayed
+#[cfg(not(feature = "network"))]
+mod network {
+    use super::ReplaySource;
+    use crate::{
+        args::Args,
+        dataflow::operators::{DroppedEvents, TargetRuns},
+        target_process::TargetProcess,
+    };
+    use anyhow::Result;
+    use std::{net::SocketAddr, num::NonZeroUsize};
+
+    pub(super) enum StreamListener {}
+
+    pub(super) struct Listeners {
+        pub(super) timely: Option<StreamListener>,
+        pub(super) differential: Option<StreamListener>,
+        pub(super) progress: Option<StreamListener>,
+    }
+
+    impl Listeners {
+        pub(super) fn bind(args: &Args) -> Result<Self> {
+            if !args.is_file_sourced() {
+                anyhow::bail!(
+                    "ddshow was built without network support, rebuild it with the `network` \
+                    feature or replay saved logs with `ddshow analyze`",
+                );
+            }
+
+            Ok(Self {
+                timely: None,
+                differential: None,
+                progress: None,
+            })
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn accept_sources<R, A>(
+        args: &Args,
+        listener: StreamListener,
+        address: SocketAddr,
+        connections: NonZeroUsize,
+        target: &str,
+        target_process: &mut Option<TargetProcess>,
+        runs: Option<&TargetRuns>,
+        dropped: &DroppedEvents,
+    ) -> Result<ReplaySource<R, A>> {
+        let _ = (
+            args,
+            address,
+            connections,
+            target,
+            target_process,
+            runs,
+            dropped,
+        );
+        match listener {}
+    }
+}
+
 use crate::{
     args::{Args, StreamEncoding},
     dataflow::{
-        constants::{IDLE_EXTRACTION_FUEL, TCP_READ_TIMEOUT},
-        operators::{EventIterator, EventReader, Fuel, RkyvEventReader},
+        compact_timeline,
+        operators::{
+            DroppedEvents, EventIterator, InjectedEventReader, RawEventAdapter, RkyvEventReader,
+            RunSegment, TargetRuns,
+        },
         utils::{DifferentialLogBundle, ProgressLogBundle, TimelyLogBundle},
-        DataflowData, DataflowReceivers,
+        DataflowData, DataflowExtractor,
+    },
+    target_process::TargetProcess,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    dataflow::{
+        constants::IDLE_EXTRACTION_FUEL,
+        operators::{Fuel, MappedEventReader},
+        DataflowReceivers, StatsSinks, TimelineSpill,
     },
+    live_server::{LiveServer, LIVE_UPDATE_INTERVAL},
+    status::{CaptureStatus, StatusLine},
 };
 use abomonation::Abomonation;
 use anyhow::{Context, Result};
 use bytecheck::CheckBytes;
 use crossbeam_channel::Receiver;
 use ddshow_sink::{DIFFERENTIAL_ARRANGEMENT_LOG_FILE, TIMELY_LOG_FILE, TIMELY_PROGRESS_LOG_FILE};
-use ddshow_types::progress_logging::TimelyProgressEvent;
+use ddshow_types::{progress_logging::TimelyProgressEvent, WorkerId};
 use differential_dataflow::logging::DifferentialEvent as RawDifferentialEvent;
+#[cfg(all(not(target_arch = "wasm32"), unix))]
+use memmap2::Advice;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
 use rkyv::{
     de::deserializers::SharedDeserializeMap, validation::validators::DefaultValidator, Archive,
     Deserialize,
 };
+use serde::de::DeserializeOwned;
 use std::{
+    collections::BTreeMap,
     ffi::OsStr,
     fmt::Debug,
     fs::{self, File},
-    io::{self, BufReader, BufWriter, Read, Write},
-    iter,
-    net::{SocketAddr, TcpListener, TcpStream},
+    io::{BufReader, BufWriter, Cursor, Read, Write},
+    iter, mem,
+    net::SocketAddr,
     num::NonZeroUsize,
-    path::PathBuf,
-    sync::{
-        atomic::{self, AtomicBool, AtomicUsize, Ordering},
-        Arc,
-    },
+    path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
-use timely::{
-    communication::WorkerGuards, dataflow::operators::capture::Event,
-    logging::TimelyEvent as RawTimelyEvent,
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    io,
+    sync::atomic::{self, AtomicBool, AtomicUsize, Ordering},
+    time::Instant,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use timely::communication::WorkerGuards;
+use timely::{dataflow::operators::capture::Event, logging::TimelyEvent as RawTimelyEvent};
 
-type AcquiredStreams<T, D1, D2> = EventReceivers<
-    RkyvEventReader<T, D1, Box<dyn Read + Send + 'static>>,
-    EventReader<T, D2, TcpStream>,
->;
+type AcquiredStreams<D1, D2> =
+    EventReceivers<TypedEventReader<Duration, D1>, RawEventReader<Duration, D2>>;
+
+/// A reader over ddshow's own event types, either rkyv or bincode encoded
+/// or adapted from a stream of raw events
+pub(crate) type TypedEventReader<T, D> = Box<dyn EventIterator<T, D> + Send + 'static>;
+
+/// A reader over timely's raw event types from abomonation encoded streams
+pub(crate) type RawEventReader<T, D> = Box<dyn EventIterator<T, D> + Send + 'static>;
 
 pub(crate) type TimelyEventReceivers = Arc<[Receiver<TimelyReplaySource>]>;
 pub(crate) type TimelyReplaySource = ReplaySource<
-    RkyvEventReader<Duration, TimelyLogBundle, Box<dyn Read + Send + 'static>>,
-    EventReader<Duration, (Duration, usize, RawTimelyEvent), TcpStream>,
+    TypedEventReader<Duration, TimelyLogBundle>,
+    RawEventReader<Duration, (Duration, usize, RawTimelyEvent)>,
 >;
 
 pub(crate) type DifferentialEventReceivers = Option<Arc<[Receiver<DifferentialReplaySource>]>>;
 pub(crate) type DifferentialReplaySource = ReplaySource<
-    RkyvEventReader<Duration, DifferentialLogBundle, Box<dyn Read + Send + 'static>>,
-    EventReader<Duration, (Duration, usize, RawDifferentialEvent), TcpStream>,
+    TypedEventReader<Duration, DifferentialLogBundle>,
+    RawEventReader<Duration, (Duration, usize, RawDifferentialEvent)>,
 >;
 
 pub(crate) type ProgressEventReceivers = Option<Arc<[Receiver<ProgressReplaySource>]>>;
 pub(crate) type ProgressReplaySource = ReplaySource<
-    RkyvEventReader<Duration, ProgressLogBundle, Box<dyn Read + Send + 'static>>,
-    EventReader<Duration, (Duration, usize, TimelyProgressEvent), TcpStream>,
+    TypedEventReader<Duration, ProgressLogBundle>,
+    RawEventReader<Duration, (Duration, usize, TimelyProgressEvent)>,
 >;
 
+/// Every source of the target's logs along with the target itself
+pub(crate) type ConnectedSources = (
+    TimelyEventReceivers,
+    DifferentialEventReceivers,
+    ProgressEventReceivers,
+    usize,
+    Option<TargetProcess>,
+    TargetRuns,
+    DroppedEvents,
+);
+
 #[derive(Debug)]
 pub enum ReplaySource<R, A> {
+    /// Sources that produce ddshow's own event types, this covers
+    /// rkyv-encoded files & streams as well as bincode-encoded streams
     Rkyv(Vec<R>),
+    /// Sources that produce timely's raw event types
     Abomonation(Vec<A>),
 }
 
@@ -91,7 +196,7 @@ impl<R, A> ReplaySource<R, A> {
         matches!(self, Self::Abomonation(..))
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, feature = "network"))]
     pub fn into_rkyv(self) -> Result<Vec<R>, Self> {
         if let Self::Rkyv(rkyv) = self {
             Ok(rkyv)
@@ -102,49 +207,18 @@ impl<R, A> ReplaySource<R, A> {
 }
 
 #[tracing::instrument(skip(args))]
-pub fn connect_to_sources(
-    args: &Args,
-) -> Result<
-    Option<(
-        TimelyEventReceivers,
-        DifferentialEventReceivers,
-        ProgressEventReceivers,
-        usize,
-    )>,
-> {
+pub fn connect_to_sources(args: &Args) -> Result<Option<ConnectedSources>> {
     let mut total_sources = 0;
 
-    let timely_listener = if !args.is_file_sourced() {
-        Some(TcpListener::bind(args.timely_address).with_context(|| {
-            anyhow::anyhow!("failed to bind to timely socket {}", args.timely_address)
-        })?)
-    } else {
-        None
-    };
-    let differential_listener = if args.differential_enabled && !args.is_file_sourced() {
-        let listener = TcpListener::bind(args.differential_address).with_context(|| {
-            anyhow::anyhow!(
-                "failed to bind to differential socket {}",
-                args.differential_address,
-            )
-        })?;
-
-        Some(listener)
-    } else {
-        None
-    };
-    let progress_listener = if args.progress_enabled && !args.is_file_sourced() {
-        let listener = TcpListener::bind(args.progress_address).with_context(|| {
-            anyhow::anyhow!(
-                "failed to bind to progress socket {}",
-                args.progress_address,
-            )
-        })?;
-
-        Some(listener)
-    } else {
-        None
-    };
+    if args.reconnect_timeout.is_some() && args.stream_encoding == StreamEncoding::Abomonation {
+        anyhow::bail!("`--reconnect-timeout` requires the rkyv, bincode or auto stream encodings");
+    }
+    let (runs, dropped) = (TargetRuns::new(), DroppedEvents::new());
+
+    let listeners = network::Listeners::bind(args)?;
+
+    // Now that all of our listeners are bound we can start up the target process
+    let mut target = TargetProcess::spawn(args)?;
 
     let mut indices = (0..args.workers.get()).cycle();
 
@@ -152,12 +226,15 @@ pub fn connect_to_sources(
     let (timely_event_receivers, are_timely_sources, num_sources) = acquire_replay_sources(
         args,
         args.timely_address,
-        timely_listener,
+        listeners.timely,
         args.timely_connections,
         args.workers,
         args.replay_logs.as_deref(),
         TIMELY_LOG_FILE,
         "Timely",
+        &mut target,
+        Some(&runs),
+        &dropped,
         &mut indices,
     )?;
     total_sources += num_sources;
@@ -167,12 +244,15 @@ pub fn connect_to_sources(
         let (receivers, are_sources, num_sources) = acquire_replay_sources(
             args,
             args.differential_address,
-            differential_listener,
+            listeners.differential,
             args.timely_connections,
             args.workers,
             args.replay_logs.as_deref(),
             DIFFERENTIAL_ARRANGEMENT_LOG_FILE,
             "Differential",
+            &mut target,
+            None,
+            &dropped,
             &mut indices,
         )?;
         total_sources += num_sources;
@@ -187,12 +267,15 @@ pub fn connect_to_sources(
         let (receivers, are_sources, num_sources) = acquire_replay_sources(
             args,
             args.progress_address,
-            progress_listener,
+            listeners.progress,
             args.timely_connections,
             args.workers,
             args.replay_logs.as_deref(),
             TIMELY_PROGRESS_LOG_FILE,
             "Progress",
+            &mut target,
+            None,
+            &dropped,
             &mut indices,
         )?;
         total_sources += num_sources;
@@ -220,29 +303,140 @@ pub fn connect_to_sources(
         differential_event_receivers,
         progress_event_receivers,
         total_sources,
+        target,
+        runs,
+        dropped,
     )))
 }
 
+/// Events and saved logs handed to ddshow directly instead of being read from
+/// the target, set with [`DDShowBuilder::inject_timely()`] and friends
+///
+/// [`DDShowBuilder::inject_timely()`]: crate::DDShowBuilder::inject_timely
+#[derive(Debug, Default)]
+pub(crate) struct InjectedEvents {
+    pub(crate) timely: Vec<TimelyLogBundle>,
+    pub(crate) differential: Vec<DifferentialLogBundle>,
+    pub(crate) progress: Vec<ProgressLogBundle>,
+    /// The contents of saved log files, each of which holds a single worker's events
+    pub(crate) timely_logs: Vec<Vec<u8>>,
+    pub(crate) differential_logs: Vec<Vec<u8>>,
+    pub(crate) progress_logs: Vec<Vec<u8>>,
+}
+
+impl InjectedEvents {
+    /// Replays the injected events as if each of the target's workers had
+    /// logged its own events over its own stream
+    pub(crate) fn into_sources(self, args: &Args) -> Result<ConnectedSources> {
+        let mut indices = (0..args.workers.get()).cycle();
+
+        let timely = injected_readers(self.timely, self.timely_logs);
+        let differential = if args.differential_enabled {
+            Some(injected_readers(self.differential, self.differential_logs))
+        } else {
+            None
+        };
+        let progress = if args.progress_enabled {
+            Some(injected_readers(self.progress, self.progress_logs))
+        } else {
+            None
+        };
+        let total_sources = timely.len()
+            + differential.as_ref().map_or(0, Vec::len)
+            + progress.as_ref().map_or(0, Vec::len);
+
+        tracing::info!("replaying injected events from {} sources", total_sources);
+
+        let timely = make_streams(
+            args.workers.get(),
+            TimelyReplaySource::Rkyv(timely),
+            &mut indices,
+        )?;
+        let differential = differential
+            .map(|readers| {
+                make_streams(
+                    args.workers.get(),
+                    DifferentialReplaySource::Rkyv(readers),
+                    &mut indices,
+                )
+            })
+            .transpose()?;
+        let progress = progress
+            .map(|readers| {
+                make_streams(
+                    args.workers.get(),
+                    ProgressReplaySource::Rkyv(readers),
+                    &mut indices,
+                )
+            })
+            .transpose()?;
+
+        Ok((
+            timely,
+            differential,
+            progress,
+            total_sources,
+            None,
+            TargetRuns::new(),
+            DroppedEvents::new(),
+        ))
+    }
+}
+
+/// Splits events up by the worker that logged them with one reader per worker,
+/// followed by a reader for each saved log
+fn injected_readers<E>(
+    events: Vec<(Duration, WorkerId, E)>,
+    logs: Vec<Vec<u8>>,
+) -> Vec<TypedEventReader<Duration, (Duration, WorkerId, E)>>
+where
+    E: Send + 'static,
+    (Duration, WorkerId, E): Archive,
+    <(Duration, WorkerId, E) as Archive>::Archived: Deserialize<(Duration, WorkerId, E), SharedDeserializeMap>
+        + for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    let mut workers: BTreeMap<WorkerId, Vec<_>> = BTreeMap::new();
+    for event in events {
+        workers.entry(event.1).or_default().push(event);
+    }
+
+    // Streams without any events still have to exist for the replay to complete
+    if workers.is_empty() && logs.is_empty() {
+        workers.insert(WorkerId::new(0), Vec::new());
+    }
+
+    workers
+        .into_iter()
+        .map(|(_, events)| Box::new(InjectedEventReader::new(events)) as TypedEventReader<_, _>)
+        .chain(logs.into_iter().map(|log| {
+            Box::new(RkyvEventReader::new(
+                Box::new(Cursor::new(log)) as Box<dyn Read + Send + 'static>
+            )) as TypedEventReader<_, _>
+        }))
+        .collect()
+}
+
 /// Connect to and prepare the replay sources
-#[tracing::instrument(skip(args, indices))]
+#[tracing::instrument(skip(args, listener, target_process, runs, dropped, indices))]
 #[allow(clippy::too_many_arguments)]
-pub fn acquire_replay_sources<T, D1, D2, I>(
+pub fn acquire_replay_sources<D1, D2, I>(
     args: &Args,
     address: SocketAddr,
-    listener: Option<TcpListener>,
+    listener: Option<network::StreamListener>,
     connections: NonZeroUsize,
     workers: NonZeroUsize,
     log_dirs: Option<&[PathBuf]>,
     file_prefix: &str,
     target: &str,
+    target_process: &mut Option<TargetProcess>,
+    runs: Option<&TargetRuns>,
+    dropped: &DroppedEvents,
     indices: &mut I,
-) -> Result<(AcquiredStreams<T, D1, D2>, bool, usize)>
+) -> Result<(AcquiredStreams<D1, D2>, bool, usize)>
 where
-    Event<T, D2>: Clone,
-    D2: Abomonation + Send + 'static,
-    T: Debug + Archive + Abomonation + Send + 'static,
-    T::Archived: Deserialize<T, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
-    D1: Debug + Archive + Send + 'static,
+    Event<Duration, D2>: Clone,
+    D2: Abomonation + Clone + Send + 'static,
+    D1: Debug + Archive + DeserializeOwned + RawEventAdapter<D2> + RunSegment + Send + 'static,
     D1::Archived: Deserialize<D1, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
     I: Iterator<Item = usize>,
 {
@@ -295,7 +489,7 @@ where
     // utils::set_steady_tick(&progress, connections.get());
 
     let replay_sources = if let Some(log_dirs) = log_dirs {
-        let mut replays = Vec::with_capacity(connections.get());
+        let mut replay_files = Vec::with_capacity(connections.get());
 
         for log_dir in log_dirs {
             // progress.set_prefix(format!(
@@ -346,8 +540,9 @@ where
                                 .context("failed to create event debug file")?,
                         );
 
-                        let mut reader =
-                            RkyvEventReader::<T, D1, _>::new(Box::new(BufReader::new(input_file)));
+                        let mut reader = RkyvEventReader::<Duration, D1, _>::new(Box::new(
+                            BufReader::new(input_file),
+                        ));
 
                         let mut is_finished = false;
                         while !is_finished {
@@ -361,19 +556,7 @@ where
                         }
                     }
 
-                    let replay_file = File::open(&replay_file_path).with_context(|| {
-                        format!("failed to open {} log file within replay directory", target)
-                    })?;
-                    tracing::debug!(
-                        length = ?replay_file.metadata().map(|meta| meta.len()),
-                        "loaded {} replay from {}",
-                        target,
-                        replay_file_path.display(),
-                    );
-
-                    replays.push(RkyvEventReader::new(
-                        Box::new(BufReader::new(replay_file)) as Box<dyn Read + Send + 'static>
-                    ));
+                    replay_files.push(replay_file_path);
 
                     // progress.inc(1);
                     num_sources += 1;
@@ -414,36 +597,27 @@ where
         //     if progress.position() == 1 { "" } else { "s" },
         // ));
 
-        ReplaySource::Rkyv(replays)
+        ReplaySource::Rkyv(open_replay_files(&replay_files, target)?)
     } else {
         let listener = listener.expect("a listener must be supplied for stream sources");
-
-        tracing::debug!(
-            stream_encoding = ?args.stream_encoding,
-            address = ?address,
-            connections = ?connections,
-            "connecting to source of encoding {}",
-            args.stream_encoding,
-        );
-
-        let source = match args.stream_encoding {
-            StreamEncoding::Abomonation => {
-                wait_for_abominated_connections(args, listener, &address, connections)?
-            }
-            StreamEncoding::Rkyv => {
-                wait_for_rkyv_connections(args, listener, &address, connections)?
-            }
-        };
-
         num_sources += connections.get();
 
+        network::accept_sources(
+            args,
+            listener,
+            address,
+            connections,
+            target,
+            target_process,
+            runs,
+            dropped,
+        )?
+
         // progress.set_style(finished_style);
         // progress.finish_with_message(format!(
         //     "connected to {} trace source{}",
         //     connections, plural,
         // ));
-
-        source
     };
 
     let are_replay_sources = !replay_sources.is_empty();
@@ -452,6 +626,81 @@ where
     Ok((event_receivers, are_replay_sources, num_sources))
 }
 
+/// Opens every replay file, see [`open_replay_file`]
+fn open_replay_files<D>(
+    replay_files: &[PathBuf],
+    target: &str,
+) -> Result<Vec<TypedEventReader<Duration, D>>>
+where
+    D: Archive + Send + 'static,
+    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    replay_files
+        .iter()
+        .map(|replay_file| open_replay_file(replay_file, target))
+        .collect()
+}
+
+/// Memory-maps the replay file so its events are decoded directly from the
+/// mapping, falling back to buffered reads for files that can't be mapped
+/// and on platforms without memory maps
+fn open_replay_file<D>(
+    replay_file_path: &Path,
+    target: &str,
+) -> Result<TypedEventReader<Duration, D>>
+where
+    D: Archive + Send + 'static,
+    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    let replay_file = File::open(replay_file_path)
+        .with_context(|| format!("failed to open {} log file within replay directory", target))?;
+    let length = replay_file.metadata().map(|meta| meta.len());
+
+    // Empty files can't be mapped on every platform
+    #[cfg(not(target_arch = "wasm32"))]
+    if length.as_ref().map_or(true, |&length| length != 0) {
+        // Safety: Replay files aren't expected to change while they're being
+        //         replayed, if the file's truncated by another process the
+        //         replay will fault instead of reading a partial event
+        match unsafe { Mmap::map(&replay_file) } {
+            Ok(mapping) => {
+                #[cfg(unix)]
+                {
+                    if let Err(err) = mapping.advise(Advice::Sequential) {
+                        tracing::debug!("failed to advise sequential replay access: {:?}", err);
+                    }
+                }
+
+                tracing::debug!(
+                    length = ?length,
+                    "mapped {} replay from {}",
+                    target,
+                    replay_file_path.display(),
+                );
+
+                return Ok(Box::new(MappedEventReader::new(mapping)));
+            }
+
+            Err(err) => tracing::warn!(
+                "failed to map {}, falling back to buffered reads: {:?}",
+                replay_file_path.display(),
+                err,
+            ),
+        }
+    }
+
+    tracing::debug!(
+        length = ?length,
+        "loaded {} replay from {}",
+        target,
+        replay_file_path.display(),
+    );
+
+    Ok(Box::new(RkyvEventReader::new(
+        Box::new(BufReader::new(replay_file)) as Box<dyn Read + Send + 'static>,
+    )))
+}
+
 pub type EventReceivers<R, A> = Arc<[Receiver<ReplaySource<R, A>>]>;
 
 #[tracing::instrument(
@@ -527,155 +776,16 @@ where
     Ok(Arc::from(receivers))
 }
 
-/// Connect to the given address and collect `connections` streams, returning all of them
-/// in non-blocking mode
-#[tracing::instrument]
-pub fn wait_for_abominated_connections<T, D, R>(
-    args: &Args,
-    listener: TcpListener,
-    addr: &SocketAddr,
-    connections: NonZeroUsize,
-    // progress: &ProgressBar,
-) -> Result<ReplaySource<R, EventReader<T, D, TcpStream>>>
-where
-    Event<T, D>: Clone,
-    T: Abomonation + Send + 'static,
-    D: Abomonation + Send + 'static,
-{
-    assert_eq!(
-        args.stream_encoding,
-        StreamEncoding::Abomonation,
-        "abominated connections come from abominated stream encodings",
-    );
-
-    // progress.set_message(format!(
-    //     "connected to 0/{} socket{}",
-    //     connections,
-    //     if connections.get() == 1 { "" } else { "s" },
-    // ));
-    // progress.set_length(connections.get() as u64);
-
-    let timely_conns = (0..connections.get())
-        .zip(listener.incoming())
-        .map(|(idx, socket)| {
-            let socket = socket.context("failed to accept socket connection")?;
-
-            socket
-                .set_nonblocking(true)
-                .context("failed to set socket to non-blocking mode")?;
-
-            if let Err(err) = socket.set_read_timeout(TCP_READ_TIMEOUT) {
-                tracing::error!(
-                    "failed to set socket to a read timeout of {:?}: {:?}",
-                    TCP_READ_TIMEOUT,
-                    err,
-                );
-            };
-
-            tracing::info!(
-                socket = ?socket,
-                stream_encoding = ?StreamEncoding::Abomonation,
-                "connected to socket {}/{}",
-                idx + 1,
-                connections,
-            );
-
-            // progress.set_message(format!(
-            //     "connected to {}/{} socket{}",
-            //     idx + 1,
-            //     connections,
-            //     if connections.get() == 1 { "" } else { "s" },
-            // ));
-            // progress.inc(1);
-
-            Ok(EventReader::new(socket))
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    Ok(ReplaySource::Abomonation(timely_conns))
-}
-
-type ConnectedRkyvSource<T, D, A> =
-    ReplaySource<RkyvEventReader<T, D, Box<dyn Read + Send + 'static>>, A>;
-
-/// Connect to the given address and collect `connections` streams, returning all of them
-/// in non-blocking mode
-#[tracing::instrument]
-pub fn wait_for_rkyv_connections<T, D, A>(
-    args: &Args,
-    listener: TcpListener,
-    addr: &SocketAddr,
-    connections: NonZeroUsize,
-    // progress: &ProgressBar,
-) -> Result<ConnectedRkyvSource<T, D, A>>
-where
-    T: Archive,
-    T::Archived: Deserialize<T, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
-    D: Archive,
-    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
-{
-    assert_eq!(
-        args.stream_encoding,
-        StreamEncoding::Rkyv,
-        "rkyv connections come from rkyv stream encodings",
-    );
-
-    // progress.set_message(format!(
-    //     "connected to 0/{} socket{}",
-    //     connections,
-    //     if connections.get() == 1 { "" } else { "s" },
-    // ));
-    // progress.set_length(connections.get() as u64);
-
-    let timely_conns = (0..connections.get())
-        .zip(listener.incoming())
-        .map(|(idx, socket)| {
-            let socket = socket.context("failed to accept socket connection")?;
-
-            socket
-                .set_nonblocking(true)
-                .context("failed to set socket to non-blocking mode")?;
-
-            if let Err(err) = socket.set_read_timeout(TCP_READ_TIMEOUT) {
-                tracing::error!(
-                    "failed to set socket to a read timeout of {:?}: {:?}",
-                    TCP_READ_TIMEOUT,
-                    err,
-                );
-            };
-
-            tracing::info!(
-                socket = ?socket,
-                "connected to socket {}/{}",
-                idx + 1,
-                connections,
-            );
-
-            // progress.set_message(format!(
-            //     "connected to {}/{} socket{}",
-            //     idx + 1,
-            //     connections,
-            //     if connections.get() == 1 { "" } else { "s" },
-            // ));
-            // progress.inc(1);
-
-            Ok(RkyvEventReader::new(
-                Box::new(socket) as Box<dyn Read + Send + 'static>
-            ))
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    Ok(ReplaySource::Rkyv(timely_conns))
-}
-
 /// Wait for user input to terminate the trace replay and wait for all timely
 /// workers to terminate
 // TODO: Add a "haven't received updates in `n` seconds" thingy to tell the user
 //       we're no longer getting data
 #[tracing::instrument(
-    skip(args, worker_guards, receivers),
+    skip(args, worker_guards, receivers, runs, dropped, stats_sinks),
     fields(workers = worker_guards.guards().len()),
 )]
+#[allow(clippy::too_many_arguments)]
+#[cfg(not(target_arch = "wasm32"))]
 pub fn wait_for_input(
     args: &Args,
     running: &AtomicBool,
@@ -684,11 +794,19 @@ pub fn wait_for_input(
     total_replays: usize,
     worker_guards: WorkerGuards<Result<()>>,
     receivers: DataflowReceivers,
+    live_server: Option<&LiveServer>,
+    runs: &TargetRuns,
+    dropped: &DroppedEvents,
+    capture_status: Arc<CaptureStatus>,
+    stats_sinks: StatsSinks,
 ) -> Result<DataflowData> {
     if args.isnt_quiet() {
         // Write a prompt to the terminal for the user
         let message = if args.is_file_sourced() {
             "Press ctrl+c to stop loading trace data (this will cause data to not be fully processed)..."
+        } else if args.spawns_target() {
+            "Collecting trace data until the target process exits, press ctrl+c to stop it early \
+            (this will cause data to not be fully processed)..."
         } else {
             "Press ctrl+c to stop collecting trace data (this will crash the source computation \
             if it's currently running and cause data to not be fully processed)..."
@@ -698,9 +816,14 @@ pub fn wait_for_input(
 
     let (mut fuel, mut extractor) = (
         Fuel::limited(IDLE_EXTRACTION_FUEL),
-        receivers.into_extractor(),
+        receivers
+            .into_extractor()
+            .with_stats_sinks(stats_sinks)
+            .with_timeline_spill(args.timeline_memory_budget.map(TimelineSpill::new)),
     );
     let num_threads = worker_guards.guards().len();
+    let mut last_live_update = Instant::now();
+    let mut status_line = StatusLine::new(args, capture_status, total_replays);
 
     loop {
         // If all workers finish their computations
@@ -743,6 +866,22 @@ pub fn wait_for_input(
             break;
         }
 
+        // Push the stats we have so far out to the live page
+        if let Some(server) = live_server {
+            if last_live_update.elapsed() >= LIVE_UPDATE_INTERVAL {
+                server.publish(
+                    &extractor.current_dataflow_data(),
+                    &runs.snapshot(),
+                    dropped.count(),
+                );
+                last_live_update = Instant::now();
+            }
+        }
+
+        if let Some(status_line) = status_line.as_mut() {
+            status_line.tick()?;
+        }
+
         tracing::trace!(
             target: "main_thread_fuel_consumption",
             used = ?fuel.used(),
@@ -759,6 +898,10 @@ pub fn wait_for_input(
     running.store(false, Ordering::Release);
     atomic::fence(Ordering::Acquire);
 
+    if let Some(status_line) = status_line {
+        status_line.finish()?;
+    }
+
     if args.isnt_quiet() {
         let mut stdout = io::stdout();
         write!(stdout, "Processing data...").context("failed to write to stdout")?;
@@ -777,8 +920,7 @@ pub fn wait_for_input(
             .map_err(|err| anyhow::anyhow!("failed to join timely worker threads: {}", err))??;
     }
 
-    tracing::debug!("extracting all remaining data from the dataflow");
-    let data = extractor.extract_all();
+    let data = extract_remaining(args, extractor);
 
     if args.isnt_quiet() {
         println!(" done!");
@@ -787,7 +929,23 @@ pub fn wait_for_input(
     Ok(data)
 }
 
-#[cfg(test)]
+/// Extracts everything left within the dataflow's channels once all of its workers have finished
+pub(crate) fn extract_remaining(args: &Args, extractor: DataflowExtractor) -> DataflowData {
+    tracing::debug!("extracting all remaining data from the dataflow");
+    let mut data = extractor.extract_all();
+
+    if args.compacts_timeline() {
+        data.timeline_events = compact_timeline(
+            mem::take(&mut data.timeline_events),
+            Duration::from_micros(args.timeline_merge_gap.unwrap_or(0)),
+            Duration::from_micros(args.timeline_minor_threshold.unwrap_or(0)),
+        );
+    }
+
+    data
+}
+
+#[cfg(all(test, feature = "network"))]
 mod tests {
     use crate::{
         args::StreamEncoding,
@@ -853,7 +1011,7 @@ mod tests {
             quiet: true,
             ..Default::default()
         };
-        logging::init_logging(args.color);
+        logging::init_logging(&args).unwrap();
 
         let barrier = Arc::new(Barrier::new(2));
         let events = vec![
@@ -864,10 +1022,11 @@ mod tests {
         target_program(barrier.clone(), args.timely_address, events.clone());
         barrier.wait();
 
-        let (timely_recv, differential_recv, progress_recv, total_sources) =
+        let (timely_recv, differential_recv, progress_recv, total_sources, target, _runs, _dropped) =
             connect_to_sources(&args).unwrap().unwrap();
 
         assert_eq!(total_sources, 1);
+        assert_matches!(target, None);
         assert_matches!(differential_recv, None);
         assert_matches!(progress_recv, None);
 
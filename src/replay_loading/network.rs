@@ -0,0 +1,407 @@
+//! Accepting the target's log streams over the network
+
+use super::{RawEventReader, ReplaySource, TypedEventReader};
+use crate::{
+    args::{Args, BufferPolicy, StreamEncoding},
+    dataflow::{
+        constants::TCP_READ_TIMEOUT,
+        operators::{
+            AutoEventReader, BincodeEventReader, BufferedReader, ConnectReader, DroppedEvents,
+            EventIterator, EventReader, RawEventAdapter, ReconnectingReader, RkyvEventReader,
+            RunSegment, TargetRuns,
+        },
+    },
+    target_process::{self, TargetProcess},
+    tls::{self, LogStream, TlsConfig},
+};
+use abomonation::Abomonation;
+use anyhow::{Context, Result};
+use bytecheck::CheckBytes;
+use rkyv::{
+    de::deserializers::SharedDeserializeMap, validation::validators::DefaultValidator, Archive,
+    Deserialize,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    io::Read,
+    net::{SocketAddr, TcpListener},
+    num::NonZeroUsize,
+    sync::Arc,
+    time::Duration,
+};
+use timely::dataflow::operators::capture::Event;
+
+/// The sockets that each of the target's log streams connect to
+pub(super) struct Listeners {
+    pub(super) timely: Option<StreamListener>,
+    pub(super) differential: Option<StreamListener>,
+    pub(super) progress: Option<StreamListener>,
+}
+
+impl Listeners {
+    /// Binds every enabled stream's socket, these have to be bound before
+    /// the target is spawned so that it has something to connect to
+    pub(super) fn bind(args: &Args) -> Result<Self> {
+        // Load the tls config before binding anything so that bad certificates fail fast
+        let tls = tls::server_config(args)?;
+
+        if args.is_file_sourced() {
+            return Ok(Self {
+                timely: None,
+                differential: None,
+                progress: None,
+            });
+        }
+
+        let bind = |address: SocketAddr, target: &str| -> Result<StreamListener> {
+            let listener = TcpListener::bind(address).with_context(|| {
+                anyhow::anyhow!("failed to bind to {} socket {}", target, address)
+            })?;
+
+            Ok(StreamListener {
+                listener,
+                tls: tls.clone(),
+            })
+        };
+
+        Ok(Self {
+            timely: Some(bind(args.timely_address, "timely")?),
+            differential: if args.differential_enabled {
+                Some(bind(args.differential_address, "differential")?)
+            } else {
+                None
+            },
+            progress: if args.progress_enabled {
+                Some(bind(args.progress_address, "progress")?)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+/// A bound socket along with the tls config that its connections are accepted with
+pub(super) struct StreamListener {
+    listener: TcpListener,
+    tls: Option<TlsConfig>,
+}
+
+/// Waits for all `connections` of a stream to connect, buffering them
+/// when `--ingest-buffer` is set
+#[allow(clippy::too_many_arguments)]
+pub(super) fn accept_sources<D1, D2>(
+    args: &Args,
+    listener: StreamListener,
+    address: SocketAddr,
+    connections: NonZeroUsize,
+    target: &str,
+    target_process: &mut Option<TargetProcess>,
+    runs: Option<&TargetRuns>,
+    dropped: &DroppedEvents,
+) -> Result<ReplaySource<TypedEventReader<Duration, D1>, RawEventReader<Duration, D2>>>
+where
+    Event<Duration, D2>: Clone,
+    D2: Abomonation + Clone + Send + 'static,
+    D1: Archive + DeserializeOwned + RawEventAdapter<D2> + RunSegment + Send + 'static,
+    D1::Archived: Deserialize<D1, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    let StreamListener { listener, tls } = listener;
+
+    tracing::debug!(
+        stream_encoding = ?args.stream_encoding,
+        address = ?address,
+        connections = ?connections,
+        "connecting to source of encoding {}",
+        args.stream_encoding,
+    );
+
+    let source = match args.stream_encoding {
+        StreamEncoding::Abomonation => wait_for_abominated_connections(
+            args,
+            listener,
+            tls.as_ref(),
+            &address,
+            connections,
+            target_process,
+        )?,
+        StreamEncoding::Rkyv | StreamEncoding::Bincode | StreamEncoding::Auto => {
+            wait_for_typed_connections(
+                args,
+                listener,
+                tls.as_ref(),
+                &address,
+                connections,
+                target_process,
+                runs,
+            )?
+        }
+    };
+
+    // Move each stream onto its own thread with a bounded buffer
+    if let Some(capacity) = args.ingest_capacity() {
+        buffer_sources(source, target, capacity.get(), args.buffer_policy, dropped)
+    } else {
+        Ok(source)
+    }
+}
+
+/// Connect to the given address and collect `connections` streams, returning all of them
+/// in non-blocking mode
+#[tracing::instrument(skip(tls, target))]
+pub fn wait_for_abominated_connections<T, D, R>(
+    args: &Args,
+    listener: TcpListener,
+    tls: Option<&TlsConfig>,
+    addr: &SocketAddr,
+    connections: NonZeroUsize,
+    target: &mut Option<TargetProcess>,
+    // progress: &ProgressBar,
+) -> Result<ReplaySource<R, RawEventReader<T, D>>>
+where
+    Event<T, D>: Clone,
+    T: Abomonation + Send + 'static,
+    D: Abomonation + Send + 'static,
+{
+    assert_eq!(
+        args.stream_encoding,
+        StreamEncoding::Abomonation,
+        "abominated connections come from abominated stream encodings",
+    );
+
+    // progress.set_message(format!(
+    //     "connected to 0/{} socket{}",
+    //     connections,
+    //     if connections.get() == 1 { "" } else { "s" },
+    // ));
+    // progress.set_length(connections.get() as u64);
+
+    let timely_conns = (0..connections.get())
+        .map(|idx| {
+            let socket = target_process::accept_connection(&listener, target)?;
+            let socket = LogStream::accept(socket, tls)?;
+
+            socket
+                .socket()
+                .set_nonblocking(true)
+                .context("failed to set socket to non-blocking mode")?;
+
+            if let Err(err) = socket.socket().set_read_timeout(TCP_READ_TIMEOUT) {
+                tracing::error!(
+                    "failed to set socket to a read timeout of {:?}: {:?}",
+                    TCP_READ_TIMEOUT,
+                    err,
+                );
+            };
+
+            tracing::info!(
+                socket = ?socket,
+                stream_encoding = ?StreamEncoding::Abomonation,
+                "connected to socket {}/{}",
+                idx + 1,
+                connections,
+            );
+
+            // progress.set_message(format!(
+            //     "connected to {}/{} socket{}",
+            //     idx + 1,
+            //     connections,
+            //     if connections.get() == 1 { "" } else { "s" },
+            // ));
+            // progress.inc(1);
+
+            Ok(Box::new(EventReader::new(socket)) as RawEventReader<T, D>)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ReplaySource::Abomonation(timely_conns))
+}
+
+type ConnectedTypedSource<D, A> =
+    ReplaySource<TypedEventReader<Duration, D>, RawEventReader<Duration, A>>;
+
+/// Connect to the given address and collect `connections` rkyv or bincode streams,
+/// returning all of them in non-blocking mode
+///
+/// When the stream encoding is `auto` each stream's encoding is detected from the
+/// first bytes it sends, with raw abomonation events being adapted into ddshow's
+/// own event types. When `--reconnect-timeout` is given the listener is kept around
+/// so that the target can reconnect after its connections close, with each
+/// reconnection starting a new run of the target
+#[tracing::instrument(skip(tls, target, runs))]
+#[allow(clippy::too_many_arguments)]
+pub fn wait_for_typed_connections<D, A>(
+    args: &Args,
+    listener: TcpListener,
+    tls: Option<&TlsConfig>,
+    addr: &SocketAddr,
+    connections: NonZeroUsize,
+    target: &mut Option<TargetProcess>,
+    runs: Option<&TargetRuns>,
+    // progress: &ProgressBar,
+) -> Result<ConnectedTypedSource<D, A>>
+where
+    D: Archive + DeserializeOwned + RawEventAdapter<A> + RunSegment + Send + 'static,
+    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+    A: Abomonation + Clone + Send + 'static,
+{
+    assert_ne!(
+        args.stream_encoding,
+        StreamEncoding::Abomonation,
+        "typed connections come from rkyv, bincode or auto-detected stream encodings",
+    );
+
+    // progress.set_message(format!(
+    //     "connected to 0/{} socket{}",
+    //     connections,
+    //     if connections.get() == 1 { "" } else { "s" },
+    // ));
+    // progress.set_length(connections.get() as u64);
+
+    let connect = connect_typed_reader::<D, A>(args.stream_encoding, tls.cloned());
+    let timely_conns = (0..connections.get())
+        .map(|idx| {
+            let socket = target_process::accept_connection(&listener, target)?;
+            let reader = connect(socket)?;
+
+            tracing::info!(
+                stream_encoding = ?args.stream_encoding,
+                "connected to socket {}/{}",
+                idx + 1,
+                connections,
+            );
+
+            // progress.set_message(format!(
+            //     "connected to {}/{} socket{}",
+            //     idx + 1,
+            //     connections,
+            //     if connections.get() == 1 { "" } else { "s" },
+            // ));
+            // progress.inc(1);
+
+            Ok(reader)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let timely_conns = if let Some(reconnect_timeout) = args.reconnect_timeout {
+        listener
+            .set_nonblocking(true)
+            .context("failed to set listener to non-blocking mode")?;
+        let listener = Arc::new(listener);
+
+        timely_conns
+            .into_iter()
+            .map(|reader| {
+                Box::new(ReconnectingReader::new(
+                    reader,
+                    listener.clone(),
+                    connect.clone(),
+                    Duration::from_secs(reconnect_timeout),
+                    connections.get(),
+                    runs.cloned(),
+                )) as TypedEventReader<Duration, D>
+            })
+            .collect()
+    } else {
+        timely_conns
+    };
+
+    Ok(ReplaySource::Rkyv(timely_conns))
+}
+
+/// Wraps every stream within `source` in a [`BufferedReader`] so that each of
+/// them is read from its own thread
+fn buffer_sources<D1, D2>(
+    source: ReplaySource<TypedEventReader<Duration, D1>, RawEventReader<Duration, D2>>,
+    target: &str,
+    capacity: usize,
+    policy: BufferPolicy,
+    dropped: &DroppedEvents,
+) -> Result<ReplaySource<TypedEventReader<Duration, D1>, RawEventReader<Duration, D2>>>
+where
+    D1: Send + 'static,
+    D2: Send + 'static,
+{
+    tracing::debug!(
+        capacity = capacity,
+        policy = ?policy,
+        "buffering {} {} streams",
+        source.len(),
+        target.to_lowercase(),
+    );
+
+    fn spawn<D>(
+        readers: Vec<Box<dyn EventIterator<Duration, D> + Send + 'static>>,
+        target: &str,
+        capacity: usize,
+        policy: BufferPolicy,
+        dropped: &DroppedEvents,
+    ) -> Result<Vec<Box<dyn EventIterator<Duration, D> + Send + 'static>>>
+    where
+        D: Send + 'static,
+    {
+        readers
+            .into_iter()
+            .enumerate()
+            .map(|(idx, reader)| {
+                let name = format!("ddshow-{}-ingest-{}", target.to_lowercase(), idx);
+                let reader = BufferedReader::spawn(name, reader, capacity, policy, dropped.clone())
+                    .with_context(|| {
+                        anyhow::anyhow!("failed to spawn {} ingestion thread {}", target, idx)
+                    })?;
+
+                Ok(Box::new(reader) as Box<dyn EventIterator<Duration, D> + Send + 'static>)
+            })
+            .collect()
+    }
+
+    let source = match source {
+        ReplaySource::Rkyv(readers) => {
+            ReplaySource::Rkyv(spawn(readers, target, capacity, policy, dropped)?)
+        }
+        ReplaySource::Abomonation(readers) => {
+            ReplaySource::Abomonation(spawn(readers, target, capacity, policy, dropped)?)
+        }
+    };
+
+    Ok(source)
+}
+
+/// Creates the function used to turn accepted sockets into readers of the given encoding
+fn connect_typed_reader<D, A>(
+    stream_encoding: StreamEncoding,
+    tls: Option<TlsConfig>,
+) -> ConnectReader<D>
+where
+    D: Archive + DeserializeOwned + RawEventAdapter<A> + Send + 'static,
+    D::Archived: Deserialize<D, SharedDeserializeMap> + for<'a> CheckBytes<DefaultValidator<'a>>,
+    A: Abomonation + Clone + Send + 'static,
+{
+    Arc::new(move |socket| {
+        let socket = LogStream::accept(socket, tls.as_ref())?;
+
+        socket
+            .socket()
+            .set_nonblocking(true)
+            .context("failed to set socket to non-blocking mode")?;
+
+        if let Err(err) = socket.socket().set_read_timeout(TCP_READ_TIMEOUT) {
+            tracing::error!(
+                "failed to set socket to a read timeout of {:?}: {:?}",
+                TCP_READ_TIMEOUT,
+                err,
+            );
+        };
+        tracing::debug!(socket = ?socket, "accepted connection");
+
+        let reader: TypedEventReader<Duration, D> = match stream_encoding {
+            StreamEncoding::Rkyv => Box::new(RkyvEventReader::new(
+                Box::new(socket) as Box<dyn Read + Send + 'static>
+            )),
+            StreamEncoding::Bincode => Box::new(BincodeEventReader::new(socket)),
+            StreamEncoding::Auto => Box::new(AutoEventReader::<Duration, D, A, _>::new(socket)),
+            StreamEncoding::Abomonation => unreachable!(),
+        };
+
+        Ok(reader)
+    })
+}
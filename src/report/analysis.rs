@@ -0,0 +1,655 @@
+//! Analyses over the collected data that the report and the run summary
+//! draw their findings from
+
+use crate::{
+    args::Args,
+    dataflow::{
+        utils::{OpKey, XXHasher},
+        Channel, DataflowData, Summation,
+    },
+    ui::{ActivationAnomaly, ChannelMessageStats, Lifespan, Throughput, WorkerSkew},
+};
+use ddshow_types::{timely_logging::OperatesEvent, ChannelId, OperatorAddr, OperatorId, WorkerId};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    time::Duration,
+};
+
+/// Calculates the skew of every operator across the given number of workers,
+/// workers that never activated an operator count as spending no time in it
+pub fn operator_skew(
+    summarized: &[(OpKey, Summation)],
+    workers: usize,
+) -> HashMap<OperatorId, WorkerSkew, XXHasher> {
+    let mut totals: HashMap<OperatorId, (Duration, Duration), XXHasher> = HashMap::default();
+    for &((_worker, operator), ref stats) in summarized {
+        let (max, total) = totals.entry(operator).or_default();
+        *max = (*max).max(stats.total);
+        *total += stats.total;
+    }
+
+    totals
+        .into_iter()
+        .map(|(operator, (max, total))| {
+            let mean = total / workers.max(1) as u32;
+            (operator, WorkerSkew { max, mean })
+        })
+        .collect()
+}
+
+/// How a worker's busy time, logged events and sent records compare to the
+/// mean across all workers, deviations are percentages of that mean
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorkerImbalance {
+    pub worker: WorkerId,
+    pub busy: Duration,
+    pub events: usize,
+    pub records_sent: usize,
+    pub busy_deviation: f64,
+    pub events_deviation: f64,
+    pub records_deviation: f64,
+}
+
+impl WorkerImbalance {
+    /// The names of the measurements that deviate from the mean by more
+    /// than `threshold` percent
+    pub fn imbalanced(&self, threshold: f64) -> Vec<&'static str> {
+        IntoIterator::into_iter([
+            ("busy time", self.busy_deviation),
+            ("events", self.events_deviation),
+            ("records sent", self.records_deviation),
+        ])
+        .filter(|&(_, deviation)| deviation.abs() > threshold)
+        .map(|(measurement, _)| measurement)
+        .collect()
+    }
+}
+
+/// Compares every worker against the mean of all workers, sorted by worker
+pub fn worker_imbalance(data: &DataflowData) -> Vec<WorkerImbalance> {
+    let mut workers: Vec<_> = data
+        .workers
+        .iter()
+        .map(|&worker| WorkerImbalance {
+            worker,
+            busy: data
+                .worker_utilization
+                .iter()
+                .find(|&&(werker, _)| worker == werker)
+                .map_or_else(
+                    || Duration::from_secs(0),
+                    |(_, utilization)| utilization.busy,
+                ),
+            events: data
+                .event_kinds
+                .iter()
+                .filter(|&&((werker, _), _)| worker == werker)
+                .map(|&(_, count)| count)
+                .sum(),
+            records_sent: data
+                .channel_messages
+                .iter()
+                .filter(|&&((werker, _), _)| worker == werker)
+                .map(|(_, messages)| messages.records_sent)
+                .sum(),
+            busy_deviation: 0.0,
+            events_deviation: 0.0,
+            records_deviation: 0.0,
+        })
+        .collect();
+
+    let deviation = |value: f64, mean: f64| {
+        if mean > 0.0 {
+            (value - mean) / mean * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    let count = workers.len().max(1) as f64;
+    let mean_busy = workers
+        .iter()
+        .map(|worker| worker.busy.as_secs_f64())
+        .sum::<f64>()
+        / count;
+    let mean_events = workers
+        .iter()
+        .map(|worker| worker.events as f64)
+        .sum::<f64>()
+        / count;
+    let mean_records = workers
+        .iter()
+        .map(|worker| worker.records_sent as f64)
+        .sum::<f64>()
+        / count;
+
+    for worker in workers.iter_mut() {
+        worker.busy_deviation = deviation(worker.busy.as_secs_f64(), mean_busy);
+        worker.events_deviation = deviation(worker.events as f64, mean_events);
+        worker.records_deviation = deviation(worker.records_sent as f64, mean_records);
+    }
+
+    workers.sort_unstable_by_key(|worker| worker.worker);
+    workers
+}
+
+/// An operator that went over the `--warn-activation-over` or
+/// `--warn-arrangement-over` limit on some worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitWarning {
+    /// The operator's longest activation took longer than the limit
+    Activation {
+        operator: OpKey,
+        max: Duration,
+        limit: Duration,
+    },
+    /// The operator's arrangement held more updates than the limit
+    Arrangement {
+        operator: OpKey,
+        max_size: usize,
+        limit: usize,
+    },
+}
+
+impl LimitWarning {
+    pub const fn operator(&self) -> OpKey {
+        match *self {
+            Self::Activation { operator, .. } | Self::Arrangement { operator, .. } => operator,
+        }
+    }
+}
+
+/// Finds every operator that went over one of the `--warn-*` limits, sorted by operator
+pub fn limit_warnings(args: &Args, data: &DataflowData) -> Vec<LimitWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(limit) = args.warn_activation_over {
+        warnings.extend(data.summarized.iter().filter_map(|&(operator, stats)| {
+            (stats.max > limit).then(|| LimitWarning::Activation {
+                operator,
+                max: stats.max,
+                limit,
+            })
+        }));
+    }
+
+    if let Some(limit) = args.warn_arrangement_over {
+        warnings.extend(data.arrangements.iter().filter_map(|(operator, stats)| {
+            (stats.max_size > limit).then(|| LimitWarning::Arrangement {
+                operator: *operator,
+                max_size: stats.max_size,
+                limit,
+            })
+        }));
+    }
+
+    warnings.sort_by_key(LimitWarning::operator);
+    warnings
+}
+
+/// Attributes the records received over each channel to the operator at the
+/// end of it and sums them up with the operator's activations across workers
+pub fn operator_throughput(
+    edges: &[(OperatesEvent, Channel, OperatesEvent)],
+    channel_messages: &[((WorkerId, ChannelId), ChannelMessageStats)],
+    summarized: &[(OpKey, Summation)],
+) -> HashMap<OperatorId, Throughput, XXHasher> {
+    let targets: HashMap<ChannelId, OperatorId, XXHasher> = edges
+        .iter()
+        .map(|(_, channel, target)| (channel.channel_id(), target.id))
+        .collect();
+
+    let mut throughput: HashMap<OperatorId, Throughput, XXHasher> = HashMap::default();
+    for &((_worker, channel), messages) in channel_messages {
+        if let Some(&operator) = targets.get(&channel) {
+            throughput.entry(operator).or_default().records += messages.records_received;
+        }
+    }
+
+    for &((_worker, operator), ref stats) in summarized {
+        if let Some(throughput) = throughput.get_mut(&operator) {
+            throughput.activations += stats.count;
+            throughput.busy += stats.total;
+        }
+    }
+
+    throughput
+}
+
+/// Finds the operators that were never activated on any worker, sorted by address
+pub fn dead_operators<'a>(
+    nodes: &'a [(OperatorAddr, OperatesEvent)],
+    summarized: &[(OpKey, Summation)],
+) -> Vec<&'a (OperatorAddr, OperatesEvent)> {
+    let activated: HashSet<OperatorId, XXHasher> = summarized
+        .iter()
+        .filter(|(_, stats)| stats.count != 0)
+        .map(|&((_worker, operator), _)| operator)
+        .collect();
+
+    let mut dead: Vec<_> = nodes
+        .iter()
+        .filter(|(_, operator)| !activated.contains(&operator.id))
+        .collect();
+    dead.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+
+    dead
+}
+
+/// Finds the channels that never carried any messages on any worker, sorted by
+/// channel id. Scope crossings are ignored since they don't carry messages of their own
+pub fn dead_channels<'a>(
+    edges: &'a [(OperatesEvent, Channel, OperatesEvent)],
+    channel_messages: &[((WorkerId, ChannelId), ChannelMessageStats)],
+) -> Vec<&'a (OperatesEvent, Channel, OperatesEvent)> {
+    let live: HashSet<ChannelId, XXHasher> = channel_messages
+        .iter()
+        .filter(|(_, messages)| messages.batches_sent != 0 || messages.batches_received != 0)
+        .map(|&((_worker, channel), _)| channel)
+        .collect();
+
+    let mut dead: Vec<_> = edges
+        .iter()
+        .filter(|(_, channel, _)| {
+            matches!(channel, Channel::Normal { .. }) && !live.contains(&channel.channel_id())
+        })
+        .collect();
+    dead.sort_unstable_by_key(|(_, channel, _)| channel.channel_id());
+    dead.dedup_by_key(|(_, channel, _)| channel.channel_id());
+
+    dead
+}
+
+/// Why an operator or its trace might have leaked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LeakKind {
+    /// The operator's dataflow was dropped but the operator never was
+    NeverDropped,
+    /// The operator was dropped after its dataflow was
+    OutlivedDataflow,
+    /// The operator's dataflow was dropped but its arrangement's trace never was
+    TraceNeverDropped,
+}
+
+impl Display for LeakKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NeverDropped => "never dropped",
+            Self::OutlivedDataflow => "outlived its dataflow",
+            Self::TraceNeverDropped => "trace never dropped",
+        })
+    }
+}
+
+/// An operator or trace that outlived the dataflow it was within
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PotentialLeak {
+    pub operator: OpKey,
+    pub kind: LeakKind,
+    /// When the operator's dataflow was dropped
+    pub dataflow_dropped: Duration,
+    /// When the operator or trace was dropped, if it ever was
+    pub dropped: Option<Duration>,
+}
+
+/// Finds the operators and traces that weren't dropped by the time their
+/// dataflows were, sorted by operator. Operators within dataflows that were
+/// still alive when the capture ended aren't leaks since nothing was expected
+/// to have dropped them yet
+pub fn potential_leaks(
+    addrs: &HashMap<OpKey, &OperatorAddr, XXHasher>,
+    lifespans: &HashMap<OpKey, Lifespan, XXHasher>,
+    trace_drops: &HashMap<OpKey, Option<Duration>, XXHasher>,
+) -> Vec<PotentialLeak> {
+    let dataflow_ids: HashMap<(WorkerId, &OperatorAddr), OperatorId, XXHasher> = addrs
+        .iter()
+        .filter(|(_, addr)| addr.is_top_level())
+        .map(|(&(worker, id), &addr)| ((worker, addr), id))
+        .collect();
+
+    let mut leaks = Vec::new();
+    for (&(worker, id), &addr) in addrs.iter().filter(|(_, addr)| !addr.is_top_level()) {
+        let dataflow_addr = OperatorAddr::from(&addr[..1]);
+        let dataflow_dropped = match dataflow_ids
+            .get(&(worker, &dataflow_addr))
+            .and_then(|&dataflow| lifespans.get(&(worker, dataflow)))
+        {
+            Some(lifespan) => lifespan.death,
+            None => continue,
+        };
+
+        let dropped = lifespans.get(&(worker, id)).map(|lifespan| lifespan.death);
+        let kind = match dropped {
+            None => Some(LeakKind::NeverDropped),
+            Some(dropped) if dropped > dataflow_dropped => Some(LeakKind::OutlivedDataflow),
+            Some(_) => None,
+        };
+        if let Some(kind) = kind {
+            leaks.push(PotentialLeak {
+                operator: (worker, id),
+                kind,
+                dataflow_dropped,
+                dropped,
+            });
+        }
+
+        if let Some(None) = trace_drops.get(&(worker, id)) {
+            leaks.push(PotentialLeak {
+                operator: (worker, id),
+                kind: LeakKind::TraceNeverDropped,
+                dataflow_dropped,
+                dropped: None,
+            });
+        }
+    }
+    leaks.sort_unstable();
+
+    leaks
+}
+
+/// Sorts the activation anomalies by how far they exceeded their operator's
+/// median activation time, keeping the worst `limit` of them
+pub fn worst_anomalies(
+    anomalies: &[ActivationAnomaly],
+    limit: usize,
+) -> Vec<(f64, &ActivationAnomaly)> {
+    let mut worst: Vec<_> = anomalies
+        .iter()
+        .map(|anomaly| {
+            let (_, (_, (duration, median))) = *anomaly;
+            let multiple = duration.as_secs_f64() / median.as_secs_f64().max(f64::EPSILON);
+
+            (multiple, anomaly)
+        })
+        .collect();
+
+    worst.sort_unstable_by(|(left, left_anomaly), (right, right_anomaly)| {
+        right
+            .partial_cmp(left)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| left_anomaly.cmp(right_anomaly))
+    });
+    worst.truncate(limit);
+
+    worst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dead_channels, dead_operators, limit_warnings, operator_skew, operator_throughput,
+        potential_leaks, worker_imbalance, worst_anomalies, LeakKind, LimitWarning,
+    };
+    use crate::{
+        args::Args,
+        dataflow::{utils::XXHasher, Channel, DataflowData, Summation},
+        ui::{ChannelMessageStats, Lifespan, LoggedEventKind, WorkerUtilization},
+    };
+    use ddshow_types::{
+        timely_logging::OperatesEvent, ChannelId, OperatorAddr, OperatorId, WorkerId,
+    };
+    use std::{collections::HashMap, time::Duration};
+
+    #[test]
+    fn skew_counts_idle_workers() {
+        let (balanced, hot) = (OperatorId::new(1), OperatorId::new(2));
+        let stats = |millis| {
+            let total = Duration::from_millis(millis);
+            Summation::new(total, total, total, total, 1)
+        };
+        let summarized = vec![
+            ((WorkerId::new(0), balanced), stats(100)),
+            ((WorkerId::new(1), balanced), stats(100)),
+            ((WorkerId::new(0), hot), stats(200)),
+        ];
+
+        let skew = operator_skew(&summarized, 2);
+        assert!((skew[&balanced].score() - 1.0).abs() < f64::EPSILON);
+        assert_eq!(skew[&hot].mean, Duration::from_millis(100));
+        assert!((skew[&hot].score() - 2.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn operators_over_limits_are_warned_about() {
+        let (fast, slow) = (
+            (WorkerId::new(0), OperatorId::new(1)),
+            (WorkerId::new(1), OperatorId::new(1)),
+        );
+        let stats = |max_ms| {
+            let max = Duration::from_millis(max_ms);
+            Summation::new(max, max, max, max, 1)
+        };
+        let data = DataflowData {
+            summarized: vec![(slow, stats(20)), (fast, stats(1))],
+            ..Default::default()
+        };
+
+        let mut args = Args::default();
+        assert!(limit_warnings(&args, &data).is_empty());
+
+        args.warn_activation_over = Some(Duration::from_millis(5));
+        assert_eq!(
+            limit_warnings(&args, &data),
+            vec![LimitWarning::Activation {
+                operator: slow,
+                max: Duration::from_millis(20),
+                limit: Duration::from_millis(5),
+            }],
+        );
+    }
+
+    #[test]
+    fn imbalanced_workers_are_flagged() {
+        let (cold, hot) = (WorkerId::new(0), WorkerId::new(1));
+        let busy = |millis| {
+            let busy = Duration::from_millis(millis);
+            WorkerUtilization::new(
+                busy * 2,
+                busy,
+                Duration::from_secs(0),
+                Duration::from_secs(0),
+            )
+        };
+        let messages = |records_sent| ChannelMessageStats {
+            records_sent,
+            ..Default::default()
+        };
+
+        let data = DataflowData {
+            workers: vec![hot, cold],
+            worker_utilization: vec![(cold, busy(100)), (hot, busy(300))],
+            event_kinds: vec![
+                ((cold, LoggedEventKind::Schedule), 50),
+                ((hot, LoggedEventKind::Schedule), 50),
+            ],
+            channel_messages: vec![
+                ((cold, ChannelId::new(0)), messages(90)),
+                ((hot, ChannelId::new(0)), messages(110)),
+            ],
+            ..Default::default()
+        };
+
+        let imbalance = worker_imbalance(&data);
+        assert_eq!(imbalance[0].worker, cold);
+        assert!((imbalance[0].busy_deviation + 50.0).abs() < 1e-9);
+        assert!((imbalance[1].busy_deviation - 50.0).abs() < 1e-9);
+        assert!(imbalance[1].events_deviation.abs() < 1e-9);
+        assert!((imbalance[1].records_deviation - 10.0).abs() < 1e-9);
+
+        assert_eq!(imbalance[1].imbalanced(25.0), vec!["busy time"]);
+        assert_eq!(
+            imbalance[1].imbalanced(5.0),
+            vec!["busy time", "records sent"],
+        );
+    }
+
+    #[test]
+    fn throughput_follows_input_channels() {
+        let (source, target) = (OperatorId::new(1), OperatorId::new(2));
+        let (source_addr, target_addr) = (
+            OperatorAddr::from(vec![0, 1]),
+            OperatorAddr::from(vec![0, 2]),
+        );
+        let edges = vec![(
+            OperatesEvent::new(source, source_addr.clone(), "Input".to_owned()),
+            Channel::Normal {
+                channel_id: ChannelId::new(7),
+                source_addr,
+                target_addr: target_addr.clone(),
+            },
+            OperatesEvent::new(target, target_addr, "Map".to_owned()),
+        )];
+
+        let received = |records_received| ChannelMessageStats {
+            records_received,
+            ..Default::default()
+        };
+        let messages = vec![
+            ((WorkerId::new(0), ChannelId::new(7)), received(300)),
+            ((WorkerId::new(1), ChannelId::new(7)), received(100)),
+            // Channels that aren't part of the graph are ignored
+            ((WorkerId::new(0), ChannelId::new(8)), received(1000)),
+        ];
+
+        let stats = |millis, count| {
+            let total = Duration::from_millis(millis);
+            Summation::new(total, total, total, total, count)
+        };
+        let summarized = vec![
+            ((WorkerId::new(0), target), stats(150, 3)),
+            ((WorkerId::new(1), target), stats(50, 1)),
+            ((WorkerId::new(0), source), stats(10, 1)),
+        ];
+
+        let throughput = operator_throughput(&edges, &messages, &summarized);
+        assert_eq!(throughput.len(), 1);
+
+        let target = throughput[&target];
+        assert_eq!(target.records, 400);
+        assert_eq!(target.activations, 4);
+        assert_eq!(target.busy, Duration::from_millis(200));
+        assert!((target.records_per_activation() - 100.0).abs() < f64::EPSILON);
+        assert!((target.records_per_second() - 2000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dead_operators_and_channels() {
+        let (input, map, sink) = (OperatorId::new(1), OperatorId::new(2), OperatorId::new(3));
+        let addrs: Vec<_> = (1..=3).map(|id| OperatorAddr::from(vec![0, id])).collect();
+        let operates = |id, index: usize, name: &str| {
+            OperatesEvent::new(id, addrs[index].clone(), name.to_owned())
+        };
+        let nodes = vec![
+            (addrs[0].clone(), operates(input, 0, "Input")),
+            (addrs[1].clone(), operates(map, 1, "Map")),
+            (addrs[2].clone(), operates(sink, 2, "Sink")),
+        ];
+
+        let stats = |count| {
+            let total = Duration::from_millis(10);
+            Summation::new(total, total, total, total, count)
+        };
+        let summarized = vec![
+            ((WorkerId::new(0), input), stats(3)),
+            ((WorkerId::new(1), map), stats(1)),
+        ];
+
+        let dead: Vec<_> = dead_operators(&nodes, &summarized)
+            .into_iter()
+            .map(|(_, operator)| operator.id)
+            .collect();
+        assert_eq!(dead, vec![sink]);
+
+        let channel = |channel_id, source: usize, target: usize| {
+            (
+                operates(OperatorId::new(source + 1), source, ""),
+                Channel::Normal {
+                    channel_id: ChannelId::new(channel_id),
+                    source_addr: addrs[source].clone(),
+                    target_addr: addrs[target].clone(),
+                },
+                operates(OperatorId::new(target + 1), target, ""),
+            )
+        };
+        let edges = vec![channel(4, 0, 1), channel(5, 1, 2)];
+        let messages = vec![(
+            (WorkerId::new(0), ChannelId::new(4)),
+            ChannelMessageStats {
+                records_sent: 10,
+                batches_sent: 1,
+                ..Default::default()
+            },
+        )];
+
+        let dead: Vec<_> = dead_channels(&edges, &messages)
+            .into_iter()
+            .map(|(_, channel, _)| channel.channel_id())
+            .collect();
+        assert_eq!(dead, vec![ChannelId::new(5)]);
+    }
+
+    #[test]
+    fn leaks_outlive_their_dataflows() {
+        let ms = Duration::from_millis;
+        let worker = WorkerId::new(0);
+        let (dataflow, live_dataflow) = (OperatorAddr::from(vec![0]), OperatorAddr::from(vec![1]));
+        let (dropped, never_dropped, late, traced, live) = (
+            OperatorAddr::from(vec![0, 1]),
+            OperatorAddr::from(vec![0, 2]),
+            OperatorAddr::from(vec![0, 3]),
+            OperatorAddr::from(vec![0, 4]),
+            OperatorAddr::from(vec![1, 1]),
+        );
+
+        let mut addrs: HashMap<_, _, XXHasher> = HashMap::default();
+        addrs.insert((worker, OperatorId::new(0)), &dataflow);
+        addrs.insert((worker, OperatorId::new(1)), &dropped);
+        addrs.insert((worker, OperatorId::new(2)), &never_dropped);
+        addrs.insert((worker, OperatorId::new(3)), &late);
+        addrs.insert((worker, OperatorId::new(4)), &traced);
+        addrs.insert((worker, OperatorId::new(5)), &live_dataflow);
+        addrs.insert((worker, OperatorId::new(6)), &live);
+
+        let mut lifespans: HashMap<_, _, XXHasher> = HashMap::default();
+        lifespans.insert((worker, OperatorId::new(0)), Lifespan::new(ms(0), ms(10)));
+        lifespans.insert((worker, OperatorId::new(1)), Lifespan::new(ms(0), ms(9)));
+        lifespans.insert((worker, OperatorId::new(3)), Lifespan::new(ms(0), ms(20)));
+        lifespans.insert((worker, OperatorId::new(4)), Lifespan::new(ms(0), ms(10)));
+
+        let mut trace_drops: HashMap<_, _, XXHasher> = HashMap::default();
+        trace_drops.insert((worker, OperatorId::new(4)), None);
+        trace_drops.insert((worker, OperatorId::new(6)), None);
+
+        let leaks: Vec<_> = potential_leaks(&addrs, &lifespans, &trace_drops)
+            .into_iter()
+            .map(|leak| (leak.operator.1, leak.kind))
+            .collect();
+        assert_eq!(
+            leaks,
+            vec![
+                (OperatorId::new(2), LeakKind::NeverDropped),
+                (OperatorId::new(3), LeakKind::OutlivedDataflow),
+                (OperatorId::new(4), LeakKind::TraceNeverDropped),
+            ],
+        );
+    }
+
+    #[test]
+    fn anomalies_are_ranked_by_multiple() {
+        let ms = Duration::from_millis;
+        let operator = (WorkerId::new(0), OperatorId::new(1));
+        let anomalies = vec![
+            (operator, (ms(0), (ms(20), ms(1)))),
+            (operator, (ms(5), (ms(50), ms(1)))),
+            (operator, (ms(9), (ms(30), ms(2)))),
+        ];
+
+        let worst: Vec<_> = worst_anomalies(&anomalies, 2)
+            .into_iter()
+            .map(|(multiple, &(_, (start, _)))| (multiple.round() as u64, start))
+            .collect();
+        assert_eq!(worst, vec![(50, ms(5)), (20, ms(0))]);
+    }
+}
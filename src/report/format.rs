@@ -0,0 +1,777 @@
+//! The format-independent report model along with its renderers
+//!
+//! Every section of the report is collected into a [`Report`] before being
+//! written out as text, markdown, json or html so that each format carries
+//! the exact same contents. Sections can be grouped under a parent section,
+//! which is how each dataflow gets its own set of tables
+
+use crate::args::ReportFormat;
+use comfy_table::{
+    presets::UTF8_FULL, CellAlignment, ColumnConstraint, Table as InnerTable, Width,
+};
+use ddshow_types::{ChannelId, OperatorId, WorkerId};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Write},
+    io,
+    time::Duration,
+};
+
+/// A complete report, made up of titled sections
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Report {
+    sections: Vec<Section>,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a section holding a table
+    pub fn table<T>(&mut self, title: T, table: Table) -> &mut Self
+    where
+        T: Into<String>,
+    {
+        self.sections.push(Section {
+            title: title.into(),
+            description: None,
+            table: Some(table),
+            text: None,
+            sections: Vec::new(),
+        });
+
+        self
+    }
+
+    /// Adds a section holding a table that's introduced by a short description
+    pub fn described_table<T, D>(&mut self, title: T, description: D, table: Table) -> &mut Self
+    where
+        T: Into<String>,
+        D: Into<String>,
+    {
+        self.sections.push(Section {
+            title: title.into(),
+            description: Some(description.into()),
+            table: Some(table),
+            text: None,
+            sections: Vec::new(),
+        });
+
+        self
+    }
+
+    /// Adds a section holding preformatted text
+    pub fn text<T, B>(&mut self, title: T, text: B) -> &mut Self
+    where
+        T: Into<String>,
+        B: Into<String>,
+    {
+        self.sections.push(Section {
+            title: title.into(),
+            description: None,
+            table: None,
+            text: Some(text.into()),
+            sections: Vec::new(),
+        });
+
+        self
+    }
+
+    /// Adds a section that groups every section of `group` beneath it
+    pub fn group<T>(&mut self, title: T, group: Report) -> &mut Self
+    where
+        T: Into<String>,
+    {
+        self.sections.push(Section {
+            title: title.into(),
+            description: None,
+            table: None,
+            text: None,
+            sections: group.sections,
+        });
+
+        self
+    }
+
+    /// Writes the report out in the given format
+    pub fn write<W>(&self, format: ReportFormat, mut writer: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let rendered = match format {
+            ReportFormat::Text => self.render_text(),
+            ReportFormat::Markdown => self.render_markdown(),
+            ReportFormat::Html => self.render_html(),
+            ReportFormat::Json => {
+                return serde_json::to_writer_pretty(writer, self).map_err(Into::into);
+            }
+        }
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to render report"))?;
+
+        writer.write_all(rendered.as_bytes())
+    }
+
+    fn render_text(&self) -> Result<String, fmt::Error> {
+        let mut out = String::new();
+        for section in self.sections.iter() {
+            section.render_text(&mut out)?;
+        }
+
+        Ok(out)
+    }
+
+    /// Renders github flavored markdown, which starts with a list of links to
+    /// each top-level section so that long reports stay navigable in issues
+    /// and wikis
+    fn render_markdown(&self) -> Result<String, fmt::Error> {
+        let (mut contents, mut body) = (String::new(), String::new());
+        let mut anchors = HashMap::new();
+
+        for section in self.sections.iter() {
+            let anchor = section.render_markdown(&mut body, 2, &mut anchors)?;
+            writeln!(contents, "- [{}](#{})", section.title, anchor)?;
+        }
+
+        Ok(format!("# ddshow Report\n\n{}\n{}", contents, body))
+    }
+
+    fn render_html(&self) -> Result<String, fmt::Error> {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>ddshow report</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; margin-bottom: 2em; }\n\
+             th, td { border: 1px solid #999; padding: 0.25em 0.5em; text-align: left; }\n\
+             th { background: #eee; }\n\
+             td.number { text-align: right; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+
+        for section in self.sections.iter() {
+            section.render_html(&mut out, 2)?;
+        }
+
+        out.push_str("</body>\n</html>\n");
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Section {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    table: Option<Table>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sections: Vec<Section>,
+}
+
+impl Section {
+    /// Groups are underlined so that the sections beneath them stand out
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{}", self.title)?;
+        if !self.sections.is_empty() {
+            writeln!(out, "{}\n", "=".repeat(self.title.chars().count()))?;
+        }
+        if let Some(description) = self.description.as_ref() {
+            writeln!(out, "{}", description)?;
+        }
+
+        if let Some(table) = self.table.as_ref() {
+            writeln!(out, "{}\n", table.to_comfy())?;
+        }
+        if let Some(text) = self.text.as_ref() {
+            writeln!(out, "{}", text)?;
+        }
+
+        for section in self.sections.iter() {
+            section.render_text(out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the anchor that github gives the section's heading
+    fn render_markdown(
+        &self,
+        out: &mut String,
+        level: usize,
+        anchors: &mut HashMap<String, usize>,
+    ) -> Result<String, fmt::Error> {
+        let escape = |cell: &str| cell.replace('|', "\\|").replace('\n', "<br>");
+
+        writeln!(out, "{} {}\n", "#".repeat(level.min(6)), self.title)?;
+        let anchor = heading_anchor(&self.title, anchors);
+
+        if let Some(description) = self.description.as_ref() {
+            writeln!(out, "{}\n", description)?;
+        }
+
+        if let Some(table) = self.table.as_ref() {
+            let collapsed = table.rows.len() > COLLAPSED_MARKDOWN_ROWS;
+            if collapsed {
+                writeln!(
+                    out,
+                    "<details>\n<summary>{} rows</summary>\n",
+                    table.rows.len(),
+                )?;
+            }
+
+            let header: Vec<_> = table.header.iter().map(|cell| escape(cell)).collect();
+            writeln!(out, "| {} |", header.join(" | "))?;
+
+            out.push('|');
+            for column in 0..table.header.len() {
+                out.push_str(if table.is_right_aligned(column) {
+                    " ---: |"
+                } else {
+                    " --- |"
+                });
+            }
+            out.push('\n');
+
+            for row in table.rows.iter() {
+                let row: Vec<_> = row.iter().map(|cell| escape(&cell.to_string())).collect();
+                writeln!(out, "| {} |", row.join(" | "))?;
+            }
+            writeln!(out)?;
+
+            if collapsed {
+                writeln!(out, "</details>\n")?;
+            }
+        }
+
+        if let Some(text) = self.text.as_ref() {
+            let lines = text.lines().count();
+            if lines > COLLAPSED_MARKDOWN_ROWS {
+                writeln!(
+                    out,
+                    "<details>\n<summary>{} lines</summary>\n\n```\n{}```\n\n</details>\n",
+                    lines, text,
+                )?;
+            } else {
+                writeln!(out, "```\n{}```\n", text)?;
+            }
+        }
+
+        for section in self.sections.iter() {
+            section.render_markdown(out, level + 1, anchors)?;
+        }
+
+        Ok(anchor)
+    }
+
+    fn render_html(&self, out: &mut String, level: usize) -> fmt::Result {
+        let level = level.min(6);
+
+        writeln!(out, "<h{0}>{1}</h{0}>", level, escape_html(&self.title))?;
+        if let Some(description) = self.description.as_ref() {
+            writeln!(out, "<p>{}</p>", escape_html(description))?;
+        }
+
+        if let Some(table) = self.table.as_ref() {
+            out.push_str("<table>\n<tr>");
+            for header in table.header.iter() {
+                write!(out, "<th>{}</th>", escape_html(header))?;
+            }
+            out.push_str("</tr>\n");
+
+            for row in table.rows.iter() {
+                out.push_str("<tr>");
+                for (column, cell) in row.iter().enumerate() {
+                    let cell = escape_html(&cell.to_string());
+                    if table.is_right_aligned(column) {
+                        write!(out, "<td class=\"number\">{}</td>", cell)?;
+                    } else {
+                        write!(out, "<td>{}</td>", cell)?;
+                    }
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+
+        if let Some(text) = self.text.as_ref() {
+            writeln!(out, "<pre>{}</pre>", escape_html(text))?;
+        }
+
+        for section in self.sections.iter() {
+            section.render_html(out, level + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A table of typed cells, which are only formatted once it's rendered
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+    /// Columns are right-aligned when every one of their cells is numeric
+    right_aligned: Vec<bool>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_header(&mut self, row: &[&str]) -> &mut Self {
+        self.header = row.iter().map(|&header| header.to_owned()).collect();
+        self
+    }
+
+    pub fn add_row<T>(&mut self, row: T) -> &mut Self
+    where
+        T: IntoIterator<Item = Cell>,
+    {
+        let is_first = self.rows.is_empty();
+        let mut cells = Vec::with_capacity(self.header.len());
+
+        for (column, cell) in row.into_iter().enumerate() {
+            if is_first {
+                self.right_aligned.push(cell.is_numeric());
+            } else if let Some(aligned) = self.right_aligned.get_mut(column) {
+                *aligned &= cell.is_numeric();
+            }
+
+            cells.push(cell);
+        }
+
+        self.rows.push(cells);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn is_right_aligned(&self, column: usize) -> bool {
+        self.right_aligned.get(column).copied().unwrap_or(false)
+    }
+
+    fn to_comfy(&self) -> InnerTable {
+        let mut table = InnerTable::new();
+        table
+            .load_preset(UTF8_FULL)
+            .set_constraints(
+                self.header.iter().map(|header| {
+                    ColumnConstraint::LowerBoundary(Width::Fixed(header.len() as u16))
+                }),
+            )
+            .set_header(&self.header);
+
+        for row in self.rows.iter() {
+            table.add_row(row.iter().map(ToString::to_string).collect::<Vec<_>>());
+        }
+
+        for column in 0..self.header.len() {
+            if self.is_right_aligned(column) {
+                if let Some(column) = table.get_column_mut(column) {
+                    column.set_cell_alignment(CellAlignment::Right);
+                }
+            }
+        }
+
+        table
+    }
+}
+
+/// Json reports carry both the formatted rows that the other formats show
+/// and the typed values behind them, so that scripts can consume the numbers
+/// without parsing them back out of text
+impl Serialize for Table {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let rows: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(ToString::to_string).collect())
+            .collect();
+
+        let mut table = serializer.serialize_struct("Table", 3)?;
+        table.serialize_field("header", &self.header)?;
+        table.serialize_field("rows", &rows)?;
+        table.serialize_field("values", &self.rows)?;
+        table.end()
+    }
+}
+
+/// A single table cell
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Cell {
+    value: Value,
+}
+
+impl Cell {
+    pub fn new<T>(content: T) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            value: Value::Text(content.to_string()),
+        }
+    }
+
+    /// A number, which is right-aligned within its column
+    pub fn number<T>(number: T) -> Self
+    where
+        T: Into<Value>,
+    {
+        Self {
+            value: number.into(),
+        }
+    }
+
+    /// A duration given in the largest unit that keeps it above one
+    pub fn duration(duration: Duration) -> Self {
+        Self {
+            value: Value::from(duration),
+        }
+    }
+
+    /// A relative change given as a fraction, shown as a signed percentage
+    /// or `n/a` when there's nothing to compare against
+    pub fn change(change: Option<f64>) -> Self {
+        Self {
+            value: change.map_or(Value::Missing, Value::Change),
+        }
+    }
+
+    /// How many times larger one value is than another
+    pub fn multiple(multiple: f64) -> Self {
+        Self {
+            value: Value::Multiple(multiple),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(self.value, Value::Text(_))
+    }
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.value, f)
+    }
+}
+
+/// The typed value held by a table cell
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Value {
+    Text(String),
+    Integer(i64),
+    /// Shown with two decimal places
+    Number(f64),
+    Nanos(u64),
+    /// A relative change given as a fraction
+    Change(f64),
+    Multiple(f64),
+    /// A value that's missing from one side of a comparison
+    Missing,
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Text(ref text) => f.write_str(text),
+            Self::Integer(integer) => write!(f, "{}", integer),
+            Self::Number(number) => write!(f, "{:.2}", number),
+            Self::Nanos(nanos) => f.write_str(&format_duration(Duration::from_nanos(nanos))),
+            Self::Change(change) => write!(f, "{:+.1}%", change * 100.0),
+            Self::Multiple(multiple) => write!(f, "{:.2}x", multiple),
+            Self::Missing => f.write_str("n/a"),
+        }
+    }
+}
+
+macro_rules! integer_values {
+    ($($integer:ty),* $(,)?) => {
+        $(
+            impl From<$integer> for Value {
+                fn from(integer: $integer) -> Self {
+                    Self::Integer(integer as i64)
+                }
+            }
+        )*
+    };
+}
+
+integer_values!(u8, u16, u32, u64, usize, i32);
+
+impl From<f64> for Value {
+    fn from(number: f64) -> Self {
+        Self::Number(number)
+    }
+}
+
+impl From<Duration> for Value {
+    fn from(duration: Duration) -> Self {
+        Self::Nanos(duration.as_nanos() as u64)
+    }
+}
+
+impl From<WorkerId> for Value {
+    fn from(worker: WorkerId) -> Self {
+        Self::from(worker.into_inner())
+    }
+}
+
+impl From<OperatorId> for Value {
+    fn from(operator: OperatorId) -> Self {
+        Self::from(operator.into_inner())
+    }
+}
+
+impl From<ChannelId> for Value {
+    fn from(channel: ChannelId) -> Self {
+        Self::from(channel.into_inner())
+    }
+}
+
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Missing, Into::into)
+    }
+}
+
+/// Formats durations with a fixed precision so that they line up within columns
+pub fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+
+    if nanos >= 1_000_000_000 {
+        format!("{:.3} s", duration.as_secs_f64())
+    } else if nanos >= 1_000_000 {
+        format!("{:.3} ms", nanos as f64 / 1_000_000.0)
+    } else if nanos >= 1_000 {
+        format!("{:.3} µs", nanos as f64 / 1_000.0)
+    } else {
+        format!("{} ns", nanos)
+    }
+}
+
+/// Tables and text longer than this are folded away within markdown reports
+/// so that they don't bury the rest of the report
+const COLLAPSED_MARKDOWN_ROWS: usize = 25;
+
+/// Builds the anchor that github links a heading to, headings are lowercased
+/// with spaces turned into dashes and other punctuation removed. Repeated
+/// headings have their occurrence appended to keep them unique
+fn heading_anchor(title: &str, anchors: &mut HashMap<String, usize>) -> String {
+    let mut anchor: String = title
+        .trim()
+        .chars()
+        .filter_map(|char| match char {
+            ' ' => Some('-'),
+            '-' | '_' => Some(char),
+            char if char.is_alphanumeric() => Some(char),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    let occurrences = anchors.entry(anchor.clone()).or_insert(0);
+    if *occurrences != 0 {
+        write!(anchor, "-{}", occurrences).expect("writing to a string is infallible");
+    }
+    *occurrences += 1;
+
+    anchor
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{heading_anchor, Cell, Report, Table, COLLAPSED_MARKDOWN_ROWS};
+    use crate::args::ReportFormat;
+    use serde_json::json;
+    use std::{collections::HashMap, time::Duration};
+
+    fn report() -> Report {
+        let mut table = Table::new();
+        table
+            .set_header(&["Name", "Runtime"])
+            .add_row(vec![Cell::new("Map | Filter"), Cell::new("<1ms>")]);
+
+        let mut report = Report::new();
+        report.table("Operators", table);
+        report
+    }
+
+    fn render(report: &Report, format: ReportFormat) -> String {
+        let mut out = Vec::new();
+        report.write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats_share_contents() {
+        let report = report();
+
+        let markdown = render(&report, ReportFormat::Markdown);
+        assert!(markdown.contains("## Operators"));
+        assert!(markdown.contains("| Map \\| Filter | <1ms> |"));
+
+        let html = render(&report, ReportFormat::Html);
+        assert!(html.contains("<td>&lt;1ms&gt;</td>"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&report, ReportFormat::Json)).unwrap();
+        assert_eq!(json["sections"][0]["table"]["rows"][0][0], "Map | Filter");
+
+        assert!(render(&report, ReportFormat::Text).starts_with("Operators\n"));
+    }
+
+    #[test]
+    fn nested_sections_align_numbers() {
+        let mut table = Table::new();
+        table
+            .set_header(&["Name", "Runtime", "Activations"])
+            .add_row(vec![
+                Cell::new("Map"),
+                Cell::duration(Duration::from_micros(1500)),
+                Cell::number(12),
+            ])
+            .add_row(vec![
+                Cell::new("Filter"),
+                Cell::duration(Duration::from_nanos(20)),
+                Cell::new("unknown"),
+            ]);
+
+        let mut dataflow = Report::new();
+        dataflow.table("Operators", table);
+
+        let mut report = Report::new();
+        report.group("Dataflow Foo [0]", dataflow);
+
+        let markdown = render(&report, ReportFormat::Markdown);
+        assert!(markdown.contains("- [Dataflow Foo [0]](#dataflow-foo-0)\n"));
+        assert!(markdown.contains("## Dataflow Foo [0]\n\n### Operators"));
+        assert!(markdown.contains("| --- | ---: | --- |"));
+        assert!(markdown.contains("| Map | 1.500 ms | 12 |"));
+
+        let html = render(&report, ReportFormat::Html);
+        assert!(html.contains("<h3>Operators</h3>"));
+        assert!(html.contains("<td class=\"number\">20 ns</td>"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&report, ReportFormat::Json)).unwrap();
+        assert_eq!(
+            json["sections"][0]["sections"][0]["table"]["rows"][1][1],
+            "20 ns",
+        );
+
+        let text = render(&report, ReportFormat::Text);
+        assert!(text.starts_with("Dataflow Foo [0]\n================\n\nOperators\n"));
+    }
+
+    #[test]
+    fn json_carries_typed_values() {
+        let mut table = Table::new();
+        table
+            .set_header(&["Name", "Runtime", "Activations", "Change", "Amplification"])
+            .add_row(vec![
+                Cell::new("Map"),
+                Cell::duration(Duration::from_micros(1500)),
+                Cell::number(12),
+                Cell::change(Some(0.25)),
+                Cell::multiple(1.5),
+            ])
+            .add_row(vec![
+                Cell::new("Filter"),
+                Cell::number(None::<Duration>),
+                Cell::number(0.5),
+                Cell::change(None),
+                Cell::multiple(0.0),
+            ]);
+
+        let mut report = Report::new();
+        report.table("Operators", table);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&report, ReportFormat::Json)).unwrap();
+        let table = &json["sections"][0]["table"];
+        assert_eq!(
+            table["rows"],
+            json!([
+                ["Map", "1.500 ms", "12", "+25.0%", "1.50x"],
+                ["Filter", "n/a", "0.50", "n/a", "0.00x"],
+            ]),
+        );
+        assert_eq!(
+            table["values"],
+            json!([
+                [
+                    { "text": "Map" },
+                    { "nanos": 1_500_000 },
+                    { "integer": 12 },
+                    { "change": 0.25 },
+                    { "multiple": 1.5 },
+                ],
+                [
+                    { "text": "Filter" },
+                    "missing",
+                    { "number": 0.5 },
+                    "missing",
+                    { "multiple": 0.0 },
+                ],
+            ]),
+        );
+    }
+
+    #[test]
+    fn markdown_anchors_and_folding() {
+        let mut anchors = HashMap::new();
+        assert_eq!(heading_anchor("Operators", &mut anchors), "operators");
+        assert_eq!(heading_anchor("Operators", &mut anchors), "operators-1");
+        assert_eq!(
+            heading_anchor("Dataflow Join (Foo) [1]", &mut anchors),
+            "dataflow-join-foo-1",
+        );
+
+        let mut table = Table::new();
+        table.set_header(&["Id"]);
+        for id in 0..=COLLAPSED_MARKDOWN_ROWS {
+            table.add_row(vec![Cell::number(id)]);
+        }
+
+        let mut report = Report::new();
+        report.table("Channels", table);
+
+        let markdown = render(&report, ReportFormat::Markdown);
+        assert!(markdown.starts_with("# ddshow Report\n\n- [Channels](#channels)\n"));
+        assert!(markdown.contains(&format!(
+            "<details>\n<summary>{} rows</summary>\n\n| Id |",
+            COLLAPSED_MARKDOWN_ROWS + 1,
+        )));
+        assert!(markdown.trim_end().ends_with("</details>"));
+    }
+}
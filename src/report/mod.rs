@@ -1,25 +1,35 @@
+pub mod analysis;
+mod format;
 mod tree;
 
 use crate::{
-    args::Args,
+    args::{Args, DiffArgs, ReportFormat},
     dataflow::{
         utils::{OpKey, XXHasher},
-        ArrangementStats, DataflowData, Summation,
+        ActivationPercentiles, ArrangementStats, Channel, DataflowData, ProgressChannelStats,
+        Summation,
     },
-    report::tree::Tree,
+    diff::{self, OperatorChange},
+    operator_groups::GroupStats,
+    report::{
+        analysis::{self, LimitWarning},
+        format::{Cell, Report, Table},
+        tree::Tree,
+    },
+    ui::{self, ChannelMessageStats, RunStats},
 };
 use anyhow::{Context, Result};
-use comfy_table::{presets::UTF8_FULL, Cell, ColumnConstraint, Row, Table as InnerTable, Width};
-use ddshow_types::{OperatorAddr, OperatorId};
+use ddshow_types::{ChannelId, OperatorAddr, OperatorId, WorkerId};
 use std::{
-    cmp::Reverse,
-    collections::HashMap,
-    fmt::{self, Display},
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, HashMap},
     fs::{self, File},
-    io::Write,
+    io::{BufWriter, Write},
+    path::Path,
     time::Duration,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_report(
     args: &Args,
     data: &DataflowData,
@@ -27,65 +37,84 @@ pub fn build_report(
     addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
     agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
     agg_arrangement_stats: &HashMap<OperatorId, &ArrangementStats, XXHasher>,
+    group_stats: &[GroupStats],
+    target_runs: &[RunStats],
+    dropped_events: Option<usize>,
+    progress_channels: &[ProgressChannelStats],
+    baseline: Option<&DataflowData>,
 ) -> Result<()> {
     if !args.no_report_file {
-        // Attempt to create the path up to the report file
-        if let Some(parent) = args
-            .report_file
-            .canonicalize()
-            .ok()
-            .and_then(|path| path.parent().map(ToOwned::to_owned))
-        {
-            tracing::debug!(
-                "creating parent directory for the report file: {}",
-                parent.display(),
-            );
+        let mut report = Report::new();
 
-            if let Err(err) = fs::create_dir_all(&parent) {
-                tracing::error!(
-                    parent = %parent.display(),
-                    "failed to create parent path for report file: {:?}",
-                    err,
-                );
-            }
-        }
+        // Operators are matched up with the `--baseline` run by their address
+        let baseline_changes: Option<HashMap<&OperatorAddr, OperatorChange, XXHasher>> = baseline
+            .map(|baseline| {
+                diff::compare(baseline, data)
+                    .into_iter()
+                    .map(|change| (change.addr, change))
+                    .collect()
+            });
 
-        // Create the report file
-        tracing::debug!("creating report file: {}", args.report_file.display());
-        let mut file = File::create(&args.report_file).context("failed to create report file")?;
+        program_overview(
+            args,
+            data,
+            dropped_events,
+            baseline_changes.as_ref(),
+            &mut report,
+        )?;
+        limit_warnings(args, &mut report, data, name_lookup)?;
+        top_offenders(args, &mut report, data, name_lookup, addr_lookup)?;
 
-        program_overview(args, data, &mut file)?;
-        worker_stats(args, data, &mut file)?;
-        operator_stats(
+        if target_runs.len() > 1 {
+            run_stats(&mut report, target_runs)?;
+        }
+        worker_stats(args, data, &mut report)?;
+        worker_imbalance(args, data, &mut report)?;
+        dataflow_rollups(&mut report, data, name_lookup)?;
+        dataflow_sections(
             args,
             data,
-            &mut file,
+            &mut report,
             name_lookup,
             addr_lookup,
             agg_operator_stats,
             agg_arrangement_stats,
+            baseline_changes.as_ref(),
         )?;
+        slowest_operators(&mut report, data, name_lookup, addr_lookup)?;
+        throughput_stats(&mut report, data, name_lookup, addr_lookup)?;
+        skew_stats(&mut report, data, name_lookup, addr_lookup)?;
+        scheduling_latency_stats(&mut report, data, name_lookup)?;
+        activation_anomalies(args, &mut report, data, name_lookup)?;
+
+        dead_operators(&mut report, data)?;
+        potential_leaks(&mut report, data, name_lookup, addr_lookup)?;
+
+        if !group_stats.is_empty() {
+            operator_group_stats(args, &mut report, group_stats)?;
+        }
 
         if args.differential_enabled {
-            arrangement_stats(
-                &mut file,
-                data,
-                name_lookup,
-                addr_lookup,
-                agg_operator_stats,
-                agg_arrangement_stats,
-            )?;
+            arrangement_details(&mut report, data, name_lookup)?;
         } else {
             tracing::debug!("differential logging is disabled, skipping arrangement stats table");
         }
 
+        if args.progress_enabled {
+            progress_stats(&mut report, data, progress_channels)?;
+        } else {
+            tracing::debug!("progress logging is disabled, skipping progress tables");
+        }
+
         operator_tree(
-            &mut file,
+            &mut report,
             data,
             name_lookup,
             addr_lookup,
             agg_operator_stats,
         )?;
+
+        write_report(args, &report)?;
     } else {
         tracing::debug!("report files are disabled, skipping generation");
     }
@@ -93,38 +122,159 @@ pub fn build_report(
     Ok(())
 }
 
-fn program_overview(args: &Args, data: &DataflowData, file: &mut File) -> Result<()> {
+/// Writes the comparison between two runs made by `ddshow diff`
+pub fn build_diff_report(args: &Args, diff: &DiffArgs, changes: &[OperatorChange]) -> Result<()> {
+    if args.no_report_file {
+        tracing::debug!("report files are disabled, skipping generation");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Address",
+        "Old Runtime",
+        "New Runtime",
+        "Runtime Change",
+        "Old Activations",
+        "New Activations",
+        "Activations Change",
+    ]);
+
+    let runtime = |stats: Option<Summation>| Cell::number(stats.map(|stats| stats.total));
+    let activations = |stats: Option<Summation>| Cell::number(stats.map(|stats| stats.count));
+
+    for change in changes {
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(change.name),
+            Cell::new(change.addr),
+            runtime(change.old),
+            runtime(change.new),
+            Cell::change(change.runtime_change()),
+            activations(change.old),
+            activations(change.new),
+            Cell::change(change.activation_change()),
+        ]));
+    }
+
+    let mut report = Report::new();
+    report.described_table(
+        "Operator Changes",
+        format!(
+            "Comparing {} against the baseline {}",
+            diff.new.display(),
+            diff.old.display(),
+        ),
+        table,
+    );
+
+    write_report(args, &report)
+}
+
+/// Writes the finished report to the report file in the selected format
+/// along with the `--report-json` copy of it
+fn write_report(args: &Args, report: &Report) -> Result<()> {
+    write_report_file(&args.report_file, args.report_format, report)?;
+
+    if let Some(report_json) = args.report_json.as_deref() {
+        write_report_file(report_json, ReportFormat::Json, report)?;
+    }
+
+    Ok(())
+}
+
+fn write_report_file(path: &Path, format: ReportFormat, report: &Report) -> Result<()> {
+    let mut file = BufWriter::new(create_report_file(path)?);
+    report
+        .write(format, &mut file)
+        .and_then(|()| file.flush())
+        .with_context(|| anyhow::anyhow!("failed to write to report file '{}'", path.display()))
+}
+
+fn create_report_file(path: &Path) -> Result<File> {
+    // Attempt to create the path up to the report file
+    if let Some(parent) = path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.parent().map(ToOwned::to_owned))
+    {
+        tracing::debug!(
+            "creating parent directory for the report file: {}",
+            parent.display(),
+        );
+
+        if let Err(err) = fs::create_dir_all(&parent) {
+            tracing::error!(
+                parent = %parent.display(),
+                "failed to create parent path for report file: {:?}",
+                err,
+            );
+        }
+    }
+
+    // Create the report file
+    tracing::debug!("creating report file: {}", path.display());
+    File::create(path)
+        .with_context(|| anyhow::anyhow!("failed to create report file '{}'", path.display()))
+}
+
+fn program_overview(
+    args: &Args,
+    data: &DataflowData,
+    dropped_events: Option<usize>,
+    baseline_changes: Option<&HashMap<&OperatorAddr, OperatorChange, XXHasher>>,
+    report: &mut Report,
+) -> Result<()> {
     tracing::debug!("generating program overview table");
 
     let mut table = Table::new();
 
     table
-        .set_header(&["Program Overview", ""])
+        .set_header(&["Metric", "Value"])
         .add_row(IntoIterator::into_iter([
             Cell::new("Workers"),
-            Cell::new(data.workers.len()),
+            Cell::number(data.workers.len()),
         ]))
         .add_row(IntoIterator::into_iter([
             Cell::new("Dataflows"),
-            Cell::new(data.dataflows.len()),
+            Cell::number(data.dataflows.len()),
         ]))
         .add_row(IntoIterator::into_iter([
             Cell::new("Operators"),
-            Cell::new(data.operators.len()),
+            Cell::number(data.operators.len()),
         ]))
         .add_row(IntoIterator::into_iter([
             Cell::new("Subgraphs"),
-            Cell::new(data.subgraphs.len()),
+            Cell::number(data.subgraphs.len()),
         ]))
         .add_row(IntoIterator::into_iter([
             Cell::new("Channels"),
-            Cell::new(data.channels.len()),
+            Cell::number(data.channels.len()),
+        ]));
+
+    let messages = channel_messages(data, |_| true);
+    table
+        .add_row(IntoIterator::into_iter([
+            Cell::new("Records Sent"),
+            Cell::number(messages.records_sent),
+        ]))
+        .add_row(IntoIterator::into_iter([
+            Cell::new("Records Received"),
+            Cell::number(messages.records_received),
+        ]))
+        .add_row(IntoIterator::into_iter([
+            Cell::new("Batches Sent"),
+            Cell::number(messages.batches_sent),
+        ]))
+        .add_row(IntoIterator::into_iter([
+            Cell::new("Batches Received"),
+            Cell::number(messages.batches_received),
         ]));
 
     if args.differential_enabled {
         table.add_row(IntoIterator::into_iter([
             Cell::new("Arrangements"),
-            Cell::new(data.arrangement_ids.len()),
+            Cell::number(data.arrangement_ids.len()),
         ]));
     }
 
@@ -141,15 +291,65 @@ fn program_overview(args: &Args, data: &DataflowData, file: &mut File) -> Result
 
     table.add_row(IntoIterator::into_iter([
         Cell::new("Total Runtime"),
-        Cell::new(format!("{:#?}", total_runtime)),
+        Cell::duration(total_runtime),
     ]));
 
-    writeln!(file, "{}\n", table).context("failed to write to report file")?;
+    if let Some(dropped_events) = dropped_events {
+        table.add_row(IntoIterator::into_iter([
+            Cell::new("Dropped Events"),
+            Cell::number(dropped_events),
+        ]));
+    }
+
+    if let (Some(baseline), Some(changes)) = (args.baseline.as_deref(), baseline_changes) {
+        let threshold = args.regression_threshold / 100.0;
+        let regressions = changes
+            .values()
+            .filter(|change| change.is_regression(threshold))
+            .count();
+
+        table
+            .add_row(IntoIterator::into_iter([
+                Cell::new("Baseline"),
+                Cell::new(baseline.display()),
+            ]))
+            .add_row(IntoIterator::into_iter([
+                Cell::new(format!("Regressions Over {}%", args.regression_threshold,)),
+                Cell::number(regressions),
+            ]));
+    }
+
+    report.table("Program Overview", table);
+
+    Ok(())
+}
+
+fn run_stats(report: &mut Report, target_runs: &[RunStats]) -> Result<()> {
+    tracing::debug!("generating target run table");
+
+    let mut table = Table::new();
+    table.set_header(&["Run", "Started At", "Ended At", "Runtime", "Connections"]);
+
+    for run in target_runs {
+        table.add_row(IntoIterator::into_iter([
+            Cell::number(run.run),
+            Cell::duration(run.started_at),
+            Cell::duration(run.ended_at),
+            Cell::duration(
+                run.ended_at
+                    .checked_sub(run.started_at)
+                    .unwrap_or_else(|| Duration::from_secs(0)),
+            ),
+            Cell::number(run.connections),
+        ]));
+    }
+
+    report.table("Target Runs", table);
 
     Ok(())
 }
 
-fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()> {
+fn worker_stats(args: &Args, data: &DataflowData, report: &mut Report) -> Result<()> {
     tracing::debug!("generating worker stats table");
 
     let mut table = Table::new();
@@ -158,19 +358,36 @@ fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()>
     if args.differential_enabled {
         headers.push("Arrangements");
     }
-    headers.extend(["Runtime"].iter());
+    headers.extend(
+        [
+            "Runtime",
+            "Busy",
+            "Busy %",
+            "Idle",
+            "Idle %",
+            "Parked",
+            "Parked %",
+            "Progress Overhead",
+            "Progress Overhead %",
+            "Records Sent",
+            "Records Received",
+            "Batches Sent",
+            "Batches Received",
+        ]
+        .iter(),
+    );
 
     table.set_header(&headers);
 
     for &worker in data.workers.iter() {
-        let mut row = Vec::with_capacity(8);
+        let mut row = Vec::with_capacity(19);
 
         row.extend(IntoIterator::into_iter([
             Cell::new(format!("Worker {}", worker.into_inner())),
-            Cell::new(data.dataflows.len()),
-            Cell::new(data.operators.len()),
-            Cell::new(data.subgraphs.len()),
-            Cell::new(data.channels.len()),
+            Cell::number(data.dataflows.len()),
+            Cell::number(data.operators.len()),
+            Cell::number(data.subgraphs.len()),
+            Cell::number(data.channels.len()),
         ]));
 
         if args.differential_enabled {
@@ -180,7 +397,7 @@ fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()>
                 .filter(|&&(werker, _)| worker == werker)
                 .count();
 
-            row.push(Cell::new(arrangements));
+            row.push(Cell::number(arrangements));
         }
 
         let total_runtime = data
@@ -196,283 +413,1507 @@ fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()>
             .checked_div(data.total_runtime.len() as u32)
             .unwrap_or_else(|| Duration::from_secs(0));
 
-        row.push(Cell::new(format!("{:#?}", total_runtime)));
+        row.push(Cell::duration(total_runtime));
+
+        let utilization = data
+            .worker_utilization
+            .iter()
+            .find(|&&(werker, _)| worker == werker)
+            .map(|&(_, utilization)| utilization)
+            .unwrap_or_default();
+        for &time in [utilization.busy, utilization.idle, utilization.parked].iter() {
+            row.push(Cell::duration(time));
+            row.push(Cell::number(utilization.percentage(time)));
+        }
+        row.push(Cell::duration(utilization.progress));
+        row.push(Cell::number(utilization.progress_overhead()));
+
+        let messages = channel_messages(data, |werker| worker == werker);
+        row.extend(IntoIterator::into_iter([
+            Cell::number(messages.records_sent),
+            Cell::number(messages.records_received),
+            Cell::number(messages.batches_sent),
+            Cell::number(messages.batches_received),
+        ]));
 
         table.add_row(row.drain(..));
     }
 
-    writeln!(file, "Per-Worker Statistics\n{}\n", table)
-        .context("failed to write to report file")?;
+    report.table("Per-Worker Statistics", table);
+
+    park_stats(data, report)
+}
+
+fn worker_imbalance(args: &Args, data: &DataflowData, report: &mut Report) -> Result<()> {
+    if data.workers.len() < 2 {
+        tracing::debug!("only one worker ran, skipping worker imbalance table");
+        return Ok(());
+    }
+    tracing::debug!("generating worker imbalance table");
+
+    let deviation = |deviation: f64| Cell::change(Some(deviation / 100.0));
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Worker",
+        "Busy",
+        "Busy Deviation",
+        "Events",
+        "Events Deviation",
+        "Records Sent",
+        "Records Sent Deviation",
+        "Imbalanced",
+    ]);
+
+    for worker in analysis::worker_imbalance(data) {
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(format!("Worker {}", worker.worker.into_inner())),
+            Cell::duration(worker.busy),
+            deviation(worker.busy_deviation),
+            Cell::number(worker.events),
+            deviation(worker.events_deviation),
+            Cell::number(worker.records_sent),
+            deviation(worker.records_deviation),
+            Cell::new(worker.imbalanced(args.imbalance_threshold).join(", ")),
+        ]));
+    }
+
+    report.described_table(
+        "Worker Imbalance",
+        format!(
+            "Workers that deviate from the mean across all workers by more than {}% \
+             (`--imbalance-threshold`) are flagged, which usually points at skewed partitioning",
+            args.imbalance_threshold,
+        ),
+        table,
+    );
 
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-fn operator_stats(
-    args: &Args,
+fn park_stats(data: &DataflowData, report: &mut Report) -> Result<()> {
+    if data.park_stats.is_empty() {
+        tracing::debug!("no workers parked, skipping worker park table");
+        return Ok(());
+    }
+    tracing::debug!("generating worker park table");
+
+    let mut parks: Vec<_> = data.park_stats.iter().collect();
+    parks.sort_by_key(|&&(worker, _)| worker);
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Worker",
+        "Parks",
+        "Timed Parks",
+        "Timed Out",
+        "Indefinite Parks",
+        "Total Parked",
+        "Max Park",
+        "p50",
+        "p90",
+        "p99",
+    ]);
+
+    for &&(worker, stats) in parks.iter() {
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(format!("Worker {}", worker.into_inner())),
+            Cell::number(stats.parks),
+            Cell::number(stats.timed),
+            Cell::number(stats.timed_out),
+            Cell::number(stats.indefinite),
+            Cell::duration(stats.total),
+            Cell::duration(stats.max),
+            Cell::duration(stats.p50),
+            Cell::duration(stats.p90),
+            Cell::duration(stats.p99),
+        ]));
+    }
+
+    report.table("Worker Parks", table);
+
+    Ok(())
+}
+
+/// The number of operators listed within each of the ranked tables
+const RANKED_OPERATORS: usize = 20;
+
+fn dataflow_rollups(
+    report: &mut Report,
     data: &DataflowData,
-    file: &mut File,
     name_lookup: &HashMap<OpKey, &str, XXHasher>,
-    addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
-    agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
-    agg_arrangement_stats: &HashMap<OperatorId, &ArrangementStats, XXHasher>,
 ) -> Result<()> {
-    tracing::debug!("generating operator stats table");
+    if data.dataflow_stats.is_empty() {
+        tracing::debug!("no dataflows were recorded, skipping dataflow rollup table");
+        return Ok(());
+    }
+    tracing::debug!("generating dataflow rollup table");
 
-    // TODO: Sort within timely
-    let mut operators_by_total_runtime: Vec<_> = agg_operator_stats.iter().collect();
-    operators_by_total_runtime.sort_by_key(|(_operator, stats)| Reverse(stats.total));
+    // Each worker has its own copy of every dataflow, so their rollups are merged
+    let mut dataflows: HashMap<OperatorId, ui::DataflowStats, XXHasher> = HashMap::default();
+    for stats in data.dataflow_stats.iter() {
+        let rollup = dataflows
+            .entry(stats.id)
+            .or_insert_with(|| ui::DataflowStats {
+                total_activation_time: Duration::from_secs(0),
+                events: 0,
+                records_exchanged: 0,
+                peak_arrangement_size: None,
+                ..stats.clone()
+            });
 
-    let mut table = Table::new();
+        rollup.total_activation_time += stats.total_activation_time;
+        rollup.events += stats.events;
+        rollup.records_exchanged += stats.records_exchanged;
+        rollup.peak_arrangement_size = rollup
+            .peak_arrangement_size
+            .max(stats.peak_arrangement_size);
+    }
 
-    let mut headers = vec![
+    let mut dataflows: Vec<_> = dataflows.into_iter().map(|(_, stats)| stats).collect();
+    dataflows.sort_by_key(|stats| (Reverse(stats.total_activation_time), stats.id));
+
+    let mut table = Table::new();
+    table.set_header(&[
         "Name",
         "Id",
-        "Address",
-        "Total Runtime",
-        "Activations",
-        "Average Activation Time",
-        "Max Activation Time",
-        "Min Activation Time",
-        "Inputs",
-        "Outputs",
-    ];
-    if args.differential_enabled {
-        headers.extend(
-            [
-                "Max Arrangement Size",
-                "Min Arrangement Size",
-                "Arrangement Batches",
-            ]
-            .iter(),
-        );
+        "Operators",
+        "Subgraphs",
+        "Channels",
+        "Total Activation Time",
+        "Events",
+        "Records Exchanged",
+        "Peak Arrangement Size",
+    ]);
+
+    for stats in dataflows {
+        let name = name_lookup
+            .get(&(stats.worker, stats.id))
+            .copied()
+            .unwrap_or("");
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name),
+            Cell::number(stats.id),
+            Cell::number(stats.operators),
+            Cell::number(stats.subgraphs),
+            Cell::number(stats.channels),
+            Cell::duration(stats.total_activation_time),
+            Cell::number(stats.events),
+            Cell::number(stats.records_exchanged),
+            Cell::number(stats.peak_arrangement_size),
+        ]));
     }
 
-    table.set_header(&headers);
+    report.table("Dataflows", table);
 
-    for (operator, stats, addr, name) in
-        operators_by_total_runtime
-            .iter()
-            .map(|&(&operator, &stats)| {
-                let addr = data
-                    .workers
-                    .iter()
-                    .find_map(|&worker| addr_lookup.get(&(worker, operator)));
-                let name: Option<&str> = data
-                    .workers
-                    .iter()
-                    .find_map(|&worker| name_lookup.get(&(worker, operator)).map(|name| &**name));
+    Ok(())
+}
 
-                (operator, stats, addr, name.unwrap_or(""))
-            })
-    {
-        let arrange = agg_arrangement_stats.get(&operator).map(|&arrange| {
-            (
-                format!("{}", arrange.max_size),
-                format!("{}", arrange.min_size),
-                format!("{}", arrange.batches),
-            )
-        });
+/// Operators that went over `--warn-activation-over` or `--warn-arrangement-over`
+fn limit_warnings(
+    args: &Args,
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+) -> Result<()> {
+    let warnings = analysis::limit_warnings(args, data);
+    if warnings.is_empty() {
+        tracing::debug!("no operators went over the warning limits, skipping limit warnings");
+        return Ok(());
+    }
+    tracing::debug!("generating limit warnings");
 
-        let (inputs, outputs) = data
-            .operator_shapes
-            .iter()
-            .find_map(|shape| {
-                if shape.id == operator {
-                    Some((shape.inputs.len(), shape.outputs.len()))
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| {
-                tracing::warn!("couldn't find operator shape for {}", operator);
-                (0, 0)
-            });
+    let mut table = Table::new();
+    table.set_header(&["Name", "Id", "Worker", "Exceeded", "Value", "Limit"]);
 
-        let mut row = vec![
-            Cell::new(name),
-            Cell::new(operator),
-            Cell::new(format!(
-                "[{}]",
-                addr.map_or_else(
-                    || String::from("{unknown}"),
-                    |addr| addr
-                        .iter()
-                        .map(ToString::to_string)
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ),
-            )),
-            Cell::new(format!("{:#?}", stats.total)),
-            Cell::new(stats.count),
-            Cell::new(format!("{:#?}", stats.average)),
-            Cell::new(format!("{:#?}", stats.max)),
-            Cell::new(format!("{:#?}", stats.min)),
-            Cell::new(inputs),
-            Cell::new(outputs),
-        ];
+    for warning in warnings.iter() {
+        let (worker, id) = warning.operator();
+        let (exceeded, value, limit) = match *warning {
+            LimitWarning::Activation { max, limit, .. } => (
+                "Activation Time",
+                Cell::duration(max),
+                Cell::duration(limit),
+            ),
+            LimitWarning::Arrangement {
+                max_size, limit, ..
+            } => (
+                "Arrangement Size",
+                Cell::number(max_size),
+                Cell::number(limit),
+            ),
+        };
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&(worker, id)).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::number(worker),
+            Cell::new(exceeded),
+            value,
+            limit,
+        ]));
+    }
+
+    report.described_table(
+        "Limit Warnings",
+        format!(
+            "Warning: operators went over the `--warn-activation-over` or \
+             `--warn-arrangement-over` limits {} times",
+            warnings.len(),
+        ),
+        table,
+    );
+
+    Ok(())
+}
+
+/// The `--top-k` operators with the highest total activation time, longest
+/// single activation, most activations and largest arrangements on any one
+/// worker, which the report opens with to show where the time is going
+fn top_offenders(
+    args: &Args,
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+    addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
+) -> Result<()> {
+    if data.summarized.is_empty() || args.top_k == 0 {
+        tracing::debug!("no operators were activated, skipping top offenders tables");
+        return Ok(());
+    }
+    tracing::debug!("generating top offenders tables");
+
+    // Offenders are ranked by the nanoseconds or counts of their value
+    let leaderboard = |header: &str, mut offenders: Vec<(OpKey, Cell, u128)>| {
+        offenders.sort_by_key(|&(key, _, rank)| (Reverse(rank), key));
+        offenders.truncate(args.top_k);
+
+        let mut table = Table::new();
+        table.set_header(&["Rank", "Name", "Id", "Address", "Worker", header]);
+
+        for (rank, ((worker, operator), value, _)) in offenders.into_iter().enumerate() {
+            let addr = addr_lookup
+                .get(&(worker, operator))
+                .map_or_else(|| String::from("[{unknown}]"), ToString::to_string);
 
-        if let Some((max, min, batches)) = arrange {
-            row.extend(vec![Cell::new(max), Cell::new(min), Cell::new(batches)]);
+            table.add_row(IntoIterator::into_iter([
+                Cell::number(rank + 1),
+                Cell::new(name_lookup.get(&(worker, operator)).copied().unwrap_or("")),
+                Cell::number(operator),
+                Cell::new(addr),
+                Cell::number(worker),
+                value,
+            ]));
         }
 
-        table.add_row(row);
+        table
+    };
+
+    let mut offenders = Report::new();
+    offenders
+        .table(
+            "Total Activation Time",
+            leaderboard(
+                "Total Activation Time",
+                data.summarized
+                    .iter()
+                    .map(|&(key, stats)| (key, Cell::duration(stats.total), stats.total.as_nanos()))
+                    .collect(),
+            ),
+        )
+        .table(
+            "Longest Activation",
+            leaderboard(
+                "Max Activation Time",
+                data.summarized
+                    .iter()
+                    .map(|&(key, stats)| (key, Cell::duration(stats.max), stats.max.as_nanos()))
+                    .collect(),
+            ),
+        )
+        .table(
+            "Most Activations",
+            leaderboard(
+                "Activations",
+                data.summarized
+                    .iter()
+                    .map(|&(key, stats)| (key, Cell::number(stats.count), stats.count as u128))
+                    .collect(),
+            ),
+        );
+
+    if args.differential_enabled && !data.arrangements.is_empty() {
+        offenders.table(
+            "Largest Arrangements",
+            leaderboard(
+                "Max Arrangement Size",
+                data.arrangements
+                    .iter()
+                    .map(|(key, arrange)| {
+                        (
+                            *key,
+                            Cell::number(arrange.max_size),
+                            arrange.max_size as u128,
+                        )
+                    })
+                    .collect(),
+            ),
+        );
     }
 
-    writeln!(file, "Operators Ranked by Total Runtime\n{}\n", table,)
-        .context("failed to write to report file")?;
+    report.group("Top Offenders", offenders);
 
     Ok(())
 }
 
-fn arrangement_stats(
-    file: &mut File,
+fn slowest_operators(
+    report: &mut Report,
     data: &DataflowData,
     name_lookup: &HashMap<OpKey, &str, XXHasher>,
     addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
-    agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
-    agg_arrangement_stats: &HashMap<OperatorId, &ArrangementStats, XXHasher>,
 ) -> Result<()> {
-    tracing::debug!("generating arrangement stats table");
-
-    let mut operators_by_arrangement_size: Vec<_> = agg_arrangement_stats
-        .iter()
-        .filter_map(|(&operator, &arrange)| {
-            agg_operator_stats
-                .get(&operator)
-                .map(|&stats| (operator, stats, arrange))
-        })
-        .collect();
+    if data.slowest_operators.is_empty() {
+        tracing::debug!("no operators were activated, skipping slowest operators table");
+        return Ok(());
+    }
+    tracing::debug!("generating slowest operators table");
 
-    operators_by_arrangement_size.sort_unstable_by_key(|(_, _, arrange)| Reverse(arrange.max_size));
+    let mut leaderboard: Vec<_> = data.slowest_operators.iter().collect();
+    leaderboard.sort_by_key(|&&(operator, stats)| (Reverse(stats.total), operator));
 
     let mut table = Table::new();
     table.set_header(&[
+        "Rank",
         "Name",
         "Id",
         "Address",
         "Total Runtime",
-        "Max Arrangement Size",
-        "Min Arrangement Size",
-        "Arrangement Batches",
+        "Activations",
+        "Average Activation Time",
     ]);
 
-    for (operator, stats, arrange, addr, name) in
-        operators_by_arrangement_size
+    for (rank, &(operator, stats)) in leaderboard.into_iter().enumerate() {
+        let name = data
+            .workers
             .iter()
-            .map(|&(operator, ref stats, arrange)| {
-                let addr = data
-                    .workers
-                    .iter()
-                    .find_map(|&worker| addr_lookup.get(&(worker, operator)));
-                let name: Option<&str> = data
-                    .workers
-                    .iter()
-                    .find_map(|&worker| name_lookup.get(&(worker, operator)).map(|name| &**name));
+            .find_map(|&worker| name_lookup.get(&(worker, operator)).copied())
+            .unwrap_or("");
+        let addr = data
+            .workers
+            .iter()
+            .find_map(|&worker| addr_lookup.get(&(worker, operator)))
+            .map_or_else(
+                || String::from("[{unknown}]"),
+                |addr| {
+                    format!(
+                        "[{}]",
+                        addr.iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    )
+                },
+            );
 
-                (operator, stats, arrange, addr, name.unwrap_or(""))
-            })
-    {
         table.add_row(IntoIterator::into_iter([
+            Cell::number(rank + 1),
             Cell::new(name),
-            Cell::new(operator),
-            Cell::new(format!(
-                "[{}]",
-                addr.map_or_else(
-                    || String::from("{unknown}"),
-                    |addr| addr
-                        .iter()
-                        .map(ToString::to_string)
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ),
-            )),
-            Cell::new(format!("{:#?}", stats.total)),
-            Cell::new(arrange.max_size),
-            Cell::new(arrange.min_size),
-            Cell::new(arrange.batches),
+            Cell::number(operator),
+            Cell::new(addr),
+            Cell::duration(stats.total),
+            Cell::number(stats.count),
+            Cell::duration(stats.average),
         ]));
     }
 
-    writeln!(file, "Operators Ranked by Arrangement Size\n{}\n", table,)
-        .context("failed to write to report file")?;
+    report.table("Slowest Operators", table);
 
     Ok(())
 }
 
-fn operator_tree(
-    file: &mut File,
+fn throughput_stats(
+    report: &mut Report,
     data: &DataflowData,
     name_lookup: &HashMap<OpKey, &str, XXHasher>,
     addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
-    agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
 ) -> Result<()> {
-    tracing::debug!("generating operator tree");
+    tracing::debug!("generating operator throughput table");
 
-    let mut tree = Tree::new(|writer, _, (total, name, addr)| {
-        writeln!(writer, "{:#?}, {}, {}", total, name, addr)
+    let throughput =
+        analysis::operator_throughput(&data.edges, &data.channel_messages, &data.summarized);
+    if throughput.is_empty() {
+        tracing::debug!("no records were received by any operators, skipping throughput table");
+        return Ok(());
+    }
+
+    let mut operators_by_throughput: Vec<_> = throughput.into_iter().collect();
+    operators_by_throughput.sort_by(|(_, left), (_, right)| {
+        right
+            .records_per_second()
+            .partial_cmp(&left.records_per_second())
+            .unwrap_or(Ordering::Equal)
     });
 
-    for (&operator, &stats) in agg_operator_stats.iter() {
-        let addr = *data
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Address",
+        "Records Received",
+        "Activations",
+        "Records per Activation",
+        "Records per Second",
+    ]);
+
+    for (operator, throughput) in operators_by_throughput.into_iter().take(RANKED_OPERATORS) {
+        let name = data
             .workers
             .iter()
-            .find_map(|&worker| addr_lookup.get(&(worker, operator)))
-            .expect("missing operator addr");
-        let name = *data
+            .find_map(|&worker| name_lookup.get(&(worker, operator)).copied())
+            .unwrap_or("");
+        let addr = data
             .workers
             .iter()
-            .find_map(|&worker| name_lookup.get(&(worker, operator)))
-            .expect("missing operator name");
+            .find_map(|&worker| addr_lookup.get(&(worker, operator)))
+            .map_or_else(
+                || String::from("[{unknown}]"),
+                |addr| {
+                    format!(
+                        "[{}]",
+                        addr.iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    )
+                },
+            );
 
-        tree.insert(addr.as_slice(), (stats.total, name, addr));
-        // debug_assert_eq!(displaced, None);
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name),
+            Cell::number(operator),
+            Cell::new(addr),
+            Cell::number(throughput.records),
+            Cell::number(throughput.activations),
+            Cell::number(throughput.records_per_activation()),
+            Cell::number(throughput.records_per_second()),
+        ]));
     }
 
-    // FIXME: Things aren't actually getting sorted for some reason
-    tree.sort_unstable_by(|(_, left), (_, right)| {
-        left.map(|&(total, _, _)| Reverse(total))
-            .cmp(&right.map(|&(total, _, _)| Reverse(total)))
-    });
-
-    write!(file, "Operator Tree\n{}", tree).context("failed to write to report file")
-}
+    report.table("Operators Ranked by Throughput", table);
 
-struct Table {
-    inner: InnerTable,
+    Ok(())
 }
 
-impl Table {
-    fn new() -> Self {
-        let mut inner = InnerTable::new();
-        inner.load_preset(UTF8_FULL);
-
-        Self { inner }
-    }
-
-    fn set_header(&mut self, row: &[&str]) -> &mut Self {
-        self.inner
-            .set_constraints(
-                row.iter().map(|header| {
-                    ColumnConstraint::LowerBoundary(Width::Fixed(header.len() as u16))
+fn skew_stats(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+    addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
+) -> Result<()> {
+    if data.workers.len() < 2 {
+        tracing::debug!("only one worker was run, skipping operator skew table");
+        return Ok(());
+    }
+    tracing::debug!("generating operator skew table");
+
+    let mut operators_by_skew: Vec<_> =
+        analysis::operator_skew(&data.summarized, data.workers.len())
+            .into_iter()
+            .collect();
+    operators_by_skew.sort_by(|(_, left), (_, right)| {
+        right
+            .score()
+            .partial_cmp(&left.score())
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| right.max.cmp(&left.max))
+    });
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Address",
+        "Skew",
+        "Max Worker Activation Time",
+        "Mean Worker Activation Time",
+    ]);
+
+    for (operator, skew) in operators_by_skew.into_iter().take(RANKED_OPERATORS) {
+        let name = data
+            .workers
+            .iter()
+            .find_map(|&worker| name_lookup.get(&(worker, operator)).copied())
+            .unwrap_or("");
+        let addr = data
+            .workers
+            .iter()
+            .find_map(|&worker| addr_lookup.get(&(worker, operator)))
+            .map_or_else(
+                || String::from("[{unknown}]"),
+                |addr| {
+                    format!(
+                        "[{}]",
+                        addr.iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    )
+                },
+            );
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name),
+            Cell::number(operator),
+            Cell::new(addr),
+            Cell::number(skew.score()),
+            Cell::duration(skew.max),
+            Cell::duration(skew.mean),
+        ]));
+    }
+
+    report.table("Most Skewed Operators", table);
+
+    Ok(())
+}
+
+fn activation_anomalies(
+    args: &Args,
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+) -> Result<()> {
+    if data.activation_anomalies.is_empty() {
+        tracing::debug!("no activations were anomalous, skipping activation anomalies table");
+        return Ok(());
+    }
+    tracing::debug!("generating activation anomalies table");
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Worker",
+        "Started At",
+        "Activation Time",
+        "Median Activation Time",
+        "Multiple of Median",
+    ]);
+
+    for (multiple, &(operator, (start, (duration, median)))) in
+        analysis::worst_anomalies(&data.activation_anomalies, RANKED_OPERATORS)
+    {
+        let (worker, id) = operator;
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&operator).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::number(worker),
+            Cell::duration(start),
+            Cell::duration(duration),
+            Cell::duration(median),
+            Cell::multiple(multiple),
+        ]));
+    }
+
+    report.described_table(
+        "Activation Anomalies",
+        format!(
+            "{} activations took over {}x their operator's median activation time",
+            data.activation_anomalies.len(),
+            args.anomaly_threshold,
+        ),
+        table,
+    );
+
+    Ok(())
+}
+
+fn potential_leaks(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+    addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
+) -> Result<()> {
+    let lifespans: HashMap<OpKey, ui::Lifespan, XXHasher> =
+        data.operator_lifespans.iter().copied().collect();
+    let trace_drops: HashMap<OpKey, Option<Duration>, XXHasher> = data
+        .arrangement_details
+        .iter()
+        .map(|(operator, details)| (*operator, details.dropped))
+        .collect();
+
+    let leaks = analysis::potential_leaks(addr_lookup, &lifespans, &trace_drops);
+    if leaks.is_empty() {
+        tracing::debug!("no operators outlived their dataflows, skipping potential leaks table");
+        return Ok(());
+    }
+    tracing::debug!("generating potential leaks table");
+
+    let last_activations: HashMap<OpKey, Duration, XXHasher> =
+        data.last_activations.iter().copied().collect();
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Worker",
+        "Leak",
+        "Dataflow Dropped At",
+        "Dropped At",
+        "Last Activation",
+    ]);
+
+    for leak in leaks.iter() {
+        let (worker, id) = leak.operator;
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&leak.operator).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::number(worker),
+            Cell::new(leak.kind),
+            Cell::duration(leak.dataflow_dropped),
+            Cell::number(leak.dropped),
+            Cell::number(last_activations.get(&leak.operator).copied()),
+        ]));
+    }
+
+    report.described_table(
+        "Potential Leaks",
+        "Operators and traces that weren't dropped along with their dataflows",
+        table,
+    );
+
+    Ok(())
+}
+
+fn dead_operators(report: &mut Report, data: &DataflowData) -> Result<()> {
+    // Without any schedule or message events everything would look dead
+    let dead_operators = if data.summarized.is_empty() {
+        tracing::debug!("no operator activations were logged, skipping dead operators table");
+        Vec::new()
+    } else {
+        analysis::dead_operators(&data.nodes, &data.summarized)
+    };
+    let dead_channels = if data.channel_messages.is_empty() {
+        tracing::debug!("no channel messages were logged, skipping dead channels table");
+        Vec::new()
+    } else {
+        analysis::dead_channels(&data.edges, &data.channel_messages)
+    };
+
+    if !dead_operators.is_empty() {
+        tracing::debug!("generating dead operators table");
+
+        let mut table = Table::new();
+        table.set_header(&["Name", "Id", "Address"]);
+
+        for (addr, operator) in dead_operators {
+            table.add_row(IntoIterator::into_iter([
+                Cell::new(&operator.name),
+                Cell::number(operator.id),
+                Cell::new(addr),
+            ]));
+        }
+
+        report.table("Operators That Were Never Activated", table);
+    }
+
+    if !dead_channels.is_empty() {
+        tracing::debug!("generating dead channels table");
+
+        let mut table = Table::new();
+        table.set_header(&[
+            "Channel",
+            "Source",
+            "Source Address",
+            "Target",
+            "Target Address",
+        ]);
+
+        for (source, channel, target) in dead_channels {
+            table.add_row(IntoIterator::into_iter([
+                Cell::number(channel.channel_id()),
+                Cell::new(&source.name),
+                Cell::new(channel.source_addr()),
+                Cell::new(&target.name),
+                Cell::new(channel.target_addr()),
+            ]));
+        }
+
+        report.table("Channels That Never Carried Messages", table);
+    }
+
+    Ok(())
+}
+
+fn scheduling_latency_stats(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+) -> Result<()> {
+    tracing::debug!("generating scheduling latency table");
+
+    if data.scheduling_latencies.is_empty() {
+        tracing::debug!("no operators were sent input, skipping scheduling latency table");
+        return Ok(());
+    }
+
+    let percentiles: HashMap<OpKey, ActivationPercentiles, XXHasher> =
+        data.scheduling_percentiles.iter().copied().collect();
+
+    // Operators are ranked by their worst case latencies
+    let mut latencies: Vec<_> = data
+        .scheduling_latencies
+        .iter()
+        .map(|(operator, latency)| (*operator, latency, percentiles.get(operator)))
+        .collect();
+    latencies.sort_by_key(|&(_, latency, percentiles)| {
+        Reverse((percentiles.map(|percentiles| percentiles.p99), latency.max))
+    });
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Worker",
+        "Schedules",
+        "Average Latency",
+        "Latency Std Dev",
+        "Max Latency",
+        "p50 Latency",
+        "p99 Latency",
+    ]);
+
+    for (operator, latency, percentiles) in latencies.into_iter().take(RANKED_OPERATORS) {
+        let (worker, id) = operator;
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&operator).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::number(worker),
+            Cell::number(latency.count),
+            Cell::duration(latency.average),
+            Cell::duration(latency.stddev),
+            Cell::duration(latency.max),
+            Cell::number(percentiles.map(|percentiles| percentiles.p50)),
+            Cell::number(percentiles.map(|percentiles| percentiles.p99)),
+        ]));
+    }
+
+    report.table("Operators Ranked by Scheduling Latency", table);
+
+    Ok(())
+}
+
+fn arrangement_details(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+) -> Result<()> {
+    tracing::debug!("generating arrangement summary table");
+
+    // Every arrangement is listed since their growth is usually what's
+    // behind a program's memory usage
+    let mut arrangements: Vec<_> = data.arrangement_details.iter().collect();
+    arrangements
+        .sort_by_key(|&&(operator, ref details)| (Reverse(details.size_stats.max), operator));
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Address",
+        "Worker",
+        "Peak Size",
+        "Average Size",
+        "Batches",
+        "Average Batch Size",
+        "Max Batch Size",
+        "Merges",
+        "Total Merge Time",
+        "Average Merge Time",
+        "Max Merge Time",
+        "Trace Shares",
+        "Lifespan",
+    ]);
+
+    for &&(operator, ref details) in arrangements.iter() {
+        let (worker, id) = operator;
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&operator).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::new(&details.operator_addr),
+            Cell::number(worker),
+            Cell::number(details.size_stats.max),
+            Cell::number(details.size_stats.average),
+            Cell::number(details.batch_stats.total),
+            Cell::number(details.batch_stats.average),
+            Cell::number(details.batch_stats.max),
+            Cell::number(details.merge_stats.total),
+            Cell::duration(details.merge_stats.sum()),
+            Cell::duration(details.merge_stats.average),
+            Cell::duration(details.merge_stats.max),
+            Cell::number(details.trace_shares),
+            Cell::duration(details.lifespan.duration()),
+        ]));
+    }
+
+    report.described_table(
+        "Arrangement Summary",
+        "Every arrangement on each worker, sorted by the peak number of updates it held",
+        table,
+    );
+
+    compaction_stats(report, data, name_lookup)
+}
+
+fn compaction_stats(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+) -> Result<()> {
+    tracing::debug!("generating trace compaction table");
+
+    // Arrangements that merge their updates the most times are the ones falling behind
+    let mut arrangements: Vec<_> = data
+        .arrangement_details
+        .iter()
+        .filter(|(_, details)| details.compaction.batched_updates != 0)
+        .collect();
+    arrangements.sort_by(|(_, left), (_, right)| {
+        right
+            .compaction
+            .merge_amplification()
+            .partial_cmp(&left.compaction.merge_amplification())
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                right
+                    .compaction
+                    .unfinished_merges
+                    .cmp(&left.compaction.unfinished_merges)
+            })
+    });
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Worker",
+        "Batched Updates",
+        "Merge Inputs",
+        "Merge Outputs",
+        "Compaction Ratio",
+        "Merge Amplification",
+        "Unfinished Merges",
+    ]);
+
+    for &&(operator, ref details) in arrangements.iter().take(RANKED_OPERATORS) {
+        let (worker, id) = operator;
+        let compaction = details.compaction;
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&operator).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::number(worker),
+            Cell::number(compaction.batched_updates),
+            Cell::number(compaction.merge_inputs),
+            Cell::number(compaction.merge_outputs),
+            Cell::number(compaction.compaction_ratio()),
+            Cell::multiple(compaction.merge_amplification()),
+            Cell::number(compaction.unfinished_merges),
+        ]));
+    }
+
+    report.table("Trace Compaction", table);
+
+    shortfall_warnings(report, data, name_lookup)
+}
+
+fn shortfall_warnings(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+) -> Result<()> {
+    let mut arrangements: Vec<_> = data
+        .arrangement_details
+        .iter()
+        .filter(|(_, details)| details.shortfalls.falls_behind_repeatedly())
+        .collect();
+
+    if arrangements.is_empty() {
+        tracing::debug!(
+            "no arrangements repeatedly fell behind on merges, skipping shortfall warnings"
+        );
+        return Ok(());
+    }
+    tracing::debug!("generating merge shortfall warnings");
+
+    arrangements.sort_by_key(|(operator, details)| {
+        (
+            Reverse(details.shortfalls.shortfalls),
+            Reverse(details.shortfalls.behind),
+            *operator,
+        )
+    });
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Name",
+        "Id",
+        "Worker",
+        "Shortfalls",
+        "Total Shortfall",
+        "Max Shortfall",
+        "Time Behind",
+    ]);
+
+    for &&(operator, ref details) in arrangements.iter() {
+        let (worker, id) = operator;
+        let shortfalls = details.shortfalls;
+
+        table.add_row(IntoIterator::into_iter([
+            Cell::new(name_lookup.get(&operator).copied().unwrap_or("")),
+            Cell::number(id),
+            Cell::number(worker),
+            Cell::number(shortfalls.shortfalls),
+            Cell::number(shortfalls.total_shortfall),
+            Cell::number(shortfalls.max_shortfall),
+            Cell::duration(shortfalls.behind),
+        ]));
+    }
+
+    report.described_table(
+        "Merge Shortfalls",
+        format!(
+            "Warning: {} arrangements repeatedly fell behind on their merges, \
+             which forces merges to complete eagerly and stalls the operators that \
+             maintain them",
+            arrangements.len(),
+        ),
+        table,
+    );
+
+    Ok(())
+}
+
+/// Sums up the messages sent over the channels of the workers that match `filter`
+fn channel_messages<F>(data: &DataflowData, filter: F) -> ChannelMessageStats
+where
+    F: Fn(WorkerId) -> bool,
+{
+    let mut total = ChannelMessageStats::default();
+    for &((worker, _), messages) in data.channel_messages.iter() {
+        if filter(worker) {
+            total += messages;
+        }
+    }
+
+    total
+}
+
+/// Scope crossings that lead into a nested scope are ingresses and ones that
+/// lead out of one are egresses
+fn channel_kind(channel: &Channel) -> &'static str {
+    match channel {
+        Channel::Normal { .. } => "Normal",
+        Channel::ScopeCrossing {
+            source_addr,
+            target_addr,
+            ..
+        } => match target_addr.len().cmp(&source_addr.len()) {
+            Ordering::Greater => "Ingress",
+            Ordering::Less => "Egress",
+            Ordering::Equal => "Scope Crossing",
+        },
+    }
+}
+
+/// Splits the operator, channel and arrangement tables up by the top-level
+/// dataflow that each operator lives within, dataflows are ordered by their
+/// total runtime
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments)]
+fn dataflow_sections(
+    args: &Args,
+    data: &DataflowData,
+    report: &mut Report,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+    addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
+    agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
+    agg_arrangement_stats: &HashMap<OperatorId, &ArrangementStats, XXHasher>,
+    baseline_changes: Option<&HashMap<&OperatorAddr, OperatorChange, XXHasher>>,
+) -> Result<()> {
+    tracing::debug!("generating per-dataflow sections");
+
+    // With a `--baseline` every operator is annotated with how it changed since then,
+    // operators that grew by more than `--regression-threshold` are flagged
+    let threshold = args.regression_threshold / 100.0;
+    let baseline_cells = |addr: &OperatorAddr| -> Vec<Cell> {
+        let changes = match baseline_changes {
+            Some(changes) => changes,
+            None => return Vec::new(),
+        };
+
+        match changes.get(addr) {
+            Some(change) => vec![
+                Cell::change(change.runtime_change()),
+                Cell::change(change.activation_change()),
+                Cell::new(if change.is_regression(threshold) {
+                    "▲ regressed"
+                } else {
+                    ""
                 }),
+            ],
+            None => vec![Cell::change(None), Cell::change(None), Cell::new("")],
+        }
+    };
+    let arrangement_change = |addr: &OperatorAddr| {
+        baseline_changes.map(|changes| {
+            Cell::change(
+                changes
+                    .get(addr)
+                    .and_then(OperatorChange::arrangement_change),
             )
-            .set_header(row);
+        })
+    };
+
+    let name_of = |operator: OperatorId| {
+        data.workers
+            .iter()
+            .find_map(|&worker| name_lookup.get(&(worker, operator)).copied())
+            .unwrap_or("")
+    };
+
+    // Every operator with activations, keyed by the dataflow it's within
+    let mut dataflows: BTreeMap<OperatorId, Vec<(OperatorId, &OperatorAddr, &Summation)>> =
+        BTreeMap::new();
+    for (&operator, &stats) in agg_operator_stats.iter() {
+        let addr = data
+            .workers
+            .iter()
+            .find_map(|&worker| addr_lookup.get(&(worker, operator)).copied());
 
-        self
+        match addr.and_then(|addr| addr.first().map(|&dataflow| (addr, dataflow))) {
+            Some((addr, dataflow)) => dataflows
+                .entry(dataflow)
+                .or_default()
+                .push((operator, addr, stats)),
+            None => tracing::warn!("couldn't find the address of operator {}", operator),
+        }
     }
 
-    fn add_row<T>(&mut self, row: T) -> &mut Self
-    where
-        T: Into<Row>,
-    {
-        self.inner.add_row(row);
-        self
+    // Channel messages summed across every worker
+    let mut messages: HashMap<ChannelId, ChannelMessageStats, XXHasher> = HashMap::default();
+    for &((_worker, channel), stats) in data.channel_messages.iter() {
+        *messages.entry(channel).or_default() += stats;
+    }
+
+    let mut channels: BTreeMap<OperatorId, Vec<_>> = BTreeMap::new();
+    for (source, channel, target) in data.edges.iter() {
+        if let Some(&dataflow) = channel.source_addr().first() {
+            let stats = messages
+                .get(&channel.channel_id())
+                .copied()
+                .unwrap_or_default();
+
+            channels
+                .entry(dataflow)
+                .or_default()
+                .push((source, channel, target, stats));
+        }
+    }
+
+    // Dataflows without any activations still list their channels
+    for &dataflow in channels.keys() {
+        dataflows.entry(dataflow).or_default();
+    }
+
+    // Channels don't log when they're dropped, so they live for as long as
+    // both of the operators they connect
+    let lifespans: HashMap<OpKey, ui::Lifespan, XXHasher> =
+        data.operator_lifespans.iter().copied().collect();
+    let channel_lifespan = |source: OperatorId, target: OperatorId| {
+        data.workers.iter().find_map(|&worker| {
+            let source = lifespans.get(&(worker, source))?;
+            Some(source.overlap(lifespans.get(&(worker, target))?))
+        })
+    };
+
+    // The dataflow operator itself is the only operator at the top level
+    let dataflow_runtime = |operators: &[(OperatorId, &OperatorAddr, &Summation)]| {
+        operators
+            .iter()
+            .find(|(_, addr, _)| addr.is_top_level())
+            .map_or_else(|| Duration::from_secs(0), |(_, _, stats)| stats.total)
+    };
+
+    let mut dataflows: Vec<_> = dataflows.into_iter().collect();
+    dataflows
+        .sort_by_key(|(dataflow, operators)| (Reverse(dataflow_runtime(operators)), *dataflow));
+
+    for (dataflow, mut operators) in dataflows {
+        let mut sections = Report::new();
+
+        operators.sort_by_key(|&(operator, _, stats)| (Reverse(stats.total), operator));
+        let root = operators
+            .iter()
+            .find(|(_, addr, _)| addr.is_top_level())
+            .map(|&(operator, addr, _)| (name_of(operator), addr));
+
+        let mut header = vec![
+            "Name",
+            "Id",
+            "Address",
+            "Total Runtime",
+            "Activations",
+            "Average Activation Time",
+            "Max Activation Time",
+            "Min Activation Time",
+            "Activation Time Std Dev",
+            "Activation Time Std Error",
+            "Inputs",
+            "Outputs",
+        ];
+        if baseline_changes.is_some() {
+            header.extend(["Runtime Change", "Activations Change", "Regressed"].iter());
+        }
+
+        let mut table = Table::new();
+        table.set_header(&header);
+
+        for &(operator, addr, stats) in operators.iter() {
+            let (inputs, outputs) = data
+                .operator_shapes
+                .iter()
+                .find_map(|shape| {
+                    if shape.id == operator {
+                        Some((shape.inputs.len(), shape.outputs.len()))
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or_else(|| {
+                    tracing::warn!("couldn't find operator shape for {}", operator);
+                    (0, 0)
+                });
+
+            table.add_row(
+                IntoIterator::into_iter([
+                    Cell::new(name_of(operator)),
+                    Cell::number(operator),
+                    Cell::new(addr),
+                    Cell::duration(stats.total),
+                    Cell::number(stats.count),
+                    Cell::duration(stats.average),
+                    Cell::duration(stats.max),
+                    Cell::duration(stats.min),
+                    Cell::duration(stats.stddev),
+                    Cell::duration(stats.stderr),
+                    Cell::number(inputs),
+                    Cell::number(outputs),
+                ])
+                .chain(baseline_cells(addr)),
+            );
+        }
+        if !table.is_empty() {
+            sections.table("Operators", table);
+        }
+
+        if let Some(mut channels) = channels.remove(&dataflow) {
+            channels.sort_by_key(|(_, channel, _, stats)| {
+                (Reverse(stats.records_sent), channel.channel_id())
+            });
+            channels.dedup_by_key(|(_, channel, _, _)| channel.channel_id());
+
+            let mut table = Table::new();
+            table.set_header(&[
+                "Id",
+                "Source",
+                "Target",
+                "Kind",
+                "Lifespan",
+                "Records Sent",
+                "Batches Sent",
+                "Records Received",
+                "Batches Received",
+            ]);
+
+            for (source, channel, target, stats) in channels {
+                table.add_row(IntoIterator::into_iter([
+                    Cell::number(channel.channel_id()),
+                    Cell::new(format!("{} {}", source.name, channel.source_addr())),
+                    Cell::new(format!("{} {}", target.name, channel.target_addr())),
+                    Cell::new(channel_kind(channel)),
+                    Cell::number(
+                        channel_lifespan(source.id, target.id).map(|lifespan| lifespan.duration()),
+                    ),
+                    Cell::number(stats.records_sent),
+                    Cell::number(stats.batches_sent),
+                    Cell::number(stats.records_received),
+                    Cell::number(stats.batches_received),
+                ]));
+            }
+            sections.table("Channels", table);
+        }
+
+        if args.differential_enabled {
+            let mut arrangements: Vec<_> = operators
+                .iter()
+                .filter_map(|&(operator, addr, stats)| {
+                    agg_arrangement_stats
+                        .get(&operator)
+                        .map(|&arrange| (operator, addr, stats, arrange))
+                })
+                .collect();
+            arrangements
+                .sort_by_key(|&(operator, _, _, arrange)| (Reverse(arrange.max_size), operator));
+
+            if !arrangements.is_empty() {
+                let mut header = vec![
+                    "Name",
+                    "Id",
+                    "Address",
+                    "Total Runtime",
+                    "Max Arrangement Size",
+                    "Min Arrangement Size",
+                    "Arrangement Batches",
+                    "Arrangement Merges",
+                ];
+                if baseline_changes.is_some() {
+                    header.push("Arrangement Size Change");
+                }
+
+                let mut table = Table::new();
+                table.set_header(&header);
+
+                for (operator, addr, stats, arrange) in arrangements {
+                    table.add_row(
+                        IntoIterator::into_iter([
+                            Cell::new(name_of(operator)),
+                            Cell::number(operator),
+                            Cell::new(addr),
+                            Cell::duration(stats.total),
+                            Cell::number(arrange.max_size),
+                            Cell::number(arrange.min_size),
+                            Cell::number(arrange.batches),
+                            Cell::number(arrange.merges),
+                        ])
+                        .chain(arrangement_change(addr)),
+                    );
+                }
+                sections.table("Arrangements", table);
+            }
+        }
+
+        let title = match root {
+            Some((name, addr)) => format!("Dataflow {} {}", name, addr),
+            None => format!("Dataflow [{}]", dataflow),
+        };
+        report.group(title, sections);
+    }
+
+    Ok(())
+}
+
+fn operator_group_stats(
+    args: &Args,
+    report: &mut Report,
+    group_stats: &[GroupStats],
+) -> Result<()> {
+    tracing::debug!("generating operator group stats table");
+
+    let mut groups_by_total_runtime: Vec<_> = group_stats.iter().collect();
+    groups_by_total_runtime
+        .sort_by_key(|group| Reverse(group.activations.map(|activations| activations.total)));
+
+    let mut table = Table::new();
+
+    let mut headers = vec![
+        "Group",
+        "Operators",
+        "Total Runtime",
+        "Activations",
+        "Average Activation Time",
+        "Max Activation Time",
+        "Min Activation Time",
+    ];
+    if args.differential_enabled {
+        headers.extend(["Total Arrangement Size", "Arrangement Batches"].iter());
+    }
+
+    table.set_header(&headers);
+
+    for group in groups_by_total_runtime {
+        let mut row = vec![Cell::new(group.name), Cell::number(group.members.len())];
+
+        if let Some(stats) = group.activations {
+            row.extend(IntoIterator::into_iter([
+                Cell::duration(stats.total),
+                Cell::number(stats.count),
+                Cell::duration(stats.average),
+                Cell::duration(stats.max),
+                Cell::duration(stats.min),
+            ]));
+        } else {
+            row.extend((0..5).map(|_| Cell::new("")));
+        }
+
+        if args.differential_enabled {
+            row.extend(IntoIterator::into_iter([
+                Cell::number(group.arrangement_size.unwrap_or(0)),
+                Cell::number(group.arrangement_batches.unwrap_or(0)),
+            ]));
+        }
+
+        table.add_row(row);
+    }
+
+    report.table("Operator Groups Ranked by Total Runtime", table);
+
+    Ok(())
+}
+
+fn progress_stats(
+    report: &mut Report,
+    data: &DataflowData,
+    progress_channels: &[ProgressChannelStats],
+) -> Result<()> {
+    tracing::debug!("generating progress stats tables");
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Worker",
+        "Scope",
+        "Channel",
+        "Messages Sent",
+        "Messages Received",
+        "Message Updates",
+        "Capability Updates",
+        "Total Pointstamps",
+    ]);
+
+    for channel in progress_channels {
+        table.add_row(IntoIterator::into_iter([
+            Cell::number(channel.worker),
+            Cell::new(&channel.scope_addr),
+            Cell::number(channel.channel),
+            Cell::number(channel.sent),
+            Cell::number(channel.received),
+            Cell::number(channel.message_updates),
+            Cell::number(channel.capability_updates),
+            Cell::number(channel.pointstamps()),
+        ]));
+    }
+
+    report.table("Progress Tracking Overhead", table);
+
+    // Collect the timestamps released by each scope & the span of time they were released over
+    let mut frontiers: HashMap<_, (usize, Duration, Duration), XXHasher> = HashMap::default();
+    for &(ref scope, (released_at, _)) in data.frontier_progression.iter() {
+        frontiers
+            .entry(scope)
+            .and_modify(|(released, first, last)| {
+                *released += 1;
+                *first = (*first).min(released_at);
+                *last = (*last).max(released_at);
+            })
+            .or_insert((1, released_at, released_at));
+    }
+
+    let mut frontiers: Vec<_> = frontiers.into_iter().collect();
+    frontiers.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+
+    let mut table = Table::new();
+    table.set_header(&[
+        "Worker",
+        "Scope",
+        "Timestamps Released",
+        "First Release",
+        "Last Release",
+    ]);
+
+    for ((worker, scope), (released, first, last)) in frontiers {
+        table.add_row(IntoIterator::into_iter([
+            Cell::number(*worker),
+            Cell::new(scope),
+            Cell::number(released),
+            Cell::duration(first),
+            Cell::duration(last),
+        ]));
+    }
+
+    report.table("Frontier Progression", table);
+
+    if !data.scope_iterations.is_empty() {
+        let mut iterations: Vec<_> = data.scope_iterations.iter().collect();
+        iterations.sort_unstable();
+
+        let mut table = Table::new();
+        table.set_header(&[
+            "Worker",
+            "Scope",
+            "Epoch",
+            "Iterations",
+            "Total Time",
+            "Average Iteration Time",
+            "Max Iteration Time",
+        ]);
+
+        for ((worker, scope), stats) in iterations {
+            table.add_row(IntoIterator::into_iter([
+                Cell::number(*worker),
+                Cell::new(scope),
+                Cell::new(&stats.epoch),
+                Cell::number(stats.iterations),
+                Cell::duration(stats.total),
+                Cell::duration(stats.iteration_times.average),
+                Cell::duration(stats.iteration_times.max),
+            ]));
+        }
+
+        report.table("Iterative Scope Convergence", table);
     }
+
+    Ok(())
 }
 
-impl Display for Table {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Display::fmt(&self.inner, f)
+fn operator_tree(
+    report: &mut Report,
+    data: &DataflowData,
+    name_lookup: &HashMap<OpKey, &str, XXHasher>,
+    addr_lookup: &HashMap<OpKey, &OperatorAddr, XXHasher>,
+    agg_operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
+) -> Result<()> {
+    tracing::debug!("generating operator tree");
+
+    let mut tree = Tree::new(|writer, _, (total, name, addr)| {
+        writeln!(writer, "{:#?}, {}, {}", total, name, addr)
+    });
+
+    for (&operator, &stats) in agg_operator_stats.iter() {
+        let addr = *data
+            .workers
+            .iter()
+            .find_map(|&worker| addr_lookup.get(&(worker, operator)))
+            .expect("missing operator addr");
+        let name = *data
+            .workers
+            .iter()
+            .find_map(|&worker| name_lookup.get(&(worker, operator)))
+            .expect("missing operator name");
+
+        tree.insert(addr.as_slice(), (stats.total, name, addr));
+        // debug_assert_eq!(displaced, None);
     }
+
+    // FIXME: Things aren't actually getting sorted for some reason
+    tree.sort_unstable_by(|(_, left), (_, right)| {
+        left.map(|&(total, _, _)| Reverse(total))
+            .cmp(&right.map(|&(total, _, _)| Reverse(total)))
+    });
+
+    report.text("Operator Tree", tree.to_string());
+
+    Ok(())
 }
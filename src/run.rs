@@ -0,0 +1,854 @@
+//! The full ddshow pipeline, from gathering the target's logs to rendering them
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::replay_loading::wait_for_input;
+use crate::{
+    args::{Args, BufferPolicy, Command},
+    colormap::{self, select_color, Color},
+    custom_streams::{CustomEvent, CustomStreams},
+    dataflow::{
+        self, progress_channel_stats,
+        utils::{HumanDuration, OpKey, XXHasher},
+        ActivationPercentiles, Channel, DataflowData, DataflowSenders, StatsSinks, Summation,
+    },
+    diff, dump,
+    live_server::LiveServer,
+    logging,
+    operator_groups::{self, OperatorGroups},
+    output_sink::{self, OutputContext, OutputSinks},
+    replay_loading::{connect_to_sources, ConnectedSources},
+    report::analysis,
+    status::CaptureStatus,
+    ui::{
+        self, ActivationDuration, ChannelBacklog, ChannelLatency, EdgeKind, RunStats,
+        SourceLocation,
+    },
+};
+#[cfg(target_arch = "wasm32")]
+use crate::{dataflow::TimelineSpill, replay_loading::extract_remaining};
+use anyhow::{Context, Result};
+use ddshow_types::{timely_logging::OperatesEvent, ChannelId, WorkerId};
+use std::{
+    collections::HashMap,
+    fs::{self},
+    io::{self},
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+#[cfg(target_arch = "wasm32")]
+use timely::communication::allocator::AllocateBuilder;
+use timely::{communication::allocator::Generic, worker::Worker};
+
+/// Everything gathered from the target, ready to be rendered
+pub(crate) struct Collected {
+    pub(crate) data: DataflowData,
+    pub(crate) target_runs: Vec<RunStats>,
+    pub(crate) dropped_events: Option<usize>,
+    pub(crate) custom_events: Vec<CustomEvent>,
+    pub(crate) interrupted: Arc<AtomicBool>,
+    pub(crate) live_server: Option<LiveServer>,
+}
+
+// FIXME: Clean this up so much
+// TODO: Set the panic hook to shut down the computation
+//       so that panics don't stick things
+/// Runs ddshow the same way that its command line interface does
+pub fn run_cli(args: Args) -> Result<()> {
+    let start_time = Instant::now();
+
+    let args = Arc::new(args);
+    logging::init_logging(&args)?;
+
+    tracing::trace!("initialized and received cli args: {:?}", args);
+
+    if args.debug_replay_files {
+        tracing::warn!("the `--debug-replay-files` arg currently does nothing");
+    }
+
+    if let Some(shell) = args.completions {
+        tracing::trace!("generating completions for {}", shell);
+        Args::clap().gen_completions_to(env!("CARGO_PKG_NAME"), shell, &mut io::stdout());
+
+        return Ok(());
+    }
+
+    // Create the output directory, `ddshow record` doesn't write anything to it
+    if !args.records_only() {
+        fs::create_dir_all(&args.output_dir).with_context(|| {
+            anyhow::anyhow!(
+                "failed to create output directory '{}'",
+                args.output_dir.display(),
+            )
+        })?;
+    }
+
+    if let Some(Command::Diff(diff_args)) = &args.command {
+        return diff::run(&args, diff_args);
+    }
+
+    // Load the operator groups and the baseline before we start waiting on sources
+    // so that the user finds out about malformed files as soon as possible
+    let operator_groups = args
+        .operator_groups
+        .as_deref()
+        .map(OperatorGroups::load)
+        .transpose()?
+        .unwrap_or_default();
+    let baseline = args
+        .baseline
+        .as_deref()
+        .map(dump::load_baseline)
+        .transpose()?;
+
+    let collected = if let Some(collected) = collect(&args, true, Vec::new(), None)? {
+        collected
+    } else {
+        return Ok(());
+    };
+
+    if args.records_only() {
+        if let Some(save_logs) = args.save_logs.as_ref() {
+            if args.isnt_quiet() {
+                println!(
+                    "Saved the target's logs to {}, analyze them with `ddshow analyze {}`",
+                    save_logs.display(),
+                    save_logs.display(),
+                );
+            }
+        }
+
+        return Ok(());
+    }
+
+    render(
+        &args,
+        collected,
+        &operator_groups,
+        baseline.as_ref(),
+        Vec::new(),
+        start_time,
+    )
+}
+
+/// Gathers the target's logs and runs them through the analysis dataflow,
+/// returns `None` if the target exited before it connected to us
+///
+/// When `handle_ctrlc` is set ctrl+c stops collecting early. Sources that
+/// were already set up are used as-is, otherwise they're connected to
+/// according to `args`
+pub(crate) fn collect(
+    args: &Arc<Args>,
+    handle_ctrlc: bool,
+    stats_sinks: StatsSinks,
+    sources: Option<ConnectedSources>,
+) -> Result<Option<Collected>> {
+    let (communication_config, worker_config) = args.timely_config();
+
+    // Start serving before we wait on the target so the user can open
+    // the live page right away
+    let live_server = LiveServer::start(args)?;
+
+    // Custom streams have to be listening before the target is spawned
+    let custom_listeners = CustomStreams::from_args(args).listen()?;
+
+    let (
+        timely_event_receivers,
+        differential_event_receivers,
+        progress_event_receivers,
+        total_sources,
+        target_process,
+        target_runs,
+        dropped_events,
+    ) = match sources {
+        Some(sources) => sources,
+        None => match connect_to_sources(args)? {
+            Some(sources) => sources,
+            None => return Ok(None),
+        },
+    };
+
+    let (running, interrupted, workers_finished, replays_finished) = (
+        Arc::new(AtomicBool::new(true)),
+        Arc::new(AtomicBool::new(false)),
+        Arc::new(AtomicUsize::new(0)),
+        Arc::new(AtomicUsize::new(0)),
+    );
+
+    let capture_status = Arc::new(CaptureStatus::new());
+
+    let (replay_shutdown, moved_replays_finished, moved_args, moved_workers_finished) = (
+        running.clone(),
+        replays_finished.clone(),
+        args.clone(),
+        workers_finished.clone(),
+    );
+    let moved_capture_status = capture_status.clone();
+
+    // Only one ctrl+c handler can be set per process, so embedders keep theirs
+    #[cfg(not(target_arch = "wasm32"))]
+    if handle_ctrlc {
+        let (ctrlc_running, ctrlc_interrupted) = (running.clone(), interrupted.clone());
+        ctrlc::set_handler(move || {
+            ctrlc_running.store(false, Ordering::Release);
+            ctrlc_interrupted.store(true, Ordering::Release);
+            tracing::info!("received ctrl+c signal, shutting down");
+        })
+        .context("failed to set ctrl+c handler")?;
+    }
+
+    // Create the *many* channels used for extracting data from the dataflow
+    let (senders, receivers) = DataflowSenders::create();
+
+    tracing::info!("starting compute dataflow");
+
+    // Build the timely allocators and loggers
+    let (builders, others) = communication_config
+        .try_build()
+        .map_err(|err| anyhow::anyhow!("failed to build timely communication config: {}", err))?;
+
+    let run_worker = move |worker: &mut Worker<Generic>| {
+        // Distribute the tcp streams across workers, converting each of them into an event reader
+        let timely_traces = timely_event_receivers[worker.index()]
+            .clone()
+            .recv()
+            .expect("failed to receive timely event traces");
+
+        let differential_traces = differential_event_receivers.as_ref().map(|recv| {
+            recv[worker.index()]
+                .recv()
+                .expect("failed to receive differential event traces")
+        });
+
+        let progress_traces = progress_event_receivers.as_ref().map(|recv| {
+            recv[worker.index()]
+                .recv()
+                .expect("failed to receive progress traces")
+        });
+
+        // Start the analysis worker's runtime
+        dataflow::worker_runtime(
+            worker,
+            moved_args.clone(),
+            senders.clone(),
+            replay_shutdown.clone(),
+            moved_workers_finished.clone(),
+            moved_replays_finished.clone(),
+            moved_capture_status.clone(),
+            timely_traces,
+            differential_traces,
+            progress_traces,
+        )
+    };
+
+    // Spin up the timely computation
+    // Note: We use `execute_from()` instead of `timely::execute()` because
+    //       `execute()` automatically sets log hooks that connect to
+    //       `TIMELY_WORKER_LOG_ADDR`, meaning that no matter what we do
+    //       our dataflow will always attempt to connect to that address
+    //       if it's present in the env, causing things like ddshow/#7.
+    //       See https://github.com/Kixiron/ddshow/issues/7
+    let dataflow_start_time = Instant::now();
+    #[cfg(not(target_arch = "wasm32"))]
+    let data = {
+        let worker_guards =
+            timely::execute::execute_from(builders, others, worker_config, run_worker)
+                .map_err(|err| anyhow::anyhow!("failed to start up timely computation: {}", err))?;
+
+        // Wait for the user's prompt
+        wait_for_input(
+            args,
+            &running,
+            &workers_finished,
+            &replays_finished,
+            total_sources,
+            worker_guards,
+            receivers,
+            live_server.as_ref(),
+            &target_runs,
+            &dropped_events,
+            capture_status,
+            stats_sinks,
+        )?
+    };
+
+    // Without threads the dataflow runs to completion on this thread before
+    // anything is extracted from it, so only a single worker can be used
+    #[cfg(target_arch = "wasm32")]
+    let data = {
+        let _ = (
+            others,
+            total_sources,
+            &running,
+            &workers_finished,
+            capture_status,
+        );
+        let allocator = match builders.into_iter().next() {
+            Some(builder) if args.workers.get() == 1 => builder.build(),
+            _ => anyhow::bail!("wasm builds of ddshow only support a single worker"),
+        };
+
+        let mut worker = Worker::new(worker_config, allocator);
+        run_worker(&mut worker)?;
+        drop(worker);
+
+        let extractor = receivers
+            .into_extractor()
+            .with_stats_sinks(stats_sinks)
+            .with_timeline_spill(args.timeline_memory_budget.map(TimelineSpill::new));
+        extract_remaining(args, extractor)
+    };
+
+    let custom_events = custom_listeners.finish();
+    if !custom_events.is_empty() {
+        tracing::info!("received {} custom events", custom_events.len());
+    }
+
+    // Wait for the target process to exit, killing it if the user interrupted us
+    // or if we stopped collecting because its streams went idle, since those
+    // targets aren't expected to exit on their own
+    if let Some(target) = target_process {
+        let command = target.command().to_owned();
+        let kill = interrupted.load(Ordering::Acquire) || args.completion_idle.is_some();
+        let status = target.shutdown(kill)?;
+
+        if !status.success() {
+            tracing::warn!(command = %command, status = %status, "target process failed");
+
+            if args.isnt_quiet() {
+                eprintln!("Target process `{}` exited with {}", command, status);
+            }
+        }
+    }
+
+    let dataflow_elapsed = dataflow_start_time.elapsed();
+    tracing::info!(
+        elapsed = ?dataflow_elapsed,
+        "spent {} within the compute dataflow",
+        HumanDuration(dataflow_elapsed),
+    );
+
+    let target_runs = target_runs.snapshot();
+    if target_runs.len() > 1 {
+        tracing::info!("the target ran {} separate times", target_runs.len());
+    }
+
+    // Only report dropped events if dropping them was possible in the first place
+    let dropped_events = args
+        .ingest_capacity()
+        .map(|_| dropped_events.count())
+        .filter(|_| args.buffer_policy == BufferPolicy::Drop);
+    if let Some(dropped) = dropped_events.filter(|&dropped| dropped != 0) {
+        tracing::warn!("dropped {} events due to full ingestion buffers", dropped);
+
+        if args.isnt_quiet() {
+            eprintln!(
+                "warning: dropped {} events due to full ingestion buffers, \
+                consider raising `--ingest-buffer`",
+                dropped,
+            );
+        }
+    }
+
+    Ok(Some(Collected {
+        data,
+        target_runs,
+        dropped_events,
+        custom_events,
+        interrupted,
+        live_server,
+    }))
+}
+
+/// Writes out the dumps, the report and the graph along with any other outputs
+/// registered by embedders
+pub(crate) fn render(
+    args: &Args,
+    collected: Collected,
+    operator_groups: &OperatorGroups,
+    baseline: Option<&DataflowData>,
+    output_sinks: OutputSinks,
+    start_time: Instant,
+) -> Result<()> {
+    let Collected {
+        mut data,
+        target_runs,
+        dropped_events,
+        custom_events,
+        interrupted,
+        live_server,
+    } = collected;
+
+    let extraction_start_time = Instant::now();
+
+    let name_lookup: HashMap<_, _, XXHasher> = data
+        .name_lookup
+        .iter()
+        .map(|(id, name)| (*id, name.deref()))
+        .collect();
+    let full_name_lookup: HashMap<_, _, XXHasher> = data
+        .full_name_lookup
+        .iter()
+        .map(|(id, name)| (*id, name.deref()))
+        .collect();
+    let addr_lookup: HashMap<_, _, XXHasher> = data
+        .addr_lookup
+        .iter()
+        .map(|(id, addr)| (*id, addr))
+        .collect();
+
+    data.nodes
+        .sort_unstable_by(|(addr1, _), (addr2, _)| addr1.cmp(addr2));
+    data.subgraphs
+        .sort_unstable_by(|(addr1, _), (addr2, _)| addr1.cmp(addr2));
+    data.edges
+        .sort_unstable_by_key(|(_, channel, _)| channel.channel_id());
+
+    let mut subgraph_ids = Vec::new();
+    for (_, event) in data.subgraphs.iter() {
+        subgraph_ids.push(event.id);
+    }
+
+    let (mut operator_stats, mut agg_operator_stats) = (
+        HashMap::with_capacity_and_hasher(data.summarized.len(), XXHasher::default()),
+        HashMap::with_capacity_and_hasher(data.aggregated_summaries.len() / 2, XXHasher::default()),
+    );
+    // Workers left out by `--workers-filter` don't contribute to the graph
+    let mut worker_activity: HashMap<_, Vec<_>, XXHasher> = HashMap::default();
+    for ((worker, operator), stats) in data.summarized.iter() {
+        if !args.renders_worker(*worker) {
+            continue;
+        }
+
+        operator_stats.insert(*operator, stats);
+        worker_activity
+            .entry(*operator)
+            .or_default()
+            .push(ui::WorkerActivity::new(*worker, stats));
+    }
+    for activity in worker_activity.values_mut() {
+        activity.sort_unstable();
+    }
+
+    // With `--aggregate-workers` each operator shows its stats merged across every worker
+    if args.aggregate_workers {
+        let addr_summaries: HashMap<_, _, XXHasher> = data
+            .addr_summaries
+            .iter()
+            .map(|(addr, stats)| (addr, stats))
+            .collect();
+
+        for (addr, operator) in data.nodes.iter().chain(data.subgraphs.iter()) {
+            if let Some(&stats) = addr_summaries.get(addr) {
+                operator_stats.insert(operator.id, stats);
+            }
+        }
+    }
+
+    let mut rendered_workers: Vec<_> = data
+        .workers
+        .iter()
+        .copied()
+        .filter(|&worker| args.renders_worker(worker))
+        .collect();
+    rendered_workers.sort_unstable();
+    for (operator, stats) in data.aggregated_summaries.iter() {
+        agg_operator_stats.insert(*operator, stats);
+    }
+
+    let mut arrangement_map =
+        HashMap::with_capacity_and_hasher(data.arrangements.len(), XXHasher::default());
+    for &(operator, ref arrangements) in data.arrangements.iter() {
+        arrangement_map.insert(operator, arrangements);
+    }
+
+    let arrangement_details: HashMap<_, _, XXHasher> = data
+        .arrangement_details
+        .iter()
+        .map(|(operator, details)| (*operator, details))
+        .collect();
+
+    let mut agg_arrangement_stats =
+        HashMap::with_capacity_and_hasher(data.aggregated_arrangements.len(), XXHasher::default());
+    for &(operator, ref arrangements) in data.aggregated_arrangements.iter() {
+        agg_arrangement_stats.insert(operator, arrangements);
+    }
+
+    let mut activations_map =
+        HashMap::with_capacity_and_hasher(data.operator_activations.len(), XXHasher::default());
+    for &(operator, activation) in data.operator_activations.iter() {
+        activations_map
+            .entry(operator)
+            .and_modify(|activations: &mut Vec<_>| activations.push(activation))
+            .or_insert_with(|| {
+                let mut activations = Vec::with_capacity(128);
+                activations.push(activation);
+
+                activations
+            });
+    }
+
+    let mut agg_activations_map =
+        HashMap::with_capacity_and_hasher(data.operator_activations.len(), XXHasher::default());
+    for (&(_worker, operator), activations) in activations_map.iter() {
+        agg_activations_map
+            .entry(operator)
+            .and_modify(|agg: &mut Vec<_>| agg.push(activations))
+            .or_insert_with(|| {
+                let mut agg = Vec::with_capacity(8);
+                agg.push(activations);
+                agg
+            });
+    }
+
+    let mut spline_levels =
+        HashMap::with_capacity_and_hasher(data.spline_levels.len(), XXHasher::default());
+    for &((worker, operator), level) in data.spline_levels.iter() {
+        spline_levels
+            .entry((worker, operator))
+            .and_modify(|levels: &mut Vec<_>| levels.push(level))
+            .or_insert_with(|| {
+                let mut levels = Vec::with_capacity(8);
+                levels.push(level);
+                levels
+            });
+    }
+
+    let group_stats = operator_groups::group_stats(
+        operator_groups,
+        &data,
+        &agg_operator_stats,
+        &agg_arrangement_stats,
+    );
+
+    let progress_channels = progress_channel_stats(&data.progress_traffic);
+
+    let worker_runtimes: HashMap<_, _, XXHasher> = data
+        .total_runtime
+        .iter()
+        .map(|&(worker, (start, end))| (worker, end.checked_sub(start).unwrap_or_default()))
+        .collect();
+
+    // The value of the `--color-by` metric for each operator, taken from the
+    // same worker that the operator's details are shown for
+    let color_metrics: HashMap<_, _, XXHasher> = data
+        .nodes
+        .iter()
+        .filter_map(|(_, operator)| {
+            let stats = operator_stats.get(&operator.id)?;
+            let worker = worker_activity
+                .get(&operator.id)
+                .and_then(|workers| workers.first())
+                .map_or(WorkerId::new(0), |activity| activity.worker);
+
+            let value = colormap::color_metric(
+                args.color_by,
+                stats,
+                arrangement_map.get(&(worker, operator.id)).copied(),
+                worker_runtimes.get(&worker).copied(),
+            )?;
+
+            Some((operator.id, value))
+        })
+        .collect();
+    let color_bounds = color_metrics
+        .values()
+        .fold((f64::MIN, f64::MAX), |(max, min), &value| {
+            (max.max(value), min.min(value))
+        });
+
+    let activation_percentiles: HashMap<OpKey, ActivationPercentiles, XXHasher> =
+        data.activation_percentiles.iter().copied().collect();
+
+    let mut histogram_counts: HashMap<OpKey, Vec<(usize, usize)>, XXHasher> = HashMap::default();
+    for &(operator, counts) in data.activation_histograms.iter() {
+        histogram_counts.entry(operator).or_default().push(counts);
+    }
+    let histogram_bounds = args.histogram_bounds();
+
+    let mut rate_windows: HashMap<OpKey, Vec<(Duration, (usize, Duration))>, XXHasher> =
+        HashMap::default();
+    for &(operator, window) in data.activation_rates.iter() {
+        rate_windows.entry(operator).or_default().push(window);
+    }
+    let rate_interval = Duration::from_millis(args.activation_rate_interval);
+
+    let html_nodes: Vec<_> = data
+        .nodes
+        .iter()
+        .filter_map(|&(ref addr, OperatesEvent { id, ref name, .. })| {
+            let Summation {
+                max,
+                min,
+                average,
+                total,
+                count: invocations,
+                ..
+            } = **operator_stats.get(&id)?;
+
+            // Per-operator details are shown for the first rendered worker
+            let workers = worker_activity.get(&id).map_or(&[][..], Vec::as_slice);
+            let worker = workers
+                .first()
+                .map_or(WorkerId::new(0), |activity| activity.worker);
+
+            let arranged = arrangement_map.get(&(worker, id)).copied();
+            let details = arrangement_details.get(&(worker, id)).copied();
+            let activation_durations = activations_map
+                .get(&(worker, id))
+                .map(|activations| {
+                    activations
+                        .iter()
+                        .map(|(duration, time)| ActivationDuration {
+                            activation_time: duration.as_nanos() as u64,
+                            activated_at: time.as_nanos() as u64,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            // Names are truncated, so locations are parsed from the full name
+            let full_name = full_name_lookup.get(&(worker, id)).copied();
+            let source_location = SourceLocation::parse(full_name.unwrap_or(name));
+            let source_url = source_location
+                .as_ref()
+                .zip(args.source_url.as_deref())
+                .map(|(location, template)| location.url(template));
+
+            // Operators without the metric get the lowest color
+            let metric = color_metrics.get(&id).copied().unwrap_or(color_bounds.1);
+            let fill_color = colormap::select_scaled_color(&args.palette, metric, color_bounds);
+            let text_color = fill_color.text_color();
+
+            Some(ui::Node {
+                id,
+                addr,
+                name,
+                full_name,
+                max_activation_time: format!("{:#?}", max),
+                min_activation_time: format!("{:#?}", min),
+                average_activation_time: format!("{:#?}", average),
+                total_activation_time: format!("{:#?}", total),
+                invocations,
+                fill_color: format!("{}", fill_color),
+                text_color: format!("{}", text_color),
+                // TODO: Teach JS to deal with durations so we don't have to allocate
+                //       so much garbage
+                activation_durations,
+                activation_percentiles: activation_percentiles.get(&(worker, id)).copied(),
+                activation_rates: rate_windows
+                    .get(&(worker, id))
+                    .map(|windows| ui::activation_rates(rate_interval, windows))
+                    .unwrap_or_default(),
+                activation_histogram: ui::activation_histogram(
+                    &histogram_bounds,
+                    histogram_counts
+                        .get(&(worker, id))
+                        .into_iter()
+                        .flatten()
+                        .copied(),
+                ),
+                source_location,
+                source_url,
+                max_arrangement_size: arranged.as_ref().map(|arr| arr.max_size),
+                min_arrangement_size: arranged.as_ref().map(|arr| arr.min_size),
+                average_batch_size: details.map(|details| details.batch_stats.average),
+                average_merge_time: details
+                    .filter(|details| details.merge_stats.total != 0)
+                    .map(|details| format!("{:#?}", details.merge_stats.average)),
+                trace_shares: details.map(|details| details.trace_shares),
+                workers,
+                comparison: None,
+            })
+        })
+        .collect();
+
+    let mut subgraph_contents =
+        ui::subgraph_contents(&data.nodes, &data.subgraphs, &operator_stats);
+    let html_subgraphs: Vec<_> = data
+        .subgraphs
+        .iter()
+        .filter_map(|&(ref addr, OperatesEvent { id, ref name, .. })| {
+            let Summation {
+                max,
+                min,
+                average,
+                total,
+                count: invocations,
+                ..
+            } = **operator_stats.get(&id)?;
+
+            let fill_color = select_color(&args.palette, total, (max, min));
+            let text_color = fill_color.text_color();
+            let (children, rollup) = subgraph_contents.remove(addr).unwrap_or_default();
+
+            Some(ui::Subgraph {
+                id,
+                addr,
+                name,
+                max_activation_time: format!("{:#?}", max),
+                min_activation_time: format!("{:#?}", min),
+                average_activation_time: format!("{:#?}", average),
+                total_activation_time: format!("{:#?}", total),
+                invocations,
+                fill_color: format!("{}", fill_color),
+                text_color: format!("{}", text_color),
+                workers: worker_activity.get(&id).map_or(&[][..], Vec::as_slice),
+                comparison: None,
+                parent: ui::parent_addr(addr),
+                children,
+                rollup,
+            })
+        })
+        .collect();
+
+    let mut channel_records: HashMap<ChannelId, usize, XXHasher> = HashMap::default();
+    for &((worker, channel), messages) in data.channel_messages.iter() {
+        if args.renders_worker(worker) {
+            *channel_records.entry(channel).or_insert(0) += messages.records_sent;
+        }
+    }
+
+    let channel_backlogs: HashMap<ChannelId, ChannelBacklog, XXHasher> =
+        data.channel_backlogs.iter().copied().collect();
+    let channel_latencies: HashMap<ChannelId, ChannelLatency, XXHasher> =
+        data.channel_latencies.iter().copied().collect();
+
+    let html_edges: Vec<_> = data
+        .edges
+        .iter()
+        .filter_map(|(source, channel, target)| {
+            let mut workers: Vec<_> = worker_activity
+                .get(&source.id)
+                .into_iter()
+                .chain(worker_activity.get(&target.id))
+                .flatten()
+                .map(|activity| activity.worker)
+                .collect();
+            workers.sort_unstable();
+            workers.dedup();
+
+            // Channels that none of the rendered workers touched are left out
+            if args.workers_filter.is_some() && workers.is_empty() {
+                return None;
+            }
+
+            Some(ui::Edge {
+                src: channel.source_addr(),
+                dest: channel.target_addr(),
+                channel_id: channel.channel_id(),
+                edge_kind: match channel {
+                    Channel::Normal { .. } => EdgeKind::Normal,
+                    Channel::ScopeCrossing { .. } => EdgeKind::Crossing,
+                },
+                workers,
+                records_sent: channel_records
+                    .get(&channel.channel_id())
+                    .copied()
+                    .unwrap_or(0),
+                peak_backlog: channel_backlogs
+                    .get(&channel.channel_id())
+                    .map(|backlog| backlog.peak_records),
+                sustained_backlog: channel_backlogs
+                    .get(&channel.channel_id())
+                    .map_or(false, ChannelBacklog::is_sustained),
+                latency: channel_latencies.get(&channel.channel_id()).copied(),
+            })
+        })
+        .collect();
+
+    let mut palette_colors = Vec::with_capacity(10);
+    let mut pos = 0.0;
+    for _ in 0..10 {
+        palette_colors.push(format!("{}", Color::new(args.palette.eval_continuous(pos))));
+        pos += 0.1;
+    }
+
+    let output = OutputContext {
+        args,
+        data: &data,
+        target_runs: &target_runs,
+        dropped_events,
+        custom_events: &custom_events,
+        workers: &rendered_workers,
+        name_lookup: &name_lookup,
+        addr_lookup: &addr_lookup,
+        nodes: &html_nodes,
+        subgraphs: &html_subgraphs,
+        edges: &html_edges,
+        palette_colors: &palette_colors,
+        arrangement_map: &arrangement_map,
+        activations_map: &activations_map,
+        agg_operator_stats: &agg_operator_stats,
+        agg_arrangement_stats: &agg_arrangement_stats,
+        agg_activations_map: &agg_activations_map,
+        spline_levels: &spline_levels,
+        group_stats: &group_stats,
+        progress_channels: &progress_channels,
+        baseline,
+    };
+
+    // The built-in outputs are written before any registered by embedders
+    output_sink::write_builtin_outputs(&output)?;
+    for mut sink in output_sinks {
+        output_sink::write_output(sink.as_mut(), &output)?;
+    }
+
+    let limit_warnings = analysis::limit_warnings(args, &data).len();
+    if limit_warnings != 0 {
+        tracing::warn!(
+            "operators went over the warning limits {} times",
+            limit_warnings
+        );
+
+        if args.isnt_quiet() {
+            eprintln!(
+                "warning: operators went over `--warn-activation-over` or \
+                `--warn-arrangement-over` {} times, see the report for details",
+                limit_warnings,
+            );
+        }
+    }
+
+    if args.isnt_quiet() {
+        println!("Finished in {}", HumanDuration(start_time.elapsed()));
+    }
+
+    let extraction_elapsed = extraction_start_time.elapsed();
+    tracing::info!(
+        elapsed = ?extraction_elapsed,
+        "spent {} within data extraction",
+        HumanDuration(extraction_elapsed),
+    );
+
+    // Fail before serving the graph so that ci runs don't wait on a ctrl+c
+    if args.fail_on_warnings && limit_warnings != 0 {
+        anyhow::bail!(
+            "operators went over the warning limits {} times with `--fail-on-warnings` set",
+            limit_warnings,
+        );
+    }
+
+    if let Some(server) = live_server {
+        // Keep `/graph.svg` up to date with the final stats
+        server.publish(&data, &target_runs, dropped_events.unwrap_or(0));
+
+        if ui::graph_enabled() {
+            server.rendered(&args.output_dir);
+        }
+
+        if args.isnt_quiet() {
+            println!(
+                "Serving the graph on http://{}, press ctrl+c to stop...",
+                server.address(),
+            );
+        }
+        server.serve_until(&interrupted);
+    }
+
+    Ok(())
+}
@@ -1,22 +1,223 @@
 #![allow(dead_code)]
 
+use crate::{args::Args, dataflow::utils::HumanDuration};
 use anyhow::{Context, Result};
+#[cfg(not(target_arch = "wasm32"))]
 use crossterm::{
     cursor::{MoveDown, MoveToColumn, RestorePosition, SavePosition, Show},
     queue,
     style::Print,
     terminal::{Clear, ClearType},
 };
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::{self, Builder, JoinHandle};
 use std::{
     io::{self, Stdout, Write},
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
-    thread::{self, Builder, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+/// How often the capture status line is redrawn
+const STATUS_LINE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The kinds of log streams that events are ingested from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventSource {
+    Timely,
+    Differential,
+    Progress,
+}
+
+impl EventSource {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Timely => "timely",
+            Self::Differential => "differential",
+            Self::Progress => "progress",
+        }
+    }
+}
+
+/// The number of events ingested from each kind of log stream, shared
+/// between the timely workers and the status line
+#[derive(Debug, Default)]
+pub struct CaptureStatus {
+    timely_events: AtomicUsize,
+    differential_events: AtomicUsize,
+    progress_events: AtomicUsize,
+}
+
+impl CaptureStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, source: EventSource, events: usize) {
+        self.events(source).fetch_add(events, Ordering::Relaxed);
+    }
+
+    fn events(&self, source: EventSource) -> &AtomicUsize {
+        match source {
+            EventSource::Timely => &self.timely_events,
+            EventSource::Differential => &self.differential_events,
+            EventSource::Progress => &self.progress_events,
+        }
+    }
+}
+
+/// A single line of terminal output that keeps the user up to date on the
+/// capture while events are still coming in
+pub struct StatusLine {
+    stdout: Stdout,
+    status: Arc<CaptureStatus>,
+    sources: Vec<EventSource>,
+    connections: usize,
+    started: Instant,
+    last_drawn: Instant,
+    last_events: Vec<usize>,
+    drawn: bool,
+}
+
+impl StatusLine {
+    /// Creates the status line, returns `None` if ddshow's output is quiet
+    pub fn new(args: &Args, status: Arc<CaptureStatus>, connections: usize) -> Option<Self> {
+        if args.is_quiet() {
+            return None;
+        }
+
+        let mut sources = vec![EventSource::Timely];
+        if args.differential_enabled {
+            sources.push(EventSource::Differential);
+        }
+        if args.progress_enabled {
+            sources.push(EventSource::Progress);
+        }
+
+        let now = Instant::now();
+        Some(Self {
+            stdout: io::stdout(),
+            status,
+            last_events: vec![0; sources.len()],
+            sources,
+            connections,
+            started: now,
+            last_drawn: now,
+            drawn: false,
+        })
+    }
+
+    /// Redraws the status line if it hasn't been drawn recently
+    pub fn tick(&mut self) -> Result<()> {
+        let since_drawn = self.last_drawn.elapsed();
+        if since_drawn < STATUS_LINE_INTERVAL {
+            return Ok(());
+        }
+
+        let mut rates = Vec::with_capacity(self.sources.len());
+        for (&source, last_events) in self.sources.iter().zip(self.last_events.iter_mut()) {
+            let events = self.status.events(source).load(Ordering::Relaxed);
+            let rate = events.saturating_sub(*last_events) as f64 / since_drawn.as_secs_f64();
+
+            rates.push((source, rate));
+            *last_events = events;
+        }
+
+        let line = format_status(
+            &rates,
+            self.connections,
+            self.started.elapsed(),
+            resident_memory(),
+        );
+        redraw_line(&mut self.stdout, &line)?;
+
+        self.last_drawn = Instant::now();
+        self.drawn = true;
+
+        Ok(())
+    }
+
+    /// Clears the status line so that later output starts on a fresh line
+    pub fn finish(mut self) -> Result<()> {
+        if self.drawn {
+            redraw_line(&mut self.stdout, "")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the contents of the terminal's current line
+#[cfg(not(target_arch = "wasm32"))]
+fn redraw_line(stdout: &mut Stdout, line: &str) -> Result<()> {
+    queue!(
+        stdout,
+        Print('\r'),
+        Clear(ClearType::CurrentLine),
+        Print(line),
+    )
+    .context("failed to queue status line")?;
+    stdout.flush().context("failed to flush stdout")
+}
+
+/// There's no terminal to draw to on wasm
+#[cfg(target_arch = "wasm32")]
+fn redraw_line(_stdout: &mut Stdout, _line: &str) -> Result<()> {
+    Ok(())
+}
+
+fn format_status(
+    rates: &[(EventSource, f64)],
+    connections: usize,
+    elapsed: Duration,
+    memory: Option<usize>,
+) -> String {
+    let rates = rates
+        .iter()
+        .map(|(source, rate)| format!("{}: {:.0} events/s", source.name(), rate))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Sub-second precision just makes the line flicker
+    let elapsed = HumanDuration(Duration::from_secs(elapsed.as_secs()));
+    let mut line = format!(
+        "{} | {} connection{} | {}",
+        rates,
+        connections,
+        if connections == 1 { "" } else { "s" },
+        elapsed,
+    );
+
+    if let Some(memory) = memory {
+        line.push_str(&format!(" | {:.1} MiB", memory as f64 / (1024.0 * 1024.0)));
+    }
+
+    line
+}
+
+/// The process's resident memory in bytes, only available on linux
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<usize>()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<usize> {
+    None
+}
+
 #[derive(Debug, Default)]
 pub struct StatusSources {
     timely_events_read: Box<[AtomicUsize]>,
@@ -35,6 +236,7 @@ pub struct StatusData {
     source_workers: usize,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub struct UserInterface {
     stdout: Stdout,
     tick_rate: Duration,
@@ -42,6 +244,7 @@ pub struct UserInterface {
     status_data: StatusData,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl UserInterface {
     pub fn new(
         tick_rate: Duration,
@@ -137,6 +340,7 @@ const SPINNERS: &[&str] = &[
 ];
 
 #[test]
+#[cfg(not(target_arch = "wasm32"))]
 fn tui_test() {
     let tick_rate = Duration::from_millis(200);
     let sources = Arc::new(StatusSources {
@@ -161,3 +365,21 @@ fn tui_test() {
     let user_interface = UserInterface::new(tick_rate, sources, status_data).unwrap();
     user_interface.spawn().join().unwrap().unwrap();
 }
+
+#[test]
+fn status_line_contents() {
+    let line = format_status(
+        &[
+            (EventSource::Timely, 1520.4),
+            (EventSource::Differential, 0.0),
+        ],
+        2,
+        Duration::from_millis(65_400),
+        Some(3 * 1024 * 1024),
+    );
+
+    assert_eq!(
+        line,
+        "timely: 1520 events/s, differential: 0 events/s | 2 connections | 1m 5s | 3.0 MiB",
+    );
+}
@@ -0,0 +1,186 @@
+//! Spawning and supervising the target computation when ddshow
+//! is invoked as `ddshow [options] -- <command>`
+
+use crate::args::Args;
+use anyhow::{Context, Result};
+use std::{
+    io::ErrorKind,
+    process::{Child, Command, ExitStatus},
+};
+#[cfg(feature = "network")]
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+/// The interval to poll the target process at while waiting for it to connect
+#[cfg(feature = "network")]
+const CONNECTION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A target computation that was spawned by ddshow
+#[derive(Debug)]
+pub struct TargetProcess {
+    child: Child,
+    command: String,
+    status: Option<ExitStatus>,
+}
+
+impl TargetProcess {
+    /// Spawns the target command given by the user (if there is one), pointing
+    /// its timely, differential and progress loggers at ddshow's listeners
+    ///
+    /// Note that this should only be called *after* all listeners have been
+    /// bound, otherwise the target will fail to connect to us
+    pub fn spawn(args: &Args) -> Result<Option<Self>> {
+        let (program, program_args) = match args.target_command.split_first() {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        let command = args.target_command.join(" ");
+
+        let mut target = Command::new(program);
+        target
+            .args(program_args)
+            .env("TIMELY_WORKER_LOG_ADDR", args.timely_address.to_string());
+
+        if args.differential_enabled {
+            target.env(
+                "DIFFERENTIAL_LOG_ADDR",
+                args.differential_address.to_string(),
+            );
+        } else {
+            target.env_remove("DIFFERENTIAL_LOG_ADDR");
+        }
+
+        if args.progress_enabled {
+            target.env(
+                "TIMELY_PROGRESS_LOG_ADDR",
+                args.progress_address.to_string(),
+            );
+        } else {
+            target.env_remove("TIMELY_PROGRESS_LOG_ADDR");
+        }
+
+        for stream in args.custom_streams.iter() {
+            target.env(stream.env_var(), stream.address.to_string());
+        }
+
+        tracing::info!(
+            command = %command,
+            timely_address = %args.timely_address,
+            differential_address = ?args.differential_enabled.then(|| args.differential_address),
+            progress_address = ?args.progress_enabled.then(|| args.progress_address),
+            "spawning target process",
+        );
+
+        let child = target
+            .spawn()
+            .with_context(|| format!("failed to spawn target process `{}`", command))?;
+
+        if args.isnt_quiet() {
+            println!("Started target process `{}` (pid {})", command, child.id());
+        }
+
+        Ok(Some(Self {
+            child,
+            command,
+            status: None,
+        }))
+    }
+
+    /// Returns the target's exit status if it has exited
+    pub fn try_status(&mut self) -> Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self
+                .child
+                .try_wait()
+                .with_context(|| format!("failed to poll target process `{}`", self.command))?;
+        }
+
+        Ok(self.status)
+    }
+
+    /// Waits for the target process to exit, killing it if `kill` is true
+    /// and it's still running
+    pub fn shutdown(mut self, kill: bool) -> Result<ExitStatus> {
+        if let Some(status) = self.try_status()? {
+            return Ok(status);
+        }
+
+        if kill {
+            tracing::info!(command = %self.command, "killing target process");
+
+            // The process could have exited between our check and now
+            if let Err(err) = self.child.kill() {
+                if err.kind() != ErrorKind::InvalidInput {
+                    return Err(err).with_context(|| {
+                        format!("failed to kill target process `{}`", self.command)
+                    });
+                }
+            }
+        }
+
+        let status = self
+            .child
+            .wait()
+            .with_context(|| format!("failed to wait on target process `{}`", self.command))?;
+        tracing::info!(command = %self.command, status = %status, "target process exited");
+
+        Ok(status)
+    }
+
+    /// Returns the command used to start the target process
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+}
+
+/// Accepts a single connection from the given listener, erroring out if the
+/// target process exits before connecting to us
+#[cfg(feature = "network")]
+pub fn accept_connection(
+    listener: &TcpListener,
+    target: &mut Option<TargetProcess>,
+) -> Result<TcpStream> {
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let (socket, _) = listener
+                .accept()
+                .context("failed to accept socket connection")?;
+
+            return Ok(socket);
+        }
+    };
+
+    listener
+        .set_nonblocking(true)
+        .context("failed to set listener to non-blocking mode")?;
+
+    let socket = loop {
+        match listener.accept() {
+            Ok((socket, _)) => break socket,
+
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                if let Some(status) = target.try_status()? {
+                    anyhow::bail!(
+                        "the target process `{}` exited with {} before connecting to ddshow",
+                        target.command(),
+                        status,
+                    );
+                }
+
+                thread::sleep(CONNECTION_POLL_INTERVAL);
+            }
+
+            Err(err) => return Err(err).context("failed to accept socket connection"),
+        }
+    };
+
+    listener
+        .set_nonblocking(false)
+        .context("failed to set listener to blocking mode")?;
+
+    Ok(socket)
+}
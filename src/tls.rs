@@ -0,0 +1,220 @@
+//! TLS termination for the log listeners, enabled by passing
+//! `--tls-cert` and `--tls-key`
+//!
+//! Requires the `tls` feature, without it plain tcp is the only option
+
+use crate::args::Args;
+#[cfg(feature = "tls")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "tls")]
+use rustls::{Certificate, PrivateKey, ServerConfig, ServerConnection, StreamOwned};
+#[cfg(feature = "tls")]
+use rustls_pemfile::Item;
+use std::{
+    fmt::{self, Debug},
+    io::{self, Read},
+    net::TcpStream,
+};
+#[cfg(feature = "tls")]
+use std::{fs::File, io::BufReader, path::Path, sync::Arc, time::Duration};
+
+/// The longest we'll wait on a client to finish its TLS handshake
+#[cfg(feature = "tls")]
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The TLS config shared by all log listeners
+#[cfg(feature = "tls")]
+pub type TlsConfig = Arc<ServerConfig>;
+
+/// TLS can never be configured without the `tls` feature
+#[cfg(not(feature = "tls"))]
+#[derive(Debug, Clone)]
+pub enum TlsConfig {}
+
+/// Builds the TLS config used for all log listeners if the user gave us
+/// a certificate and private key
+pub fn server_config(args: &Args) -> Result<Option<TlsConfig>> {
+    let (cert_path, key_path) = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) => return Ok(None),
+        _ => anyhow::bail!("both `--tls-cert` and `--tls-key` must be given to enable tls"),
+    };
+
+    #[cfg(not(feature = "tls"))]
+    {
+        let _ = (cert_path, key_path);
+        anyhow::bail!("ddshow was built without tls support, rebuild it with the `tls` feature");
+    }
+
+    #[cfg(feature = "tls")]
+    {
+        let certs = load_certs(cert_path)?;
+        let key = load_private_key(key_path)?;
+
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("invalid tls certificate or private key")?;
+        tracing::debug!(
+            cert = %cert_path.display(),
+            key = %key_path.display(),
+            "loaded tls config",
+        );
+
+        Ok(Some(Arc::new(config)))
+    }
+}
+
+#[cfg(feature = "tls")]
+fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let file = File::open(path)
+        .with_context(|| anyhow::anyhow!("failed to open tls certificate '{}'", path.display()))?;
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| anyhow::anyhow!("failed to read tls certificate '{}'", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificates were found within '{}'", path.display());
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+#[cfg(feature = "tls")]
+fn load_private_key(path: &Path) -> Result<PrivateKey> {
+    let file = File::open(path)
+        .with_context(|| anyhow::anyhow!("failed to open tls private key '{}'", path.display()))?;
+
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| anyhow::anyhow!("failed to read tls private key '{}'", path.display()))?;
+
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .with_context(|| anyhow::anyhow!("no private key was found within '{}'", path.display()))
+}
+
+/// A connection to one of the target's loggers, optionally wrapped in TLS
+pub enum LogStream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<StreamOwned<ServerConnection, TcpStream>>),
+}
+
+impl LogStream {
+    /// Wraps a freshly accepted socket
+    #[cfg(not(feature = "tls"))]
+    pub fn accept(socket: TcpStream, tls: Option<&TlsConfig>) -> Result<Self> {
+        match tls {
+            Some(config) => match *config {},
+            None => Ok(Self::Plain(socket)),
+        }
+    }
+
+    /// Wraps a freshly accepted socket, performing the TLS handshake
+    /// if a tls config was given
+    ///
+    /// The socket must still be in blocking mode
+    #[cfg(feature = "tls")]
+    pub fn accept(mut socket: TcpStream, tls: Option<&TlsConfig>) -> Result<Self> {
+        let config = match tls {
+            Some(config) => config,
+            None => return Ok(Self::Plain(socket)),
+        };
+
+        let mut connection = ServerConnection::new(config.clone())
+            .context("failed to create tls server connection")?;
+
+        // Don't let a client that never speaks tls hang us forever
+        let previous_timeout = socket
+            .read_timeout()
+            .context("failed to get socket read timeout")?;
+        socket
+            .set_read_timeout(Some(TLS_HANDSHAKE_TIMEOUT))
+            .context("failed to set socket read timeout")?;
+
+        while connection.is_handshaking() {
+            connection
+                .complete_io(&mut socket)
+                .with_context(|| anyhow::anyhow!("tls handshake with {:?} failed", socket))?;
+        }
+
+        socket
+            .set_read_timeout(previous_timeout)
+            .context("failed to set socket read timeout")?;
+        tracing::debug!(socket = ?socket, "completed tls handshake");
+
+        Ok(Self::Tls(Box::new(StreamOwned::new(connection, socket))))
+    }
+
+    /// Returns the underlying tcp socket
+    pub fn socket(&self) -> &TcpStream {
+        match self {
+            Self::Plain(socket) => socket,
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => &stream.sock,
+        }
+    }
+
+    /// Returns `true` if the stream is encrypted
+    pub const fn is_tls(&self) -> bool {
+        #[cfg(feature = "tls")]
+        {
+            matches!(self, Self::Tls(_))
+        }
+
+        #[cfg(not(feature = "tls"))]
+        {
+            false
+        }
+    }
+}
+
+impl Read for LogStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(socket) => socket.read(buf),
+            // Peers that hang up without sending `close_notify` produce an
+            // unexpected eof error, treat it the same as a plain socket closing
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => match stream.read(buf) {
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    tracing::debug!(socket = ?stream.sock, "tls peer closed without close_notify");
+                    Ok(0)
+                }
+                result => result,
+            },
+        }
+    }
+}
+
+impl Debug for LogStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogStream")
+            .field("socket", self.socket())
+            .field("is_tls", &self.is_tls())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::server_config;
+    use crate::args::Args;
+    use std::path::PathBuf;
+
+    #[test]
+    fn tls_is_opt_in() {
+        assert!(server_config(&Args::default()).unwrap().is_none());
+
+        let args = Args {
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            ..Default::default()
+        };
+        assert!(server_config(&args).is_err());
+    }
+}
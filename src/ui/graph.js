@@ -38,6 +38,7 @@
  *     id: number;
  *     addr: number[];
  *     name: string;
+ *     full_name: string | null;
  *     max_activation_time: string;
  *     min_activation_time: string;
  *     average_activation_time: string;
@@ -46,11 +47,37 @@
  *     fill_color: string;
  *     text_color: string;
  *     activation_durations: ActivationDuration[];
+ *     activation_percentiles: ActivationPercentiles | null;
+ *     activation_histogram: HistogramBucket[];
+ *     activation_rates: ActivationRate[];
+ *     source_location: SourceLocation | null;
+ *     source_url: string | null;
  *     max_arrangement_size: number | null;
  *     min_arrangement_size: number | null;
+ *     average_batch_size: number | null;
+ *     average_merge_time: string | null;
+ *     trace_shares: number | null;
+ *     workers: WorkerActivity[];
+ *     comparison: string | null;
  * }} RawNode
  *
  * @typedef {{ activation_time: number, activated_at: number }} ActivationDuration
+ *
+ * @typedef {{ p50: RustDuration, p90: RustDuration, p99: RustDuration, p999: RustDuration }} ActivationPercentiles
+ *
+ * @typedef {{ lower: RustDuration, upper: RustDuration | null, count: number }} HistogramBucket
+ *
+ * @typedef {{ window: number, activations_per_sec: number, busy_per_sec: number }} ActivationRate
+ *
+ * @typedef {{ file: string, line: number, column: number }} SourceLocation
+ *
+ * @typedef {{
+ *     worker: number;
+ *     total_ns: number;
+ *     max_ns: number;
+ *     min_ns: number;
+ *     invocations: number;
+ * }} WorkerActivity
  * 
  * @typedef {{
  *    id: number;
@@ -63,14 +90,39 @@
  *    invocations: number;
  *    fill_color: string;
  *    text_color: string;
+ *    workers: WorkerActivity[];
+ *    comparison: string | null;
+ *    parent: number[] | null;
+ *    children: number[][];
+ *    rollup: SubgraphRollup;
  * }} Subgraph
+ *
+ * @typedef {{
+ *     total_ns: number;
+ *     invocations: number;
+ *     operators: number;
+ * }} SubgraphRollup
  * 
  * @typedef {{
  *     src: number[];
  *     dest: number[];
  *     channel_id: number;
  *     edge_kind: EdgeKind;
+ *     workers: number[];
+ *     records_sent: number;
+ *     peak_backlog: number | null;
+ *     sustained_backlog: boolean;
+ *     latency: ChannelLatency | null;
  * }} Edge
+ *
+ * @typedef {{
+ *     messages: number;
+ *     min: RustDuration;
+ *     p50: RustDuration;
+ *     p90: RustDuration;
+ *     p99: RustDuration;
+ *     max: RustDuration;
+ * }} ChannelLatency
  * 
  * @typedef {"Normal" | "Crossing"} EdgeKind
  * 
@@ -82,7 +134,34 @@
  *     collapsed_events: number;
  * }} TimelineEvent
  * 
- * @typedef {Activation | Application | "Parked" | "Input" | "Message" | "Progress" | Merge} EventKind
+ * @typedef {Activation | Application | "Parked" | "Input" | "Message" | "Progress" | "MinorActivity" | Merge} EventKind
+ *
+ * @typedef {{
+ *     worker: number;
+ *     tier: number;
+ *     category: string;
+ *     start_time: number;
+ *     busy_time: number;
+ *     events: number;
+ * }} TimelineBucket
+ *
+ * @typedef {{
+ *     id: number;
+ *     name: string;
+ *     addr: number[];
+ *     dataflow: number;
+ *     activations: [number, number, number][];
+ * }} OperatorLane
+ *
+ * @typedef {{
+ *     worker: number;
+ *     id: number;
+ *     name: string;
+ *     addr: number[];
+ *     is_dataflow: boolean;
+ *     created_at: number;
+ *     dropped_at: number;
+ * }} OperatorLifespan
  * 
  * @typedef {{ OperatorActivation: { operator_id: number } }} Activation
  * @typedef {{ Application: { id: number } }} Application
@@ -101,6 +180,89 @@
  *     input_messages: [number, [number, number]][];
  *     output_messages: [number, [number, number]][];
  * }} OperatorProgress
+ *
+ * @typedef {{
+ *     name: string;
+ *     members: number[][];
+ * }} OperatorGroup
+ *
+ * @typedef {{ secs: number, nanos: number }} RustDuration
+ *
+ * @typedef {{
+ *     run: number;
+ *     started_at: RustDuration;
+ *     ended_at: RustDuration;
+ *     connections: number;
+ * }} TargetRun
+ *
+ * @typedef {{
+ *     worker: number;
+ *     scope_addr: number[];
+ *     channel: number;
+ *     sent: number;
+ *     received: number;
+ *     message_updates: number;
+ *     capability_updates: number;
+ * }} ProgressChannel
+ *
+ * @typedef {[[number, number[]], [RustDuration, string]]} FrontierRelease
+ *
+ * @typedef {[number[], [RustDuration, string]]} DataflowFrontier
+ *
+ * @typedef {[[number, number], number]} WorkerExchange
+ *
+ * @typedef {[[number, string], number]} WorkerEventKind
+ *
+ * @typedef {[[number, number], [RustDuration, [RustDuration, RustDuration]]]} ActivationAnomaly
+ *
+ * @typedef {{ worker: number, addr: number[], sizes: [number, number][] }} ArrangementSizes
+ *
+ * @typedef {[number[], {
+ *     timestamp: string;
+ *     first_seen: RustDuration;
+ *     completed: RustDuration;
+ *     latency: RustDuration;
+ * }]} EpochLatency
+ *
+ * @typedef {[number, {
+ *     runtime: RustDuration;
+ *     busy: RustDuration;
+ *     parked: RustDuration;
+ *     idle: RustDuration;
+ *     progress: RustDuration;
+ * }]} WorkerUtilization
+ *
+ * @typedef {{
+ *     stream: string;
+ *     worker: number;
+ *     start: RustDuration;
+ *     duration: RustDuration;
+ *     label: string;
+ * }} CustomEvent
+ *
+ * @typedef {{
+ *     id: number;
+ *     worker: number;
+ *     name: string;
+ *     addr: number[];
+ *     is_subgraph: boolean;
+ *     text: string;
+ * }} SearchEntry
+ *
+ * @typedef {{
+ *     addr: number[];
+ *     x: number;
+ *     y: number;
+ *     width: number;
+ *     height: number;
+ * }} NodePosition
+ *
+ * @typedef {{
+ *     hash: number;
+ *     width: number;
+ *     height: number;
+ *     nodes: NodePosition[];
+ * }} GraphLayout
  * #}
  */
 
@@ -113,27 +275,89 @@ const dagreD3 = undefined;
 const vega = undefined;
 /** @type {any} */
 const vegaEmbed = undefined;
+/** @type {any} */
+const ddshow_data = undefined;
 // #}
 
 /** @type {RawNode[]} */
-const raw_nodes = {{ nodes | json_encode() }};
+const raw_nodes = ddshow_data.nodes;
 
 /** @type {Subgraph[]} */
-const raw_subgraphs = {{ subgraphs | json_encode() }};
+const raw_subgraphs = ddshow_data.subgraphs;
 
 /** @type {Edge[]} */
-const raw_edges = {{ edges | json_encode() }};
+const raw_edges = ddshow_data.edges;
 
 /** @type {string[]} */
 const palette_colors = {{ palette_colors | json_encode() }};
 
 /** @type {TimelineEvent[]} */
-const timeline_events = {{ timeline_events | json_encode() }};
+const timeline_events = ddshow_data.timeline_events;
+
+/** @type {number[]} */
+const timeline_tier_widths = {{ timeline_tier_widths | json_encode() }};
+
+/** @type {TimelineBucket[]} */
+const timeline_buckets = ddshow_data.timeline_buckets;
+
+/** @type {OperatorLane[]} */
+const operator_lanes = ddshow_data.operator_lanes;
+
+/** @type {OperatorLifespan[]} */
+const operator_lifespans = ddshow_data.operator_lifespans;
+
+/** @type {WorkerUtilization[]} */
+const worker_utilization = {{ worker_utilization | json_encode() }};
+
+/** @type {WorkerExchange[]} */
+const exchange_matrix = {{ exchange_matrix | json_encode() }};
+
+/** @type {WorkerEventKind[]} */
+const event_kinds = {{ event_kinds | json_encode() }};
+
+/** @type {ActivationAnomaly[]} */
+const activation_anomalies = {{ activation_anomalies | json_encode() }};
+
+/** @type {ArrangementSizes[]} */
+const arrangement_sizes = ddshow_data.arrangement_sizes;
 
 /** @type {OperatorShape[]} */
-const operator_shapes = {{ operator_shapes | json_encode() }};
+const operator_shapes = ddshow_data.operator_shapes;
+
+const vega_data = ddshow_data.vega_data;
+
+/** @type {OperatorGroup[]} */
+const operator_groups = {{ operator_groups | json_encode() }};
+
+/** @type {TargetRun[]} */
+const target_runs = {{ target_runs | json_encode() }};
+
+/** @type {ProgressChannel[]} */
+const progress_channels = ddshow_data.progress_channels;
+
+/** @type {FrontierRelease[]} */
+const frontier_progression = ddshow_data.frontier_progression;
+
+/** @type {DataflowFrontier[]} */
+const dataflow_frontiers = ddshow_data.dataflow_frontiers;
+
+/** @type {EpochLatency[]} */
+const epoch_latencies = ddshow_data.epoch_latencies;
 
-const vega_data = {{ vega_data | json_encode() }};
+/** @type {CustomEvent[]} */
+const custom_events = ddshow_data.custom_events;
+
+/** @type {SearchEntry[]} */
+const search_index = ddshow_data.search_index;
+
+/** @type {GraphLayout} */
+const graph_layout = ddshow_data.graph_layout;
+
+/** @type {number[]} */
+const rendered_workers = {{ workers | json_encode() }};
+
+/** @type {"activation-time" | "invocations" | "arrangement-size" | "idle-time"} */
+const color_by = {{ color_by | json_encode() }};
 
 
 const dataflow_svg = d3.select("#dataflow-graph");
@@ -148,7 +372,138 @@ dataflow_svg.call(zoom);
 const graph = new dagreD3.graphlib.Graph({ compound: true });
 graph.setGraph({ nodesep: 50, ranksep: 50 });
 
-const render = new dagreD3.render();
+/** @type {Map<string, NodePosition>} The positions ddshow laid the graph's nodes out at */
+const precomputed_positions = new Map(
+    graph_layout.nodes.map(position => [format_addr(position.addr), position]),
+);
+
+/**
+ * Places the graph's nodes at the positions ddshow computed while rendering,
+ * which only cover the fully expanded graph
+ *
+ * @param {any} g The graph to lay out
+ */
+const apply_precomputed_layout = g => {
+    for (const addr of g.nodes()) {
+        const node = g.node(addr);
+        const position = precomputed_positions.get(addr);
+
+        node.x = position.x;
+        node.y = position.y;
+
+        // Operators keep the size of their rendered label, subgraphs wrap their contents
+        if (g.children(addr).length !== 0) {
+            node.width = position.width;
+            node.height = position.height;
+        }
+    }
+
+    for (const edge_id of g.edges()) {
+        const [src, dest, edge] = [g.node(edge_id.v), g.node(edge_id.w), g.edge(edge_id)];
+        const middle = { x: (src.x + dest.x) / 2, y: (src.y + dest.y) / 2 };
+
+        edge.x = middle.x;
+        edge.y = middle.y;
+        edge.points = [{ x: src.x, y: src.y }, middle, { x: dest.x, y: dest.y }];
+    }
+
+    g.graph().width = graph_layout.width;
+    g.graph().height = graph_layout.height;
+};
+
+const dagre_render = new dagreD3.render();
+
+/**
+ * Selects the group with the given class within `root`, creating it if it doesn't exist yet
+ *
+ * @param {any} root The selection to search within
+ * @param {string} name The group's class
+ * @returns {any} The group's selection
+ */
+const select_or_create_group = (root, name) => {
+    const group = root.select(`g.${name}`);
+    return group.empty() ? root.append("g").attr("class", name) : group;
+};
+
+/**
+ * Renders the graph at the positions ddshow laid it out at, following the same
+ * steps as dagre-d3's renderer minus the call into dagre's layout
+ *
+ * @param {any} svg The svg group the graph is rendered into
+ * @param {any} g The graph to render
+ * @returns {boolean} Whether the graph was rendered, which it's only
+ *                    done when every node has a precomputed position
+ */
+const render_precomputed = (svg, g) => {
+    if (collapsed_subgraphs.size !== 0 || !g.nodes().every(addr => precomputed_positions.has(addr))) {
+        return false;
+    }
+
+    // Fill in the defaults that dagre-d3 gives nodes and edges
+    const padded = new Map();
+    for (const addr of g.nodes()) {
+        const node = g.node(addr);
+        for (const side of ["paddingLeft", "paddingRight", "paddingTop", "paddingBottom"]) {
+            node[side] = Number(node[side] === undefined ? 10 : node[side]);
+        }
+        node.rx = node.rx || 0;
+        node.ry = node.ry || 0;
+        node.shape = node.shape || "rect";
+
+        padded.set(addr, { width: node.width, height: node.height });
+    }
+    for (const edge_id of g.edges()) {
+        const edge = g.edge(edge_id);
+        edge.label = edge.label || "";
+        edge.arrowhead = edge.arrowhead || "normal";
+        edge.curve = edge.curve || d3.curveLinear;
+    }
+
+    const output = select_or_create_group(svg, "output");
+    const clusters_group = select_or_create_group(output, "clusters");
+    const edge_paths_group = select_or_create_group(output, "edgePaths");
+    const edge_labels = dagre_render.createEdgeLabels()(select_or_create_group(output, "edgeLabels"), g);
+    const nodes = dagre_render.createNodes()(select_or_create_group(output, "nodes"), g, dagre_render.shapes());
+
+    apply_precomputed_layout(g);
+
+    const translate = ({ x, y }) => `translate(${x},${y})`;
+    nodes.style("opacity", 1).attr("transform", addr => translate(g.node(addr)));
+    edge_labels
+        .style("opacity", 1)
+        .attr("transform", edge_id => g.edge(edge_id).x === undefined ? "" : translate(g.edge(edge_id)));
+    dagre_render.createEdgePaths()(edge_paths_group, g, dagre_render.arrows());
+
+    const clusters = dagre_render.createClusters()(clusters_group, g);
+    clusters.style("opacity", 1).attr("transform", addr => translate(g.node(addr)));
+    clusters.selectAll("rect")
+        .attr("width", addr => g.node(addr).width)
+        .attr("height", addr => g.node(addr).height)
+        .attr("x", addr => -g.node(addr).width / 2)
+        .attr("y", addr => -g.node(addr).height / 2);
+
+    // Restore the sizes nodes were given so that they're measured again on the next render
+    for (const [addr, size] of padded) {
+        const node = g.node(addr);
+        for (const dimension of ["width", "height"]) {
+            if (size[dimension] === undefined) {
+                delete node[dimension];
+            } else {
+                node[dimension] = size[dimension];
+            }
+        }
+    }
+
+    return true;
+};
+
+// Laying out large graphs with dagre takes ages, so it's only used once
+// subgraphs have been collapsed or expanded
+const render = (svg, g) => {
+    if (!render_precomputed(svg, g)) {
+        dagre_render(svg, g);
+    }
+};
 
 /** @type {Set<string>} */
 let error_nodes = new Set();
@@ -192,197 +547,746 @@ const create_error_node = target_addr => {
 
 const slash_regexp = new RegExp("\\\\", "g");
 
+for (const operator of [...raw_subgraphs, ...raw_nodes]) {
+    operator_names.set(operator.id, operator.name);
+}
 for (const subgraph of raw_subgraphs) {
-    const subgraph_name = subgraph.name;
-    operator_names.set(subgraph.id, subgraph_name);
+    subgraph_addrs.add(format_addr(subgraph.addr));
+}
 
-    const subgraph_addr = format_addr(subgraph.addr);
-    operator_addrs.add(subgraph_addr);
-    subgraph_addrs.add(subgraph_addr);
+/** @type {Set<string>} The addresses of every collapsed subgraph */
+const collapsed_subgraphs = new Set();
 
-    graph.setNode(
-        subgraph_addr,
-        {
-            label: `${subgraph_name.replace(slash_regexp, "\\\\")} @ ${subgraph.id}, ${subgraph_addr}`,
-            style: "fill: #EEEEEE; stroke-dasharray: 5, 2;",
-            clusterLabelPos: "top",
-            data: { kind: "Subgraph", ...subgraph },
-        },
-    );
+// Edges are scaled between these widths by the number of records sent over them
+const [min_edge_width, max_edge_width] = [1.5, 8];
+/** The color of channels that had records waiting to be received for most of their lifetime */
+const BACKLOG_COLOR = "#D62728";
+/** The color of activations that took far longer than their operator's median */
+const ANOMALY_COLOR = "#FF7F0E";
+
+const max_records_sent = raw_edges.reduce((max, edge) => Math.max(max, edge.records_sent), 0);
+
+/**
+ * @param {number} records_sent The number of records sent over the channel
+ * @returns {number} The stroke width of the channel's edge in pixels
+ */
+const edge_width = records_sent => {
+    if (max_records_sent === 0) {
+        return min_edge_width;
+    }
+
+    const scale = Math.log1p(records_sent) / Math.log1p(max_records_sent);
+    return min_edge_width + scale * (max_edge_width - min_edge_width);
+};
+
+// Nested scopes with more operators than this start out collapsed
+const collapse_threshold = 100;
+for (const subgraph of raw_subgraphs) {
+    if (subgraph.parent !== null && subgraph.rollup.operators > collapse_threshold) {
+        collapsed_subgraphs.add(format_addr(subgraph.addr));
+    }
+}
+
+/**
+ * Finds the outermost collapsed subgraph containing the given operator
+ * @param {number[]} addr The operator's address
+ * @returns {string | undefined} The collapsed subgraph's address
+ */
+const collapsed_ancestor = addr => {
+    for (let length = 1; length < addr.length; length += 1) {
+        const ancestor = format_addr(addr.slice(0, length));
+        if (collapsed_subgraphs.has(ancestor)) {
+            return ancestor;
+        }
+    }
+
+    return undefined;
+};
+
+/**
+ * Checks if the given address is drawn as a cluster
+ * @param {string} addr The address to check
+ * @returns {boolean}
+ */
+const is_cluster = addr => subgraph_addrs.has(addr) && !collapsed_subgraphs.has(addr);
+
+/** Fills the graph with every operator, subgraph and channel that isn't collapsed */
+const build_graph = () => {
+    for (const node of graph.nodes()) {
+        graph.removeNode(node);
+    }
+    error_nodes.clear();
+    operator_addrs.clear();
+
+    for (const subgraph of raw_subgraphs) {
+        if (collapsed_ancestor(subgraph.addr) !== undefined) {
+            continue;
+        }
+
+        const subgraph_name = subgraph.name;
+        const subgraph_addr = format_addr(subgraph.addr);
+        operator_addrs.add(subgraph_addr);
+
+        const label = `${subgraph_name.replace(slash_regexp, "\\\\")} @ ${subgraph.id}, ${subgraph_addr}`;
+        if (collapsed_subgraphs.has(subgraph_addr)) {
+            // Collapsed subgraphs are drawn as a single node showing their rollup
+            graph.setNode(
+                subgraph_addr,
+                {
+                    label: `${label}\n${subgraph.rollup.operators} operators, ${format_duration(subgraph.rollup.total_ns)}`,
+                    style: `fill: ${subgraph.fill_color}; stroke-dasharray: 5, 2;`,
+                    labelStyle: `fill: ${subgraph.text_color}`,
+                    data: { kind: "Subgraph", collapsed: true, ...subgraph },
+                },
+            );
+        } else {
+            graph.setNode(
+                subgraph_addr,
+                {
+                    label: label,
+                    style: "fill: var(--surface); stroke-dasharray: 5, 2;",
+                    clusterLabelPos: "top",
+                    data: { kind: "Subgraph", collapsed: false, ...subgraph },
+                },
+            );
+        }
+
+        if (subgraph.addr.length > 1) {
+            const parent_addr = format_addr(subgraph.addr.slice(0, subgraph.addr.length - 1));
 
-    if (subgraph.addr.length > 1) {
-        const parent_addr = format_addr(subgraph.addr.slice(0, subgraph.addr.length - 1));
+            if (!node_id_exists(parent_addr)) {
+                create_error_node(parent_addr);
+            }
 
+            graph.setParent(subgraph_addr, parent_addr);
+        }
+    }
+
+    for (const node of raw_nodes) {
+        if (collapsed_ancestor(node.addr) !== undefined) {
+            continue;
+        }
+
+        const node_name = node.name;
+        const node_addr = format_addr(node.addr);
+        operator_addrs.add(node_addr);
+
+        graph.setNode(
+            node_addr,
+            {
+                label: `${node_name.replace(slash_regexp, "\\\\")} @ ${node.id}, ${node_addr}`,
+                style: `fill: ${node.fill_color}`,
+                labelStyle: `fill: ${node.text_color}`,
+                data: { kind: "Node", ...node },
+            },
+        );
+
+        const parent_addr = format_addr(node.addr.slice(0, node.addr.length - 1));
         if (!node_id_exists(parent_addr)) {
             create_error_node(parent_addr);
         }
 
-        graph.setParent(subgraph_addr, parent_addr);
+        graph.setParent(node_addr, parent_addr);
+    }
+
+    for (const edge of raw_edges) {
+        let style = `stroke-width: ${edge_width(edge.records_sent)}px; `;
+        if (edge.sustained_backlog) {
+            style += `stroke: ${BACKLOG_COLOR}; `;
+        }
+        switch (edge.edge_kind) {
+            case "Crossing":
+                style += "stroke: var(--accent); stroke-dasharray: 5, 2; fill: none;"
+                break;
+
+            case "Normal":
+                break;
+
+            default:
+                console.error(`invalid edge kind received: ${edge.edge_kind}"`);
+                break;
+        }
+
+        // Channels touching the insides of collapsed subgraphs are attached to the subgraph itself
+        const src_id = collapsed_ancestor(edge.src) || format_addr(edge.src);
+        const dest_id = collapsed_ancestor(edge.dest) || format_addr(edge.dest);
+        if (src_id === dest_id) {
+            continue;
+        }
+
+        if (!is_cluster(src_id) && !is_cluster(dest_id)) {
+            if (!node_id_exists(src_id)) {
+                create_error_node(src_id);
+            }
+            if (!node_id_exists(dest_id)) {
+                create_error_node(dest_id);
+            }
+
+            graph.setEdge(
+                src_id,
+                dest_id,
+                {
+                    style: style,
+                    data: { kind: "Edge", ...edge },
+                },
+            );
+        } else {
+            console.warn(
+                `skipped edge from ${src_id} to ${dest_id}, ${is_cluster(src_id) ? src_id : dest_id} is a subgraph and dagre is stupid`,
+            );
+        }
+    }
+};
+
+// Outline each operator group around the bounding box of its members
+const group_padding = 15;
+const group_outlines = svg.insert("g", ":first-child").attr("class", "operator-groups");
+
+const draw_group_outlines = () => {
+    group_outlines.selectAll("*").remove();
+
+    for (const group of operator_groups) {
+        let [min_x, min_y, max_x, max_y] = [Infinity, Infinity, -Infinity, -Infinity];
+
+        for (const member of group.members) {
+            const node = graph.node(format_addr(member));
+            if (!node || node.x === undefined || node.y === undefined) {
+                continue;
+            }
+
+            min_x = Math.min(min_x, node.x - node.width / 2);
+            min_y = Math.min(min_y, node.y - node.height / 2);
+            max_x = Math.max(max_x, node.x + node.width / 2);
+            max_y = Math.max(max_y, node.y + node.height / 2);
+        }
+
+        if (!isFinite(min_x) || !isFinite(min_y)) {
+            console.warn(`operator group "${group.name}" has no rendered members`);
+            continue;
+        }
+
+        const outline = group_outlines.append("g").attr("class", "operator-group");
+        outline
+            .append("rect")
+            .attr("x", min_x - group_padding)
+            .attr("y", min_y - group_padding)
+            .attr("width", max_x - min_x + group_padding * 2)
+            .attr("height", max_y - min_y + group_padding * 2)
+            .attr("rx", 5);
+        outline
+            .append("text")
+            .attr("x", min_x - group_padding)
+            .attr("y", min_y - group_padding - 5)
+            .text(group.name);
     }
+};
+
+// Create the tooltip div
+const tooltip = d3.select("#dataflow-graph-div")
+    .append("div")
+    .attr("id", "tooltip-template");
+
+/**
+ * Draws an operator's activation histogram as a row of bars scaled to the fullest bucket
+ *
+ * @param {HistogramBucket[]} histogram
+ * @returns {string}
+ */
+const activation_histogram_html = histogram => {
+    const fullest = Math.max(...histogram.map(bucket => bucket.count));
+
+    const bars = histogram.map(({ lower, upper, count }) => {
+        // Buckets are labeled by their upper bound, the overflow bucket by its lower one
+        const label = upper
+            ? `&lt;${format_duration(duration_nanos(upper))}`
+            : `&ge;${format_duration(duration_nanos(lower))}`;
+        const height = Math.max(count / fullest * 100, count === 0 ? 0 : 5);
+
+        return `<div class="histogram-bucket"><span>${count}</span>\
+            <div class="histogram-bar" style="height: ${height * 0.04}em"></div>\
+            <span>${label}</span></div>`;
+    });
+
+    return `<br>activation durations<div class="histogram">${bars.join("")}</div>`;
+};
+
+/**
+ * Draws an operator's activations per second and share of time spent activated
+ * over the run as a pair of sparklines, each scaled to its own peak
+ *
+ * @param {ActivationRate[]} rates
+ * @returns {string}
+ */
+const activation_rate_sparkline = rates => {
+    const [width, height] = [200, 40];
+    const peak_activations = Math.max(...rates.map(rate => rate.activations_per_sec));
+    const peak_busy = Math.max(...rates.map(rate => rate.busy_per_sec));
+
+    /** @param {(rate: ActivationRate) => number} scaled */
+    const points = scaled => rates
+        .map((rate, idx) => {
+            const x = idx / (rates.length - 1) * width;
+            const y = height - scaled(rate) * height;
+
+            return `${x.toFixed(1)},${y.toFixed(1)}`;
+        })
+        .join(" ");
+
+    const activations = points(rate => peak_activations === 0 ? 0 : rate.activations_per_sec / peak_activations);
+    const busy = points(rate => peak_busy === 0 ? 0 : rate.busy_per_sec / peak_busy);
+    const start = format_duration(rates[0].window);
+    const end = format_duration(rates[rates.length - 1].window);
+
+    return `<br>activations over ${start} to ${end} \
+        (solid: ${peak_activations.toFixed(1)} activations/s at peak, \
+        dashed: ${(peak_busy * 100).toFixed(1)}% busy at peak)<br>\
+        <svg class="sparkline" width="${width}" height="${height}">\
+            <polyline class="sparkline-activations" points="${activations}"></polyline>\
+            <polyline class="sparkline-busy" points="${busy}"></polyline>\
+        </svg>`;
+};
+
+/** @type {Map<string, ArrangementSizes[]>} Each operator's arrangement sizes, keyed by address */
+const arrangement_sizes_by_addr = new Map();
+for (const series of arrangement_sizes) {
+    const addr = format_addr(series.addr);
+    arrangement_sizes_by_addr.set(addr, (arrangement_sizes_by_addr.get(addr) || []).concat([series]));
 }
 
-for (const node of raw_nodes) {
-    const node_name = node.name;
-    operator_names.set(node.id, node_name);
+/**
+ * Draws the sizes of an operator's arrangement over time with a line for each
+ * selected worker, all sharing the same time and size scales
+ *
+ * @param {ArrangementSizes[]} all_series
+ * @returns {string}
+ */
+const arrangement_size_sparkline = all_series => {
+    const series = all_series.filter(({ worker }) => selected_workers.has(worker));
+    const points = series.flatMap(({ sizes }) => sizes);
+    if (points.length < 2) {
+        return "";
+    }
 
-    const node_addr = format_addr(node.addr);
-    operator_addrs.add(node_addr);
+    const [width, height] = [200, 40];
+    const start = Math.min(...points.map(([time]) => time));
+    const end = Math.max(...points.map(([time]) => time));
+    const peak = Math.max(...points.map(([, size]) => size));
 
-    graph.setNode(
-        node_addr,
-        {
-            label: `${node_name.replace(slash_regexp, "\\\\")} @ ${node.id}, ${node_addr}`,
-            style: `fill: ${node.fill_color}`,
-            labelStyle: `fill: ${node.text_color}`,
-            data: { kind: "Node", ...node },
-        },
-    );
+    const lines = series.map(({ sizes }) => {
+        const line = sizes
+            .map(([time, size]) => {
+                const x = end === start ? 0 : (time - start) / (end - start) * width;
+                const y = peak === 0 ? height : height - size / peak * height;
+
+                return `${x.toFixed(1)},${y.toFixed(1)}`;
+            })
+            .join(" ");
+
+        return `<polyline class="sparkline-size" points="${line}"></polyline>`;
+    });
+
+    return `<br>arrangement size over ${format_duration(start)} to ${format_duration(end)} \
+        (${peak} updates at peak)<br>\
+        <svg class="sparkline" width="${width}" height="${height}">${lines.join("")}</svg>`;
+};
+
+/** Installs the tooltips and collapse toggles onto the rendered graph */
+const install_graph_handlers = () => {
+    // Node tooltips
+    svg.selectAll("g.node")
+        // Reveal the tooltip on hover
+        .on("mouseover", () => tooltip.style("visibility", "visible"))
+        .on(
+            "mousemove",
+            /** @param {string} node_id */
+            node_id => {
+                const unsafe_node = graph.node(node_id);
+                if (!unsafe_node || !unsafe_node.data || !unsafe_node.data.name || unsafe_node.data.kind === "Error") {
+                    tooltip.style("visibility", "hidden");
+                    return;
+                }
+
+                const node = unsafe_node.data;
+                let html = "";
+                if (node.full_name) {
+                    html += `<span class="full-name">${escape_html(node.full_name)}</span><br>`;
+                }
+
+                html += `ran for ${node.total_activation_time} over ${node.invocations} invocations < br >\
+                    average runtime of ${node.average_activation_time} \
+                    (max: ${node.max_activation_time}, min: ${node.min_activation_time})`;
+
+                if (node.activation_percentiles) {
+                    const { p50, p90, p99, p999 } = node.activation_percentiles;
+                    html += `<br>p50: ${format_duration(duration_nanos(p50))}, \
+                        p90: ${format_duration(duration_nanos(p90))}, \
+                        p99: ${format_duration(duration_nanos(p99))}, \
+                        p99.9: ${format_duration(duration_nanos(p999))}`;
+                }
+
+                if (node.activation_rates && node.activation_rates.length > 1) {
+                    html += activation_rate_sparkline(node.activation_rates);
+                }
+
+                if (node.activation_histogram && node.activation_histogram.length !== 0) {
+                    html += activation_histogram_html(node.activation_histogram);
+                }
+
+                if (node.kind === "Node" && node.source_location) {
+                    const { file, line, column } = node.source_location;
+                    html += `<br>defined at ${escape_html(file)}:${line}:${column}`;
+
+                    if (node.source_url) {
+                        html += " (click to open)";
+                    }
+                }
+
+                if (node.comparison) {
+                    html += `<br>${escape_html(node.comparison)}`;
+                }
+
+                if (node.kind === "Subgraph" && node.rollup) {
+                    html += `<br>contains ${node.rollup.operators} operators which ran for \
+                        ${format_duration(node.rollup.total_ns)} over ${node.rollup.invocations} invocations \
+                        (click to ${node.collapsed ? "expand" : "collapse"})`;
+                }
+
+                if (selected_workers.size !== rendered_workers.length && node.workers) {
+                    const activity = selected_activity(node.workers);
+                    html += `<br>selected workers ran for ${format_duration(activity.total_ns)} \
+                        over ${activity.invocations} invocations`;
+                }
+
+                if (node.kind === "Node"
+                    && node.max_arrangement_size !== null
+                    && node.min_arrangement_size !== null
+                ) {
+                    html += `< br > max arrangement size: ${node.max_arrangement_size}, \
+                        min arrangement size: ${node.min_arrangement_size} `;
+                }
+
+                if (node.kind === "Node" && arrangement_sizes_by_addr.has(format_addr(node.addr))) {
+                    html += arrangement_size_sparkline(arrangement_sizes_by_addr.get(format_addr(node.addr)));
+                }
+
+                if (node.kind === "Node" && node.average_batch_size !== null) {
+                    html += `<br>average batch size: ${node.average_batch_size}`;
+
+                    if (node.average_merge_time !== null) {
+                        html += `, average merge time: ${node.average_merge_time}`;
+                    }
+                    if (node.trace_shares) {
+                        html += `<br>shared by ${node.trace_shares} traces`;
+                    }
+                }
+
+                tooltip
+                    .html(html)
+                    .style("top", (d3.event.pageY - 40) + "px")
+                    .style("left", (d3.event.pageX + 40) + "px");
+            },
+        )
+        // Hide the tooltip on mouseout
+        .on("mouseout", () => tooltip.style("visibility", "hidden"));
+
+    // Edge tooltips
+    svg.selectAll("g.edgePath")
+        // Reveal the tooltip on hover
+        .on("mouseover", () => tooltip.style("visibility", "visible"))
+        .on(
+            "mousemove",
+            /** @param {string} edge_id */
+            edge_id => {
+                const unsafe_edge = graph.edge(edge_id);
+                if (!unsafe_edge || !unsafe_edge.data || !unsafe_edge.data.kind || unsafe_edge.data.kind === "Error") {
+                    tooltip.style("visibility", "hidden");
+                    return;
+                }
+
+                /** @type {Edge} */
+                const edge = unsafe_edge.data;
+
+                /**
+                 * @param {number[]} node_addr The node address to get the name of
+                 * @returns {string} The node's name
+                 */
+                const get_node_name = node_addr => {
+                    const node = graph.node(format_addr(node_addr));
+
+                    let node_name = "";
+                    if (!node || !node.data || !node.data.name || !node.data.kind || node.data.kind === "Error") {
+                        node_name = "Error";
+                    } else {
+                        node_name = node.data.name;
+                    }
+
+                    return node_name;
+                };
+
+                const src_name = get_node_name(edge.src);
+                const dest_name = get_node_name(edge.dest);
+
+                let html = `channel from ${src_name} to ${dest_name} <br>\
+                    sent ${edge.records_sent} records`;
+                if (edge.peak_backlog !== null) {
+                    html += `<br>at most ${edge.peak_backlog} records in flight`;
+                    if (edge.sustained_backlog) {
+                        html += " (sustained backlog)";
+                    }
+                }
+                if (edge.latency !== null) {
+                    const { messages, p50, p99, max } = edge.latency;
+                    html += `<br>${messages} messages took ${format_duration(duration_nanos(p50))} \
+                        to be received (p99 ${format_duration(duration_nanos(p99))}, \
+                        max ${format_duration(duration_nanos(max))})`;
+                }
+
+                tooltip
+                    .html(html)
+                    .style("top", (d3.event.pageY - 40) + "px")
+                    .style("left", (d3.event.pageX + 40) + "px");
+            },
+        )
+        // Hide the tooltip on mouseout
+        .on("mouseout", () => tooltip.style("visibility", "hidden"));
+
+    // Clicking a subgraph collapses or expands it and clicking an operator
+    // opens its source location
+    svg.selectAll("g.node, g.cluster")
+        .on(
+            "click",
+            /** @param {string} node_id */
+            node_id => {
+                const node = graph.node(node_id);
+                if (node && node.data && node.data.kind === "Node" && node.data.source_url) {
+                    window.open(node.data.source_url, "_blank", "noopener");
+                    return;
+                }
+
+                if (!node || !node.data || node.data.kind !== "Subgraph") {
+                    return;
+                }
+
+                if (collapsed_subgraphs.has(node_id)) {
+                    collapsed_subgraphs.delete(node_id);
+                } else {
+                    collapsed_subgraphs.add(node_id);
+                }
+
+                tooltip.style("visibility", "hidden");
+                redraw_graph();
+            },
+        );
+};
+
+// Operator search
+const max_search_results = 25;
+const search_zoom_scale = 1.5;
+const search_input = d3.select("#operator-search");
+const search_results = d3.select("#search-results");
+
+/**
+ * Centers the graph on the given operator and highlights it
+ * @param {SearchEntry} entry The operator to jump to
+ */
+const jump_to_operator = entry => {
+    // Expand any collapsed subgraphs the operator is hidden within
+    if (collapsed_ancestor(entry.addr) !== undefined) {
+        for (let length = 1; length < entry.addr.length; length += 1) {
+            collapsed_subgraphs.delete(format_addr(entry.addr.slice(0, length)));
+        }
+
+        redraw_graph();
+    }
+
+    const node_id = format_addr(entry.addr);
+    const node = graph.node(node_id);
+    if (!node || node.x === undefined || node.y === undefined) {
+        console.warn(`operator ${node_id} isn't within the rendered graph`);
+        return;
+    }
+
+    const bounds = dataflow_svg.node().getBoundingClientRect();
+    dataflow_svg
+        .transition()
+        .duration(500)
+        .call(
+            zoom.transform,
+            d3.zoomIdentity
+                .translate(
+                    bounds.width / 2 - node.x * search_zoom_scale,
+                    bounds.height / 2 - node.y * search_zoom_scale,
+                )
+                .scale(search_zoom_scale),
+        );
+
+    svg.selectAll("g.node, g.cluster")
+        .classed("search-highlight", id => id === node_id);
+};
+
+/**
+ * Returns every search entry that matches the query
+ * @param {string} query The user's search query
+ * @returns {SearchEntry[]}
+ */
+const search_operators = query => {
+    const needle = query.trim().toLowerCase();
+    if (needle.length === 0) {
+        return [];
+    }
+
+    return search_index
+        .filter(entry => entry.text.includes(needle))
+        .slice(0, max_search_results);
+};
+
+search_input.on("input", () => {
+    const results = search_operators(search_input.property("value"));
+
+    const items = search_results
+        .selectAll("li")
+        .data(results)
+        .join("li")
+        .text(entry => `${entry.name} @ ${format_addr(entry.addr)} (worker ${entry.worker})`)
+        .on("click", jump_to_operator);
+
+    items.classed("subgraph-result", entry => entry.is_subgraph);
+});
 
-    const parent_addr = format_addr(node.addr.slice(0, node.addr.length - 1));
-    if (!node_id_exists(parent_addr)) {
-        create_error_node(parent_addr);
+// Jump to the first match when enter is pressed
+search_input.on("keydown", () => {
+    if (d3.event.key === "Enter") {
+        const [first] = search_operators(search_input.property("value"));
+        if (first) {
+            jump_to_operator(first);
+        }
     }
+});
 
-    graph.setParent(node_addr, parent_addr);
-}
+// Worker toggles
+/** @type {Set<number>} */
+const selected_workers = new Set(rendered_workers);
 
-for (const edge of raw_edges) {
-    let style = "";
-    switch (edge.edge_kind) {
-        case "Crossing":
-            // Blue
-            style = "stroke: #5d5de6; stroke-dasharray: 5, 2; fill: none;"
-            break;
+/**
+ * Sums up an operator's activity over the selected workers
+ * @param {WorkerActivity[]} activity The operator's per-worker activity
+ * @returns {{ total_ns: number, invocations: number, workers: number }}
+ */
+const selected_activity = activity => {
+    const selected = activity.filter(worker => selected_workers.has(worker.worker));
+
+    return {
+        total_ns: selected.reduce((total, worker) => total + worker.total_ns, 0),
+        invocations: selected.reduce((total, worker) => total + worker.invocations, 0),
+        workers: selected.length,
+    };
+};
 
-        case "Normal":
-            break;
+/**
+ * Picks the palette color for a runtime within the given range
+ * @param {number} total The runtime to color
+ * @param {number} min The fastest runtime
+ * @param {number} max The slowest runtime
+ * @returns {string}
+ */
+const palette_color = (total, min, max) => {
+    const position = max > min ? (total - min) / (max - min) : 0;
+    return palette_colors[Math.min(Math.floor(position * palette_colors.length), palette_colors.length - 1)];
+};
 
-        default:
-            console.error(`invalid edge kind received: ${edge.edge_kind}"`);
-            break;
+/** Recolors and fades the graph to only reflect the selected workers */
+const update_selected_workers = () => {
+    const totals = new Map();
+    for (const node of raw_nodes) {
+        const activity = selected_activity(node.workers);
+        if (activity.workers !== 0) {
+            totals.set(format_addr(node.addr), activity.total_ns);
+        }
     }
 
-    const src_id = format_addr(edge.src);
-    const dest_id = format_addr(edge.dest);
+    const [min, max] = [Math.min(...totals.values()), Math.max(...totals.values())];
+    // Only activation times can be recolored from per-worker activity
+    const all_selected = selected_workers.size === rendered_workers.length || color_by !== "activation-time";
 
-    if (!subgraph_addrs.has(src_id) && !subgraph_addrs.has(dest_id)) {
-        if (!node_id_exists(src_id)) {
-            create_error_node(src_id);
-        }
-        if (!node_id_exists(dest_id)) {
-            create_error_node(dest_id);
+    svg.selectAll("g.node").each(function (node_id) {
+        const node = graph.node(node_id);
+        if (!node || !node.data || node.data.kind !== "Node") {
+            return;
         }
 
-        graph.setEdge(
-            src_id,
-            dest_id,
-            {
-                style: style,
-                data: { kind: "Edge", ...edge },
-            },
-        );
-    } else {
-        console.warn(
-            `skipped edge from ${src_id} to ${dest_id}, ${subgraph_addrs.has(src_id) ? src_id : dest_id} is a subgraph and dagre is stupid`,
-        );
-    }
-}
-
-// Render the graph
-try {
-    render(svg, graph);
-} catch (err) {
-    console.error(`failed to render dataflow graph: ${err}`);
-}
+        const element = d3.select(this);
+        const total = totals.get(node_id);
+
+        element.style("opacity", total === undefined ? 0.25 : 1);
+        element
+            .select("rect")
+            .style(
+                "fill",
+                all_selected || total === undefined ? node.data.fill_color : palette_color(total, min, max),
+            );
+    });
+
+    svg.selectAll("g.cluster").each(function (cluster_id) {
+        const cluster = graph.node(cluster_id);
+        if (!cluster || !cluster.data || cluster.data.kind !== "Subgraph") {
+            return;
+        }
 
-// Create the tooltip div
-const tooltip = d3.select("#dataflow-graph-div")
-    .append("div")
-    .attr("id", "tooltip-template");
+        const active = selected_activity(cluster.data.workers).workers !== 0;
+        d3.select(this).style("opacity", active ? 1 : 0.25);
+    });
 
-// Node tooltips
-svg.selectAll("g.node")
-    // Reveal the tooltip on hover
-    .on("mouseover", () => tooltip.style("visibility", "visible"))
-    .on(
-        "mousemove",
-        /** @param {string} node_id */
-        node_id => {
-            const unsafe_node = graph.node(node_id);
-            if (!unsafe_node || !unsafe_node.data || !unsafe_node.data.name || unsafe_node.data.kind === "Error") {
-                tooltip.style("visibility", "hidden");
-                return;
-            }
+    svg.selectAll("g.edgePath").each(function (edge_id) {
+        const edge = graph.edge(edge_id);
+        if (!edge || !edge.data || edge.data.kind !== "Edge") {
+            return;
+        }
 
-            const node = unsafe_node.data;
-            let html = `ran for ${node.total_activation_time} over ${node.invocations} invocations < br >\
-                average runtime of ${node.average_activation_time} \
-                (max: ${node.max_activation_time}, min: ${node.min_activation_time})`;
-
-            if (node.kind === "Node"
-                && node.max_arrangement_size !== null
-                && node.min_arrangement_size !== null
-            ) {
-                html += `< br > max arrangement size: ${node.max_arrangement_size}, \
-                    min arrangement size: ${node.min_arrangement_size} `;
-            }
+        const active = edge.data.workers.some(worker => selected_workers.has(worker));
+        d3.select(this).style("opacity", active ? 1 : 0.1);
+    });
+};
 
-            tooltip
-                .html(html)
-                .style("top", (d3.event.pageY - 40) + "px")
-                .style("left", (d3.event.pageX + 40) + "px");
-        },
-    )
-    // Hide the tooltip on mouseout
-    .on("mouseout", () => tooltip.style("visibility", "hidden"));
-
-// Edge tooltips
-svg.selectAll("g.edgePath")
-    // Reveal the tooltip on hover
-    .on("mouseover", () => tooltip.style("visibility", "visible"))
-    .on(
-        "mousemove",
-        /** @param {string} edge_id */
-        edge_id => {
-            const unsafe_edge = graph.edge(edge_id);
-            if (!unsafe_edge || !unsafe_edge.data || !unsafe_edge.data.kind || unsafe_edge.data.kind === "Error") {
-                tooltip.style("visibility", "hidden");
-                return;
+// Only bother with toggles if there's more than one worker to choose from
+if (rendered_workers.length > 1) {
+    const toggles = d3.select("#worker-toggles")
+        .selectAll("label")
+        .data(rendered_workers)
+        .join("label");
+
+    toggles
+        .append("input")
+        .attr("type", "checkbox")
+        .property("checked", true)
+        .on("change", function (worker) {
+            if (this.checked) {
+                selected_workers.add(worker);
+            } else {
+                selected_workers.delete(worker);
             }
 
-            /** @type {Edge} */
-            const edge = unsafe_edge.data;
-
-            /**
-             * @param {number[]} node_addr The node address to get the name of
-             * @returns {string} The node's name
-             */
-            const get_node_name = node_addr => {
-                const node = graph.node(format_addr(node_addr));
-
-                let node_name = "";
-                if (!node || !node.data || !node.data.name || !node.data.kind || node.data.kind === "Error") {
-                    node_name = "Error";
-                } else {
-                    node_name = node.data.name;
-                }
+            update_selected_workers();
+        });
+    toggles.append("span").text(worker => `Worker ${worker}`);
+}
 
-                return node_name;
-            };
+/** Rebuilds and rerenders the graph, used whenever subgraphs are collapsed or expanded */
+const redraw_graph = () => {
+    build_graph();
 
-            const src_name = get_node_name(edge.src);
-            const dest_name = get_node_name(edge.dest);
+    try {
+        render(svg, graph);
+    } catch (err) {
+        console.error(`failed to render dataflow graph: ${err}`);
+    }
 
-            let html = `channel from ${src_name} to ${dest_name} `;
+    draw_group_outlines();
+    install_graph_handlers();
+    update_selected_workers();
+};
 
-            tooltip
-                .html(html)
-                .style("top", (d3.event.pageY - 40) + "px")
-                .style("left", (d3.event.pageX + 40) + "px");
-        },
-    )
-    // Hide the tooltip on mouseout
-    .on("mouseout", () => tooltip.style("visibility", "hidden"));
+// Render the graph
+redraw_graph();
 
 // Add the palette legend
 const palette_legend = d3.select("body")
@@ -400,12 +1304,21 @@ if (palette_gradient.endsWith(", ")) {
     palette_gradient = palette_gradient.substring(0, palette_gradient.length - 2);
 }
 
+/** @type {Record<string, [string, string]>} The legend's top and bottom text for each coloring metric */
+const palette_text = {
+    "activation-time": ["slower", "faster"],
+    "invocations": ["more activations", "fewer activations"],
+    "arrangement-size": ["larger", "smaller"],
+    "idle-time": ["more idle", "less idle"],
+};
+const [palette_top_text, palette_bottom_text] = palette_text[color_by] || palette_text["activation-time"];
+
 // Top text
 palette_legend
     .append("div")
     .attr("class", "palette-text")
     .attr("id", "palette-top-text")
-    .text("slower");
+    .text(palette_top_text);
 
 // Heatgraph gradient
 palette_legend
@@ -418,7 +1331,7 @@ palette_legend
     .append("div")
     .attr("class", "palette-text")
     .attr("id", "palette-bottom-text")
-    .text("faster");
+    .text(palette_bottom_text);
 
 /**
  * Formats an operator address into a human-readable string
@@ -456,6 +1369,39 @@ function format_addr(addr) {
     return buf;
 }
 
+/**
+ * Escapes a string for use within html, operator names are full of angle brackets
+ * @param {string} text The text to escape
+ * @returns string
+ */
+function escape_html(text) {
+    return text
+        .replace(/&/g, "&amp;")
+        .replace(/</g, "&lt;")
+        .replace(/>/g, "&gt;")
+        .replace(/"/g, "&quot;");
+}
+
+/**
+ * Converts a serialized rust `Duration` into nanoseconds
+ * @param {RustDuration} duration The duration to convert
+ * @returns number The duration in nanoseconds
+ */
+function duration_nanos(duration) {
+    return duration.secs * 1000000000 + duration.nanos;
+}
+
+/**
+ * Gets the end of a timeline mark, instantaneous events are given a sliver
+ * of width so that they stay visible
+ * @param {number} start The event's start in nanoseconds
+ * @param {number} duration The event's duration in nanoseconds
+ * @returns number The end of the event's mark in nanoseconds
+ */
+function mark_end(start, duration) {
+    return start + Math.max(duration, 1);
+}
+
 // TODO: Make this operate off of the `Duration` type
 /**
  * Formats a duration into a human-readable string
@@ -550,6 +1496,16 @@ function format_duration(input_nanos) {
     return buf;
 }
 
+/**
+ * Reads one of the theme's css variables
+ *
+ * @param {string} name
+ * @returns {string}
+ */
+const theme_color = name => getComputedStyle(document.documentElement).getPropertyValue(name).trim();
+
+const foreground_color = theme_color("--foreground");
+
 // TODO: Type this with `VisualizationSpec`
 const ddshow_spec = {
     $schema: "https://vega.github.io/schema/vega-lite/v5.json",
@@ -559,8 +1515,19 @@ const ddshow_spec = {
     },
     config: {
         customFormatTypes: true,
+        axis: {
+            labelColor: foreground_color,
+            titleColor: foreground_color,
+            domainColor: foreground_color,
+            tickColor: foreground_color,
+        },
+        legend: {
+            labelColor: foreground_color,
+            titleColor: foreground_color,
+        },
+        title: { color: foreground_color },
     },
-    background: "#EEEEEE",
+    background: theme_color("--background"),
     params: [
         {
             name: "filter_subgraphs",
@@ -681,7 +1648,7 @@ const ddshow_spec = {
                     filter: "datum.rank <= operators_to_display",
                 },
                 {
-                    calculate: "[datum.max_arrangement_size, datum.min_arrangement_size || 0, datum.arrangement_batches || 0]",
+                    calculate: "[datum.max_arrangement_size, datum.min_arrangement_size || 0, datum.arrangement_batches || 0, datum.arrangement_merges || 0]",
                     as: "arrangement_stats",
                 },
             ],
@@ -723,6 +1690,10 @@ const ddshow_spec = {
                         field: "arrangement_stats[2]",
                         title: "Batches",
                     },
+                    {
+                        field: "arrangement_stats[3]",
+                        title: "Merges",
+                    },
                 ]
             }
         },
@@ -733,7 +1704,7 @@ const ddshow_spec = {
                     filter: "datum.arrangement_batches != null && datum.arrangement_batches > 0",
                 },
                 {
-                    calculate: "[datum.max_arrangement_size, datum.min_arrangement_size || 0, datum.arrangement_batches || 0]",
+                    calculate: "[datum.max_arrangement_size, datum.min_arrangement_size || 0, datum.arrangement_batches || 0, datum.arrangement_merges || 0]",
                     as: "arrangement_stats",
                 },
                 {
@@ -778,6 +1749,10 @@ const ddshow_spec = {
                         field: "arrangement_batches",
                         title: "Batches"
                     },
+                    {
+                        field: "arrangement_stats[3]",
+                        title: "Merges"
+                    },
                     {
                         field: "max_arrangement_size",
                         title: "Max Size"
@@ -955,6 +1930,826 @@ const ddshow_spec = {
     ],
 };
 
+// Only chart the target's runs if it actually reconnected
+if (target_runs.length > 1) {
+    ddshow_spec.vconcat.push({
+        title: "Target Runs",
+        data: {
+            values: target_runs.map(run => ({
+                run: run.run,
+                started_at: duration_nanos(run.started_at),
+                ended_at: duration_nanos(run.ended_at),
+                connections: run.connections,
+            })),
+        },
+        mark: "bar",
+        width: 1000,
+        encoding: {
+            y: {
+                field: "run",
+                title: "Run",
+                type: "ordinal",
+            },
+            x: {
+                field: "started_at",
+                title: "Time",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            x2: { field: "ended_at" },
+            tooltip: [
+                {
+                    field: "run",
+                    title: "Run",
+                },
+                {
+                    field: "started_at",
+                    title: "Started At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "ended_at",
+                    title: "Ended At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "connections",
+                    title: "Connections",
+                },
+            ],
+        },
+    });
+}
+
+// Only chart progress tracking if progress logging was enabled
+if (progress_channels.length !== 0) {
+    ddshow_spec.vconcat.push({
+        title: "Progress Tracking Overhead",
+        data: {
+            values: progress_channels.map(channel => ({
+                channel: `${channel.channel} on worker ${channel.worker}`,
+                scope: format_addr(channel.scope_addr),
+                sent: channel.sent,
+                received: channel.received,
+                message_updates: channel.message_updates,
+                capability_updates: channel.capability_updates,
+                pointstamps: channel.message_updates + channel.capability_updates,
+            })),
+        },
+        transform: [
+            {
+                window: [{
+                    op: "rank",
+                    as: "rank",
+                }],
+                sort: [{ field: "pointstamps", order: "descending" }],
+            },
+            {
+                filter: "datum.rank <= operators_to_display",
+            },
+        ],
+        mark: "bar",
+        width: 1000,
+        encoding: {
+            y: {
+                field: "channel",
+                title: "Progress Channel",
+                type: "nominal",
+                sort: "-x",
+            },
+            x: {
+                field: "pointstamps",
+                title: "Pointstamp Updates",
+                type: "quantitative",
+            },
+            tooltip: [
+                { field: "channel", title: "Channel" },
+                { field: "scope", title: "Scope" },
+                { field: "sent", title: "Messages Sent" },
+                { field: "received", title: "Messages Received" },
+                { field: "message_updates", title: "Message Updates" },
+                { field: "capability_updates", title: "Capability Updates" },
+            ],
+        },
+    });
+}
+
+if (frontier_progression.length !== 0) {
+    ddshow_spec.vconcat.push({
+        title: "Frontier Progression",
+        data: {
+            values: frontier_progression.map(([[worker, addr], [released_at, timestamp]]) => ({
+                scope: `${format_addr(addr)} on worker ${worker}`,
+                released_at: duration_nanos(released_at),
+                timestamp: timestamp,
+            })),
+        },
+        mark: "tick",
+        width: 1000,
+        encoding: {
+            x: {
+                field: "released_at",
+                title: "Released At",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            y: {
+                field: "scope",
+                title: "Scope",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "scope", title: "Scope" },
+                { field: "timestamp", title: "Timestamp" },
+                {
+                    field: "released_at",
+                    title: "Released At",
+                    formatType: "format_duration",
+                },
+            ],
+        },
+    });
+}
+
+// The end-to-end latency of each dataflow's timestamps over the run
+if (epoch_latencies.length !== 0) {
+    ddshow_spec.vconcat.push({
+        title: "Epoch Latency",
+        data: {
+            values: epoch_latencies.map(([addr, epoch]) => ({
+                dataflow: format_addr(addr),
+                timestamp: epoch.timestamp,
+                first_seen: duration_nanos(epoch.first_seen),
+                completed: duration_nanos(epoch.completed),
+                latency: duration_nanos(epoch.latency),
+            })),
+        },
+        mark: { type: "line", point: true },
+        width: 1000,
+        encoding: {
+            x: {
+                field: "first_seen",
+                title: "First Seen At",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            y: {
+                field: "latency",
+                title: "Latency",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            color: {
+                field: "dataflow",
+                title: "Dataflow",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "dataflow", title: "Dataflow" },
+                { field: "timestamp", title: "Timestamp" },
+                {
+                    field: "first_seen",
+                    title: "First Seen At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "completed",
+                    title: "Completed At",
+                    formatType: "format_duration",
+                },
+                { field: "latency", title: "Latency", formatType: "format_duration" },
+            ],
+        },
+    });
+}
+
+// Each custom stream gets its own lane per worker, laid along the same
+// time axis as operator activations
+if (custom_events.length !== 0) {
+    ddshow_spec.vconcat.push({
+        title: "Custom Events",
+        data: {
+            values: custom_events.map(event => {
+                const start = duration_nanos(event.start);
+
+                return {
+                    lane: `${event.stream} on worker ${event.worker}`,
+                    stream: event.stream,
+                    label: event.label,
+                    start: start,
+                    end: mark_end(start, duration_nanos(event.duration)),
+                    duration: duration_nanos(event.duration),
+                };
+            }),
+        },
+        mark: { type: "bar", minWidth: 2 },
+        width: 1000,
+        encoding: {
+            y: {
+                field: "lane",
+                title: "Lane",
+                type: "nominal",
+            },
+            x: {
+                field: "start",
+                title: "Time",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            x2: { field: "end" },
+            color: {
+                field: "stream",
+                title: "Stream",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "label", title: "Event" },
+                { field: "lane", title: "Lane" },
+                {
+                    field: "start",
+                    title: "Started At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "duration",
+                    title: "Duration",
+                    formatType: "format_duration",
+                },
+            ],
+        },
+    });
+}
+
+// The worker timeline shows the finest tier of events that fits within the
+// visible window, zooming in swaps in finer tiers until the raw events are shown
+const max_timeline_marks = 5000;
+
+/** @type {TimelineBucket[][]} */
+const timeline_tiers = timeline_tier_widths.map((_, tier) => timeline_buckets.filter(bucket => bucket.tier === tier));
+
+/**
+ * @param {EventKind} kind
+ * @returns {[string, string]} The event's category and a label for it
+ */
+const describe_event = kind => {
+    if (typeof kind === "string") {
+        return [kind, kind];
+    } else if ("OperatorActivation" in kind) {
+        const id = kind.OperatorActivation.operator_id;
+        return ["Activation", operator_names.get(id) || `Operator ${id}`];
+    } else if ("Application" in kind) {
+        return ["Application", `Application ${kind.Application.id}`];
+    } else {
+        const id = kind.Merge.operator_id;
+        return ["Merge", `Merge within ${operator_names.get(id) || id}`];
+    }
+};
+
+/**
+ * Collects the timeline marks overlapping `[start, end]`
+ *
+ * @param {number} start
+ * @param {number} end
+ */
+const timeline_marks = (start, end) => {
+    const visible_events = timeline_events
+        .filter(event => event.start_time + event.duration >= start && event.start_time <= end);
+
+    if (visible_events.length <= max_timeline_marks) {
+        return visible_events.map(event => {
+            const [category, label] = describe_event(event.event);
+
+            return {
+                lane: `Worker ${event.worker}: ${category}`,
+                category: category,
+                label: label,
+                start: event.start_time,
+                end: mark_end(event.start_time, event.duration),
+                busy: event.duration,
+                events: event.collapsed_events,
+                utilization: 1,
+            };
+        });
+    }
+
+    // Walk from the finest tier to the coarsest, the coarsest tier is always used
+    // as a last resort
+    for (let tier = timeline_tiers.length - 1; tier >= 0; tier -= 1) {
+        const width = timeline_tier_widths[tier];
+        const visible_buckets = timeline_tiers[tier]
+            .filter(bucket => bucket.start_time + width >= start && bucket.start_time <= end);
+
+        if (visible_buckets.length <= max_timeline_marks || tier === 0) {
+            return visible_buckets.map(bucket => ({
+                lane: `Worker ${bucket.worker}: ${bucket.category}`,
+                category: bucket.category,
+                label: `${bucket.events} events over ${format_duration(width)}`,
+                start: bucket.start_time,
+                end: bucket.start_time + width,
+                busy: bucket.busy_time,
+                events: bucket.events,
+                utilization: Math.min(bucket.busy_time / width, 1),
+            }));
+        }
+    }
+
+    return [];
+};
+
+const has_timeline = timeline_events.length !== 0;
+if (has_timeline) {
+    ddshow_spec.datasets = { timeline: timeline_marks(-Infinity, Infinity) };
+    // Anomalous activations are marked within their worker's activation lane
+    const anomaly_layers = activation_anomalies.length === 0 ? [] : [{
+        data: {
+            values: activation_anomalies.map(([[worker, id], [start, [duration, median]]]) => ({
+                lane: `Worker ${worker}: Activation`,
+                label: operator_names.get(id) || `Operator ${id}`,
+                start: duration_nanos(start),
+                duration: duration_nanos(duration),
+                median: duration_nanos(median),
+                multiple: duration_nanos(duration) / Math.max(duration_nanos(median), 1),
+            })),
+        },
+        mark: { type: "point", shape: "triangle-down", filled: true, size: 60, color: ANOMALY_COLOR },
+        encoding: {
+            y: { field: "lane", type: "nominal" },
+            x: { field: "start", type: "quantitative" },
+            tooltip: [
+                { field: "label", title: "Anomalous Activation" },
+                { field: "start", title: "Started At", formatType: "format_duration" },
+                { field: "duration", title: "Activation Time", formatType: "format_duration" },
+                { field: "median", title: "Median Activation Time", formatType: "format_duration" },
+                { field: "multiple", title: "Multiple of Median", format: ".1f" },
+            ],
+        },
+    }];
+
+    ddshow_spec.vconcat.push({
+        title: "Worker Timeline",
+        width: 1000,
+        layer: [
+            {
+                data: { name: "timeline" },
+                params: [
+                    {
+                        name: "timeline_zoom",
+                        select: { type: "interval", encodings: ["x"] },
+                        bind: "scales",
+                    },
+                ],
+                mark: { type: "bar", minWidth: 1 },
+                encoding: {
+                    y: {
+                        field: "lane",
+                        title: "Worker",
+                        type: "nominal",
+                    },
+                    x: {
+                        field: "start",
+                        title: "Time",
+                        type: "quantitative",
+                        axis: { formatType: "format_duration" },
+                    },
+                    x2: { field: "end" },
+                    color: {
+                        field: "category",
+                        title: "Event",
+                        type: "nominal",
+                    },
+                    // Buckets are shaded by how much of their window was spent busy
+                    opacity: {
+                        field: "utilization",
+                        type: "quantitative",
+                        scale: { domain: [0, 1], range: [0.2, 1] },
+                        legend: null,
+                    },
+                    // Parked workers are sleeping rather than busy-waiting, so their parks
+                    // are drawn as outlined gaps instead of solid bars
+                    fillOpacity: {
+                        condition: { test: "datum.category === 'Parked'", value: 0 },
+                        value: 1,
+                    },
+                    strokeDash: {
+                        condition: { test: "datum.category === 'Parked'", value: [3, 2] },
+                        value: [],
+                    },
+                    strokeWidth: {
+                        condition: { test: "datum.category === 'Parked'", value: 1 },
+                        value: 0,
+                    },
+                    stroke: {
+                        field: "category",
+                        type: "nominal",
+                        legend: null,
+                    },
+                    tooltip: [
+                        { field: "label", title: "Event" },
+                        { field: "lane", title: "Lane" },
+                        { field: "events", title: "Events" },
+                        {
+                            field: "start",
+                            title: "Started At",
+                            formatType: "format_duration",
+                        },
+                        {
+                            field: "busy",
+                            title: "Busy For",
+                            formatType: "format_duration",
+                        },
+                    ],
+                },
+            },
+            ...anomaly_layers,
+        ],
+    });
+}
+
+// The operator timeline gives each operator its own lane, showing how its
+// activations interleave across workers
+if (operator_lanes.length !== 0) {
+    /** @type {number[]} */
+    const dataflows = [...new Set(operator_lanes.map(lane => lane.dataflow))].sort((left, right) => left - right);
+
+    /**
+     * @param {number} dataflow
+     * @returns {string}
+     */
+    const dataflow_name = dataflow => {
+        const entry = search_index.find(entry => entry.addr.length === 1 && entry.addr[0] === dataflow);
+        return entry ? `${entry.name} (dataflow ${dataflow})` : `Dataflow ${dataflow}`;
+    };
+
+    let lane_marks = operator_lanes.flatMap(lane => lane.activations.map(([worker, start, duration]) => ({
+        lane: `${lane.name} ${format_addr(lane.addr)}`,
+        dataflow: lane.dataflow,
+        worker: `Worker ${worker}`,
+        start: start,
+        end: start + Math.max(duration, 1),
+        duration: duration,
+    })));
+
+    // Only the longest activations are kept when there's too many to draw
+    if (lane_marks.length > max_timeline_marks) {
+        console.warn(`only drawing the longest ${max_timeline_marks} of ${lane_marks.length} operator activations`);
+
+        lane_marks.sort((left, right) => right.duration - left.duration);
+        lane_marks = lane_marks.slice(0, max_timeline_marks);
+    }
+
+    ddshow_spec.vconcat.push({
+        title: "Operator Timeline",
+        data: { values: lane_marks },
+        params: [
+            {
+                name: "lane_dataflow",
+                value: -1,
+                bind: {
+                    input: "select",
+                    name: "Dataflow ",
+                    options: [-1, ...dataflows],
+                    labels: ["All", ...dataflows.map(dataflow_name)],
+                },
+            },
+            {
+                name: "lane_zoom",
+                select: { type: "interval", encodings: ["x"] },
+                bind: "scales",
+            },
+        ],
+        transform: [{ filter: "lane_dataflow < 0 || datum.dataflow === lane_dataflow" }],
+        mark: { type: "bar", minWidth: 1 },
+        width: 1000,
+        encoding: {
+            y: {
+                field: "lane",
+                title: "Operator",
+                type: "nominal",
+            },
+            x: {
+                field: "start",
+                title: "Time",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            x2: { field: "end" },
+            color: {
+                field: "worker",
+                title: "Worker",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "lane", title: "Operator" },
+                { field: "worker", title: "Worker" },
+                {
+                    field: "start",
+                    title: "Started At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "duration",
+                    title: "Duration",
+                    formatType: "format_duration",
+                },
+            ],
+        },
+    });
+}
+
+// A gantt chart of when each dataflow and operator was created and dropped
+if (operator_lifespans.length !== 0) {
+    ddshow_spec.vconcat.push({
+        title: "Operator Lifespans",
+        data: {
+            values: operator_lifespans.map(lifespan => ({
+                lane: `${lifespan.name} ${format_addr(lifespan.addr)}`,
+                kind: lifespan.is_dataflow ? "Dataflow" : "Operator",
+                worker: lifespan.worker,
+                created_at: lifespan.created_at,
+                dropped_at: lifespan.dropped_at,
+                lifetime: lifespan.dropped_at - lifespan.created_at,
+            })),
+        },
+        params: [
+            {
+                name: "lifespan_zoom",
+                select: { type: "interval", encodings: ["x"] },
+                bind: "scales",
+            },
+        ],
+        mark: { type: "bar", minWidth: 1, opacity: 0.6 },
+        width: 1000,
+        encoding: {
+            y: {
+                field: "lane",
+                title: "Operator",
+                type: "nominal",
+                // Keep operators in address order so dataflows sit above their contents
+                sort: null,
+            },
+            x: {
+                field: "created_at",
+                title: "Time Since Program Start",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+            },
+            x2: { field: "dropped_at" },
+            color: {
+                field: "kind",
+                title: "Kind",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "lane", title: "Operator" },
+                { field: "worker", title: "Worker" },
+                {
+                    field: "created_at",
+                    title: "Created At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "dropped_at",
+                    title: "Dropped At",
+                    formatType: "format_duration",
+                },
+                {
+                    field: "lifetime",
+                    title: "Lifetime",
+                    formatType: "format_duration",
+                },
+            ],
+        },
+    });
+}
+
+// A stacked bar of how each worker's runtime was spent
+if (worker_utilization.length !== 0) {
+    ddshow_spec.vconcat.push({
+        title: "Worker Utilization",
+        data: {
+            values: worker_utilization.flatMap(([worker, utilization]) => {
+                const runtime = duration_nanos(utilization.runtime);
+                const progress = duration_nanos(utilization.progress);
+
+                // Progress tracking is split out of the worker's busy time
+                return [
+                    ["Busy", duration_nanos(utilization.busy) - progress],
+                    ["Progress", progress],
+                    ["Idle", duration_nanos(utilization.idle)],
+                    ["Parked", duration_nanos(utilization.parked)],
+                ].map(([state, time], order) => ({
+                    worker: `Worker ${worker}`,
+                    state: state,
+                    order: order,
+                    time: time,
+                    percentage: runtime === 0 ? 0 : time / runtime,
+                }));
+            }),
+        },
+        mark: { type: "bar" },
+        width: 1000,
+        encoding: {
+            y: {
+                field: "worker",
+                title: "Worker",
+                type: "nominal",
+            },
+            x: {
+                field: "time",
+                title: "Time",
+                type: "quantitative",
+                stack: "zero",
+                axis: { formatType: "format_duration" },
+            },
+            color: {
+                field: "state",
+                title: "State",
+                type: "nominal",
+                sort: ["Busy", "Progress", "Idle", "Parked"],
+            },
+            order: { field: "order" },
+            tooltip: [
+                { field: "worker", title: "Worker" },
+                { field: "state", title: "State" },
+                { field: "time", title: "Time", formatType: "format_duration" },
+                { field: "percentage", title: "Share of Runtime", format: ".1%" },
+            ],
+        },
+    });
+}
+
+// A stacked bar of the kinds of events each worker logged
+if (event_kinds.length !== 0) {
+    /** @type {Map<number, number>} */
+    const worker_totals = new Map();
+    for (const [[worker], count] of event_kinds) {
+        worker_totals.set(worker, (worker_totals.get(worker) || 0) + count);
+    }
+
+    ddshow_spec.vconcat.push({
+        title: "Logged Events",
+        data: {
+            values: event_kinds.map(([[worker, kind], count]) => ({
+                worker: `Worker ${worker}`,
+                kind: kind,
+                count: count,
+                share: count / worker_totals.get(worker),
+            })),
+        },
+        mark: { type: "bar" },
+        width: 1000,
+        encoding: {
+            y: {
+                field: "worker",
+                title: "Worker",
+                type: "nominal",
+            },
+            x: {
+                field: "count",
+                title: "Events",
+                type: "quantitative",
+                stack: "zero",
+            },
+            color: {
+                field: "kind",
+                title: "Event Kind",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "worker", title: "Worker" },
+                { field: "kind", title: "Event Kind" },
+                { field: "count", title: "Events" },
+                { field: "share", title: "Share of Events", format: ".1%" },
+            ],
+        },
+    });
+}
+
+// A heatmap of the records each worker sent to every other worker, hot cells off
+// the diagonal are pairs of workers that exchanged disproportionately much data
+if (exchange_matrix.length !== 0) {
+    const total_records = exchange_matrix.reduce((total, [, records]) => total + records, 0);
+
+    ddshow_spec.vconcat.push({
+        title: "Worker Exchange Volume",
+        data: {
+            values: exchange_matrix.map(([[source, target], records]) => ({
+                source: `Worker ${source}`,
+                target: `Worker ${target}`,
+                records: records,
+                share: total_records === 0 ? 0 : records / total_records,
+            })),
+        },
+        mark: "rect",
+        width: 400,
+        height: 400,
+        encoding: {
+            x: {
+                field: "target",
+                title: "Target Worker",
+                type: "ordinal",
+            },
+            y: {
+                field: "source",
+                title: "Source Worker",
+                type: "ordinal",
+            },
+            color: {
+                field: "records",
+                title: "Records",
+                type: "quantitative",
+                scale: { scheme: "inferno" },
+            },
+            tooltip: [
+                { field: "source", title: "Source" },
+                { field: "target", title: "Target" },
+                { field: "records", title: "Records" },
+                { field: "share", title: "Share of Exchanged Records", format: ".1%" },
+            ],
+        },
+    });
+}
+
+// A stepped series of how far each dataflow's frontier had advanced over time,
+// flat stretches are stalls in timestamp progress
+if (dataflow_frontiers.length !== 0) {
+    /** @type {Map<string, [number, string][]>} */
+    const advances = new Map();
+    for (const [addr, [passed_at, timestamp]] of dataflow_frontiers) {
+        const dataflow = format_addr(addr);
+        if (!advances.has(dataflow)) {
+            advances.set(dataflow, []);
+        }
+
+        advances.get(dataflow).push([duration_nanos(passed_at), timestamp]);
+    }
+
+    const values = [];
+    for (const [dataflow, passed] of advances) {
+        passed.sort(([left], [right]) => left - right);
+        passed.forEach(([passed_at, timestamp], index) => {
+            values.push({ dataflow, passed_at, timestamp, advances: index + 1 });
+        });
+    }
+
+    ddshow_spec.vconcat.push({
+        title: "Dataflow Frontier Progression",
+        data: { values },
+        mark: { type: "line", interpolate: "step-after", point: true },
+        width: 1000,
+        encoding: {
+            x: {
+                field: "passed_at",
+                title: "Time",
+                type: "quantitative",
+                axis: { formatType: "format_duration" },
+                // Follow the timeline's zoom so stalls line up with the activations around them
+                scale: has_timeline ? { domain: { param: "timeline_zoom" } } : {},
+            },
+            y: {
+                field: "advances",
+                title: "Timestamps Passed",
+                type: "quantitative",
+            },
+            color: {
+                field: "dataflow",
+                title: "Dataflow",
+                type: "nominal",
+            },
+            tooltip: [
+                { field: "dataflow", title: "Dataflow" },
+                { field: "timestamp", title: "Timestamp" },
+                {
+                    field: "passed_at",
+                    title: "Passed At",
+                    formatType: "format_duration",
+                },
+            ],
+        },
+    });
+}
+
+/**
+ * Swaps the timeline's marks for the tier that best fits the zoomed window
+ *
+ * @param {any} view
+ */
+const install_timeline_zoom = view => {
+    /** @type {number | undefined} */
+    let pending_update;
+
+    view.addSignalListener("timeline_zoom", (_name, value) => {
+        const [start, end] = (value && value.start) || [-Infinity, Infinity];
+
+        // Wait for the user to stop zooming before recollecting marks
+        clearTimeout(pending_update);
+        pending_update = setTimeout(() => {
+            const marks = timeline_marks(start, end);
+            view.change("timeline", vega.changeset().remove(vega.truthy).insert(marks)).run();
+        }, 100);
+    });
+};
+
 vega.expressionFunction(
     "format_duration",
     /**
@@ -976,4 +2771,8 @@ vegaEmbed(
             editor: false,
         },
     },
-);
+).then(result => {
+    if (has_timeline) {
+        install_timeline_zoom(result.view);
+    }
+});
@@ -0,0 +1,518 @@
+//! Lays out the dataflow graph ahead of time so that the browser doesn't
+//! have to
+//!
+//! Laying out large graphs with dagre can take the browser well over half a
+//! minute on every page load, so the graph is laid out while rendering and
+//! the positions are handed to the page within `data/graph_layout.js`. Each
+//! scope is laid out on its own, innermost scopes first, by sorting its
+//! direct children into layers by the longest path leading up to them. The
+//! children of a scope are its operators along with its subgraphs, each
+//! subgraph taking up the space of its own laid out contents
+//!
+//! Layouts only depend on the structure of the graph, so they're cached within
+//! the output directory keyed by a hash of that structure and reused as long
+//! as the graph doesn't change
+
+use crate::{
+    dataflow::{constants::DDSHOW_VERSION, utils::XXHasher},
+    ui::{Edge, Node, Subgraph},
+};
+use anyhow::{Context, Result};
+use ddshow_types::{OperatorAddr, OperatorId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    hash::{BuildHasher, Hash, Hasher},
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// The file layouts are cached in, within the output directory
+const LAYOUT_CACHE_FILE: &str = "layout-cache.json";
+
+/// The approximate width of a single character within a node's label
+const CHAR_WIDTH: f64 = 8.0;
+/// The padding on each side of a node's label
+const NODE_PADDING: f64 = 10.0;
+/// The height of a single line of text
+pub(super) const LINE_HEIGHT: f64 = 18.0;
+/// The padding between a subgraph's border and its contents
+pub(super) const CLUSTER_PADDING: f64 = 20.0;
+/// The horizontal space between nodes within the same layer, the same
+/// as the `nodesep` given to dagre
+const NODE_SPACING: f64 = 50.0;
+/// The vertical space between layers, the same as the `ranksep` given to dagre
+const LAYER_SPACING: f64 = 50.0;
+
+/// The positions of every operator and subgraph within the graph
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub struct GraphLayout {
+    /// The hash of the graph structure that the layout was computed for
+    pub hash: u64,
+    pub width: f64,
+    pub height: f64,
+    pub nodes: Vec<NodePosition>,
+}
+
+/// The position of a single operator or subgraph, `x` and `y` are the center
+/// of the node the same as they are with dagre
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NodePosition {
+    pub addr: OperatorAddr,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The parts of an operator or subgraph that its layout depends on
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutNode<'a> {
+    pub addr: &'a OperatorAddr,
+    pub id: OperatorId,
+    pub name: &'a str,
+}
+
+/// Returns the cached layout of the graph if its structure hasn't changed
+/// since it was cached, otherwise lays it out and caches the new layout
+pub fn cached_layout(
+    output_dir: &Path,
+    nodes: &[Node],
+    subgraphs: &[Subgraph],
+    edges: &[Edge],
+) -> GraphLayout {
+    let nodes: Vec<_> = nodes
+        .iter()
+        .map(|node| LayoutNode {
+            addr: node.addr,
+            id: node.id,
+            name: node.name,
+        })
+        .collect();
+    let subgraphs: Vec<_> = subgraphs
+        .iter()
+        .map(|subgraph| LayoutNode {
+            addr: subgraph.addr,
+            id: subgraph.id,
+            name: subgraph.name,
+        })
+        .collect();
+    let edges: Vec<_> = edges.iter().map(|edge| (edge.src, edge.dest)).collect();
+
+    let hash = structure_hash(&nodes, &subgraphs, &edges);
+    let cache_path = output_dir.join(LAYOUT_CACHE_FILE);
+
+    match read_cache(&cache_path) {
+        Ok(layout) if layout.hash == hash => {
+            tracing::debug!(hash, "reusing the cached graph layout");
+            return layout;
+        }
+
+        Ok(_) => tracing::debug!(hash, "the graph changed since its layout was cached"),
+        Err(err) => tracing::debug!("failed to read the cached graph layout: {:?}", err),
+    }
+
+    let mut layout = layout_graph(&nodes, &subgraphs, &edges);
+    layout.hash = hash;
+
+    if let Err(err) = write_cache(&cache_path, &layout) {
+        tracing::warn!("failed to cache the graph layout: {:?}", err);
+    }
+
+    layout
+}
+
+fn read_cache(path: &Path) -> Result<GraphLayout> {
+    let file = File::open(path)
+        .with_context(|| anyhow::format_err!("failed to open layout cache '{}'", path.display()))?;
+
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| anyhow::format_err!("failed to decode layout cache '{}'", path.display()))
+}
+
+fn write_cache(path: &Path, layout: &GraphLayout) -> Result<()> {
+    let failed_write = || anyhow::format_err!("failed to write layout cache '{}'", path.display());
+
+    // Write to a temporary file first so that an interrupted write
+    // doesn't leave a corrupted cache behind
+    let temp_path = path.with_extension("json.tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path).with_context(failed_write)?);
+    serde_json::to_writer(&mut writer, layout).with_context(failed_write)?;
+    writer.flush().with_context(failed_write)?;
+    drop(writer);
+
+    fs::rename(&temp_path, path).with_context(failed_write)
+}
+
+/// Hashes everything that the layout depends on, which is the address and
+/// label of every node along with the channels between them. The version of
+/// ddshow is included so that layouts cached by other versions are recomputed
+fn structure_hash(
+    nodes: &[LayoutNode],
+    subgraphs: &[LayoutNode],
+    edges: &[(&OperatorAddr, &OperatorAddr)],
+) -> u64 {
+    let mut hasher = XXHasher::default().build_hasher();
+
+    DDSHOW_VERSION.hash(&mut hasher);
+
+    nodes.len().hash(&mut hasher);
+    for node in nodes {
+        (node.addr, node.id, node.name).hash(&mut hasher);
+    }
+
+    subgraphs.len().hash(&mut hasher);
+    for subgraph in subgraphs {
+        (subgraph.addr, subgraph.id, subgraph.name).hash(&mut hasher);
+    }
+
+    edges.len().hash(&mut hasher);
+    for edge in edges {
+        edge.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// A node or subgraph within a scope
+#[derive(Debug, Clone, Copy)]
+struct Item<'a> {
+    addr: &'a OperatorAddr,
+    width: f64,
+    height: f64,
+}
+
+/// Lays out every scope of the graph, innermost scopes first
+pub fn layout_graph(
+    nodes: &[LayoutNode],
+    subgraphs: &[LayoutNode],
+    edges: &[(&OperatorAddr, &OperatorAddr)],
+) -> GraphLayout {
+    let mut items: HashMap<&OperatorAddr, Item, XXHasher> = HashMap::default();
+    for node in nodes {
+        let width = label_width(node.name, node.id, node.addr) + NODE_PADDING * 2.0;
+        items.insert(
+            node.addr,
+            Item {
+                addr: node.addr,
+                width,
+                height: LINE_HEIGHT + NODE_PADDING * 2.0,
+            },
+        );
+    }
+
+    // Subgraphs start out just large enough for their label, which is all
+    // that empty subgraphs ever need
+    let mut subgraph_labels: HashMap<&[OperatorId], (&OperatorAddr, f64), XXHasher> =
+        HashMap::default();
+    for subgraph in subgraphs {
+        let label = label_width(subgraph.name, subgraph.id, subgraph.addr);
+        subgraph_labels.insert(subgraph.addr.as_slice(), (subgraph.addr, label));
+        items.insert(
+            subgraph.addr,
+            Item {
+                addr: subgraph.addr,
+                width: label + CLUSTER_PADDING * 2.0,
+                height: LINE_HEIGHT + CLUSTER_PADDING * 2.0,
+            },
+        );
+    }
+
+    // Every item belongs to the scope of its parent, items whose parents
+    // aren't subgraphs are laid out within the root scope
+    let mut scopes: HashMap<&[OperatorId], Vec<&OperatorAddr>, XXHasher> = HashMap::default();
+    for &addr in items.keys() {
+        let parent = &addr[..addr.len().saturating_sub(1)];
+        let scope = if subgraph_labels.contains_key(parent) {
+            parent
+        } else {
+            &[]
+        };
+
+        scopes.entry(scope).or_default().push(addr);
+    }
+    for children in scopes.values_mut() {
+        children.sort_unstable();
+    }
+
+    // Each channel is laid out within the innermost scope that contains both of its ends
+    let mut scope_edges: HashMap<&[OperatorId], Vec<(&[OperatorId], &[OperatorId])>, XXHasher> =
+        HashMap::default();
+    for &(src, dest) in edges {
+        let common = src
+            .iter()
+            .zip(dest.iter())
+            .take_while(|(src, dest)| src == dest)
+            .count();
+
+        // Channels between a subgraph and its own contents don't affect its layout
+        if common < src.len() && common < dest.len() {
+            scope_edges
+                .entry(&src[..common])
+                .or_default()
+                .push((&src[..common + 1], &dest[..common + 1]));
+        }
+    }
+
+    // Innermost scopes are laid out first so that the size of each subgraph
+    // is known by the time its parent scope is laid out
+    let mut scope_order: Vec<_> = scopes.keys().copied().collect();
+    scope_order.sort_unstable_by(|left, right| right.len().cmp(&left.len()).then(left.cmp(right)));
+
+    // The position of every item relative to the top left of its scope's contents
+    let mut relative: HashMap<&OperatorAddr, (f64, f64), XXHasher> = HashMap::default();
+    let mut root_size = (0.0, 0.0);
+    for scope in scope_order {
+        let children = &scopes[scope];
+        let sizes: Vec<_> = children
+            .iter()
+            .map(|addr| {
+                items
+                    .get(addr)
+                    .map_or((0.0, 0.0), |item| (item.width, item.height))
+            })
+            .collect();
+
+        let indices: HashMap<&[OperatorId], usize, XXHasher> = children
+            .iter()
+            .enumerate()
+            .map(|(idx, addr)| (addr.as_slice(), idx))
+            .collect();
+        let mut local_edges: Vec<_> = scope_edges
+            .get(scope)
+            .into_iter()
+            .flatten()
+            .filter_map(|(src, dest)| Some((*indices.get(src)?, *indices.get(dest)?)))
+            .filter(|(src, dest)| src != dest)
+            .collect();
+        local_edges.sort_unstable();
+        local_edges.dedup();
+
+        let layers = order_layers(&assign_layers(children.len(), &local_edges), &local_edges);
+        let (positions, size) = place_layers(&sizes, &layers);
+        for (&addr, position) in children.iter().zip(positions) {
+            relative.insert(addr, position);
+        }
+
+        // Subgraphs wrap their contents and are at least as wide as their label
+        match subgraph_labels.get(scope) {
+            Some(&(addr, label)) => {
+                items.insert(
+                    addr,
+                    Item {
+                        addr,
+                        width: size.0.max(label) + CLUSTER_PADDING * 2.0,
+                        height: size.1 + LINE_HEIGHT + CLUSTER_PADDING * 2.0,
+                    },
+                );
+            }
+
+            None => root_size = size,
+        }
+    }
+
+    // Resolve each item's absolute position from the outermost scopes inwards
+    let mut positions = Vec::with_capacity(items.len());
+    let mut pending = vec![(&[][..], (0.0, 0.0))];
+    while let Some((scope, origin)) = pending.pop() {
+        for &addr in scopes.get(scope).into_iter().flatten() {
+            let item = items[addr];
+            let (x, y) = relative[addr];
+            let (x, y) = (origin.0 + x, origin.1 + y);
+
+            positions.push(NodePosition {
+                addr: item.addr.clone(),
+                x: x + item.width / 2.0,
+                y: y + item.height / 2.0,
+                width: item.width,
+                height: item.height,
+            });
+
+            if scopes.contains_key(addr.as_slice()) {
+                pending.push((
+                    addr.as_slice(),
+                    (x + CLUSTER_PADDING, y + LINE_HEIGHT + CLUSTER_PADDING),
+                ));
+            }
+        }
+    }
+    positions.sort_unstable_by(|left, right| left.addr.cmp(&right.addr));
+
+    GraphLayout {
+        hash: 0,
+        width: root_size.0,
+        height: root_size.1,
+        nodes: positions,
+    }
+}
+
+/// The label the graph gives the node
+pub(super) fn node_label(name: &str, id: OperatorId, addr: &OperatorAddr) -> String {
+    format!("{} @ {}, {}", name, id, addr)
+}
+
+/// The approximate width of the node's label
+fn label_width(name: &str, id: OperatorId, addr: &OperatorAddr) -> f64 {
+    node_label(name, id, addr).chars().count() as f64 * CHAR_WIDTH
+}
+
+/// Assigns each node to a layer by the longest path leading up to it, cycles
+/// are broken at the first node that's still waiting on its inputs
+fn assign_layers(nodes: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let (mut inputs, mut outputs) = (vec![0_usize; nodes], vec![Vec::new(); nodes]);
+    for &(src, dest) in edges {
+        inputs[dest] += 1;
+        outputs[src].push(dest);
+    }
+
+    let (mut layers, mut placed) = (vec![0; nodes], vec![false; nodes]);
+    let mut ready: Vec<_> = (0..nodes).rev().filter(|&node| inputs[node] == 0).collect();
+
+    let mut remaining = nodes;
+    while remaining != 0 {
+        let node = match ready.pop() {
+            Some(node) => node,
+            None => match (0..nodes).find(|&node| !placed[node]) {
+                Some(node) => node,
+                None => break,
+            },
+        };
+
+        if placed[node] {
+            continue;
+        }
+        placed[node] = true;
+        remaining -= 1;
+
+        for &dest in &outputs[node] {
+            if !placed[dest] {
+                layers[dest] = layers[dest].max(layers[node] + 1);
+
+                inputs[dest] -= 1;
+                if inputs[dest] == 0 {
+                    ready.push(dest);
+                }
+            }
+        }
+    }
+
+    layers
+}
+
+/// Groups nodes by their layer and orders each layer by the average position
+/// of each node's inputs to cut down on crossed edges
+fn order_layers(layers: &[usize], edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut ordered = vec![Vec::new(); layers.iter().max().map_or(0, |&max| max + 1)];
+    for (node, &layer) in layers.iter().enumerate() {
+        ordered[layer].push(node);
+    }
+
+    let mut inputs = vec![Vec::new(); layers.len()];
+    for &(src, dest) in edges {
+        inputs[dest].push(src);
+    }
+
+    let mut positions = vec![0.0; layers.len()];
+    for layer in ordered.iter_mut() {
+        let mut barycenters: Vec<_> = layer
+            .iter()
+            .enumerate()
+            .map(|(position, &node)| {
+                let placed_inputs: Vec<_> = inputs[node]
+                    .iter()
+                    .filter(|&&src| layers[src] < layers[node])
+                    .map(|&src| positions[src])
+                    .collect();
+
+                let barycenter = if placed_inputs.is_empty() {
+                    position as f64
+                } else {
+                    placed_inputs.iter().sum::<f64>() / placed_inputs.len() as f64
+                };
+
+                (barycenter, node)
+            })
+            .collect();
+        barycenters.sort_by(|(left, _), (right, _)| left.partial_cmp(right).unwrap());
+
+        for (position, (_, node)) in barycenters.iter().enumerate() {
+            layer[position] = *node;
+            positions[*node] = position as f64;
+        }
+    }
+
+    ordered
+}
+
+/// Places the nodes of each layer side by side, centering each layer
+/// horizontally and each node vertically within its layer
+///
+/// Returns the top left corner of every node along with the size of the
+/// whole layout
+fn place_layers(sizes: &[(f64, f64)], layers: &[Vec<usize>]) -> (Vec<(f64, f64)>, (f64, f64)) {
+    let layer_width = |layer: &[usize]| {
+        layer.iter().map(|&node| sizes[node].0).sum::<f64>()
+            + NODE_SPACING * layer.len().saturating_sub(1) as f64
+    };
+    let widest = layers
+        .iter()
+        .map(|layer| layer_width(layer))
+        .fold(0.0, f64::max);
+
+    let mut positions = vec![(0.0, 0.0); sizes.len()];
+    let mut y = 0.0;
+    for (depth, layer) in layers.iter().enumerate() {
+        if depth != 0 {
+            y += LAYER_SPACING;
+        }
+
+        let height = layer.iter().map(|&node| sizes[node].1).fold(0.0, f64::max);
+        let mut x = (widest - layer_width(layer)) / 2.0;
+
+        for &node in layer {
+            let (width, node_height) = sizes[node];
+            positions[node] = (x, y + (height - node_height) / 2.0);
+            x += width + NODE_SPACING;
+        }
+
+        y += height;
+    }
+
+    (positions, (widest, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assign_layers, order_layers, place_layers, LAYER_SPACING, NODE_SPACING};
+
+    #[test]
+    fn layers_follow_the_longest_path() {
+        // 0 -> 1 -> 2 and 0 -> 2, with a feedback edge from 2 back into 1
+        let edges = [(0, 1), (1, 2), (0, 2), (2, 1), (3, 3)];
+
+        let layers = assign_layers(4, &edges);
+        assert_eq!(layers, vec![0, 1, 2, 0]);
+        assert_eq!(
+            order_layers(&layers, &edges),
+            vec![vec![0, 3], vec![1], vec![2]],
+        );
+    }
+
+    #[test]
+    fn layers_are_centered() {
+        let sizes = [(100.0, 40.0), (50.0, 20.0), (20.0, 20.0)];
+        let (positions, size) = place_layers(&sizes, &[vec![0, 1], vec![2]]);
+
+        let widest = 150.0 + NODE_SPACING;
+        assert_eq!(size, (widest, 40.0 + LAYER_SPACING + 20.0));
+        assert_eq!(
+            positions,
+            vec![
+                (0.0, 0.0),
+                (150.0, 10.0),
+                ((widest - 20.0) / 2.0, 40.0 + LAYER_SPACING),
+            ],
+        );
+    }
+}
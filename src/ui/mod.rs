@@ -1,29 +1,66 @@
+mod layout;
+#[cfg(feature = "live-server")]
+pub mod svg;
+#[cfg(feature = "html-graph")]
+mod theme;
+
+pub use layout::{GraphLayout, NodePosition};
+
+#[cfg(feature = "html-graph")]
+use crate::dataflow::constants::OUTPUT_BUFFER_SIZE;
 use crate::{
     args::Args,
+    custom_streams::CustomEvent,
     dataflow::{
         utils::{OpKey, XXHasher},
-        ArrangementStats as DataflowArrangementStats, DataflowData, OperatorShape, SplineLevel,
-        Summation, TimelineEvent as RawTimelineEvent,
+        ActivationPercentiles, ArrangementStats as DataflowArrangementStats, DataflowData,
+        EventKind, OperatorShape, ProgressChannelStats, SplineLevel, Summation, TimelineBucket,
+        TimelineEvent as RawTimelineEvent, TIMELINE_TIER_WIDTHS,
     },
+    operator_groups::GroupStats,
+    report::analysis::worst_anomalies,
 };
 use abomonation_derive::Abomonation;
 use anyhow::{Context as _, Result};
+#[cfg(feature = "html-graph")]
+use base64::write::EncoderWriter;
 use bytecheck::CheckBytes;
-use ddshow_types::{ChannelId, OperatorAddr, OperatorId, PortId, WorkerId};
+#[cfg(feature = "html-graph")]
+use crossbeam_utils::thread;
+use ddshow_types::{
+    differential_logging::DifferentialEvent,
+    timely_logging::{OperatesEvent, TimelyEvent},
+    ChannelId, OperatorAddr, OperatorId, PortId, WorkerId,
+};
+#[cfg(feature = "html-graph")]
+use flate2::{write::GzEncoder, Compression};
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    fs::{self, File},
-    io::BufWriter,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs,
+    ops::AddAssign,
+    path::Path,
     time::Duration,
 };
+#[cfg(feature = "html-graph")]
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    panic,
+};
+#[cfg(feature = "html-graph")]
 use tera::{Context, Tera};
 
+#[cfg(feature = "html-graph")]
 const GRAPH_HTML: &str = include_str!("graph.html");
+#[cfg(feature = "html-graph")]
 const GRAPH_CSS: &str = include_str!("graph.css");
+#[cfg(feature = "html-graph")]
 const GRAPH_JS: &str = include_str!("graph.js");
+#[cfg(feature = "html-graph")]
 const D3_JS: &str = include_str!("d3.v5.js");
+#[cfg(feature = "html-graph")]
 const DAGRE_JS: &str = include_str!("dagre-d3.js");
 
 #[allow(clippy::too_many_arguments)]
@@ -40,6 +77,11 @@ pub fn render(
     agg_arrangement_stats: &HashMap<OperatorId, &DataflowArrangementStats, XXHasher>,
     agg_activations: &HashMap<OperatorId, Vec<&Vec<(Duration, Duration)>>, XXHasher>,
     spline_levels: &HashMap<OpKey, Vec<SplineLevel>, XXHasher>,
+    operator_groups: &[GroupStats],
+    target_runs: &[RunStats],
+    progress_channels: &[ProgressChannelStats],
+    custom_events: &[CustomEvent],
+    workers: &[WorkerId],
 ) -> Result<()> {
     let output_dir = args.output_dir.canonicalize().with_context(|| {
         anyhow::anyhow!("failed to canonicalize '{}'", args.output_dir.display())
@@ -48,11 +90,6 @@ pub fn render(
 
     fs::create_dir_all(&output_dir).context("failed to create output directory")?;
 
-    fs::write(output_dir.join("d3.v5.js"), D3_JS)
-        .context("failed to write output graph to file")?;
-    fs::write(output_dir.join("dagre-d3.js"), DAGRE_JS)
-        .context("failed to write output graph to file")?;
-
     let vega_data = vega_data(
         data,
         arrangement_map,
@@ -62,17 +99,125 @@ pub fn render(
         agg_activations,
         spline_levels,
     );
+    let mut search_index = search_index(&data.name_lookup, &data.addr_lookup, &data.subgraphs);
+    search_index.retain(|entry| args.renders_worker(entry.worker));
+    let operator_lanes = operator_lanes(
+        data.timeline_events
+            .iter()
+            .filter(|event| args.renders_worker(event.worker)),
+        &data.name_lookup,
+        &data.addr_lookup,
+    );
+    let operator_lifespans = operator_lifespans(
+        data.operator_lifespans
+            .iter()
+            .filter(|((worker, _), _)| args.renders_worker(*worker)),
+        &data.name_lookup,
+        &data.addr_lookup,
+    );
+    let worker_utilization: Vec<_> = data
+        .worker_utilization
+        .iter()
+        .filter(|(worker, _)| args.renders_worker(*worker))
+        .copied()
+        .collect();
+    let rendered_anomalies: Vec<_> = data
+        .activation_anomalies
+        .iter()
+        .filter(|((worker, _), _)| args.renders_worker(*worker))
+        .copied()
+        .collect();
+    // With `--no-timeline` there's no timeline to mark the anomalies on
+    let activation_anomalies: Vec<_> = if !args.runs_timeline() {
+        Vec::new()
+    } else {
+        worst_anomalies(&rendered_anomalies, MAX_TIMELINE_ANOMALIES)
+            .into_iter()
+            .map(|(_, &anomaly)| anomaly)
+            .collect()
+    };
+    let event_kinds: Vec<_> = data
+        .event_kinds
+        .iter()
+        .filter(|((worker, _), _)| args.renders_worker(*worker))
+        .copied()
+        .collect();
+    let arrangement_sizes: Vec<_> = data
+        .arrangement_details
+        .iter()
+        .filter(|((worker, _), stats)| {
+            args.renders_worker(*worker) && !stats.size_series.is_empty()
+        })
+        .map(|&((worker, _), ref stats)| ArrangementSizes {
+            worker,
+            addr: &stats.operator_addr,
+            sizes: thin_sizes(&stats.size_series, MAX_ARRANGEMENT_SIZE_POINTS),
+        })
+        .collect();
+    let exchange_matrix: Vec<_> = data
+        .exchange_matrix
+        .iter()
+        .filter(|((source, target), _)| {
+            args.renders_worker(*source) && args.renders_worker(*target)
+        })
+        .copied()
+        .collect();
+    let graph_layout = layout::cached_layout(&output_dir, nodes, subgraphs, edges);
+
     let graph_data = GraphData {
         nodes,
         subgraphs,
         edges,
+        graph_layout: &graph_layout,
         palette_colors,
         timeline_events: &data.timeline_events,
+        timeline_tier_widths: if !args.runs_timeline() {
+            &[]
+        } else {
+            &TIMELINE_TIER_WIDTHS
+        },
+        timeline_buckets: &data.timeline_buckets,
+        operator_lanes: &operator_lanes,
+        operator_lifespans: &operator_lifespans,
+        worker_utilization: &worker_utilization,
+        exchange_matrix: &exchange_matrix,
+        event_kinds: &event_kinds,
+        activation_anomalies: &activation_anomalies,
+        arrangement_sizes: &arrangement_sizes,
         operator_shapes: &data.operator_shapes,
         // operator_progress: &data.operator_progress,
         vega_data: &vega_data,
+        operator_groups,
+        target_runs,
+        progress_channels,
+        frontier_progression: &data.frontier_progression,
+        dataflow_frontiers: &data.dataflow_frontiers,
+        epoch_latencies: &data.epoch_latencies,
+        custom_events,
+        search_index: &search_index,
+        workers,
+        color_by: args.color_by.to_string(),
     };
 
+    write_graph(args, &output_dir, &graph_data)
+}
+
+/// Returns `true` if ddshow was built with the html graph
+pub const fn graph_enabled() -> bool {
+    cfg!(feature = "html-graph")
+}
+
+/// Renders the graph's html, css and javascript into the output directory
+#[cfg(feature = "html-graph")]
+fn write_graph(args: &Args, output_dir: &Path, graph_data: &GraphData) -> Result<()> {
+    let data_dir = output_dir.join("data");
+    fs::create_dir_all(&data_dir).with_context(|| {
+        anyhow::format_err!(
+            "failed to create graph data directory '{}'",
+            data_dir.display()
+        )
+    })?;
+
     let mut context =
         Context::from_serialize(graph_data).context("failed to render graph context as json")?;
 
@@ -82,39 +227,167 @@ pub fn render(
     tera.add_raw_template("graph_html", GRAPH_HTML)
         .context("internal error: failed to add graph.html template to tera")?;
 
-    // Render the javascript file & write it to disk
-    let js_file = File::create(args.output_dir.join("graph.js")).with_context(|| {
-        anyhow::format_err!(
-            "failed to create graph.js file at '{}'",
-            args.output_dir.join("graph.js").display(),
-        )
-    })?;
-    tera.render_to("graph_js", &context, BufWriter::new(js_file))
-        .with_context(|| {
+    // The static assets and the data files are written while the templates are
+    // rendered, only graph.html needs to know which data files were written
+    thread::scope(|scope| {
+        let assets = scope.spawn(|_| write_assets(output_dir));
+        let data_files =
+            scope.spawn(|_| graph_data.write_data_files(&data_dir, args.compress_data));
+
+        // Render the javascript file & write it to disk
+        let js_file = File::create(output_dir.join("graph.js")).with_context(|| {
             anyhow::format_err!(
-                "failed to render graph.js to {}",
-                args.output_dir.join("graph.js").display(),
+                "failed to create graph.js file at '{}'",
+                output_dir.join("graph.js").display(),
             )
         })?;
+        tera.render_to("graph_js", &context, BufWriter::new(js_file))
+            .with_context(|| {
+                anyhow::format_err!(
+                    "failed to render graph.js to {}",
+                    output_dir.join("graph.js").display(),
+                )
+            })?;
 
-    // Add the stylesheet into the tera context
-    context.insert("stylesheet", GRAPH_CSS);
+        let data_files = data_files
+            .join()
+            .unwrap_or_else(|err| panic::resume_unwind(err))?;
+        context.insert("data_files", &data_files);
+        context.insert("compressed_data", &args.compress_data);
 
-    // Render the html file & write it to disk
-    let html_file = File::create(args.output_dir.join("graph.html")).with_context(|| {
-        anyhow::format_err!(
-            "failed to create graph.html file at '{}'",
-            args.output_dir.join("graph.html").display(),
-        )
-    })?;
-    tera.render_to("graph_html", &context, BufWriter::new(html_file))
-        .with_context(|| {
+        // Add the themed stylesheet into the tera context
+        let stylesheet = format!(
+            "{}\n{}",
+            theme::theme_variables(args.theme, args.palette),
+            GRAPH_CSS,
+        );
+        context.insert("stylesheet", &stylesheet);
+
+        // Render the html file & write it to disk
+        let html_file = File::create(output_dir.join("graph.html")).with_context(|| {
             anyhow::format_err!(
-                "failed to render graph.html to {}",
-                args.output_dir.join("graph.html").display(),
+                "failed to create graph.html file at '{}'",
+                output_dir.join("graph.html").display(),
             )
         })?;
+        tera.render_to("graph_html", &context, BufWriter::new(html_file))
+            .with_context(|| {
+                anyhow::format_err!(
+                    "failed to render graph.html to {}",
+                    output_dir.join("graph.html").display(),
+                )
+            })?;
+
+        assets
+            .join()
+            .unwrap_or_else(|err| panic::resume_unwind(err))
+    })
+    .unwrap_or_else(|err| panic::resume_unwind(err))
+}
+
+/// Writes the graph's static javascript libraries
+#[cfg(feature = "html-graph")]
+fn write_assets(output_dir: &Path) -> Result<()> {
+    fs::write(output_dir.join("d3.v5.js"), D3_JS)
+        .context("failed to write output graph to file")?;
+    fs::write(output_dir.join("dagre-d3.js"), DAGRE_JS)
+        .context("failed to write output graph to file")
+}
+
+#[cfg(feature = "html-graph")]
+impl GraphData<'_> {
+    /// Streams each of the graph's large arrays into its own script within
+    /// `data_dir`, returning the name of every file that was written
+    ///
+    /// The scripts are loaded by the html file before `graph.js` runs, browsers
+    /// refuse to `fetch()` files from `file://` urls so plain json files
+    /// couldn't be loaded when the graph is opened straight from disk.
+    /// Compressed files hold base64 encoded gzipped json instead, which
+    /// the html file inflates before loading `graph.js`
+    fn write_data_files(&self, data_dir: &Path, compressed: bool) -> Result<Vec<&'static str>> {
+        // Each file is serialized on its own thread
+        macro_rules! write_fields {
+            ($($field:ident),* $(,)?) => {{
+                let results = thread::scope(|scope| {
+                    let handles = vec![$(
+                        scope.spawn(move |_| write_data_file(data_dir, stringify!($field), self.$field, compressed)),
+                    )*];
+
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().unwrap_or_else(|err| panic::resume_unwind(err)))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_else(|err| panic::resume_unwind(err));
 
+                results.into_iter().collect::<Result<()>>()?;
+                vec![$(stringify!($field)),*]
+            }};
+        }
+
+        Ok(write_fields!(
+            nodes,
+            subgraphs,
+            edges,
+            timeline_events,
+            timeline_buckets,
+            operator_lanes,
+            operator_lifespans,
+            arrangement_sizes,
+            operator_shapes,
+            vega_data,
+            progress_channels,
+            frontier_progression,
+            dataflow_frontiers,
+            epoch_latencies,
+            custom_events,
+            search_index,
+            graph_layout,
+        ))
+    }
+}
+
+/// Serializes `value` straight into `data_dir/{name}.js` without buffering it,
+/// compressed files are gzipped and base64 encoded on the way out
+#[cfg(feature = "html-graph")]
+fn write_data_file<T>(data_dir: &Path, name: &str, value: &T, compressed: bool) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    let path = data_dir.join(format!("{}.js", name));
+    let failed_write =
+        || anyhow::format_err!("failed to write graph data file '{}'", path.display());
+
+    let file = File::create(&path).with_context(failed_write)?;
+    let mut writer = BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, file);
+
+    if compressed {
+        write!(writer, "ddshow_compressed.{} = \"", name).with_context(failed_write)?;
+
+        let mut encoder = GzEncoder::new(
+            EncoderWriter::new(&mut writer, base64::STANDARD),
+            Compression::default(),
+        );
+        serde_json::to_writer(&mut encoder, value).with_context(failed_write)?;
+        encoder
+            .finish()
+            .and_then(|mut base64_writer| base64_writer.finish().map(drop))
+            .with_context(failed_write)?;
+
+        writer.write_all(b"\";\n").with_context(failed_write)?;
+    } else {
+        write!(writer, "ddshow_data.{} = ", name).with_context(failed_write)?;
+        serde_json::to_writer(&mut writer, value).with_context(failed_write)?;
+        writer.write_all(b";\n").with_context(failed_write)?;
+    }
+
+    writer.flush().with_context(failed_write)
+}
+
+/// The graph's assets weren't compiled in, so there's nothing to write
+#[cfg(not(feature = "html-graph"))]
+fn write_graph(_args: &Args, _output_dir: &Path, _graph_data: &GraphData) -> Result<()> {
+    tracing::warn!("ddshow was built without the `html-graph` feature, skipping graph rendering");
     Ok(())
 }
 
@@ -133,6 +406,7 @@ pub struct VegaNode<'a> {
     pub max_arrangement_size: Option<usize>,
     pub min_arrangement_size: Option<usize>,
     pub arrangement_batches: Option<usize>,
+    pub arrangement_merges: Option<usize>,
     pub node_kind: VegaNodeKind,
     pub per_worker: Vec<(WorkerId, VegaWorkerNode)>,
 }
@@ -148,6 +422,7 @@ pub struct VegaWorkerNode {
     pub max_arrangement_size: Option<usize>,
     pub min_arrangement_size: Option<usize>,
     pub arrangement_batches: Option<usize>,
+    pub arrangement_merges: Option<usize>,
     pub spline_levels: Option<Vec<(u64, usize, usize)>>,
 }
 
@@ -247,6 +522,7 @@ fn vega_data<'a>(
                         max_arrangement_size: arranged.map(|arr| arr.max_size),
                         min_arrangement_size: arranged.map(|arr| arr.min_size),
                         arrangement_batches: arranged.map(|arr| arr.batches),
+                        arrangement_merges: arranged.map(|arr| arr.merges),
                         spline_levels,
                     };
 
@@ -267,6 +543,7 @@ fn vega_data<'a>(
                 max_arrangement_size: arranged.map(|arr| arr.max_size),
                 min_arrangement_size: arranged.map(|arr| arr.min_size),
                 arrangement_batches: arranged.map(|arr| arr.batches),
+                arrangement_merges: arranged.map(|arr| arr.merges),
                 node_kind,
                 per_worker,
             })
@@ -338,9 +615,92 @@ pub struct ProgramStats {
     pub arrangements: usize,
     pub events: usize,
     pub runtime: Duration,
+    /// The separate runs of the target process, there's only more
+    /// than one if the target reconnected after its logs closed
+    pub runs: Vec<RunStats>,
+    /// The number of events that were dropped because an
+    /// ingestion buffer was full
+    pub dropped_events: usize,
+    /// The records and batches sent over every channel
+    pub messages: ChannelMessageStats,
     // TODO: Missing nodes & edges
 }
 
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct RunStats {
+    pub run: usize,
+    /// The time the run's first connection was made at, every run
+    /// is shifted to start after the end of the previous one
+    pub started_at: Duration,
+    pub ended_at: Duration,
+    /// The number of log streams that connected during the run
+    pub connections: usize,
+}
+
+impl ProgramStats {
+    /// Fills in the stats from whatever has arrived so far along with the target's
+    /// runs and the number of events dropped so far, anything that hasn't arrived
+    /// yet is left defaulted so that live mode can show stats as soon as the first
+    /// events come in
+    pub fn partial(data: &DataflowData, runs: &[RunStats], dropped_events: usize) -> Self {
+        let start = data
+            .total_runtime
+            .iter()
+            .map(|&(_, (start, _))| start)
+            .min();
+        let end = data.total_runtime.iter().map(|&(_, (_, end))| end).max();
+
+        let mut messages = ChannelMessageStats::default();
+        for &(_, worker_messages) in data.channel_messages.iter() {
+            messages += worker_messages;
+        }
+
+        Self {
+            workers: data.workers.len(),
+            dataflows: data.dataflows.len(),
+            operators: data.operators.len(),
+            subgraphs: data.subgraphs.len(),
+            channels: data.channels.len(),
+            arrangements: data.arrangement_ids.len(),
+            events: data.event_kinds.iter().map(|&(_, count)| count).sum(),
+            runtime: start
+                .zip(end)
+                .and_then(|(start, end)| end.checked_sub(start))
+                .unwrap_or_default(),
+            runs: runs.to_vec(),
+            dropped_events,
+            messages,
+        }
+    }
+}
+
+impl RunStats {
+    pub const fn new(run: usize) -> Self {
+        Self {
+            run,
+            started_at: Duration::from_secs(0),
+            ended_at: Duration::from_secs(0),
+            connections: 0,
+        }
+    }
+}
+
 // - Worker stats
 //   - total worker runtime
 //  - # dataflows
@@ -380,9 +740,310 @@ pub struct WorkerStats {
     pub events: usize,
     pub runtime: Duration,
     pub dataflow_addrs: Vec<OperatorAddr>,
+    /// The records and batches sent over the worker's channels
+    pub messages: ChannelMessageStats,
+    /// How the worker's runtime was split between work, idling and parking
+    pub utilization: WorkerUtilization,
+    /// The number of events of each kind that the worker logged, which add up to `events`
+    pub event_kinds: Vec<(LoggedEventKind, usize)>,
+    /// How long and why the worker parked
+    pub parks: ParkStats,
     // TODO: Missing nodes & edges
 }
 
+impl WorkerStats {
+    /// Fills in the stats of `worker` from whatever has arrived so far,
+    /// defaulting everything that hasn't
+    pub fn partial(data: &DataflowData, worker: WorkerId) -> Self {
+        let mut messages = ChannelMessageStats::default();
+        for &((werker, _), worker_messages) in data.channel_messages.iter() {
+            if werker == worker {
+                messages += worker_messages;
+            }
+        }
+
+        let event_kinds: Vec<_> = data
+            .event_kinds
+            .iter()
+            .filter(|&&((werker, _), _)| werker == worker)
+            .map(|&((_, kind), count)| (kind, count))
+            .collect();
+
+        Self {
+            id: worker,
+            dataflows: data.dataflows.len(),
+            operators: data.operators.len(),
+            subgraphs: data.subgraphs.len(),
+            channels: data.channels.len(),
+            arrangements: data
+                .arrangement_ids
+                .iter()
+                .filter(|&&(werker, _)| werker == worker)
+                .count(),
+            events: event_kinds.iter().map(|&(_, count)| count).sum(),
+            runtime: data
+                .total_runtime
+                .iter()
+                .find(|&&(werker, _)| werker == worker)
+                .and_then(|&(_, (start, end))| end.checked_sub(start))
+                .unwrap_or_default(),
+            dataflow_addrs: data.dataflows.clone(),
+            messages,
+            utilization: data
+                .worker_utilization
+                .iter()
+                .find(|&&(werker, _)| werker == worker)
+                .map(|&(_, utilization)| utilization)
+                .unwrap_or_default(),
+            event_kinds,
+            parks: data
+                .park_stats
+                .iter()
+                .find(|&&(werker, _)| werker == worker)
+                .map(|&(_, parks)| parks)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// How often and for how long a worker parked
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct ParkStats {
+    /// The parks that were ended by an unpark
+    pub parks: usize,
+    /// Parks with a timeout, taken while the worker had delayed activations pending
+    pub timed: usize,
+    /// Parks without a timeout, taken while the worker had nothing left to do
+    pub indefinite: usize,
+    /// Timed parks that lasted for their entire timeout
+    pub timed_out: usize,
+    pub total: Duration,
+    pub max: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+}
+
+/// The kind of a logged timely or differential event
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub enum LoggedEventKind {
+    Operates,
+    Channels,
+    Schedule,
+    Messages,
+    PushProgress,
+    GuardedProgress,
+    GuardedMessage,
+    Park,
+    Application,
+    Input,
+    CommChannels,
+    Shutdown,
+    Text,
+    Batch,
+    Merge,
+    MergeShortfall,
+    Drop,
+    TraceShare,
+}
+
+impl From<&TimelyEvent> for LoggedEventKind {
+    fn from(event: &TimelyEvent) -> Self {
+        match event {
+            TimelyEvent::Operates(_) => Self::Operates,
+            TimelyEvent::Channels(_) => Self::Channels,
+            TimelyEvent::Schedule(_) => Self::Schedule,
+            TimelyEvent::Messages(_) => Self::Messages,
+            TimelyEvent::PushProgress(_) => Self::PushProgress,
+            TimelyEvent::GuardedProgress(_) => Self::GuardedProgress,
+            TimelyEvent::GuardedMessage(_) => Self::GuardedMessage,
+            TimelyEvent::Park(_) => Self::Park,
+            TimelyEvent::Application(_) => Self::Application,
+            TimelyEvent::Input(_) => Self::Input,
+            TimelyEvent::CommChannels(_) => Self::CommChannels,
+            TimelyEvent::Shutdown(_) => Self::Shutdown,
+            TimelyEvent::Text(_) => Self::Text,
+        }
+    }
+}
+
+impl From<&DifferentialEvent> for LoggedEventKind {
+    fn from(event: &DifferentialEvent) -> Self {
+        match event {
+            DifferentialEvent::Batch(_) => Self::Batch,
+            DifferentialEvent::Merge(_) => Self::Merge,
+            DifferentialEvent::MergeShortfall(_) => Self::MergeShortfall,
+            DifferentialEvent::Drop(_) => Self::Drop,
+            DifferentialEvent::TraceShare(_) => Self::TraceShare,
+        }
+    }
+}
+
+/// The loop iterations that an iterative scope ran for a single outer timestamp
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct IterationStats {
+    /// The outer timestamp that the iterations were for
+    pub epoch: String,
+    pub iterations: u64,
+    /// The time between the first and last iteration being released
+    pub total: Duration,
+    /// The time between each iteration being released and the next
+    pub iteration_times: AggregatedStats<Duration>,
+}
+
+/// The end-to-end latency of a single timestamp within a dataflow, from the first
+/// pointstamp carrying it until the dataflow's frontier passed it
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct EpochLatency {
+    pub timestamp: String,
+    pub first_seen: Duration,
+    pub completed: Duration,
+    pub latency: Duration,
+}
+
+impl EpochLatency {
+    pub fn new(timestamp: String, first_seen: Duration, completed: Duration) -> Self {
+        Self {
+            timestamp,
+            first_seen,
+            completed,
+            latency: completed.checked_sub(first_seen).unwrap_or_default(),
+        }
+    }
+}
+
+/// How a worker's runtime was split between running operators, spinning idly
+/// and being parked
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct WorkerUtilization {
+    pub runtime: Duration,
+    /// The time spent running operators
+    pub busy: Duration,
+    /// The time spent parked
+    pub parked: Duration,
+    /// The rest of the worker's runtime
+    pub idle: Duration,
+    /// The portion of the worker's busy time that went towards handling
+    /// progress updates
+    pub progress: Duration,
+}
+
+impl WorkerUtilization {
+    pub fn new(runtime: Duration, busy: Duration, parked: Duration, progress: Duration) -> Self {
+        Self {
+            runtime,
+            busy,
+            parked,
+            progress: progress.min(busy),
+            idle: runtime
+                .checked_sub(busy)
+                .and_then(|rest| rest.checked_sub(parked))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The percentage of the worker's runtime that `time` takes up
+    pub fn percentage(&self, time: Duration) -> f64 {
+        if self.runtime.as_nanos() == 0 {
+            0.0
+        } else {
+            time.as_secs_f64() / self.runtime.as_secs_f64() * 100.0
+        }
+    }
+
+    /// The percentage of the worker's busy time that was spent on progress
+    /// tracking instead of operator work
+    pub fn progress_overhead(&self) -> f64 {
+        if self.busy.as_nanos() == 0 {
+            0.0
+        } else {
+            self.progress.as_secs_f64() / self.busy.as_secs_f64() * 100.0
+        }
+    }
+}
+
 // - Dataflow stats
 //   - creation time
 //   - drop time
@@ -414,6 +1075,15 @@ pub struct DataflowStats {
     pub subgraphs: usize,
     pub channels: usize,
     pub lifespan: Lifespan,
+    /// The time the dataflow was scheduled for, which contains the time
+    /// of every operator within it
+    pub total_activation_time: Duration,
+    /// The operator activations and message batches logged within the dataflow
+    pub events: usize,
+    /// The records sent over the dataflow's channels
+    pub records_exchanged: usize,
+    /// The largest any arrangement within the dataflow grew, only with differential logging
+    pub peak_arrangement_size: Option<usize>,
     // TODO: Arrangements within the current dataflow
 }
 
@@ -460,8 +1130,17 @@ pub struct NodeStats {
     pub lifespan: Lifespan,
     pub kind: NodeKind,
     pub activations: AggregatedStats<Duration>,
+    /// Where the operator was defined, parsed from its name
+    pub location: Option<SourceLocation>,
+    pub throughput: Option<Throughput>,
+    /// How unevenly the operator's work was spread across workers
+    pub skew: Option<WorkerSkew>,
+    /// The operator's activations within each `--histogram-buckets` bucket
+    pub histogram: Vec<HistogramBucket>,
 }
 
+/// The number of an operator's activations that took between `lower`
+/// and `upper`, the final bucket of a histogram has no upper bound
 #[derive(
     Debug,
     Clone,
@@ -471,6 +1150,7 @@ pub struct NodeStats {
     PartialOrd,
     Ord,
     Hash,
+    Default,
     Deserialize,
     Serialize,
     Abomonation,
@@ -479,18 +1159,45 @@ pub struct NodeStats {
     RkyvDeserialize,
 )]
 #[archive_attr(derive(CheckBytes))]
-pub enum NodeKind {
-    Operator,
-    Subgraph,
-    Dataflow,
+pub struct HistogramBucket {
+    pub lower: Duration,
+    pub upper: Option<Duration>,
+    pub count: usize,
 }
 
-impl Default for NodeKind {
-    fn default() -> Self {
-        Self::Operator
+/// Builds the full histogram described by the sorted `bounds` from the
+/// `(bucket, count)` pairs of an operator, filling in empty buckets and
+/// leaving operators without any activations with an empty histogram
+pub fn activation_histogram<I>(bounds: &[Duration], counts: I) -> Vec<HistogramBucket>
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    let mut histogram: Vec<_> = (0..=bounds.len())
+        .map(|bucket| HistogramBucket {
+            lower: bucket
+                .checked_sub(1)
+                .map_or(Duration::from_secs(0), |lower| bounds[lower]),
+            upper: bounds.get(bucket).copied(),
+            count: 0,
+        })
+        .collect();
+
+    let mut total = 0;
+    for (bucket, count) in counts {
+        if let Some(entry) = histogram.get_mut(bucket) {
+            entry.count += count;
+            total += count;
+        }
+    }
+
+    if total == 0 {
+        histogram.clear();
     }
+
+    histogram
 }
 
+/// How an operator's total activation time was spread across workers
 #[derive(
     Debug,
     Clone,
@@ -509,35 +1216,34 @@ impl Default for NodeKind {
     RkyvDeserialize,
 )]
 #[archive_attr(derive(CheckBytes))]
-pub struct Lifespan {
-    pub birth: Duration,
-    pub death: Duration,
+pub struct WorkerSkew {
+    /// The longest total activation time of any single worker
+    pub max: Duration,
+    /// The average total activation time across every worker
+    pub mean: Duration,
 }
 
-impl Lifespan {
-    pub const fn new(birth: Duration, death: Duration) -> Self {
-        Self { birth, death }
-    }
+impl WorkerSkew {
+    /// The max worker's activation time divided by the mean, `1.0` for
+    /// perfectly balanced operators and the number of workers for operators
+    /// that only ever ran on a single worker
+    pub fn score(&self) -> f64 {
+        let mean = self.mean.as_secs_f64();
 
-    pub fn duration(&self) -> Duration {
-        self.death - self.birth
+        if mean == 0.0 {
+            1.0
+        } else {
+            self.max.as_secs_f64() / mean
+        }
     }
 }
 
-// - Edges
-//   - id
-//   - worker
-//   - address
-//   - name
-//   - edge kind
-//   - edge id (is this even a real thing?)
-//   - source node
-//   - dest node
-//   - creation time
-//   - drop time
+/// The records an operator received over its input channels compared
+/// to the time it spent activated
 #[derive(
     Debug,
     Clone,
+    Copy,
     PartialEq,
     Eq,
     PartialOrd,
@@ -552,16 +1258,341 @@ impl Lifespan {
     RkyvDeserialize,
 )]
 #[archive_attr(derive(CheckBytes))]
-pub struct ChannelStats {
-    // TODO: Do these two actually even exist?
-    pub id: ChannelId,
-    // TODO: Make `ChannelAddr`
-    pub addr: OperatorAddr,
-    pub worker: WorkerId,
-    pub source_node: OperatorId,
-    pub dest_node: OperatorId,
-    pub kind: ChannelKind,
-    pub lifespan: Lifespan,
+pub struct Throughput {
+    pub records: usize,
+    pub activations: usize,
+    /// The total time the operator spent activated
+    pub busy: Duration,
+}
+
+impl Throughput {
+    pub fn records_per_activation(&self) -> f64 {
+        if self.activations == 0 {
+            0.0
+        } else {
+            self.records as f64 / self.activations as f64
+        }
+    }
+
+    pub fn records_per_second(&self) -> f64 {
+        let busy = self.busy.as_secs_f64();
+
+        if busy == 0.0 {
+            0.0
+        } else {
+            self.records as f64 / busy
+        }
+    }
+}
+
+/// The most activation anomalies that are marked on the worker timeline
+const MAX_TIMELINE_ANOMALIES: usize = 1000;
+
+/// An activation's start, duration and its operator's median activation time
+pub type ActivationAnomaly = (OpKey, (Duration, (Duration, Duration)));
+
+/// The most points that each arrangement's size series is thinned down to
+const MAX_ARRANGEMENT_SIZE_POINTS: usize = 250;
+
+/// The sizes of a single worker's arrangement over time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArrangementSizes<'a> {
+    pub worker: WorkerId,
+    pub addr: &'a OperatorAddr,
+    /// The arrangement's size after its batches and merges as `(time, size)`
+    pub sizes: Vec<(u64, usize)>,
+}
+
+/// Thins out a size series to at most `limit` evenly spaced points, always
+/// keeping the final size so that growth at the end of the run isn't hidden
+fn thin_sizes(series: &[(Duration, usize)], limit: usize) -> Vec<(u64, usize)> {
+    let point = |&(time, size): &(Duration, usize)| (time.as_nanos() as u64, size);
+    if series.len() <= limit {
+        return series.iter().map(point).collect();
+    }
+
+    // One point is held back for the final size
+    let spaced = limit.saturating_sub(1).max(1);
+    let step = (series.len() + spaced - 1) / spaced;
+
+    let mut sizes: Vec<_> = series.iter().step_by(step).map(point).collect();
+    if let Some(last) = series.last().map(point) {
+        if sizes.last() != Some(&last) {
+            sizes.push(last);
+        }
+    }
+
+    sizes
+}
+
+/// A source location parsed from the `Name @ file:line:column` suffix
+/// of an operator's name
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl SourceLocation {
+    /// Parses the source location out of an operator's name, `None` if the
+    /// name doesn't end with one
+    pub fn parse(name: &str) -> Option<Self> {
+        let (_, location) = name.rsplit_once(" @ ")?;
+
+        // Files can contain colons on windows, so split from the right
+        let mut segments = location.trim().rsplitn(3, ':');
+        let column = segments.next()?.parse().ok()?;
+        let line = segments.next()?.parse().ok()?;
+        let file = segments.next().filter(|file| !file.is_empty())?;
+
+        Some(Self {
+            file: file.replace('\\', "/"),
+            line,
+            column,
+        })
+    }
+
+    /// Fills in the `{file}`, `{line}` and `{column}` placeholders of a
+    /// `--source-url` template
+    pub fn url(&self, template: &str) -> String {
+        template
+            .replace("{file}", &self.file)
+            .replace("{line}", &self.line.to_string())
+            .replace("{column}", &self.column.to_string())
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub enum NodeKind {
+    Operator,
+    Subgraph,
+    Dataflow,
+}
+
+impl Default for NodeKind {
+    fn default() -> Self {
+        Self::Operator
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct Lifespan {
+    pub birth: Duration,
+    pub death: Duration,
+}
+
+impl Lifespan {
+    pub const fn new(birth: Duration, death: Duration) -> Self {
+        Self { birth, death }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.death - self.birth
+    }
+
+    /// The time that both lifespans were alive for, empty ones start and end
+    /// at the later birth
+    pub fn overlap(&self, other: &Self) -> Self {
+        let birth = self.birth.max(other.birth);
+        Self::new(birth, self.death.min(other.death).max(birth))
+    }
+}
+
+// - Edges
+//   - id
+//   - worker
+//   - address
+//   - name
+//   - edge kind
+//   - edge id (is this even a real thing?)
+//   - source node
+//   - dest node
+//   - creation time
+//   - drop time
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct ChannelStats {
+    // TODO: Do these two actually even exist?
+    pub id: ChannelId,
+    // TODO: Make `ChannelAddr`
+    pub addr: OperatorAddr,
+    pub worker: WorkerId,
+    pub source_node: OperatorId,
+    pub dest_node: OperatorId,
+    pub kind: ChannelKind,
+    pub lifespan: Lifespan,
+    pub messages: ChannelMessageStats,
+    /// The records that were left waiting to be received
+    pub backlog: Option<ChannelBacklog>,
+    /// The time messages took to be received after being sent
+    pub latency: Option<ChannelLatency>,
+}
+
+/// An estimate of the records that were in flight over a channel, sent but
+/// not yet received
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct ChannelBacklog {
+    /// The most records that were in flight at once
+    pub peak_records: usize,
+    /// The time that any records were in flight for
+    pub backlogged: Duration,
+    /// The time between the first and last message over the channel
+    pub span: Duration,
+}
+
+impl ChannelBacklog {
+    /// Returns `true` if records were in flight for at least half of the
+    /// channel's lifetime
+    pub fn is_sustained(&self) -> bool {
+        self.peak_records != 0 && self.backlogged * 2 >= self.span
+    }
+}
+
+/// The distribution of times between messages being sent over a channel and
+/// them being received
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct ChannelLatency {
+    /// The number of messages that were both sent and received
+    pub messages: usize,
+    pub min: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// The number of records and batches that were sent and received over a channel
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct ChannelMessageStats {
+    pub records_sent: usize,
+    pub records_received: usize,
+    pub batches_sent: usize,
+    pub batches_received: usize,
+}
+
+impl AddAssign for ChannelMessageStats {
+    fn add_assign(&mut self, other: Self) {
+        self.records_sent += other.records_sent;
+        self.records_received += other.records_received;
+        self.batches_sent += other.batches_sent;
+        self.batches_received += other.batches_received;
+    }
 }
 
 #[derive(
@@ -627,10 +1658,108 @@ impl Default for ChannelKind {
 pub struct ArrangementStats {
     pub operator_addr: OperatorAddr,
     pub size_stats: AggregatedStats<usize>,
+    /// The number of updates held within the arrangement after each of its
+    /// batches and merges, as `(time, size)`
+    pub size_series: Vec<(Duration, usize)>,
     pub merge_stats: AggregatedStats<Duration>,
     pub batch_stats: AggregatedStats<usize>,
     pub trace_shares: usize,
     pub lifespan: Lifespan,
+    pub compaction: CompactionStats,
+    pub shortfalls: ShortfallStats,
+    /// When the arrangement's trace was dropped, `None` if it outlived the capture
+    pub dropped: Option<Duration>,
+}
+
+/// The merges that an arrangement's trace couldn't finish with the fuel it was given
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct ShortfallStats {
+    /// The number of merge shortfalls that were logged
+    pub shortfalls: usize,
+    /// The summed amount of work that merges were short by
+    pub total_shortfall: usize,
+    /// The most work that a single merge was short by
+    pub max_shortfall: usize,
+    /// The time between shortfalls and the trace's merges catching back up
+    pub behind: Duration,
+}
+
+impl ShortfallStats {
+    /// The number of shortfalls after which an arrangement is considered to
+    /// repeatedly fall behind on its merges
+    pub const REPEATED_SHORTFALLS: usize = 3;
+
+    pub const fn falls_behind_repeatedly(&self) -> bool {
+        self.shortfalls >= Self::REPEATED_SHORTFALLS
+    }
+}
+
+/// How effectively an arrangement's trace compacted its updates
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive_attr(derive(CheckBytes))]
+pub struct CompactionStats {
+    /// The updates that entered the trace within batches
+    pub batched_updates: usize,
+    /// The updates that went into completed merges
+    pub merge_inputs: usize,
+    /// The updates that came out of completed merges
+    pub merge_outputs: usize,
+    /// Merges that were started but never completed
+    pub unfinished_merges: usize,
+}
+
+impl CompactionStats {
+    /// The fraction of merged updates that survived their merges, lower is
+    /// better and `1.0` means that merges never compacted anything
+    pub fn compaction_ratio(&self) -> f64 {
+        if self.merge_inputs == 0 {
+            1.0
+        } else {
+            self.merge_outputs as f64 / self.merge_inputs as f64
+        }
+    }
+
+    /// The number of times each batched update was merged on average
+    pub fn merge_amplification(&self) -> f64 {
+        if self.batched_updates == 0 {
+            0.0
+        } else {
+            self.merge_inputs as f64 / self.batched_updates as f64
+        }
+    }
 }
 
 #[derive(
@@ -656,7 +1785,101 @@ pub struct AggregatedStats<T> {
     pub min: T,
     pub average: T,
     pub data_points: Vec<T>,
-    // TODO: Standard deviation, standard error
+    pub stddev: T,
+    pub stderr: T,
+}
+
+impl AggregatedStats<usize> {
+    pub fn from_sizes(sizes: Vec<usize>, max_data_points: usize) -> Self {
+        Self::from_data_points(
+            sizes,
+            max_data_points,
+            |&size| size as f64,
+            |size| size.round() as usize,
+        )
+    }
+}
+
+impl AggregatedStats<Duration> {
+    pub fn from_durations(durations: Vec<Duration>, max_data_points: usize) -> Self {
+        Self::from_data_points(
+            durations,
+            max_data_points,
+            Duration::as_secs_f64,
+            Duration::from_secs_f64,
+        )
+    }
+
+    /// The sum of every duration, which outlives the data points being downsampled
+    pub fn sum(&self) -> Duration {
+        self.average.mul_f64(self.total as f64)
+    }
+}
+
+impl<T> AggregatedStats<T>
+where
+    T: Copy + Ord + Default,
+{
+    /// Summarizes every data point before only keeping `max_data_points` of them
+    fn from_data_points<F, U>(
+        mut data_points: Vec<T>,
+        max_data_points: usize,
+        to_float: F,
+        from_float: U,
+    ) -> Self
+    where
+        F: Fn(&T) -> f64,
+        U: Fn(f64) -> T,
+    {
+        let total = data_points.len();
+        if total == 0 {
+            return Self::default();
+        }
+
+        let mean = data_points.iter().map(&to_float).sum::<f64>() / total as f64;
+        let stddev = if total > 1 {
+            let squared_distance: f64 = data_points
+                .iter()
+                .map(|point| (to_float(point) - mean).powi(2))
+                .sum();
+
+            (squared_distance / (total - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        let max = data_points.iter().copied().max().unwrap_or_default();
+        let min = data_points.iter().copied().min().unwrap_or_default();
+        downsample(&mut data_points, max_data_points);
+
+        Self {
+            total,
+            max,
+            min,
+            average: from_float(mean),
+            stddev: from_float(stddev),
+            stderr: from_float(stddev / (total as f64).sqrt()),
+            data_points,
+        }
+    }
+}
+
+/// Keeps `limit` evenly spaced points out of the given ones, always keeping the
+/// first and last points so that the sample still spans all of them
+fn downsample<T: Copy>(points: &mut Vec<T>, limit: usize) {
+    let len = points.len();
+    if len <= limit {
+        return;
+    }
+
+    *points = match limit {
+        0 => Vec::new(),
+        1 => vec![points[len - 1]],
+        // Done in u64s so that long runs can't overflow on 32bit targets
+        _ => (0..limit as u64)
+            .map(|idx| points[(idx * (len as u64 - 1) / (limit as u64 - 1)) as usize])
+            .collect(),
+    };
 }
 
 // - Timeline events
@@ -690,16 +1913,238 @@ pub struct TimelineEvent {
     pub lifespan: Lifespan,
 }
 
+/// Everything rendered within the graph
+///
+/// Only the small fields are rendered into the graph's templates, the fields
+/// skipped while serializing are streamed into separate data files instead
 #[derive(Debug, Serialize)]
 pub struct GraphData<'a> {
+    #[serde(skip)]
     pub nodes: &'a [Node<'a>],
+    #[serde(skip)]
     pub subgraphs: &'a [Subgraph<'a>],
+    #[serde(skip)]
     pub edges: &'a [Edge<'a>],
     pub palette_colors: &'a [String],
+    #[serde(skip)]
     pub timeline_events: &'a [RawTimelineEvent],
+    /// The bucket width of each timeline tier, from the coarsest to the finest
+    pub timeline_tier_widths: &'a [u64],
+    #[serde(skip)]
+    pub timeline_buckets: &'a [TimelineBucket],
+    /// Timeline events regrouped into a lane for each operator
+    #[serde(skip)]
+    pub operator_lanes: &'a [OperatorLane<'a>],
+    /// When each operator and dataflow was created and dropped
+    #[serde(skip)]
+    pub operator_lifespans: &'a [OperatorLifespan<'a>],
+    /// How each worker's runtime was split between work, idling and parking
+    pub worker_utilization: &'a [(WorkerId, WorkerUtilization)],
+    /// The records each worker sent to each worker over exchange channels
+    pub exchange_matrix: &'a [((WorkerId, WorkerId), usize)],
+    /// The number of events of each kind that each worker logged
+    pub event_kinds: &'a [((WorkerId, LoggedEventKind), usize)],
+    /// The worst activation anomalies, marked on the worker timeline
+    pub activation_anomalies: &'a [ActivationAnomaly],
+    /// The size of each rendered arrangement over time, only with differential logging
+    #[serde(skip)]
+    pub arrangement_sizes: &'a [ArrangementSizes<'a>],
+    #[serde(skip)]
     pub operator_shapes: &'a [OperatorShape],
     // pub operator_progress: &'a [OperatorProgress],
+    #[serde(skip)]
     pub vega_data: &'a [VegaNode<'a>],
+    pub operator_groups: &'a [GroupStats<'a>],
+    pub target_runs: &'a [RunStats],
+    #[serde(skip)]
+    pub progress_channels: &'a [ProgressChannelStats],
+    #[serde(skip)]
+    pub frontier_progression: &'a [((WorkerId, OperatorAddr), (Duration, String))],
+    /// When each dataflow's frontier passed each of its timestamps
+    #[serde(skip)]
+    pub dataflow_frontiers: &'a [(OperatorAddr, (Duration, String))],
+    #[serde(skip)]
+    pub epoch_latencies: &'a [(OperatorAddr, EpochLatency)],
+    #[serde(skip)]
+    pub custom_events: &'a [CustomEvent],
+    #[serde(skip)]
+    pub search_index: &'a [SearchEntry<'a>],
+    /// The positions of the graph's nodes, computed ahead of time
+    #[serde(skip)]
+    pub graph_layout: &'a GraphLayout,
+    /// Every worker that's rendered within the graph
+    pub workers: &'a [WorkerId],
+    /// The metric that operators are colored by
+    pub color_by: String,
+}
+
+/// An operator that can be searched for within the graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchEntry<'a> {
+    pub id: OperatorId,
+    pub worker: WorkerId,
+    pub name: &'a str,
+    pub addr: &'a OperatorAddr,
+    pub is_subgraph: bool,
+    /// The lowercased name and address that searches are matched against
+    pub text: String,
+}
+
+/// Builds the search index of every named operator on every worker,
+/// sorted by address and then worker
+fn search_index<'a>(
+    name_lookup: &'a [((WorkerId, OperatorId), String)],
+    addr_lookup: &'a [((WorkerId, OperatorId), OperatorAddr)],
+    subgraphs: &[(OperatorAddr, OperatesEvent)],
+) -> Vec<SearchEntry<'a>> {
+    let addrs: HashMap<_, _, XXHasher> =
+        addr_lookup.iter().map(|(key, addr)| (*key, addr)).collect();
+    let subgraph_ids: HashSet<_, XXHasher> = subgraphs.iter().map(|(_, event)| event.id).collect();
+
+    let mut index: Vec<_> = name_lookup
+        .iter()
+        .filter_map(|&((worker, id), ref name)| {
+            let addr = *addrs.get(&(worker, id))?;
+
+            Some(SearchEntry {
+                id,
+                worker,
+                name,
+                addr,
+                is_subgraph: subgraph_ids.contains(&id),
+                text: format!("{} {}", name, addr).to_lowercase(),
+            })
+        })
+        .collect();
+    index.sort_unstable_by(|left, right| (left.addr, left.worker).cmp(&(right.addr, right.worker)));
+
+    index
+}
+
+/// A timeline lane holding a single operator's activations across every worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperatorLane<'a> {
+    pub id: OperatorId,
+    pub name: &'a str,
+    pub addr: &'a OperatorAddr,
+    /// The top-level dataflow that the operator belongs to
+    pub dataflow: OperatorId,
+    /// The operator's activations as `(worker, start_time, duration)`,
+    /// ordered by their start time
+    pub activations: Vec<(WorkerId, u64, u64)>,
+}
+
+/// Groups the activations within timeline events by operator rather than
+/// by worker, sorted by address
+fn operator_lanes<'a, 'b, I>(
+    events: I,
+    name_lookup: &'a [((WorkerId, OperatorId), String)],
+    addr_lookup: &'a [((WorkerId, OperatorId), OperatorAddr)],
+) -> Vec<OperatorLane<'a>>
+where
+    I: IntoIterator<Item = &'b RawTimelineEvent>,
+{
+    let names: HashMap<_, _, XXHasher> = name_lookup
+        .iter()
+        .map(|(key, name)| (*key, name.as_str()))
+        .collect();
+    let addrs: HashMap<_, _, XXHasher> =
+        addr_lookup.iter().map(|(key, addr)| (*key, addr)).collect();
+
+    let mut lanes: HashMap<OperatorId, OperatorLane<'a>, XXHasher> = HashMap::default();
+    for event in events {
+        let id = match event.event {
+            EventKind::OperatorActivation { operator_id } => operator_id,
+            _ => continue,
+        };
+
+        let lane = match lanes.entry(id) {
+            Entry::Occupied(lane) => lane.into_mut(),
+            Entry::Vacant(lane) => {
+                let key = (event.worker, id);
+                let (name, addr) = match (names.get(&key), addrs.get(&key)) {
+                    (Some(&name), Some(&addr)) => (name, addr),
+                    _ => continue,
+                };
+
+                lane.insert(OperatorLane {
+                    id,
+                    name,
+                    addr,
+                    dataflow: addr.first().copied().unwrap_or(id),
+                    activations: Vec::new(),
+                })
+            }
+        };
+
+        lane.activations
+            .push((event.worker, event.start_time, event.duration));
+    }
+
+    let mut lanes: Vec<_> = lanes
+        .into_iter()
+        .map(|(_, mut lane)| {
+            lane.activations
+                .sort_unstable_by_key(|&(worker, start_time, _)| (start_time, worker));
+            lane
+        })
+        .collect();
+    lanes.sort_unstable_by(|left, right| left.addr.cmp(right.addr));
+
+    lanes
+}
+
+/// When an operator was created and dropped relative to the start of the program,
+/// operators that were never dropped don't have a lifespan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperatorLifespan<'a> {
+    pub worker: WorkerId,
+    pub id: OperatorId,
+    pub name: &'a str,
+    pub addr: &'a OperatorAddr,
+    /// Whether the operator is a top-level dataflow
+    pub is_dataflow: bool,
+    pub created_at: u64,
+    pub dropped_at: u64,
+}
+
+/// Attaches names and addresses to operator lifespans, sorted by address
+/// and then worker
+fn operator_lifespans<'a, 'b, I>(
+    lifespans: I,
+    name_lookup: &'a [((WorkerId, OperatorId), String)],
+    addr_lookup: &'a [((WorkerId, OperatorId), OperatorAddr)],
+) -> Vec<OperatorLifespan<'a>>
+where
+    I: IntoIterator<Item = &'b (OpKey, Lifespan)>,
+{
+    let names: HashMap<_, _, XXHasher> = name_lookup
+        .iter()
+        .map(|(key, name)| (*key, name.as_str()))
+        .collect();
+    let addrs: HashMap<_, _, XXHasher> =
+        addr_lookup.iter().map(|(key, addr)| (*key, addr)).collect();
+
+    let mut lifespans: Vec<_> = lifespans
+        .into_iter()
+        .filter_map(|&((worker, id), lifespan)| {
+            let addr = *addrs.get(&(worker, id))?;
+
+            Some(OperatorLifespan {
+                worker,
+                id,
+                name: names.get(&(worker, id)).copied().unwrap_or("Unknown"),
+                addr,
+                is_dataflow: addr.is_top_level(),
+                created_at: lifespan.birth.as_nanos() as u64,
+                dropped_at: lifespan.death.as_nanos() as u64,
+            })
+        })
+        .collect();
+    lifespans
+        .sort_unstable_by(|left, right| (left.addr, left.worker).cmp(&(right.addr, right.worker)));
+
+    lifespans
 }
 
 #[derive(Debug, Serialize)]
@@ -707,6 +2152,8 @@ pub struct Node<'a> {
     pub id: OperatorId,
     pub addr: &'a OperatorAddr,
     pub name: &'a str,
+    /// The operator's untruncated name, only present when `name` was truncated
+    pub full_name: Option<&'a str>,
     pub max_activation_time: String,
     pub min_activation_time: String,
     pub average_activation_time: String,
@@ -715,8 +2162,26 @@ pub struct Node<'a> {
     pub fill_color: String,
     pub text_color: String,
     pub activation_durations: Vec<ActivationDuration>,
+    /// Percentiles of the operator's activation times
+    pub activation_percentiles: Option<ActivationPercentiles>,
+    /// The operator's activations sorted into the `--histogram-buckets`
+    pub activation_histogram: Vec<HistogramBucket>,
+    /// The operator's activation rate over each `--activation-rate-interval` of the run
+    pub activation_rates: Vec<ActivationRate>,
+    /// Where the operator was defined, if its name carries a location
+    pub source_location: Option<SourceLocation>,
+    /// The location's url within `--source-url`
+    pub source_url: Option<String>,
     pub max_arrangement_size: Option<usize>,
     pub min_arrangement_size: Option<usize>,
+    pub average_batch_size: Option<usize>,
+    pub average_merge_time: Option<String>,
+    /// The most traces that shared the operator's arrangement at once
+    pub trace_shares: Option<usize>,
+    /// The operator's activity on each rendered worker
+    pub workers: &'a [WorkerActivity],
+    /// How the operator changed relative to a baseline run, only set by `ddshow diff`
+    pub comparison: Option<String>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
@@ -725,6 +2190,55 @@ pub struct ActivationDuration {
     pub activated_at: u64,
 }
 
+/// How often an operator was activated and how much of its time it spent
+/// activated within a single window of the run
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
+pub struct ActivationRate {
+    /// The start of the window in nanoseconds
+    pub window: u64,
+    pub activations_per_sec: f64,
+    /// The seconds spent activated for each second of the window
+    pub busy_per_sec: f64,
+}
+
+/// Turns an operator's `(window start, (activations, time spent))` windows into
+/// rates, windows between the first and last ones without any activations
+/// are filled in so that bursts aren't stretched across idle stretches
+pub fn activation_rates(
+    interval: Duration,
+    windows: &[(Duration, (usize, Duration))],
+) -> Vec<ActivationRate> {
+    let seconds = interval.as_secs_f64();
+    if windows.is_empty() || seconds == 0.0 {
+        return Vec::new();
+    }
+
+    let mut windows = windows.to_vec();
+    windows.sort_unstable_by_key(|&(start, _)| start);
+
+    let mut rates = Vec::with_capacity(windows.len());
+    let mut next = windows[0].0;
+    for (start, (activations, busy)) in windows {
+        while next < start {
+            rates.push(ActivationRate {
+                window: next.as_nanos() as u64,
+                activations_per_sec: 0.0,
+                busy_per_sec: 0.0,
+            });
+            next += interval;
+        }
+
+        rates.push(ActivationRate {
+            window: start.as_nanos() as u64,
+            activations_per_sec: activations as f64 / seconds,
+            busy_per_sec: busy.as_secs_f64() / seconds,
+        });
+        next = start + interval;
+    }
+
+    rates
+}
+
 #[derive(Debug, Serialize)]
 pub struct Subgraph<'a> {
     pub id: OperatorId,
@@ -737,6 +2251,84 @@ pub struct Subgraph<'a> {
     pub invocations: usize,
     pub fill_color: String,
     pub text_color: String,
+    /// The subgraph's activity on each rendered worker
+    pub workers: &'a [WorkerActivity],
+    /// How the subgraph changed relative to a baseline run, only set by `ddshow diff`
+    pub comparison: Option<String>,
+    /// The address of the scope containing the subgraph, `None` for dataflows
+    pub parent: Option<OperatorAddr>,
+    /// The operators and subgraphs directly contained within the subgraph
+    pub children: Vec<&'a OperatorAddr>,
+    /// The stats of every operator nested within the subgraph, shown
+    /// when the subgraph is collapsed
+    pub rollup: SubgraphRollup,
+}
+
+/// The combined stats of every operator nested within a subgraph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SubgraphRollup {
+    /// The total runtime of the nested operators in nanoseconds
+    pub total_ns: u64,
+    pub invocations: usize,
+    /// The number of operators and subgraphs nested within the subgraph
+    pub operators: usize,
+}
+
+/// Returns the address of the scope containing the given operator
+pub fn parent_addr(addr: &OperatorAddr) -> Option<OperatorAddr> {
+    if addr.len() > 1 {
+        Some(OperatorAddr::from(&addr[..addr.len() - 1]))
+    } else {
+        None
+    }
+}
+
+/// Collects the children of every subgraph along with the rolled
+/// up stats of every operator nested within them
+pub fn subgraph_contents<'a>(
+    nodes: &'a [(OperatorAddr, OperatesEvent)],
+    subgraphs: &'a [(OperatorAddr, OperatesEvent)],
+    operator_stats: &HashMap<OperatorId, &Summation, XXHasher>,
+) -> HashMap<&'a OperatorAddr, (Vec<&'a OperatorAddr>, SubgraphRollup), XXHasher> {
+    let mut contents: HashMap<_, (Vec<_>, SubgraphRollup), XXHasher> = subgraphs
+        .iter()
+        .map(|(addr, _)| (addr, Default::default()))
+        .collect();
+
+    let mut seen = HashSet::with_capacity(nodes.len() + subgraphs.len());
+    let operators = nodes
+        .iter()
+        .map(|node| (node, true))
+        .chain(subgraphs.iter().map(|subgraph| (subgraph, false)));
+
+    for ((addr, operator), is_leaf) in operators {
+        if !seen.insert(addr) {
+            continue;
+        }
+
+        // Only leaves contribute their stats since subgraph runtimes
+        // already include the runtimes of their children
+        let stats = operator_stats
+            .get(&operator.id)
+            .filter(|_| is_leaf)
+            .copied();
+
+        for len in 1..addr.len() {
+            if let Some((children, rollup)) = contents.get_mut(&OperatorAddr::from(&addr[..len])) {
+                if len == addr.len() - 1 {
+                    children.push(addr);
+                }
+
+                rollup.operators += 1;
+                if let Some(stats) = stats {
+                    rollup.total_ns += stats.total.as_nanos() as u64;
+                    rollup.invocations += stats.count;
+                }
+            }
+        }
+    }
+
+    contents
 }
 
 #[derive(Debug, Serialize)]
@@ -745,6 +2337,39 @@ pub struct Edge<'a> {
     pub dest: &'a OperatorAddr,
     pub channel_id: ChannelId,
     pub edge_kind: EdgeKind,
+    /// The rendered workers that either end of the channel was active on
+    pub workers: Vec<WorkerId>,
+    /// The number of records the rendered workers sent over the channel
+    pub records_sent: usize,
+    /// The most records that were in flight over the channel at once
+    pub peak_backlog: Option<usize>,
+    /// Whether records were in flight over the channel for most of its lifetime
+    pub sustained_backlog: bool,
+    /// The time messages took to cross the channel
+    pub latency: Option<ChannelLatency>,
+}
+
+/// An operator's activity on a single worker, used for toggling
+/// workers on and off within the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct WorkerActivity {
+    pub worker: WorkerId,
+    pub total_ns: u64,
+    pub max_ns: u64,
+    pub min_ns: u64,
+    pub invocations: usize,
+}
+
+impl WorkerActivity {
+    pub fn new(worker: WorkerId, stats: &Summation) -> Self {
+        Self {
+            worker,
+            total_ns: stats.total.as_nanos() as u64,
+            max_ns: stats.max.as_nanos() as u64,
+            min_ns: stats.min.as_nanos() as u64,
+            invocations: stats.count,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
@@ -753,9 +2378,305 @@ pub enum EdgeKind {
     Crossing,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
-pub struct ChannelMessageStats {
-    pub channel: ChannelId,
-    pub messages: usize,
-    pub capability_updates: usize,
+#[cfg(test)]
+mod tests {
+    use super::{
+        activation_histogram, activation_rates, operator_lanes, search_index, subgraph_contents,
+        thin_sizes, AggregatedStats, Lifespan, LoggedEventKind, ProgramStats, RunStats,
+        SourceLocation, WorkerStats,
+    };
+    use crate::dataflow::{utils::XXHasher, DataflowData, EventKind, Summation, TimelineEvent};
+    use ddshow_types::{timely_logging::OperatesEvent, OperatorAddr, OperatorId, WorkerId};
+    use std::{collections::HashMap, time::Duration};
+
+    #[test]
+    fn thinned_sizes_keep_the_final_size() {
+        let ms = Duration::from_millis;
+        let series: Vec<_> = (0..10).map(|idx| (ms(idx), idx as usize * 10)).collect();
+
+        assert_eq!(
+            thin_sizes(&series, 4),
+            vec![(0, 0), (4_000_000, 40), (8_000_000, 80), (9_000_000, 90)],
+        );
+        assert_eq!(
+            thin_sizes(&series, 3),
+            vec![(0, 0), (5_000_000, 50), (9_000_000, 90)],
+        );
+        assert_eq!(thin_sizes(&series[..2], 4).len(), 2);
+    }
+
+    #[test]
+    fn capped_data_points_keep_exact_stats() {
+        let stats = AggregatedStats::from_sizes((0..=100).collect(), 5);
+
+        assert_eq!(stats.data_points, vec![0, 25, 50, 75, 100]);
+        assert_eq!((stats.total, stats.max, stats.min), (101, 100, 0));
+        assert_eq!(stats.average, 50);
+
+        assert!(AggregatedStats::from_sizes(vec![1, 2], 0)
+            .data_points
+            .is_empty());
+        assert_eq!(
+            AggregatedStats::from_sizes(vec![1, 2], 1).data_points,
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn rates_fill_idle_windows() {
+        let ms = Duration::from_millis;
+        let windows = vec![(ms(300), (1, ms(10))), (ms(0), (5, ms(50)))];
+
+        let rates = activation_rates(ms(100), &windows);
+        let windows: Vec<_> = rates.iter().map(|rate| rate.window).collect();
+        assert_eq!(windows, vec![0, 100_000_000, 200_000_000, 300_000_000]);
+
+        assert!((rates[0].activations_per_sec - 50.0).abs() < f64::EPSILON);
+        assert!((rates[0].busy_per_sec - 0.5).abs() < f64::EPSILON);
+        assert!(rates[1].activations_per_sec.abs() < f64::EPSILON);
+        assert!((rates[3].activations_per_sec - 10.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn histogram_fills_empty_buckets() {
+        let bounds = [Duration::from_micros(10), Duration::from_micros(100)];
+
+        let histogram = activation_histogram(&bounds, vec![(0, 3), (2, 1), (0, 2)]);
+        let counts: Vec<_> = histogram.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, vec![5, 0, 1]);
+        assert_eq!(histogram[0].lower, Duration::from_secs(0));
+        assert_eq!(histogram[1].upper, Some(Duration::from_micros(100)));
+        assert_eq!(histogram[2].lower, Duration::from_micros(100));
+        assert_eq!(histogram[2].upper, None);
+
+        assert!(activation_histogram(&bounds, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn lifespan_overlaps() {
+        let ms = Duration::from_millis;
+
+        let overlap = Lifespan::new(ms(10), ms(50)).overlap(&Lifespan::new(ms(20), ms(80)));
+        assert_eq!(overlap, Lifespan::new(ms(20), ms(50)));
+
+        let disjoint = Lifespan::new(ms(10), ms(20)).overlap(&Lifespan::new(ms(30), ms(40)));
+        assert_eq!(disjoint.duration(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn search_index_covers_every_worker() {
+        let (map, region) = (OperatorId::new(2), OperatorId::new(1));
+        let (map_addr, region_addr) = (
+            OperatorAddr::from(vec![0, 1, 2]),
+            OperatorAddr::from(vec![0, 1]),
+        );
+
+        let names = vec![
+            ((WorkerId::new(1), map), "Map".to_owned()),
+            ((WorkerId::new(0), map), "Map".to_owned()),
+            ((WorkerId::new(0), region), "Region".to_owned()),
+            // Operators without an address are left out
+            ((WorkerId::new(0), OperatorId::new(3)), "Orphan".to_owned()),
+        ];
+        let addrs = vec![
+            ((WorkerId::new(0), map), map_addr.clone()),
+            ((WorkerId::new(1), map), map_addr.clone()),
+            ((WorkerId::new(0), region), region_addr.clone()),
+        ];
+        let subgraphs = vec![(
+            region_addr.clone(),
+            OperatesEvent::new(region, region_addr, "Region".to_owned()),
+        )];
+
+        let index = search_index(&names, &addrs, &subgraphs);
+        let entries: Vec<_> = index
+            .iter()
+            .map(|entry| {
+                (
+                    entry.worker,
+                    entry.id,
+                    entry.is_subgraph,
+                    entry.text.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                (WorkerId::new(0), region, true, "region [0, 1]"),
+                (WorkerId::new(0), map, false, "map [0, 1, 2]"),
+                (WorkerId::new(1), map, false, "map [0, 1, 2]"),
+            ],
+        );
+    }
+
+    #[test]
+    fn source_locations() {
+        let location = SourceLocation::parse("SortBy @ src/dataflow/mod.rs:104:10").unwrap();
+        assert_eq!(
+            location,
+            SourceLocation {
+                file: "src/dataflow/mod.rs".to_owned(),
+                line: 104,
+                column: 10,
+            },
+        );
+        assert_eq!(
+            location.url("https://example.com/blob/main/{file}#L{line}"),
+            "https://example.com/blob/main/src/dataflow/mod.rs#L104",
+        );
+
+        let windows = SourceLocation::parse("Map @ C:\\src\\lib.rs:1:2").unwrap();
+        assert_eq!(windows.file, "C:/src/lib.rs");
+
+        assert_eq!(SourceLocation::parse("Map"), None);
+        assert_eq!(SourceLocation::parse("Map @ src/lib.rs"), None);
+        assert_eq!(SourceLocation::parse("Map @ :1:2"), None);
+    }
+
+    #[test]
+    fn operator_lanes_span_workers() {
+        let (map, filter) = (OperatorId::new(2), OperatorId::new(3));
+        let (map_addr, filter_addr) = (
+            OperatorAddr::from(vec![1, 2]),
+            OperatorAddr::from(vec![0, 3]),
+        );
+
+        let names = vec![
+            ((WorkerId::new(0), map), "Map".to_owned()),
+            ((WorkerId::new(1), map), "Map".to_owned()),
+            ((WorkerId::new(1), filter), "Filter".to_owned()),
+        ];
+        let addrs = vec![
+            ((WorkerId::new(0), map), map_addr.clone()),
+            ((WorkerId::new(1), map), map_addr),
+            ((WorkerId::new(1), filter), filter_addr),
+        ];
+
+        let event = |worker: usize, kind: EventKind, start: u64| {
+            TimelineEvent::new(
+                WorkerId::new(worker),
+                kind,
+                Duration::from_nanos(start),
+                Duration::from_nanos(10),
+            )
+        };
+        let events = vec![
+            event(1, EventKind::activation(map), 50),
+            event(0, EventKind::activation(map), 20),
+            event(1, EventKind::activation(filter), 0),
+            // Only activations make it into lanes
+            event(0, EventKind::merge(map), 30),
+            // Operators without a name or address are left out
+            event(0, EventKind::activation(OperatorId::new(4)), 40),
+        ];
+
+        let lanes = operator_lanes(&events, &names, &addrs);
+        let lanes: Vec<_> = lanes
+            .iter()
+            .map(|lane| (lane.name, lane.dataflow, lane.activations.clone()))
+            .collect();
+
+        assert_eq!(
+            lanes,
+            vec![
+                (
+                    "Filter",
+                    OperatorId::new(0),
+                    vec![(WorkerId::new(1), 0, 10)]
+                ),
+                (
+                    "Map",
+                    OperatorId::new(1),
+                    vec![(WorkerId::new(0), 20, 10), (WorkerId::new(1), 50, 10)],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn subgraph_rollups_include_nested_operators() {
+        let operator = |id: usize, addr: Vec<usize>, name: &str| {
+            let addr = OperatorAddr::from(addr);
+            (
+                addr.clone(),
+                OperatesEvent::new(OperatorId::new(id), addr, name.to_owned()),
+            )
+        };
+        let stats = |millis: u64, count: usize| {
+            let total = Duration::from_millis(millis);
+            Summation::new(total, total, total, total, count)
+        };
+
+        let subgraphs = vec![
+            operator(0, vec![0], "Dataflow"),
+            operator(1, vec![0, 1], "Region"),
+        ];
+        let nodes = vec![
+            operator(2, vec![0, 1, 2], "Map"),
+            operator(3, vec![0, 1, 3], "Filter"),
+            operator(4, vec![0, 2], "Source"),
+        ];
+
+        let (region, map, filter, source) = (stats(100, 4), stats(10, 3), stats(5, 2), stats(1, 1));
+        let mut operator_stats: HashMap<_, _, XXHasher> = HashMap::default();
+        operator_stats.insert(OperatorId::new(1), &region);
+        operator_stats.insert(OperatorId::new(2), &map);
+        operator_stats.insert(OperatorId::new(3), &filter);
+        operator_stats.insert(OperatorId::new(4), &source);
+
+        let contents = subgraph_contents(&nodes, &subgraphs, &operator_stats);
+
+        let (children, rollup) = &contents[&OperatorAddr::from(vec![0, 1])];
+        assert_eq!(children, &vec![&nodes[0].0, &nodes[1].0]);
+        assert_eq!(rollup.total_ns, 15_000_000);
+        assert_eq!(rollup.invocations, 5);
+        assert_eq!(rollup.operators, 2);
+
+        let (children, rollup) = &contents[&OperatorAddr::from(vec![0])];
+        let mut children = children.clone();
+        children.sort();
+        assert_eq!(children, vec![&subgraphs[1].0, &nodes[2].0]);
+        assert_eq!(rollup.total_ns, 16_000_000);
+        assert_eq!(rollup.invocations, 6);
+        assert_eq!(rollup.operators, 4);
+    }
+
+    #[test]
+    fn partial_stats_default_missing_data() {
+        let (worker0, worker1) = (WorkerId::new(0), WorkerId::new(1));
+        let ms = Duration::from_millis;
+
+        let data = DataflowData {
+            workers: vec![worker0, worker1],
+            total_runtime: vec![(worker0, (ms(5), ms(20)))],
+            event_kinds: vec![
+                ((worker0, LoggedEventKind::Schedule), 4),
+                ((worker1, LoggedEventKind::Park), 2),
+            ],
+            ..DataflowData::default()
+        };
+
+        let program = ProgramStats::partial(&data, &[], 0);
+        assert_eq!(program.workers, 2);
+        assert_eq!(program.events, 6);
+        assert_eq!(program.runtime, ms(15));
+        assert!(program.runs.is_empty());
+
+        // The second worker's runtime hasn't arrived yet
+        let worker = WorkerStats::partial(&data, worker1);
+        assert_eq!(worker.events, 2);
+        assert_eq!(worker.runtime, Duration::from_secs(0));
+        assert_eq!(worker.event_kinds, vec![(LoggedEventKind::Park, 2)]);
+    }
+
+    #[test]
+    fn partial_stats_include_runs() {
+        let data = DataflowData::default();
+        let runs = vec![RunStats::new(0), RunStats::new(1)];
+
+        let program = ProgramStats::partial(&data, &runs, 0);
+        assert_eq!(program.runs, runs);
+    }
 }
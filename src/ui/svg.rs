@@ -0,0 +1,191 @@
+//! Renders a static svg picture of the dataflow graph
+//!
+//! Used by the live server's `/graph.svg` endpoint so that dashboards can
+//! embed an always-current picture of the running dataflow without needing
+//! a browser to lay it out. The graph is laid out by [`layout_graph()`], the
+//! same as the graph page, and operators are colored by their total
+//! activation time
+
+use crate::{
+    colormap::select_scaled_color,
+    dataflow::{utils::XXHasher, DataflowData},
+    ui::layout::{
+        layout_graph, node_label, LayoutNode, NodePosition, CLUSTER_PADDING, LINE_HEIGHT,
+    },
+};
+use colorous::Gradient;
+use ddshow_types::{timely_logging::OperatesEvent, OperatorAddr};
+use std::{collections::HashMap, fmt::Write};
+
+const MARGIN: f64 = 20.0;
+
+/// Renders the graph of the given data into an svg document
+pub fn render_svg(data: &DataflowData, palette: &Gradient) -> String {
+    let nodes: Vec<_> = data.nodes.iter().map(layout_node).collect();
+    let subgraphs: Vec<_> = data.subgraphs.iter().map(layout_node).collect();
+    let channels: Vec<_> = data
+        .edges
+        .iter()
+        .map(|(_, channel, _)| (channel.source_addr(), channel.target_addr()))
+        .collect();
+
+    let layout = layout_graph(&nodes, &subgraphs, &channels);
+    let positions: HashMap<&OperatorAddr, &NodePosition, XXHasher> = layout
+        .nodes
+        .iter()
+        .map(|position| (&position.addr, position))
+        .collect();
+
+    let totals: HashMap<_, _, XXHasher> = data
+        .aggregated_summaries
+        .iter()
+        .map(|(id, stats)| (*id, stats))
+        .collect();
+    let bounds = totals
+        .values()
+        .map(|stats| stats.total.as_nanos() as f64)
+        .fold((f64::MIN, f64::MAX), |(max, min), total| {
+            (max.max(total), min.min(total))
+        });
+
+    let width = layout.width + MARGIN * 2.0;
+    let height = layout.height + MARGIN * 2.0;
+
+    let mut svg = String::with_capacity(256 + data.nodes.len() * 256);
+    let _ = write!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.0} {height:.0}" font-family="monospace" font-size="12">
+<defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="6" markerHeight="6" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z" fill="#333333"/></marker></defs>
+<rect width="100%" height="100%" fill="#FFFFFF"/>
+"##,
+        width = width,
+        height = height,
+    );
+
+    // Subgraphs are drawn first so that their contents are drawn over them
+    for (addr, subgraph) in data.subgraphs.iter() {
+        let position = match positions.get(addr) {
+            Some(position) => position,
+            None => continue,
+        };
+        let (x, y) = top_left(position);
+
+        let _ = writeln!(
+            svg,
+            r##"<g><rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" rx="3" fill="none" stroke="#999999"/><text x="{text_x:.1}" y="{text_y:.1}" text-anchor="middle" dominant-baseline="central" fill="#333333">{name}</text></g>"##,
+            x = x,
+            y = y,
+            width = position.width,
+            height = position.height,
+            text_x = position.x + MARGIN,
+            text_y = y + (CLUSTER_PADDING + LINE_HEIGHT) / 2.0,
+            name = escape_xml(&node_label(&subgraph.name, subgraph.id, addr)),
+        );
+    }
+
+    // Channels that cross into or out of subgraphs don't connect two
+    // operators, so only the ones between operators are drawn
+    let operators: HashMap<&OperatorAddr, &NodePosition, XXHasher> = data
+        .nodes
+        .iter()
+        .filter_map(|(addr, _)| Some((addr, *positions.get(addr)?)))
+        .collect();
+    for &(src, dest) in &channels {
+        if let (Some(src), Some(dest)) = (operators.get(src), operators.get(dest)) {
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#333333" stroke-width="1.5" marker-end="url(#arrow)"/>"##,
+                src.x + MARGIN,
+                src.y + src.height / 2.0 + MARGIN,
+                dest.x + MARGIN,
+                dest.y - dest.height / 2.0 + MARGIN,
+            );
+        }
+    }
+
+    for (addr, operator) in data.nodes.iter() {
+        let position = match operators.get(addr) {
+            Some(position) => position,
+            None => continue,
+        };
+        let (x, y) = top_left(position);
+
+        let stats = totals.get(&operator.id);
+        let fill_color = select_scaled_color(
+            palette,
+            stats.map_or(bounds.1, |stats| stats.total.as_nanos() as f64),
+            bounds,
+        );
+        let title = match stats {
+            Some(stats) => format!(
+                "{} @ {}: ran for {:#?} over {} invocations",
+                operator.name, addr, stats.total, stats.count,
+            ),
+            None => format!("{} @ {}", operator.name, addr),
+        };
+
+        let _ = writeln!(
+            svg,
+            r##"<g><title>{title}</title><rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" rx="3" fill="{fill}" stroke="#333333"/><text x="{text_x:.1}" y="{text_y:.1}" text-anchor="middle" dominant-baseline="central" fill="{text}">{name}</text></g>"##,
+            title = escape_xml(&title),
+            x = x,
+            y = y,
+            width = position.width,
+            height = position.height,
+            fill = fill_color,
+            text_x = position.x + MARGIN,
+            text_y = position.y + MARGIN,
+            text = fill_color.text_color(),
+            name = escape_xml(&node_label(&operator.name, operator.id, addr)),
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn layout_node((addr, operator): &(OperatorAddr, OperatesEvent)) -> LayoutNode<'_> {
+    LayoutNode {
+        addr,
+        id: operator.id,
+        name: &operator.name,
+    }
+}
+
+/// The top left corner of the node within the svg, layouts are
+/// positioned by the center of each node
+fn top_left(position: &NodePosition) -> (f64, f64) {
+    (
+        position.x - position.width / 2.0 + MARGIN,
+        position.y - position.height / 2.0 + MARGIN,
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_xml;
+
+    #[test]
+    fn names_are_escaped() {
+        assert_eq!(
+            escape_xml("Map<&'a str> \"x\""),
+            "Map&lt;&amp;&apos;a str&gt; &quot;x&quot;",
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! The graph's color themes
+//!
+//! `graph.css` only refers to colors through css variables, the variables
+//! themselves are generated from the selected `--theme` and `--palette`
+
+use crate::{args::Theme, colormap::Color};
+use colorous::Gradient;
+use std::fmt::{self, Display};
+
+/// The colors of a single theme
+#[derive(Debug, Clone, Copy)]
+struct ThemeColors {
+    /// The page's background
+    background: &'static str,
+    /// The background of clusters, tooltips and other floating elements
+    surface: &'static str,
+    /// Text, borders and edges
+    foreground: &'static str,
+    /// Hovered elements
+    hover: &'static str,
+}
+
+const LIGHT: ThemeColors = ThemeColors {
+    background: "#EEEEEE",
+    surface: "#EEEEEE",
+    foreground: "#333333",
+    hover: "#DDDDDD",
+};
+
+const DARK: ThemeColors = ThemeColors {
+    background: "#1E1E1E",
+    surface: "#2A2A2A",
+    foreground: "#DDDDDD",
+    hover: "#3A3A3A",
+};
+
+impl Display for ThemeColors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "    --background: {};", self.background)?;
+        writeln!(f, "    --surface: {};", self.surface)?;
+        writeln!(f, "    --foreground: {};", self.foreground)?;
+        writeln!(f, "    --hover: {};", self.hover)
+    }
+}
+
+/// Generates the css variables used by `graph.css`
+///
+/// The accent colors used for operator groups, scope-crossing edges and
+/// search results are taken from the palette so that they fit in with
+/// the colors of the operators themselves
+pub fn theme_variables(theme: Theme, palette: Gradient) -> String {
+    let accents = format!(
+        "    --accent: {};\n    --highlight: {};\n",
+        Color::new(palette.eval_continuous(0.4)),
+        Color::new(palette.eval_continuous(0.8)),
+    );
+
+    match theme {
+        Theme::Light => format!(":root {{\n{}{}}}\n", LIGHT, accents),
+        Theme::Dark => format!(":root {{\n{}{}}}\n", DARK, accents),
+        Theme::Auto => format!(
+            ":root {{\n{}{}}}\n\n@media (prefers-color-scheme: dark) {{\n:root {{\n{}}}\n}}\n",
+            LIGHT, accents, DARK,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::theme_variables;
+    use crate::args::Theme;
+
+    #[test]
+    fn auto_theme_follows_system_preference() {
+        let light = theme_variables(Theme::Light, colorous::INFERNO);
+        assert!(light.contains("--background: #EEEEEE;"));
+        assert!(!light.contains("prefers-color-scheme"));
+
+        let auto = theme_variables(Theme::Auto, colorous::INFERNO);
+        assert!(auto.starts_with(&light));
+        assert!(auto.contains("@media (prefers-color-scheme: dark)"));
+        assert!(auto.contains("--background: #1E1E1E;"));
+    }
+}